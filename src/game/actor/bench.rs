@@ -0,0 +1,396 @@
+use std::{
+    hint::black_box,
+    ops::ControlFlow,
+    time::{Duration, Instant},
+};
+
+use bevy_ecs::entity::Entity;
+use macroquad::{
+    color::{Color, WHITE},
+    input::{is_key_pressed, KeyCode},
+    math::{IVec2, Vec2},
+};
+
+use crate::game::{
+    math::{
+        aabb::{Aabb, AabbI},
+        noise::hash_unit,
+    },
+    tile::{
+        autotile::AutoTileLayout,
+        broadphase::ColliderGrid,
+        collider::CollisionLayers,
+        data::TileLayerConfig,
+        draw_list::{
+            prepare_draw_lists, prepare_draw_lists_serial, AutoTileStyle, ChunkDrawList,
+            LayerSnapshot, TileStyle, TileStyles,
+        },
+    },
+};
+
+// === Collider Query Benchmark === //
+
+#[derive(Debug, Copy, Clone)]
+pub struct ColliderBenchConfig {
+    pub colliders: usize,
+    pub queries: usize,
+    pub extent: f32,
+    pub collider_size: f32,
+    pub query_size: f32,
+    pub cell_size: f32,
+}
+
+impl Default for ColliderBenchConfig {
+    fn default() -> Self {
+        Self {
+            colliders: 5000,
+            queries: 10000,
+            extent: 20000.,
+            collider_size: 40.,
+            query_size: 120.,
+            cell_size: 200.,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct ColliderBenchReport {
+    pub build: Duration,
+    pub update: Duration,
+    pub grid_queries: Duration,
+    pub linear_queries: Duration,
+    pub hits: usize,
+}
+
+/// Compares [`ColliderGrid`] queries against a linear scan over the same randomly scattered
+/// colliders, checking that both approaches agree on the number of hits.
+pub fn bench_collider_queries(config: ColliderBenchConfig) -> ColliderBenchReport {
+    let random_aabb = |seed: u32, i: usize, size: f32| {
+        let pos = Vec2::new(
+            hash_unit(seed, IVec2::new(i as i32, 0)),
+            hash_unit(seed, IVec2::new(i as i32, 1)),
+        ) * config.extent;
+
+        Aabb::new_centered(pos, Vec2::splat(size))
+    };
+
+    let colliders = (0..config.colliders)
+        .map(|i| {
+            (
+                Entity::from_raw(i as u32),
+                random_aabb(1, i, config.collider_size),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let queries = (0..config.queries)
+        .map(|i| random_aabb(2, i, config.query_size))
+        .collect::<Vec<_>>();
+
+    // Build
+    let start = Instant::now();
+    let mut grid = ColliderGrid::new(config.cell_size);
+    let handles = colliders
+        .iter()
+        .map(|&(entity, aabb)| grid.insert(entity, aabb, CollisionLayers::ACTORS))
+        .collect::<Vec<_>>();
+    let build = start.elapsed();
+
+    // Incremental updates
+    let start = Instant::now();
+    for (&handle, &(_, aabb)) in handles.iter().zip(&colliders) {
+        grid.update(handle, aabb.translated(Vec2::splat(config.collider_size)));
+    }
+    for (&handle, &(_, aabb)) in handles.iter().zip(&colliders) {
+        grid.update(handle, aabb);
+    }
+    let update = start.elapsed();
+
+    // Grid queries
+    let start = Instant::now();
+    let mut grid_hits = 0;
+    for &query in &queries {
+        let _ = grid.query::<()>(query, CollisionLayers::ALL, |hit| {
+            black_box(hit);
+            grid_hits += 1;
+            ControlFlow::Continue(())
+        });
+    }
+    let grid_queries = start.elapsed();
+
+    // Linear queries
+    let start = Instant::now();
+    let mut linear_hits = 0;
+    for &query in &queries {
+        for hit in &colliders {
+            if query.intersects(hit.1) {
+                black_box(hit);
+                linear_hits += 1;
+            }
+        }
+    }
+    let linear_queries = start.elapsed();
+
+    if grid_hits != linear_hits {
+        log::error!(
+            "Collider grid reported {grid_hits} hit(s) but a linear scan found {linear_hits}"
+        );
+    }
+
+    ColliderBenchReport {
+        build,
+        update,
+        grid_queries,
+        linear_queries,
+        hits: linear_hits,
+    }
+}
+
+// === Ray Traversal Benchmark === //
+
+#[derive(Debug, Copy, Clone)]
+pub struct RayBenchConfig {
+    pub rays: usize,
+    pub extent: f32,
+    pub max_length: f32,
+    pub tile_size: f32,
+}
+
+impl Default for RayBenchConfig {
+    fn default() -> Self {
+        Self {
+            rays: 10000,
+            extent: 20000.,
+            max_length: 2000.,
+            tile_size: 16.,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct RayBenchReport {
+    pub stepped: Duration,
+    pub traversed: Duration,
+    pub tiles: usize,
+}
+
+/// Compares [`TileLayerConfig::traverse_ray`] against walking the same rays one tile-sized
+/// [`step_ray`](TileLayerConfig::step_ray) at a time, checking that both approaches visit the
+/// same number of tiles.
+pub fn bench_ray_traversal(config: RayBenchConfig) -> RayBenchReport {
+    let layer = TileLayerConfig::from_size(config.tile_size);
+
+    let rays = (0..config.rays)
+        .map(|i| {
+            let point = |seed: u32| {
+                Vec2::new(
+                    hash_unit(seed, IVec2::new(i as i32, 0)),
+                    hash_unit(seed, IVec2::new(i as i32, 1)),
+                )
+            };
+
+            let src = point(3) * config.extent;
+            let dst = src + (point(4) - Vec2::splat(0.5)) * 2. * config.max_length;
+            (src, dst)
+        })
+        .collect::<Vec<_>>();
+
+    // Fixed-size steps
+    let start = Instant::now();
+    let mut stepped_tiles = 0;
+    for &(src, dst) in &rays {
+        let mut origin = src;
+        let mut length = (dst - src).length();
+        let delta = (dst - src) / length;
+
+        if !delta.is_nan() {
+            while length > 0. {
+                let step_size = length.min(layer.size);
+                for isect in layer.step_ray(origin, delta * step_size) {
+                    black_box(isect.entered_tile);
+                    stepped_tiles += 1;
+                }
+                length -= step_size;
+                origin += delta * step_size;
+            }
+        }
+    }
+    let stepped = start.elapsed();
+
+    // Grid traversal
+    let start = Instant::now();
+    let mut traversed_tiles = 0;
+    for &(src, dst) in &rays {
+        let _ = layer.traverse_ray::<()>(src, dst, |isect| {
+            black_box(isect.entered_tile);
+            traversed_tiles += 1;
+            ControlFlow::Continue(())
+        });
+    }
+    let traversed = start.elapsed();
+
+    if stepped_tiles != traversed_tiles {
+        log::error!(
+            "Ray traversal visited {traversed_tiles} tile(s) but fixed-size stepping visited \
+             {stepped_tiles}"
+        );
+    }
+
+    RayBenchReport {
+        stepped,
+        traversed,
+        tiles: traversed_tiles,
+    }
+}
+
+// === Chunk Preparation Benchmark === //
+
+#[derive(Debug, Copy, Clone)]
+pub struct ChunkPrepBenchConfig {
+    /// The number of chunks along each side of the prepared region.
+    pub chunks: i32,
+    pub iterations: usize,
+    pub tile_size: f32,
+}
+
+impl Default for ChunkPrepBenchConfig {
+    fn default() -> Self {
+        Self {
+            chunks: 8,
+            iterations: 20,
+            tile_size: 16.,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct ChunkPrepBenchReport {
+    pub serial: Duration,
+    pub parallel: Duration,
+    pub quads: usize,
+}
+
+/// Compares preparing the draw lists of a region of randomly filled chunks on a single thread
+/// against [`prepare_draw_lists`], checking that both approaches produce the same number of quads.
+pub fn bench_chunk_preparation(config: ChunkPrepBenchConfig) -> ChunkPrepBenchReport {
+    let layer = TileLayerConfig::from_size(config.tile_size);
+    let tiles = AabbI {
+        min: IVec2::ZERO,
+        max: IVec2::splat(config.chunks * TileLayerConfig::CHUNK_EDGE),
+    };
+
+    // Mix every kind of style so that autotiling and occlusion are exercised as well.
+    let styles = TileStyles::from_styles(vec![
+        TileStyle::Invisible,
+        TileStyle::Solid(WHITE),
+        TileStyle::Textured {
+            texture: 0,
+            uv: Aabb::ZERO,
+            tint: WHITE,
+        },
+        TileStyle::AutoTile(AutoTileStyle {
+            texture: 0,
+            layout: AutoTileLayout::Blob,
+            uvs: vec![Aabb::ZERO; AutoTileLayout::Blob.sprite_count() as usize],
+            tint: Color::new(0.5, 0.8, 0.5, 1.),
+            connect_to_solids: true,
+        }),
+    ]);
+
+    let mut snapshot = LayerSnapshot::new(layer, tiles);
+    for chunk in tiles_to_chunks(tiles).grow(IVec2::ONE).iter() {
+        let mut data = Box::new([0; TileLayerConfig::CHUNK_AREA as usize]);
+        for (i, tile) in data.iter_mut().enumerate() {
+            let noise = hash_unit(5, chunk * TileLayerConfig::CHUNK_AREA + IVec2::X * i as i32);
+            *tile = (noise * 4.) as u16;
+        }
+        snapshot.insert(chunk, data);
+    }
+
+    let count_quads = |lists: Vec<ChunkDrawList>| lists.iter().map(ChunkDrawList::len).sum();
+
+    // Single-threaded
+    let start = Instant::now();
+    let mut serial_quads = 0;
+    for _ in 0..config.iterations {
+        serial_quads = count_quads(black_box(prepare_draw_lists_serial(
+            &snapshot, &styles, tiles,
+        )));
+    }
+    let serial = start.elapsed();
+
+    // Parallel
+    let start = Instant::now();
+    let mut parallel_quads = 0;
+    for _ in 0..config.iterations {
+        parallel_quads = count_quads(black_box(prepare_draw_lists(&snapshot, &styles, tiles)));
+    }
+    let parallel = start.elapsed();
+
+    if serial_quads != parallel_quads {
+        log::error!(
+            "Parallel chunk preparation produced {parallel_quads} quad(s) but serial preparation \
+             produced {serial_quads}"
+        );
+    }
+
+    ChunkPrepBenchReport {
+        serial,
+        parallel,
+        quads: serial_quads,
+    }
+}
+
+fn tiles_to_chunks(tiles: AabbI) -> AabbI {
+    AabbI {
+        min: TileLayerConfig::decompose_world_pos(tiles.min).0,
+        max: TileLayerConfig::decompose_world_pos(tiles.max - IVec2::ONE).0,
+    }
+    .inclusive()
+}
+
+// === Systems === //
+
+pub fn sys_run_benchmarks() {
+    if !is_key_pressed(KeyCode::F11) {
+        return;
+    }
+
+    let config = ColliderBenchConfig::default();
+    let report = bench_collider_queries(config);
+
+    log::info!(
+        "Collider query benchmark ({} colliders, {} queries, {} hits): build {:?}, update {:?}, \
+         grid {:?}, linear {:?}",
+        config.colliders,
+        config.queries,
+        report.hits,
+        report.build,
+        report.update,
+        report.grid_queries,
+        report.linear_queries,
+    );
+
+    let config = RayBenchConfig::default();
+    let report = bench_ray_traversal(config);
+
+    log::info!(
+        "Ray traversal benchmark ({} rays, {} tiles): stepped {:?}, traversed {:?}",
+        config.rays,
+        report.tiles,
+        report.stepped,
+        report.traversed,
+    );
+    let config = ChunkPrepBenchConfig::default();
+    let report = bench_chunk_preparation(config);
+
+    log::info!(
+        "Chunk preparation benchmark ({} chunks, {} iterations, {} quads): serial {:?}, \
+         parallel {:?}",
+        config.chunks * config.chunks,
+        config.iterations,
+        report.quads,
+        report.serial,
+        report.parallel,
+    );
+}
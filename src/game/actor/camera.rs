@@ -1,15 +1,28 @@
-use bevy_ecs::system::{ResMut, Resource};
+use bevy_ecs::{
+    system::{In, Res, ResMut, Resource},
+    world::World,
+};
 use macroquad::{
     camera::{pop_camera_state, push_camera_state, set_camera, Camera},
-    math::{Affine2, Mat4, Vec2, Vec4},
+    color::{BLACK, RED, WHITE},
+    input::{is_key_pressed, KeyCode},
+    math::{Affine2, Mat4, UVec2, Vec2, Vec3, Vec4},
     miniquad::RenderPass,
-    window::{screen_height, screen_width},
+    text::draw_text,
+    texture::{render_target, FilterMode, RenderTarget},
+    time::get_frame_time,
+    window::{clear_background, screen_height, screen_width},
 };
 
 use crate::{
-    game::math::aabb::Aabb,
+    game::{
+        math::{aabb::Aabb, draw::draw_texture_aabb, noise::value_noise_1d},
+        tile::data::TileWorld,
+        time::RenderAlpha,
+    },
     random_component,
     util::arena::{Obj, RandomAccess},
+    RenderViewport,
 };
 
 // === VirtualCamera === //
@@ -19,9 +32,16 @@ random_component!(VirtualCamera);
 #[derive(Debug)]
 pub struct VirtualCamera {
     transform: Affine2,
+    prev_transform: Affine2,
+    render_transform: Affine2,
     aabb: Aabb,
     constraints: VirtualCameraConstraints,
 
+    // Following
+    focus: Option<Vec2>,
+    trauma: f32,
+    shake_time: f32,
+
     // Caches
     last_viewport_size: Vec2,
     screen_to_world_ogl: Affine2,
@@ -34,8 +54,13 @@ impl VirtualCamera {
     pub fn new(transform: Affine2, aabb: Aabb, constraints: VirtualCameraConstraints) -> Self {
         Self {
             transform,
+            prev_transform: transform,
+            render_transform: transform,
             aabb,
             constraints,
+            focus: None,
+            trauma: 0.,
+            shake_time: 0.,
             last_viewport_size: Vec2::ONE,
             screen_to_world_ogl: Affine2::IDENTITY,
             world_to_screen_ogl: Affine2::IDENTITY,
@@ -48,12 +73,37 @@ impl VirtualCamera {
         self.aabb().map_affine(self.transform)
     }
 
+    /// The region of the world covered by the camera as of its last [`update`](Self::update),
+    /// which accounts for interpolation and texel snapping unlike [`visible_aabb`](Self::visible_aabb).
+    pub fn rendered_aabb(&self) -> Aabb {
+        self.aabb().map_affine(self.render_transform)
+    }
+
     pub fn transform(&self) -> Affine2 {
         self.transform
     }
 
+    /// Moves the camera without interpolating from its previous transform.
     pub fn set_transform(&mut self, xform: Affine2) {
         self.transform = xform;
+        self.prev_transform = xform;
+        self.render_transform = xform;
+    }
+
+    /// The transform with which the camera was last rendered. This lies between the transforms of
+    /// the last two ticks according to the alpha passed to [`interpolate`](Self::interpolate).
+    pub fn render_transform(&self) -> Affine2 {
+        self.render_transform
+    }
+
+    /// Places the camera `alpha` of the way between its previous and current translation for
+    /// rendering. This doesn't affect the camera's [`transform`](Self::transform).
+    pub fn interpolate(&mut self, alpha: f32) {
+        self.render_transform = self.transform;
+        self.render_transform.translation = self
+            .prev_transform
+            .translation
+            .lerp(self.transform.translation, alpha);
     }
 
     pub fn aabb(&self) -> Aabb {
@@ -72,13 +122,99 @@ impl VirtualCamera {
         &mut self.constraints
     }
 
-    pub fn update(&mut self, viewport_size: Vec2) {
-        self.last_viewport_size = viewport_size;
+    /// The amount of trauma lost every tick.
+    pub const TRAUMA_DECAY: f32 = 0.02;
+
+    /// The offset, in world units, of the camera at full trauma.
+    pub const MAX_SHAKE: f32 = 40.;
+
+    /// The rate at which the shake offset changes, in noise cells per tick.
+    pub const SHAKE_FREQUENCY: f32 = 0.6;
+
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// Adds trauma to the camera, making it shake until it decays. Trauma ranges from zero to one
+    /// and the strength of the shake grows quadratically with it so that small hits only cause a
+    /// subtle shake.
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0., 1.);
+    }
+
+    /// Moves the camera a single tick towards `target` according to its follow constraints and
+    /// applies any screen shake on top.
+    pub fn follow(&mut self, target: Vec2) {
+        let constraints = &self.constraints;
+        let mut focus = self.focus.unwrap_or(target);
+
+        // Only move once the target leaves the deadzone, and then only as far as is needed to bring
+        // it back to the zone's edge.
+        let desired = match constraints.deadzone {
+            Some(deadzone) => {
+                let half = deadzone / 2.;
+                target - (target - focus).clamp(-half, half)
+            }
+            None => target,
+        };
+
+        focus = focus.lerp(desired, constraints.smoothing);
+
+        if let Some(bounds) = constraints.bounds {
+            let half = self.aabb.size() / 2.;
+            let min = bounds.min + half;
+            let max = bounds.max - half;
+
+            // Center the camera along axes on which it's larger than the bounds.
+            focus = Vec2::new(
+                if min.x <= max.x {
+                    focus.x.clamp(min.x, max.x)
+                } else {
+                    bounds.center().x
+                },
+                if min.y <= max.y {
+                    focus.y.clamp(min.y, max.y)
+                } else {
+                    bounds.center().y
+                },
+            );
+        }
+
+        self.focus = Some(focus);
+
+        // Apply screen shake
+        self.shake_time += Self::SHAKE_FREQUENCY;
+        let shake = self.trauma * self.trauma * Self::MAX_SHAKE;
+        let offset = Vec2::new(
+            value_noise_1d(0, self.shake_time) * 2. - 1.,
+            value_noise_1d(1, self.shake_time) * 2. - 1.,
+        ) * shake;
+
+        self.trauma = (self.trauma - Self::TRAUMA_DECAY).max(0.);
+        self.prev_transform = self.transform;
+        self.transform = Affine2::from_translation(focus + offset);
+    }
+
+    /// Forgets the camera's smoothed focus so that the next call to [`follow`](Self::follow) snaps
+    /// straight to its target.
+    pub fn reset_follow(&mut self) {
+        self.focus = None;
+    }
+
+    /// Updates the camera to render directly into the `viewport` rectangle of the window.
+    pub fn update(&mut self, viewport: Aabb) {
+        self.update_in_viewport(viewport.size(), viewport);
+    }
+
+    /// Updates the camera to render into a target of size `target_size` which is then presented in
+    /// the `viewport` rectangle of the window. Pixel-relative projections are relative to the window.
+    pub fn update_in_viewport(&mut self, target_size: Vec2, viewport: Aabb) {
+        self.last_viewport_size = target_size;
 
         // Apply constraints
         if let Some(kept_area) = self.constraints.keep_area {
-            let size = viewport_size;
-            let size = size * (kept_area / (size.x * size.y)).sqrt();
+            let size = target_size;
+            let size = size * (kept_area / (size.x * size.y)).sqrt() / self.constraints.zoom;
             self.aabb = Aabb::new_centered(self.aabb.center(), size);
         }
 
@@ -100,7 +236,7 @@ impl VirtualCamera {
 
             // Now that the camera is mapped to the AABB's bounds in local space, we can convert that
             // into world-space coordinates.
-            let mat = self.transform * mat;
+            let mat = self.render_transform * mat;
 
             // We now have a affine transformation from OpenGL coordinates to world coordinates and
             // its inverse.
@@ -108,14 +244,22 @@ impl VirtualCamera {
             self.world_to_screen_ogl = mat.inverse();
 
             // Finally, let's derive a pixel-relative version of it.
-            self.world_to_screen_px = Affine2::from_translation(viewport_size / 2.)
-                * Affine2::from_scale(viewport_size * Vec2::new(0.5, -0.5))
+            self.world_to_screen_px = Affine2::from_translation(viewport.center())
+                * Affine2::from_scale(viewport.size() * Vec2::new(0.5, -0.5))
                 * self.world_to_screen_ogl;
 
             self.screen_to_world_px = self.world_to_screen_px.inverse();
         }
     }
 
+    /// Rounds the camera's render translation to the nearest texel of a render target of size
+    /// `target_size` so that pixel-aligned content doesn't shimmer as the camera moves.
+    pub fn snap_to_texels(&mut self, target_size: Vec2) {
+        let texel = self.aabb.size() / target_size;
+        self.render_transform.translation =
+            (self.render_transform.translation / texel).round() * texel;
+    }
+
     pub fn screen_to_world_ogl(&self) -> Affine2 {
         self.screen_to_world_ogl
     }
@@ -149,16 +293,46 @@ impl VirtualCamera {
             mat.translation.extend(0.).extend(1.),
         );
 
-        VirtualCameraSnapshot(mat)
+        VirtualCameraSnapshot {
+            matrix: mat,
+            viewport: None,
+        }
+    }
+
+    /// Produces a snapshot suitable for rendering into a render target, which macroquad expects to
+    /// be flipped vertically relative to the screen.
+    pub fn target_snapshot(&self) -> VirtualCameraSnapshot {
+        let snapshot = self.snapshot();
+        VirtualCameraSnapshot {
+            matrix: Mat4::from_scale(Vec3::new(1., -1., 1.)) * snapshot.matrix,
+            ..snapshot
+        }
     }
 }
 
 #[derive(Debug, Copy, Clone)]
-pub struct VirtualCameraSnapshot(Mat4);
+pub struct VirtualCameraSnapshot {
+    matrix: Mat4,
+    viewport: Option<(i32, i32, i32, i32)>,
+}
+
+impl VirtualCameraSnapshot {
+    /// Restricts rendering to the `rect` portion of the window.
+    pub fn with_viewport(mut self, rect: Aabb) -> Self {
+        // OpenGL viewports have their origin at the bottom left of the window.
+        self.viewport = Some((
+            rect.min.x.round() as i32,
+            (screen_height() - rect.max.y).round() as i32,
+            rect.w().round() as i32,
+            rect.h().round() as i32,
+        ));
+        self
+    }
+}
 
 impl Camera for VirtualCameraSnapshot {
     fn matrix(&self) -> Mat4 {
-        self.0
+        self.matrix
     }
 
     fn depth_enabled(&self) -> bool {
@@ -169,14 +343,66 @@ impl Camera for VirtualCameraSnapshot {
         None
     }
 
+    fn viewport(&self) -> Option<(i32, i32, i32, i32)> {
+        self.viewport
+    }
+}
+
+#[derive(Debug)]
+struct TargetCamera<'a> {
+    snapshot: VirtualCameraSnapshot,
+    target: &'a RenderTarget,
+}
+
+impl Camera for TargetCamera<'_> {
+    fn matrix(&self) -> Mat4 {
+        self.snapshot.matrix()
+    }
+
+    fn depth_enabled(&self) -> bool {
+        true
+    }
+
+    fn render_pass(&self) -> Option<RenderPass> {
+        Some(self.target.render_pass)
+    }
+
     fn viewport(&self) -> Option<(i32, i32, i32, i32)> {
         None
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct VirtualCameraConstraints {
     pub keep_area: Option<f32>,
+
+    /// How much larger than usual things appear, shrinking the [kept area](Self::keep_area)
+    /// accordingly. This has no effect on cameras which don't keep their visible area.
+    pub zoom: f32,
+
+    /// The fraction of the remaining distance to its target which a following camera covers every
+    /// tick. A value of one snaps the camera to its target.
+    pub smoothing: f32,
+
+    /// The size of a rectangle around the camera's center in which its target can move freely
+    /// without the camera following it.
+    pub deadzone: Option<Vec2>,
+
+    /// The region of the world which a following camera is never allowed to show anything outside
+    /// of.
+    pub bounds: Option<Aabb>,
+}
+
+impl Default for VirtualCameraConstraints {
+    fn default() -> Self {
+        Self {
+            keep_area: None,
+            zoom: 1.,
+            smoothing: 1.,
+            deadzone: None,
+            bounds: None,
+        }
+    }
 }
 
 impl VirtualCameraConstraints {
@@ -184,21 +410,159 @@ impl VirtualCameraConstraints {
         self.keep_area = Some(area.x * area.y);
         self
     }
+
+    pub fn with_smoothing(mut self, smoothing: f32) -> Self {
+        self.smoothing = smoothing.clamp(0., 1.);
+        self
+    }
+
+    pub fn with_deadzone(mut self, size: Vec2) -> Self {
+        self.deadzone = Some(size);
+        self
+    }
+
+    pub fn with_bounds(mut self, bounds: Aabb) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+}
+
+// === CameraStack === //
+
+/// A camera along with the portion of the window it renders into.
+#[derive(Debug)]
+pub struct CameraViewport {
+    pub camera: Obj<VirtualCamera>,
+
+    /// The portion of the window covered by this viewport, with both axes ranging from zero to one.
+    pub rect: Aabb,
+
+    target: Option<RenderTarget>,
+}
+
+impl CameraViewport {
+    pub fn screen_rect(&self, window: Vec2) -> Aabb {
+        Aabb {
+            min: self.rect.min * window,
+            max: self.rect.max * window,
+        }
+    }
+
+    fn target(&mut self, resolution: UVec2, filter: FilterMode) -> &RenderTarget {
+        cached_target(&mut self.target, resolution, filter)
+    }
+}
+
+/// The set of cameras rendered every frame. The first camera is the primary one, which is used to
+/// project the cursor and which UI overlays are rendered relative to.
+#[derive(Debug, Default, Resource)]
+pub struct CameraStack {
+    viewports: Vec<CameraViewport>,
+}
+
+impl CameraStack {
+    pub fn viewports(&self) -> &[CameraViewport] {
+        &self.viewports
+    }
+
+    pub fn primary(&self) -> Option<Obj<VirtualCamera>> {
+        self.viewports.first().map(|viewport| viewport.camera)
+    }
+
+    pub fn cameras(&self) -> impl Iterator<Item = Obj<VirtualCamera>> + '_ {
+        self.viewports.iter().map(|viewport| viewport.camera)
+    }
+
+    /// Adds a camera to the stack and splits the window evenly between all cameras.
+    pub fn push(&mut self, camera: Obj<VirtualCamera>) {
+        self.viewports.push(CameraViewport {
+            camera,
+            rect: Aabb::ZERO_TO_ONE,
+            target: None,
+        });
+        self.split_evenly();
+    }
+
+    /// Renders `new` in place of `old`, keeping the viewport's layout. This is used to switch
+    /// which world a viewport shows.
+    pub fn replace(&mut self, old: Obj<VirtualCamera>, new: Obj<VirtualCamera>) {
+        for viewport in &mut self.viewports {
+            if viewport.camera == old {
+                viewport.camera = new;
+            }
+        }
+    }
+
+    pub fn remove(&mut self, camera: Obj<VirtualCamera>) {
+        self.viewports.retain(|viewport| viewport.camera != camera);
+        self.split_evenly();
+    }
+
+    /// Lays the viewports out side-by-side as equally wide columns spanning the window's height.
+    pub fn split_evenly(&mut self) {
+        let width = 1. / self.viewports.len() as f32;
+
+        for (i, viewport) in self.viewports.iter_mut().enumerate() {
+            viewport.rect = Aabb::new(i as f32 * width, 0., width, 1.);
+        }
+    }
 }
 
 // === Systems === //
 
-#[derive(Debug, Clone, Default, Resource)]
+/// The camera currently being rendered. Outside of the [`RenderViewport`] schedule, this is the
+/// primary camera of the [`CameraStack`].
+#[derive(Debug, Clone, Resource)]
 pub struct ActiveCamera {
+    /// The index of the viewport in the [`CameraStack`] being rendered.
+    pub viewport: usize,
     pub camera: Option<Obj<VirtualCamera>>,
+
+    /// The world viewed by `camera`. Cameras view the world whose entity they're attached to.
+    pub world: Option<Obj<TileWorld>>,
+
+    /// The region of `world` rendered by `camera`.
+    pub visible: Aabb,
     pub snapshot: Option<VirtualCameraSnapshot>,
+    pub target: Option<RenderTarget>,
+
+    /// The portion of the window into which `target` is presented.
+    pub present_rect: Aabb,
+}
+
+impl Default for ActiveCamera {
+    fn default() -> Self {
+        Self {
+            viewport: 0,
+            camera: None,
+            world: None,
+            visible: Aabb::ZERO,
+            snapshot: None,
+            target: None,
+            present_rect: Aabb::ZERO,
+        }
+    }
 }
 
 impl ActiveCamera {
+    /// Whether things inside `world` should be rendered through this camera.
+    pub fn shows(&self, world: Obj<TileWorld>) -> bool {
+        self.world == Some(world)
+    }
+
+    /// Whether something occupying `aabb` in `world` could appear through this camera. Render
+    /// systems use this to skip drawing things which are off-screen.
+    pub fn sees(&self, world: Obj<TileWorld>, aabb: Aabb) -> bool {
+        self.shows(world) && self.visible.grow(Vec2::splat(CULL_MARGIN)).intersects(aabb)
+    }
+
     pub fn apply(&self) -> impl Drop {
         push_camera_state();
-        if let Some(camera) = self.snapshot {
-            set_camera(&camera);
+        if let Some(snapshot) = self.snapshot {
+            match &self.target {
+                Some(target) => set_camera(&TargetCamera { snapshot, target }),
+                None => set_camera(&snapshot),
+            }
         }
 
         scopeguard::guard((), |()| {
@@ -207,14 +571,382 @@ impl ActiveCamera {
     }
 }
 
+/// The distance by which the visible region is grown when culling so that camera shake doesn't
+/// reveal missing draws on the screen's edges.
+const CULL_MARGIN: f32 = VirtualCamera::MAX_SHAKE;
+
+/// Skips the items of an iterator which are [not seen](ActiveCamera::sees) by a camera.
+pub trait CullExt: Iterator + Sized {
+    /// Keeps the items for which `bounds` returns a world and bounding box seen by `camera`.
+    fn culled<F>(self, camera: &ActiveCamera, bounds: F) -> Culled<'_, Self, F>
+    where
+        F: FnMut(&Self::Item) -> (Obj<TileWorld>, Aabb),
+    {
+        Culled {
+            iter: self,
+            camera,
+            bounds,
+        }
+    }
+}
+
+impl<I: Iterator> CullExt for I {}
+
+#[derive(Debug, Clone)]
+pub struct Culled<'a, I, F> {
+    iter: I,
+    camera: &'a ActiveCamera,
+    bounds: F,
+}
+
+impl<I, F> Iterator for Culled<'_, I, F>
+where
+    I: Iterator,
+    F: FnMut(&I::Item) -> (Obj<TileWorld>, Aabb),
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.find(|item| {
+            let (world, aabb) = (self.bounds)(item);
+            self.camera.sees(world, aabb)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+/// Runs the [`RenderViewport`] schedule once for every camera in the [`CameraStack`].
+pub fn sys_render_viewports(world: &mut World) {
+    let count = world.resource::<CameraStack>().viewports().len();
+
+    // The primary camera is rendered last so that it stays active for the rest of the frame.
+    for viewport in (0..count).rev() {
+        world.resource_mut::<ActiveCamera>().viewport = viewport;
+        world.run_schedule(RenderViewport);
+    }
+}
+
 pub fn sys_update_camera(
-    mut rand: RandomAccess<&mut VirtualCamera>,
+    mut rand: RandomAccess<(&mut VirtualCamera, &TileWorld)>,
     mut res: ResMut<ActiveCamera>,
+    mut stack: ResMut<CameraStack>,
+    pixel: Res<PixelPerfect>,
+    dynamic: Res<DynamicResolution>,
+    alpha: Res<RenderAlpha>,
 ) {
     rand.provide(|| {
-        if let Some(mut camera) = res.camera {
-            camera.update(Vec2::new(screen_width(), screen_height()));
-            res.snapshot = Some(camera.snapshot());
+        let Some(viewport) = stack.viewports.get_mut(res.viewport) else {
+            res.camera = None;
+            res.world = None;
+            return;
+        };
+
+        let mut camera = viewport.camera;
+        let rect = viewport.screen_rect(Vec2::new(screen_width(), screen_height()));
+        res.camera = Some(camera);
+        res.world = camera.try_map_entity::<TileWorld>();
+        camera.interpolate(alpha.0);
+
+        if pixel.enabled {
+            let resolution = pixel.resolution.as_vec2();
+            res.present_rect = pixel.viewport(rect);
+            camera.snap_to_texels(resolution);
+            camera.update_in_viewport(resolution, res.present_rect);
+            res.snapshot = Some(camera.target_snapshot());
+            res.target = Some(
+                viewport
+                    .target(pixel.resolution, FilterMode::Nearest)
+                    .clone(),
+            );
+
+            let _guard = res.apply();
+            clear_background(BLACK);
+        } else if let Some(resolution) = dynamic.resolution(rect.size()) {
+            // Dynamic resolution targets are stretched over the entire viewport.
+            res.present_rect = rect;
+            camera.update_in_viewport(resolution.as_vec2(), rect);
+            res.snapshot = Some(camera.target_snapshot());
+            res.target = Some(viewport.target(resolution, FilterMode::Linear).clone());
+
+            let _guard = res.apply();
+            clear_background(BLACK);
+        } else {
+            res.present_rect = rect;
+            camera.update(rect);
+            res.snapshot = Some(camera.snapshot().with_viewport(rect));
+            res.target = None;
         }
+
+        res.visible = camera.rendered_aabb();
     });
 }
+
+// === PixelPerfect === //
+
+#[derive(Debug, Clone, Resource)]
+pub struct PixelPerfect {
+    pub enabled: bool,
+    pub resolution: UVec2,
+}
+
+impl Default for PixelPerfect {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            resolution: UVec2::new(480, 270),
+        }
+    }
+}
+
+impl PixelPerfect {
+    /// Computes the largest integer upscale of the virtual resolution which fits in `area`,
+    /// centered within it.
+    pub fn viewport(&self, area: Aabb) -> Aabb {
+        let resolution = self.resolution.as_vec2();
+        let scale = (area.size() / resolution).min_element().floor().max(1.);
+        Aabb::new_centered(area.center(), resolution * scale)
+    }
+}
+
+fn cached_target(
+    cache: &mut Option<RenderTarget>,
+    resolution: UVec2,
+    filter: FilterMode,
+) -> &RenderTarget {
+    let stale = match cache {
+        Some(target) => {
+            target.texture.width() as u32 != resolution.x
+                || target.texture.height() as u32 != resolution.y
+        }
+        None => true,
+    };
+
+    if stale {
+        let target = render_target(resolution.x, resolution.y);
+        target.texture.set_filter(filter);
+        *cache = Some(target);
+    }
+
+    cache.as_ref().unwrap()
+}
+
+pub fn sys_toggle_pixel_perfect(mut pixel: ResMut<PixelPerfect>) {
+    if is_key_pressed(KeyCode::F3) {
+        pixel.enabled = !pixel.enabled;
+    }
+}
+
+pub fn sys_present_pixel_target(camera: Res<ActiveCamera>) {
+    let Some(target) = &camera.target else {
+        return;
+    };
+
+    draw_texture_aabb(&target.texture, camera.present_rect, WHITE);
+}
+
+// === DynamicResolution === //
+
+/// The fractions of the window resolution at which the world can be rendered, from sharpest to
+/// cheapest.
+pub const RESOLUTION_SCALES: [f32; 4] = [1., 0.75, 0.5, 0.35];
+
+/// Automatically lowers the resolution of the world render target while frames take longer than
+/// `budget` and raises it again once they comfortably fit. This only affects presentation; the
+/// simulation is never touched.
+///
+/// With vsync enabled, frame times never drop below the refresh interval, so headroom is judged
+/// from the time spent building each frame, as reported through
+/// [`record_work`](Self::record_work), rather than from the frame time itself.
+#[derive(Debug, Clone, Resource)]
+pub struct DynamicResolution {
+    pub enabled: bool,
+
+    /// The target duration of a single frame, in seconds.
+    pub budget: f32,
+
+    /// Frames must be slower than `budget * downscale_ratio` for `downscale_delay` seconds before
+    /// the resolution is lowered.
+    pub downscale_ratio: f32,
+    pub downscale_delay: f32,
+
+    /// Frames must be built in less than `budget * upscale_ratio` for `upscale_delay` seconds
+    /// before the resolution is raised. This is intentionally stricter than the downscale condition
+    /// so that we don't oscillate between two levels.
+    pub upscale_ratio: f32,
+    pub upscale_delay: f32,
+
+    level: usize,
+    smoothed_frame_time: f32,
+    smoothed_work_time: f32,
+    last_work_time: f32,
+    over_budget_for: f32,
+    under_budget_for: f32,
+
+    /// The number of times in a row the resolution had to be lowered again shortly after being
+    /// raised. Each one doubles the time needed before the next upscale.
+    failed_upscales: u32,
+    since_upscale: Option<f32>,
+}
+
+impl Default for DynamicResolution {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            budget: 1. / 60.,
+            downscale_ratio: 1.15,
+            downscale_delay: 0.5,
+            upscale_ratio: 0.75,
+            upscale_delay: 2.,
+            level: 0,
+            smoothed_frame_time: 1. / 60.,
+            smoothed_work_time: 1. / 60.,
+            last_work_time: 1. / 60.,
+            over_budget_for: 0.,
+            under_budget_for: 0.,
+            failed_upscales: 0,
+            since_upscale: None,
+        }
+    }
+}
+
+impl DynamicResolution {
+    /// The weight given to the most recent frame when smoothing frame times.
+    const SMOOTHING: f32 = 0.1;
+
+    /// The maximum number of times the upscale delay is doubled by failed upscales.
+    const MAX_UPSCALE_BACKOFF: u32 = 4;
+
+    pub fn scale(&self) -> f32 {
+        if self.enabled {
+            RESOLUTION_SCALES[self.level]
+        } else {
+            1.
+        }
+    }
+
+    pub fn smoothed_frame_time(&self) -> f32 {
+        self.smoothed_frame_time
+    }
+
+    pub fn smoothed_work_time(&self) -> f32 {
+        self.smoothed_work_time
+    }
+
+    /// Returns the resolution of the world render target for a viewport of size `size`, or `None`
+    /// if the world should be rendered directly to the screen.
+    pub fn resolution(&self, size: Vec2) -> Option<UVec2> {
+        let scale = self.scale();
+        (scale < 1.).then(|| (size * scale).round().max(Vec2::ONE).as_uvec2())
+    }
+
+    /// Records how long the last frame took to build, in seconds, not counting the time spent
+    /// waiting for it to be presented.
+    pub fn record_work(&mut self, work: f32) {
+        self.last_work_time = work;
+    }
+
+    /// Accounts for a frame which took `dt` seconds, adjusting the resolution level if necessary.
+    pub fn record_frame(&mut self, dt: f32) {
+        self.smoothed_frame_time += (dt - self.smoothed_frame_time) * Self::SMOOTHING;
+        self.smoothed_work_time +=
+            (self.last_work_time - self.smoothed_work_time) * Self::SMOOTHING;
+
+        if !self.enabled {
+            return;
+        }
+
+        if let Some(since_upscale) = &mut self.since_upscale {
+            *since_upscale += dt;
+
+            // The upscale held long enough to count as a success.
+            if *since_upscale >= self.upscale_delay {
+                self.since_upscale = None;
+                self.failed_upscales = 0;
+            }
+        }
+
+        if self.smoothed_frame_time > self.budget * self.downscale_ratio {
+            self.over_budget_for += dt;
+            self.under_budget_for = 0.;
+        } else if self.smoothed_work_time < self.budget * self.upscale_ratio {
+            self.under_budget_for += dt;
+            self.over_budget_for = 0.;
+        } else {
+            self.over_budget_for = 0.;
+            self.under_budget_for = 0.;
+        }
+
+        let upscale_delay = self.upscale_delay * (1 << self.failed_upscales) as f32;
+
+        if self.over_budget_for >= self.downscale_delay && self.level + 1 < RESOLUTION_SCALES.len()
+        {
+            if self.since_upscale.take().is_some() {
+                self.failed_upscales = (self.failed_upscales + 1).min(Self::MAX_UPSCALE_BACKOFF);
+            }
+            self.set_level(self.level + 1);
+        } else if self.under_budget_for >= upscale_delay && self.level > 0 {
+            self.since_upscale = Some(0.);
+            self.set_level(self.level - 1);
+        }
+    }
+
+    fn set_level(&mut self, level: usize) {
+        log::info!(
+            "Dynamic resolution scale changed to {:.0}% (frame time: {:.1}ms, work time: {:.1}ms)",
+            RESOLUTION_SCALES[level] * 100.,
+            self.smoothed_frame_time * 1000.,
+            self.smoothed_work_time * 1000.,
+        );
+
+        self.level = level;
+        self.over_budget_for = 0.;
+        self.under_budget_for = 0.;
+    }
+}
+
+pub fn sys_update_dynamic_resolution(mut dynamic: ResMut<DynamicResolution>) {
+    if is_key_pressed(KeyCode::F4) {
+        dynamic.enabled = !dynamic.enabled;
+    }
+
+    dynamic.record_frame(get_frame_time());
+}
+
+pub fn sys_render_resolution_metrics(
+    In(rect): In<Aabb>,
+    dynamic: Res<DynamicResolution>,
+    pixel: Res<PixelPerfect>,
+) {
+    let window = Vec2::new(screen_width(), screen_height());
+    let (mode, resolution) = if pixel.enabled {
+        ("pixel", pixel.resolution)
+    } else if !dynamic.enabled {
+        ("fixed", window.as_uvec2())
+    } else {
+        (
+            "dynamic",
+            dynamic
+                .resolution(window)
+                .unwrap_or_else(|| window.as_uvec2()),
+        )
+    };
+
+    draw_text(
+        &format!(
+            "Resolution: {}x{} ({mode}, {:.0}%) | Frame: {:.1}ms, work: {:.1}ms / {:.1}ms",
+            resolution.x,
+            resolution.y,
+            dynamic.scale() * 100.,
+            dynamic.smoothed_frame_time() * 1000.,
+            dynamic.smoothed_work_time() * 1000.,
+            dynamic.budget * 1000.,
+        ),
+        rect.min.x,
+        rect.max.y,
+        24.,
+        RED,
+    );
+}
@@ -0,0 +1,231 @@
+use bevy_ecs::{
+    bundle::Bundle,
+    component::Component,
+    entity::Entity,
+    event::{Event, EventReader, EventWriter},
+    query::{With, Without},
+    system::{Commands, Local, Query, Res, ResMut},
+};
+use macroquad::{
+    color::{Color, GRAY, GREEN, LIGHTGRAY, WHITE},
+    math::Vec2,
+    shapes::{draw_rectangle, draw_triangle},
+    text::{draw_text, measure_text},
+    time::get_time,
+    window::screen_width,
+};
+
+use crate::{
+    game::{
+        draw_order::{DrawQueue, RenderLayer, RenderOrder},
+        math::{
+            aabb::Aabb,
+            draw::{draw_rectangle_aabb, stroke_rectangle_aabb},
+        },
+        tile::{
+            collider::{Collider, CollisionLayers, InsideWorld},
+            data::TileWorld,
+        },
+    },
+    util::arena::{Obj, ObjOwner, RandomAccess},
+};
+
+use super::{
+    camera::ActiveCamera,
+    death::{Dead, Respawns},
+    health::Health,
+    kinematic::{ColliderEvent, ColliderListens, Pos},
+    player::RemoteInput,
+};
+
+// === Components === //
+
+#[derive(Bundle)]
+pub struct CheckpointBundle {
+    pub pos: Pos,
+    pub world: InsideWorld,
+    pub collider: Collider,
+    pub layers: CollisionLayers,
+    pub listens: ColliderListens,
+    pub checkpoint: Checkpoint,
+}
+
+impl CheckpointBundle {
+    /// Creates a checkpoint respawning actors at `pos`, which is the center of an actor standing on
+    /// the ground beneath the checkpoint's flag.
+    pub fn new(world: InsideWorld, pos: Vec2) -> Self {
+        let ground = pos.y + 20.;
+
+        Self {
+            pos: Pos(pos),
+            world,
+            collider: Collider(Aabb {
+                min: Vec2::new(pos.x - CHECKPOINT_SIZE.x / 2., ground - CHECKPOINT_SIZE.y),
+                max: Vec2::new(pos.x + CHECKPOINT_SIZE.x / 2., ground),
+            }),
+            layers: CHECKPOINT_LAYERS,
+            listens: ColliderListens::with_mask(CollisionLayers::PLAYERS),
+            checkpoint: Checkpoint,
+        }
+    }
+}
+
+/// Checkpoints are found by overlap queries but never block anything.
+pub const CHECKPOINT_LAYERS: CollisionLayers = CollisionLayers::new(CollisionLayers::TRIGGERS, 0);
+
+/// The size of the trigger volume of checkpoints created by [`CheckpointBundle::new`].
+pub const CHECKPOINT_SIZE: Vec2 = Vec2::new(60., 120.);
+
+/// Marks a trigger volume which actors that [`Respawns`] can touch to respawn at its [`Pos`] from
+/// then on.
+#[derive(Debug, Copy, Clone, Default, Component)]
+pub struct Checkpoint;
+
+/// The checkpoint an actor last touched.
+#[derive(Debug, Clone, Component)]
+pub struct ActiveCheckpoint {
+    pub checkpoint: Entity,
+    pub world: Obj<TileWorld>,
+    pub pos: Vec2,
+
+    /// The actor's health when it touched the checkpoint, which it gets back upon respawning there.
+    pub health: Health,
+}
+
+/// Sent when an actor touches a checkpoint other than its current one.
+#[derive(Debug, Event)]
+pub struct CheckpointActivated {
+    pub entity: Entity,
+    pub checkpoint: Entity,
+    pub pos: Vec2,
+}
+
+// === Systems === //
+
+pub fn sys_activate_checkpoints(
+    mut rand: RandomAccess<&Health>,
+    checkpoints: Query<(&InsideWorld, &Pos), With<Checkpoint>>,
+    actors: Query<(&ObjOwner<Health>, Option<&ActiveCheckpoint>), (With<Respawns>, Without<Dead>)>,
+    mut events: EventReader<ColliderEvent>,
+    mut activated: EventWriter<CheckpointActivated>,
+    mut commands: Commands,
+) {
+    rand.provide(|| {
+        for event in events.read() {
+            if !event.entered {
+                continue;
+            }
+
+            let (Ok((&InsideWorld(world), &Pos(pos))), Ok((&ObjOwner(health), active))) =
+                (checkpoints.get(event.listener), actors.get(event.other))
+            else {
+                continue;
+            };
+
+            // Walking back through the current checkpoint shouldn't announce it again.
+            if active.is_some_and(|active| active.checkpoint == event.listener) {
+                continue;
+            }
+
+            commands.entity(event.other).insert(ActiveCheckpoint {
+                checkpoint: event.listener,
+                world,
+                pos,
+                health: (*health).clone(),
+            });
+
+            activated.send(CheckpointActivated {
+                entity: event.other,
+                checkpoint: event.listener,
+                pos,
+            });
+        }
+    });
+}
+
+pub fn sys_render_checkpoints(
+    query: Query<
+        (
+            Entity,
+            &InsideWorld,
+            &Pos,
+            Option<&RenderLayer>,
+            Option<&RenderOrder>,
+        ),
+        With<Checkpoint>,
+    >,
+    active: Query<&ActiveCheckpoint, Without<RemoteInput>>,
+    mut draws: ResMut<DrawQueue>,
+    camera: Res<ActiveCamera>,
+) {
+    for (entity, &InsideWorld(world), &Pos(pos), layer, order) in query.iter() {
+        if !camera.shows(world) {
+            continue;
+        }
+
+        let color = if active.iter().any(|active| active.checkpoint == entity) {
+            GREEN
+        } else {
+            GRAY
+        };
+
+        let ground = pos.y + 20.;
+        let top = ground - CHECKPOINT_SIZE.y * 0.6;
+
+        let layer = layer.copied().unwrap_or(RenderLayer::Background);
+        draws.push(layer, RenderOrder::key_of(order, ground), move || {
+            draw_rectangle(pos.x - 2., top, 4., ground - top, LIGHTGRAY);
+            draw_triangle(
+                Vec2::new(pos.x + 2., top),
+                Vec2::new(pos.x + 2., top + 24.),
+                Vec2::new(pos.x + 32., top + 12.),
+                color,
+            );
+        });
+    }
+}
+
+/// Briefly tells local players that they reached a new checkpoint.
+pub fn sys_render_checkpoint_toast(
+    mut events: EventReader<CheckpointActivated>,
+    remote: Query<(), With<RemoteInput>>,
+    mut shown_at: Local<Option<f64>>,
+) {
+    const TOAST_DURATION: f64 = 2.;
+    const TEXT: &str = "Checkpoint reached";
+
+    for event in events.read() {
+        if !remote.contains(event.entity) {
+            *shown_at = Some(get_time());
+        }
+    }
+
+    let Some(age) = shown_at.map(|shown_at| get_time() - shown_at) else {
+        return;
+    };
+
+    if age >= TOAST_DURATION {
+        *shown_at = None;
+        return;
+    }
+
+    // Slide down into place and fade out over the last half second.
+    let alpha = ((TOAST_DURATION - age) * 2.).min(1.) as f32;
+    let slide = (1. - (age * 8.).min(1.)) as f32 * 20.;
+
+    let dims = measure_text(TEXT, None, 24, 1.);
+    let rect = Aabb::new_centered(
+        Vec2::new(screen_width() / 2., 160. - slide),
+        Vec2::new(dims.width + 32., 40.),
+    );
+
+    draw_rectangle_aabb(rect, Color::new(0., 0., 0., 0.6 * alpha));
+    stroke_rectangle_aabb(rect, 2., Color { a: alpha, ..GREEN });
+    draw_text(
+        TEXT,
+        rect.min.x + 16.,
+        rect.center().y + dims.offset_y / 2.,
+        24.,
+        Color { a: alpha, ..WHITE },
+    );
+}
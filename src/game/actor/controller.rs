@@ -0,0 +1,201 @@
+use bevy_ecs::{component::Component, entity::Entity, query::With, system::Query};
+use macroquad::math::Vec2;
+
+use crate::{
+    game::tile::{
+        collider::{
+            Collider, CollisionLayers, InsideWorld, TrackedCollider, TrackedColliderChunk,
+            WorldColliders,
+        },
+        data::{TileChunk, TileWorld},
+        kinematic::{AnyCollision, KinematicApi, TileColliderDescriptor},
+        material::MaterialRegistry,
+    },
+    util::arena::{RandomAccess, RandomEntityExt},
+};
+
+use super::kinematic::{ColliderMoves, Vel};
+
+// === CharacterController === //
+
+/// Drives a moving collider like a platformer character: it runs left and right, jumps off of the
+/// ground, and falls according to its [`Gravity`](super::kinematic::Gravity) component. Holding
+/// the jump input for longer jumps higher.
+///
+/// Inputs are fed in every tick through [`set_input`](Self::set_input) and applied to the actor's
+/// velocity by [`sys_update_character_controllers`].
+#[derive(Debug, Clone, Component)]
+pub struct CharacterController {
+    /// The horizontal speed the character accelerates towards while running, in units per tick.
+    pub max_run_speed: f32,
+
+    /// The most by which the horizontal speed can change in a single tick while grounded.
+    pub run_acceleration: f32,
+
+    /// The fraction of `run_acceleration` available while airborne.
+    pub air_control: f32,
+
+    /// The upward speed given to the character when it jumps.
+    pub jump_impulse: f32,
+
+    /// The factor by which the upward speed of a jump is scaled once the jump input is released.
+    /// Lower values give the player more control over the height of their jumps.
+    pub jump_cut: f32,
+
+    /// The number of ticks after walking off of a ledge during which the character can still jump.
+    pub coyote_ticks: u32,
+
+    /// The number of ticks before landing during which a jump input is remembered and performed
+    /// upon touching the ground.
+    pub jump_buffer_ticks: u32,
+
+    // Input
+    run: f32,
+    jump_held: bool,
+
+    // State
+    grounded: bool,
+    airborne_for: u32,
+    jump_requested_for: Option<u32>,
+    was_jump_held: bool,
+    is_jumping: bool,
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self {
+            max_run_speed: 8.,
+            run_acceleration: 1.,
+            air_control: 0.6,
+            jump_impulse: 12.,
+            jump_cut: 0.5,
+            coyote_ticks: 6,
+            jump_buffer_ticks: 6,
+            run: 0.,
+            jump_held: false,
+            grounded: false,
+            airborne_for: 0,
+            jump_requested_for: None,
+            was_jump_held: false,
+            is_jumping: false,
+        }
+    }
+}
+
+impl CharacterController {
+    /// Sets the inputs for the upcoming tick. `run` ranges from `-1` (left) to `1` (right).
+    pub fn set_input(&mut self, run: f32, jump_held: bool) {
+        self.run = run.clamp(-1., 1.);
+        self.jump_held = jump_held;
+    }
+
+    /// Derives the controller inputs from a movement heading, jumping while it points upwards.
+    pub fn set_input_from_heading(&mut self, heading: Vec2) {
+        self.set_input(heading.x, heading.y < -0.5);
+    }
+
+    /// Whether the character was standing on something during the last tick.
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    /// Advances the controller by a tick, updating the character's velocity `vel` given whether
+    /// it's currently `grounded`.
+    pub fn tick(&mut self, grounded: bool, vel: &mut Vec2) {
+        self.grounded = grounded;
+
+        // Track how long we've been in the air for coyote time.
+        if grounded {
+            self.airborne_for = 0;
+            self.is_jumping = false;
+        } else {
+            self.airborne_for = self.airborne_for.saturating_add(1);
+        }
+
+        // Buffer jump presses so that jumping slightly before landing still works.
+        let pressed = self.jump_held && !self.was_jump_held;
+        self.was_jump_held = self.jump_held;
+
+        self.jump_requested_for = if pressed {
+            Some(0)
+        } else {
+            self.jump_requested_for
+                .map(|ticks| ticks + 1)
+                .filter(|&ticks| ticks <= self.jump_buffer_ticks)
+        };
+
+        // Jump
+        if self.jump_requested_for.is_some() && self.airborne_for <= self.coyote_ticks {
+            vel.y = -self.jump_impulse;
+            self.jump_requested_for = None;
+            self.airborne_for = self.coyote_ticks + 1;
+            self.is_jumping = true;
+        }
+
+        // Releasing the jump input early cuts the jump short.
+        if self.is_jumping && !self.jump_held && vel.y < 0. {
+            vel.y *= self.jump_cut;
+            self.is_jumping = false;
+        }
+
+        if vel.y >= 0. {
+            self.is_jumping = false;
+        }
+
+        // Run
+        let target = self.run * self.max_run_speed;
+        let acceleration = if grounded {
+            self.run_acceleration
+        } else {
+            self.run_acceleration * self.air_control
+        };
+        vel.x += (target - vel.x).clamp(-acceleration, acceleration);
+    }
+}
+
+// === Systems === //
+
+pub fn sys_update_character_controllers(
+    mut query: Query<
+        (
+            Entity,
+            &InsideWorld,
+            &Collider,
+            Option<&CollisionLayers>,
+            &mut Vel,
+            &mut CharacterController,
+        ),
+        With<ColliderMoves>,
+    >,
+    mut rand: RandomAccess<(
+        &mut KinematicApi,
+        &TileWorld,
+        &TileChunk,
+        &MaterialRegistry,
+        &TileColliderDescriptor,
+        &WorldColliders,
+        &TrackedCollider,
+        &TrackedColliderChunk,
+    )>,
+) {
+    rand.provide(|| {
+        for (me, &InsideWorld(world), &Collider(aabb), layers, mut vel, mut controller) in
+            query.iter_mut()
+        {
+            let mut kinematics = world.entity().get::<KinematicApi>();
+            let mask = layers.copied().unwrap_or_default().mask;
+
+            // We're grounded if something blocks us from moving down and we aren't already moving
+            // up, which would otherwise let us jump again right after taking off.
+            let grounded = vel.0.y >= 0.
+                && !kinematics
+                    .get_clip_mask(aabb, Vec2::Y, mask, |coll| match coll {
+                        AnyCollision::Tile(_, _, _) => true,
+                        AnyCollision::Collider(other, _) => other != me,
+                    })
+                    .y;
+
+            controller.tick(grounded, &mut vel.0);
+        }
+    });
+}
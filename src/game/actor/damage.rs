@@ -0,0 +1,125 @@
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    event::{Event, EventReader},
+    system::Query,
+};
+use serde::Deserialize;
+
+use crate::util::arena::{ObjOwner, RandomAccess, SendsEvent};
+
+use super::{
+    health::{Health, HealthChangeCause, HealthChanged},
+    player::HitInvulnerability,
+};
+
+// === Components === //
+
+/// Requests that `amount` damage be dealt to the [`Health`] of `target`. All damage which should
+/// respect resistances and hit invulnerability goes through this event so that it is applied in a
+/// single place by [`sys_apply_damage`].
+#[derive(Debug, Event)]
+pub struct DamageEvent {
+    /// The entity which dealt the damage, e.g. a bullet or an enemy.
+    pub source: Entity,
+    pub target: Entity,
+    pub kind: DamageKind,
+    pub amount: f32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DamageKind {
+    Projectile,
+    Melee,
+    Explosion,
+
+    /// Touching an actor which deals [contact damage](super::player::ContactDamage).
+    Contact,
+
+    /// The world itself, such as a [`Hazard`] or the void outside of the world's bounds.
+    Environmental,
+}
+
+impl DamageKind {
+    pub fn cause(self) -> HealthChangeCause {
+        match self {
+            DamageKind::Projectile => HealthChangeCause::Bullet,
+            DamageKind::Melee => HealthChangeCause::Melee,
+            DamageKind::Explosion => HealthChangeCause::Explosion,
+            DamageKind::Contact => HealthChangeCause::Contact,
+            DamageKind::Environmental => HealthChangeCause::Environment,
+        }
+    }
+}
+
+/// Marks a source of contact damage as part of the environment rather than an actor.
+#[derive(Debug, Copy, Clone, Component)]
+pub struct Hazard;
+
+/// The fraction of each kind of damage which an actor shrugs off, where `1.0` makes it immune.
+#[derive(Debug, Copy, Clone, Default, Component, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DamageResistance {
+    pub projectile: f32,
+    pub melee: f32,
+    pub explosion: f32,
+    pub contact: f32,
+    pub environmental: f32,
+}
+
+impl DamageResistance {
+    pub fn get(&self, kind: DamageKind) -> f32 {
+        match kind {
+            DamageKind::Projectile => self.projectile,
+            DamageKind::Melee => self.melee,
+            DamageKind::Explosion => self.explosion,
+            DamageKind::Contact => self.contact,
+            DamageKind::Environmental => self.environmental,
+        }
+    }
+
+    pub fn apply(&self, kind: DamageKind, amount: f32) -> f32 {
+        amount * (1. - self.get(kind).clamp(0., 1.))
+    }
+}
+
+// === Systems === //
+
+pub fn sys_apply_damage(
+    mut rand: RandomAccess<(&mut Health, SendsEvent<HealthChanged>)>,
+    mut query: Query<(
+        &ObjOwner<Health>,
+        Option<&DamageResistance>,
+        Option<&mut HitInvulnerability>,
+    )>,
+    mut events: EventReader<DamageEvent>,
+) {
+    for (_, _, iframes) in query.iter_mut() {
+        if let Some(mut iframes) = iframes {
+            iframes.tick();
+        }
+    }
+
+    rand.provide(|| {
+        for event in events.read() {
+            let Ok((&ObjOwner(health), resistance, iframes)) = query.get_mut(event.target) else {
+                continue;
+            };
+
+            if iframes.as_ref().is_some_and(|iframes| iframes.is_active()) {
+                continue;
+            }
+
+            let amount = resistance.map_or(event.amount, |resistance| {
+                resistance.apply(event.kind, event.amount)
+            });
+
+            let before = health.health();
+            health.damage(amount, event.kind.cause());
+
+            if let Some(mut iframes) = iframes.filter(|_| health.health() < before) {
+                iframes.trigger();
+            }
+        }
+    });
+}
@@ -0,0 +1,189 @@
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    event::{Event, EventWriter},
+    query::{Has, With, Without},
+    system::{Commands, Query},
+};
+use macroquad::math::Vec2;
+
+use crate::{
+    game::{
+        math::aabb::Aabb,
+        tile::collider::{Collider, InsideWorld, TrackedCollider},
+    },
+    util::arena::{despawn_entity, ObjOwner, RandomAccess, SendsEvent},
+};
+
+use super::{
+    checkpoint::ActiveCheckpoint,
+    effects::{StatusEffect, StatusEffectApplied, StatusEffectExpired, StatusEffects},
+    health::{Health, HealthChangeCause, HealthChanged},
+    kinematic::{ColliderMoves, Pos, PrevPos, Vel},
+};
+
+// === Components === //
+
+/// Sent when an actor's [`Health`] reaches zero.
+#[derive(Debug, Clone, Event)]
+pub struct ActorDied {
+    pub entity: Entity,
+}
+
+/// Sent when a dead actor is brought back at a [`SpawnPoint`].
+#[derive(Debug, Event)]
+pub struct ActorRespawned {
+    pub entity: Entity,
+    pub pos: Vec2,
+}
+
+/// Lets an actor come back after dying instead of being despawned.
+#[derive(Debug, Copy, Clone, Component)]
+pub struct Respawns {
+    /// The number of ticks between the actor's death and its respawn.
+    pub delay: u32,
+
+    /// The number of ticks for which the actor is invulnerable after respawning.
+    pub invulnerability: u32,
+}
+
+/// Marks a location at which dead actors inside the same world respawn.
+#[derive(Debug, Clone, Component)]
+pub struct SpawnPoint;
+
+/// Attached to actors which are waiting to respawn. Their colliders are removed for as long as they
+/// are dead.
+#[derive(Debug, Clone, Component)]
+pub struct Dead {
+    /// The number of ticks left until the actor respawns.
+    pub respawn_in: u32,
+    moves: bool,
+}
+
+// === Systems === //
+
+/// Handles the death of actors whose health reached zero. Only entities inside a world are
+/// considered so that bases, whose health is tracked by the game's rules, are left alone.
+pub fn sys_check_death(
+    mut rand: RandomAccess<(&Health, &mut StatusEffects, SendsEvent<StatusEffectExpired>)>,
+    mut query: Query<
+        (
+            Entity,
+            &ObjOwner<Health>,
+            Option<&ObjOwner<StatusEffects>>,
+            Option<&Respawns>,
+            Has<ColliderMoves>,
+        ),
+        (With<InsideWorld>, Without<Dead>),
+    >,
+    mut events: EventWriter<ActorDied>,
+    mut commands: Commands,
+) {
+    rand.provide(|| {
+        for (entity, &ObjOwner(health), effects, respawns, moves) in query.iter_mut() {
+            if health.is_alive() {
+                continue;
+            }
+
+            events.send(ActorDied { entity });
+
+            let Some(respawns) = respawns else {
+                despawn_entity(entity);
+                continue;
+            };
+
+            // Lingering effects such as poison shouldn't carry over into the next life.
+            if let Some(&ObjOwner(effects)) = effects {
+                effects.clear();
+            }
+
+            commands
+                .entity(entity)
+                .remove::<(Collider, ObjOwner<TrackedCollider>, ColliderMoves)>()
+                .insert(Dead {
+                    respawn_in: respawns.delay,
+                    moves,
+                });
+        }
+    });
+}
+
+pub fn sys_tick_respawns(
+    mut rand: RandomAccess<(
+        &mut Health,
+        &mut StatusEffects,
+        SendsEvent<StatusEffectApplied>,
+        SendsEvent<HealthChanged>,
+    )>,
+    mut query: Query<(
+        Entity,
+        &InsideWorld,
+        &mut Dead,
+        &Respawns,
+        &mut Pos,
+        &mut Vel,
+        &ObjOwner<Health>,
+        Option<&ObjOwner<StatusEffects>>,
+        Option<&ActiveCheckpoint>,
+    )>,
+    spawn_points: Query<(&InsideWorld, &Pos), (With<SpawnPoint>, Without<Dead>)>,
+    mut events: EventWriter<ActorRespawned>,
+    mut commands: Commands,
+) {
+    rand.provide(|| {
+        for (
+            entity,
+            &InsideWorld(world),
+            mut dead,
+            respawns,
+            mut pos,
+            mut vel,
+            &ObjOwner(health),
+            effects,
+            checkpoint,
+        ) in query.iter_mut()
+        {
+            dead.respawn_in = dead.respawn_in.saturating_sub(1);
+            if dead.respawn_in > 0 {
+                continue;
+            }
+
+            // Actors come back at their last checkpoint with the health they had when touching it.
+            // Otherwise, they come back fully healed at a spawn point or, if their world has none,
+            // wherever they died.
+            if let Some(checkpoint) = checkpoint.filter(|checkpoint| checkpoint.world == world) {
+                pos.0 = checkpoint.pos;
+                health.restore(&checkpoint.health, HealthChangeCause::Respawn);
+            } else {
+                if let Some((_, spawn)) = spawn_points
+                    .iter()
+                    .find(|(&InsideWorld(spawn_world), _)| spawn_world == world)
+                {
+                    pos.0 = spawn.0;
+                }
+
+                health.reheal(HealthChangeCause::Respawn);
+            }
+
+            vel.0 = Vec2::ZERO;
+
+            if let Some(&ObjOwner(effects)) = effects {
+                effects.apply(StatusEffect::Invulnerable, respawns.invulnerability);
+            }
+
+            // Resetting the previous position keeps the actor from being drawn sliding across the
+            // world to its spawn point.
+            let mut entity_cmds = commands.entity(entity);
+            entity_cmds.remove::<Dead>().insert((
+                Collider(Aabb::new_centered(pos.0, Vec2::splat(40.))),
+                PrevPos(pos.0),
+            ));
+
+            if dead.moves {
+                entity_cmds.insert(ColliderMoves);
+            }
+
+            events.send(ActorRespawned { entity, pos: pos.0 });
+        }
+    });
+}
@@ -0,0 +1,185 @@
+use bevy_ecs::{
+    bundle::Bundle,
+    component::Component,
+    event::EventReader,
+    query::Without,
+    system::{Commands, Query, Res, ResMut},
+};
+use macroquad::{
+    color::{Color, WHITE},
+    math::Vec2,
+};
+
+use crate::{
+    game::{
+        draw_order::{DrawQueue, RenderLayer, RenderOrder},
+        math::{
+            aabb::Aabb,
+            draw::{draw_rectangle_aabb, stroke_rectangle_aabb},
+        },
+        rules::GameStats,
+        tile::{
+            breaking::TileBroken,
+            collider::{Collider, CollisionLayers, InsideWorld},
+            data::TileWorld,
+            material::{BaseMaterialDescriptor, MaterialRegistry},
+            render::SolidTileMaterial,
+        },
+        time::RenderAlpha,
+    },
+    util::arena::{spawn_entity, RandomAccess, RandomEntityExt},
+};
+
+use super::{
+    camera::ActiveCamera,
+    death::Dead,
+    inventory::{tile_item_color, Inventory, Item, ItemStack},
+    kinematic::{
+        ColliderEvent, ColliderListens, ColliderMoves, ColliderSize, Gravity, Pos, PrevPos, Vel,
+    },
+};
+
+// === Components === //
+
+#[derive(Bundle)]
+pub struct ItemDropBundle {
+    pub pos: Pos,
+    pub vel: Vel,
+    pub world: InsideWorld,
+    pub collider: Collider,
+    pub size: ColliderSize,
+    pub layers: CollisionLayers,
+    pub moves: ColliderMoves,
+    pub gravity: Gravity,
+    pub listens: ColliderListens,
+    pub drop: ItemDrop,
+}
+
+impl ItemDropBundle {
+    /// The upwards velocity with which drops pop out of the tile they came from.
+    pub const POP_SPEED: f32 = 4.;
+
+    /// The width and height of a drop's collider, which is also how large it's drawn.
+    pub const SIZE: f32 = 16.;
+
+    pub fn new(world: InsideWorld, pos: Vec2, stack: ItemStack) -> Self {
+        Self {
+            pos: Pos(pos),
+            vel: Vel(Vec2::new(0., -Self::POP_SPEED)),
+            world,
+            collider: Collider(Aabb::new_centered(pos, Vec2::splat(Self::SIZE))),
+            size: ColliderSize(Vec2::splat(Self::SIZE)),
+            layers: ITEM_DROP_LAYERS,
+            moves: ColliderMoves,
+            gravity: Gravity::default(),
+            listens: ColliderListens::with_mask(CollisionLayers::PLAYERS),
+            drop: ItemDrop { stack },
+        }
+    }
+}
+
+/// Drops rest on tiles but pass through everything else.
+pub const ITEM_DROP_LAYERS: CollisionLayers =
+    CollisionLayers::new(CollisionLayers::ITEMS, CollisionLayers::TILES);
+
+/// A stack of items lying in the world which is added to the inventory of the first player to touch
+/// it.
+#[derive(Debug, Component)]
+pub struct ItemDrop {
+    pub stack: ItemStack,
+}
+
+// === Systems === //
+
+pub fn sys_spawn_tile_drops(
+    mut rand: RandomAccess<(&TileWorld, &MaterialRegistry, &BaseMaterialDescriptor)>,
+    mut events: EventReader<TileBroken>,
+) {
+    rand.provide(|| {
+        for event in events.read() {
+            let world = event.world.get::<TileWorld>();
+            let registry = event.world.get::<MaterialRegistry>();
+            let descriptor = registry
+                .lookup(event.material)
+                .get::<BaseMaterialDescriptor>();
+
+            let center = world.config().tile_to_actor_rect(event.pos).center();
+
+            spawn_entity(ItemDropBundle::new(
+                InsideWorld(world),
+                center,
+                ItemStack::new(Item::Tile(descriptor.name.clone()), 1),
+            ));
+        }
+    });
+}
+
+pub fn sys_pickup_item_drops(
+    drops: Query<&ItemDrop>,
+    mut players: Query<&mut Inventory, Without<Dead>>,
+    mut events: EventReader<ColliderEvent>,
+    mut stats: ResMut<GameStats>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        if !event.entered {
+            continue;
+        }
+
+        let (Ok(drop), Ok(mut inventory)) =
+            (drops.get(event.listener), players.get_mut(event.other))
+        else {
+            continue;
+        };
+
+        // Drops which don't fit stay where they are until the player walks over them again.
+        if inventory.add(drop.stack.item.clone(), drop.stack.count) {
+            stats.bump("items_collected", drop.stack.count as u64);
+            commands.entity(event.listener).despawn();
+        }
+    }
+}
+
+pub fn sys_render_item_drops(
+    mut rand: RandomAccess<(&TileWorld, &MaterialRegistry, &SolidTileMaterial)>,
+    query: Query<(
+        &InsideWorld,
+        &Pos,
+        Option<&PrevPos>,
+        &Collider,
+        &ItemDrop,
+        Option<&RenderLayer>,
+        Option<&RenderOrder>,
+    )>,
+    mut draws: ResMut<DrawQueue>,
+    alpha: Res<RenderAlpha>,
+    camera: Res<ActiveCamera>,
+) {
+    rand.provide(|| {
+        for (&InsideWorld(world), &Pos(pos), prev, &Collider(aabb), drop, layer, order) in
+            query.iter()
+        {
+            if !camera.shows(world) {
+                continue;
+            }
+
+            let rect = aabb.translated(PrevPos::interpolate(prev, pos, *alpha) - pos);
+            let color = match &drop.stack.item {
+                Item::Tile(material) => {
+                    tile_item_color(&world.entity().get::<MaterialRegistry>(), material)
+                }
+                Item::Weapon(_) | Item::Melee(_) => Color::new(0.6, 0.6, 0.6, 1.),
+            };
+
+            let layer = layer.copied().unwrap_or(RenderLayer::Items);
+            draws.push(
+                layer,
+                RenderOrder::key_of(order, rect.center().y),
+                move || {
+                    draw_rectangle_aabb(rect, color);
+                    stroke_rectangle_aabb(rect, 2., WHITE);
+                },
+            );
+        }
+    });
+}
@@ -0,0 +1,185 @@
+use bevy_ecs::{entity::Entity, event::Event, system::Query};
+
+use crate::{
+    random_component, random_event,
+    util::arena::{send_event, Obj, ObjOwner, RandomAccess, SendsEvent},
+};
+
+use super::health::{Health, HealthChangeCause, HealthChanged};
+
+// === Definition === //
+
+random_component!(StatusEffects);
+random_event!(StatusEffectApplied, StatusEffectExpired);
+
+/// Sent whenever an effect is applied to or refreshed on an entity's [`StatusEffects`].
+#[derive(Debug, Event)]
+pub struct StatusEffectApplied {
+    pub target: Entity,
+    pub effect: StatusEffect,
+    pub duration: u32,
+}
+
+/// Sent whenever an effect runs out or is cleared.
+#[derive(Debug, Event)]
+pub struct StatusEffectExpired {
+    pub target: Entity,
+    pub effect: StatusEffect,
+}
+
+// === StatusEffect === //
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StatusEffect {
+    /// Heals the target by the given amount every tick.
+    Regen(f32),
+
+    /// Damages the target by the given amount every tick.
+    Poison(f32),
+
+    /// Prevents the target from taking any damage.
+    Invulnerable,
+}
+
+impl StatusEffect {
+    /// Determines whether two effects are of the same kind, ignoring their magnitudes. An entity
+    /// can only have one effect of each kind at a time.
+    pub fn is_same_kind(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct ActiveEffect {
+    pub effect: StatusEffect,
+
+    /// The number of ticks left before the effect expires.
+    pub remaining: u32,
+}
+
+// === StatusEffects === //
+
+/// The set of timed effects acting upon a [`Health`] component.
+#[derive(Debug)]
+pub struct StatusEffects {
+    target: Obj<Health>,
+    active: Vec<ActiveEffect>,
+}
+
+impl StatusEffects {
+    pub fn new(target: Obj<Health>) -> Self {
+        Self {
+            target,
+            active: Vec::new(),
+        }
+    }
+
+    pub fn target(&self) -> Obj<Health> {
+        self.target
+    }
+
+    pub fn active(&self) -> &[ActiveEffect] {
+        &self.active
+    }
+
+    pub fn has(&self, effect: StatusEffect) -> bool {
+        self.active
+            .iter()
+            .any(|active| active.effect.is_same_kind(&effect))
+    }
+
+    /// Applies `effect` for `duration` ticks, replacing any existing effect of the same kind.
+    pub fn apply(mut self: Obj<Self>, effect: StatusEffect, duration: u32) {
+        self.active
+            .retain(|active| !active.effect.is_same_kind(&effect));
+
+        self.active.push(ActiveEffect {
+            effect,
+            remaining: duration,
+        });
+
+        self.sync_target();
+
+        send_event(StatusEffectApplied {
+            target: self.entity(),
+            effect,
+            duration,
+        });
+    }
+
+    /// Replaces the active effects without sending any events. Gameplay should go through
+    /// [`apply`](Self::apply) and [`clear`](Self::clear) instead.
+    pub fn set_active(mut self: Obj<Self>, active: Vec<ActiveEffect>) {
+        self.active = active;
+        self.sync_target();
+    }
+
+    /// Removes every active effect, sending an expiration event for each of them.
+    pub fn clear(mut self: Obj<Self>) {
+        let target = self.entity();
+
+        for ActiveEffect { effect, .. } in std::mem::take(&mut self.active) {
+            send_event(StatusEffectExpired { target, effect });
+        }
+
+        self.sync_target();
+    }
+
+    /// Applies every active effect to the target once and expires those which have run out.
+    pub fn tick(mut self: Obj<Self>) {
+        let target = self.entity();
+        let health = self.target;
+
+        let mut expired = false;
+        self.active.retain_mut(|active| {
+            match active.effect {
+                StatusEffect::Regen(amount) => {
+                    health.change_health(amount, HealthChangeCause::Effect(active.effect));
+                }
+                StatusEffect::Poison(amount) => {
+                    health.damage(amount, HealthChangeCause::Effect(active.effect));
+                }
+                StatusEffect::Invulnerable => {}
+            }
+
+            active.remaining = active.remaining.saturating_sub(1);
+            if active.remaining > 0 {
+                return true;
+            }
+
+            send_event(StatusEffectExpired {
+                target,
+                effect: active.effect,
+            });
+            expired = true;
+            false
+        });
+
+        if expired {
+            self.sync_target();
+        }
+    }
+
+    fn sync_target(mut self: Obj<Self>) {
+        let invulnerable = self.has(StatusEffect::Invulnerable);
+        self.target.set_invulnerable(invulnerable);
+    }
+}
+
+// === Systems === //
+
+pub fn sys_tick_status_effects(
+    mut rand: RandomAccess<(
+        &mut Health,
+        &mut StatusEffects,
+        SendsEvent<StatusEffectExpired>,
+        SendsEvent<HealthChanged>,
+    )>,
+    mut query: Query<&ObjOwner<StatusEffects>>,
+) {
+    rand.provide(|| {
+        for &ObjOwner(effects) in query.iter_mut() {
+            effects.tick();
+        }
+    });
+}
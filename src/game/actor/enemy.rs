@@ -0,0 +1,308 @@
+use bevy_ecs::{
+    component::Component,
+    query::{With, Without},
+    system::{Query, Res, ResMut},
+};
+use macroquad::{
+    color::{Color, GREEN, MAROON, ORANGE, RED, YELLOW},
+    math::{IVec2, Vec2},
+    shapes::draw_circle,
+};
+use serde::Deserialize;
+
+use crate::{
+    game::{
+        debug_draw::DebugDraw,
+        draw_order::{DrawQueue, RenderLayer, RenderOrder},
+        fx::tween::Opacity,
+        tile::{
+            collider::InsideWorld,
+            data::{TileChunk, TileWorld},
+            kinematic::{KinematicApi, TileColliderDescriptor},
+            material::MaterialRegistry,
+            pathfind::{find_path, find_path_traced, PathTrace},
+        },
+        time::RenderAlpha,
+    },
+    util::arena::{Obj, RandomAccess, RandomEntityExt},
+};
+
+use super::{
+    camera::ActiveCamera,
+    death::Dead,
+    kinematic::{ColliderDebug, ColliderDebugCategory, Pos, PrevPos, Vel},
+    player::PlayerState,
+};
+
+// === Components === //
+
+/// An actor which chases the nearest living player in its world by following a path of free tiles.
+#[derive(Debug, Clone, Component, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Enemy {
+    /// The maximum change in velocity applied every tick while steering.
+    pub acceleration: f32,
+
+    /// The speed at which the enemy travels along its path.
+    pub max_speed: f32,
+
+    /// The number of ticks between two path searches.
+    pub repath_interval: u32,
+
+    /// The maximum number of tiles expanded by a single path search.
+    pub search_budget: usize,
+
+    /// The remaining waypoints, stored in reverse so that the next one is at the end.
+    #[serde(skip)]
+    path: Vec<IVec2>,
+    #[serde(skip)]
+    repath_in: u32,
+
+    /// The last path search, which is only recorded while path debugging is enabled.
+    #[serde(skip)]
+    trace: Option<Box<PathTrace>>,
+}
+
+impl Default for Enemy {
+    fn default() -> Self {
+        Self {
+            acceleration: 0.5,
+            max_speed: 6.,
+            repath_interval: 30,
+            search_budget: 2000,
+            path: Vec::new(),
+            repath_in: 0,
+            trace: None,
+        }
+    }
+}
+
+impl Enemy {
+    /// The tiles the enemy still has to visit, starting with the next one.
+    pub fn path(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.path.iter().rev().copied()
+    }
+
+    /// The last path search, if [path debugging](ColliderDebug::wants_path_trace) was enabled
+    /// at the time.
+    pub fn trace(&self) -> Option<&PathTrace> {
+        self.trace.as_deref()
+    }
+}
+
+// === Systems === //
+
+fn nearest_player(
+    players: &Query<(&InsideWorld, &Pos), (With<PlayerState>, Without<Dead>)>,
+    world: Obj<TileWorld>,
+    pos: Vec2,
+) -> Option<Vec2> {
+    players
+        .iter()
+        .filter(|(&InsideWorld(player_world), _)| player_world == world)
+        .map(|(_, player)| player.0)
+        .min_by(|a, b| a.distance_squared(pos).total_cmp(&b.distance_squared(pos)))
+}
+
+pub fn sys_update_enemy_paths(
+    mut rand: RandomAccess<(
+        &TileWorld,
+        &TileChunk,
+        &mut KinematicApi,
+        &MaterialRegistry,
+        &TileColliderDescriptor,
+    )>,
+    mut enemies: Query<(&InsideWorld, &Pos, &mut Enemy)>,
+    players: Query<(&InsideWorld, &Pos), (With<PlayerState>, Without<Dead>)>,
+    debug: Res<ColliderDebug>,
+) {
+    rand.provide(|| {
+        for (&InsideWorld(world), pos, mut enemy) in enemies.iter_mut() {
+            if !debug.wants_path_trace() {
+                enemy.trace = None;
+            }
+
+            enemy.repath_in = enemy.repath_in.saturating_sub(1);
+            if enemy.repath_in > 0 {
+                continue;
+            }
+            enemy.repath_in = enemy.repath_interval;
+
+            let Some(target) = nearest_player(&players, world, pos.0) else {
+                enemy.path.clear();
+                continue;
+            };
+
+            let config = world.config();
+            let mut kinematics = world.entity().get::<KinematicApi>();
+
+            let start = config.actor_to_tile(pos.0);
+            let goal = config.actor_to_tile(target);
+            let budget = enemy.search_budget;
+
+            let path = if debug.wants_path_trace() {
+                let trace = enemy.trace.get_or_insert_with(Default::default);
+                find_path_traced(&mut kinematics, start, goal, budget, trace)
+            } else {
+                find_path(&mut kinematics, start, goal, budget)
+            };
+
+            enemy.path = path.unwrap_or_default();
+            enemy.path.reverse();
+        }
+    });
+}
+
+pub fn sys_steer_enemies(
+    mut rand: RandomAccess<&TileWorld>,
+    mut enemies: Query<(&InsideWorld, &Pos, &mut Vel, &mut Enemy)>,
+    players: Query<(&InsideWorld, &Pos), (With<PlayerState>, Without<Dead>)>,
+) {
+    rand.provide(|| {
+        for (&InsideWorld(world), pos, mut vel, mut enemy) in enemies.iter_mut() {
+            let config = world.config();
+
+            // Skip past the waypoints we've already reached.
+            while let Some(&next) = enemy.path.last() {
+                let center = config.tile_to_actor_rect(next).center();
+                if center.distance(pos.0) > config.size * 0.5 {
+                    break;
+                }
+                enemy.path.pop();
+            }
+
+            // Head towards the next waypoint or straight at the player if we don't have a path.
+            let target = match enemy.path.last() {
+                Some(&next) => Some(config.tile_to_actor_rect(next).center()),
+                None => nearest_player(&players, world, pos.0),
+            };
+
+            let desired = target.map_or(Vec2::ZERO, |target| {
+                (target - pos.0).normalize_or_zero() * enemy.max_speed
+            });
+
+            let steering = (desired - vel.0).clamp_length_max(enemy.acceleration);
+            vel.0 += steering;
+        }
+    });
+}
+
+pub fn sys_render_enemies(
+    query: Query<
+        (
+            &InsideWorld,
+            &Pos,
+            Option<&PrevPos>,
+            Option<&RenderLayer>,
+            Option<&RenderOrder>,
+            Option<&Opacity>,
+        ),
+        With<Enemy>,
+    >,
+    mut draws: ResMut<DrawQueue>,
+    alpha: Res<RenderAlpha>,
+    camera: Res<ActiveCamera>,
+) {
+    for (&InsideWorld(world), &Pos(pos), prev, layer, order, opacity) in query.iter() {
+        if !camera.shows(world) {
+            continue;
+        }
+
+        let pos = PrevPos::interpolate(prev, pos, *alpha);
+        let opacity = opacity.copied().unwrap_or_default().0;
+        let layer = layer.copied().unwrap_or(RenderLayer::Actors);
+        draws.push(layer, RenderOrder::key_of(order, pos.y), move || {
+            draw_circle(
+                pos.x,
+                pos.y,
+                20.,
+                Color {
+                    a: opacity,
+                    ..MAROON
+                },
+            );
+            draw_circle(
+                pos.x,
+                pos.y,
+                12.,
+                Color {
+                    a: opacity,
+                    ..ORANGE
+                },
+            );
+        });
+    }
+}
+
+/// Draws the last path search of every enemy: the tiles it explored, the tiles it reached but never
+/// explored, and the path it chose. Run once per frame before the viewports are rendered.
+pub fn sys_debug_draw_enemy_paths(
+    mut rand: RandomAccess<&TileWorld>,
+    enemies: Query<(&InsideWorld, &Enemy)>,
+    debug: Res<ColliderDebug>,
+    mut draw: ResMut<DebugDraw>,
+) {
+    if !debug.wants_path_trace() {
+        return;
+    }
+
+    let show_sets = debug.is_enabled(ColliderDebugCategory::PathSearches);
+    let show_costs = debug.is_enabled(ColliderDebugCategory::PathCosts);
+    let translucent = |color: Color| Color { a: 0.4, ..color };
+
+    rand.provide(|| {
+        for (&InsideWorld(world), enemy) in enemies.iter() {
+            let Some(trace) = enemy.trace() else {
+                continue;
+            };
+
+            let config = world.config();
+            let rect = |tile: IVec2| config.tile_to_actor_rect(tile);
+            let inset = Vec2::splat(config.size * 0.1);
+
+            if show_sets {
+                let closed = translucent(ColliderDebugCategory::PathSearches.color());
+                for &tile in &trace.closed {
+                    draw.aabb(rect(tile).shrink(inset), closed);
+                }
+
+                for tile in trace.open() {
+                    draw.aabb(rect(tile).shrink(inset), translucent(GREEN));
+                }
+
+                match &trace.path {
+                    Some(path) => {
+                        let mut prev = rect(trace.start).center();
+                        for &tile in path {
+                            let next = rect(tile).center();
+                            draw.line(prev, next, YELLOW).thickness(4.);
+                            prev = next;
+                        }
+                    }
+                    None => {
+                        // Searches which gave up are usually the interesting ones.
+                        draw.line(rect(trace.start).center(), rect(trace.goal).center(), RED);
+                    }
+                }
+
+                draw.point(rect(trace.start).center(), YELLOW);
+                draw.point(rect(trace.goal).center(), RED);
+            }
+
+            if show_costs {
+                let color = ColliderDebugCategory::PathCosts.color();
+                let font_size = config.size * 0.3;
+
+                for (&tile, &cost) in &trace.costs {
+                    let estimate = trace.estimate(tile);
+                    let rect = rect(tile);
+                    let pos = rect.min + Vec2::new(inset.x, inset.y + font_size);
+                    draw.text(pos, format!("{cost}"), color)
+                        .font_size(font_size);
+                    draw.text(pos + Vec2::Y * font_size, format!("+{estimate}"), color)
+                        .font_size(font_size);
+                }
+            }
+        }
+    });
+}
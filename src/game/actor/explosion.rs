@@ -0,0 +1,199 @@
+use bevy_ecs::{
+    entity::Entity,
+    event::{Event, EventReader, EventWriter},
+    system::{Local, Query},
+};
+use cbit::cbit;
+use macroquad::math::{IVec2, Vec2};
+
+use crate::{
+    game::{
+        fx::particles::ParticleEmitter,
+        math::aabb::Aabb,
+        tile::{
+            breaking::TileBroken,
+            collider::{InsideWorld, WorldColliders},
+            data::{TileChanged, TileChunk, TileWorld, WorldCreatedChunk},
+            kinematic::{AnyCollision, KinematicApi, TileColliderDescriptor},
+            material::{BaseMaterialDescriptor, MaterialId, MaterialRegistry},
+            tile_entity::{TileEntityCreated, TileEntityDescriptor},
+        },
+    },
+    random_event,
+    util::arena::{send_event, spawn_entity, Obj, RandomAccess, RandomEntityExt, SendsEvent},
+};
+
+use super::{
+    camera::VirtualCamera,
+    damage::{DamageEvent, DamageKind},
+    kinematic::{Pos, Vel},
+};
+
+// === Explosion === //
+
+random_event!(Explosion);
+
+/// Blows up everything within `radius` of `center`. Damage, knockback, and the hardness of the
+/// tiles which can be destroyed all fall off linearly from the center to the edge of the blast.
+#[derive(Debug, Clone, Event)]
+pub struct Explosion {
+    pub world: Obj<TileWorld>,
+
+    /// The entity which caused the explosion. Destroyed tiles are reported as broken by it.
+    pub source: Entity,
+    pub center: Vec2,
+    pub radius: f32,
+
+    /// The damage dealt to actors at the center of the blast.
+    pub damage: f32,
+
+    /// The speed at which actors at the center of the blast are pushed away from it.
+    pub knockback: f32,
+
+    /// The hardest tile which can be destroyed at the center of the blast. Explosions with no power
+    /// leave the terrain intact.
+    pub power: f32,
+
+    /// The layers of the actors which are damaged and pushed away.
+    pub mask: u32,
+}
+
+impl Explosion {
+    /// The camera trauma added by an explosion right at the center of the camera.
+    pub const MAX_TRAUMA: f32 = 0.6;
+
+    /// The distance at which explosions stop shaking the camera, in multiples of their radius.
+    pub const SHAKE_RANGE: f32 = 4.;
+
+    /// The fraction of the explosion's full effect felt `distance` away from its center.
+    pub fn falloff(&self, distance: f32) -> f32 {
+        if self.radius <= 0. {
+            return 0.;
+        }
+
+        (1. - distance / self.radius).max(0.)
+    }
+}
+
+// === Systems === //
+
+pub fn sys_handle_explosions(
+    mut events: EventReader<Explosion>,
+    mut rand: RandomAccess<(
+        (
+            &MaterialRegistry,
+            &BaseMaterialDescriptor,
+            &TileColliderDescriptor,
+            &TileEntityDescriptor,
+        ),
+        &mut KinematicApi,
+        &mut TileChunk,
+        &mut TileWorld,
+        &mut VirtualCamera,
+        &WorldColliders,
+        SendsEvent<TileBroken>,
+        SendsEvent<TileChanged>,
+        SendsEvent<TileEntityCreated>,
+        SendsEvent<WorldCreatedChunk>,
+    )>,
+    mut actors: Query<Option<&mut Vel>>,
+    mut damage: EventWriter<DamageEvent>,
+    mut victims: Local<Vec<(Entity, Aabb)>>,
+    mut destroyed: Local<Vec<(IVec2, MaterialId)>>,
+) {
+    rand.provide(|| {
+        for explosion in events.read() {
+            let &Explosion {
+                world,
+                source,
+                center,
+                radius,
+                ..
+            } = explosion;
+
+            if !world.is_alive() {
+                continue;
+            }
+
+            // Destroy the terrain
+            let registry = world.entity().get::<MaterialRegistry>();
+            let config = world.config();
+
+            destroyed.extend(world.query_materials_in_circle(center, radius).filter(
+                |&(pos, material)| {
+                    if material == MaterialId::AIR {
+                        return false;
+                    }
+
+                    let distance = config.tile_to_actor_rect(pos).center().distance(center);
+                    let hardness = registry
+                        .lookup(material)
+                        .get::<BaseMaterialDescriptor>()
+                        .hardness;
+
+                    hardness <= explosion.power * explosion.falloff(distance)
+                },
+            ));
+
+            for (pos, material) in destroyed.drain(..) {
+                world.set_tile(pos, MaterialId::AIR);
+                send_event(TileBroken {
+                    world: world.entity(),
+                    breaker: source,
+                    pos,
+                    material,
+                });
+            }
+
+            // Damage and push away actors
+            let mut kinematics = world.entity().get::<KinematicApi>();
+            let area = Aabb::new_centered(center, Vec2::splat(radius * 2.));
+
+            cbit! {
+                for collider in kinematics.iter_colliders_in(area, explosion.mask) {
+                    if let AnyCollision::Collider(victim, aabb) = collider {
+                        victims.push((victim, aabb));
+                    }
+                }
+            }
+
+            for (victim, aabb) in victims.drain(..) {
+                let offset = aabb.center() - center;
+                let falloff = explosion.falloff(offset.length());
+                if falloff <= 0. {
+                    continue;
+                }
+
+                let Ok(vel) = actors.get_mut(victim) else {
+                    continue;
+                };
+
+                damage.send(DamageEvent {
+                    source,
+                    target: victim,
+                    kind: DamageKind::Explosion,
+                    amount: explosion.damage * falloff,
+                });
+
+                if let Some(mut vel) = vel {
+                    // Actors caught right at the center are thrown upwards.
+                    let dir = offset.try_normalize().unwrap_or(Vec2::NEG_Y);
+                    vel.0 += dir * explosion.knockback * falloff;
+                }
+            }
+
+            // Show the blast
+            spawn_entity((
+                InsideWorld(world),
+                Pos(center),
+                ParticleEmitter::explosion(radius),
+            ));
+
+            if let Some(mut camera) = world.entity().try_get::<VirtualCamera>() {
+                let distance = camera.transform().translation.distance(center);
+                let falloff = (1. - distance / (radius * Explosion::SHAKE_RANGE)).max(0.);
+                camera.add_trauma(Explosion::MAX_TRAUMA * falloff);
+            }
+        }
+    });
+}
@@ -1,13 +1,61 @@
-use crate::random_component;
+use bevy_ecs::{entity::Entity, event::Event};
+
+use crate::{
+    random_component, random_event,
+    util::arena::{send_event, Obj},
+};
+
+use super::effects::StatusEffect;
 
 random_component!(Health);
+random_event!(HealthChanged);
+
+// === HealthChanged === //
+
+/// Sent whenever gameplay changes the health of a [`Health`] component. Direct edits of the state,
+/// such as those made through [`Health::set_health`] when restoring a snapshot, aren't reported.
+#[derive(Debug, Event)]
+pub struct HealthChanged {
+    pub target: Entity,
+    pub old: f32,
+    pub new: f32,
+    pub cause: HealthChangeCause,
+}
+
+impl HealthChanged {
+    /// The amount of health gained, which is negative for damage.
+    pub fn delta(&self) -> f32 {
+        self.new - self.old
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HealthChangeCause {
+    Bullet,
+    Melee,
+    Explosion,
+
+    /// Touching something which deals [contact damage](super::player::ContactDamage).
+    Contact,
+
+    /// A [hazard](super::damage::Hazard) or the void outside of the world's bounds.
+    Environment,
+
+    /// A player died in the world which this health belongs to.
+    DeathPenalty,
+
+    Effect(StatusEffect),
+    Script,
+    Respawn,
+}
 
 // === Health === //
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Health {
     health: f32,
     max: f32,
+    invulnerable: bool,
 }
 
 impl Health {
@@ -15,7 +63,11 @@ impl Health {
         let max = max.max(0.);
         let health = health.clamp(0., max);
 
-        Self { health, max }
+        Self {
+            health,
+            max,
+            invulnerable: false,
+        }
     }
 
     pub fn new_full(max: f32) -> Self {
@@ -30,6 +82,8 @@ impl Health {
         self.max
     }
 
+    /// Sets the health without sending a [`HealthChanged`] event. Gameplay should go through
+    /// [`change_health`](Self::change_health) and friends instead.
     pub fn set_health(&mut self, health: f32) {
         self.health = health.clamp(0., self.max);
     }
@@ -39,16 +93,48 @@ impl Health {
         self.health = self.health.min(self.max);
     }
 
-    pub fn change_health(&mut self, amount: f32) {
-        self.set_health(self.health() + amount);
-    }
-
     pub fn change_max(&mut self, by: f32) {
         self.set_max(self.max() + by);
     }
 
-    pub fn reheal(&mut self) {
-        self.health = self.max;
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable
+    }
+
+    pub fn set_invulnerable(&mut self, invulnerable: bool) {
+        self.invulnerable = invulnerable;
+    }
+
+    /// Changes the health by `amount`, sending a [`HealthChanged`] event if that changed anything.
+    pub fn change_health(mut self: Obj<Self>, amount: f32, cause: HealthChangeCause) {
+        let old = self.health;
+        self.set_health(old + amount);
+
+        if self.health != old {
+            send_event(HealthChanged {
+                target: self.entity(),
+                old,
+                new: self.health,
+                cause,
+            });
+        }
+    }
+
+    /// Reduces the health by `amount` unless the health is currently invulnerable.
+    pub fn damage(self: Obj<Self>, amount: f32, cause: HealthChangeCause) {
+        if !self.invulnerable {
+            self.change_health(-amount, cause);
+        }
+    }
+
+    pub fn reheal(self: Obj<Self>, cause: HealthChangeCause) {
+        self.change_health(self.max - self.health, cause);
+    }
+
+    /// Restores the maximum and current health of `snapshot`, only reporting the latter.
+    pub fn restore(mut self: Obj<Self>, snapshot: &Health, cause: HealthChangeCause) {
+        self.set_max(snapshot.max);
+        self.change_health(snapshot.health - self.health, cause);
     }
 
     pub fn is_alive(&self) -> bool {
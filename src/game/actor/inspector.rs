@@ -0,0 +1,361 @@
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    query::Without,
+    system::{Query, Res, ResMut, Resource},
+    world::{Mut, World},
+};
+use macroquad::{
+    color::{Color, GRAY, WHITE, YELLOW},
+    input::{
+        is_key_down, is_key_pressed, is_mouse_button_pressed, mouse_position, KeyCode, MouseButton,
+    },
+    math::Vec2,
+    shapes::draw_rectangle,
+    text::draw_text,
+};
+
+use crate::{
+    game::{
+        input::ControlsMenu,
+        math::draw::stroke_rectangle_aabb,
+        tile::collider::{Collider, InsideWorld},
+    },
+    util::{
+        arena::{Obj, RandomAccess, RandomArena, RandomArenaRegistry, RandomComponent},
+        diagnostics::short_type_name,
+    },
+};
+
+use super::{
+    camera::{ActiveCamera, VirtualCamera},
+    controller::CharacterController,
+    health::Health,
+    kinematic::{Gravity, Pos, Vel},
+    projectile::Pooled,
+};
+
+// === InspectFields === //
+
+/// Exposes a component's simple numeric fields to the inspector for live editing.
+pub trait InspectFields {
+    fn inspect_fields(&mut self, f: &mut dyn FnMut(&'static str, &mut f32));
+}
+
+impl InspectFields for Pos {
+    fn inspect_fields(&mut self, f: &mut dyn FnMut(&'static str, &mut f32)) {
+        f("x", &mut self.0.x);
+        f("y", &mut self.0.y);
+    }
+}
+
+impl InspectFields for Vel {
+    fn inspect_fields(&mut self, f: &mut dyn FnMut(&'static str, &mut f32)) {
+        f("x", &mut self.0.x);
+        f("y", &mut self.0.y);
+    }
+}
+
+impl InspectFields for Gravity {
+    fn inspect_fields(&mut self, f: &mut dyn FnMut(&'static str, &mut f32)) {
+        f("acceleration", &mut self.acceleration);
+        f("terminal_velocity", &mut self.terminal_velocity);
+    }
+}
+
+impl InspectFields for CharacterController {
+    fn inspect_fields(&mut self, f: &mut dyn FnMut(&'static str, &mut f32)) {
+        f("max_run_speed", &mut self.max_run_speed);
+        f("run_acceleration", &mut self.run_acceleration);
+        f("air_control", &mut self.air_control);
+        f("jump_impulse", &mut self.jump_impulse);
+        f("jump_cut", &mut self.jump_cut);
+    }
+}
+
+impl InspectFields for Health {
+    fn inspect_fields(&mut self, f: &mut dyn FnMut(&'static str, &mut f32)) {
+        // Go through the setters so that the health stays within its bounds.
+        let mut max = self.max();
+        f("max", &mut max);
+        self.set_max(max);
+
+        let mut health = self.health();
+        f("health", &mut health);
+        self.set_health(health);
+    }
+}
+
+// === InspectorRegistry === //
+
+type FieldEditor = fn(&mut World, Entity, &mut dyn FnMut(&'static str, &mut f32));
+
+/// The set of component types whose fields can be edited through the inspector.
+#[derive(Debug, Resource)]
+pub struct InspectorRegistry {
+    editors: Vec<(&'static str, FieldEditor)>,
+}
+
+impl Default for InspectorRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            editors: Vec::new(),
+        };
+        registry.register_component::<Pos>();
+        registry.register_component::<Vel>();
+        registry.register_component::<Gravity>();
+        registry.register_component::<CharacterController>();
+        registry.register_random::<Health>();
+        registry
+    }
+}
+
+impl InspectorRegistry {
+    pub fn register_component<T: Component + InspectFields>(&mut self) {
+        self.editors
+            .push((std::any::type_name::<T>(), |world, entity, f| {
+                if let Some(mut component) = world.get_mut::<T>(entity) {
+                    component.inspect_fields(f);
+                }
+            }));
+    }
+
+    pub fn register_random<T: RandomComponent + InspectFields>(&mut self) {
+        self.editors
+            .push((std::any::type_name::<T>(), |world, entity, f| {
+                let mut arena = world.resource_mut::<RandomArena<T>>();
+                let Some(&obj) = arena.map.get(&entity) else {
+                    return;
+                };
+                arena.arena[Obj::index(obj)].1.inspect_fields(f);
+            }));
+    }
+
+    /// Visits every editable field of `entity`, passing the owning component's name alongside each
+    /// field.
+    pub fn visit(
+        &self,
+        world: &mut World,
+        entity: Entity,
+        mut f: impl FnMut(&'static str, &'static str, &mut f32),
+    ) {
+        for &(component, editor) in &self.editors {
+            editor(world, entity, &mut |field, value| {
+                f(component, field, value)
+            });
+        }
+    }
+}
+
+// === Inspector === //
+
+#[derive(Debug, Default, Resource)]
+pub struct Inspector {
+    pub enabled: bool,
+    pub selected: Option<Entity>,
+
+    selected_field: usize,
+    pending_edit: f32,
+
+    components: Vec<String>,
+    random_components: Vec<(String, String)>,
+    fields: Vec<(String, f32)>,
+}
+
+impl Inspector {
+    /// The step by which the selected field is changed. Holding shift makes it ten times larger.
+    pub const EDIT_STEP: f32 = 1.;
+}
+
+// === Systems === //
+
+pub fn sys_handle_inspector_input(
+    mut rand: RandomAccess<&VirtualCamera>,
+    mut inspector: ResMut<Inspector>,
+    camera: Res<ActiveCamera>,
+    controls_menu: Res<ControlsMenu>,
+    colliders: Query<(Entity, &Collider, &InsideWorld), Without<Pooled>>,
+) {
+    if is_key_pressed(KeyCode::F1) {
+        inspector.enabled = !inspector.enabled;
+    }
+
+    if !inspector.enabled {
+        return;
+    }
+
+    // Pick the smallest collider under the cursor so that actors standing in front of larger ones
+    // can still be selected.
+    if is_mouse_button_pressed(MouseButton::Left) && !controls_menu.has_focus() {
+        rand.provide(|| {
+            let Some(active) = camera.camera else {
+                return;
+            };
+
+            let cursor = active.project(Vec2::from(mouse_position()));
+
+            inspector.selected = colliders
+                .iter()
+                .filter(|&(_, collider, &InsideWorld(world))| {
+                    camera.shows(world) && collider.0.contains(cursor)
+                })
+                .min_by(|(_, a, _), (_, b, _)| {
+                    let a = a.0.w() * a.0.h();
+                    let b = b.0.w() * b.0.h();
+                    a.total_cmp(&b)
+                })
+                .map(|(entity, _, _)| entity);
+            inspector.selected_field = 0;
+        });
+    }
+
+    // Handle field editing
+    let field_count = inspector.fields.len();
+    if field_count == 0 {
+        return;
+    }
+
+    if is_key_pressed(KeyCode::LeftBracket) {
+        inspector.selected_field = (inspector.selected_field + field_count - 1) % field_count;
+    }
+
+    if is_key_pressed(KeyCode::RightBracket) {
+        inspector.selected_field = (inspector.selected_field + 1) % field_count;
+    }
+
+    let step = if is_key_down(KeyCode::LeftShift) {
+        Inspector::EDIT_STEP * 10.
+    } else {
+        Inspector::EDIT_STEP
+    };
+
+    if is_key_pressed(KeyCode::Minus) {
+        inspector.pending_edit -= step;
+    }
+
+    if is_key_pressed(KeyCode::Equal) {
+        inspector.pending_edit += step;
+    }
+}
+
+pub fn sys_refresh_inspector(world: &mut World) {
+    world.resource_scope(|world, mut inspector: Mut<Inspector>| {
+        let selected = inspector
+            .selected
+            .filter(|_| inspector.enabled)
+            .filter(|&entity| world.get_entity(entity).is_some());
+
+        inspector.components.clear();
+        inspector.random_components.clear();
+        inspector.fields.clear();
+
+        let Some(entity) = selected else {
+            inspector.selected = None;
+            inspector.pending_edit = 0.;
+            return;
+        };
+
+        // Collect the entity's components
+        inspector.components = world
+            .inspect_entity(entity)
+            .into_iter()
+            .map(|info| short_type_name(info.name()))
+            .collect();
+        inspector.components.sort();
+
+        inspector.random_components = world
+            .resource::<RandomArenaRegistry>()
+            .describe_entity(world, entity)
+            .into_iter()
+            .map(|(name, desc)| (short_type_name(name), desc))
+            .collect();
+
+        // Apply pending edits and collect editable fields
+        let edit = std::mem::take(&mut inspector.pending_edit);
+        let selected_field = inspector.selected_field;
+        let mut fields = Vec::new();
+
+        world.resource_scope(|world, registry: Mut<InspectorRegistry>| {
+            registry.visit(world, entity, |component, field, value| {
+                if fields.len() == selected_field {
+                    *value += edit;
+                }
+
+                fields.push((format!("{}.{field}", short_type_name(component)), *value));
+            });
+        });
+
+        inspector.selected_field = selected_field.min(fields.len().saturating_sub(1));
+        inspector.fields = fields;
+    });
+}
+
+pub fn sys_render_inspector(
+    inspector: Res<Inspector>,
+    camera: Res<ActiveCamera>,
+    colliders: Query<&Collider>,
+) {
+    if !inspector.enabled {
+        return;
+    }
+
+    const LEFT: f32 = 15.;
+    const TOP: f32 = 80.;
+    const WIDTH: f32 = 420.;
+    const ROW_HEIGHT: f32 = 18.;
+
+    // Highlight the selected entity
+    if let Some(&Collider(aabb)) = inspector.selected.and_then(|e| colliders.get(e).ok()) {
+        let _guard = camera.apply();
+        stroke_rectangle_aabb(aabb, 3., YELLOW);
+    }
+
+    // Build the panel's contents
+    let mut lines = Vec::<(String, Color)>::new();
+
+    match inspector.selected {
+        Some(entity) => {
+            lines.push((format!("Inspecting {entity:?}"), YELLOW));
+
+            lines.push(("Components".to_string(), GRAY));
+            for name in &inspector.components {
+                lines.push((format!("  {name}"), WHITE));
+            }
+
+            lines.push(("Random components".to_string(), GRAY));
+            for (name, desc) in &inspector.random_components {
+                lines.push((format!("  {name}: {desc}"), WHITE));
+            }
+
+            if !inspector.fields.is_empty() {
+                lines.push(("Fields ([ ] select, - = edit)".to_string(), GRAY));
+            }
+            for (i, (name, value)) in inspector.fields.iter().enumerate() {
+                let selected = i == inspector.selected_field;
+                lines.push((
+                    format!("{} {name} = {value:.3}", if selected { ">" } else { " " }),
+                    if selected { YELLOW } else { WHITE },
+                ));
+            }
+        }
+        None => lines.push(("Click an entity to inspect it".to_string(), WHITE)),
+    }
+
+    draw_rectangle(
+        LEFT,
+        TOP,
+        WIDTH,
+        ROW_HEIGHT * lines.len() as f32 + 10.,
+        Color::new(0., 0., 0., 0.7),
+    );
+
+    for (i, (line, color)) in lines.iter().enumerate() {
+        draw_text(
+            line,
+            LEFT + 5.,
+            TOP + ROW_HEIGHT * (i + 1) as f32,
+            18.,
+            *color,
+        );
+    }
+}
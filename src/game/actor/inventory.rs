@@ -0,0 +1,454 @@
+use bevy_ecs::{
+    component::Component,
+    query::{With, Without},
+    system::{Commands, In, Query},
+};
+use macroquad::{
+    color::{Color, DARKGRAY, GRAY, WHITE, YELLOW},
+    math::Vec2,
+    shapes::{draw_rectangle, draw_rectangle_lines},
+    text::{draw_text, measure_text},
+};
+
+use crate::{
+    game::{
+        math::aabb::Aabb,
+        math::draw::{draw_rectangle_aabb, stroke_rectangle_aabb},
+        tile::{
+            collider::{Collider, CollisionLayers, InsideWorld},
+            data::TileWorld,
+            material::MaterialRegistry,
+            render::SolidTileMaterial,
+        },
+    },
+    util::arena::{RandomAccess, RandomEntityExt},
+};
+
+use super::{
+    death::Dead,
+    kinematic::{ColliderListens, ColliderMoves, ContinuousCollision, Pos, Vel},
+    melee::MeleeWeapon,
+    player::{PlayerState, RemoteInput},
+    projectile::{BulletBaseBundle, BulletDamage, BulletImpact, BulletPool, BULLET_LAYERS},
+};
+
+// === Items === //
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    /// Places tiles of the material with the given name.
+    Tile(String),
+
+    /// Fires projectiles towards the cursor.
+    Weapon(Weapon),
+
+    /// Swings at actors in the direction of the cursor.
+    Melee(MeleeWeapon),
+}
+
+impl Item {
+    pub fn name(&self) -> &str {
+        match self {
+            Item::Tile(material) => material.strip_prefix("game:").unwrap_or(material),
+            Item::Weapon(weapon) => &weapon.name,
+            Item::Melee(weapon) => &weapon.name,
+        }
+    }
+
+    /// Whether several of this item can share a single inventory slot. Weapons are never used up
+    /// so there's no point in holding more than one.
+    pub fn is_stackable(&self) -> bool {
+        matches!(self, Item::Tile(_))
+    }
+}
+
+/// An item along with the number held in a single slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemStack {
+    pub item: Item,
+    pub count: u32,
+}
+
+impl ItemStack {
+    pub fn new(item: Item, count: u32) -> Self {
+        Self { item, count }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weapon {
+    pub name: String,
+    pub damage: BulletDamage,
+
+    /// The speed at which fired projectiles leave the player.
+    pub speed: f32,
+
+    /// The number of ticks between two shots.
+    pub cooldown: u32,
+}
+
+impl Weapon {
+    /// Fires a single projectile from `from` towards `to` which only hits actors other than
+    /// players. Must be called within a [`RandomAccess`] providing `&mut TangibleMarker`.
+    pub fn fire(
+        &self,
+        pool: &mut BulletPool,
+        commands: &mut Commands,
+        world: InsideWorld,
+        from: Vec2,
+        to: Vec2,
+    ) {
+        let dir = (to - from).try_normalize().unwrap_or(Vec2::X);
+
+        pool.spawn(
+            commands,
+            BulletBaseBundle {
+                pos: Pos(from),
+                vel: Vel(dir * self.speed),
+                world,
+                collider: Collider(Aabb::ZERO),
+                layers: BULLET_LAYERS,
+                moves: ColliderMoves,
+                continuous: ContinuousCollision,
+                listens: ColliderListens::with_mask(CollisionLayers::ACTORS),
+                damage: self.damage,
+            },
+        );
+    }
+}
+
+// === Inventory === //
+
+/// The player's hotbar of items and the currently selected slot.
+#[derive(Debug, Clone, Component)]
+pub struct Inventory {
+    slots: [Option<ItemStack>; Self::SLOTS],
+    selected: usize,
+    cooldown: u32,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        let mut inventory = Self {
+            slots: Default::default(),
+            selected: 0,
+            cooldown: 0,
+        };
+
+        for (i, (material, count)) in [
+            ("game:stone", 64),
+            ("game:brick", 32),
+            ("game:ice", 16),
+            ("game:rubber", 16),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            inventory.set_slot(
+                i,
+                Some(ItemStack::new(Item::Tile(material.to_string()), count)),
+            );
+        }
+
+        inventory.set_slot(
+            5,
+            Some(ItemStack::new(
+                Item::Melee(MeleeWeapon {
+                    name: "Sword".to_string(),
+                    damage: 6.,
+                    knockback: 12.,
+                    reach: 90.,
+                    arc: 120f32.to_radians(),
+                    active_ticks: 8,
+                    cooldown: 20,
+                }),
+                1,
+            )),
+        );
+        inventory.set_slot(
+            6,
+            Some(ItemStack::new(
+                Item::Weapon(Weapon {
+                    name: "Pistol".to_string(),
+                    damage: BulletDamage {
+                        amount: 5.,
+                        impact: BulletImpact::Despawn,
+                        ricochets: 1,
+                    },
+                    speed: 25.,
+                    cooldown: 10,
+                }),
+                1,
+            )),
+        );
+        inventory.set_slot(
+            7,
+            Some(ItemStack::new(
+                Item::Weapon(Weapon {
+                    name: "Railgun".to_string(),
+                    damage: BulletDamage {
+                        amount: 8.,
+                        impact: BulletImpact::Pierce(3),
+                        ricochets: 0,
+                    },
+                    speed: 40.,
+                    cooldown: 40,
+                }),
+                1,
+            )),
+        );
+        inventory.set_slot(
+            8,
+            Some(ItemStack::new(
+                Item::Weapon(Weapon {
+                    name: "Grenade".to_string(),
+                    damage: BulletDamage {
+                        amount: 10.,
+                        impact: BulletImpact::Explode {
+                            radius: 150.,
+                            power: 30.,
+                        },
+                        ricochets: 3,
+                    },
+                    speed: 15.,
+                    cooldown: 60,
+                }),
+                1,
+            )),
+        );
+
+        inventory
+    }
+}
+
+impl Inventory {
+    pub const SLOTS: usize = 9;
+
+    pub fn slot(&self, index: usize) -> Option<&ItemStack> {
+        self.slots[index].as_ref()
+    }
+
+    pub fn set_slot(&mut self, index: usize, stack: Option<ItemStack>) {
+        self.slots[index] = stack;
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected_item(&self) -> Option<&Item> {
+        self.slot(self.selected).map(|stack| &stack.item)
+    }
+
+    pub fn select(&mut self, index: usize) {
+        self.selected = index.min(Self::SLOTS - 1);
+    }
+
+    /// Moves the selection by `by` slots, wrapping around either end of the hotbar.
+    pub fn scroll(&mut self, by: i32) {
+        self.selected = (self.selected as i64 + by as i64).rem_euclid(Self::SLOTS as i64) as usize;
+    }
+
+    /// Adds `count` of `item` to the slot already holding it or, failing that, to the first empty
+    /// slot. Returns `false` without modifying the inventory if there was no room.
+    pub fn add(&mut self, item: Item, count: u32) -> bool {
+        if item.is_stackable() {
+            let existing = self
+                .slots
+                .iter_mut()
+                .flatten()
+                .find(|stack| stack.item == item);
+            if let Some(stack) = existing {
+                stack.count += count;
+                return true;
+            }
+        }
+
+        let Some(empty) = self.slots.iter_mut().find(|slot| slot.is_none()) else {
+            return false;
+        };
+
+        *empty = Some(ItemStack::new(item, count));
+        true
+    }
+
+    /// Uses up one of the selected item, emptying its slot once none are left. Returns `false` if
+    /// there was nothing to use up.
+    pub fn consume_selected(&mut self) -> bool {
+        let slot = &mut self.slots[self.selected];
+        let Some(stack) = slot else {
+            return false;
+        };
+
+        stack.count -= 1;
+        if stack.count == 0 {
+            *slot = None;
+        }
+
+        true
+    }
+
+    /// The number of ticks left until the selected weapon can be used again.
+    pub fn cooldown(&self) -> u32 {
+        self.cooldown
+    }
+
+    /// Advances the weapon cooldown by a tick.
+    pub fn tick(&mut self) {
+        self.cooldown = self.cooldown.saturating_sub(1);
+    }
+
+    /// Starts the cooldown of the selected weapon and returns it if it's ready to fire.
+    pub fn try_fire(&mut self) -> Option<Weapon> {
+        if self.cooldown > 0 {
+            return None;
+        }
+
+        let Some(Item::Weapon(weapon)) = self.selected_item() else {
+            return None;
+        };
+
+        let weapon = weapon.clone();
+        self.cooldown = weapon.cooldown;
+        Some(weapon)
+    }
+
+    /// Starts the cooldown of the selected melee weapon and returns it if it's ready to swing.
+    pub fn try_swing(&mut self) -> Option<MeleeWeapon> {
+        if self.cooldown > 0 {
+            return None;
+        }
+
+        let Some(Item::Melee(weapon)) = self.selected_item() else {
+            return None;
+        };
+
+        let weapon = weapon.clone();
+        self.cooldown = weapon.cooldown;
+        Some(weapon)
+    }
+}
+
+// === Systems === //
+
+const HOTBAR_SLOT_SIZE: f32 = 50.;
+const HOTBAR_SLOT_GAP: f32 = 5.;
+
+/// The size of the hotbar on screen.
+pub const HOTBAR_SIZE: Vec2 = Vec2::new(
+    Inventory::SLOTS as f32 * (HOTBAR_SLOT_SIZE + HOTBAR_SLOT_GAP) - HOTBAR_SLOT_GAP,
+    HOTBAR_SLOT_SIZE,
+);
+
+/// The color with which a tile item of the given material is displayed.
+pub fn tile_item_color(registry: &MaterialRegistry, material: &str) -> Color {
+    registry
+        .lookup_by_name(material)
+        .and_then(|id| registry.lookup(id).try_get::<SolidTileMaterial>())
+        .map_or(DARKGRAY, |solid| solid.color)
+}
+
+pub fn sys_render_hotbar(
+    In(bar): In<Aabb>,
+    mut rand: RandomAccess<(&TileWorld, &MaterialRegistry, &SolidTileMaterial)>,
+    query: Query<
+        (&InsideWorld, &Inventory),
+        (With<PlayerState>, Without<Dead>, Without<RemoteInput>),
+    >,
+) {
+    let Some((&InsideWorld(world), inventory)) = query.iter().next() else {
+        return;
+    };
+
+    let (left, top) = (bar.min.x, bar.min.y);
+
+    rand.provide(|| {
+        let registry = world.entity().get::<MaterialRegistry>();
+
+        for i in 0..Inventory::SLOTS {
+            let x = left + i as f32 * (HOTBAR_SLOT_SIZE + HOTBAR_SLOT_GAP);
+            draw_rectangle(
+                x,
+                top,
+                HOTBAR_SLOT_SIZE,
+                HOTBAR_SLOT_SIZE,
+                Color::new(0., 0., 0., 0.6),
+            );
+
+            if let Some(ItemStack { item, count }) = inventory.slot(i) {
+                // Show tiles by their color
+                if let Item::Tile(material) = item {
+                    let color = tile_item_color(&registry, material);
+                    draw_rectangle(
+                        x + 10.,
+                        top + 8.,
+                        HOTBAR_SLOT_SIZE - 20.,
+                        HOTBAR_SLOT_SIZE - 26.,
+                        color,
+                    );
+                }
+
+                if item.is_stackable() {
+                    let label = count.to_string();
+                    let size = measure_text(&label, None, 14, 1.);
+                    draw_text(
+                        &label,
+                        x + HOTBAR_SLOT_SIZE - size.width - 3.,
+                        top + 12.,
+                        14.,
+                        WHITE,
+                    );
+                }
+
+                let label = item.name();
+                let size = measure_text(label, None, 14, 1.);
+                draw_text(
+                    label,
+                    x + (HOTBAR_SLOT_SIZE - size.width) / 2.,
+                    top + HOTBAR_SLOT_SIZE - 5.,
+                    14.,
+                    WHITE,
+                );
+            }
+
+            draw_text(&(i + 1).to_string(), x + 3., top + 12., 14., GRAY);
+
+            if i == inventory.selected() {
+                draw_rectangle_lines(x, top, HOTBAR_SLOT_SIZE, HOTBAR_SLOT_SIZE, 3., YELLOW);
+            }
+        }
+    });
+}
+
+/// Shows the selected weapon and how long it takes until it can be used again.
+pub fn sys_render_weapon_cooldown(
+    In(rect): In<Aabb>,
+    query: Query<&Inventory, (With<PlayerState>, Without<Dead>, Without<RemoteInput>)>,
+) {
+    let Some(inventory) = query.iter().next() else {
+        return;
+    };
+
+    let (name, cooldown) = match inventory.selected_item() {
+        Some(Item::Weapon(weapon)) => (&weapon.name, weapon.cooldown),
+        Some(Item::Melee(weapon)) => (&weapon.name, weapon.cooldown),
+        _ => return,
+    };
+
+    let ready = 1. - inventory.cooldown() as f32 / cooldown.max(1) as f32;
+
+    draw_rectangle_aabb(rect, Color::new(0., 0., 0., 0.6));
+    draw_text(name, rect.min.x + 5., rect.min.y + 16., 18., WHITE);
+
+    let bar = Aabb::new_sized(rect.min + Vec2::new(5., 24.), Vec2::new(rect.w() - 10., 6.));
+    draw_rectangle_aabb(bar, DARKGRAY);
+    draw_rectangle_aabb(
+        bar.with_width(bar.w() * ready.clamp(0., 1.)),
+        if inventory.cooldown() == 0 {
+            WHITE
+        } else {
+            YELLOW
+        },
+    );
+    stroke_rectangle_aabb(rect, 1., GRAY);
+}
@@ -2,49 +2,131 @@ use bevy_ecs::{
     component::Component,
     entity::Entity,
     event::{Event, EventWriter},
-    query::With,
-    system::{Query, Res},
+    query::{Has, With, Without},
+    system::{Commands, Query, Res, ResMut, Resource},
 };
 use cbit::cbit;
 use macroquad::{
-    color::{Color, BLUE},
+    color::{Color, BLUE, GOLD, GREEN, MAGENTA, ORANGE, PURPLE, RED, SKYBLUE, WHITE, YELLOW},
+    input::{is_key_pressed, KeyCode},
     math::Vec2,
+    text::draw_text,
+    window::screen_width,
 };
 use rustc_hash::FxHashSet;
+use serde::Deserialize;
 
 use crate::{
     game::{
-        math::{aabb::Aabb, draw::draw_rectangle_aabb, glam::Vec2Ext},
+        math::{
+            aabb::Aabb,
+            draw::{draw_rectangle_aabb, stroke_rectangle_aabb},
+        },
         tile::{
             collider::{
-                Collider, InsideWorld, TrackedCollider, TrackedColliderChunk, WorldColliders,
+                Collider, CollisionLayers, InsideWorld, TrackedCollider, TrackedColliderChunk,
+                WorldColliders,
             },
             data::{TileChunk, TileWorld, WorldCreatedChunk},
-            kinematic::{AnyCollision, KinematicApi, TileColliderDescriptor},
+            kinematic::{
+                AnyCollision, KinematicApi, TileColliderDescriptor, TilePhysicsDescriptor,
+            },
+            liquid::LiquidMaterial,
             material::MaterialRegistry,
         },
+        time::RenderAlpha,
     },
-    util::arena::{RandomAccess, RandomEntityExt, SendsEvent},
+    util::arena::{Obj, ObjOwner, RandomAccess, RandomEntityExt, SendsEvent},
 };
 
-use super::camera::ActiveCamera;
+use super::{
+    camera::{ActiveCamera, CullExt, VirtualCamera},
+    platform::PlatformRider,
+    projectile::Pooled,
+};
 
 // === Systems === //
 
-#[derive(Debug, Component)]
+#[derive(Debug, Clone, Component)]
 pub struct Pos(pub Vec2);
 
-#[derive(Debug, Component)]
+/// The [`Pos`] of an actor as of the previous tick. Render systems draw actors between their
+/// previous and current positions according to the [`RenderAlpha`] so that motion stays smooth
+/// on displays refreshing faster than the tick rate. This is added to every actor with a `Pos`
+/// automatically.
+#[derive(Debug, Copy, Clone, Component)]
+pub struct PrevPos(pub Vec2);
+
+impl PrevPos {
+    /// The position at which to draw an actor at `pos` this frame.
+    pub fn interpolate(prev: Option<&PrevPos>, pos: Vec2, alpha: RenderAlpha) -> Vec2 {
+        prev.map_or(pos, |prev| alpha.lerp(prev.0, pos))
+    }
+}
+
+#[derive(Debug, Clone, Component)]
 pub struct Vel(pub Vec2);
 
-#[derive(Debug, Component, Default)]
+#[derive(Debug, Clone, Component, Default)]
 pub struct ColliderMoves;
 
-#[derive(Debug, Component, Default)]
+/// The size of the collider kept centered on a [`ColliderMoves`] entity's position. Entities without
+/// one are [`ColliderSize::DEFAULT`] units square.
+#[derive(Debug, Copy, Clone, Component)]
+pub struct ColliderSize(pub Vec2);
+
+impl ColliderSize {
+    pub const DEFAULT: Vec2 = Vec2::splat(40.);
+}
+
+/// Accelerates a moving collider downwards every tick until it reaches its terminal velocity.
+#[derive(Debug, Copy, Clone, Component, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Gravity {
+    pub acceleration: f32,
+    pub terminal_velocity: f32,
+}
+
+impl Default for Gravity {
+    fn default() -> Self {
+        Self {
+            acceleration: 0.4,
+            terminal_velocity: 30.,
+        }
+    }
+}
+
+/// Marks a moving collider as requiring swept collision detection. This should be used for fast
+/// movers such as projectiles which could otherwise tunnel through thin walls.
+#[derive(Debug, Clone, Component, Default)]
+pub struct ContinuousCollision;
+
+#[derive(Debug, Clone, Component)]
 pub struct ColliderListens {
+    mask: u32,
     contains: FxHashSet<Entity>,
 }
 
+impl Default for ColliderListens {
+    fn default() -> Self {
+        Self::with_mask(CollisionLayers::ALL)
+    }
+}
+
+impl ColliderListens {
+    /// Creates a listener which only reports colliders belonging to one of the layers in `mask`.
+    pub fn with_mask(mask: u32) -> Self {
+        Self {
+            mask,
+            contains: FxHashSet::default(),
+        }
+    }
+
+    pub fn mask(&self) -> u32 {
+        self.mask
+    }
+}
+
 #[derive(Debug, Event)]
 pub struct ColliderEvent {
     pub listener: Entity,
@@ -52,8 +134,37 @@ pub struct ColliderEvent {
     pub entered: bool,
 }
 
+/// Records every actor's position before this tick moves it. Run at the start of every update.
+pub fn sys_store_previous_pos(
+    mut query: Query<(Entity, &Pos, Option<&mut PrevPos>)>,
+    mut commands: Commands,
+) {
+    for (entity, &Pos(pos), prev) in query.iter_mut() {
+        match prev {
+            Some(mut prev) => prev.0 = pos,
+            None => {
+                commands.entity(entity).insert(PrevPos(pos));
+            }
+        }
+    }
+}
+
 pub fn sys_update_moving_colliders(
-    mut query: Query<(&InsideWorld, &mut Pos, &mut Vel, &mut Collider), With<ColliderMoves>>,
+    mut query: Query<
+        (
+            Entity,
+            &InsideWorld,
+            &mut Pos,
+            &mut Vel,
+            &mut Collider,
+            Option<&ColliderSize>,
+            Option<&CollisionLayers>,
+            Option<&Gravity>,
+            Option<&mut PlatformRider>,
+            Has<ContinuousCollision>,
+        ),
+        (With<ColliderMoves>, Without<Pooled>),
+    >,
     mut rand: RandomAccess<(
         &mut TileWorld,
         &mut TileChunk,
@@ -62,26 +173,63 @@ pub fn sys_update_moving_colliders(
         &TrackedCollider,
         &WorldColliders,
         &TileColliderDescriptor,
+        &TilePhysicsDescriptor,
+        &LiquidMaterial,
         &MaterialRegistry,
         SendsEvent<WorldCreatedChunk>,
     )>,
 ) {
     rand.provide(|| {
-        for (&InsideWorld(world), mut pos, mut vel, mut collider) in query.iter_mut() {
+        for (
+            me,
+            &InsideWorld(world),
+            mut pos,
+            mut vel,
+            mut collider,
+            size,
+            layers,
+            gravity,
+            rider,
+            continuous,
+        ) in query.iter_mut()
+        {
             let mut world = world.entity().get::<KinematicApi>();
+            let size = size.map_or(ColliderSize::DEFAULT, |size| size.0);
+            let mask = layers.copied().unwrap_or_default().mask;
+
+            // Ride along with the platform we were standing on. It has already moved so we ignore
+            // it here lest we get caught on its new position.
+            if let Some((platform, carry)) = rider.and_then(|mut rider| rider.take_carry()) {
+                let carried = world.move_by(collider.0, carry, mask, |coll| match coll {
+                    AnyCollision::Tile(_, _, _) => true,
+                    AnyCollision::Collider(other, _) => other != me && other != platform,
+                });
+                pos.0 += carried;
+                collider.0 = Aabb::new_centered(pos.0, size);
+            }
+
+            if let Some(gravity) = gravity {
+                vel.0.y = (vel.0.y + gravity.acceleration).min(gravity.terminal_velocity);
+            }
+
+            let acceleration = gravity.map_or(0., |gravity| gravity.acceleration);
+            vel.0 = world.apply_liquid_forces(collider.0, vel.0, acceleration);
 
             let delta = vel.0;
             let filter = |coll| match coll {
                 AnyCollision::Tile(_, _, _) => true,
-                AnyCollision::Collider(_, _) => false,
+                AnyCollision::Collider(other, _) => other != me,
             };
 
-            let delta = world.move_by(collider.0, delta, filter);
+            let delta = if continuous {
+                world.move_by_swept(collider.0, delta, mask, filter)
+            } else {
+                world.move_by(collider.0, delta, mask, filter)
+            };
             pos.0 += delta;
-            collider.0 = Aabb::new_centered(pos.0, Vec2::splat(40.));
+            collider.0 = Aabb::new_centered(pos.0, size);
 
-            let mask = world.get_clip_mask(collider.0, vel.0, filter);
-            vel.0 = vel.0.mask(mask);
+            vel.0 = world.apply_contact_response(collider.0, vel.0, mask, filter);
         }
     });
 }
@@ -95,7 +243,7 @@ pub fn sys_update_listening_colliders(
         &TrackedCollider,
         SendsEvent<WorldCreatedChunk>,
     )>,
-    mut query: Query<(Entity, &InsideWorld, &Collider, &mut ColliderListens)>,
+    mut query: Query<(Entity, &InsideWorld, &Collider, &mut ColliderListens), Without<Pooled>>,
     mut events: EventWriter<ColliderEvent>,
 ) {
     rand.provide(|| {
@@ -108,7 +256,7 @@ pub fn sys_update_listening_colliders(
             removed.extend(listen_state.contains.drain());
 
             cbit! {
-                for (other, _) in world.collisions(aabb) {
+                for (other, _) in world.collisions(aabb, listen_state.mask) {
                     if listener == other {
                         continue;
                     }
@@ -133,10 +281,258 @@ pub fn sys_update_listening_colliders(
     });
 }
 
-pub fn sys_draw_debug_colliders(mut query: Query<&Collider>, camera: Res<ActiveCamera>) {
+// === Debug Rendering === //
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum ColliderDebugCategory {
+    Actors,
+    Players,
+    Bullets,
+    Sensors,
+    Platforms,
+    TouchedTiles,
+    CheckAabbs,
+    PathSearches,
+    PathCosts,
+}
+
+impl ColliderDebugCategory {
+    pub const ALL: [Self; 9] = [
+        Self::Actors,
+        Self::Players,
+        Self::Bullets,
+        Self::Sensors,
+        Self::Platforms,
+        Self::TouchedTiles,
+        Self::CheckAabbs,
+        Self::PathSearches,
+        Self::PathCosts,
+    ];
+
+    /// Categorizes a collider by the first of its collision layers which has a dedicated category.
+    pub fn of(layers: CollisionLayers) -> Self {
+        let membership = layers.membership;
+
+        if membership & CollisionLayers::PROJECTILES != 0 {
+            Self::Bullets
+        } else if membership & CollisionLayers::TRIGGERS != 0 {
+            Self::Sensors
+        } else if membership & CollisionLayers::PLATFORMS != 0 {
+            Self::Platforms
+        } else if membership & CollisionLayers::PLAYERS != 0 {
+            Self::Players
+        } else {
+            Self::Actors
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Actors => "Actors",
+            Self::Players => "Players",
+            Self::Bullets => "Bullets",
+            Self::Sensors => "Sensors",
+            Self::Platforms => "Platforms",
+            Self::TouchedTiles => "Tiles touched by sweeps",
+            Self::CheckAabbs => "Sweep check AABBs",
+            Self::PathSearches => "Enemy path searches",
+            Self::PathCosts => "Enemy path costs",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Self::Actors => BLUE,
+            Self::Players => GREEN,
+            Self::Bullets => ORANGE,
+            Self::Sensors => YELLOW,
+            Self::Platforms => PURPLE,
+            Self::TouchedTiles => RED,
+            Self::CheckAabbs => MAGENTA,
+            Self::PathSearches => SKYBLUE,
+            Self::PathCosts => GOLD,
+        }
+    }
+
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// Determines which colliders are drawn by [`sys_draw_debug_colliders`].
+#[derive(Debug, Clone, Resource)]
+pub struct ColliderDebug {
+    pub menu_open: bool,
+    enabled: u32,
+}
+
+impl Default for ColliderDebug {
+    fn default() -> Self {
+        let mut debug = Self {
+            menu_open: false,
+            enabled: 0,
+        };
+
+        for category in [
+            ColliderDebugCategory::Actors,
+            ColliderDebugCategory::Players,
+            ColliderDebugCategory::Bullets,
+            ColliderDebugCategory::Sensors,
+            ColliderDebugCategory::Platforms,
+        ] {
+            debug.set_enabled(category, true);
+        }
+
+        debug
+    }
+}
+
+impl ColliderDebug {
+    pub fn is_enabled(&self, category: ColliderDebugCategory) -> bool {
+        self.enabled & category.bit() != 0
+    }
+
+    pub fn set_enabled(&mut self, category: ColliderDebugCategory, enabled: bool) {
+        if enabled {
+            self.enabled |= category.bit();
+        } else {
+            self.enabled &= !category.bit();
+        }
+    }
+
+    pub fn toggle(&mut self, category: ColliderDebugCategory) {
+        self.set_enabled(category, !self.is_enabled(category));
+    }
+
+    pub fn wants_trace(&self) -> bool {
+        self.is_enabled(ColliderDebugCategory::TouchedTiles)
+            || self.is_enabled(ColliderDebugCategory::CheckAabbs)
+    }
+
+    pub fn wants_path_trace(&self) -> bool {
+        self.is_enabled(ColliderDebugCategory::PathSearches)
+            || self.is_enabled(ColliderDebugCategory::PathCosts)
+    }
+}
+
+/// Overrides the category-wide debug visibility of a single collider.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Component)]
+pub enum ColliderDebugVisibility {
+    Show,
+    Hide,
+}
+
+pub fn sys_handle_collider_debug_input(mut debug: ResMut<ColliderDebug>) {
+    if is_key_pressed(KeyCode::F2) {
+        debug.menu_open = !debug.menu_open;
+    }
+
+    if !debug.menu_open {
+        return;
+    }
+
+    const KEYS: [KeyCode; 9] = [
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+        KeyCode::Key8,
+        KeyCode::Key9,
+    ];
+
+    for (key, category) in KEYS.into_iter().zip(ColliderDebugCategory::ALL) {
+        if is_key_pressed(key) {
+            debug.toggle(category);
+        }
+    }
+}
+
+pub fn sys_draw_debug_colliders(
+    query: Query<
+        (
+            &InsideWorld,
+            &Collider,
+            Option<&CollisionLayers>,
+            Option<&ColliderDebugVisibility>,
+        ),
+        Without<Pooled>,
+    >,
+    mut worlds: Query<(Entity, &ObjOwner<KinematicApi>)>,
+    mut rand: RandomAccess<(&mut KinematicApi, &VirtualCamera)>,
+    debug: Res<ColliderDebug>,
+    camera: Res<ActiveCamera>,
+) {
     let _guard = camera.apply();
+    let translucent = |color: Color| Color::from_vec(color.to_vec().truncate().extend(0.3));
+
+    let visible = query
+        .iter()
+        .culled(&camera, |&(&InsideWorld(world), &Collider(aabb), ..)| {
+            (world, aabb)
+        });
+
+    for (_, &Collider(aabb), layers, visibility) in visible {
+        let category = ColliderDebugCategory::of(layers.copied().unwrap_or_default());
+        let visible = match visibility {
+            Some(ColliderDebugVisibility::Show) => true,
+            Some(ColliderDebugVisibility::Hide) => false,
+            None => debug.is_enabled(category),
+        };
+
+        if visible {
+            draw_rectangle_aabb(aabb, translucent(category.color()));
+        }
+    }
+
+    // Sweep traces are only recorded while they're being displayed and are consumed every frame so
+    // that we only ever show the checks performed since the last render.
+    rand.provide(|| {
+        let shown = camera.camera.map(Obj::entity);
+
+        for (entity, &ObjOwner(mut kinematics)) in worlds.iter_mut() {
+            kinematics.set_tracing(debug.wants_trace());
+            let trace = kinematics.take_trace();
+
+            if shown != Some(entity) {
+                continue;
+            }
+
+            if debug.is_enabled(ColliderDebugCategory::TouchedTiles) {
+                let color = translucent(ColliderDebugCategory::TouchedTiles.color());
+                for &aabb in &trace.touched_tiles {
+                    draw_rectangle_aabb(aabb, color);
+                }
+            }
+
+            if debug.is_enabled(ColliderDebugCategory::CheckAabbs) {
+                let color = ColliderDebugCategory::CheckAabbs.color();
+                for &aabb in &trace.check_aabbs {
+                    stroke_rectangle_aabb(aabb, 2., color);
+                }
+            }
+        }
+    });
+}
+
+pub fn sys_render_collider_debug_menu(debug: Res<ColliderDebug>) {
+    if !debug.menu_open {
+        return;
+    }
+
+    let x = screen_width() - 300.;
+    draw_text("Collider debug (F2)", x, 30., 24., WHITE);
 
-    for &Collider(aabb) in query.iter_mut() {
-        draw_rectangle_aabb(aabb, Color::from_vec(BLUE.to_vec().truncate().extend(0.3)));
+    for (i, category) in ColliderDebugCategory::ALL.into_iter().enumerate() {
+        let mark = if debug.is_enabled(category) { "x" } else { " " };
+        draw_text(
+            &format!("[{mark}] {}: {}", i + 1, category.name()),
+            x,
+            55. + i as f32 * 20.,
+            20.,
+            category.color(),
+        );
     }
 }
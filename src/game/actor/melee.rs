@@ -0,0 +1,218 @@
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    event::EventWriter,
+    system::{Query, Res, ResMut},
+};
+use cbit::cbit;
+use macroquad::{
+    color::Color,
+    math::{Vec2, Vec3},
+    shapes::draw_triangle,
+};
+use smallvec::SmallVec;
+
+use crate::{
+    game::{
+        draw_order::{DrawQueue, RenderLayer},
+        math::aabb::Aabb,
+        tile::{
+            collider::{CollisionLayers, InsideWorld, WorldColliders},
+            data::TileWorld,
+        },
+        time::RenderAlpha,
+    },
+    util::arena::{despawn_entity, spawn_entity, RandomAccess, RandomEntityExt},
+};
+
+use super::{
+    camera::ActiveCamera,
+    damage::{DamageEvent, DamageKind},
+    kinematic::{Pos, PrevPos, Vel},
+};
+
+// === MeleeWeapon === //
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeleeWeapon {
+    pub name: String,
+
+    /// The damage dealt to every actor caught by a swing.
+    pub damage: f32,
+
+    /// The speed at which actors caught by a swing are pushed away from the attacker.
+    pub knockback: f32,
+
+    /// The distance from the attacker's center covered by a swing.
+    pub reach: f32,
+
+    /// The angle covered by a swing, in radians.
+    pub arc: f32,
+
+    /// The number of ticks for which a swing hurts actors.
+    pub active_ticks: u32,
+
+    /// The number of ticks between two swings.
+    pub cooldown: u32,
+}
+
+impl MeleeWeapon {
+    /// Swings the weapon from `attacker` towards `target`, hitting actors other than players.
+    /// Must be called within a [`RandomAccess`].
+    pub fn swing(&self, attacker: Entity, world: InsideWorld, from: Vec2, target: Vec2) -> Entity {
+        spawn_entity((
+            world,
+            MeleeSwing {
+                attacker,
+                dir: (target - from).try_normalize().unwrap_or(Vec2::X),
+                weapon: self.clone(),
+                mask: CollisionLayers::ACTORS,
+                remaining: self.active_ticks,
+                hit: SmallVec::new(),
+            },
+        ))
+    }
+}
+
+// === MeleeSwing === //
+
+/// A short-lived sector in front of an attacker which hurts every actor it touches once.
+#[derive(Debug, Clone, Component)]
+pub struct MeleeSwing {
+    /// The entity swinging the weapon. The sector stays centered on it as it moves.
+    pub attacker: Entity,
+
+    /// The direction of the middle of the sector.
+    pub dir: Vec2,
+    pub weapon: MeleeWeapon,
+
+    /// The layers of the actors which can be hit.
+    pub mask: u32,
+
+    /// The number of ticks left until the swing stops hurting actors.
+    remaining: u32,
+
+    /// The actors already hit by this swing.
+    hit: SmallVec<[Entity; 4]>,
+}
+
+impl MeleeSwing {
+    /// Determines whether the sector centered on `origin` overlaps `aabb`. The collider's closest
+    /// point must be within reach and its center within the arc.
+    pub fn covers(&self, origin: Vec2, aabb: Aabb) -> bool {
+        let closest = origin.clamp(aabb.min, aabb.max);
+        if closest.distance(origin) > self.weapon.reach {
+            return false;
+        }
+
+        // Colliders overlapping the attacker are always hit.
+        let Some(to_center) = (aabb.center() - origin).try_normalize() else {
+            return true;
+        };
+
+        aabb.contains(origin) || self.dir.angle_between(to_center).abs() <= self.weapon.arc / 2.
+    }
+
+    /// The fraction of the swing which has played out.
+    pub fn progress(&self) -> f32 {
+        1. - self.remaining as f32 / self.weapon.active_ticks.max(1) as f32
+    }
+}
+
+// === Systems === //
+
+pub fn sys_update_melee_swings(
+    mut rand: RandomAccess<(&TileWorld, &WorldColliders)>,
+    mut swings: Query<(Entity, &InsideWorld, &mut MeleeSwing)>,
+    mut targets: Query<(&Pos, Option<&mut Vel>)>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    rand.provide(|| {
+        for (entity, &InsideWorld(world), mut swing) in swings.iter_mut() {
+            let Ok((&Pos(origin), _)) = targets.get(swing.attacker) else {
+                despawn_entity(entity);
+                continue;
+            };
+
+            if swing.remaining == 0 {
+                despawn_entity(entity);
+                continue;
+            }
+            swing.remaining -= 1;
+
+            // Find the actors entering the sector this tick.
+            let colliders = world.entity().get::<WorldColliders>();
+            let area = Aabb::new_centered(origin, Vec2::splat(swing.weapon.reach * 2.));
+            let mut victims = SmallVec::<[Entity; 4]>::new();
+
+            cbit! {
+                for (victim, aabb) in colliders.collisions(area, swing.mask) {
+                    if victim != swing.attacker
+                        && !swing.hit.contains(&victim)
+                        && swing.covers(origin, aabb)
+                    {
+                        victims.push(victim);
+                    }
+                }
+            }
+
+            // Hurt them and push them away from the attacker.
+            for victim in victims {
+                swing.hit.push(victim);
+
+                let Ok((&Pos(pos), vel)) = targets.get_mut(victim) else {
+                    continue;
+                };
+
+                damage.send(DamageEvent {
+                    source: swing.attacker,
+                    target: victim,
+                    kind: DamageKind::Melee,
+                    amount: swing.weapon.damage,
+                });
+
+                if let Some(mut vel) = vel {
+                    let dir = (pos - origin).try_normalize().unwrap_or(swing.dir);
+                    vel.0 += dir * swing.weapon.knockback;
+                }
+            }
+        }
+    });
+}
+
+pub fn sys_render_melee_swings(
+    swings: Query<(&InsideWorld, &MeleeSwing)>,
+    attackers: Query<(&Pos, Option<&PrevPos>)>,
+    mut draws: ResMut<DrawQueue>,
+    alpha: Res<RenderAlpha>,
+    camera: Res<ActiveCamera>,
+) {
+    const SEGMENTS: usize = 8;
+
+    for (&InsideWorld(world), swing) in swings.iter() {
+        if !camera.shows(world) {
+            continue;
+        }
+
+        let Ok((&Pos(pos), prev)) = attackers.get(swing.attacker) else {
+            continue;
+        };
+
+        let origin = PrevPos::interpolate(prev, pos, *alpha);
+        let reach = swing.weapon.reach;
+        let arc = swing.weapon.arc;
+        let start = swing.dir.rotate(Vec2::from_angle(-arc / 2.));
+        let color = Color::from_vec(Vec3::ONE.extend(0.5 * (1. - swing.progress())));
+
+        draws.push(RenderLayer::Projectiles, origin.y, move || {
+            let step = Vec2::from_angle(arc / SEGMENTS as f32);
+            let mut edge = start;
+
+            for _ in 0..SEGMENTS {
+                let next = edge.rotate(step);
+                draw_triangle(origin, origin + edge * reach, origin + next * reach, color);
+                edge = next;
+            }
+        });
+    }
+}
@@ -1,5 +1,23 @@
+pub mod bench;
 pub mod camera;
+pub mod checkpoint;
+pub mod controller;
+pub mod damage;
+pub mod death;
+pub mod drops;
+pub mod effects;
+pub mod enemy;
+pub mod explosion;
 pub mod health;
+pub mod inspector;
+pub mod inventory;
 pub mod kinematic;
+pub mod melee;
+pub mod platform;
 pub mod player;
+pub mod portal;
 pub mod projectile;
+pub mod shadow;
+pub mod trigger;
+pub mod void;
+pub mod wave;
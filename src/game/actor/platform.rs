@@ -0,0 +1,263 @@
+use bevy_ecs::{
+    bundle::Bundle,
+    component::Component,
+    entity::Entity,
+    system::{Local, Query, Res, ResMut},
+};
+use cbit::cbit;
+use macroquad::{
+    color::{Color, DARKBROWN},
+    math::Vec2,
+};
+
+use crate::{
+    game::{
+        draw_order::{DrawQueue, RenderLayer, RenderOrder},
+        math::{
+            aabb::Aabb,
+            draw::{draw_rectangle_aabb, stroke_rectangle_aabb},
+        },
+        tile::{
+            collider::{
+                Collider, CollisionLayers, InsideWorld, TrackedCollider, TrackedColliderChunk,
+                WorldColliders,
+            },
+            data::{TileChunk, TileWorld, WorldCreatedChunk},
+            kinematic::{AnyCollision, KinematicApi, TileColliderDescriptor},
+            material::MaterialRegistry,
+        },
+        time::RenderAlpha,
+    },
+    util::arena::{ObjOwner, RandomAccess, RandomEntityExt, SendsEvent},
+};
+
+use super::{
+    camera::ActiveCamera,
+    kinematic::{Pos, PrevPos},
+};
+
+// === MovingPlatform === //
+
+/// A solid collider which follows a scripted path, carrying any [`PlatformRider`] standing on top
+/// of it along.
+#[derive(Debug, Clone, Component)]
+pub struct MovingPlatform {
+    pub size: Vec2,
+
+    /// The points visited by the platform, in order. The platform returns to the first point after
+    /// reaching the last so paths which should be retraced must list their points in both
+    /// directions.
+    pub path: Vec<Vec2>,
+
+    /// The distance covered by the platform every tick.
+    pub speed: f32,
+
+    /// The number of ticks the platform rests at each point of its path.
+    pub wait_ticks: u32,
+
+    target: usize,
+    waiting: u32,
+}
+
+impl MovingPlatform {
+    pub fn new(size: Vec2, path: Vec<Vec2>, speed: f32) -> Self {
+        Self {
+            size,
+            path,
+            speed,
+            wait_ticks: 0,
+            target: 0,
+            waiting: 0,
+        }
+    }
+
+    pub fn with_wait(mut self, ticks: u32) -> Self {
+        self.wait_ticks = ticks;
+        self
+    }
+
+    /// Advances the platform along its path by a tick, returning how far it moves from `pos`.
+    pub fn step(&mut self, pos: Vec2) -> Vec2 {
+        if self.path.is_empty() {
+            return Vec2::ZERO;
+        }
+
+        if self.waiting > 0 {
+            self.waiting -= 1;
+            return Vec2::ZERO;
+        }
+
+        let to_target = self.path[self.target] - pos;
+        if to_target.length() <= self.speed {
+            self.target = (self.target + 1) % self.path.len();
+            self.waiting = self.wait_ticks;
+            return to_target;
+        }
+
+        to_target.normalize_or_zero() * self.speed
+    }
+}
+
+#[derive(Bundle)]
+pub struct PlatformBundle {
+    pub pos: Pos,
+    pub world: InsideWorld,
+    pub collider: Collider,
+    pub layers: CollisionLayers,
+    pub platform: MovingPlatform,
+}
+
+impl PlatformBundle {
+    /// Creates a platform starting at the first point of its path.
+    pub fn new(world: InsideWorld, platform: MovingPlatform) -> Self {
+        let pos = platform.path.first().copied().unwrap_or_default();
+
+        Self {
+            pos: Pos(pos),
+            world,
+            collider: Collider(Aabb::new_centered(pos, platform.size)),
+            layers: CollisionLayers::new(CollisionLayers::PLATFORMS, CollisionLayers::TILES),
+            platform,
+        }
+    }
+}
+
+// === PlatformRider === //
+
+/// Lets a moving collider be carried by the [`MovingPlatform`] it's standing on. Riders must
+/// include [`CollisionLayers::PLATFORMS`] in their mask to be able to stand on platforms at all.
+#[derive(Debug, Default, Component)]
+pub struct PlatformRider {
+    platform: Option<Entity>,
+    carry: Vec2,
+}
+
+impl PlatformRider {
+    /// The platform the actor was standing on at the start of this tick.
+    pub fn platform(&self) -> Option<Entity> {
+        self.platform
+    }
+
+    /// Takes the platform carrying the actor along with the distance it moved this tick.
+    pub fn take_carry(&mut self) -> Option<(Entity, Vec2)> {
+        let platform = self.platform.take()?;
+        Some((platform, std::mem::take(&mut self.carry)))
+    }
+}
+
+// === Systems === //
+
+/// The distance above a platform within which actors are considered to be standing on it.
+const RIDER_REACH: f32 = KinematicApi::TOLERANCE * 4.;
+
+pub fn sys_move_platforms(
+    mut rand: RandomAccess<(
+        &mut KinematicApi,
+        &mut TileWorld,
+        &mut TileChunk,
+        &mut TrackedColliderChunk,
+        &mut TrackedCollider,
+        &mut WorldColliders,
+        &MaterialRegistry,
+        &TileColliderDescriptor,
+        SendsEvent<WorldCreatedChunk>,
+    )>,
+    mut platforms: Query<(
+        Entity,
+        &InsideWorld,
+        &mut Pos,
+        &mut Collider,
+        &mut MovingPlatform,
+        Option<&ObjOwner<TrackedCollider>>,
+    )>,
+    mut riders: Query<&mut PlatformRider>,
+    mut candidates: Local<Vec<(Entity, Aabb)>>,
+) {
+    // Riders which weren't able to move last tick mustn't be carried by stale deltas.
+    for mut rider in riders.iter_mut() {
+        rider.platform = None;
+    }
+
+    rand.provide(|| {
+        for (me, &InsideWorld(world), mut pos, mut collider, mut platform, tracked) in
+            platforms.iter_mut()
+        {
+            let mut kinematics = world.entity().get::<KinematicApi>();
+            let delta = platform.step(pos.0);
+
+            // Find the actors resting on top of the platform before it moves.
+            let aabb = collider.0;
+            let above = Aabb {
+                min: Vec2::new(aabb.min.x, aabb.min.y - RIDER_REACH),
+                max: Vec2::new(aabb.max.x, aabb.min.y),
+            };
+
+            candidates.clear();
+            cbit! {
+                for collider in kinematics.iter_colliders_in(above, !CollisionLayers::TILES) {
+                    if let AnyCollision::Collider(other, other_aabb) = collider {
+                        if other != me {
+                            candidates.push((other, other_aabb));
+                        }
+                    }
+                }
+            }
+
+            for &(other, other_aabb) in candidates.iter() {
+                let Ok(mut rider) = riders.get_mut(other) else {
+                    continue;
+                };
+
+                let clip = kinematics.get_clip_mask(
+                    other_aabb,
+                    Vec2::Y,
+                    CollisionLayers::PLATFORMS,
+                    |coll| matches!(coll, AnyCollision::Collider(platform, _) if platform == me),
+                );
+
+                if !clip.y {
+                    rider.platform = Some(me);
+                    rider.carry = delta;
+                }
+            }
+
+            // Move the platform right away so that riders and other movers collide with its new
+            // position this tick.
+            pos.0 += delta;
+            collider.0 = Aabb::new_centered(pos.0, platform.size);
+
+            if let Some(&ObjOwner(tracked)) = tracked {
+                tracked.move_to(collider.0);
+            }
+        }
+    });
+}
+
+pub fn sys_render_platforms(
+    query: Query<(
+        &InsideWorld,
+        &Pos,
+        Option<&PrevPos>,
+        &MovingPlatform,
+        Option<&RenderLayer>,
+        Option<&RenderOrder>,
+    )>,
+    mut draws: ResMut<DrawQueue>,
+    alpha: Res<RenderAlpha>,
+    camera: Res<ActiveCamera>,
+) {
+    for (&InsideWorld(world), &Pos(pos), prev, platform, layer, order) in query.iter() {
+        if !camera.shows(world) {
+            continue;
+        }
+
+        let pos = PrevPos::interpolate(prev, pos, *alpha);
+        let aabb = Aabb::new_centered(pos, platform.size);
+
+        let layer = layer.copied().unwrap_or(RenderLayer::Background);
+        draws.push(layer, RenderOrder::key_of(order, pos.y), move || {
+            draw_rectangle_aabb(aabb, Color::new(0.55, 0.4, 0.25, 1.));
+            stroke_rectangle_aabb(aabb, 3., DARKBROWN);
+        });
+    }
+}
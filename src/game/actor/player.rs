@@ -1,50 +1,147 @@
 use std::collections::VecDeque;
 
 use bevy_ecs::{
+    bundle::Bundle,
     component::Component,
-    event::EventReader,
-    query::With,
-    system::{Query, Res, ResMut},
+    entity::Entity,
+    event::{EventReader, EventWriter},
+    query::{Has, With, Without},
+    system::{Commands, In, Local, Query, Res, ResMut, Resource, SystemParam},
 };
 use cbit::cbit;
 use macroquad::{
-    color::{Color, DARKPURPLE, GRAY, GREEN, RED, WHITE, YELLOW},
-    input::{is_key_down, is_mouse_button_down, mouse_position, KeyCode, MouseButton},
-    math::{Affine2, IVec2, Vec2},
-    miniquad::window::screen_size,
+    color::{
+        Color, BEIGE, BROWN, DARKGRAY, DARKPURPLE, GOLD, GRAY, GREEN, LIME, ORANGE, PINK, RED,
+        SKYBLUE, WHITE, YELLOW,
+    },
+    input::{is_key_pressed, mouse_position, mouse_wheel, KeyCode},
+    math::{Affine2, UVec2, Vec2},
     shapes::draw_circle,
 };
 
 use crate::{
     game::{
+        background::{ParallaxBackground, ParallaxLayer},
+        draw_order::{DrawQueue, RenderLayer, RenderOrder},
+        fx::tween::{Easing, Transition, Tween},
+        input::{Actions, ControlsMenu, InputAction},
         math::{
             aabb::Aabb,
             draw::{draw_rectangle_aabb, stroke_rectangle_aabb},
+            noise::hash_unit,
         },
+        prefab::{PrefabAccess, PrefabRegistry},
+        replay::ReplayState,
+        rng::Rng,
+        rules::GameStats,
+        save::SavedWorld,
+        spatial::{Spatial, SpatialSync},
         tile::{
+            autotile::{AutoTileLayout, AutoTileMask, AutoTileMaterial},
+            breaking::{TileBreaker, TileBroken},
             collider::{
-                Collider, InsideWorld, TrackedCollider, TrackedColliderChunk, WorldColliders,
+                Collider, CollisionLayers, InsideWorld, TrackedCollider, TrackedColliderChunk,
+                WorldColliders,
+            },
+            data::{
+                TileChanged, TileChunk, TileLayerConfig, TileLayers, TileWorld, WorldBounds,
+                WorldCreatedChunk,
+            },
+            edit_log::{TileEdit, TileEditLog},
+            generator::{
+                FloodPass, HillsGenerator, TileGenerator, UndergroundBiome, UndergroundPass,
             },
-            data::{TileChunk, TileLayerConfig, TileWorld, WorldCreatedChunk},
             kinematic::{
                 filter_tangible_actors, KinematicApi, TangibleMarker, TileColliderDescriptor,
+                TilePhysicsDescriptor,
             },
+            lighting::WorldLighting,
+            liquid::{LiquidMaterial, WorldLiquids},
             material::{BaseMaterialDescriptor, MaterialId, MaterialRegistry},
-            render::{RenderableWorld, SolidTileMaterial},
+            render::{
+                paint_atlas_image, FluidTileMaterial, RenderableWorld, SolidTileMaterial,
+                TexturedTileMaterial, TileAtlas,
+            },
+            schematic::Schematic,
+            tile_entity::{TileEntityCreated, TileEntityDescriptor},
+            visibility::{Viewer, WorldVisibility},
         },
+        time::RenderAlpha,
     },
-    util::arena::{spawn_entity, ObjOwner, RandomAccess, RandomEntityExt, SendsEvent},
+    util::arena::{spawn_entity, Obj, ObjOwner, RandomAccess, RandomEntityExt, SendsEvent},
+    Headless,
 };
 
 use super::{
-    camera::{ActiveCamera, VirtualCamera, VirtualCameraConstraints},
-    health::Health,
-    kinematic::{ColliderEvent, ColliderListens, ColliderMoves, Pos, Vel},
-    projectile::BulletSpawner,
+    camera::{ActiveCamera, CameraStack, CullExt, VirtualCamera, VirtualCameraConstraints},
+    checkpoint::CheckpointBundle,
+    controller::CharacterController,
+    damage::{DamageEvent, DamageKind, Hazard},
+    death::{ActorDied, Dead, Respawns},
+    effects::{StatusEffect, StatusEffectApplied, StatusEffects},
+    health::{Health, HealthChangeCause, HealthChanged},
+    inspector::Inspector,
+    inventory::{Inventory, Item},
+    kinematic::{ColliderDebug, ColliderEvent, ColliderMoves, Gravity, Pos, PrevPos, Vel},
+    platform::{MovingPlatform, PlatformBundle, PlatformRider},
+    portal::{Portal, PortalBundle},
+    projectile::BulletPool,
+    shadow::CastsShadow,
 };
 
 // === Systems === //
 
+const STONE_SPRITE: u32 = 0;
+const BRICK_SPRITE: u32 = 1;
+const GRASS_SPRITES: u32 = 2;
+
+const PLAYER_HEALTH: f32 = 20.;
+
+/// The number of ticks for which a freshly spawned player is protected from damage.
+const SPAWN_INVULNERABILITY: u32 = 180;
+
+/// The number of ticks for which a player ignores contact damage after being hit.
+const HIT_INVULNERABILITY: u32 = 45;
+
+/// The number of ticks a dead player waits before respawning.
+const RESPAWN_DELAY: u32 = 120;
+
+/// The amount of base health lost every time a player dies.
+const DEATH_PENALTY: f32 = 10.;
+
+/// The tile column of the checkpoint placed in the default world.
+const CHECKPOINT_TILE_X: i32 = 24;
+
+/// The [`SavedWorld`] id of the caverns, the second world of the default setup.
+const CAVERNS_WORLD: SavedWorld = SavedWorld(1);
+
+/// The tile column of the portals linking the default world with the caverns.
+const PORTAL_TILE_X: i32 = -12;
+
+const PORTAL_SIZE: Vec2 = Vec2::new(60., 100.);
+
+/// The camera trauma caused by every point of damage dealt to the player.
+const TRAUMA_PER_DAMAGE: f32 = 0.12;
+
+/// The camera zoom eased towards while the player is dead.
+const DEATH_ZOOM: f32 = 1.6;
+
+/// The number of ticks the camera takes to zoom in when the player dies and back out when they
+/// respawn.
+const DEATH_ZOOM_TICKS: u32 = 45;
+
+const HOTBAR_KEYS: [KeyCode; Inventory::SLOTS] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
 #[derive(Component, Default)]
 pub struct WorldState {
     focused_tile: Vec2,
@@ -53,43 +150,378 @@ pub struct WorldState {
 #[derive(Component, Default)]
 pub struct PlayerState {
     trail: VecDeque<Vec2>,
-    last_tile: Option<Vec2>,
+    last_health: f32,
+}
+
+/// A single frame of player input with the cursor already projected into world-space so that it
+/// can be replayed independently of the camera.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct InputFrame {
+    pub heading: Vec2,
+    pub stroke: Option<InputStroke>,
+
+    /// The hotbar slot selected this frame, if any.
+    pub select: Option<usize>,
+
+    /// The number of slots by which to move the hotbar selection.
+    pub scroll: i32,
+}
+
+impl InputFrame {
+    /// The farthest a stroke may reach from the player performing it.
+    pub const REACH: f32 = 1000.;
+
+    /// Makes a frame from an untrusted source safe to simulate for a player at `origin`: the
+    /// heading is clamped to unit length, the stroke to [`Self::REACH`] around `origin` and the
+    /// hotbar changes to the size of the hotbar. Returns `None` if the frame has non-finite values.
+    pub fn sanitize(mut self, origin: Vec2) -> Option<Self> {
+        if !self.heading.is_finite() {
+            return None;
+        }
+        self.heading = self.heading.clamp_length_max(1.);
+
+        if let Some(stroke) = &mut self.stroke {
+            if !stroke.from.is_finite() || !stroke.to.is_finite() {
+                return None;
+            }
+
+            stroke.from = origin + (stroke.from - origin).clamp_length_max(Self::REACH);
+            stroke.to = origin + (stroke.to - origin).clamp_length_max(Self::REACH);
+        }
+
+        let slots = Inventory::SLOTS as i32;
+        self.select = self.select.map(|slot| slot.min(Inventory::SLOTS - 1));
+        self.scroll = self.scroll.clamp(-slots, slots);
+
+        Some(self)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct InputStroke {
+    pub from: Vec2,
+    pub to: Vec2,
+    pub action: StrokeAction,
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum StrokeAction {
+    Mine,
+    Use,
 }
 
+/// The input of the local player. Input is sampled once per rendered frame into `pending` and
+/// handed to the simulation as `frame` at the start of every tick. Since a frame may run any number
+/// of ticks, one-off inputs such as hotbar selections are only handed to the first of them.
+#[derive(Debug, Default, Resource)]
+pub struct PlayerInput {
+    pub frame: InputFrame,
+    pending: InputFrame,
+}
+
+impl PlayerInput {
+    /// Hands the pending input to the simulation, keeping its held inputs around for the next tick.
+    pub fn take_pending(&mut self) {
+        self.frame = self.pending;
+        self.pending.select = None;
+        self.pending.scroll = 0;
+
+        // The next stroke continues from wherever this one ended.
+        if let Some(stroke) = &mut self.pending.stroke {
+            stroke.from = stroke.to;
+        }
+    }
+}
+
+/// The menus and tools which keep some of the player's input to themselves while they're open.
+#[derive(SystemParam)]
+pub struct InputFocus<'w> {
+    pub inspector: Res<'w, Inspector>,
+    pub controls_menu: Res<'w, ControlsMenu>,
+    pub collider_debug: Res<'w, ColliderDebug>,
+}
+
+/// The health bar's trailing segments, as percentages of the maximum health. These jump to the old
+/// health on every [`HealthChanged`] event and then ease towards the new health.
 #[derive(Component)]
-pub struct HealthAnimation(f32);
+pub struct HealthAnimation {
+    lost_from: Transition<f32>,
+    gained_from: Transition<f32>,
+}
+
+impl HealthAnimation {
+    /// The number of ticks it takes a trailing segment to catch up with the actual health.
+    pub const DURATION: u32 = 20;
+}
+
+impl Default for HealthAnimation {
+    fn default() -> Self {
+        let full = Transition::new(1., 1., Self::DURATION, Easing::CubicOut);
+        Self {
+            lost_from: full,
+            gained_from: full,
+        }
+    }
+}
+
+/// Damages players whenever they start touching this listener.
+#[derive(Debug, Copy, Clone, Component)]
+pub struct ContactDamage(pub f32);
+
+/// Makes a player ignore damage for a short while after every hit so that touching several damage
+/// sources at once doesn't drain all of its health.
+#[derive(Debug, Copy, Clone, Component)]
+pub struct HitInvulnerability {
+    /// The number of ticks for which the player is invulnerable after being hit.
+    pub duration: u32,
+    remaining: u32,
+}
+
+impl HitInvulnerability {
+    /// The number of ticks after a hit during which the player flashes white.
+    pub const FLASH_TICKS: u32 = 6;
+
+    /// The number of ticks in a single blink once the flash is over.
+    pub const BLINK_PERIOD: u32 = 8;
+
+    pub fn new(duration: u32) -> Self {
+        Self {
+            duration,
+            remaining: 0,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.remaining > 0
+    }
+
+    /// The number of ticks left until the player can be hurt again.
+    pub fn remaining(&self) -> u32 {
+        self.remaining
+    }
+
+    /// Advances the invulnerability window by a single tick.
+    pub fn tick(&mut self) {
+        self.remaining = self.remaining.saturating_sub(1);
+    }
+
+    /// Starts a new invulnerability window, replacing the current one.
+    pub fn trigger(&mut self) {
+        self.remaining = self.duration;
+    }
+
+    /// The color in which to draw a player whose regular color is `color`: white right after a hit
+    /// and blinking until the invulnerability wears off.
+    pub fn tint(&self, color: Color) -> Color {
+        if !self.is_active() {
+            return color;
+        }
+
+        if self.duration - self.remaining.min(self.duration) < Self::FLASH_TICKS {
+            WHITE
+        } else if self.remaining % Self::BLINK_PERIOD < Self::BLINK_PERIOD / 2 {
+            Color {
+                a: color.a * 0.3,
+                ..color
+            }
+        } else {
+            color
+        }
+    }
+}
+
+/// Drives a player with inputs received over the network instead of the local [`PlayerInput`].
+#[derive(Debug, Copy, Clone, Default, Component)]
+pub struct RemoteInput(pub InputFrame);
+
+/// Spawns a player at `pos` with `bundle` attached. The player is controlled by the local
+/// [`PlayerInput`] unless the bundle contains a [`RemoteInput`].
+pub fn spawn_player(world: Obj<TileWorld>, pos: Vec2, bundle: impl Bundle) -> Entity {
+    let player = spawn_entity((
+        Pos(pos),
+        Vel(Vec2::ONE),
+        InsideWorld(world),
+        Collider(Aabb::ZERO),
+        CollisionLayers::new(
+            CollisionLayers::PLAYERS,
+            CollisionLayers::TILES | CollisionLayers::PLATFORMS,
+        ),
+        ColliderMoves,
+        (
+            Gravity::default(),
+            CharacterController::default(),
+            PlatformRider::default(),
+        ),
+        PlayerState::default(),
+        Spatial::new_at(pos),
+        SpatialSync::FromPos,
+        CastsShadow,
+        (
+            Respawns {
+                delay: RESPAWN_DELAY,
+                invulnerability: SPAWN_INVULNERABILITY,
+            },
+            HitInvulnerability::new(HIT_INVULNERABILITY),
+        ),
+        (
+            Inventory::default(),
+            TileBreaker::default(),
+            Viewer::default(),
+        ),
+        bundle,
+    ));
+    player.insert(TangibleMarker);
+
+    let health = player.insert(Health::new_full(PLAYER_HEALTH));
+    let effects = player.insert(StatusEffects::new(health));
+    effects.apply(StatusEffect::Invulnerable, SPAWN_INVULNERABILITY);
+
+    player
+}
+
+/// Paints a texel of the grass sprite for `mask`. Grass grows on exposed tops while other exposed
+/// edges and inner corners get a dark dirt border.
+fn paint_grass_texel(mask: AutoTileMask, texel: UVec2) -> Color {
+    const GRASS_DEPTH: u32 = 4;
+    const BORDER: u32 = 2;
+
+    let shade = 0.85 + 0.15 * hash_unit(GRASS_SPRITES, texel.as_ivec2());
+    let (x, y) = (texel.x, texel.y);
+    let (near_left, near_right) = (x < BORDER, x >= 16 - BORDER);
+    let (near_top, near_bottom) = (y < BORDER, y >= 16 - BORDER);
+
+    if !mask.has(AutoTileMask::TOP) && y < GRASS_DEPTH {
+        return Color::new(0.3 * shade, 0.65 * shade, 0.2 * shade, 1.);
+    }
+
+    let is_border = (!mask.has(AutoTileMask::LEFT) && near_left)
+        || (!mask.has(AutoTileMask::RIGHT) && near_right)
+        || (!mask.has(AutoTileMask::BOTTOM) && near_bottom)
+        || (!mask.has(AutoTileMask::TOP_LEFT) && near_top && near_left)
+        || (!mask.has(AutoTileMask::TOP_RIGHT) && near_top && near_right)
+        || (!mask.has(AutoTileMask::BOTTOM_LEFT) && near_bottom && near_left)
+        || (!mask.has(AutoTileMask::BOTTOM_RIGHT) && near_bottom && near_right);
+
+    if is_border {
+        Color::new(0.3 * shade, 0.2 * shade, 0.12 * shade, 1.)
+    } else {
+        Color::new(0.45 * shade, 0.3 * shade, 0.18 * shade, 1.)
+    }
+}
 
 pub fn sys_create_local_player(
     mut rand: RandomAccess<(
-        &mut BaseMaterialDescriptor,
-        &mut Health,
+        (
+            &mut AutoTileMaterial,
+            &mut BaseMaterialDescriptor,
+            &mut FluidTileMaterial,
+            &mut LiquidMaterial,
+            &mut MaterialRegistry,
+            &mut SolidTileMaterial,
+            &mut TexturedTileMaterial,
+            &mut TileColliderDescriptor,
+            &mut TilePhysicsDescriptor,
+        ),
+        (&mut StatusEffects, SendsEvent<StatusEffectApplied>),
+        PrefabAccess,
         &mut KinematicApi,
-        &mut MaterialRegistry,
-        &mut SolidTileMaterial,
-        &mut TangibleMarker,
         &mut TileChunk,
-        &mut TileColliderDescriptor,
+        &mut TileGenerator,
+        &mut TileLayers,
         &mut TileWorld,
         &mut VirtualCamera,
         &mut WorldColliders,
         SendsEvent<WorldCreatedChunk>,
     )>,
-    mut camera: ResMut<ActiveCamera>,
+    mut cameras: ResMut<CameraStack>,
+    prefabs: Res<PrefabRegistry>,
+    rng: Res<Rng>,
+    headless: Option<Res<Headless>>,
 ) {
     rand.provide(|| {
+        // Every generator's seed is derived from the world seed so that `--seed` picks the world.
+        let world_seed = rng.seed();
+
         // Spawn world
         let world = spawn_entity((
-            HealthAnimation(1.),
+            HealthAnimation::default(),
             RenderableWorld::default(),
+            WorldLighting::default(),
+            WorldVisibility::default(),
+            WorldLiquids::default(),
+            ParallaxBackground {
+                layers: vec![
+                    // Sky
+                    ParallaxLayer::gradient(
+                        0.9,
+                        -1500.,
+                        0.,
+                        Color::new(0.05, 0.07, 0.2, 1.),
+                        Color::new(0.45, 0.65, 0.85, 1.),
+                    ),
+                    // Distant earth
+                    ParallaxLayer::gradient(
+                        0.5,
+                        0.,
+                        600.,
+                        Color::new(0.25, 0.2, 0.15, 0.),
+                        Color::new(0.15, 0.12, 0.1, 1.),
+                    ),
+                ],
+            },
+            SavedWorld::MAIN,
             WorldState::default(),
         ));
 
         // Setup camera
-        camera.camera = Some(world.insert(VirtualCamera::new(
-            Affine2::IDENTITY,
-            Aabb::new_centered(Vec2::ZERO, Vec2::splat(1000.)),
-            VirtualCameraConstraints::default().keep_visible_area(Vec2::new(1000., 1000.)),
-        )));
+        cameras.push(
+            world.insert(VirtualCamera::new(
+                Affine2::IDENTITY,
+                Aabb::new_centered(Vec2::ZERO, Vec2::splat(1000.)),
+                VirtualCameraConstraints::default()
+                    .keep_visible_area(Vec2::new(1000., 1000.))
+                    .with_smoothing(0.15)
+                    .with_deadzone(Vec2::new(120., 80.)),
+            )),
+        );
+
+        // Setup tile atlas. Headless apps can't create textures so their materials go untextured.
+        let atlas = headless.is_none().then(|| {
+            TileAtlas::from_image(
+                &paint_atlas_image(
+                    GRASS_SPRITES + AutoTileLayout::Blob.sprite_count(),
+                    UVec2::splat(16),
+                    |sprite, texel| {
+                        if sprite >= GRASS_SPRITES {
+                            let mask = AutoTileLayout::Blob.mask(sprite - GRASS_SPRITES);
+                            return paint_grass_texel(mask, texel);
+                        }
+
+                        let shade = 0.4 + 0.15 * hash_unit(sprite, texel.as_ivec2());
+                        let is_mortar = match sprite {
+                            STONE_SPRITE => false,
+                            _ => {
+                                let row = texel.y / 8;
+                                let offset = if row % 2 == 0 { 0 } else { 4 };
+                                texel.y % 8 == 0 || (texel.x + offset) % 8 == 0
+                            }
+                        };
+
+                        if is_mortar {
+                            Color::new(0.2, 0.2, 0.2, 1.)
+                        } else {
+                            Color::new(shade, shade, shade, 1.)
+                        }
+                    },
+                ),
+                UVec2::splat(16),
+            )
+        });
+        let insert_sprite = |descriptor: Entity, sprite: u32, tint: Color| {
+            if let Some(atlas) = &atlas {
+                descriptor.insert(atlas.material(sprite, tint));
+            }
+        };
 
         // Setup material registry
         let mut registry = world.insert(MaterialRegistry::default());
@@ -97,28 +529,211 @@ pub fn sys_create_local_player(
         let grass = registry.register("game:grass", {
             let descriptor = spawn_entity(());
             descriptor.insert(SolidTileMaterial { color: GREEN });
+            if let Some(atlas) = &atlas {
+                descriptor.insert(AutoTileMaterial {
+                    atlas: atlas.clone(),
+                    layout: AutoTileLayout::Blob,
+                    first_sprite: GRASS_SPRITES,
+                    tint: WHITE,
+                    connect_to_solids: true,
+                });
+            }
             descriptor.insert(TileColliderDescriptor::new([Aabb::ZERO_TO_ONE]));
             descriptor
         });
         let stone = registry.register("game:stone", {
             let descriptor = spawn_entity(());
             descriptor.insert(SolidTileMaterial { color: GRAY });
+            insert_sprite(descriptor, STONE_SPRITE, WHITE);
+            descriptor.insert(TileColliderDescriptor::new([Aabb::ZERO_TO_ONE]));
+            descriptor
+        });
+        let stone_wall = registry.register("game:stone_wall", {
+            let descriptor = spawn_entity(());
+            descriptor.insert(SolidTileMaterial { color: DARKGRAY });
+            insert_sprite(descriptor, BRICK_SPRITE, DARKGRAY);
+            descriptor
+        });
+        let water = registry.register("game:water", {
+            let descriptor = spawn_entity(());
+            descriptor.insert(FluidTileMaterial {
+                color: Color::new(0.2, 0.4, 0.9, 0.6),
+                wave_amplitude: 0.2,
+                wave_length: 3.,
+                wave_speed: 1.5,
+            });
+            descriptor.insert(LiquidMaterial {
+                buoyancy: 1.2,
+                drag: 0.08,
+            });
+            descriptor
+        });
+        let brick = registry.register("game:brick", {
+            let descriptor = spawn_entity(());
+            descriptor.insert(SolidTileMaterial { color: BROWN });
+            insert_sprite(descriptor, BRICK_SPRITE, BEIGE);
+            descriptor.insert(TileColliderDescriptor::new([Aabb::ZERO_TO_ONE]));
+            descriptor
+        });
+        let ice = registry.register("game:ice", {
+            let descriptor = spawn_entity(());
+            descriptor.insert(SolidTileMaterial { color: SKYBLUE });
+            descriptor.insert(TileColliderDescriptor::new([Aabb::ZERO_TO_ONE]));
+            descriptor.insert(TilePhysicsDescriptor {
+                friction: 0.005,
+                bounciness: 0.,
+            });
+            descriptor
+        });
+        let rubber = registry.register("game:rubber", {
+            let descriptor = spawn_entity(());
+            descriptor.insert(SolidTileMaterial { color: PINK });
+            descriptor.insert(TileColliderDescriptor::new([Aabb::ZERO_TO_ONE]));
+            descriptor.insert(TilePhysicsDescriptor {
+                friction: 0.2,
+                bounciness: 0.8,
+            });
+            descriptor
+        });
+        let iron_ore = registry.register("game:iron_ore", {
+            let descriptor = spawn_entity(());
+            descriptor.insert(SolidTileMaterial { color: ORANGE });
+            insert_sprite(descriptor, STONE_SPRITE, ORANGE);
             descriptor.insert(TileColliderDescriptor::new([Aabb::ZERO_TO_ONE]));
             descriptor
         });
+        let gold_ore = registry.register("game:gold_ore", {
+            let descriptor = spawn_entity(());
+            descriptor.insert(SolidTileMaterial { color: GOLD });
+            insert_sprite(descriptor, STONE_SPRITE, GOLD);
+            descriptor.insert(TileColliderDescriptor::new([Aabb::ZERO_TO_ONE]));
+            descriptor
+        });
+
+        registry
+            .lookup(gold_ore)
+            .get::<BaseMaterialDescriptor>()
+            .light = 8;
+
+        // Setup hardness
+        for (material, hardness) in [
+            (grass, 5.),
+            (stone, 20.),
+            (stone_wall, 30.),
+            (brick, 30.),
+            (ice, 10.),
+            (rubber, 15.),
+            (iron_ore, 40.),
+            (gold_ore, 50.),
+        ] {
+            registry
+                .lookup(material)
+                .get::<BaseMaterialDescriptor>()
+                .hardness = hardness;
+        }
 
         // Setup world
-        let world_data = world.insert(TileWorld::new(TileLayerConfig {
+        let world_data = world.insert(
+            TileWorld::new(TileLayerConfig {
+                offset: Vec2::ZERO,
+                size: 50.,
+            })
+            .with_bounds(WorldBounds {
+                min_y: Some(-60),
+                max_y: Some(120),
+                ..WorldBounds::INFINITE
+            }),
+        );
+        let world_colliders = world.insert(WorldColliders::new(world_data));
+
+        let ruin = Schematic::from_rows(
+            &[
+                "#########",
+                "#.......#",
+                "#.......#",
+                "#..#.#..#",
+                "####.####",
+            ],
+            &[('#', brick), ('.', MaterialId::AIR)],
+        );
+        let frozen_ruin = Schematic::from_rows(
+            &[
+                "#########",
+                "#.......#",
+                "#.......#",
+                "#r.....r#",
+                "iiiiiiiii",
+            ],
+            &[
+                ('#', brick),
+                ('i', ice),
+                ('r', rubber),
+                ('.', MaterialId::AIR),
+            ],
+        );
+
+        let generator = world.insert(
+            TileGenerator::new(HillsGenerator {
+                seed: 0,
+                base_height: 10,
+                amplitude: 8.,
+                wavelength: 30.,
+                surface: grass,
+                ground: stone,
+            })
+            .with_pass(UndergroundPass {
+                seed: 0,
+                biomes: vec![
+                    UndergroundBiome {
+                        cave_fill: 0.45,
+                        cave_min_depth: 4,
+                        ore: iron_ore,
+                        ore_chance: 0.5,
+                        ore_vein_length: 12,
+                        ruins: vec![ruin.clone()],
+                        ruin_chance: 0.4,
+                    },
+                    UndergroundBiome {
+                        cave_fill: 0.35,
+                        cave_min_depth: 8,
+                        ore: gold_ore,
+                        ore_chance: 0.3,
+                        ore_vein_length: 8,
+                        ruins: vec![ruin, frozen_ruin],
+                        ruin_chance: 0.1,
+                    },
+                ],
+                biome_width: 120.,
+                cave_iterations: 4,
+            })
+            .with_pass(FloodPass {
+                level: 14,
+                fluid: water,
+            })
+            .with_seed(world_seed, SavedWorld::MAIN.0),
+        );
+
+        // Setup background layer
+        let background = spawn_entity(());
+        let background_data = background.insert(TileWorld::new(TileLayerConfig {
             offset: Vec2::ZERO,
             size: 50.,
         }));
-        let world_colliders = world.insert(WorldColliders::new(world_data));
+        // The background shares the world's salt so that its hills follow the world's.
+        background.insert(
+            TileGenerator::new(HillsGenerator {
+                seed: 0,
+                base_height: 11,
+                amplitude: 8.,
+                wavelength: 30.,
+                surface: stone_wall,
+                ground: stone_wall,
+            })
+            .with_seed(world_seed, SavedWorld::MAIN.0),
+        );
 
-        for x in 0..500 {
-            let v = (x as f32 / 10.).sin();
-            world_data.set_tile(IVec2::new(x, (v * 10.) as i32), grass);
-            world_data.set_tile(IVec2::new(x, (v * 10.) as i32 - 20), stone);
-        }
+        let mut layers = world.insert(TileLayers::new(world_data));
+        layers.insert(TileLayers::BACKGROUND, background_data);
 
         world.insert(KinematicApi::new(world_data, registry, world_colliders));
 
@@ -126,73 +741,298 @@ pub fn sys_create_local_player(
         world.insert(Health::new_full(50.));
 
         // Spawn player
-        let player = spawn_entity((
-            Pos(Vec2::new(0., -50.)),
-            Vel(Vec2::ONE),
+        spawn_player(world_data, Vec2::new(0., -50.), ());
+
+        prefabs.spawn_prefab(
+            "spawn_point",
+            (Pos(Vec2::new(0., -50.)), InsideWorld(world_data)),
+        );
+
+        // Setup the caverns, which share the materials of the main world
+        let caverns = spawn_entity((
+            RenderableWorld::default(),
+            WorldLighting::default(),
+            WorldVisibility::default(),
+            WorldLiquids::default(),
+            ParallaxBackground {
+                layers: vec![ParallaxLayer::gradient(
+                    0.9,
+                    -1500.,
+                    600.,
+                    Color::new(0.08, 0.06, 0.1, 1.),
+                    Color::new(0.02, 0.02, 0.03, 1.),
+                )],
+            },
+            CAVERNS_WORLD,
+            WorldState::default(),
+        ));
+
+        caverns.insert(VirtualCamera::new(
+            Affine2::IDENTITY,
+            Aabb::new_centered(Vec2::ZERO, Vec2::splat(1000.)),
+            VirtualCameraConstraints::default()
+                .keep_visible_area(Vec2::new(1000., 1000.))
+                .with_smoothing(0.15)
+                .with_deadzone(Vec2::new(120., 80.)),
+        ));
+
+        let caverns_registry = caverns.insert((*registry).clone());
+        let caverns_data = caverns.insert(
+            TileWorld::new(TileLayerConfig {
+                offset: Vec2::ZERO,
+                size: 50.,
+            })
+            .with_bounds(WorldBounds {
+                min_y: Some(-60),
+                max_y: Some(120),
+                ..WorldBounds::INFINITE
+            }),
+        );
+        let caverns_colliders = caverns.insert(WorldColliders::new(caverns_data));
+
+        let caverns_generator = caverns.insert(
+            TileGenerator::new(HillsGenerator {
+                seed: 0,
+                base_height: 4,
+                amplitude: 3.,
+                wavelength: 12.,
+                surface: stone,
+                ground: stone,
+            })
+            .with_pass(UndergroundPass {
+                seed: 0,
+                biomes: vec![UndergroundBiome {
+                    cave_fill: 0.5,
+                    cave_min_depth: 2,
+                    ore: gold_ore,
+                    ore_chance: 0.6,
+                    ore_vein_length: 10,
+                    ruins: Vec::new(),
+                    ruin_chance: 0.,
+                }],
+                biome_width: 120.,
+                cave_iterations: 4,
+            })
+            .with_seed(world_seed, CAVERNS_WORLD.0),
+        );
+
+        caverns.insert(TileLayers::new(caverns_data));
+        caverns.insert(KinematicApi::new(
+            caverns_data,
+            caverns_registry,
+            caverns_colliders,
+        ));
+
+        // Link the two worlds with a pair of portals standing on their surfaces. Each portal lets
+        // travelers out on top of the other one.
+        let portal_center = |world: Obj<TileWorld>, generator: Obj<TileGenerator>| {
+            let config = world.config();
+            let surface = generator.surface_height(PORTAL_TILE_X).unwrap_or(0);
+            Vec2::new(
+                (PORTAL_TILE_X as f32 + 0.5) * config.size,
+                surface as f32 * config.size - PORTAL_SIZE.y / 2.,
+            )
+        };
+        let main_portal = portal_center(world_data, generator);
+        let caverns_portal = portal_center(caverns_data, caverns_generator);
+
+        spawn_entity(PortalBundle::new(
             InsideWorld(world_data),
-            Collider(Aabb::ZERO),
-            ColliderMoves,
-            PlayerState::default(),
+            Aabb::new_centered(main_portal, PORTAL_SIZE),
+            Portal::new(caverns_data, caverns_portal),
+        ));
+        spawn_entity(PortalBundle::new(
+            InsideWorld(caverns_data),
+            Aabb::new_centered(caverns_portal, PORTAL_SIZE),
+            Portal::new(world_data, main_portal),
         ));
-        player.insert(TangibleMarker);
 
-        spawn_entity((
-            Pos(Vec2::new(-500., -200.)),
+        // Spawn a checkpoint on the surface a short walk away from the spawn point
+        let config = world_data.config();
+        let surface = generator.surface_height(CHECKPOINT_TILE_X).unwrap_or(0);
+        spawn_entity(CheckpointBundle::new(
             InsideWorld(world_data),
-            BulletSpawner,
+            Vec2::new(
+                (CHECKPOINT_TILE_X as f32 + 0.5) * config.size,
+                surface as f32 * config.size - 20.,
+            ),
         ));
 
-        // Spawn listener
-        spawn_entity((
+        prefabs.spawn_prefab(
+            "bullet_spawner",
+            (Pos(Vec2::new(-500., -200.)), InsideWorld(world_data)),
+        );
+
+        // Spawn an elevator
+        spawn_entity(PlatformBundle::new(
             InsideWorld(world_data),
-            Collider(Aabb::new(100., 100., 500., 500.)),
-            ColliderListens::default(),
+            MovingPlatform::new(
+                Vec2::new(150., 20.),
+                vec![Vec2::new(300., -100.), Vec2::new(300., -700.)],
+                3.,
+            )
+            .with_wait(60),
         ));
+
+        // Spawn enemy waves
+        for pos in [Vec2::new(600., -300.), Vec2::new(-800., -300.)] {
+            prefabs.spawn_prefab("wave_spawn_point", (Pos(pos), InsideWorld(world_data)));
+        }
+
+        // Spawn listener
+        prefabs.spawn_prefab("damage_zone", InsideWorld(world_data));
+    });
+}
+
+pub fn sys_sample_player_input(
+    mut rand: RandomAccess<&VirtualCamera>,
+    mut input: ResMut<PlayerInput>,
+    camera: Res<ActiveCamera>,
+    replay: Res<ReplayState>,
+    focus: InputFocus,
+    actions: Actions,
+) {
+    // The replay viewer and playback feed recorded inputs instead.
+    if replay.is_feeding_input() {
+        return;
+    }
+
+    // The controls menu keeps clicks and keys to itself while it's open.
+    if focus.controls_menu.has_focus() {
+        input.pending.heading = Vec2::ZERO;
+        input.pending.stroke = None;
+        return;
+    }
+
+    rand.provide(|| {
+        input.pending.heading = actions.movement();
+
+        // Handle hotbar selection. The number keys are used by other menus while they're open.
+        // Selections and scrolls accumulate until a tick takes them.
+        if !focus.collider_debug.menu_open {
+            if let Some(slot) = HOTBAR_KEYS.iter().position(|&key| is_key_pressed(key)) {
+                input.pending.select = Some(slot);
+            }
+        }
+
+        let wheel = mouse_wheel().1;
+        if wheel != 0. {
+            input.pending.scroll -= wheel.signum() as i32;
+        }
+        if actions.is_just_pressed(InputAction::NextItem) {
+            input.pending.scroll += 1;
+        }
+        if actions.is_just_pressed(InputAction::PreviousItem) {
+            input.pending.scroll -= 1;
+        }
+
+        // Determine the tile over which the player's cursor is hovering. Clicks are reserved for
+        // selecting entities while the inspector is open.
+        let action = if focus.inspector.enabled {
+            None
+        } else if actions.is_down(InputAction::BreakTile) {
+            Some(StrokeAction::Mine)
+        } else if actions.is_down(InputAction::UseItem) {
+            Some(StrokeAction::Use)
+        } else {
+            None
+        };
+
+        let (Some(action), Some(camera)) = (action, camera.camera) else {
+            input.pending.stroke = None;
+            return;
+        };
+
+        // Strokes which no tick has taken yet are extended rather than replaced.
+        let to = camera.project(Vec2::from(mouse_position()));
+        let from = input.pending.stroke.map_or(to, |stroke| stroke.from);
+        input.pending.stroke = Some(InputStroke { from, to, action });
     });
 }
 
+pub fn sys_take_player_input(mut input: ResMut<PlayerInput>) {
+    input.take_pending();
+}
+
 pub fn sys_handle_controls(
     mut rand: RandomAccess<(
-        &MaterialRegistry,
+        (
+            &MaterialRegistry,
+            &BaseMaterialDescriptor,
+            &TileColliderDescriptor,
+            &TileEntityDescriptor,
+        ),
         &mut KinematicApi,
         &mut TileChunk,
         &mut TileWorld,
-        &mut VirtualCamera,
         &mut WorldColliders,
-        &TangibleMarker,
-        &TileColliderDescriptor,
+        &mut TangibleMarker,
         &TrackedCollider,
         &TrackedColliderChunk,
+        SendsEvent<TileBroken>,
+        SendsEvent<TileEntityCreated>,
+        SendsEvent<TileChanged>,
         SendsEvent<WorldCreatedChunk>,
     )>,
-    mut query: Query<(&InsideWorld, &Pos, &mut Vel, &mut PlayerState)>,
+    mut query: Query<
+        (
+            Entity,
+            &InsideWorld,
+            &Pos,
+            &mut Vel,
+            &mut PlayerState,
+            &mut Inventory,
+            &mut TileBreaker,
+            Option<&mut CharacterController>,
+            Option<&RemoteInput>,
+        ),
+        Without<Dead>,
+    >,
+    input: Res<PlayerInput>,
+    mut stats: ResMut<GameStats>,
+    mut edits: ResMut<TileEditLog>,
+    mut pool: ResMut<BulletPool>,
+    mut commands: Commands,
 ) {
     rand.provide(|| {
-        let mut heading = Vec2::ZERO;
-        if is_key_down(KeyCode::A) {
-            heading += Vec2::NEG_X;
-        }
-        if is_key_down(KeyCode::D) {
-            heading += Vec2::X;
-        }
-        if is_key_down(KeyCode::W) {
-            heading += Vec2::NEG_Y;
-        }
-        if is_key_down(KeyCode::S) {
-            heading += Vec2::Y;
-        }
-
-        heading = heading.normalize_or_zero();
+        for (
+            me,
+            &InsideWorld(world),
+            pos,
+            mut vel,
+            mut player,
+            mut inventory,
+            mut breaker,
+            controller,
+            remote,
+        ) in query.iter_mut()
+        {
+            let InputFrame {
+                heading,
+                stroke,
+                select,
+                scroll,
+            } = remote.map_or(input.frame, |remote| remote.0);
 
-        for (&InsideWorld(world), pos, mut vel, mut player) in query.iter_mut() {
             let config = world.config();
-            let camera = world.entity().get::<VirtualCamera>();
             let registry = world.entity().get::<MaterialRegistry>();
             let mut kinematics = world.entity().get::<KinematicApi>();
 
             // Update heading vector
-            vel.0 += heading;
-            vel.0 *= 0.98;
+            match controller {
+                Some(mut controller) => controller.set_input_from_heading(heading),
+                None => {
+                    vel.0 += heading;
+                    vel.0 *= 0.98;
+                }
+            }
+
+            // Update hotbar
+            if let Some(slot) = select {
+                inventory.select(slot);
+            }
+            inventory.scroll(scroll);
+            inventory.tick();
 
             // Update trail
             player.trail.push_front(pos.0);
@@ -200,104 +1040,285 @@ pub fn sys_handle_controls(
                 player.trail.pop_back();
             }
 
-            // Determine the tile over which the player's cursor is hovering.
-            let dest = Vec2::from(mouse_position());
-            let dest = camera.project(dest);
+            // Only the local player's edits can be undone. Every stroke is undone as a whole.
+            let is_local = remote.is_none();
 
-            let src = player.last_tile.unwrap_or(dest);
-            player.last_tile = Some(dest);
+            let Some(InputStroke { from, to, action }) = stroke else {
+                breaker.reset();
+                if is_local {
+                    edits.commit();
+                }
+                continue;
+            };
 
-            if is_mouse_button_down(MouseButton::Left) {
-                cbit! {
-                    for pos in config.step_ray_tiles(src, dest) {
-                        world.set_tile(pos, MaterialId::AIR);
+            match action {
+                StrokeAction::Mine => {
+                    if let Some((pos, material)) = breaker.mine(me, world, from, to) {
+                        stats.bump("tiles_mined", 1);
+
+                        if is_local {
+                            edits.record(TileEdit {
+                                world,
+                                pos,
+                                old: material,
+                                new: MaterialId::AIR,
+                            });
+                        }
                     }
                 }
-            } else if is_mouse_button_down(MouseButton::Right) {
-                cbit! {
-                    for pos in config.step_ray_tiles(src, dest) {
-                        let place_aabb = config
-                            .tile_to_actor_rect(pos)
-                            .shrink(Vec2::splat(0.01));
-
-                        if kinematics.has_colliders_in(place_aabb, filter_tangible_actors) {
-                            continue;
-                        }
+                StrokeAction::Use => {
+                    breaker.reset();
 
-                        if world.tile(pos) != MaterialId::AIR {
-                            continue;
-                        }
+                    if let Some(weapon) = inventory.try_fire() {
+                        weapon.fire(&mut pool, &mut commands, InsideWorld(world), pos.0, to);
+                        continue;
+                    }
 
-                        world.set_tile(pos, registry.lookup_by_name("game:stone").unwrap());
+                    if let Some(weapon) = inventory.try_swing() {
+                        weapon.swing(me, InsideWorld(world), pos.0, to);
+                        continue;
+                    }
+
+                    let Some(Item::Tile(material)) = inventory.selected_item() else {
+                        continue;
+                    };
+
+                    let Some(material) = registry.lookup_by_name(material) else {
+                        continue;
+                    };
+
+                    cbit! {
+                        for pos in config.step_ray_tiles(from, to) {
+                            let place_aabb = config
+                                .tile_to_actor_rect(pos)
+                                .shrink(Vec2::splat(0.01));
+
+                            if kinematics.has_colliders_in(
+                                place_aabb,
+                                !CollisionLayers::TILES,
+                                filter_tangible_actors,
+                            ) {
+                                continue;
+                            }
+
+                            if world.tile(pos) != MaterialId::AIR {
+                                continue;
+                            }
+
+                            if !inventory.consume_selected() {
+                                break;
+                            }
+
+                            if is_local {
+                                edits.set_tile(world, pos, material);
+                            } else {
+                                world.set_tile(pos, material);
+                            }
+                        }
                     }
                 }
-            } else {
-                player.last_tile = None;
             }
         }
     });
 }
 
-pub fn sys_handle_damage(
-    mut rand: RandomAccess<(&TileWorld, &mut Health)>,
-    mut query: Query<&InsideWorld, With<PlayerState>>,
+pub fn sys_send_contact_damage(
+    players: Query<(), With<PlayerState>>,
+    sources: Query<(&ContactDamage, Has<Hazard>)>,
     mut events: EventReader<ColliderEvent>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    for event in events.read() {
+        if !event.entered || !players.contains(event.other) {
+            continue;
+        }
+
+        let Ok((&ContactDamage(amount), hazard)) = sources.get(event.listener) else {
+            continue;
+        };
+
+        damage.send(DamageEvent {
+            source: event.listener,
+            target: event.other,
+            kind: if hazard {
+                DamageKind::Environmental
+            } else {
+                DamageKind::Contact
+            },
+            amount,
+        });
+    }
+}
+
+pub fn sys_apply_death_penalty(
+    mut rand: RandomAccess<(&TileWorld, &mut Health, SendsEvent<HealthChanged>)>,
+    mut query: Query<&InsideWorld, With<PlayerState>>,
+    mut events: EventReader<ActorDied>,
 ) {
     rand.provide(|| {
         for event in events.read() {
-            if !event.entered {
-                continue;
-            }
-
-            let Ok(&InsideWorld(world)) = query.get_mut(event.other) else {
+            let Ok(&InsideWorld(world)) = query.get_mut(event.entity) else {
                 continue;
             };
 
-            world.entity().get::<Health>().change_health(-2.);
+            if let Some(base) = world.entity().try_get::<Health>() {
+                base.damage(DEATH_PENALTY, HealthChangeCause::DeathPenalty);
+            }
         }
     });
 }
 
 pub fn sys_focus_camera_on_player(
-    mut query: Query<(&InsideWorld, &Pos), With<PlayerState>>,
-    mut rand: RandomAccess<(&mut TileWorld, &mut VirtualCamera)>,
+    mut query: Query<
+        (
+            &InsideWorld,
+            &Pos,
+            &mut PlayerState,
+            Option<&ObjOwner<Health>>,
+        ),
+        Without<RemoteInput>,
+    >,
+    mut rand: RandomAccess<(&mut TileWorld, &mut VirtualCamera, &Health)>,
+    mut cameras: ResMut<CameraStack>,
+    replay: Res<ReplayState>,
 ) {
     rand.provide(|| {
-        let Some((&InsideWorld(world), pos)) = query.iter_mut().next() else {
+        let Some((&InsideWorld(world), pos, mut state, health)) = query.iter_mut().next() else {
             return;
         };
 
-        world
-            .entity()
-            .get::<VirtualCamera>()
-            .set_transform(Affine2::from_translation(pos.0));
+        let mut camera = world.entity().get::<VirtualCamera>();
+
+        // Switch the primary viewport over to the player's world after they go through a portal.
+        if let Some(primary) = cameras.primary().filter(|&primary| primary != camera) {
+            cameras.replace(primary, camera);
+            camera.set_transform(Affine2::from_translation(pos.0));
+            camera.reset_follow();
+        }
+
+        // Keep the camera from showing anything outside of the world.
+        let bounds = world.bounds();
+        camera.constraints_mut().bounds =
+            (!bounds.is_infinite()).then(|| bounds.actor_rect(world.config()));
+
+        // Shake the camera whenever the player gets hurt.
+        if let Some(&ObjOwner(health)) = health {
+            let lost = state.last_health - health.health();
+            if lost > 0. {
+                camera.add_trauma(lost * TRAUMA_PER_DAMAGE);
+            }
+            state.last_health = health.health();
+        }
+
+        if replay.is_following_player() {
+            camera.follow(pos.0);
+        } else {
+            camera.reset_follow();
+        }
+    });
+}
+
+/// Zooms the camera in on the local player while they're dead and back out once they respawn.
+pub fn sys_zoom_camera_on_death(
+    mut rand: RandomAccess<(&TileWorld, &VirtualCamera)>,
+    query: Query<(&InsideWorld, Has<Dead>), (With<PlayerState>, Without<RemoteInput>)>,
+    mut was_dead: Local<bool>,
+    mut commands: Commands,
+) {
+    rand.provide(|| {
+        let Some((&InsideWorld(world), is_dead)) = query.iter().next() else {
+            return;
+        };
+
+        // Compared against the previous tick rather than read from death events so that seeking a
+        // replay across a death zooms the camera as well.
+        if is_dead == *was_dead {
+            return;
+        }
+        *was_dead = is_dead;
+
+        let zoom = if is_dead {
+            DEATH_ZOOM
+        } else {
+            VirtualCameraConstraints::default().zoom
+        };
+
+        // Replaces any zoom still in progress, starting from wherever it got to.
+        let from = world.map_entity::<VirtualCamera>().constraints().zoom;
+        commands
+            .entity(world.entity())
+            .insert(Tween::CameraZoom(Transition::new(
+                from,
+                zoom,
+                DEATH_ZOOM_TICKS,
+                Easing::CubicInOut,
+            )));
     });
 }
 
 pub fn sys_render_players(
-    mut rand: RandomAccess<(&TileWorld, &mut VirtualCamera)>,
-    mut query: Query<(&Pos, &PlayerState)>,
+    mut rand: RandomAccess<&Health>,
+    query: Query<
+        (
+            &InsideWorld,
+            &Pos,
+            Option<&PrevPos>,
+            &PlayerState,
+            Option<&ObjOwner<Health>>,
+            Option<&HitInvulnerability>,
+            Option<&RenderLayer>,
+            Option<&RenderOrder>,
+        ),
+        Without<Dead>,
+    >,
+    mut draws: ResMut<DrawQueue>,
+    alpha: Res<RenderAlpha>,
     camera: Res<ActiveCamera>,
 ) {
-    let _guard = camera.apply();
-
     rand.provide(|| {
-        for (pos, player) in query.iter_mut() {
-            // Draw player
-            for (i, &trail) in player.trail.iter().rev().enumerate() {
-                draw_circle(
-                    trail.x,
-                    trail.y,
-                    20.,
-                    Color::from_vec(
-                        DARKPURPLE
-                            .to_vec()
-                            .lerp(RED.to_vec(), i as f32 / player.trail.len() as f32),
-                    ),
-                );
-            }
+        let visible = query.iter().culled(
+            &camera,
+            |&(&InsideWorld(world), &Pos(pos), _, player, ..)| {
+                // Account for the trail and the health bar above the player.
+                let (min, max) = player.trail.iter().fold((pos, pos), |(min, max), &trail| {
+                    (min.min(trail), max.max(trail))
+                });
+
+                (world, Aabb { min, max }.grow(Vec2::splat(40.)))
+            },
+        );
+
+        for (_, &Pos(pos), prev, player, health, iframes, layer, order) in visible {
+            let pos = PrevPos::interpolate(prev, pos, *alpha);
+            let trail = player.trail.iter().rev().copied().collect::<Vec<_>>();
+            let health = health.map(|&ObjOwner(health)| health.percentage());
+            let color = iframes.map_or(RED, |iframes| iframes.tint(RED));
+
+            let layer = layer.copied().unwrap_or(RenderLayer::Actors);
+            draws.push(layer, RenderOrder::key_of(order, pos.y), move || {
+                // Draw player
+                for (i, &trail_pos) in trail.iter().enumerate() {
+                    draw_circle(
+                        trail_pos.x,
+                        trail_pos.y,
+                        20.,
+                        Color::from_vec(
+                            DARKPURPLE
+                                .to_vec()
+                                .lerp(RED.to_vec(), i as f32 / trail.len() as f32),
+                        ),
+                    );
+                }
 
-            draw_circle(pos.0.x, pos.0.y, 20., RED);
+                draw_circle(pos.x, pos.y, 20., color);
+
+                // Draw health
+                if let Some(health) = health {
+                    let aabb = Aabb::new_centered(pos - Vec2::new(0., 35.), Vec2::new(40., 5.));
+                    draw_rectangle_aabb(aabb, RED);
+                    draw_rectangle_aabb(aabb.with_width(aabb.w() * health), GREEN);
+                }
+            });
         }
     });
 }
@@ -311,6 +1332,10 @@ pub fn sys_render_selection_indicator(
 
     rand.provide(|| {
         for (&ObjOwner(world), mut world_state) in query.iter_mut() {
+            if !camera.shows(world) {
+                continue;
+            }
+
             let config = world.config();
 
             let pos = Vec2::from(mouse_position());
@@ -326,35 +1351,68 @@ pub fn sys_render_selection_indicator(
     });
 }
 
-pub fn sys_render_health_bar(
+pub fn sys_animate_health_bar(
     mut rand: RandomAccess<&Health>,
     mut query: Query<(&ObjOwner<Health>, &mut HealthAnimation), With<ObjOwner<TileWorld>>>,
+    mut changes: EventReader<HealthChanged>,
 ) {
-    let screen_size = Vec2::from(screen_size());
-
     rand.provide(|| {
-        for (&ObjOwner(hp), mut hp_anim) in query.iter_mut() {
-            let aabb = Aabb::new_centered(
-                Vec2::new(screen_size.x / 2., screen_size.y - 20.),
-                Vec2::new(screen_size.x * 0.8, 10.),
-            );
+        for (_, mut hp_anim) in query.iter_mut() {
+            hp_anim.lost_from.tick();
+            hp_anim.gained_from.tick();
+        }
 
+        for event in changes.read() {
+            let Some((&ObjOwner(hp), mut hp_anim)) = query
+                .iter_mut()
+                .find(|(&ObjOwner(hp), _)| hp.entity() == event.target)
+            else {
+                continue;
+            };
+
+            // Keep trailing from wherever the segment currently is if it's further behind.
+            let old = event.old / hp.max();
+            let new = event.new / hp.max();
+            if event.delta() < 0. {
+                let from = hp_anim.lost_from.value().max(old);
+                hp_anim.lost_from.restart(from, new);
+            } else {
+                let from = hp_anim.gained_from.value().min(old);
+                hp_anim.gained_from.restart(from, new);
+            }
+        }
+    });
+}
+
+pub fn sys_render_health_bar(
+    In(aabb): In<Aabb>,
+    mut rand: RandomAccess<&Health>,
+    query: Query<(&ObjOwner<Health>, &HealthAnimation), With<ObjOwner<TileWorld>>>,
+) {
+    rand.provide(|| {
+        for (&ObjOwner(hp), hp_anim) in query.iter() {
             draw_rectangle_aabb(aabb.grow(Vec2::splat(5.)), WHITE);
 
             let hp_active = hp.percentage();
-            hp_anim.0 = (hp_anim.0 + hp_active) / 2.;
+            let lost_from = hp_anim.lost_from.value().max(hp_active);
+            let gained_from = hp_anim.gained_from.value().min(hp_active);
+
+            let segment = |from: f32, to: f32| {
+                Aabb::new_poly(&[
+                    aabb.point_at(Vec2::new(from, 0.)),
+                    aabb.point_at(Vec2::new(to, 1.)),
+                ])
+            };
 
             draw_rectangle_aabb(aabb, RED);
-            draw_rectangle_aabb(aabb.with_width(aabb.w() * hp.percentage()), GREEN);
-
-            if hp_anim.0 > hp_active {
-                draw_rectangle_aabb(
-                    Aabb::new_poly(&[
-                        aabb.point_at(Vec2::new(hp_active, 0.)),
-                        aabb.point_at(Vec2::new(hp_anim.0, 1.)),
-                    ]),
-                    YELLOW,
-                );
+            draw_rectangle_aabb(segment(0., gained_from), GREEN);
+
+            if gained_from < hp_active {
+                draw_rectangle_aabb(segment(gained_from, hp_active), LIME);
+            }
+
+            if lost_from > hp_active {
+                draw_rectangle_aabb(segment(hp_active, lost_from), YELLOW);
             }
         }
     });
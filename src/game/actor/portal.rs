@@ -0,0 +1,191 @@
+use bevy_ecs::{
+    bundle::Bundle,
+    component::Component,
+    entity::Entity,
+    query::{With, Without},
+    system::{Local, Query, Res, ResMut},
+};
+use cbit::cbit;
+use macroquad::{
+    color::{Color, VIOLET},
+    math::Vec2,
+};
+use rustc_hash::FxHashSet;
+
+use crate::{
+    game::{
+        draw_order::{DrawQueue, RenderLayer, RenderOrder},
+        math::{
+            aabb::Aabb,
+            draw::{draw_rectangle_aabb, stroke_rectangle_aabb},
+        },
+        tile::{
+            collider::{
+                Collider, CollisionLayers, InsideWorld, TrackedCollider, TrackedColliderChunk,
+                WorldColliders,
+            },
+            data::{TileChunk, TileWorld, WorldCreatedChunk},
+        },
+    },
+    util::arena::{Obj, ObjOwner, RandomAccess, RandomEntityExt, SendsEvent},
+};
+
+use super::{
+    camera::ActiveCamera,
+    kinematic::{ColliderMoves, Pos, PrevPos},
+};
+
+// === Components === //
+
+#[derive(Bundle)]
+pub struct PortalBundle {
+    pub world: InsideWorld,
+    pub collider: Collider,
+    pub layers: CollisionLayers,
+    pub portal: Portal,
+}
+
+impl PortalBundle {
+    pub fn new(world: InsideWorld, aabb: Aabb, portal: Portal) -> Self {
+        Self {
+            world,
+            collider: Collider(aabb),
+            layers: PORTAL_LAYERS,
+            portal,
+        }
+    }
+}
+
+/// Portals are found by overlap queries but never block anything.
+pub const PORTAL_LAYERS: CollisionLayers = CollisionLayers::new(CollisionLayers::TRIGGERS, 0);
+
+/// Sends every moving collider touching the portal's [`Collider`] to another world, centering it on
+/// `exit`. Actors which arrive on top of a portal won't travel again until they've stepped off of
+/// every portal so that pairs of portals don't bounce them back and forth.
+#[derive(Debug, Component)]
+pub struct Portal {
+    pub destination: Obj<TileWorld>,
+    pub exit: Vec2,
+
+    /// The layers of the colliders which can travel through the portal.
+    pub mask: u32,
+}
+
+impl Portal {
+    pub fn new(destination: Obj<TileWorld>, exit: Vec2) -> Self {
+        Self {
+            destination,
+            exit,
+            mask: CollisionLayers::PLAYERS | CollisionLayers::ACTORS | CollisionLayers::ITEMS,
+        }
+    }
+
+    pub fn with_mask(mut self, mask: u32) -> Self {
+        self.mask = mask;
+        self
+    }
+}
+
+// === Systems === //
+
+pub fn sys_use_portals(
+    mut rand: RandomAccess<(
+        &mut TileWorld,
+        &mut TileChunk,
+        &mut WorldColliders,
+        &mut TrackedColliderChunk,
+        &mut TrackedCollider,
+        SendsEvent<WorldCreatedChunk>,
+    )>,
+    portals: Query<(Entity, &InsideWorld, &Collider, &Portal)>,
+    mut travelers: Query<
+        (
+            &mut InsideWorld,
+            &mut Pos,
+            Option<&mut PrevPos>,
+            &mut Collider,
+            Option<&CollisionLayers>,
+            Option<&ObjOwner<TrackedCollider>>,
+        ),
+        (With<ColliderMoves>, Without<Portal>),
+    >,
+    mut arrived: Local<FxHashSet<Entity>>,
+    mut touching: Local<FxHashSet<Entity>>,
+    mut trips: Local<Vec<(Entity, Obj<TileWorld>, Vec2)>>,
+) {
+    rand.provide(|| {
+        // Collect every trip before moving anyone so that the order in which portals are visited
+        // doesn't matter.
+        touching.clear();
+
+        for (me, &InsideWorld(world), &Collider(aabb), portal) in portals.iter() {
+            if !portal.destination.is_alive() {
+                continue;
+            }
+
+            let colliders = world.entity().get::<WorldColliders>();
+
+            cbit! {
+                for (other, _) in colliders.collisions(aabb, portal.mask) {
+                    if other == me || !touching.insert(other) {
+                        continue;
+                    }
+
+                    if !arrived.contains(&other) && travelers.contains(other) {
+                        trips.push((other, portal.destination, portal.exit));
+                    }
+                }
+            }
+        }
+
+        arrived.retain(|actor| touching.contains(actor));
+
+        for (actor, destination, exit) in trips.drain(..) {
+            let (mut world, mut pos, prev, mut collider, layers, tracked) =
+                travelers.get_mut(actor).unwrap();
+
+            let delta = exit - collider.0.center();
+            world.0 = destination;
+            pos.0 += delta;
+            collider.0 = collider.0.translated(delta);
+
+            // Don't interpolate the actor between the two worlds.
+            if let Some(mut prev) = prev {
+                prev.0 = pos.0;
+            }
+
+            if let Some(&ObjOwner(tracked)) = tracked {
+                let layers = layers.copied().unwrap_or_default();
+                tracked.transfer_to(destination, collider.0, layers);
+            }
+
+            arrived.insert(actor);
+        }
+    });
+}
+
+pub fn sys_render_portals(
+    query: Query<
+        (
+            &InsideWorld,
+            &Collider,
+            Option<&RenderLayer>,
+            Option<&RenderOrder>,
+        ),
+        With<Portal>,
+    >,
+    mut draws: ResMut<DrawQueue>,
+    camera: Res<ActiveCamera>,
+) {
+    for (&InsideWorld(world), &Collider(aabb), layer, order) in query.iter() {
+        if !camera.shows(world) {
+            continue;
+        }
+
+        let layer = layer.copied().unwrap_or(RenderLayer::Background);
+        draws.push(layer, RenderOrder::key_of(order, aabb.max.y), move || {
+            draw_rectangle_aabb(aabb, Color::new(0.55, 0.25, 0.85, 0.5));
+            stroke_rectangle_aabb(aabb, 3., VIOLET);
+        });
+    }
+}
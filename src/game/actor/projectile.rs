@@ -3,29 +3,50 @@ use std::f32::consts::TAU;
 use bevy_ecs::{
     bundle::Bundle,
     component::Component,
-    event::EventReader,
-    query::With,
-    system::{Commands, Query, Res},
+    entity::Entity,
+    event::{EventReader, EventWriter},
+    query::{Added, With, Without},
+    removal_detection::RemovedComponents,
+    system::{Commands, Local, Query, Res, ResMut, Resource, SystemParam},
 };
-use macroquad::{color::BLUE, math::Vec2, rand::gen_range, shapes::draw_circle};
+use macroquad::{
+    color::{Color, BLUE, ORANGE, VIOLET},
+    math::Vec2,
+    shapes::draw_circle,
+};
+use rustc_hash::FxHashSet;
 
 use crate::{
     game::{
+        draw_order::{DrawQueue, RenderLayer, RenderOrder},
+        fx::particles::ParticleEmitter,
         math::aabb::Aabb,
+        rng::{Rng, RngChannel},
         tile::{
-            collider::{Collider, InsideWorld},
-            data::TileWorld,
-            kinematic::TangibleMarker,
+            collider::{
+                Collider, CollisionLayers, InsideWorld, TrackedCollider, TrackedColliderChunk,
+                WorldColliders,
+            },
+            data::{TileChunk, TileWorld, WorldCreatedChunk},
+            kinematic::{KinematicApi, TangibleMarker, TileColliderDescriptor},
+            material::MaterialRegistry,
         },
+        time::RenderAlpha,
+    },
+    util::{
+        arena::{send_event, spawn_entity, ObjOwner, RandomAccess, RandomEntityExt, SendsEvent},
+        timer::Cooldown,
     },
-    util::arena::{despawn_entity, RandomAccess, RandomEntityExt},
 };
 
 use super::{
-    camera::ActiveCamera,
+    camera::{ActiveCamera, CullExt},
+    damage::{DamageEvent, DamageKind},
+    explosion::Explosion,
     health::Health,
-    kinematic::{ColliderEvent, ColliderListens, ColliderMoves, Pos, Vel},
-    player::PlayerState,
+    kinematic::{
+        ColliderEvent, ColliderListens, ColliderMoves, ContinuousCollision, Pos, PrevPos, Vel,
+    },
 };
 
 // === Systems === //
@@ -36,80 +57,419 @@ pub struct BulletBaseBundle {
     pub vel: Vel,
     pub world: InsideWorld,
     pub collider: Collider,
+    pub layers: CollisionLayers,
     pub moves: ColliderMoves,
+    pub continuous: ContinuousCollision,
     pub listens: ColliderListens,
     pub damage: BulletDamage,
 }
 
-#[derive(Debug, Component)]
+/// Bullets only collide with tiles and only report overlaps with players, letting them pass through
+/// each other.
+pub const BULLET_LAYERS: CollisionLayers =
+    CollisionLayers::new(CollisionLayers::PROJECTILES, CollisionLayers::TILES);
+
+pub const BULLET_LISTEN_MASK: u32 = CollisionLayers::PLAYERS;
+
+/// The speed at which exploding bullets push away actors at the center of the blast.
+pub const BULLET_EXPLOSION_KNOCKBACK: f32 = 20.;
+
+#[derive(Debug, Copy, Clone, PartialEq, Component)]
 pub struct BulletDamage {
     pub amount: f32,
-    pub despawn: bool,
+
+    /// What happens when the bullet hits an actor it listens for.
+    pub impact: BulletImpact,
+
+    /// The number of times the bullet can still bounce off of tiles. Bullets which have run out of
+    /// ricochets slide along the tiles they hit instead.
+    pub ricochets: u32,
 }
 
-#[derive(Debug, Component)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BulletImpact {
+    /// Damages the actor and despawns.
+    Despawn,
+
+    /// Damages the actor and keeps going, despawning once it has passed through the given number
+    /// of additional actors.
+    Pierce(u32),
+
+    /// Despawns in an [`Explosion`] which damages every actor the bullet listens for within `radius`
+    /// of it and destroys tiles up to a hardness of `power`.
+    Explode { radius: f32, power: f32 },
+}
+
+impl BulletImpact {
+    pub fn color(self) -> Color {
+        match self {
+            BulletImpact::Despawn => BLUE,
+            BulletImpact::Pierce(_) => VIOLET,
+            BulletImpact::Explode { .. } => ORANGE,
+        }
+    }
+}
+
+/// Fires bullets in random directions, limited by the [`Cooldown`] on the same entity if any.
+#[derive(Debug, Clone, Component)]
 pub struct BulletSpawner;
 
+// === BulletPool === //
+
+/// Marks a bullet parked in the [`BulletPool`]. Parked bullets keep all of their components so
+/// that they can be fired again without being respawned but are ignored by collision, movement, and
+/// rendering, and are left out of the broadphase.
+///
+/// This is stored in a sparse set so that parking and recycling a bullet doesn't move the rest of
+/// its components between tables.
+#[derive(Debug, Component)]
+#[component(storage = "SparseSet")]
+pub struct Pooled;
+
+/// Recycles the entities of spent bullets so that heavy fire doesn't keep spawning and despawning
+/// entities, moving them between archetypes, and reallocating their arena components.
+#[derive(Debug, Resource)]
+pub struct BulletPool {
+    parked: Vec<Entity>,
+
+    /// The most bullets kept parked at once. Bullets released beyond this are despawned.
+    pub capacity: usize,
+
+    spawned: u64,
+    recycled: u64,
+}
+
+impl Default for BulletPool {
+    fn default() -> Self {
+        Self {
+            parked: Vec::new(),
+            capacity: 512,
+            spawned: 0,
+            recycled: 0,
+        }
+    }
+}
+
+impl BulletPool {
+    /// Fires a bullet, reusing a parked one if there is any. Must be called within a
+    /// [`RandomAccess`] providing `&mut TangibleMarker`.
+    pub fn spawn(&mut self, commands: &mut Commands, bundle: BulletBaseBundle) -> Entity {
+        // Parked bullets may have been despawned along with everything else, e.g. on scene
+        // changes.
+        while let Some(entity) = self.parked.pop() {
+            let Some(mut bullet) = commands.get_entity(entity) else {
+                continue;
+            };
+
+            // Every component of the bundle already exists so this overwrites them in place.
+            let prev = PrevPos(bundle.pos.0);
+            bullet.remove::<Pooled>().insert((bundle, prev));
+            self.recycled += 1;
+            return entity;
+        }
+
+        let entity = commands.spawn(bundle).id();
+        entity.insert(TangibleMarker);
+        self.spawned += 1;
+        entity
+    }
+
+    /// Parks a spent bullet so that it can be fired again, despawning it instead if the pool is
+    /// full.
+    pub fn release(&mut self, commands: &mut Commands, entity: Entity) {
+        if self.parked.len() >= self.capacity {
+            commands.entity(entity).despawn();
+            return;
+        }
+
+        commands.entity(entity).insert(Pooled);
+        self.parked.push(entity);
+    }
+
+    pub fn parked(&self) -> usize {
+        self.parked.len()
+    }
+
+    /// The number of bullets which had to be spawned as new entities.
+    pub fn spawned(&self) -> u64 {
+        self.spawned
+    }
+
+    /// The number of bullets fired by reusing a parked entity.
+    pub fn recycled(&self) -> u64 {
+        self.recycled
+    }
+}
+
+/// Releases bullets to the [`BulletPool`] as they're spent.
+#[derive(SystemParam)]
+pub struct SpentBullets<'w, 's> {
+    pool: ResMut<'w, BulletPool>,
+    commands: Commands<'w, 's>,
+    spent: Local<'s, FxHashSet<Entity>>,
+}
+
+impl SpentBullets<'_, '_> {
+    /// Bullets are only parked once commands are applied so we have to remember which ones were
+    /// spent this tick lest they hit something else and get parked twice. This forgets the bullets
+    /// spent during the previous tick.
+    pub fn start_tick(&mut self) {
+        self.spent.clear();
+    }
+
+    pub fn contains(&self, bullet: Entity) -> bool {
+        self.spent.contains(&bullet)
+    }
+
+    pub fn release(&mut self, bullet: Entity) {
+        if self.spent.insert(bullet) {
+            self.pool.release(&mut self.commands, bullet);
+        }
+    }
+}
+
+// === Systems === //
+
+pub fn sys_ricochet_bullets(
+    mut query: Query<(&InsideWorld, &Collider, &mut Vel, &mut BulletDamage), Without<Pooled>>,
+    mut rand: RandomAccess<(
+        &mut KinematicApi,
+        &TileWorld,
+        &TileChunk,
+        &MaterialRegistry,
+        &TileColliderDescriptor,
+        &WorldColliders,
+        &TrackedCollider,
+        &TrackedColliderChunk,
+    )>,
+) {
+    rand.provide(|| {
+        for (&InsideWorld(world), &Collider(aabb), mut vel, mut damage) in query.iter_mut() {
+            if damage.ricochets == 0 {
+                continue;
+            }
+
+            let mut kinematics = world.entity().get::<KinematicApi>();
+
+            // See whether a tile will stop us this tick and, if so, reflect our velocity along
+            // every axis blocked at the point of contact.
+            let allowed = kinematics.move_by_swept(aabb, vel.0, CollisionLayers::TILES, |_| true);
+            if allowed.distance_squared(vel.0) < KinematicApi::TOLERANCE {
+                continue;
+            }
+
+            let clip = kinematics.get_clip_mask(
+                aabb.translated(allowed),
+                vel.0,
+                CollisionLayers::TILES,
+                |_| true,
+            );
+
+            if !clip.x {
+                vel.0.x = -vel.0.x;
+            }
+            if !clip.y {
+                vel.0.y = -vel.0.y;
+            }
+
+            damage.ricochets -= 1;
+        }
+    });
+}
+
 pub fn sys_apply_bullet_damage(
     mut events: EventReader<ColliderEvent>,
-    mut bullet_query: Query<&BulletDamage>,
-    mut player_query: Query<&InsideWorld, With<PlayerState>>,
-    mut rand: RandomAccess<(&TileWorld, &mut Health)>,
+    mut bullet_query: Query<
+        (&InsideWorld, &Pos, &ColliderListens, &mut BulletDamage),
+        Without<Pooled>,
+    >,
+    health_query: Query<&ObjOwner<Health>>,
+    mut rand: RandomAccess<(&TileWorld, SendsEvent<Explosion>)>,
+    mut damage: EventWriter<DamageEvent>,
+    mut spent: SpentBullets,
 ) {
+    spent.start_tick();
+
     rand.provide(|| {
         for event in events.read() {
-            if !event.entered {
+            if !event.entered || spent.contains(event.listener) {
                 continue;
             }
 
-            let Ok(bullet) = bullet_query.get_mut(event.listener) else {
+            let Ok((&InsideWorld(world), &Pos(pos), listens, mut bullet)) =
+                bullet_query.get_mut(event.listener)
+            else {
                 continue;
             };
 
-            let Ok(&InsideWorld(world)) = player_query.get_mut(event.other) else {
+            if !health_query.contains(event.other) {
                 continue;
-            };
+            }
 
-            world.entity().get::<Health>().change_health(-bullet.amount);
+            let hit = DamageEvent {
+                source: event.listener,
+                target: event.other,
+                kind: DamageKind::Projectile,
+                amount: bullet.amount,
+            };
 
-            if bullet.despawn {
-                despawn_entity(event.listener);
+            match bullet.impact {
+                BulletImpact::Despawn | BulletImpact::Pierce(0) => {
+                    damage.send(hit);
+                    spawn_entity((
+                        InsideWorld(world),
+                        Pos(pos),
+                        ParticleEmitter::sparks(bullet.impact.color()),
+                    ));
+                    spent.release(event.listener);
+                }
+                BulletImpact::Pierce(remaining) => {
+                    damage.send(hit);
+                    spawn_entity((
+                        InsideWorld(world),
+                        Pos(pos),
+                        ParticleEmitter::sparks(bullet.impact.color()),
+                    ));
+                    bullet.impact = BulletImpact::Pierce(remaining - 1);
+                }
+                BulletImpact::Explode { radius, power } => {
+                    send_event(Explosion {
+                        world,
+                        source: event.listener,
+                        center: pos,
+                        radius,
+                        damage: bullet.amount,
+                        knockback: BULLET_EXPLOSION_KNOCKBACK,
+                        power,
+                        mask: listens.mask(),
+                    });
+                    spent.release(event.listener);
+                }
             }
         }
     });
 }
 
+/// Fires a bullet from every [`BulletSpawner`] whose fire-rate [`Cooldown`] is ready. Spawners
+/// without a cooldown fire every tick.
 pub fn sys_tick_bullet_spawner(
-    mut query: Query<(&InsideWorld, &Pos), With<BulletSpawner>>,
+    mut query: Query<(&InsideWorld, &Pos, Option<&mut Cooldown>), With<BulletSpawner>>,
     mut rand: RandomAccess<&mut TangibleMarker>,
+    mut rng: ResMut<Rng>,
+    mut pool: ResMut<BulletPool>,
     mut commands: Commands,
 ) {
+    let rng = rng.stream(RngChannel::Projectiles);
+
     rand.provide(|| {
-        for (&InsideWorld(world), &Pos(pos)) in query.iter_mut() {
-            let entity = commands
-                .spawn(BulletBaseBundle {
+        for (&InsideWorld(world), &Pos(pos), cooldown) in query.iter_mut() {
+            if cooldown.is_some_and(|mut cooldown| !cooldown.try_trigger()) {
+                continue;
+            }
+
+            pool.spawn(
+                &mut commands,
+                BulletBaseBundle {
                     pos: Pos(pos),
-                    vel: Vel(Vec2::from_angle(gen_range(0., TAU)) * 10.),
+                    vel: Vel(Vec2::from_angle(rng.gen_range(0., TAU)) * 10.),
                     world: InsideWorld(world),
                     collider: Collider(Aabb::ZERO),
+                    layers: BULLET_LAYERS,
                     moves: ColliderMoves,
-                    listens: ColliderListens::default(),
+                    continuous: ContinuousCollision,
+                    listens: ColliderListens::with_mask(BULLET_LISTEN_MASK),
                     damage: BulletDamage {
-                        despawn: true,
                         amount: 2.,
+                        impact: match rng.gen_range(0, 4) {
+                            0 => BulletImpact::Pierce(2),
+                            1 => BulletImpact::Explode {
+                                radius: 150.,
+                                power: 0.,
+                            },
+                            _ => BulletImpact::Despawn,
+                        },
+                        ricochets: rng.gen_range(0, 3),
                     },
-                })
-                .id();
+                },
+            );
+        }
+    });
+}
+
+/// Takes bullets parked in the [`BulletPool`] out of the broadphase and puts recycled ones back in.
+pub fn sys_update_pooled_bullets(
+    mut rand: RandomAccess<(
+        &mut TileWorld,
+        &mut TileChunk,
+        &mut WorldColliders,
+        &mut TrackedColliderChunk,
+        &mut TrackedCollider,
+        SendsEvent<WorldCreatedChunk>,
+    )>,
+    mut parked: Query<(&mut Vel, Option<&ObjOwner<TrackedCollider>>), Added<Pooled>>,
+    recycled: Query<
+        (
+            &InsideWorld,
+            &Collider,
+            Option<&CollisionLayers>,
+            &ObjOwner<TrackedCollider>,
+        ),
+        Without<Pooled>,
+    >,
+    mut unpooled: RemovedComponents<Pooled>,
+) {
+    rand.provide(|| {
+        for (mut vel, tracked) in parked.iter_mut() {
+            vel.0 = Vec2::ZERO;
+
+            if let Some(&ObjOwner(tracked)) = tracked {
+                tracked.disable();
+            }
+        }
+
+        // Recycled bullets may have been fired into another world so we re-register them from
+        // scratch.
+        for entity in unpooled.read() {
+            let Ok((&InsideWorld(world), &Collider(aabb), layers, &ObjOwner(tracked))) =
+                recycled.get(entity)
+            else {
+                continue;
+            };
 
-            entity.insert(TangibleMarker);
+            tracked.transfer_to(world, aabb, layers.copied().unwrap_or_default());
         }
     });
 }
 
-pub fn sys_render_bullets(mut query: Query<&Pos, With<BulletDamage>>, camera: Res<ActiveCamera>) {
-    let _guard = camera.apply();
+pub fn sys_render_bullets(
+    query: Query<
+        (
+            &InsideWorld,
+            &Pos,
+            Option<&PrevPos>,
+            &BulletDamage,
+            Option<&RenderLayer>,
+            Option<&RenderOrder>,
+        ),
+        Without<Pooled>,
+    >,
+    mut draws: ResMut<DrawQueue>,
+    alpha: Res<RenderAlpha>,
+    camera: Res<ActiveCamera>,
+) {
+    let visible = query
+        .iter()
+        .culled(&camera, |&(&InsideWorld(world), &Pos(pos), ..)| {
+            (world, Aabb::new_centered(pos, Vec2::splat(40.)))
+        });
+
+    for (_, &Pos(pos), prev, damage, layer, order) in visible {
+        let pos = PrevPos::interpolate(prev, pos, *alpha);
+        let color = damage.impact.color();
 
-    for &Pos(pos) in query.iter_mut() {
-        draw_circle(pos.x, pos.y, 20., BLUE);
+        let layer = layer.copied().unwrap_or(RenderLayer::Projectiles);
+        draws.push(layer, RenderOrder::key_of(order, pos.y), move || {
+            draw_circle(pos.x, pos.y, 20., color);
+        });
     }
 }
@@ -0,0 +1,99 @@
+use std::f32::consts::TAU;
+
+use bevy_ecs::{
+    component::Component,
+    query::With,
+    system::{Query, Res},
+};
+use macroquad::{color::Color, math::Vec2};
+
+use crate::{
+    game::{
+        math::draw::QuadBatch,
+        tile::{
+            collider::{
+                Collider, CollisionLayers, InsideWorld, TrackedCollider, TrackedColliderChunk,
+                WorldColliders,
+            },
+            data::{TileChunk, TileWorld},
+            kinematic::{KinematicApi, TileColliderDescriptor},
+            material::MaterialRegistry,
+        },
+    },
+    util::arena::{RandomAccess, RandomEntityExt},
+};
+
+use super::camera::ActiveCamera;
+
+// === Systems === //
+
+/// The maximum distance between an actor and the ground below it at which it still casts a shadow.
+pub const MAX_SHADOW_DISTANCE: f32 = 400.;
+
+/// The opacity of a shadow cast by an actor standing directly on the ground.
+pub const SHADOW_STRENGTH: f32 = 0.45;
+
+const SHADOW_SEGMENTS: usize = 16;
+
+#[derive(Debug, Clone, Component, Default)]
+pub struct CastsShadow;
+
+pub fn sys_render_actor_shadows(
+    mut query: Query<(&InsideWorld, &Collider), With<CastsShadow>>,
+    mut rand: RandomAccess<(
+        &mut KinematicApi,
+        &TileWorld,
+        &TileChunk,
+        &MaterialRegistry,
+        &TileColliderDescriptor,
+        &WorldColliders,
+        &TrackedCollider,
+        &TrackedColliderChunk,
+    )>,
+    camera: Res<ActiveCamera>,
+) {
+    let _guard = camera.apply();
+
+    rand.provide(|| {
+        let mut batch = QuadBatch::default();
+
+        for (&InsideWorld(world), &Collider(aabb)) in query.iter_mut() {
+            if !camera.shows(world) {
+                continue;
+            }
+
+            let mut kinematics = world.entity().get::<KinematicApi>();
+
+            // Find the ground directly below the actor.
+            let origin = Vec2::new(aabb.center().x, aabb.max.y);
+            let Some(hit) = kinematics.cast_ray(
+                origin,
+                Vec2::Y,
+                MAX_SHADOW_DISTANCE,
+                CollisionLayers::TILES,
+                |_| true,
+            ) else {
+                continue;
+            };
+
+            // Shadows shrink and fade as the actor gets further from the ground.
+            let falloff = 1. - hit.dist / MAX_SHADOW_DISTANCE;
+            let radii = Vec2::new(aabb.w() * (0.5 + 0.5 * falloff), aabb.w() * 0.25) / 2.;
+            let shade = Color::new(0., 0., 0., SHADOW_STRENGTH * falloff);
+            let clear = Color::new(0., 0., 0., 0.);
+
+            let rim = |i: usize| {
+                hit.pos + Vec2::from_angle(i as f32 / SHADOW_SEGMENTS as f32 * TAU) * radii
+            };
+
+            for i in 0..SHADOW_SEGMENTS {
+                batch.push_gradient(
+                    [hit.pos, rim(i), rim(i + 1), hit.pos],
+                    [shade, clear, clear, shade],
+                );
+            }
+        }
+
+        batch.flush();
+    });
+}
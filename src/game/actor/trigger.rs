@@ -0,0 +1,180 @@
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    system::{Local, Query},
+};
+use cbit::cbit;
+use rustc_hash::FxHashSet;
+
+use crate::{
+    game::tile::{
+        collider::{
+            Collider, CollisionLayers, InsideWorld, TrackedCollider, TrackedColliderChunk,
+            WorldColliders,
+        },
+        data::{TileChunk, TileWorld, WorldCreatedChunk},
+    },
+    util::{
+        arena::{RandomAccess, RandomEntityExt, SendsEvent},
+        delegate::Delegate,
+    },
+};
+
+use super::{
+    effects::{StatusEffectApplied, StatusEffectExpired, StatusEffects},
+    health::Health,
+};
+
+// === TriggerZone === //
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum TriggerPhase {
+    /// The collider started overlapping the zone this tick.
+    Enter,
+
+    /// The collider was already overlapping the zone and still is.
+    Stay,
+
+    /// The collider stopped overlapping the zone this tick, either because it left or because it
+    /// was despawned.
+    Exit,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct TriggerContact {
+    pub zone: Entity,
+    pub other: Entity,
+    pub phase: TriggerPhase,
+}
+
+pub type TriggerHandler = Delegate<TriggerContact>;
+
+/// A sensor which calls its handlers for every collider overlapping its [`Collider`]. Handlers are
+/// called from within [`sys_update_trigger_zones`] and can therefore access [`Health`] and
+/// [`StatusEffects`] as well as spawn and despawn entities.
+#[derive(Debug, Component)]
+pub struct TriggerZone {
+    mask: u32,
+    contains: FxHashSet<Entity>,
+    on_enter: Option<TriggerHandler>,
+    on_stay: Option<TriggerHandler>,
+    on_exit: Option<TriggerHandler>,
+}
+
+impl Default for TriggerZone {
+    fn default() -> Self {
+        Self::with_mask(CollisionLayers::ALL)
+    }
+}
+
+impl TriggerZone {
+    /// Creates a zone which only reacts to colliders belonging to one of the layers in `mask`.
+    pub fn with_mask(mask: u32) -> Self {
+        Self {
+            mask,
+            contains: FxHashSet::default(),
+            on_enter: None,
+            on_stay: None,
+            on_exit: None,
+        }
+    }
+
+    pub fn on_enter(mut self, f: impl 'static + Send + Sync + Fn(TriggerContact)) -> Self {
+        self.on_enter = Some(Delegate::new(f));
+        self
+    }
+
+    pub fn on_stay(mut self, f: impl 'static + Send + Sync + Fn(TriggerContact)) -> Self {
+        self.on_stay = Some(Delegate::new(f));
+        self
+    }
+
+    pub fn on_exit(mut self, f: impl 'static + Send + Sync + Fn(TriggerContact)) -> Self {
+        self.on_exit = Some(Delegate::new(f));
+        self
+    }
+
+    pub fn mask(&self) -> u32 {
+        self.mask
+    }
+
+    /// The colliders overlapping the zone as of the last update.
+    pub fn contains(&self) -> impl ExactSizeIterator<Item = Entity> + '_ {
+        self.contains.iter().copied()
+    }
+
+    fn handler(&self, phase: TriggerPhase) -> Option<&TriggerHandler> {
+        match phase {
+            TriggerPhase::Enter => self.on_enter.as_ref(),
+            TriggerPhase::Stay => self.on_stay.as_ref(),
+            TriggerPhase::Exit => self.on_exit.as_ref(),
+        }
+    }
+}
+
+// === Systems === //
+
+pub fn sys_update_trigger_zones(
+    mut rand: RandomAccess<(
+        &mut TileWorld,
+        &mut TileChunk,
+        &mut WorldColliders,
+        &mut TrackedColliderChunk,
+        &TrackedCollider,
+        &mut Health,
+        &mut StatusEffects,
+        SendsEvent<WorldCreatedChunk>,
+        SendsEvent<StatusEffectApplied>,
+        SendsEvent<StatusEffectExpired>,
+    )>,
+    mut query: Query<(Entity, &InsideWorld, &Collider, &mut TriggerZone)>,
+    mut calls: Local<Vec<(TriggerHandler, TriggerContact)>>,
+    mut removed: Local<FxHashSet<Entity>>,
+) {
+    rand.provide(|| {
+        // Diff every zone's contents, deferring the handlers until we're done so that they can't
+        // observe a half-updated set of zones.
+        for (zone, &InsideWorld(world), &Collider(aabb), mut state) in query.iter_mut() {
+            let world = world.entity().get::<WorldColliders>();
+            let state = &mut *state;
+
+            removed.clear();
+            removed.extend(state.contains.drain());
+
+            cbit! {
+                for (other, _) in world.collisions(aabb, state.mask) {
+                    if zone == other || !state.contains.insert(other) {
+                        continue;
+                    }
+
+                    let phase = if removed.remove(&other) {
+                        TriggerPhase::Stay
+                    } else {
+                        TriggerPhase::Enter
+                    };
+
+                    if let Some(handler) = state.handler(phase) {
+                        calls.push((handler.clone(), TriggerContact { zone, other, phase }));
+                    }
+                }
+            }
+
+            if let Some(handler) = &state.on_exit {
+                for other in removed.drain() {
+                    calls.push((
+                        handler.clone(),
+                        TriggerContact {
+                            zone,
+                            other,
+                            phase: TriggerPhase::Exit,
+                        },
+                    ));
+                }
+            }
+        }
+
+        for (handler, contact) in calls.drain(..) {
+            handler.call(contact);
+        }
+    });
+}
@@ -0,0 +1,95 @@
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    event::EventWriter,
+    query::{With, Without},
+    system::Query,
+};
+use macroquad::math::Vec2;
+
+use crate::{
+    game::tile::{collider::InsideWorld, data::TileWorld},
+    util::arena::{ObjOwner, RandomAccess},
+};
+
+use super::{
+    checkpoint::ActiveCheckpoint,
+    damage::{DamageEvent, DamageKind},
+    death::{Dead, SpawnPoint},
+    health::Health,
+    kinematic::{Pos, PrevPos, Vel},
+};
+
+// === Components === //
+
+/// Decides what happens to actors which leave the [bounds](TileWorld::bounds) of the world this
+/// is attached to. Worlds without a policy [respawn](Self::Respawn) such actors.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Component)]
+pub enum VoidPolicy {
+    /// Moves the actor to its [last checkpoint](ActiveCheckpoint) or a [`SpawnPoint`] in its world,
+    /// falling back to the world's origin if there are neither.
+    #[default]
+    Respawn,
+
+    /// Deals this much environmental damage to the actor every tick until it dies.
+    Damage(f32),
+}
+
+// === Systems === //
+
+pub fn sys_handle_void(
+    mut rand: RandomAccess<&TileWorld>,
+    mut actors: Query<
+        (
+            Entity,
+            &InsideWorld,
+            &mut Pos,
+            Option<&mut PrevPos>,
+            Option<&mut Vel>,
+            Option<&ActiveCheckpoint>,
+        ),
+        (With<ObjOwner<Health>>, Without<Dead>, Without<SpawnPoint>),
+    >,
+    spawn_points: Query<(&InsideWorld, &Pos), With<SpawnPoint>>,
+    policies: Query<&VoidPolicy>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    rand.provide(|| {
+        for (entity, &InsideWorld(world), mut pos, prev, vel, checkpoint) in actors.iter_mut() {
+            let bounds = world.bounds();
+            let config = world.config();
+
+            if bounds.contains_tile(config.actor_to_tile(pos.0)) {
+                continue;
+            }
+
+            match policies.get(world.entity()).copied().unwrap_or_default() {
+                VoidPolicy::Respawn => {
+                    pos.0 = match checkpoint.filter(|checkpoint| checkpoint.world == world) {
+                        Some(checkpoint) => checkpoint.pos,
+                        None => spawn_points
+                            .iter()
+                            .find(|(&InsideWorld(spawn_world), _)| spawn_world == world)
+                            .map_or(Vec2::ZERO, |(_, spawn)| spawn.0),
+                    };
+
+                    // Don't draw the actor sliding all the way back from the void.
+                    if let Some(mut prev) = prev {
+                        prev.0 = pos.0;
+                    }
+                    if let Some(mut vel) = vel {
+                        vel.0 = Vec2::ZERO;
+                    }
+                }
+                VoidPolicy::Damage(amount) => {
+                    damage.send(DamageEvent {
+                        source: world.entity(),
+                        target: entity,
+                        kind: DamageKind::Environmental,
+                        amount,
+                    });
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,352 @@
+use bevy_ecs::{
+    component::Component,
+    event::{Event, EventReader, EventWriter},
+    query::{With, Without},
+    system::{In, Local, Query, Res, ResMut, Resource, SystemParam},
+};
+use macroquad::{
+    color::{Color, ORANGE, WHITE},
+    math::Vec2,
+    text::{draw_text, measure_text},
+    time::get_time,
+    window::screen_width,
+};
+use rustc_hash::FxHashSet;
+use serde::Deserialize;
+
+use crate::{
+    game::{
+        math::aabb::Aabb,
+        prefab::{PrefabAccess, PrefabRegistry},
+        rng::{Rng, RngChannel},
+        tile::collider::InsideWorld,
+        time::{WorldClock, TICK_DURATION},
+    },
+    util::arena::{RandomAccess, RandomEntityExt},
+};
+
+use super::{death::Dead, health::Health, kinematic::Pos};
+
+// === Components === //
+
+/// Marks a location at which the [`WaveDirector`] spawns enemies.
+#[derive(Debug, Clone, Component, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WaveSpawnPoint {
+    /// The prefab spawned at this point.
+    pub prefab: String,
+
+    /// The distance from the point at which enemies may appear.
+    pub spread: f32,
+}
+
+impl Default for WaveSpawnPoint {
+    fn default() -> Self {
+        Self {
+            prefab: "enemy".to_string(),
+            spread: 100.,
+        }
+    }
+}
+
+/// Attached to enemies spawned by the [`WaveDirector`]. A wave ends once none of its members are
+/// left alive.
+#[derive(Debug, Copy, Clone, Component)]
+pub struct WaveMember {
+    pub wave: u32,
+}
+
+// === WaveDirector === //
+
+/// Schedules waves of enemies at the [`WaveSpawnPoint`]s. Waves grow and their enemies get tougher
+/// as the game goes on, and they are larger at night.
+#[derive(Debug, Clone, Resource)]
+pub struct WaveDirector {
+    /// The number of ticks between the end of a wave and the start of the next one.
+    pub intermission: u32,
+
+    /// The number of ticks between two spawns within a wave.
+    pub spawn_interval: u32,
+
+    /// The number of enemies in the first wave.
+    pub base_size: u32,
+
+    /// The number of enemies added to every subsequent wave.
+    pub size_growth: f32,
+
+    /// The factor by which waves starting at night are larger.
+    pub night_size_factor: f32,
+
+    /// The fraction of their base health which enemies gain with every wave.
+    pub health_growth: f32,
+
+    /// The maximum number of wave enemies alive at once. Spawning pauses while this many are alive.
+    pub max_alive: u32,
+
+    wave: u32,
+    phase: WavePhase,
+    alive: u32,
+}
+
+/// What the [`WaveDirector`] is currently doing.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WavePhase {
+    /// Waiting for the next wave to start.
+    Intermission { remaining: u32 },
+
+    /// Spawning the enemies of the current wave and waiting for them to be defeated.
+    Active { to_spawn: u32, spawn_in: u32 },
+}
+
+impl Default for WaveDirector {
+    fn default() -> Self {
+        Self {
+            intermission: (20. / TICK_DURATION) as u32,
+            spawn_interval: (0.75 / TICK_DURATION) as u32,
+            base_size: 3,
+            size_growth: 1.5,
+            night_size_factor: 1.5,
+            health_growth: 0.2,
+            max_alive: 16,
+            wave: 0,
+            phase: WavePhase::Intermission {
+                remaining: (5. / TICK_DURATION) as u32,
+            },
+            alive: 0,
+        }
+    }
+}
+
+impl WaveDirector {
+    /// The number of the current or most recent wave, starting at `1`. This is `0` before the first
+    /// wave starts.
+    pub fn wave(&self) -> u32 {
+        self.wave
+    }
+
+    pub fn phase(&self) -> WavePhase {
+        self.phase
+    }
+
+    /// The number of wave enemies alive as of the last tick.
+    pub fn alive(&self) -> u32 {
+        self.alive
+    }
+
+    /// The number of enemies in the given wave.
+    pub fn wave_size(&self, wave: u32, night: bool) -> u32 {
+        let size = self.base_size as f32 + self.size_growth * wave.saturating_sub(1) as f32;
+        let factor = if night { self.night_size_factor } else { 1. };
+        (size * factor).round() as u32
+    }
+
+    /// The factor applied to the health of enemies spawned in the given wave.
+    pub fn health_factor(&self, wave: u32) -> f32 {
+        1. + self.health_growth * wave.saturating_sub(1) as f32
+    }
+
+    /// Starts the next wave immediately, skipping the rest of the intermission.
+    pub fn skip_intermission(&mut self) {
+        if let WavePhase::Intermission { remaining } = &mut self.phase {
+            *remaining = 0;
+        }
+    }
+}
+
+/// Sent when the [`WaveDirector`] starts spawning a wave.
+#[derive(Debug, Clone, Event)]
+pub struct WaveStarted {
+    pub wave: u32,
+    pub size: u32,
+}
+
+/// Sent once every enemy of a wave has been spawned and defeated.
+#[derive(Debug, Event)]
+pub struct WaveEnded {
+    pub wave: u32,
+}
+
+/// The state of the world by which the [`WaveDirector`] paces waves.
+#[derive(SystemParam)]
+pub struct WaveConditions<'w, 's> {
+    pub spawn_points: Query<'w, 's, (&'static InsideWorld, &'static Pos, &'static WaveSpawnPoint)>,
+    pub members: Query<'w, 's, (), (With<WaveMember>, Without<Dead>)>,
+    pub clock: Res<'w, WorldClock>,
+}
+
+// === Systems === //
+
+pub fn sys_direct_waves(
+    mut rand: RandomAccess<PrefabAccess>,
+    conditions: WaveConditions,
+    mut director: ResMut<WaveDirector>,
+    mut started: EventWriter<WaveStarted>,
+    mut ended: EventWriter<WaveEnded>,
+    prefabs: Res<PrefabRegistry>,
+    mut rng: ResMut<Rng>,
+    mut unknown_prefabs: Local<FxHashSet<String>>,
+) {
+    let WaveConditions {
+        spawn_points,
+        members,
+        clock,
+    } = conditions;
+
+    let director = &mut *director;
+    director.alive = members.iter().count() as u32;
+
+    // Spawn points with an unknown prefab can't spawn anything so they don't count.
+    let spawn_points = spawn_points
+        .iter()
+        .filter(|(.., point)| {
+            let known = prefabs.get(&point.prefab).is_some();
+            if !known && unknown_prefabs.insert(point.prefab.clone()) {
+                log::warn!("Wave spawn point uses unknown prefab {:?}", point.prefab);
+            }
+            known
+        })
+        .collect::<Vec<_>>();
+
+    match &mut director.phase {
+        WavePhase::Intermission { remaining } => {
+            if *remaining > 0 {
+                *remaining -= 1;
+                return;
+            }
+
+            // Wait for somewhere to spawn the wave before starting it.
+            if spawn_points.is_empty() {
+                return;
+            }
+
+            director.wave += 1;
+            let size = director.wave_size(director.wave, clock.is_night());
+            director.phase = WavePhase::Active {
+                to_spawn: size,
+                spawn_in: 0,
+            };
+
+            log::info!("Wave {} started with {size} enemies", director.wave);
+            started.send(WaveStarted {
+                wave: director.wave,
+                size,
+            });
+        }
+        WavePhase::Active { to_spawn, spawn_in } => {
+            if *to_spawn == 0 {
+                if director.alive == 0 {
+                    log::info!("Wave {} ended", director.wave);
+                    ended.send(WaveEnded {
+                        wave: director.wave,
+                    });
+                    director.phase = WavePhase::Intermission {
+                        remaining: director.intermission,
+                    };
+                }
+                return;
+            }
+
+            if *spawn_in > 0 {
+                *spawn_in -= 1;
+                return;
+            }
+
+            if director.alive >= director.max_alive || spawn_points.is_empty() {
+                return;
+            }
+
+            *to_spawn -= 1;
+            *spawn_in = director.spawn_interval;
+
+            // Pick a spawn point and a spot around it.
+            let rng = rng.stream(RngChannel::Waves);
+            let (&InsideWorld(world), pos, point) =
+                spawn_points[rng.gen_range(0, spawn_points.len())];
+
+            let offset = Vec2::new(
+                rng.gen_range(-point.spread, point.spread),
+                rng.gen_range(-point.spread, point.spread),
+            );
+
+            let wave = director.wave;
+            let health_factor = director.health_factor(wave);
+
+            rand.provide(|| {
+                let enemy = prefabs.spawn_prefab(
+                    &point.prefab,
+                    (Pos(pos.0 + offset), InsideWorld(world), WaveMember { wave }),
+                );
+
+                if let Some(mut health) = enemy.try_get::<Health>() {
+                    let max = health.max() * health_factor;
+                    health.set_max(max);
+                    health.set_health(max);
+                }
+            });
+        }
+    }
+}
+
+/// Announces the start and end of waves at the top of the screen.
+pub fn sys_render_wave_banner(
+    mut started: EventReader<WaveStarted>,
+    mut ended: EventReader<WaveEnded>,
+    mut banner: Local<Option<(String, Color, f64)>>,
+) {
+    const BANNER_DURATION: f64 = 3.;
+
+    for event in started.read() {
+        *banner = Some((
+            format!("Wave {} - {} enemies", event.wave, event.size),
+            ORANGE,
+            get_time(),
+        ));
+    }
+
+    for event in ended.read() {
+        *banner = Some((format!("Wave {} cleared", event.wave), WHITE, get_time()));
+    }
+
+    if let Some((text, color, shown_at)) = &*banner {
+        let age = get_time() - shown_at;
+        if age < BANNER_DURATION {
+            // Fade out over the last second.
+            let alpha = (BANNER_DURATION - age).min(1.) as f32;
+            let dims = measure_text(text, None, 40, 1.);
+            draw_text(
+                text,
+                (screen_width() - dims.width) / 2.,
+                110.,
+                40.,
+                Color { a: alpha, ..*color },
+            );
+        } else {
+            *banner = None;
+        }
+    }
+}
+
+/// Shows the number of enemies left in the current wave or the time until the next one starts.
+pub fn sys_render_wave_counter(In(rect): In<Aabb>, director: Res<WaveDirector>) {
+    let text = match director.phase() {
+        WavePhase::Active { to_spawn, .. } => format!(
+            "Wave {}: {} remaining",
+            director.wave(),
+            to_spawn + director.alive()
+        ),
+        WavePhase::Intermission { remaining } => format!(
+            "Wave {} in {:.0}s",
+            director.wave() + 1,
+            (remaining as f32 * TICK_DURATION).ceil()
+        ),
+    };
+
+    let dims = measure_text(&text, None, 20, 1.);
+    draw_text(
+        &text,
+        rect.center().x - dims.width / 2.,
+        rect.max.y,
+        20.,
+        WHITE,
+    );
+}
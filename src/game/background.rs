@@ -0,0 +1,169 @@
+use bevy_ecs::{
+    component::Component,
+    system::{Query, Res},
+};
+use macroquad::{color::Color, math::Vec2, texture::Texture2D};
+
+use crate::util::arena::RandomAccess;
+
+use super::{
+    actor::camera::{ActiveCamera, VirtualCamera},
+    math::{aabb::Aabb, draw::QuadBatch},
+};
+
+// === Components === //
+
+/// The layers drawn behind the tile world of the entity owning the active camera, from furthest to
+/// nearest.
+#[derive(Debug, Default, Component)]
+pub struct ParallaxBackground {
+    pub layers: Vec<ParallaxLayer>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParallaxLayer {
+    /// The fraction of the camera's movement which the layer follows. Layers with a factor of zero
+    /// move along with the tile world while layers with a factor of one stay fixed to the screen.
+    pub factor: f32,
+    pub fill: ParallaxFill,
+}
+
+#[derive(Debug, Clone)]
+pub enum ParallaxFill {
+    /// A vertical gradient between two heights in layer-space. Everything above `top` takes on
+    /// `top_color` and everything below `bottom` takes on `bottom_color`.
+    Gradient {
+        top: f32,
+        bottom: f32,
+        top_color: Color,
+        bottom_color: Color,
+    },
+
+    /// A texture repeated across the entire layer in cells of the given size.
+    Texture {
+        texture: Texture2D,
+        size: Vec2,
+        tint: Color,
+    },
+}
+
+impl ParallaxLayer {
+    pub fn gradient(
+        factor: f32,
+        top: f32,
+        bottom: f32,
+        top_color: Color,
+        bottom_color: Color,
+    ) -> Self {
+        Self {
+            factor,
+            fill: ParallaxFill::Gradient {
+                top,
+                bottom,
+                top_color,
+                bottom_color,
+            },
+        }
+    }
+
+    pub fn texture(factor: f32, texture: Texture2D, size: Vec2, tint: Color) -> Self {
+        Self {
+            factor,
+            fill: ParallaxFill::Texture {
+                texture,
+                size,
+                tint,
+            },
+        }
+    }
+
+    /// Draws the portion of the layer covering the world-space `visible` rectangle of a camera
+    /// centered at `center`.
+    fn render(&self, batch: &mut QuadBatch, center: Vec2, visible: Aabb) {
+        // Layer-space positions are converted to world-space by adding this offset.
+        let offset = center * self.factor;
+        let local = visible.translated(-offset);
+
+        match &self.fill {
+            &ParallaxFill::Gradient {
+                top,
+                bottom,
+                top_color,
+                bottom_color,
+            } => {
+                let band = |min_y: f32, max_y: f32| {
+                    Aabb {
+                        min: Vec2::new(local.min.x, min_y),
+                        max: Vec2::new(local.max.x, max_y),
+                    }
+                    .translated(offset)
+                };
+
+                if local.min.y < top {
+                    batch.push_rect(band(local.min.y, top), top_color);
+                }
+
+                if local.min.y < bottom && local.max.y > top {
+                    batch.push_gradient(
+                        band(top, bottom).corners(),
+                        [top_color, top_color, bottom_color, bottom_color],
+                    );
+                }
+
+                if local.max.y > bottom {
+                    batch.push_rect(band(bottom, local.max.y), bottom_color);
+                }
+            }
+            ParallaxFill::Texture {
+                texture,
+                size,
+                tint,
+            } => {
+                let min = (local.min / *size).floor().as_ivec2();
+                let max = (local.max / *size).ceil().as_ivec2();
+
+                for y in min.y..max.y {
+                    for x in min.x..max.x {
+                        let cell = Vec2::new(x as f32, y as f32) * *size;
+                        batch.push_textured(
+                            texture,
+                            Aabb::new_sized(cell + offset, *size),
+                            Aabb::ZERO_TO_ONE,
+                            *tint,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+// === Systems === //
+
+pub fn sys_render_background(
+    mut rand: RandomAccess<&VirtualCamera>,
+    query: Query<&ParallaxBackground>,
+    camera: Res<ActiveCamera>,
+) {
+    let _guard = camera.apply();
+
+    rand.provide(|| {
+        let Some(active) = camera.camera else {
+            return;
+        };
+
+        let Ok(background) = query.get(active.entity()) else {
+            return;
+        };
+
+        let center = active.render_transform().translation;
+        let visible = active.visible_aabb();
+        let mut batch = QuadBatch::default();
+
+        for layer in &background.layers {
+            layer.render(&mut batch, center, visible);
+        }
+
+        batch.flush();
+    });
+}
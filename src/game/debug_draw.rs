@@ -0,0 +1,207 @@
+use bevy_ecs::system::{Res, ResMut, Resource};
+use macroquad::{
+    color::Color,
+    math::Vec2,
+    shapes::{draw_circle_lines, draw_line},
+    text::draw_text,
+    time::get_frame_time,
+};
+
+use super::{
+    actor::camera::ActiveCamera,
+    math::{aabb::Aabb, draw::stroke_rectangle_aabb},
+};
+
+// === DebugDraw === //
+
+/// Immediate-mode world-space debug drawing which can be used from any system. Shapes are buffered
+/// and drawn through every camera during the next rendered frame. By default, a shape is only drawn
+/// once but it can be kept around for longer using [`DebugShape::lasting`].
+#[derive(Debug, Resource)]
+pub struct DebugDraw {
+    /// Whether shapes are recorded at all. Headless apps never render so they disable this.
+    pub enabled: bool,
+    shapes: Vec<DebugShape>,
+    discarded: DebugShape,
+}
+
+impl Default for DebugDraw {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            shapes: Vec::new(),
+            discarded: DebugShape::new(DebugShapeKind::Point(Vec2::ZERO), Color::default()),
+        }
+    }
+}
+
+impl DebugDraw {
+    /// The most shapes which can be buffered at once. Shapes beyond this are dropped so that a
+    /// runaway system can't grind rendering to a halt.
+    pub const MAX_SHAPES: usize = 16_384;
+
+    /// The thickness of lines and outlines, in world units, unless otherwise specified.
+    pub const DEFAULT_THICKNESS: f32 = 2.;
+
+    /// The font size of text, in world units, unless otherwise specified.
+    pub const DEFAULT_FONT_SIZE: f32 = 20.;
+
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    /// Removes every buffered shape, including those which haven't expired yet.
+    pub fn clear(&mut self) {
+        self.shapes.clear();
+    }
+
+    pub fn line(&mut self, from: Vec2, to: Vec2, color: Color) -> &mut DebugShape {
+        self.push(DebugShapeKind::Line { from, to }, color)
+    }
+
+    /// Draws a line from `origin` to `origin + delta` with a dot at its tip.
+    pub fn ray(&mut self, origin: Vec2, delta: Vec2, color: Color) -> &mut DebugShape {
+        self.push(DebugShapeKind::Ray { origin, delta }, color)
+    }
+
+    pub fn aabb(&mut self, aabb: Aabb, color: Color) -> &mut DebugShape {
+        self.push(DebugShapeKind::Aabb(aabb), color)
+    }
+
+    pub fn circle(&mut self, center: Vec2, radius: f32, color: Color) -> &mut DebugShape {
+        self.push(DebugShapeKind::Circle { center, radius }, color)
+    }
+
+    pub fn point(&mut self, pos: Vec2, color: Color) -> &mut DebugShape {
+        self.push(DebugShapeKind::Point(pos), color)
+    }
+
+    /// Draws `text` with its baseline starting at `pos`.
+    pub fn text(&mut self, pos: Vec2, text: impl Into<String>, color: Color) -> &mut DebugShape {
+        let text = text.into();
+        self.push(DebugShapeKind::Text { pos, text }, color)
+    }
+
+    fn push(&mut self, kind: DebugShapeKind, color: Color) -> &mut DebugShape {
+        if !self.enabled || self.shapes.len() >= Self::MAX_SHAPES {
+            // Hand out a scratch shape so that callers can still configure it.
+            self.discarded = DebugShape::new(kind, color);
+            return &mut self.discarded;
+        }
+
+        self.shapes.push(DebugShape::new(kind, color));
+        self.shapes.last_mut().unwrap()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DebugShape {
+    kind: DebugShapeKind,
+    color: Color,
+    thickness: f32,
+    font_size: f32,
+    remaining: f32,
+}
+
+#[derive(Debug, Clone)]
+enum DebugShapeKind {
+    Line { from: Vec2, to: Vec2 },
+    Ray { origin: Vec2, delta: Vec2 },
+    Aabb(Aabb),
+    Circle { center: Vec2, radius: f32 },
+    Point(Vec2),
+    Text { pos: Vec2, text: String },
+}
+
+impl DebugShape {
+    fn new(kind: DebugShapeKind, color: Color) -> Self {
+        Self {
+            kind,
+            color,
+            thickness: DebugDraw::DEFAULT_THICKNESS,
+            font_size: DebugDraw::DEFAULT_FONT_SIZE,
+            remaining: 0.,
+        }
+    }
+
+    /// Keeps the shape visible for `secs` seconds of real time rather than a single frame.
+    pub fn lasting(&mut self, secs: f32) -> &mut Self {
+        self.remaining = secs;
+        self
+    }
+
+    pub fn thickness(&mut self, thickness: f32) -> &mut Self {
+        self.thickness = thickness;
+        self
+    }
+
+    pub fn font_size(&mut self, size: f32) -> &mut Self {
+        self.font_size = size;
+        self
+    }
+
+    fn draw(&self) {
+        let Self {
+            color, thickness, ..
+        } = *self;
+
+        match self.kind {
+            DebugShapeKind::Line { from, to } => {
+                draw_line(from.x, from.y, to.x, to.y, thickness, color);
+            }
+            DebugShapeKind::Ray { origin, delta } => {
+                let tip = origin + delta;
+                draw_line(origin.x, origin.y, tip.x, tip.y, thickness, color);
+                draw_circle_lines(tip.x, tip.y, thickness * 2., thickness, color);
+            }
+            DebugShapeKind::Aabb(aabb) => {
+                stroke_rectangle_aabb(aabb, thickness, color);
+            }
+            DebugShapeKind::Circle { center, radius } => {
+                draw_circle_lines(center.x, center.y, radius, thickness, color);
+            }
+            DebugShapeKind::Point(pos) => {
+                draw_circle_lines(pos.x, pos.y, thickness * 2., thickness, color);
+            }
+            DebugShapeKind::Text { pos, ref text } => {
+                draw_text(text, pos.x, pos.y, self.font_size, color);
+            }
+        }
+    }
+}
+
+// === Systems === //
+
+pub fn sys_render_debug_draw(debug: Res<DebugDraw>, camera: Res<ActiveCamera>) {
+    if debug.is_empty() {
+        return;
+    }
+
+    let _guard = camera.apply();
+
+    for shape in &debug.shapes {
+        shape.draw();
+    }
+}
+
+/// Ages the buffered shapes by a frame, removing those which have been drawn for long enough. Run
+/// once per frame after every camera has been rendered.
+pub fn sys_expire_debug_draw(mut debug: ResMut<DebugDraw>) {
+    let dt = get_frame_time();
+
+    debug.shapes.retain_mut(|shape| {
+        shape.remaining -= dt;
+        shape.remaining > 0.
+    });
+}
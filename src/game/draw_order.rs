@@ -0,0 +1,84 @@
+use bevy_ecs::{
+    component::Component,
+    system::{Res, ResMut, Resource},
+};
+
+use super::actor::camera::ActiveCamera;
+
+// === Components === //
+
+/// The layer an actor is drawn on. Layers are drawn from first to last so later layers appear on
+/// top of earlier ones.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Component)]
+pub enum RenderLayer {
+    Background,
+    Items,
+    Actors,
+    Projectiles,
+    Foreground,
+}
+
+/// Overrides the key by which an actor is sorted within its [`RenderLayer`]. Actors without one
+/// are sorted by their y position so that actors lower on the screen appear in front.
+#[derive(Debug, Copy, Clone, Component)]
+pub struct RenderOrder(pub f32);
+
+impl RenderOrder {
+    /// Picks the sort key of an actor at the given y position.
+    pub fn key_of(order: Option<&RenderOrder>, y: f32) -> f32 {
+        order.map_or(y, |order| order.0)
+    }
+}
+
+// === DrawQueue === //
+
+/// Collects the world-space draws of every actor render system so that they can be sorted by
+/// layer and depth before being drawn in [`sys_flush_draw_queue`].
+#[derive(Default, Resource)]
+pub struct DrawQueue {
+    draws: Vec<QueuedDraw>,
+}
+
+struct QueuedDraw {
+    layer: RenderLayer,
+    key: f32,
+    draw: Box<dyn FnOnce() + Send + Sync>,
+}
+
+impl DrawQueue {
+    pub fn push(
+        &mut self,
+        layer: RenderLayer,
+        key: f32,
+        draw: impl 'static + Send + Sync + FnOnce(),
+    ) {
+        self.draws.push(QueuedDraw {
+            layer,
+            key,
+            draw: Box::new(draw),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.draws.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.draws.is_empty()
+    }
+}
+
+// === Systems === //
+
+pub fn sys_flush_draw_queue(mut queue: ResMut<DrawQueue>, camera: Res<ActiveCamera>) {
+    let _guard = camera.apply();
+
+    // The sort is stable so draws with equal keys keep the order in which they were queued.
+    queue
+        .draws
+        .sort_by(|a, b| a.layer.cmp(&b.layer).then(a.key.total_cmp(&b.key)));
+
+    for draw in queue.draws.drain(..) {
+        (draw.draw)();
+    }
+}
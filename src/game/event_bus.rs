@@ -0,0 +1,128 @@
+use std::any::{Any, TypeId};
+
+use bevy_ecs::{
+    event::{Event, EventReader},
+    system::{Query, Res, ResMut, Resource, SystemParam},
+};
+use rustc_hash::FxHashMap;
+
+use crate::{
+    game::{
+        actor::{death::ActorDied, health::HealthChanged, kinematic::Pos, wave::WaveStarted},
+        prefab::{PrefabAccess, PrefabRegistry},
+        rng::Rng,
+        script::Scripts,
+        tile::{
+            breaking::TileBroken,
+            collider::InsideWorld,
+            data::{TileChanged, TileChunk, TileWorld, WorldCreatedChunk},
+            kinematic::TileColliderDescriptor,
+            material::{BaseMaterialDescriptor, MaterialRegistry},
+            tile_entity::{TileEntityCreated, TileEntityDescriptor},
+        },
+    },
+    util::arena::{RandomAccess, SendsEvent},
+};
+
+// === EventBus === //
+
+/// A gameplay event which [`EventBus`] handlers can subscribe to.
+pub trait BusEvent: Event {}
+
+impl BusEvent for ActorDied {}
+
+impl BusEvent for TileBroken {}
+
+impl BusEvent for WaveStarted {}
+
+/// The random components which [`EventBus`] handlers and script hooks may access. They can edit
+/// tiles, spawn prefabs, and change the health of actors.
+pub type EventBusAccess = (
+    (
+        &'static MaterialRegistry,
+        &'static BaseMaterialDescriptor,
+        &'static TileColliderDescriptor,
+        &'static TileEntityDescriptor,
+    ),
+    PrefabAccess,
+    &'static mut TileChunk,
+    &'static mut TileWorld,
+    SendsEvent<TileChanged>,
+    SendsEvent<TileEntityCreated>,
+    SendsEvent<WorldCreatedChunk>,
+    SendsEvent<HealthChanged>,
+);
+
+/// The resources and queries which [`EventBus`] handlers are given alongside each event.
+#[derive(SystemParam)]
+pub struct BusContext<'w, 's> {
+    pub scripts: Res<'w, Scripts>,
+    pub prefabs: Res<'w, PrefabRegistry>,
+    pub rng: ResMut<'w, Rng>,
+    pub actors: Query<'w, 's, (&'static InsideWorld, &'static Pos)>,
+}
+
+pub type BusHandler<E> = Box<dyn Fn(&E, &mut BusContext) + Send + Sync>;
+
+/// Lets gameplay code react to [`BusEvent`]s through callbacks instead of dedicated systems.
+///
+/// Handlers are subscribed through the resource, usually from a startup system, and are called in
+/// the order in which they were subscribed from within [`sys_dispatch_event_bus`]. They can
+/// therefore access the components in [`EventBusAccess`] as well as spawn and despawn entities.
+#[derive(Debug, Default, Resource)]
+pub struct EventBus {
+    handlers: FxHashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl EventBus {
+    pub fn subscribe<E: BusEvent>(
+        &mut self,
+        f: impl 'static + Send + Sync + Fn(&E, &mut BusContext),
+    ) {
+        self.handlers
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| Box::new(Vec::<BusHandler<E>>::new()))
+            .downcast_mut::<Vec<BusHandler<E>>>()
+            .unwrap()
+            .push(Box::new(f));
+    }
+
+    pub fn handlers<E: BusEvent>(&self) -> &[BusHandler<E>] {
+        self.handlers
+            .get(&TypeId::of::<E>())
+            .map_or(&[], |handlers| {
+                handlers.downcast_ref::<Vec<BusHandler<E>>>().unwrap()
+            })
+    }
+
+    fn dispatch<E: BusEvent>(&self, events: &mut EventReader<E>, cx: &mut BusContext) {
+        let handlers = self.handlers::<E>();
+        if handlers.is_empty() {
+            events.clear();
+            return;
+        }
+
+        for event in events.read() {
+            for handler in handlers {
+                handler(event, cx);
+            }
+        }
+    }
+}
+
+// === Systems === //
+
+pub fn sys_dispatch_event_bus(
+    mut rand: RandomAccess<EventBusAccess>,
+    bus: Res<EventBus>,
+    mut cx: BusContext,
+    mut broken: EventReader<TileBroken>,
+    mut died: EventReader<ActorDied>,
+    mut waves: EventReader<WaveStarted>,
+) {
+    rand.provide(|| {
+        bus.dispatch(&mut broken, &mut cx);
+        bus.dispatch(&mut died, &mut cx);
+        bus.dispatch(&mut waves, &mut cx);
+    });
+}
@@ -0,0 +1,2 @@
+pub mod particles;
+pub mod tween;
@@ -0,0 +1,266 @@
+use std::f32::consts::TAU;
+
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    event::EventReader,
+    system::{Commands, Query, Res, ResMut},
+};
+use macroquad::{color::Color, math::Vec2};
+
+use crate::{
+    game::{
+        actor::{camera::ActiveCamera, kinematic::Pos},
+        math::{aabb::Aabb, draw::QuadBatch},
+        rng::{Rng, RngChannel, RngStream},
+        tile::{
+            breaking::TileBroken, collider::InsideWorld, data::TileWorld,
+            material::MaterialRegistry, render::SolidTileMaterial,
+        },
+    },
+    util::arena::{RandomAccess, RandomEntityExt},
+};
+
+// === ParticleEmitter === //
+
+#[derive(Debug, Copy, Clone)]
+struct Particle {
+    pos: Vec2,
+    vel: Vec2,
+    age: u32,
+    lifetime: u32,
+}
+
+/// Spawns and simulates a pool of particles around the emitter's [`Pos`]. Particles are stored
+/// inline in the emitter rather than as entities so that thousands of them stay cheap.
+#[derive(Debug, Clone, Component)]
+pub struct ParticleEmitter {
+    /// The number of particles spawned every tick. Fractional rates accumulate across ticks.
+    pub rate: f32,
+
+    /// The minimum and maximum number of ticks for which a particle lives.
+    pub lifetime: (u32, u32),
+
+    /// The minimum and maximum speed at which particles are launched.
+    pub speed: (f32, f32),
+
+    /// The angle, in radians, at which particles are launched and the angle on either side of it
+    /// by which the launch direction may randomly vary.
+    pub direction: f32,
+    pub spread: f32,
+
+    /// The acceleration applied to every particle every tick.
+    pub gravity: Vec2,
+
+    pub size: f32,
+
+    /// The color of a particle at the start and end of its life. Colors are linearly interpolated
+    /// in between.
+    pub start_color: Color,
+    pub end_color: Color,
+
+    /// The maximum number of live particles. New particles are dropped once the pool is full.
+    pub capacity: usize,
+
+    /// Whether to despawn the emitter's entity once it has nothing left to spawn or simulate.
+    pub despawn_when_done: bool,
+
+    particles: Vec<Particle>,
+    pending: u32,
+    accumulator: f32,
+}
+
+impl Default for ParticleEmitter {
+    fn default() -> Self {
+        Self {
+            rate: 0.,
+            lifetime: (20, 40),
+            speed: (2., 5.),
+            direction: 0.,
+            spread: TAU / 2.,
+            gravity: Vec2::new(0., 0.3),
+            size: 6.,
+            start_color: Color::new(1., 1., 1., 1.),
+            end_color: Color::new(1., 1., 1., 0.),
+            capacity: 256,
+            despawn_when_done: false,
+            particles: Vec::new(),
+            pending: 0,
+            accumulator: 0.,
+        }
+    }
+}
+
+impl ParticleEmitter {
+    /// An emitter which spawns `count` particles on its next tick and despawns once they've all
+    /// died.
+    pub fn burst(count: u32) -> Self {
+        let mut emitter = Self {
+            despawn_when_done: true,
+            ..Default::default()
+        };
+        emitter.emit(count);
+        emitter
+    }
+
+    /// A short burst of sparks in the given color, used for small impacts.
+    pub fn sparks(color: Color) -> Self {
+        Self {
+            lifetime: (10, 20),
+            speed: (3., 8.),
+            size: 4.,
+            start_color: color,
+            end_color: Color { a: 0., ..color },
+            ..Self::burst(12)
+        }
+    }
+
+    /// A large burst of fire and smoke.
+    pub fn explosion(radius: f32) -> Self {
+        Self {
+            lifetime: (20, 45),
+            speed: (radius / 40., radius / 15.),
+            gravity: Vec2::new(0., -0.05),
+            size: 10.,
+            start_color: Color::new(1., 0.8, 0.2, 1.),
+            end_color: Color::new(0.3, 0.3, 0.3, 0.),
+            ..Self::burst(60)
+        }
+    }
+
+    /// Chunks of a broken tile which fall to the ground.
+    pub fn debris(color: Color) -> Self {
+        Self {
+            lifetime: (25, 40),
+            speed: (2., 5.),
+            direction: -TAU / 4.,
+            spread: TAU / 6.,
+            gravity: Vec2::new(0., 0.5),
+            size: 7.,
+            start_color: color,
+            end_color: Color { a: 0., ..color },
+            ..Self::burst(10)
+        }
+    }
+
+    /// Queues `count` particles to be spawned on the next tick.
+    pub fn emit(&mut self, count: u32) {
+        self.pending += count;
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.particles.is_empty() && self.pending == 0 && self.rate <= 0.
+    }
+
+    /// Spawns this tick's particles at `origin` and advances every live particle by a tick.
+    pub fn tick(&mut self, origin: Vec2, rng: &mut RngStream) {
+        // Spawn new particles
+        self.accumulator += self.rate;
+        let spawned = self.pending + self.accumulator as u32;
+        self.accumulator = self.accumulator.fract();
+        self.pending = 0;
+
+        for _ in 0..spawned {
+            if self.particles.len() >= self.capacity {
+                break;
+            }
+
+            let angle = self.direction + rng.gen_range(-self.spread, self.spread);
+            let speed = rng.gen_range(self.speed.0, self.speed.1);
+
+            self.particles.push(Particle {
+                pos: origin,
+                vel: Vec2::from_angle(angle) * speed,
+                age: 0,
+                lifetime: rng.gen_range(self.lifetime.0, self.lifetime.1).max(1),
+            });
+        }
+
+        // Simulate existing particles
+        let gravity = self.gravity;
+        self.particles.retain_mut(|particle| {
+            particle.age += 1;
+            particle.vel += gravity;
+            particle.pos += particle.vel;
+            particle.age < particle.lifetime
+        });
+    }
+
+    fn render(&self, batch: &mut QuadBatch) {
+        let start = self.start_color.to_vec();
+        let end = self.end_color.to_vec();
+
+        for particle in &self.particles {
+            let life = particle.age as f32 / particle.lifetime as f32;
+            batch.push_rect(
+                Aabb::new_centered(particle.pos, Vec2::splat(self.size)),
+                Color::from_vec(start.lerp(end, life)),
+            );
+        }
+    }
+}
+
+// === Systems === //
+
+pub fn sys_spawn_tile_break_particles(
+    mut rand: RandomAccess<(&TileWorld, &MaterialRegistry, &SolidTileMaterial)>,
+    mut events: EventReader<TileBroken>,
+    mut commands: Commands,
+) {
+    rand.provide(|| {
+        for event in events.read() {
+            let world = event.world.get::<TileWorld>();
+            let registry = event.world.get::<MaterialRegistry>();
+            let color = registry
+                .lookup(event.material)
+                .try_get::<SolidTileMaterial>()
+                .map_or(Color::new(0.5, 0.5, 0.5, 1.), |solid| solid.color);
+
+            let center = world.config().tile_to_actor_rect(event.pos).center();
+            commands.spawn((
+                InsideWorld(world),
+                Pos(center),
+                ParticleEmitter::debris(color),
+            ));
+        }
+    });
+}
+
+pub fn sys_simulate_particles(
+    mut query: Query<(Entity, &Pos, &mut ParticleEmitter)>,
+    mut rng: ResMut<Rng>,
+    mut commands: Commands,
+) {
+    let rng = rng.stream(RngChannel::Particles);
+
+    for (entity, &Pos(pos), mut emitter) in query.iter_mut() {
+        emitter.tick(pos, rng);
+
+        if emitter.despawn_when_done && emitter.is_done() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Renders every emitter inside the camera's world. Emitters which don't belong to a world are
+/// rendered through every camera.
+pub fn sys_render_particles(
+    query: Query<(Option<&InsideWorld>, &ParticleEmitter)>,
+    camera: Res<ActiveCamera>,
+) {
+    let _guard = camera.apply();
+
+    let mut batch = QuadBatch::default();
+    for (world, emitter) in query.iter() {
+        if world.is_some_and(|&InsideWorld(world)| !camera.shows(world)) {
+            continue;
+        }
+
+        emitter.render(&mut batch);
+    }
+    batch.flush();
+}
@@ -0,0 +1,223 @@
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    event::{Event, EventWriter},
+    system::{Commands, Query},
+};
+use macroquad::math::Vec2;
+
+use crate::{
+    game::{
+        actor::{camera::VirtualCamera, kinematic::Pos},
+        math::scalar::lerp_f32,
+    },
+    util::{
+        arena::{ObjOwner, RandomAccess},
+        timer::Timer,
+    },
+};
+
+// === Easing === //
+
+/// Maps the linear progress of a [`Transition`] to how far along its value is.
+///
+/// See [easings.net](https://easings.net) for what each curve looks like.
+#[derive(Debug, Copy, Clone, Default, Hash, Eq, PartialEq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+}
+
+impl Easing {
+    /// Eases `t`, which is clamped to the range `0.0` to `1.0`. The curves all start at zero and end
+    /// at one.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1. - (1. - t).powi(2),
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2. * t * t
+                } else {
+                    1. - (-2. * t + 2.).powi(2) / 2.
+                }
+            }
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1. - (1. - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4. * t * t * t
+                } else {
+                    1. - (-2. * t + 2.).powi(3) / 2.
+                }
+            }
+        }
+    }
+}
+
+/// A value which can be interpolated by a [`Transition`].
+pub trait Lerp: Copy {
+    fn lerp(self, to: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        lerp_f32(self, to, t)
+    }
+}
+
+impl Lerp for Vec2 {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        Vec2::lerp(self, to, t)
+    }
+}
+
+// === Transition === //
+
+/// Eases a value from `from` to `to` over a number of ticks. Like a [`Timer`], transitions can be
+/// stored anywhere and ticked manually, or attached to an entity through a [`Tween`].
+#[derive(Debug, Copy, Clone)]
+pub struct Transition<T> {
+    pub from: T,
+    pub to: T,
+    pub easing: Easing,
+    timer: Timer,
+}
+
+impl<T: Lerp> Transition<T> {
+    pub fn new(from: T, to: T, duration: u32, easing: Easing) -> Self {
+        Self {
+            from,
+            to,
+            easing,
+            timer: Timer::once(duration),
+        }
+    }
+
+    /// A transition which has already finished at `value`.
+    pub fn settled(value: T) -> Self {
+        Self::new(value, value, 0, Easing::Linear)
+    }
+
+    pub fn value(&self) -> T {
+        self.from
+            .lerp(self.to, self.easing.apply(self.timer.fraction()))
+    }
+
+    pub fn timer(&self) -> &Timer {
+        &self.timer
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.timer.finished()
+    }
+
+    /// Advances the transition by a single tick, returning whether it has finished.
+    pub fn tick(&mut self) -> bool {
+        self.timer.tick();
+        self.is_finished()
+    }
+
+    /// Starts a new transition to `to` from `from` which keeps the duration and easing of this one.
+    pub fn restart(&mut self, from: T, to: T) {
+        self.from = from;
+        self.to = to;
+        self.timer.reset();
+    }
+}
+
+// === Tween === //
+
+/// Drives a field of the entity it's attached to with a [`Transition`]. The tween is removed once it
+/// finishes, sending a [`TweenFinished`] event. An entity can only have one tween at a time.
+#[derive(Debug, Clone, Component)]
+pub enum Tween {
+    /// Moves the entity's [`Pos`].
+    Pos(Transition<Vec2>),
+
+    /// Fades the entity's [`Opacity`].
+    Opacity(Transition<f32>),
+
+    /// Zooms the [camera](VirtualCamera) owned by the entity, as in
+    /// [`VirtualCameraConstraints::zoom`](crate::game::actor::camera::VirtualCameraConstraints::zoom).
+    CameraZoom(Transition<f32>),
+}
+
+impl Tween {
+    /// Advances the tween by a single tick, returning whether it's finished.
+    fn tick(&mut self) -> bool {
+        match self {
+            Tween::Pos(transition) => transition.tick(),
+            Tween::Opacity(transition) | Tween::CameraZoom(transition) => transition.tick(),
+        }
+    }
+}
+
+/// Sent once the [`Tween`] of `entity` finishes.
+#[derive(Debug, Event)]
+pub struct TweenFinished {
+    pub entity: Entity,
+}
+
+/// Multiplies the alpha of the colors an entity is drawn in, for the renderers which support it.
+#[derive(Debug, Copy, Clone, Component)]
+pub struct Opacity(pub f32);
+
+impl Default for Opacity {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+// === Systems === //
+
+pub fn sys_advance_tweens(
+    mut rand: RandomAccess<&mut VirtualCamera>,
+    mut query: Query<(
+        Entity,
+        &mut Tween,
+        Option<&mut Pos>,
+        Option<&mut Opacity>,
+        Option<&ObjOwner<VirtualCamera>>,
+    )>,
+    mut finished: EventWriter<TweenFinished>,
+    mut commands: Commands,
+) {
+    rand.provide(|| {
+        for (entity, mut tween, pos, opacity, camera) in query.iter_mut() {
+            let is_finished = tween.tick();
+
+            match &*tween {
+                Tween::Pos(transition) => {
+                    if let Some(mut pos) = pos {
+                        pos.0 = transition.value();
+                    }
+                }
+                Tween::Opacity(transition) => {
+                    if let Some(mut opacity) = opacity {
+                        opacity.0 = transition.value();
+                    }
+                }
+                Tween::CameraZoom(transition) => {
+                    if let Some(&ObjOwner(mut camera)) = camera {
+                        camera.constraints_mut().zoom = transition.value();
+                    }
+                }
+            }
+
+            if is_finished {
+                commands.entity(entity).remove::<Tween>();
+                finished.send(TweenFinished { entity });
+            }
+        }
+    });
+}
@@ -0,0 +1,724 @@
+use std::fmt;
+
+use bevy_ecs::system::{NonSendMut, Res, ResMut, Resource, SystemParam};
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+use macroquad::{
+    color::{Color, GRAY, WHITE, YELLOW},
+    input::{
+        get_last_key_pressed, is_key_down, is_key_pressed, is_key_released, is_mouse_button_down,
+        is_mouse_button_pressed, is_mouse_button_released, KeyCode, MouseButton,
+    },
+    math::Vec2,
+    shapes::draw_rectangle,
+    text::draw_text,
+    window::screen_height,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+use smallvec::{smallvec, SmallVec};
+
+use super::settings::Settings;
+
+// === InputAction === //
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum InputAction {
+    MoveLeft,
+    MoveRight,
+    Jump,
+    MoveDown,
+    BreakTile,
+    UseItem,
+    NextItem,
+    PreviousItem,
+}
+
+impl InputAction {
+    pub const ALL: [Self; 8] = [
+        Self::MoveLeft,
+        Self::MoveRight,
+        Self::Jump,
+        Self::MoveDown,
+        Self::BreakTile,
+        Self::UseItem,
+        Self::NextItem,
+        Self::PreviousItem,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::MoveLeft => "Move left",
+            Self::MoveRight => "Move right",
+            Self::Jump => "Jump",
+            Self::MoveDown => "Move down",
+            Self::BreakTile => "Break tile",
+            Self::UseItem => "Use item",
+            Self::NextItem => "Next item",
+            Self::PreviousItem => "Previous item",
+        }
+    }
+
+    /// The name by which the action is referred to in the settings file.
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::MoveLeft => "move_left",
+            Self::MoveRight => "move_right",
+            Self::Jump => "jump",
+            Self::MoveDown => "move_down",
+            Self::BreakTile => "break_tile",
+            Self::UseItem => "use_item",
+            Self::NextItem => "next_item",
+            Self::PreviousItem => "previous_item",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.id() == id)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(Button),
+}
+
+impl InputBinding {
+    pub fn is_down(self, gamepad: &GamepadState) -> bool {
+        match self {
+            Self::Key(key) => is_key_down(key),
+            Self::Mouse(button) => is_mouse_button_down(button),
+            Self::Gamepad(button) => gamepad.is_down(button),
+        }
+    }
+
+    pub fn is_pressed(self, gamepad: &GamepadState) -> bool {
+        match self {
+            Self::Key(key) => is_key_pressed(key),
+            Self::Mouse(button) => is_mouse_button_pressed(button),
+            Self::Gamepad(button) => gamepad.is_pressed(button),
+        }
+    }
+
+    pub fn is_released(self, gamepad: &GamepadState) -> bool {
+        match self {
+            Self::Key(key) => is_key_released(key),
+            Self::Mouse(button) => is_mouse_button_released(button),
+            Self::Gamepad(button) => gamepad.is_released(button),
+        }
+    }
+
+    /// Parses a binding written as `key:<name>`, `mouse:<name>`, or `pad:<name>`, as produced by
+    /// its [`Display`](fmt::Display) implementation.
+    pub fn parse(text: &str) -> Option<Self> {
+        fn find<T: Copy + fmt::Debug>(options: &[T], name: &str) -> Option<T> {
+            options
+                .iter()
+                .copied()
+                .find(|option| format!("{option:?}") == name)
+        }
+
+        let (kind, name) = text.split_once(':')?;
+        match kind {
+            "key" => find(NAMED_KEYS, name).map(Self::Key),
+            "mouse" => find(
+                &[MouseButton::Left, MouseButton::Right, MouseButton::Middle],
+                name,
+            )
+            .map(Self::Mouse),
+            "pad" => find(NAMED_BUTTONS, name).map(Self::Gamepad),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for InputBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Key(key) => write!(f, "key:{key:?}"),
+            Self::Mouse(button) => write!(f, "mouse:{button:?}"),
+            Self::Gamepad(button) => write!(f, "pad:{button:?}"),
+        }
+    }
+}
+
+/// The keys which [`InputBinding::parse`] recognizes by name.
+const NAMED_KEYS: &[KeyCode] = {
+    use KeyCode::*;
+
+    &[
+        Space,
+        Apostrophe,
+        Comma,
+        Minus,
+        Period,
+        Slash,
+        Key0,
+        Key1,
+        Key2,
+        Key3,
+        Key4,
+        Key5,
+        Key6,
+        Key7,
+        Key8,
+        Key9,
+        Semicolon,
+        Equal,
+        A,
+        B,
+        C,
+        D,
+        E,
+        F,
+        G,
+        H,
+        I,
+        J,
+        K,
+        L,
+        M,
+        N,
+        O,
+        P,
+        Q,
+        R,
+        S,
+        T,
+        U,
+        V,
+        W,
+        X,
+        Y,
+        Z,
+        LeftBracket,
+        Backslash,
+        RightBracket,
+        GraveAccent,
+        Escape,
+        Enter,
+        Tab,
+        Backspace,
+        Insert,
+        Delete,
+        Right,
+        Left,
+        Down,
+        Up,
+        PageUp,
+        PageDown,
+        Home,
+        End,
+        CapsLock,
+        ScrollLock,
+        NumLock,
+        PrintScreen,
+        Pause,
+        F1,
+        F2,
+        F3,
+        F4,
+        F5,
+        F6,
+        F7,
+        F8,
+        F9,
+        F10,
+        F11,
+        F12,
+        Kp0,
+        Kp1,
+        Kp2,
+        Kp3,
+        Kp4,
+        Kp5,
+        Kp6,
+        Kp7,
+        Kp8,
+        Kp9,
+        KpDecimal,
+        KpDivide,
+        KpMultiply,
+        KpSubtract,
+        KpAdd,
+        KpEnter,
+        KpEqual,
+        LeftShift,
+        LeftControl,
+        LeftAlt,
+        LeftSuper,
+        RightShift,
+        RightControl,
+        RightAlt,
+        RightSuper,
+        Menu,
+    ]
+};
+
+/// The gamepad buttons which [`InputBinding::parse`] recognizes by name.
+const NAMED_BUTTONS: &[Button] = {
+    use Button::*;
+
+    &[
+        South,
+        East,
+        North,
+        West,
+        C,
+        Z,
+        LeftTrigger,
+        LeftTrigger2,
+        RightTrigger,
+        RightTrigger2,
+        Select,
+        Start,
+        Mode,
+        LeftThumb,
+        RightThumb,
+        DPadUp,
+        DPadDown,
+        DPadLeft,
+        DPadRight,
+    ]
+};
+
+// === InputMap === //
+
+/// The bindings of each [`InputAction`]. An action is held if any of its bindings are.
+#[derive(Debug, Clone, Resource)]
+pub struct InputMap {
+    bindings: FxHashMap<InputAction, SmallVec<[InputBinding; 3]>>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use InputBinding::*;
+
+        let mut map = Self {
+            bindings: FxHashMap::default(),
+        };
+        map.set_bindings(
+            InputAction::MoveLeft,
+            [Key(KeyCode::A), Gamepad(Button::DPadLeft)],
+        );
+        map.set_bindings(
+            InputAction::MoveRight,
+            [Key(KeyCode::D), Gamepad(Button::DPadRight)],
+        );
+        map.set_bindings(
+            InputAction::Jump,
+            [
+                Key(KeyCode::W),
+                Gamepad(Button::DPadUp),
+                Gamepad(Button::South),
+            ],
+        );
+        map.set_bindings(
+            InputAction::MoveDown,
+            [Key(KeyCode::S), Gamepad(Button::DPadDown)],
+        );
+        map.set_bindings(
+            InputAction::BreakTile,
+            [Mouse(MouseButton::Left), Gamepad(Button::RightTrigger2)],
+        );
+        map.set_bindings(
+            InputAction::UseItem,
+            [Mouse(MouseButton::Right), Gamepad(Button::LeftTrigger2)],
+        );
+        map.set_bindings(
+            InputAction::NextItem,
+            [Key(KeyCode::E), Gamepad(Button::RightTrigger)],
+        );
+        map.set_bindings(
+            InputAction::PreviousItem,
+            [Key(KeyCode::Q), Gamepad(Button::LeftTrigger)],
+        );
+        map
+    }
+}
+
+impl InputMap {
+    pub fn bindings(&self, action: InputAction) -> &[InputBinding] {
+        self.bindings.get(&action).map_or(&[], |bindings| bindings)
+    }
+
+    pub fn set_bindings(
+        &mut self,
+        action: InputAction,
+        bindings: impl IntoIterator<Item = InputBinding>,
+    ) {
+        self.bindings.insert(action, bindings.into_iter().collect());
+    }
+
+    pub fn bind(&mut self, action: InputAction, binding: InputBinding) {
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Replaces every binding of `action` with `binding`.
+    pub fn rebind(&mut self, action: InputAction, binding: InputBinding) {
+        self.bindings.insert(action, smallvec![binding]);
+    }
+}
+
+// === Gamepad === //
+
+/// The `gilrs` context. Some platforms only allow polling gamepads from the main thread.
+pub struct GamepadBackend {
+    gilrs: Option<Gilrs>,
+}
+
+impl Default for GamepadBackend {
+    fn default() -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(err) => {
+                log::warn!("Gamepad support is unavailable: {err}");
+                None
+            }
+        };
+
+        Self { gilrs }
+    }
+}
+
+/// A snapshot of the most recently used gamepad, refreshed once per frame.
+#[derive(Debug, Resource)]
+pub struct GamepadState {
+    /// Stick deflections shorter than this are ignored.
+    pub deadzone: f32,
+
+    active: Option<(GamepadId, String)>,
+    stick: Vec2,
+    held: FxHashSet<Button>,
+    pressed: FxHashSet<Button>,
+    released: FxHashSet<Button>,
+    last_pressed: Option<Button>,
+}
+
+impl Default for GamepadState {
+    fn default() -> Self {
+        Self {
+            deadzone: 0.2,
+            active: None,
+            stick: Vec2::ZERO,
+            held: FxHashSet::default(),
+            pressed: FxHashSet::default(),
+            released: FxHashSet::default(),
+            last_pressed: None,
+        }
+    }
+}
+
+impl GamepadState {
+    /// The name of the gamepad being read, if any are connected.
+    pub fn name(&self) -> Option<&str> {
+        self.active.as_ref().map(|(_, name)| name.as_str())
+    }
+
+    /// The left stick's deflection in screen-space with the deadzone removed. Its length never
+    /// exceeds one.
+    pub fn stick(&self) -> Vec2 {
+        let len = self.stick.length();
+        if len <= self.deadzone {
+            return Vec2::ZERO;
+        }
+
+        let scaled = ((len - self.deadzone) / (1. - self.deadzone)).min(1.);
+        self.stick * (scaled / len)
+    }
+
+    pub fn is_down(&self, button: Button) -> bool {
+        self.held.contains(&button)
+    }
+
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    pub fn is_released(&self, button: Button) -> bool {
+        self.released.contains(&button)
+    }
+
+    /// The last button to have been pressed this frame.
+    pub fn last_pressed(&self) -> Option<Button> {
+        self.last_pressed
+    }
+
+    fn release_all(&mut self) {
+        self.released.extend(self.held.drain());
+        self.stick = Vec2::ZERO;
+    }
+}
+
+// === Actions === //
+
+/// Reads the state of each [`InputAction`] across every input device.
+#[derive(SystemParam)]
+pub struct Actions<'w> {
+    pub map: Res<'w, InputMap>,
+    pub gamepad: Res<'w, GamepadState>,
+}
+
+impl Actions<'_> {
+    pub fn is_down(&self, action: InputAction) -> bool {
+        self.map
+            .bindings(action)
+            .iter()
+            .any(|binding| binding.is_down(&self.gamepad))
+    }
+
+    pub fn is_just_pressed(&self, action: InputAction) -> bool {
+        self.map
+            .bindings(action)
+            .iter()
+            .any(|binding| binding.is_pressed(&self.gamepad))
+    }
+
+    pub fn is_just_released(&self, action: InputAction) -> bool {
+        self.map
+            .bindings(action)
+            .iter()
+            .any(|binding| binding.is_released(&self.gamepad))
+    }
+
+    /// Combines the movement actions and the left stick into a heading no longer than one.
+    pub fn movement(&self) -> Vec2 {
+        let mut heading = Vec2::ZERO;
+        if self.is_down(InputAction::MoveLeft) {
+            heading += Vec2::NEG_X;
+        }
+        if self.is_down(InputAction::MoveRight) {
+            heading += Vec2::X;
+        }
+        if self.is_down(InputAction::Jump) {
+            heading += Vec2::NEG_Y;
+        }
+        if self.is_down(InputAction::MoveDown) {
+            heading += Vec2::Y;
+        }
+
+        (heading.normalize_or_zero() + self.gamepad.stick()).clamp_length_max(1.)
+    }
+}
+
+// === Systems === //
+
+pub fn sys_poll_gamepad(mut backend: NonSendMut<GamepadBackend>, mut state: ResMut<GamepadState>) {
+    state.pressed.clear();
+    state.released.clear();
+    state.last_pressed = None;
+
+    let Some(gilrs) = &mut backend.gilrs else {
+        return;
+    };
+
+    while let Some(event) = gilrs.next_event() {
+        // Follow whichever gamepad was used last.
+        let is_active = state.active.as_ref().map(|&(id, _)| id) == Some(event.id);
+
+        match event.event {
+            EventType::ButtonPressed(button, _) => {
+                if !is_active {
+                    state.release_all();
+                    state.active = Some((event.id, gilrs.gamepad(event.id).name().to_string()));
+                }
+
+                state.held.insert(button);
+                state.pressed.insert(button);
+                state.last_pressed = Some(button);
+            }
+            EventType::ButtonReleased(button, _) if is_active => {
+                if state.held.remove(&button) {
+                    state.released.insert(button);
+                }
+            }
+            EventType::Connected if state.active.is_none() => {
+                let name = gilrs.gamepad(event.id).name().to_string();
+                log::info!("Connected gamepad {name:?}");
+                state.active = Some((event.id, name));
+            }
+            EventType::Disconnected if is_active => {
+                log::info!(
+                    "Disconnected gamepad {:?}",
+                    state.name().unwrap_or_default()
+                );
+                state.release_all();
+                state.active = None;
+            }
+            _ => {}
+        }
+    }
+
+    // Sample the active gamepad's stick
+    let Some(gamepad) = state
+        .active
+        .as_ref()
+        .and_then(|&(id, _)| gilrs.connected_gamepad(id))
+    else {
+        state.stick = Vec2::ZERO;
+        return;
+    };
+
+    // `gilrs` points the y-axis upwards.
+    state.stick = Vec2::new(
+        gamepad.value(Axis::LeftStickX),
+        -gamepad.value(Axis::LeftStickY),
+    );
+}
+
+// === Controls Menu === //
+
+/// The state of the in-game controls menu used to rebind actions at runtime.
+#[derive(Debug, Default, Resource)]
+pub struct ControlsMenu {
+    pub open: bool,
+
+    /// The action whose next button press becomes its new binding.
+    pub rebinding: Option<InputAction>,
+
+    has_focus: bool,
+}
+
+impl ControlsMenu {
+    /// Whether the menu took this frame's input, which gameplay and other menus should then ignore.
+    pub fn has_focus(&self) -> bool {
+        self.has_focus
+    }
+}
+
+const ACTION_KEYS: [KeyCode; InputAction::ALL.len()] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+];
+
+pub fn sys_handle_controls_menu(
+    mut menu: ResMut<ControlsMenu>,
+    mut map: ResMut<InputMap>,
+    mut settings: ResMut<Settings>,
+    gamepad: Res<GamepadState>,
+) {
+    // Keep focus on the frame the menu closes so that gameplay doesn't see the closing key.
+    menu.has_focus = menu.open;
+
+    if is_key_pressed(KeyCode::Tab) {
+        menu.open = !menu.open;
+        menu.rebinding = None;
+        menu.has_focus = true;
+    }
+
+    if !menu.open {
+        return;
+    }
+
+    // Escape cancels rebinding or closes the menu, so it can't be bound.
+    if is_key_pressed(KeyCode::Escape) {
+        if menu.rebinding.take().is_none() {
+            menu.open = false;
+        }
+        return;
+    }
+
+    // Capture the new binding
+    if let Some(action) = menu.rebinding {
+        let binding = [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+            .into_iter()
+            .find(|&button| is_mouse_button_pressed(button))
+            .map(InputBinding::Mouse)
+            .or_else(|| gamepad.last_pressed().map(InputBinding::Gamepad))
+            .or_else(|| get_last_key_pressed().map(InputBinding::Key));
+
+        if let Some(binding) = binding {
+            log::info!("Bound {} to {binding:?}", action.name());
+            map.rebind(action, binding);
+            settings.bindings.rebind(action, binding);
+            settings.request_save();
+            menu.rebinding = None;
+        }
+
+        return;
+    }
+
+    // Select the action to rebind
+    for (action, key) in InputAction::ALL.into_iter().zip(ACTION_KEYS) {
+        if is_key_pressed(key) {
+            menu.rebinding = Some(action);
+        }
+    }
+}
+
+pub fn sys_render_controls_menu(
+    menu: Res<ControlsMenu>,
+    map: Res<InputMap>,
+    gamepad: Res<GamepadState>,
+) {
+    if !menu.open {
+        return;
+    }
+
+    const LEFT: f32 = 15.;
+    const ROW_HEIGHT: f32 = 22.;
+
+    let rows = InputAction::ALL.len() + 2;
+    let top = screen_height() - ROW_HEIGHT * (rows + 1) as f32 - 15.;
+    draw_rectangle(
+        LEFT,
+        top,
+        420.,
+        ROW_HEIGHT * rows as f32 + 10.,
+        Color::new(0., 0., 0., 0.7),
+    );
+
+    let header = match menu.rebinding {
+        Some(action) => format!("Press a key for {} (Esc cancels)", action.name()),
+        None => "Controls (Tab or Esc closes, 1-8 rebinds)".to_string(),
+    };
+    draw_text(&header, LEFT + 5., top + ROW_HEIGHT, 20., GRAY);
+
+    for (i, action) in InputAction::ALL.into_iter().enumerate() {
+        let bindings = map
+            .bindings(action)
+            .iter()
+            .map(|binding| match binding {
+                InputBinding::Key(key) => format!("{key:?}"),
+                InputBinding::Mouse(button) => format!("{button:?}"),
+                InputBinding::Gamepad(button) => format!("Pad {button:?}"),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let color = if menu.rebinding == Some(action) {
+            YELLOW
+        } else {
+            WHITE
+        };
+
+        draw_text(
+            &format!("{}. {}: {bindings}", i + 1, action.name()),
+            LEFT + 5.,
+            top + ROW_HEIGHT * (i + 2) as f32,
+            20.,
+            color,
+        );
+    }
+
+    let gamepad = match gamepad.name() {
+        Some(name) => format!("Gamepad: {name}"),
+        None => "Gamepad: none connected".to_string(),
+    };
+    draw_text(
+        &gamepad,
+        LEFT + 5.,
+        top + ROW_HEIGHT * rows as f32,
+        20.,
+        GRAY,
+    );
+}
@@ -0,0 +1,69 @@
+use bevy_ecs::{
+    entity::Entity,
+    system::{Local, Query},
+};
+
+use crate::util::arena::{RandomAccess, RandomComponent};
+
+use super::{
+    spatial::{check_spatial_links, Spatial},
+    tile::{
+        collider::{TrackedCollider, TrackedColliderChunk},
+        data::{TileChunk, TileWorld},
+    },
+};
+
+// === Systems === //
+
+/// The number of ticks between two integrity checks.
+pub const INTEGRITY_CHECK_INTERVAL: u32 = 300;
+
+/// Walks the relationships between objects which are kept in sync by hand and panics with a report
+/// of every back-reference which disagrees with its forward reference. Only registered in debug
+/// builds.
+pub fn sys_check_integrity(
+    mut rand: RandomAccess<(
+        &TileWorld,
+        &TileChunk,
+        &TrackedColliderChunk,
+        &TrackedCollider,
+    )>,
+    spatials: Query<(Entity, &Spatial)>,
+    mut ticks: Local<u32>,
+) {
+    *ticks += 1;
+    if *ticks < INTEGRITY_CHECK_INTERVAL {
+        return;
+    }
+    *ticks = 0;
+
+    let mut problems = Vec::new();
+
+    rand.provide(|| {
+        for (world, ..) in TileWorld::arena().iter() {
+            world.check_links(&mut problems);
+        }
+
+        for (chunk, ..) in TileChunk::arena().iter() {
+            chunk.check_links(&mut problems);
+        }
+
+        for (chunk, ..) in TrackedColliderChunk::arena().iter() {
+            chunk.check_links(&mut problems);
+        }
+
+        for (collider, ..) in TrackedCollider::arena().iter() {
+            collider.check_links(&mut problems);
+        }
+    });
+
+    check_spatial_links(&spatials, &mut problems);
+
+    if !problems.is_empty() {
+        panic!(
+            "Found {} broken relationship(s):\n- {}",
+            problems.len(),
+            problems.join("\n- "),
+        );
+    }
+}
@@ -2,7 +2,10 @@ use std::iter;
 
 use macroquad::math::{Affine2, IVec2, Vec2};
 
-use super::glam::{AaLine, Axis2};
+use super::{
+    glam::{AaLine, Axis2, Sign, Vec2Ext},
+    shape::Contact,
+};
 
 use super::glam::{AaLineI, TileFace};
 
@@ -152,6 +155,77 @@ impl Aabb {
         pos.clamp(self.min, self.max)
     }
 
+    /// Casts a ray against this AABB using the slab method, returning the distance along `dir` (in
+    /// multiples of its length) at which the ray enters the box and the normal of the entered face.
+    /// Rays starting inside the box hit immediately with a zero normal.
+    pub fn ray_cast(self, origin: Vec2, dir: Vec2) -> Option<(f32, Vec2)> {
+        let mut t_enter = f32::NEG_INFINITY;
+        let mut t_exit = f32::INFINITY;
+        let mut normal = Vec2::ZERO;
+
+        for axis in Axis2::iter() {
+            let origin = origin.get_axis(axis);
+            let dir = dir.get_axis(axis);
+            let min = self.min.get_axis(axis);
+            let max = self.max.get_axis(axis);
+
+            if dir == 0. {
+                if origin < min || origin > max {
+                    return None;
+                }
+                continue;
+            }
+
+            let t_min = (min - origin) / dir;
+            let t_max = (max - origin) / dir;
+            let (t_near, t_far) = if t_min < t_max {
+                (t_min, t_max)
+            } else {
+                (t_max, t_min)
+            };
+
+            if t_near > t_enter {
+                t_enter = t_near;
+                normal = axis.unit_mag(-dir.signum());
+            }
+
+            t_exit = t_exit.min(t_far);
+        }
+
+        if t_exit < t_enter.max(0.) {
+            None
+        } else if t_enter < 0. {
+            Some((0., Vec2::ZERO))
+        } else {
+            Some((t_enter, normal))
+        }
+    }
+
+    /// Computes the contact between two AABBs, resolving along the axis with the least
+    /// penetration.
+    pub fn contact(self, other: Aabb) -> Option<Contact> {
+        if !self.intersects(other) {
+            return None;
+        }
+
+        let overlap_min = self.min.max(other.min);
+        let overlap_max = self.max.min(other.max);
+        let overlap = overlap_max - overlap_min;
+        let delta = self.center() - other.center();
+
+        let normal = if overlap.x < overlap.y {
+            Axis2::X.unit_mag(Sign::of_biased(delta.x).unit_mag(1.))
+        } else {
+            Axis2::Y.unit_mag(Sign::of_biased(delta.y).unit_mag(1.))
+        };
+
+        Some(Contact {
+            normal,
+            depth: overlap.x.min(overlap.y),
+            point: overlap_min.lerp(overlap_max, 0.5),
+        })
+    }
+
     pub fn grow(self, by: Vec2) -> Self {
         Self::new_centered(self.center(), self.size() + by)
     }
@@ -257,19 +331,21 @@ impl AabbI {
         }
     }
 
+    /// Iterates over the tiles in this rect row by row. Like [`contains`](Self::contains), this
+    /// treats `max` as exclusive so use [`inclusive`](Self::inclusive) to visit it as well.
     pub fn iter(mut self) -> impl Iterator<Item = IVec2> {
         self = self.normalized();
 
         let mut pos = self.min - IVec2::X;
         iter::from_fn(move || {
-            if pos.x < self.max.x {
+            if pos.x + 1 < self.max.x {
                 pos.x += 1;
             } else {
                 pos.x = self.min.x;
                 pos.y += 1;
             }
 
-            (pos.y < self.max.y).then_some(pos)
+            (pos.x < self.max.x && pos.y < self.max.y).then_some(pos)
         })
     }
 
@@ -277,6 +353,41 @@ impl AabbI {
         self.max - self.min
     }
 
+    /// Determines whether the tile at `pos` lies within this rect, which includes `min` but
+    /// excludes `max`.
+    pub fn contains(self, pos: IVec2) -> bool {
+        (self.min.cmple(pos) & pos.cmplt(self.max)).all()
+    }
+
+    pub fn translated(self, rel: IVec2) -> Self {
+        Self {
+            min: self.min + rel,
+            max: self.max + rel,
+        }
+    }
+
+    pub fn grow(self, by: IVec2) -> Self {
+        Self {
+            min: self.min - by,
+            max: self.max + by,
+        }
+    }
+
+    /// The smallest rect containing both `self` and `other`.
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// The overlap of `self` and `other`, which is empty if they don't overlap.
+    pub fn intersection(self, other: Self) -> Self {
+        let min = self.min.max(other.min);
+        let max = self.max.min(other.max).max(min);
+        Self { min, max }
+    }
+
     pub fn as_aabb(self) -> Aabb {
         Aabb {
             min: self.min.as_vec2(),
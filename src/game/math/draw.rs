@@ -1,7 +1,132 @@
-use macroquad::{color::Color, math::Vec2, shapes::draw_rectangle};
+use std::{cell::Cell, mem};
+
+use macroquad::{
+    color::Color,
+    math::Vec2,
+    models::{draw_mesh, Mesh, Vertex},
+    shapes::draw_rectangle,
+    texture::{draw_texture_ex, DrawTextureParams, Texture2D},
+};
 
 use super::aabb::Aabb;
 
+// === DrawStats === //
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DrawStats {
+    pub draw_calls: u32,
+    pub vertices: u32,
+}
+
+thread_local! {
+    static DRAW_STATS: Cell<DrawStats> = const {
+        Cell::new(DrawStats {
+            draw_calls: 0,
+            vertices: 0,
+        })
+    };
+}
+
+impl DrawStats {
+    pub fn record(vertices: usize) {
+        DRAW_STATS.with(|stats| {
+            let mut curr = stats.get();
+            curr.draw_calls += 1;
+            curr.vertices += vertices as u32;
+            stats.set(curr);
+        });
+    }
+
+    /// Takes the statistics accumulated since the last call to `take`.
+    pub fn take() -> Self {
+        DRAW_STATS.with(|stats| stats.take())
+    }
+}
+
+// === QuadBatch === //
+
+/// Accumulates axis-aligned quads and submits consecutive quads sharing the same texture as a single
+/// mesh.
+#[derive(Debug, Default)]
+pub struct QuadBatch {
+    texture: Option<Texture2D>,
+    vertices: Vec<Vertex>,
+    indices: Vec<u16>,
+}
+
+impl QuadBatch {
+    /// The maximum number of quads submitted in a single draw call. This is chosen to stay well
+    /// within macroquad's default per-draw-call geometry capacity.
+    pub const MAX_QUADS: usize = 512;
+
+    pub fn push_rect(&mut self, aabb: Aabb, color: Color) {
+        self.push_quad(None, aabb, Aabb::ZERO_TO_ONE, color);
+    }
+
+    pub fn push_textured(&mut self, texture: &Texture2D, aabb: Aabb, uv: Aabb, color: Color) {
+        self.push_quad(Some(texture), aabb, uv, color);
+    }
+
+    /// Pushes an untextured quad with arbitrary corners, which must be given in winding order.
+    pub fn push_corners(&mut self, corners: [Vec2; 4], color: Color) {
+        self.push_vertices(None, corners, Aabb::ZERO_TO_ONE, [color; 4]);
+    }
+
+    /// Pushes an untextured quad whose colors are interpolated between its corners.
+    pub fn push_gradient(&mut self, corners: [Vec2; 4], colors: [Color; 4]) {
+        self.push_vertices(None, corners, Aabb::ZERO_TO_ONE, colors);
+    }
+
+    fn push_quad(&mut self, texture: Option<&Texture2D>, aabb: Aabb, uv: Aabb, color: Color) {
+        self.push_vertices(texture, aabb.normalized().corners(), uv, [color; 4]);
+    }
+
+    fn push_vertices(
+        &mut self,
+        texture: Option<&Texture2D>,
+        corners: [Vec2; 4],
+        uv: Aabb,
+        colors: [Color; 4],
+    ) {
+        if self.texture.as_ref() != texture || self.vertices.len() >= Self::MAX_QUADS * 4 {
+            self.flush();
+            self.texture = texture.cloned();
+        }
+
+        let base = self.vertices.len() as u16;
+
+        for ((pos, uv), color) in corners.into_iter().zip(uv.corners()).zip(colors) {
+            self.vertices
+                .push(Vertex::new(pos.x, pos.y, 0., uv.x, uv.y, color));
+        }
+
+        self.indices
+            .extend([0, 1, 2, 0, 2, 3].map(|offset| base + offset));
+    }
+
+    pub fn flush(&mut self) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        let mut mesh = Mesh {
+            vertices: mem::take(&mut self.vertices),
+            indices: mem::take(&mut self.indices),
+            texture: self.texture.clone(),
+        };
+
+        draw_mesh(&mesh);
+        DrawStats::record(mesh.vertices.len());
+
+        mesh.vertices.clear();
+        mesh.indices.clear();
+        self.vertices = mesh.vertices;
+        self.indices = mesh.indices;
+    }
+}
+
+// === Immediate Helpers === //
+
 pub fn stroke_rectangle_aabb(aabb: Aabb, border: f32, color: Color) {
     draw_rectangle_aabb(
         aabb.bottom_right_to(Vec2::new(aabb.max.x, aabb.min.y + border)),
@@ -29,4 +154,20 @@ pub fn stroke_rectangle_aabb(aabb: Aabb, border: f32, color: Color) {
 pub fn draw_rectangle_aabb(aabb: Aabb, color: Color) {
     let aabb = aabb.normalized();
     draw_rectangle(aabb.x(), aabb.y(), aabb.w(), aabb.h(), color);
+    DrawStats::record(4);
+}
+
+pub fn draw_texture_aabb(texture: &Texture2D, aabb: Aabb, color: Color) {
+    let aabb = aabb.normalized();
+    draw_texture_ex(
+        texture,
+        aabb.x(),
+        aabb.y(),
+        color,
+        DrawTextureParams {
+            dest_size: Some(aabb.size()),
+            ..Default::default()
+        },
+    );
+    DrawStats::record(4);
 }
@@ -1,4 +1,6 @@
 pub mod aabb;
 pub mod draw;
 pub mod glam;
+pub mod noise;
 pub mod scalar;
+pub mod shape;
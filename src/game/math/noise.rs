@@ -0,0 +1,90 @@
+use macroquad::math::{IVec2, Vec2};
+
+use super::scalar::lerp_f32;
+
+// === Hashing === //
+
+pub fn hash_ivec2(seed: u32, pos: IVec2) -> u32 {
+    let mut h = seed ^ 0x9E37_79B9;
+    h = (h ^ pos.x as u32).wrapping_mul(0x85EB_CA6B);
+    h = h.rotate_left(13);
+    h = (h ^ pos.y as u32).wrapping_mul(0xC2B2_AE35);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7FEB_352D);
+    h ^= h >> 15;
+    h
+}
+
+pub fn hash_unit(seed: u32, pos: IVec2) -> f32 {
+    hash_ivec2(seed, pos) as f32 / u32::MAX as f32
+}
+
+// === Value Noise === //
+
+fn smooth(t: f32) -> f32 {
+    t * t * (3. - 2. * t)
+}
+
+/// Samples smooth 1D value noise in the range `[0, 1]`.
+pub fn value_noise_1d(seed: u32, x: f32) -> f32 {
+    let cell = x.floor();
+    let t = smooth(x - cell);
+    let cell = cell as i32;
+
+    lerp_f32(
+        hash_unit(seed, IVec2::new(cell, 0)),
+        hash_unit(seed, IVec2::new(cell + 1, 0)),
+        t,
+    )
+}
+
+/// Samples smooth 2D value noise in the range `[0, 1]`.
+pub fn value_noise_2d(seed: u32, pos: Vec2) -> f32 {
+    let cell = pos.floor();
+    let t = pos - cell;
+    let t = Vec2::new(smooth(t.x), smooth(t.y));
+    let cell = cell.as_ivec2();
+
+    let top = lerp_f32(hash_unit(seed, cell), hash_unit(seed, cell + IVec2::X), t.x);
+    let bottom = lerp_f32(
+        hash_unit(seed, cell + IVec2::Y),
+        hash_unit(seed, cell + IVec2::ONE),
+        t.x,
+    );
+
+    lerp_f32(top, bottom, t.y)
+}
+
+/// Sums several octaves of 1D value noise, normalized back into the range `[0, 1]`.
+pub fn fbm_1d(seed: u32, x: f32, octaves: u32) -> f32 {
+    let mut sum = 0.;
+    let mut weight = 1.;
+    let mut total = 0.;
+    let mut freq = 1.;
+
+    for octave in 0..octaves {
+        sum += value_noise_1d(seed.wrapping_add(octave), x * freq) * weight;
+        total += weight;
+        weight *= 0.5;
+        freq *= 2.;
+    }
+
+    sum / total
+}
+
+/// Sums several octaves of 2D value noise, normalized back into the range `[0, 1]`.
+pub fn fbm_2d(seed: u32, pos: Vec2, octaves: u32) -> f32 {
+    let mut sum = 0.;
+    let mut weight = 1.;
+    let mut total = 0.;
+    let mut freq = 1.;
+
+    for octave in 0..octaves {
+        sum += value_noise_2d(seed.wrapping_add(octave), pos * freq) * weight;
+        total += weight;
+        weight *= 0.5;
+        freq *= 2.;
+    }
+
+    sum / total
+}
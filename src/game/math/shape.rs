@@ -0,0 +1,224 @@
+use macroquad::math::Vec2;
+
+use super::aabb::Aabb;
+
+// === Contact === //
+
+/// Describes how two overlapping shapes intersect.
+#[derive(Debug, Copy, Clone)]
+pub struct Contact {
+    /// The unit direction in which the first shape must be pushed to separate it from the second.
+    pub normal: Vec2,
+
+    /// How far the first shape must be pushed along `normal` to separate it from the second.
+    pub depth: f32,
+
+    /// A representative point of the overlapping region.
+    pub point: Vec2,
+}
+
+impl Contact {
+    /// The same contact as seen from the second shape.
+    pub fn flipped(self) -> Self {
+        Self {
+            normal: -self.normal,
+            ..self
+        }
+    }
+
+    /// The translation which separates the first shape from the second.
+    pub fn separation(self) -> Vec2 {
+        self.normal * self.depth
+    }
+}
+
+// === Circle === //
+
+#[derive(Debug, Copy, Clone)]
+pub struct Circle {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+impl Circle {
+    pub fn new(center: Vec2, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    pub fn aabb(self) -> Aabb {
+        Aabb::new_centered(self.center, Vec2::splat(self.radius * 2.))
+    }
+
+    pub fn translated(self, rel: Vec2) -> Self {
+        Self::new(self.center + rel, self.radius)
+    }
+
+    pub fn contains(self, point: Vec2) -> bool {
+        self.center.distance_squared(point) <= self.radius * self.radius
+    }
+
+    pub fn intersects(self, other: Circle) -> bool {
+        let reach = self.radius + other.radius;
+        self.center.distance_squared(other.center) <= reach * reach
+    }
+
+    pub fn intersects_aabb(self, aabb: Aabb) -> bool {
+        self.contains(aabb.clamp(self.center))
+    }
+
+    /// Computes the contact between two circles.
+    pub fn contact(self, other: Circle) -> Option<Contact> {
+        if !self.intersects(other) {
+            return None;
+        }
+
+        let delta = self.center - other.center;
+        let dist = delta.length();
+
+        // Concentric circles have no preferred direction so we pick an arbitrary one.
+        let normal = if dist > 0. { delta / dist } else { Vec2::Y };
+
+        Some(Contact {
+            normal,
+            depth: self.radius + other.radius - dist,
+            point: other.center + normal * other.radius,
+        })
+    }
+
+    /// Computes the contact between the circle and an AABB. Circles whose center lies within the
+    /// box are pushed out through the nearest face.
+    pub fn contact_aabb(self, aabb: Aabb) -> Option<Contact> {
+        let closest = aabb.clamp(self.center);
+        let delta = self.center - closest;
+        let dist_sq = delta.length_squared();
+
+        if dist_sq > self.radius * self.radius {
+            return None;
+        }
+
+        if dist_sq > 0. {
+            let dist = dist_sq.sqrt();
+
+            return Some(Contact {
+                normal: delta / dist,
+                depth: self.radius - dist,
+                point: closest,
+            });
+        }
+
+        // The center is inside the box so find the face it's closest to.
+        let to_min = self.center - aabb.min;
+        let to_max = aabb.max - self.center;
+
+        let (dist, normal) = [
+            (to_min.x, Vec2::NEG_X),
+            (to_max.x, Vec2::X),
+            (to_min.y, Vec2::NEG_Y),
+            (to_max.y, Vec2::Y),
+        ]
+        .into_iter()
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .unwrap();
+
+        Some(Contact {
+            normal,
+            depth: self.radius + dist,
+            point: self.center + normal * dist,
+        })
+    }
+}
+
+// === Segment === //
+
+#[derive(Debug, Copy, Clone)]
+pub struct Segment {
+    pub start: Vec2,
+    pub end: Vec2,
+}
+
+impl Segment {
+    pub fn new(start: Vec2, end: Vec2) -> Self {
+        Self { start, end }
+    }
+
+    pub fn delta(self) -> Vec2 {
+        self.end - self.start
+    }
+
+    pub fn length(self) -> f32 {
+        self.delta().length()
+    }
+
+    pub fn aabb(self) -> Aabb {
+        Aabb {
+            min: self.start.min(self.end),
+            max: self.start.max(self.end),
+        }
+    }
+
+    /// The point along the segment at `t`, where `0` is the start and `1` is the end.
+    pub fn point_at(self, t: f32) -> Vec2 {
+        self.start.lerp(self.end, t)
+    }
+
+    /// The fraction along the segment of the point closest to `point`.
+    pub fn closest_t(self, point: Vec2) -> f32 {
+        let delta = self.delta();
+        let len_sq = delta.length_squared();
+
+        if len_sq == 0. {
+            return 0.;
+        }
+
+        ((point - self.start).dot(delta) / len_sq).clamp(0., 1.)
+    }
+
+    pub fn closest_point(self, point: Vec2) -> Vec2 {
+        self.point_at(self.closest_t(point))
+    }
+
+    /// Casts the segment against an AABB, returning the fraction along the segment at which it
+    /// enters the box and the normal of the entered face. Segments starting inside the box hit
+    /// immediately with a zero normal.
+    pub fn cast_aabb(self, aabb: Aabb) -> Option<(f32, Vec2)> {
+        aabb.ray_cast(self.start, self.delta())
+            .filter(|&(t, _)| t <= 1.)
+    }
+
+    pub fn intersects_aabb(self, aabb: Aabb) -> bool {
+        self.cast_aabb(aabb).is_some()
+    }
+
+    /// Casts the segment against a circle, returning the fraction along the segment at which it
+    /// enters the circle and the circle's normal at that point. Segments starting inside the
+    /// circle hit immediately with a zero normal.
+    pub fn cast_circle(self, circle: Circle) -> Option<(f32, Vec2)> {
+        if circle.contains(self.start) {
+            return Some((0., Vec2::ZERO));
+        }
+
+        // Solve `|start + delta * t - center|² = radius²` for the smallest positive `t`.
+        let delta = self.delta();
+        let rel = self.start - circle.center;
+        let a = delta.length_squared();
+        let b = 2. * rel.dot(delta);
+        let c = rel.length_squared() - circle.radius * circle.radius;
+        let disc = b * b - 4. * a * c;
+
+        if a == 0. || disc < 0. {
+            return None;
+        }
+
+        let t = (-b - disc.sqrt()) / (2. * a);
+        if !(0. ..=1.).contains(&t) {
+            return None;
+        }
+
+        let normal = (self.point_at(t) - circle.center).normalize_or_zero();
+        Some((t, normal))
+    }
+
+    pub fn intersects_circle(self, circle: Circle) -> bool {
+        circle.contains(self.closest_point(circle.center))
+    }
+}
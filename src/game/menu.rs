@@ -0,0 +1,412 @@
+use bevy_app::AppExit;
+use bevy_ecs::{
+    event::EventWriter,
+    schedule::{NextState, State},
+    system::{Res, ResMut, Resource},
+};
+use macroquad::{
+    color::{Color, GRAY, WHITE},
+    input::{is_key_pressed, KeyCode},
+    math::Vec2,
+    text::draw_text,
+    texture::{draw_texture_ex, DrawTextureParams, Texture2D},
+    window::{screen_height, screen_width},
+};
+
+use super::{
+    input::ControlsMenu,
+    math::{
+        aabb::Aabb,
+        draw::{draw_rectangle_aabb, stroke_rectangle_aabb},
+    },
+    rules::{GameOutcome, GameStats},
+    save::{RestoreRequest, SaveBackup, SaveConfig, SaveIndex, SaveKind, SaveState, SaveThumbnail},
+    scene::GameScene,
+    ui::{
+        layout::screen_rect,
+        widget::{button_column, Button, Label, BUTTON_GAP},
+    },
+};
+
+// === Main Menu === //
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+enum MainMenuItem {
+    NewGame,
+    Load,
+    Quit,
+}
+
+impl MainMenuItem {
+    const ALL: [Self; 3] = [Self::NewGame, Self::Load, Self::Quit];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::NewGame => "New Game",
+            Self::Load => "Load",
+            Self::Quit => "Quit",
+        }
+    }
+}
+
+fn main_menu_buttons() -> impl Iterator<Item = (MainMenuItem, Button<'static>)> {
+    let labels = MainMenuItem::ALL.map(MainMenuItem::label);
+    MainMenuItem::ALL
+        .into_iter()
+        .zip(button_column(&labels, screen_height() / 2.))
+}
+
+// === Load Menu === //
+
+/// The list of save slots shown after picking "Load" in the main menu.
+#[derive(Default, Resource)]
+pub struct LoadMenu {
+    slots: Option<Vec<LoadMenuSlot>>,
+}
+
+struct LoadMenuSlot {
+    name: String,
+    details: String,
+    thumbnail: Option<SaveThumbnail>,
+
+    /// The thumbnail once it has been uploaded by the render system.
+    texture: Option<Texture2D>,
+}
+
+impl LoadMenu {
+    /// The maximum number of slots listed, most recently saved first.
+    pub const MAX_SLOTS: usize = 6;
+
+    pub fn is_open(&self) -> bool {
+        self.slots.is_some()
+    }
+
+    /// Lists the slots recorded in the save index along with the thumbnail of their latest backup.
+    pub fn open(&mut self, config: &SaveConfig) {
+        let index = match SaveIndex::load(&config.root) {
+            Ok(index) => index,
+            Err(err) => {
+                log::error!("Failed to read the save index: {err}");
+                SaveIndex::default()
+            }
+        };
+
+        let slots = index
+            .slots
+            .into_iter()
+            .take(Self::MAX_SLOTS)
+            .map(|slot| {
+                let thumbnail = SaveBackup::list(&config.root.join(&slot.name))
+                    .ok()
+                    .and_then(|backups| backups.into_iter().next())
+                    .and_then(|backup| match backup.thumbnail() {
+                        Ok(thumbnail) => thumbnail,
+                        Err(err) => {
+                            log::warn!("Failed to read the thumbnail of {}: {err}", slot.name);
+                            None
+                        }
+                    });
+
+                LoadMenuSlot {
+                    details: format!(
+                        "Played {}, saved {}",
+                        slot.latest.playtime_label(),
+                        slot.latest.age_label()
+                    ),
+                    name: slot.name,
+                    thumbnail,
+                    texture: None,
+                }
+            })
+            .collect();
+
+        self.slots = Some(slots);
+    }
+
+    pub fn close(&mut self) {
+        self.slots = None;
+    }
+}
+
+/// Lays out one button per slot followed by a button returning to the main menu.
+fn load_menu_buttons(slots: &[LoadMenuSlot]) -> Vec<Button<'_>> {
+    let labels = slots
+        .iter()
+        .map(|slot| slot.name.as_str())
+        .chain(["Back"])
+        .collect::<Vec<_>>();
+
+    button_column(&labels, screen_height() / 3.)
+}
+
+// === Pause Menu === //
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+enum PauseMenuItem {
+    Resume,
+    Save,
+    MainMenu,
+}
+
+impl PauseMenuItem {
+    const ALL: [Self; 3] = [Self::Resume, Self::Save, Self::MainMenu];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Resume => "Resume",
+            Self::Save => "Save",
+            Self::MainMenu => "Main Menu",
+        }
+    }
+}
+
+fn pause_menu_buttons() -> impl Iterator<Item = (PauseMenuItem, Button<'static>)> {
+    let labels = PauseMenuItem::ALL.map(PauseMenuItem::label);
+    PauseMenuItem::ALL
+        .into_iter()
+        .zip(button_column(&labels, screen_height() / 2.))
+}
+
+// === Game Over Menu === //
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+enum GameOverMenuItem {
+    MainMenu,
+}
+
+impl GameOverMenuItem {
+    const ALL: [Self; 1] = [Self::MainMenu];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::MainMenu => "Main Menu",
+        }
+    }
+}
+
+fn game_over_menu_buttons() -> impl Iterator<Item = (GameOverMenuItem, Button<'static>)> {
+    // The buttons go below the game summary.
+    let labels = GameOverMenuItem::ALL.map(GameOverMenuItem::label);
+    GameOverMenuItem::ALL
+        .into_iter()
+        .zip(button_column(&labels, screen_height() * 3. / 4.))
+}
+
+// === Systems === //
+
+pub fn sys_handle_main_menu_input(
+    mut next_scene: ResMut<NextState<GameScene>>,
+    mut saves: ResMut<SaveState>,
+    mut config: ResMut<SaveConfig>,
+    mut load_menu: ResMut<LoadMenu>,
+    mut exit: EventWriter<AppExit>,
+    stats: Res<GameStats>,
+    controls_menu: Res<ControlsMenu>,
+) {
+    if controls_menu.has_focus() {
+        return;
+    }
+
+    if let Some(slots) = &load_menu.slots {
+        if is_key_pressed(KeyCode::Escape) {
+            load_menu.close();
+            return;
+        }
+
+        let clicked = load_menu_buttons(slots)
+            .iter()
+            .position(|button| button.is_clicked());
+
+        let Some(clicked) = clicked else {
+            return;
+        };
+
+        // Every button but the last one loads a slot.
+        if let Some(slot) = slots.get(clicked) {
+            config.slot = slot.name.clone();
+            saves.pending_restore = Some(RestoreRequest::Latest);
+            next_scene.set(GameScene::InGame);
+        }
+
+        load_menu.close();
+        return;
+    }
+
+    let clicked = main_menu_buttons().find(|(_, button)| button.is_clicked());
+
+    match clicked.map(|(item, _)| item) {
+        Some(MainMenuItem::NewGame) => {
+            // Give every new game its own slot so that it doesn't overwrite older ones.
+            match SaveIndex::load(&config.root) {
+                Ok(index) => config.slot = index.fresh_slot_name(),
+                Err(err) => log::error!("Failed to read the save index: {err}"),
+            }
+
+            saves.reset_playtime(&stats);
+            next_scene.set(GameScene::InGame);
+        }
+        Some(MainMenuItem::Load) => load_menu.open(&config),
+        Some(MainMenuItem::Quit) => {
+            exit.send(AppExit);
+        }
+        None => {}
+    }
+}
+
+pub fn sys_handle_pause_menu_input(
+    scene: Res<State<GameScene>>,
+    mut next_scene: ResMut<NextState<GameScene>>,
+    mut saves: ResMut<SaveState>,
+    mut outcome: ResMut<GameOutcome>,
+    controls_menu: Res<ControlsMenu>,
+) {
+    if controls_menu.has_focus() {
+        return;
+    }
+
+    match scene.get() {
+        GameScene::MainMenu => {}
+        GameScene::InGame => {
+            if is_key_pressed(KeyCode::Escape) {
+                next_scene.set(GameScene::Paused);
+            }
+        }
+        GameScene::Paused => {
+            if is_key_pressed(KeyCode::Escape) {
+                next_scene.set(GameScene::InGame);
+                return;
+            }
+
+            let clicked = pause_menu_buttons().find(|(_, button)| button.is_clicked());
+
+            match clicked.map(|(item, _)| item) {
+                Some(PauseMenuItem::Resume) => next_scene.set(GameScene::InGame),
+                Some(PauseMenuItem::Save) => saves.pending_save = Some(SaveKind::Manual),
+                Some(PauseMenuItem::MainMenu) => {
+                    // Saves are disabled in the main menu so this is our last chance.
+                    saves.pending_save = Some(SaveKind::Auto);
+                    next_scene.set(GameScene::MainMenu);
+                }
+                None => {}
+            }
+        }
+        GameScene::GameOver => {
+            let clicked = game_over_menu_buttons().find(|(_, button)| button.is_clicked());
+
+            if let Some(GameOverMenuItem::MainMenu) = clicked.map(|(item, _)| item) {
+                // The next game starts out undecided.
+                *outcome = GameOutcome::default();
+                next_scene.set(GameScene::MainMenu);
+            }
+        }
+    }
+}
+
+pub fn sys_render_main_menu(mut load_menu: ResMut<LoadMenu>) {
+    draw_rectangle_aabb(screen_rect(), Color::new(0., 0., 0., 0.85));
+
+    if let Some(slots) = &mut load_menu.slots {
+        render_load_menu(slots);
+        return;
+    }
+
+    Label::new(
+        "Bevy Demo",
+        Vec2::new(screen_width() / 2., screen_height() / 3.),
+        64,
+        WHITE,
+    )
+    .draw();
+
+    for (_, button) in main_menu_buttons() {
+        button.draw();
+    }
+}
+
+fn render_load_menu(slots: &mut [LoadMenuSlot]) {
+    Label::new(
+        "Load Game",
+        Vec2::new(screen_width() / 2., screen_height() / 3. - 50.),
+        48,
+        WHITE,
+    )
+    .draw();
+
+    if slots.is_empty() {
+        Label::new(
+            "No saved games",
+            Vec2::new(screen_width() / 2., screen_height() / 3. - 15.),
+            20,
+            GRAY,
+        )
+        .draw();
+    }
+
+    for slot in slots.iter_mut() {
+        if slot.texture.is_none() {
+            slot.texture = slot.thumbnail.as_ref().map(SaveThumbnail::to_texture);
+        }
+    }
+
+    let buttons = load_menu_buttons(slots);
+
+    for (slot, button) in slots.iter().zip(&buttons) {
+        let bounds = button.bounds;
+
+        // Show the thumbnail to the left of the button...
+        let thumb = Aabb::new_sized(
+            Vec2::new(bounds.min.x - BUTTON_GAP - bounds.h(), bounds.min.y),
+            Vec2::splat(bounds.h()),
+        );
+        draw_rectangle_aabb(thumb, Color::new(0., 0., 0., 0.6));
+
+        if let Some(texture) = &slot.texture {
+            draw_texture_ex(
+                texture,
+                thumb.x(),
+                thumb.y(),
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(thumb.size()),
+                    ..Default::default()
+                },
+            );
+        }
+        stroke_rectangle_aabb(thumb, 2., WHITE);
+
+        // ...and its details to the right.
+        draw_text(
+            &slot.details,
+            bounds.max.x + BUTTON_GAP,
+            bounds.center().y + 6.,
+            20.,
+            WHITE,
+        );
+    }
+
+    for button in &buttons {
+        button.draw();
+    }
+}
+
+pub fn sys_render_pause_menu() {
+    draw_rectangle_aabb(screen_rect(), Color::new(0., 0., 0., 0.5));
+
+    Label::new(
+        "Paused",
+        Vec2::new(screen_width() / 2., screen_height() / 3.),
+        48,
+        WHITE,
+    )
+    .draw();
+
+    for (_, button) in pause_menu_buttons() {
+        button.draw();
+    }
+}
+
+pub fn sys_render_game_over_menu() {
+    for (_, button) in game_over_menu_buttons() {
+        button.draw();
+    }
+}
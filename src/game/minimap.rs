@@ -0,0 +1,305 @@
+use bevy_ecs::{
+    entity::Entity,
+    query::{With, Without},
+    system::{In, Query, Res, ResMut, Resource},
+};
+use macroquad::{
+    color::{Color, GRAY, ORANGE, WHITE},
+    input::{is_key_pressed, KeyCode},
+    math::{IVec2, Rect, Vec2},
+    shapes::{draw_circle, draw_rectangle, draw_rectangle_lines},
+    texture::{draw_texture_ex, DrawTextureParams, FilterMode, Image, Texture2D},
+};
+use rustc_hash::FxHashMap;
+
+use crate::util::arena::{Obj, ObjOwner, RandomAccess, RandomEntityExt};
+
+use super::{
+    actor::{
+        camera::{ActiveCamera, VirtualCamera},
+        death::Dead,
+        enemy::Enemy,
+        kinematic::Pos,
+        player::PlayerState,
+    },
+    math::aabb::{Aabb, AabbI},
+    save::SaveThumbnail,
+    tile::{
+        collider::InsideWorld,
+        data::{TileChunk, TileLayerConfig, TileWorld},
+        material::{MaterialId, MaterialRegistry},
+        render::{FluidTileMaterial, SolidTileMaterial, TexturedTileMaterial},
+    },
+};
+
+// === Minimap === //
+
+/// A small overview of the tiles around the primary camera, drawn in the top-right corner of the
+/// screen. Every chunk is rasterized into its own texture which is only redrawn once the chunk's
+/// tiles change.
+#[derive(Debug, Resource)]
+pub struct Minimap {
+    pub enabled: bool,
+
+    /// The size of a single tile on the minimap, in pixels.
+    pub tile_scale: f32,
+
+    chunks: FxHashMap<(Entity, IVec2), MinimapChunk>,
+}
+
+#[derive(Debug)]
+struct MinimapChunk {
+    version: u32,
+    texture: Texture2D,
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            tile_scale: 2.,
+            chunks: FxHashMap::default(),
+        }
+    }
+}
+
+impl Minimap {
+    /// Returns the texture of a chunk, redrawing it if the chunk has changed since it was last
+    /// drawn.
+    fn chunk_texture(
+        &mut self,
+        world: Entity,
+        pos: IVec2,
+        chunk: &TileChunk,
+        colors: &mut MaterialColors,
+    ) -> &Texture2D {
+        let entry = self.chunks.entry((world, pos)).or_insert_with(|| {
+            let edge = TileLayerConfig::CHUNK_EDGE as u16;
+            let texture = Texture2D::from_image(&Image::gen_image_color(
+                edge,
+                edge,
+                Color::new(0., 0., 0., 0.),
+            ));
+            texture.set_filter(FilterMode::Nearest);
+
+            MinimapChunk {
+                // Chunk versions are never zero so this forces the first draw.
+                version: 0,
+                texture,
+            }
+        });
+
+        if entry.version != chunk.version() {
+            let edge = TileLayerConfig::CHUNK_EDGE as u16;
+            let mut image = Image::gen_image_color(edge, edge, Color::new(0., 0., 0., 0.));
+
+            for y in 0..TileLayerConfig::CHUNK_EDGE {
+                for x in 0..TileLayerConfig::CHUNK_EDGE {
+                    let material = chunk.tile(IVec2::new(x, y));
+                    image.set_pixel(x as u32, y as u32, colors.get(material));
+                }
+            }
+
+            entry.texture.update(&image);
+            entry.version = chunk.version();
+        }
+
+        &entry.texture
+    }
+}
+
+/// Caches the color each material is drawn with on the minimap.
+struct MaterialColors<'a> {
+    registry: &'a MaterialRegistry,
+    cache: FxHashMap<MaterialId, Color>,
+}
+
+impl<'a> MaterialColors<'a> {
+    fn new(registry: &'a MaterialRegistry) -> Self {
+        Self {
+            registry,
+            cache: FxHashMap::default(),
+        }
+    }
+
+    fn get(&mut self, material: MaterialId) -> Color {
+        if material == MaterialId::AIR {
+            return Color::new(0., 0., 0., 0.);
+        }
+
+        *self.cache.entry(material).or_insert_with(|| {
+            let descriptor = self.registry.lookup(material);
+
+            if let Some(solid) = descriptor.try_get::<SolidTileMaterial>() {
+                solid.color
+            } else if let Some(textured) = descriptor.try_get::<TexturedTileMaterial>() {
+                textured.tint
+            } else if let Some(fluid) = descriptor.try_get::<FluidTileMaterial>() {
+                fluid.color
+            } else {
+                GRAY
+            }
+        })
+    }
+}
+
+// === Thumbnails === //
+
+/// The random components read when capturing a thumbnail, besides the tiles themselves and the
+/// [`MaterialRegistry`].
+pub type ThumbnailAccess = (
+    &'static SolidTileMaterial,
+    &'static TexturedTileMaterial,
+    &'static FluidTileMaterial,
+    &'static VirtualCamera,
+);
+
+/// Draws the `size` by `size` tiles around `center` in the minimap's colors, one pixel per tile.
+/// Must be called within a [`RandomAccess`] providing [`ThumbnailAccess`].
+pub fn capture_thumbnail(world: Obj<TileWorld>, center: Vec2, size: u16) -> SaveThumbnail {
+    let registry = world.entity().get::<MaterialRegistry>();
+    let mut colors = MaterialColors::new(&registry);
+    let min = center.floor().as_ivec2() - IVec2::splat(size as i32 / 2);
+
+    let mut pixels = Vec::with_capacity(size as usize * size as usize);
+    for y in 0..size as i32 {
+        for x in 0..size as i32 {
+            let material = world.tile(min + IVec2::new(x, y));
+            pixels.push(colors.get(material).into());
+        }
+    }
+
+    SaveThumbnail {
+        width: size,
+        height: size,
+        pixels,
+    }
+}
+
+// === Systems === //
+
+pub fn sys_toggle_minimap(mut minimap: ResMut<Minimap>) {
+    if is_key_pressed(KeyCode::M) {
+        minimap.enabled = !minimap.enabled;
+    }
+}
+
+pub fn sys_render_minimap(
+    In(frame): In<Aabb>,
+    mut rand: RandomAccess<(
+        &TileWorld,
+        &TileChunk,
+        &MaterialRegistry,
+        &SolidTileMaterial,
+        &TexturedTileMaterial,
+        &FluidTileMaterial,
+        &VirtualCamera,
+    )>,
+    mut minimap: ResMut<Minimap>,
+    worlds: Query<&ObjOwner<TileWorld>>,
+    players: Query<(&InsideWorld, &Pos), (With<PlayerState>, Without<Dead>)>,
+    enemies: Query<(&InsideWorld, &Pos), With<Enemy>>,
+    camera: Res<ActiveCamera>,
+) {
+    if !minimap.enabled {
+        return;
+    }
+
+    rand.provide(|| {
+        let Some(active) = camera.camera else {
+            return;
+        };
+
+        let Ok(&ObjOwner(world)) = worlds.get(active.entity()) else {
+            return;
+        };
+
+        let config = world.config();
+        let registry = world.entity().get::<MaterialRegistry>();
+        let mut colors = MaterialColors::new(&registry);
+
+        // Forget about chunks which have been unloaded.
+        minimap
+            .chunks
+            .retain(|&(owner, pos), _| owner != world.entity() || world.chunk(pos).is_some());
+
+        // Determine the region of the world covered by the minimap.
+        let center = active.transform().translation / config.size;
+        let scale = minimap.tile_scale;
+        let to_screen = |tile: Vec2| frame.center() + (tile - center) * scale;
+
+        draw_rectangle(
+            frame.x(),
+            frame.y(),
+            frame.w(),
+            frame.h(),
+            Color::new(0., 0., 0., 0.6),
+        );
+
+        let visible = Aabb::new_centered(center, frame.size() / scale);
+        let visible = AabbI {
+            min: TileLayerConfig::decompose_world_pos(visible.min.floor().as_ivec2()).0,
+            max: TileLayerConfig::decompose_world_pos(visible.max.ceil().as_ivec2()).0,
+        };
+
+        for chunk_pos in visible.inclusive().iter() {
+            let Some(chunk) = world.chunk(chunk_pos) else {
+                continue;
+            };
+
+            let origin = (chunk_pos * TileLayerConfig::CHUNK_EDGE).as_vec2();
+            let edge = TileLayerConfig::CHUNK_EDGE as f32;
+            let rect = Aabb::new_sized(to_screen(origin), Vec2::splat(edge * scale));
+
+            // Crop chunks which only partially overlap the minimap.
+            let min = rect.min.max(frame.min);
+            let max = rect.max.min(frame.max);
+            if min.x >= max.x || min.y >= max.y {
+                continue;
+            }
+
+            let source_min = (min - rect.min) / scale;
+            let source_size = (max - min) / scale;
+
+            let texture = minimap.chunk_texture(world.entity(), chunk_pos, &chunk, &mut colors);
+            draw_texture_ex(
+                texture,
+                min.x,
+                min.y,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(max - min),
+                    source: Some(Rect::new(
+                        source_min.x,
+                        source_min.y,
+                        source_size.x,
+                        source_size.y,
+                    )),
+                    ..Default::default()
+                },
+            );
+        }
+
+        // Mark actors
+        let draw_dot = |pos: Vec2, color: Color| {
+            let pos = to_screen(pos / config.size);
+            if frame.contains(pos) {
+                draw_circle(pos.x, pos.y, 3., color);
+            }
+        };
+
+        for (&InsideWorld(enemy_world), &Pos(pos)) in enemies.iter() {
+            if enemy_world == world {
+                draw_dot(pos, ORANGE);
+            }
+        }
+
+        for (&InsideWorld(player_world), &Pos(pos)) in players.iter() {
+            if player_world == world {
+                draw_dot(pos, Color::new(0.2, 1., 0.2, 1.));
+            }
+        }
+
+        draw_rectangle_lines(frame.x(), frame.y(), frame.w(), frame.h(), 2., WHITE);
+    });
+}
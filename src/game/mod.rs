@@ -1,3 +1,24 @@
 pub mod actor;
+pub mod background;
+pub mod debug_draw;
+pub mod draw_order;
+pub mod event_bus;
+pub mod fx;
+pub mod input;
+pub mod integrity;
 pub mod math;
+pub mod menu;
+pub mod minimap;
+pub mod net;
+pub mod prefab;
+pub mod replay;
+pub mod rng;
+pub mod rules;
+pub mod save;
+pub mod scene;
+pub mod script;
+pub mod settings;
+pub mod spatial;
 pub mod tile;
+pub mod time;
+pub mod ui;
@@ -0,0 +1,1037 @@
+use std::{
+    collections::{hash_map, VecDeque},
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    query::{With, Without},
+    schedule::NextState,
+    system::{Query, Res, ResMut, Resource},
+};
+use macroquad::math::{IVec2, Vec2};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    game::{
+        actor::{
+            death::SpawnPoint,
+            effects::{StatusEffectApplied, StatusEffects},
+            health::Health,
+            kinematic::{Pos, Vel},
+            player::{spawn_player, InputFrame, PlayerInput, PlayerState, RemoteInput},
+        },
+        replay::{decode_frame, encode_frame, ByteReader},
+        save::{
+            apply_regions, world_layers, RegionData, RegionKey, RestoreRequest, SaveState,
+            SavedWorld,
+        },
+        scene::GameScene,
+        tile::{
+            collider::InsideWorld,
+            data::{TileChanged, TileChunk, TileLayers, TileWorld, WorldCreatedChunk},
+            kinematic::TangibleMarker,
+            material::MaterialRegistry,
+            tile_entity::{TileEntityCreated, TileEntityDescriptor},
+        },
+    },
+    util::arena::{despawn_entity, Obj, ObjOwner, RandomAccess, SendsEvent},
+};
+
+// === Protocol === //
+
+pub const DEFAULT_PORT: u16 = 7777;
+
+const NET_MAGIC: &[u8; 4] = b"BDNT";
+const PROTOCOL_VERSION: u32 = 3;
+
+/// The largest payload a UDP datagram can carry.
+const MAX_DATAGRAM_SIZE: usize = 65_507;
+
+/// The id of the host's own player.
+const HOST_PLAYER_ID: u32 = 0;
+
+#[derive(Debug, Clone)]
+enum Packet {
+    // Client to server
+    Hello { version: u32 },
+    Input { tick: u32, frame: InputFrame },
+    RegionAck { key: RegionKey, version: u32 },
+
+    // Server to client
+    Welcome { id: u32 },
+    Players { players: Vec<PlayerSync> },
+    Region { version: u32, region: RegionData },
+
+    // Either way
+    Goodbye,
+}
+
+/// The authoritative state of a single player.
+#[derive(Debug, Copy, Clone)]
+struct PlayerSync {
+    id: u32,
+
+    /// The tick of the last input from the player's client which has been applied to this state.
+    ack: u32,
+
+    /// The [`SavedWorld`] id of the world the player is in.
+    world: u32,
+    pos: Vec2,
+    vel: Vec2,
+}
+
+impl Packet {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = NET_MAGIC.to_vec();
+
+        match self {
+            Packet::Hello { version } => {
+                bytes.push(0);
+                bytes.extend_from_slice(&version.to_le_bytes());
+            }
+            Packet::Input { tick, frame } => {
+                bytes.push(1);
+                bytes.extend_from_slice(&tick.to_le_bytes());
+                encode_frame(&mut bytes, frame);
+            }
+            Packet::Welcome { id } => {
+                bytes.push(2);
+                bytes.extend_from_slice(&id.to_le_bytes());
+            }
+            Packet::Players { players } => {
+                bytes.push(3);
+                bytes.extend_from_slice(&(players.len() as u32).to_le_bytes());
+
+                for player in players {
+                    bytes.extend_from_slice(&player.id.to_le_bytes());
+                    bytes.extend_from_slice(&player.ack.to_le_bytes());
+                    bytes.extend_from_slice(&player.world.to_le_bytes());
+
+                    for value in [player.pos.x, player.pos.y, player.vel.x, player.vel.y] {
+                        bytes.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+            }
+            Packet::Region { version, region } => {
+                bytes.push(4);
+                bytes.extend_from_slice(&version.to_le_bytes());
+                bytes.extend_from_slice(&region.world.to_le_bytes());
+                bytes.extend_from_slice(&region.encode());
+            }
+            Packet::Goodbye => bytes.push(5),
+            Packet::RegionAck {
+                key: (world, layer, pos),
+                version,
+            } => {
+                bytes.push(6);
+                bytes.extend_from_slice(&world.to_le_bytes());
+                bytes.extend_from_slice(&layer.to_le_bytes());
+                bytes.extend_from_slice(&pos.x.to_le_bytes());
+                bytes.extend_from_slice(&pos.y.to_le_bytes());
+                bytes.extend_from_slice(&version.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> io::Result<Self> {
+        let mut reader = ByteReader { bytes };
+
+        if &reader.array::<4>()? != NET_MAGIC {
+            return Err(invalid_packet("packet has a bad magic number"));
+        }
+
+        Ok(match reader.u8()? {
+            0 => Packet::Hello {
+                version: reader.u32()?,
+            },
+            1 => Packet::Input {
+                tick: reader.u32()?,
+                frame: decode_frame(&mut reader)?,
+            },
+            2 => Packet::Welcome { id: reader.u32()? },
+            3 => {
+                let count = reader.u32()?;
+                let mut players = Vec::new();
+
+                for _ in 0..count {
+                    players.push(PlayerSync {
+                        id: reader.u32()?,
+                        ack: reader.u32()?,
+                        world: reader.u32()?,
+                        pos: Vec2::new(reader.f32()?, reader.f32()?),
+                        vel: Vec2::new(reader.f32()?, reader.f32()?),
+                    });
+                }
+
+                Packet::Players { players }
+            }
+            4 => {
+                let version = reader.u32()?;
+                let world = reader.u32()?;
+
+                Packet::Region {
+                    version,
+                    region: RegionData {
+                        world,
+                        ..RegionData::decode(reader.bytes)?
+                    },
+                }
+            }
+            5 => Packet::Goodbye,
+            6 => Packet::RegionAck {
+                key: (
+                    reader.u32()?,
+                    reader.u32()?,
+                    IVec2::new(reader.i32()?, reader.i32()?),
+                ),
+                version: reader.u32()?,
+            },
+            _ => return Err(invalid_packet("packet has an unknown kind")),
+        })
+    }
+}
+
+fn invalid_packet(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn send(socket: &UdpSocket, addr: SocketAddr, packet: &Packet) {
+    if let Err(err) = socket.send_to(&packet.encode(), addr) {
+        log::warn!("Failed to send packet to {addr}: {err}");
+    }
+}
+
+/// Drains every datagram waiting on the non-blocking `socket`, skipping malformed ones.
+fn receive(socket: &UdpSocket) -> Vec<(SocketAddr, Packet)> {
+    let mut packets = Vec::new();
+    let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+
+    loop {
+        match socket.recv_from(&mut buf) {
+            Ok((len, addr)) => match Packet::decode(&buf[..len]) {
+                Ok(packet) => packets.push((addr, packet)),
+                Err(err) => log::debug!("Dropped malformed packet from {addr}: {err}"),
+            },
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+            // Windows reports unreachable peers as errors on the next receive.
+            Err(err) if err.kind() == io::ErrorKind::ConnectionReset => continue,
+            Err(err) => {
+                log::warn!("Failed to receive packets: {err}");
+                break;
+            }
+        }
+    }
+
+    packets
+}
+
+// === NetSession === //
+
+/// Clients which haven't been heard from in this long are dropped.
+pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The interval at which clients repeat their hello until the server welcomes them.
+pub const HELLO_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The most clients a server accepts at once, both in total and from a single host.
+pub const MAX_CLIENTS: usize = 16;
+pub const MAX_CLIENTS_PER_HOST: usize = 4;
+
+/// The maximum number of regions sent to each client per tick so that joining doesn't flood the
+/// socket's buffers.
+pub const REGIONS_PER_TICK: usize = 8;
+
+/// Regions which a client hasn't acknowledged within this long are sent again.
+pub const REGION_RESEND_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The number of inputs the server buffers for each client. A single input is applied every tick
+/// so older inputs are skipped once a client gets this far ahead.
+pub const INPUT_BUFFER: usize = 8;
+
+/// The number of ticks without any input from a client after which its player stops moving.
+pub const INPUT_STALE_TICKS: u32 = 10;
+
+/// The distance, in world units, by which a client's predicted position may differ from the
+/// server's before it's corrected.
+pub const RECONCILE_TOLERANCE: f32 = 1.;
+
+/// The number of predicted positions a client keeps around while waiting for the server to
+/// acknowledge them.
+const PREDICTION_HISTORY: usize = 256;
+
+/// The multiplayer session this game is a part of. Sessions are started from the command line with
+/// `--host [port]` or `--connect <address>`.
+///
+/// The server owns the authoritative state of its saved worlds and of every player. Clients send it
+/// their inputs and predict their own player's movement locally, correcting the prediction
+/// whenever it strays from the server's. Everything else on the client, such as enemies and
+/// bullets, is simulated locally and never synchronized.
+#[derive(Debug, Default, Resource)]
+pub struct NetSession {
+    role: Option<NetRole>,
+}
+
+#[derive(Debug)]
+enum NetRole {
+    Server(NetServer),
+    Client(NetClient),
+}
+
+#[derive(Debug)]
+struct NetServer {
+    socket: UdpSocket,
+    clients: FxHashMap<SocketAddr, RemoteClient>,
+    next_id: u32,
+
+    /// The version of every region of the saved worlds as of the last time changes were queued.
+    versions: FxHashMap<RegionKey, u32>,
+}
+
+impl NetServer {
+    fn has_room_for(&self, addr: SocketAddr) -> bool {
+        let from_host = self
+            .clients
+            .keys()
+            .filter(|other| other.ip() == addr.ip())
+            .count();
+
+        self.clients.len() < MAX_CLIENTS && from_host < MAX_CLIENTS_PER_HOST
+    }
+}
+
+#[derive(Debug)]
+struct RemoteClient {
+    id: u32,
+    player: Option<Entity>,
+    last_heard: Instant,
+
+    /// The tick of the last input applied to the client's player.
+    ack: u32,
+
+    /// The tick of the newest input received from the client, which may not have been applied yet.
+    received: u32,
+
+    /// The inputs received from the client which have yet to be applied, oldest first.
+    inputs: VecDeque<(u32, InputFrame)>,
+
+    /// The number of ticks since an input from the client was last applied.
+    starved_ticks: u32,
+
+    regions: VecDeque<RegionKey>,
+    queued: FxHashSet<RegionKey>,
+
+    /// The version of every region sent to the client which it has yet to acknowledge along with
+    /// when it was sent.
+    in_flight: FxHashMap<RegionKey, (u32, Instant)>,
+}
+
+impl RemoteClient {
+    fn queue_region(&mut self, key: RegionKey) {
+        if self.queued.insert(key) {
+            self.regions.push_back(key);
+        }
+    }
+
+    /// Queues every region which has gone unacknowledged for too long to be sent again.
+    fn requeue_unacked(&mut self, now: Instant) {
+        let unacked = self
+            .in_flight
+            .iter()
+            .filter(|(_, &(_, sent))| now.duration_since(sent) >= REGION_RESEND_INTERVAL)
+            .map(|(&key, _)| key)
+            .collect::<Vec<_>>();
+
+        for key in unacked {
+            self.queue_region(key);
+        }
+    }
+
+    /// Applies the oldest buffered input to the client's `remote` input, if there is one.
+    fn apply_input(&mut self, remote: &mut RemoteInput) {
+        if let Some((tick, frame)) = self.inputs.pop_front() {
+            remote.0 = frame;
+            self.ack = tick;
+            self.starved_ticks = 0;
+            return;
+        }
+
+        // Hotbar changes only ever apply once but movement is kept up for a few ticks so that a
+        // single late packet doesn't make the player stutter.
+        self.starved_ticks += 1;
+        remote.0.select = None;
+        remote.0.scroll = 0;
+
+        if self.starved_ticks >= INPUT_STALE_TICKS {
+            remote.0 = InputFrame::default();
+        }
+    }
+}
+
+#[derive(Debug)]
+struct NetClient {
+    socket: UdpSocket,
+    server: SocketAddr,
+    id: Option<u32>,
+    tick: u32,
+    last_hello: Option<Instant>,
+
+    /// The predicted position of the local player at the end of every tick which the server has
+    /// yet to acknowledge.
+    history: VecDeque<(u32, Vec2)>,
+}
+
+/// Marks a player which is synchronized over the network, identifying it across the session.
+#[derive(Debug, Copy, Clone, Component)]
+pub struct NetPlayer(pub u32);
+
+impl NetSession {
+    pub fn is_server(&self) -> bool {
+        matches!(self.role, Some(NetRole::Server(_)))
+    }
+
+    pub fn is_client(&self) -> bool {
+        matches!(self.role, Some(NetRole::Client(_)))
+    }
+
+    pub fn host(&mut self, port: u16) -> io::Result<()> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        socket.set_nonblocking(true)?;
+
+        self.role = Some(NetRole::Server(NetServer {
+            socket,
+            clients: FxHashMap::default(),
+            next_id: HOST_PLAYER_ID + 1,
+            versions: FxHashMap::default(),
+        }));
+
+        log::info!("Hosting a session on port {port}");
+        Ok(())
+    }
+
+    pub fn connect(&mut self, addr: &str) -> io::Result<()> {
+        let server = addr.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("{addr} has no addresses"))
+        })?;
+
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.set_nonblocking(true)?;
+
+        self.role = Some(NetRole::Client(NetClient {
+            socket,
+            server,
+            id: None,
+            tick: 0,
+            last_hello: None,
+            history: VecDeque::new(),
+        }));
+
+        log::info!("Connecting to {server}");
+        Ok(())
+    }
+}
+
+// === Systems === //
+
+pub fn sys_start_net_session(
+    mut session: ResMut<NetSession>,
+    mut saves: ResMut<SaveState>,
+    mut next_scene: ResMut<NextState<GameScene>>,
+) {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    let result = if let Some(index) = args.iter().position(|arg| arg == "--host") {
+        let port = args
+            .get(index + 1)
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(DEFAULT_PORT);
+
+        // Hosts keep playing in their own world, even when they skip the main menu.
+        saves.pending_restore.get_or_insert(RestoreRequest::Latest);
+        session.host(port)
+    } else if let Some(pair) = args.windows(2).find(|pair| pair[0] == "--connect") {
+        // Clients start from the server's world rather than their own save.
+        saves.pending_restore = None;
+        session.connect(&pair[1])
+    } else {
+        return;
+    };
+
+    match result {
+        Ok(()) => next_scene.set(GameScene::InGame),
+        Err(err) => log::error!("Failed to start the multiplayer session: {err}"),
+    }
+}
+
+pub fn sys_receive_net_messages(
+    mut rand: RandomAccess<(
+        (
+            &mut Health,
+            &mut StatusEffects,
+            &mut TangibleMarker,
+            SendsEvent<StatusEffectApplied>,
+        ),
+        &mut TileWorld,
+        &mut TileChunk,
+        &TileLayers,
+        &MaterialRegistry,
+        &TileEntityDescriptor,
+        SendsEvent<TileEntityCreated>,
+        SendsEvent<TileChanged>,
+        SendsEvent<WorldCreatedChunk>,
+    )>,
+    mut session: ResMut<NetSession>,
+    worlds: Query<(&ObjOwner<TileWorld>, &SavedWorld)>,
+    spawns: Query<(&InsideWorld, &Pos), (With<SpawnPoint>, Without<PlayerState>)>,
+    mut players: Query<
+        (
+            Entity,
+            &mut Pos,
+            &mut Vel,
+            &InsideWorld,
+            Option<&mut RemoteInput>,
+            Option<&NetPlayer>,
+        ),
+        With<PlayerState>,
+    >,
+) {
+    rand.provide(|| {
+        let worlds = saved_worlds(&worlds);
+
+        match &mut session.role {
+            Some(NetRole::Server(server)) => {
+                let spawn = spawns
+                    .iter()
+                    .next()
+                    .map(|(&InsideWorld(world), &Pos(pos))| (world, pos));
+
+                receive_as_server(server, spawn, &worlds, &mut players);
+            }
+            Some(NetRole::Client(client)) => {
+                if !receive_as_client(client, &worlds, &mut players) {
+                    session.role = None;
+                }
+            }
+            None => {}
+        }
+    });
+}
+
+/// Maps the id of every saved world to the world.
+fn saved_worlds(
+    query: &Query<(&ObjOwner<TileWorld>, &SavedWorld)>,
+) -> FxHashMap<u32, Obj<TileWorld>> {
+    query
+        .iter()
+        .map(|(&ObjOwner(world), &SavedWorld(id))| (id, world))
+        .collect()
+}
+
+fn receive_as_server(
+    server: &mut NetServer,
+    spawn: Option<(Obj<TileWorld>, Vec2)>,
+    worlds: &FxHashMap<u32, Obj<TileWorld>>,
+    players: &mut Query<
+        (
+            Entity,
+            &mut Pos,
+            &mut Vel,
+            &InsideWorld,
+            Option<&mut RemoteInput>,
+            Option<&NetPlayer>,
+        ),
+        With<PlayerState>,
+    >,
+) {
+    let now = Instant::now();
+
+    for (addr, packet) in receive(&server.socket) {
+        match packet {
+            Packet::Hello { version } => {
+                if version != PROTOCOL_VERSION {
+                    log::warn!("Rejected {addr} which speaks protocol version {version}");
+                    continue;
+                }
+
+                if !server.clients.contains_key(&addr) && !server.has_room_for(addr) {
+                    log::warn!("Rejected {addr} since the session is full");
+                    send(&server.socket, addr, &Packet::Goodbye);
+                    continue;
+                }
+
+                // Clients repeat their hello until they're welcomed so this may be a duplicate.
+                let client = match server.clients.entry(addr) {
+                    hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                    hash_map::Entry::Vacant(entry) => {
+                        let id = server.next_id;
+                        server.next_id += 1;
+                        log::info!("Player {id} joined from {addr}");
+
+                        let mut client = RemoteClient {
+                            id,
+                            player: spawn.map(|(world, pos)| {
+                                spawn_player(world, pos, (RemoteInput::default(), NetPlayer(id)))
+                            }),
+                            last_heard: now,
+                            ack: 0,
+                            received: 0,
+                            inputs: VecDeque::new(),
+                            starved_ticks: 0,
+                            regions: VecDeque::new(),
+                            queued: FxHashSet::default(),
+                            in_flight: FxHashMap::default(),
+                        };
+
+                        // Send the new client everything we've got.
+                        for (&id, &world) in worlds {
+                            for (layer, data) in world_layers(world).into_iter().enumerate() {
+                                for (pos, chunk) in data.chunks() {
+                                    if chunk.is_generated() {
+                                        client.queue_region((id, layer as u32, pos));
+                                    }
+                                }
+                            }
+                        }
+
+                        entry.insert(client)
+                    }
+                };
+
+                client.last_heard = now;
+                send(&server.socket, addr, &Packet::Welcome { id: client.id });
+            }
+            Packet::Input { tick, frame } => {
+                let Some(client) = server.clients.get_mut(&addr) else {
+                    continue;
+                };
+
+                client.last_heard = now;
+
+                // Inputs which arrive out of order are stale.
+                if tick <= client.received {
+                    continue;
+                }
+                client.received = tick;
+
+                // Clients can send anything so their inputs are checked before the simulation
+                // sees them.
+                let Some((_, pos, ..)) = client.player.and_then(|player| players.get(player).ok())
+                else {
+                    continue;
+                };
+
+                let Some(frame) = frame.sanitize(pos.0) else {
+                    log::warn!("Dropped a malformed input from player {}", client.id);
+                    continue;
+                };
+
+                client.inputs.push_back((tick, frame));
+                if client.inputs.len() > INPUT_BUFFER {
+                    client.inputs.pop_front();
+                }
+            }
+            Packet::RegionAck { key, version } => {
+                let Some(client) = server.clients.get_mut(&addr) else {
+                    continue;
+                };
+
+                client.last_heard = now;
+
+                // Acks for older versions of a region which has since been resent don't count.
+                if client
+                    .in_flight
+                    .get(&key)
+                    .is_some_and(|&(sent, _)| sent == version)
+                {
+                    client.in_flight.remove(&key);
+                }
+            }
+            Packet::Goodbye => {
+                if let Some(client) = server.clients.remove(&addr) {
+                    log::info!("Player {} left", client.id);
+
+                    if let Some(player) = client.player {
+                        despawn_entity(player);
+                    }
+                }
+            }
+            _ => log::debug!("Ignored a client-bound packet from {addr}"),
+        }
+    }
+
+    // Drive every player with a single input per tick.
+    for client in server.clients.values_mut() {
+        if let Some(Ok((.., Some(mut remote), _))) =
+            client.player.map(|player| players.get_mut(player))
+        {
+            client.apply_input(&mut remote);
+        }
+    }
+
+    // Drop clients which stopped responding.
+    server.clients.retain(|_, client| {
+        if client.last_heard.elapsed() < CLIENT_TIMEOUT {
+            return true;
+        }
+
+        log::info!("Player {} timed out", client.id);
+
+        if let Some(player) = client.player {
+            despawn_entity(player);
+        }
+        false
+    });
+}
+
+/// Returns `false` once the server has ended the session.
+fn receive_as_client(
+    client: &mut NetClient,
+    worlds: &FxHashMap<u32, Obj<TileWorld>>,
+    players: &mut Query<
+        (
+            Entity,
+            &mut Pos,
+            &mut Vel,
+            &InsideWorld,
+            Option<&mut RemoteInput>,
+            Option<&NetPlayer>,
+        ),
+        With<PlayerState>,
+    >,
+) -> bool {
+    for (addr, packet) in receive(&client.socket) {
+        if addr != client.server {
+            continue;
+        }
+
+        match packet {
+            Packet::Welcome { id } => {
+                if client.id.is_none() {
+                    log::info!("Joined {addr} as player {id}");
+                    client.id = Some(id);
+                }
+            }
+            Packet::Players { players: synced } => {
+                sync_players(client, worlds, players, &synced);
+            }
+            Packet::Region { version, region } => {
+                let Some(&world) = worlds.get(&region.world) else {
+                    continue;
+                };
+
+                let ack = Packet::RegionAck {
+                    key: region.key(),
+                    version,
+                };
+
+                apply_regions(world, [&region]);
+                send(&client.socket, addr, &ack);
+            }
+            Packet::Goodbye => {
+                log::info!("The server ended the session");
+                return false;
+            }
+            _ => log::debug!("Ignored a server-bound packet from {addr}"),
+        }
+    }
+
+    true
+}
+
+fn sync_players(
+    client: &mut NetClient,
+    worlds: &FxHashMap<u32, Obj<TileWorld>>,
+    players: &mut Query<
+        (
+            Entity,
+            &mut Pos,
+            &mut Vel,
+            &InsideWorld,
+            Option<&mut RemoteInput>,
+            Option<&NetPlayer>,
+        ),
+        With<PlayerState>,
+    >,
+    synced: &[PlayerSync],
+) {
+    let mut ghosts = FxHashMap::default();
+    let mut local = None;
+
+    for (entity, .., net) in players.iter() {
+        match net {
+            Some(&NetPlayer(id)) => {
+                ghosts.insert(id, entity);
+            }
+            None => local = Some(entity),
+        }
+    }
+
+    for sync in synced {
+        // Correct our own prediction by however far it strayed from the server as of the last
+        // input the server applied.
+        if Some(sync.id) == client.id {
+            let Some(index) = client
+                .history
+                .iter()
+                .position(|&(tick, _)| tick == sync.ack)
+            else {
+                continue;
+            };
+
+            let predicted = client.history[index].1;
+            client.history.drain(..=index);
+
+            let error = sync.pos - predicted;
+            if error.length() <= RECONCILE_TOLERANCE {
+                continue;
+            }
+
+            if let Some(Ok((_, mut pos, ..))) = local.map(|local| players.get_mut(local)) {
+                pos.0 += error;
+            }
+
+            for (_, predicted) in &mut client.history {
+                *predicted += error;
+            }
+
+            continue;
+        }
+
+        // Everyone else is placed wherever the server says they are.
+        let Some(&world) = worlds.get(&sync.world) else {
+            continue;
+        };
+
+        let ghost = ghosts.remove(&sync.id);
+        if let Some(Ok((_, mut pos, mut vel, &InsideWorld(current), ..))) =
+            ghost.map(|ghost| players.get_mut(ghost))
+        {
+            if current == world {
+                pos.0 = sync.pos;
+                vel.0 = sync.vel;
+                continue;
+            }
+        }
+
+        // Players who went through a portal are spawned again in their new world rather than
+        // moving their colliders over.
+        if let Some(ghost) = ghost {
+            despawn_entity(ghost);
+        }
+
+        spawn_player(
+            world,
+            sync.pos,
+            (RemoteInput::default(), NetPlayer(sync.id)),
+        );
+    }
+
+    // Players missing from the snapshot have left.
+    for ghost in ghosts.into_values() {
+        despawn_entity(ghost);
+    }
+}
+
+pub fn sys_send_net_messages(
+    mut rand: RandomAccess<(&TileWorld, &TileChunk, &TileLayers)>,
+    mut session: ResMut<NetSession>,
+    worlds: Query<(&ObjOwner<TileWorld>, &SavedWorld)>,
+    players: Query<
+        (
+            &Pos,
+            &Vel,
+            &InsideWorld,
+            Option<&NetPlayer>,
+            Option<&RemoteInput>,
+        ),
+        With<PlayerState>,
+    >,
+    input: Res<PlayerInput>,
+) {
+    rand.provide(|| match &mut session.role {
+        Some(NetRole::Server(server)) => {
+            send_as_server(server, &saved_worlds(&worlds), &players);
+        }
+        Some(NetRole::Client(client)) => {
+            send_as_client(client, &players, &input);
+        }
+        None => {}
+    });
+}
+
+fn send_as_server(
+    server: &mut NetServer,
+    worlds: &FxHashMap<u32, Obj<TileWorld>>,
+    players: &Query<
+        (
+            &Pos,
+            &Vel,
+            &InsideWorld,
+            Option<&NetPlayer>,
+            Option<&RemoteInput>,
+        ),
+        With<PlayerState>,
+    >,
+) {
+    let layers = worlds
+        .iter()
+        .map(|(&id, &world)| (id, world_layers(world)))
+        .collect::<FxHashMap<_, _>>();
+
+    // Queue the regions which changed since the last tick.
+    for (&id, layers) in &layers {
+        for (layer, data) in layers.iter().enumerate() {
+            for (pos, chunk) in data.chunks() {
+                if !chunk.is_generated() {
+                    continue;
+                }
+
+                let key = (id, layer as u32, pos);
+                if server.versions.insert(key, chunk.version()) == Some(chunk.version()) {
+                    continue;
+                }
+
+                for client in server.clients.values_mut() {
+                    client.queue_region(key);
+                }
+            }
+        }
+    }
+
+    // Send the state of every player.
+    let acks = server
+        .clients
+        .values()
+        .map(|client| (client.id, client.ack))
+        .collect::<FxHashMap<_, _>>();
+
+    let synced = Packet::Players {
+        players: players
+            .iter()
+            .filter_map(|(pos, vel, &InsideWorld(world), net, _)| {
+                let id = net.map_or(HOST_PLAYER_ID, |net| net.0);
+
+                // Players can only be synchronized while they're inside of a saved world.
+                let (&world, _) = worlds.iter().find(|&(_, &other)| other == world)?;
+
+                Some(PlayerSync {
+                    id,
+                    ack: acks.get(&id).copied().unwrap_or(0),
+                    world,
+                    pos: pos.0,
+                    vel: vel.0,
+                })
+            })
+            .collect(),
+    };
+
+    let now = Instant::now();
+
+    for (&addr, client) in &mut server.clients {
+        send(&server.socket, addr, &synced);
+
+        client.requeue_unacked(now);
+
+        for _ in 0..REGIONS_PER_TICK {
+            let Some(key) = client.regions.pop_front() else {
+                break;
+            };
+            client.queued.remove(&key);
+
+            let (world, layer, pos) = key;
+            let Some(chunk) = layers
+                .get(&world)
+                .and_then(|layers| layers.get(layer as usize))
+                .and_then(|data| data.chunk(pos))
+            else {
+                client.in_flight.remove(&key);
+                continue;
+            };
+
+            let version = chunk.version();
+            let region = RegionData {
+                world,
+                layer,
+                pos,
+                tiles: Box::new(*chunk.raw_tiles()),
+                explored: None,
+            };
+            send(&server.socket, addr, &Packet::Region { version, region });
+            client.in_flight.insert(key, (version, now));
+        }
+    }
+}
+
+fn send_as_client(
+    client: &mut NetClient,
+    players: &Query<
+        (
+            &Pos,
+            &Vel,
+            &InsideWorld,
+            Option<&NetPlayer>,
+            Option<&RemoteInput>,
+        ),
+        With<PlayerState>,
+    >,
+    input: &PlayerInput,
+) {
+    if client.id.is_none() {
+        if client
+            .last_hello
+            .map_or(true, |last| last.elapsed() >= HELLO_INTERVAL)
+        {
+            client.last_hello = Some(Instant::now());
+            send(
+                &client.socket,
+                client.server,
+                &Packet::Hello {
+                    version: PROTOCOL_VERSION,
+                },
+            );
+        }
+
+        return;
+    }
+
+    client.tick += 1;
+    send(
+        &client.socket,
+        client.server,
+        &Packet::Input {
+            tick: client.tick,
+            frame: input.frame,
+        },
+    );
+
+    // Remember where we predicted ourselves to be so that we can compare it against the server.
+    let local = players
+        .iter()
+        .find(|(.., net, remote)| net.is_none() && remote.is_none());
+
+    if let Some((pos, ..)) = local {
+        client.history.push_back((client.tick, pos.0));
+        if client.history.len() > PREDICTION_HISTORY {
+            client.history.pop_front();
+        }
+    }
+}
+
+pub fn sys_close_net_session(mut session: ResMut<NetSession>) {
+    match session.role.take() {
+        Some(NetRole::Server(server)) => {
+            for &addr in server.clients.keys() {
+                send(&server.socket, addr, &Packet::Goodbye);
+            }
+        }
+        Some(NetRole::Client(client)) => {
+            send(&client.socket, client.server, &Packet::Goodbye);
+        }
+        None => {}
+    }
+}
@@ -0,0 +1,320 @@
+use std::{fs, io};
+
+use bevy_ecs::{
+    bundle::Bundle,
+    component::Component,
+    entity::Entity,
+    system::{ResMut, Resource},
+};
+use macroquad::math::Vec2;
+use ron::{error::SpannedError, extensions::Extensions, Options};
+use rustc_hash::FxHashMap;
+use serde::Deserialize;
+
+use crate::{
+    game::{
+        actor::{
+            damage::{DamageResistance, Hazard},
+            death::SpawnPoint,
+            enemy::Enemy,
+            health::Health,
+            kinematic::{ColliderListens, ColliderMoves, ContinuousCollision, Gravity, Pos, Vel},
+            player::ContactDamage,
+            projectile::BulletSpawner,
+            shadow::CastsShadow,
+            wave::WaveSpawnPoint,
+        },
+        math::aabb::Aabb,
+        tile::{
+            collider::{Collider, CollisionLayers},
+            kinematic::TangibleMarker,
+        },
+    },
+    util::{
+        arena::{insert_bundle, spawn_entity, RandomEntityExt},
+        timer::Cooldown,
+    },
+};
+
+// === Prefab Files === //
+
+/// The prefabs available before any prefab file is loaded.
+const DEFAULT_PREFABS: &str = r#"
+{
+    // Flies over the terrain towards the nearest player, only colliding with tiles, and hurts
+    // players on contact.
+    "enemy": [
+        Vel(0, 0),
+        Collider(),
+        CollisionLayers(membership: ["actors"], mask: ["tiles"]),
+        ColliderMoves,
+        ColliderListens(mask: ["players"]),
+        Enemy(),
+        ContactDamage(2),
+        Health(max: 15),
+    ],
+
+    // Fires a stream of bullets in random directions.
+    "bullet_spawner": [
+        BulletSpawner,
+        Cooldown(5),
+    ],
+
+    // Where players respawn after dying.
+    "spawn_point": [
+        SpawnPoint,
+    ],
+
+    // Where the wave director spawns enemies.
+    "wave_spawn_point": [
+        WaveSpawnPoint(),
+    ],
+
+    // Hurts everything standing inside of it.
+    "damage_zone": [
+        Collider(min: (100, 100), max: (500, 500)),
+        CollisionLayers(membership: ["triggers"], mask: ["all"]),
+        ColliderListens(),
+        ContactDamage(2),
+        Hazard,
+    ],
+}
+"#;
+
+/// The prefab file, relative to the working directory. Its prefabs are added to the defaults,
+/// replacing any with the same name.
+pub const PREFABS_PATH: &str = "prefabs.ron";
+
+/// The random components a prefab may contain. Systems spawning prefabs must provide mutable
+/// access to all of them.
+pub type PrefabAccess = (&'static mut Health, &'static mut TangibleMarker);
+
+// === PrefabComponent === //
+
+/// A component of a prefab, written in prefab files like the component's Rust constructor.
+/// Components with optional fields are written with parentheses even when all of them are left
+/// out, e.g. `Enemy()`.
+#[derive(Debug, Clone, Deserialize)]
+enum PrefabComponent {
+    Pos(f32, f32),
+    Vel(f32, f32),
+    Collider(ColliderDef),
+    CollisionLayers(CollisionLayersDef),
+    ColliderMoves,
+    ColliderListens(ColliderListensDef),
+    ContinuousCollision,
+    Gravity(Gravity),
+    CastsShadow,
+    Enemy(Enemy),
+    ContactDamage(f32),
+    DamageResistance(DamageResistance),
+    Hazard,
+    BulletSpawner,
+    Cooldown(u32),
+    SpawnPoint,
+    WaveSpawnPoint(WaveSpawnPoint),
+    Health(HealthDef),
+    TangibleMarker,
+}
+
+/// A collider spanning from `min` to `max`, which default to the origin.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ColliderDef {
+    min: (f32, f32),
+    max: (f32, f32),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CollisionLayersDef {
+    membership: Layers,
+    mask: Layers,
+}
+
+/// A listener for the layers in `mask`, which defaults to all of them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ColliderListensDef {
+    mask: Layers,
+}
+
+impl Default for ColliderListensDef {
+    fn default() -> Self {
+        Self {
+            mask: Layers(CollisionLayers::ALL),
+        }
+    }
+}
+
+/// Health starting out at `health`, which defaults to `max`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HealthDef {
+    max: f32,
+    #[serde(default)]
+    health: Option<f32>,
+}
+
+/// A collision layer mask written as a list of layer names.
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(try_from = "Vec<String>")]
+struct Layers(u32);
+
+impl TryFrom<Vec<String>> for Layers {
+    type Error = String;
+
+    fn try_from(names: Vec<String>) -> Result<Self, Self::Error> {
+        names.iter().try_fold(Self(0), |Self(mask), name| {
+            let layer = match name.as_str() {
+                "tiles" => CollisionLayers::TILES,
+                "actors" => CollisionLayers::ACTORS,
+                "players" => CollisionLayers::PLAYERS,
+                "projectiles" => CollisionLayers::PROJECTILES,
+                "triggers" => CollisionLayers::TRIGGERS,
+                "platforms" => CollisionLayers::PLATFORMS,
+                "items" => CollisionLayers::ITEMS,
+                "all" => CollisionLayers::ALL,
+                layer => return Err(format!("unknown collision layer {layer:?}")),
+            };
+
+            Ok(Self(mask | layer))
+        })
+    }
+}
+
+impl PrefabComponent {
+    /// Inserts a copy of the component onto `entity`.
+    fn insert(&self, entity: Entity) {
+        let vec2 = |(x, y): (f32, f32)| Vec2::new(x, y);
+
+        match self.clone() {
+            Self::Pos(x, y) => insert_bundle(entity, Pos(Vec2::new(x, y))),
+            Self::Vel(x, y) => insert_bundle(entity, Vel(Vec2::new(x, y))),
+            Self::Collider(ColliderDef { min, max }) => insert_bundle(
+                entity,
+                Collider(Aabb {
+                    min: vec2(min),
+                    max: vec2(max),
+                }),
+            ),
+            Self::CollisionLayers(CollisionLayersDef { membership, mask }) => {
+                insert_bundle(entity, CollisionLayers::new(membership.0, mask.0))
+            }
+            Self::ColliderMoves => insert_bundle(entity, ColliderMoves),
+            Self::ColliderListens(ColliderListensDef { mask }) => {
+                insert_bundle(entity, ColliderListens::with_mask(mask.0))
+            }
+            Self::ContinuousCollision => insert_bundle(entity, ContinuousCollision),
+            Self::Gravity(gravity) => insert_bundle(entity, gravity),
+            Self::CastsShadow => insert_bundle(entity, CastsShadow),
+            Self::Enemy(enemy) => insert_bundle(entity, enemy),
+            Self::ContactDamage(damage) => insert_bundle(entity, ContactDamage(damage)),
+            Self::DamageResistance(resistance) => insert_bundle(entity, resistance),
+            Self::Hazard => insert_bundle(entity, Hazard),
+            Self::BulletSpawner => insert_bundle(entity, BulletSpawner),
+            Self::Cooldown(duration) => insert_bundle(entity, Cooldown::new(duration)),
+            Self::SpawnPoint => insert_bundle(entity, SpawnPoint),
+            Self::WaveSpawnPoint(point) => insert_bundle(entity, point),
+            Self::Health(HealthDef { max, health }) => {
+                entity.insert(Health::new(health.unwrap_or(max), max));
+            }
+            Self::TangibleMarker => {
+                entity.insert(TangibleMarker);
+            }
+        }
+    }
+}
+
+// === PrefabRegistry === //
+
+/// Records the name of the prefab an entity was spawned from.
+#[derive(Debug, Clone, Component)]
+pub struct PrefabInstance(pub String);
+
+/// An entity template made up of a list of components with their initial values.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(transparent)]
+pub struct Prefab {
+    components: Vec<PrefabComponent>,
+}
+
+/// The set of prefabs which can be spawned by name.
+///
+/// Prefab files are [RON](https://github.com/ron-rs/ron) maps from each prefab's name to the list
+/// of its components, e.g. `"enemy": [Enemy(max_speed: 4), Health(max: 15)]`. Components are
+/// parsed and validated when the file is loaded so that spawning a prefab only clones their values.
+#[derive(Debug, Resource)]
+pub struct PrefabRegistry {
+    prefabs: FxHashMap<String, Prefab>,
+}
+
+impl Default for PrefabRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            prefabs: FxHashMap::default(),
+        };
+
+        registry.load(DEFAULT_PREFABS).unwrap();
+        registry
+    }
+}
+
+impl PrefabRegistry {
+    /// Parses a prefab file and adds its prefabs to the registry, replacing those with the same
+    /// name. Nothing is added if any of them is invalid.
+    pub fn load(&mut self, text: &str) -> Result<(), SpannedError> {
+        // Lets components with fields be written as `Enemy(max_speed: 4)` rather than
+        // `Enemy((max_speed: 4))`.
+        let options =
+            Options::default().with_default_extension(Extensions::UNWRAP_VARIANT_NEWTYPES);
+        let loaded = options.from_str::<FxHashMap<String, Prefab>>(text)?;
+
+        self.prefabs.extend(loaded);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Prefab> {
+        self.prefabs.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.prefabs.keys().map(String::as_str)
+    }
+
+    /// Spawns an instance of the named prefab, inserting `overrides` on top of its components.
+    /// Random components can be overridden by inserting them onto the returned entity. Must be
+    /// called within a [`RandomAccess`](crate::util::arena::RandomAccess) providing
+    /// [`PrefabAccess`].
+    pub fn spawn_prefab(&self, name: &str, overrides: impl Bundle) -> Entity {
+        let Some(prefab) = self.get(name) else {
+            panic!("unknown prefab {name:?}");
+        };
+
+        let entity = spawn_entity(PrefabInstance(name.to_string()));
+        for component in &prefab.components {
+            component.insert(entity);
+        }
+        insert_bundle(entity, overrides);
+
+        entity
+    }
+}
+
+// === Systems === //
+
+pub fn sys_load_prefabs(mut prefabs: ResMut<PrefabRegistry>) {
+    let text = match fs::read_to_string(PREFABS_PATH) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return,
+        Err(err) => {
+            log::error!("Failed to read {PREFABS_PATH}: {err}");
+            return;
+        }
+    };
+
+    match prefabs.load(&text) {
+        Ok(()) => log::info!("Loaded prefabs from {PREFABS_PATH}"),
+        Err(err) => log::error!("Failed to load {PREFABS_PATH}: {err}"),
+    }
+}
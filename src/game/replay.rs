@@ -0,0 +1,1478 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy_ecs::{
+    entity::Entity,
+    query::{With, Without},
+    schedule::{NextState, State},
+    system::{Commands, Query, Res, ResMut, Resource, SystemParam},
+};
+use macroquad::{
+    color::{Color, GRAY, RED, WHITE, YELLOW},
+    input::{is_key_down, is_key_pressed, KeyCode},
+    math::{Affine2, IVec2, Vec2},
+    shapes::{draw_line, draw_rectangle},
+    text::draw_text,
+    window::{screen_height, screen_width},
+};
+use rustc_hash::FxHashSet;
+
+use crate::{
+    game::{
+        actor::{
+            camera::{ActiveCamera, VirtualCamera},
+            checkpoint::ActiveCheckpoint,
+            controller::CharacterController,
+            death::Dead,
+            drops::{ItemDrop, ItemDropBundle},
+            effects::{ActiveEffect, StatusEffects},
+            enemy::Enemy,
+            health::Health,
+            inventory::{Inventory, ItemStack},
+            kinematic::{ColliderListens, ColliderMoves, ContinuousCollision, Pos, PrevPos, Vel},
+            platform::MovingPlatform,
+            player::{
+                HitInvulnerability, InputFrame, InputStroke, PlayerInput, PlayerState, StrokeAction,
+            },
+            projectile::{
+                BulletBaseBundle, BulletDamage, Pooled, BULLET_LAYERS, BULLET_LISTEN_MASK,
+            },
+            wave::{WaveDirector, WaveMember},
+        },
+        math::aabb::Aabb,
+        prefab::{PrefabInstance, PrefabRegistry},
+        rng::{Rng, RngChannel},
+        rules::{GameOutcome, GameStats},
+        save::{
+            apply_regions, collect_regions, world_layers, RegionData, RestoreRequest, SaveBackup,
+            SaveConfig, SaveState, SavedWorld,
+        },
+        scene::GameScene,
+        tile::{
+            breaking::TileBreaker,
+            collider::{Collider, InsideWorld, TrackedCollider},
+            data::{FillLevels, TileChanged, TileChunk, TileLayers, TileWorld, WorldCreatedChunk},
+            generator::TileGenerator,
+            kinematic::TangibleMarker,
+            liquid::WorldLiquids,
+            material::MaterialRegistry,
+            tile_entity::{TileEntityCreated, TileEntityDescriptor},
+        },
+        time::WorldClock,
+    },
+    util::{
+        arena::{
+            despawn_entity, insert_bundle, spawn_entity, Obj, ObjOwner, RandomAccess,
+            RandomEntityExt, SendsEvent,
+        },
+        timer::Cooldown,
+    },
+};
+
+// === ReplayState === //
+
+/// The initial number of recorded frames between two consecutive snapshots. Seeking restores the
+/// nearest snapshot and re-simulates at most this many frames.
+pub const SNAPSHOT_INTERVAL: usize = 300;
+
+/// The most snapshots kept in memory at once. Every other snapshot is dropped and the interval
+/// between them doubled once a recording exceeds this.
+pub const MAX_SNAPSHOTS: usize = 32;
+
+/// The most frames recorded in a single session, which is an hour of play.
+pub const MAX_RECORDED_FRAMES: usize = 60 * 60 * 60;
+
+/// The number of frames skipped by a single scrub key press.
+pub const SEEK_STEP: usize = 120;
+
+pub const MIN_SPEED: f32 = 0.125;
+pub const MAX_SPEED: f32 = 8.;
+
+pub const FREE_CAMERA_SPEED: f32 = 15.;
+
+/// The file to which the recording of a session is written on exit.
+pub const REPLAY_LOG_PATH: &str = "replays/latest.replay";
+
+/// Records the inputs of the session so that it can be viewed and written to a [`ReplayLog`].
+/// Sessions are only recorded when started with `--record` or `--replay`.
+#[derive(Debug, Resource)]
+pub struct ReplayState {
+    recording: bool,
+    frames: Vec<InputFrame>,
+    snapshots: Vec<Snapshot>,
+    snapshot_interval: usize,
+    viewer: Option<ReplayViewer>,
+    playback: Option<ReplayPlayback>,
+
+    /// The name of the backup from which the recorded session started.
+    origin: Option<String>,
+
+    /// The state of the RNG as of the first recorded frame.
+    start_rng: Option<Rng>,
+}
+
+impl Default for ReplayState {
+    fn default() -> Self {
+        Self {
+            recording: false,
+            frames: Vec::new(),
+            snapshots: Vec::new(),
+            snapshot_interval: SNAPSHOT_INTERVAL,
+            viewer: None,
+            playback: None,
+            origin: None,
+            start_rng: None,
+        }
+    }
+}
+
+/// Feeds the inputs of a [`ReplayLog`] to the game in place of live inputs until the log runs out.
+#[derive(Debug)]
+struct ReplayPlayback {
+    log: ReplayLog,
+}
+
+#[derive(Debug)]
+struct ReplayViewer {
+    /// The index of the next recorded frame to be played back.
+    cursor: usize,
+    paused: bool,
+    speed: f32,
+    budget: f32,
+    pending_updates: u32,
+    follow_player: bool,
+    show_timeline: bool,
+    show_inputs: bool,
+}
+
+impl ReplayState {
+    pub fn is_viewing(&self) -> bool {
+        self.viewer.is_some()
+    }
+
+    pub fn is_following_player(&self) -> bool {
+        match &self.viewer {
+            Some(viewer) => viewer.follow_player,
+            None => true,
+        }
+    }
+
+    pub fn is_playing_back(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    /// Whether recorded inputs are currently being fed to the game instead of live inputs.
+    pub fn is_feeding_input(&self) -> bool {
+        self.is_viewing() || self.is_playing_back()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Builds a log of everything recorded so far.
+    pub fn to_log(&self) -> ReplayLog {
+        ReplayLog {
+            origin: self.origin.clone(),
+            rng: self.start_rng.clone().unwrap_or_else(|| Rng::new(0)),
+            frames: self.frames.clone(),
+        }
+    }
+
+    /// Replaces the live session with the playback of `log`, which is recorded as well. This must
+    /// be started before the first update so that the session begins from the same state as the
+    /// recorded one.
+    pub fn start_playback(&mut self, log: ReplayLog) {
+        self.recording = true;
+        self.playback = Some(ReplayPlayback { log });
+    }
+
+    fn push_snapshot(&mut self, snapshot: Snapshot) {
+        self.snapshots.push(snapshot);
+
+        if self.snapshots.len() > MAX_SNAPSHOTS {
+            // The first snapshot is always kept since seeking can't go back any further.
+            let mut index = 0;
+            self.snapshots.retain(|_| {
+                index += 1;
+                index % 2 == 1
+            });
+            self.snapshot_interval *= 2;
+        }
+    }
+
+    /// Determines how many times the `Update` schedule should run during this rendered frame. The
+    /// viewer may run zero (while paused) or many (while fast-forwarding or seeking) updates. Live
+    /// sessions return `None`, leaving the decision to [`GameTime`](super::time::GameTime).
+    pub fn take_updates(&mut self) -> Option<u32> {
+        self.viewer
+            .as_mut()
+            .map(|viewer| std::mem::take(&mut viewer.pending_updates))
+    }
+
+    fn seek(&mut self, target: usize) -> Option<(Snapshot, u32)> {
+        let target = target.min(self.frames.len());
+        let snapshot = self
+            .snapshots
+            .iter()
+            .rev()
+            .find(|snapshot| snapshot.frame <= target)?
+            .clone();
+
+        let viewer = self.viewer.as_mut().unwrap();
+        viewer.cursor = snapshot.frame;
+        viewer.budget = 0.;
+
+        let updates = (target - snapshot.frame) as u32;
+        Some((snapshot, updates))
+    }
+}
+
+// === Snapshot === //
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    frame: usize,
+    rng: Rng,
+    worlds: Vec<WorldSnapshot>,
+    players: Vec<PlayerSnapshot>,
+    bullets: Vec<BulletSnapshot>,
+    enemies: Vec<EnemySnapshot>,
+    drops: Vec<DropSnapshot>,
+    platforms: Vec<PlatformSnapshot>,
+    cooldowns: Vec<(Entity, Cooldown)>,
+    stats: GameStats,
+    outcome: GameOutcome,
+    clock: WorldClock,
+    director: WaveDirector,
+}
+
+#[derive(Debug, Clone)]
+struct WorldSnapshot {
+    world: Obj<TileWorld>,
+    health: Option<(Obj<Health>, f32)>,
+    regions: Vec<RegionData>,
+
+    /// The fill levels of every chunk with partially filled liquid tiles, keyed like `regions`.
+    fill_levels: Vec<(u32, IVec2, Box<FillLevels>)>,
+    liquid_ticks: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+struct PlayerSnapshot {
+    entity: Entity,
+    world: Obj<TileWorld>,
+    pos: Vec2,
+    vel: Vec2,
+    collider: Option<Aabb>,
+    health: Health,
+    effects: Vec<ActiveEffect>,
+    inventory: Inventory,
+    hit_invulnerability: Option<HitInvulnerability>,
+    controller: Option<CharacterController>,
+    breaker: Option<TileBreaker>,
+    dead: Option<Dead>,
+    checkpoint: Option<ActiveCheckpoint>,
+}
+
+#[derive(Debug, Clone)]
+struct BulletSnapshot {
+    world: Obj<TileWorld>,
+    pos: Vec2,
+    vel: Vec2,
+    collider: Aabb,
+    damage: BulletDamage,
+}
+
+/// Enemies are respawned from their prefab since they may have died since the snapshot was taken.
+#[derive(Debug, Clone)]
+struct EnemySnapshot {
+    prefab: String,
+    world: Obj<TileWorld>,
+    pos: Vec2,
+    vel: Vec2,
+    collider: Aabb,
+    enemy: Enemy,
+    health: Option<Health>,
+    wave: Option<WaveMember>,
+}
+
+#[derive(Debug, Clone)]
+struct DropSnapshot {
+    world: Obj<TileWorld>,
+    pos: Vec2,
+    vel: Vec2,
+    collider: Aabb,
+    stack: ItemStack,
+}
+
+#[derive(Debug, Clone)]
+struct PlatformSnapshot {
+    entity: Entity,
+    pos: Vec2,
+    vel: Vec2,
+    collider: Aabb,
+    platform: MovingPlatform,
+}
+
+/// The simulation state which [`Snapshot`]s capture.
+#[derive(SystemParam)]
+pub struct SnapshotState<'w, 's> {
+    worlds: Query<
+        'w,
+        's,
+        (
+            &'static ObjOwner<TileWorld>,
+            &'static SavedWorld,
+            Option<&'static ObjOwner<Health>>,
+            Option<&'static mut WorldLiquids>,
+        ),
+    >,
+    players: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static mut InsideWorld,
+            &'static mut Pos,
+            &'static mut Vel,
+            Option<&'static mut Collider>,
+            &'static ObjOwner<Health>,
+            Option<&'static ObjOwner<StatusEffects>>,
+            &'static mut Inventory,
+            Option<&'static mut HitInvulnerability>,
+            Option<&'static mut CharacterController>,
+            Option<&'static mut TileBreaker>,
+            Option<&'static Dead>,
+            Option<&'static ActiveCheckpoint>,
+        ),
+        With<PlayerState>,
+    >,
+    bullets: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static InsideWorld,
+            &'static Pos,
+            &'static Vel,
+            &'static Collider,
+            &'static BulletDamage,
+        ),
+        (
+            Without<Pooled>,
+            Without<PlayerState>,
+            Without<MovingPlatform>,
+        ),
+    >,
+    enemies: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static PrefabInstance,
+            &'static InsideWorld,
+            &'static Pos,
+            &'static Vel,
+            &'static Collider,
+            &'static Enemy,
+            Option<&'static ObjOwner<Health>>,
+            Option<&'static WaveMember>,
+        ),
+        (Without<PlayerState>, Without<MovingPlatform>),
+    >,
+    drops: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static InsideWorld,
+            &'static Pos,
+            &'static Vel,
+            &'static Collider,
+            &'static ItemDrop,
+        ),
+        (Without<PlayerState>, Without<MovingPlatform>),
+    >,
+    platforms: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static mut Pos,
+            &'static mut Vel,
+            &'static mut Collider,
+            &'static mut MovingPlatform,
+        ),
+        Without<PlayerState>,
+    >,
+    cooldowns: Query<'w, 's, (Entity, &'static mut Cooldown)>,
+    stats: ResMut<'w, GameStats>,
+    outcome: ResMut<'w, GameOutcome>,
+    clock: ResMut<'w, WorldClock>,
+    director: ResMut<'w, WaveDirector>,
+    rng: ResMut<'w, Rng>,
+    prefabs: Res<'w, PrefabRegistry>,
+    commands: Commands<'w, 's>,
+}
+
+impl SnapshotState<'_, '_> {
+    fn take(&self, frame: usize) -> Snapshot {
+        Snapshot {
+            frame,
+            rng: self.rng.clone(),
+            worlds: self
+                .worlds
+                .iter()
+                .map(
+                    |(&ObjOwner(world), &SavedWorld(id), health, liquids)| WorldSnapshot {
+                        world,
+                        health: health.map(|&ObjOwner(hp)| (hp, hp.health())),
+                        regions: collect_regions(id, world),
+                        fill_levels: collect_fill_levels(world),
+                        liquid_ticks: liquids.map(|liquids| liquids.ticks()),
+                    },
+                )
+                .collect(),
+            players: self
+                .players
+                .iter()
+                .map(
+                    |(
+                        entity,
+                        &InsideWorld(world),
+                        pos,
+                        vel,
+                        collider,
+                        &ObjOwner(health),
+                        effects,
+                        inventory,
+                        hit_invulnerability,
+                        controller,
+                        breaker,
+                        dead,
+                        checkpoint,
+                    )| PlayerSnapshot {
+                        entity,
+                        world,
+                        pos: pos.0,
+                        vel: vel.0,
+                        collider: collider.map(|collider| collider.0),
+                        health: (*health).clone(),
+                        effects: effects
+                            .map(|&ObjOwner(effects)| effects.active().to_vec())
+                            .unwrap_or_default(),
+                        inventory: inventory.clone(),
+                        hit_invulnerability: hit_invulnerability.copied(),
+                        controller: controller.cloned(),
+                        breaker: breaker.cloned(),
+                        dead: dead.cloned(),
+                        checkpoint: checkpoint.cloned(),
+                    },
+                )
+                .collect(),
+            bullets: self
+                .bullets
+                .iter()
+                .map(
+                    |(_, &InsideWorld(world), pos, vel, collider, damage)| BulletSnapshot {
+                        world,
+                        pos: pos.0,
+                        vel: vel.0,
+                        collider: collider.0,
+                        damage: *damage,
+                    },
+                )
+                .collect(),
+            enemies: self
+                .enemies
+                .iter()
+                .map(
+                    |(_, prefab, &InsideWorld(world), pos, vel, collider, enemy, health, wave)| {
+                        EnemySnapshot {
+                            prefab: prefab.0.clone(),
+                            world,
+                            pos: pos.0,
+                            vel: vel.0,
+                            collider: collider.0,
+                            enemy: enemy.clone(),
+                            health: health.map(|&ObjOwner(health)| (*health).clone()),
+                            wave: wave.copied(),
+                        }
+                    },
+                )
+                .collect(),
+            drops: self
+                .drops
+                .iter()
+                .map(
+                    |(_, &InsideWorld(world), pos, vel, collider, drop)| DropSnapshot {
+                        world,
+                        pos: pos.0,
+                        vel: vel.0,
+                        collider: collider.0,
+                        stack: drop.stack.clone(),
+                    },
+                )
+                .collect(),
+            platforms: self
+                .platforms
+                .iter()
+                .map(|(entity, pos, vel, collider, platform)| PlatformSnapshot {
+                    entity,
+                    pos: pos.0,
+                    vel: vel.0,
+                    collider: collider.0,
+                    platform: platform.clone(),
+                })
+                .collect(),
+            cooldowns: self
+                .cooldowns
+                .iter()
+                .map(|(entity, &cooldown)| (entity, cooldown))
+                .collect(),
+            stats: self.stats.clone(),
+            outcome: self.outcome.clone(),
+            clock: self.clock.clone(),
+            director: self.director.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) {
+        *self.rng = snapshot.rng.clone();
+
+        for world in &snapshot.worlds {
+            if let Some((mut hp, health)) = world.health {
+                hp.set_health(health);
+            }
+
+            // Chunks which were created after the snapshot may have been edited so we regenerate
+            // them from scratch.
+            let saved = world
+                .regions
+                .iter()
+                .map(|region| (region.layer, region.pos))
+                .collect::<FxHashSet<_>>();
+
+            let layers = world_layers(world.world);
+
+            for (layer_idx, &layer) in layers.iter().enumerate() {
+                let generator = layer.entity().try_get::<TileGenerator>();
+
+                for (pos, mut chunk) in layer.chunks() {
+                    if saved.contains(&(layer_idx as u32, pos)) {
+                        continue;
+                    }
+
+                    match &generator {
+                        Some(generator) => generator.regenerate(&mut chunk),
+                        None => chunk.raw_tiles_mut().fill(0),
+                    }
+                }
+            }
+
+            // Applying the regions fills every liquid tile back up so the partially filled ones
+            // are drained again afterwards.
+            apply_regions(world.world, &world.regions);
+
+            for (layer, pos, fill_levels) in &world.fill_levels {
+                let chunk = layers
+                    .get(*layer as usize)
+                    .and_then(|layer| layer.chunk(*pos));
+
+                if let Some(mut chunk) = chunk {
+                    chunk.set_fill_levels(Some(fill_levels.clone()));
+                }
+            }
+
+            if let (Some(ticks), Ok((.., Some(mut liquids)))) = (
+                world.liquid_ticks,
+                self.worlds.get_mut(world.world.entity()),
+            ) {
+                liquids.rewind(ticks);
+            }
+        }
+
+        for player in &snapshot.players {
+            let Ok((
+                entity,
+                mut world,
+                mut pos,
+                mut vel,
+                collider,
+                &ObjOwner(mut health),
+                effects,
+                mut inventory,
+                hit_invulnerability,
+                controller,
+                breaker,
+                dead,
+                checkpoint,
+            )) = self.players.get_mut(player.entity)
+            else {
+                continue;
+            };
+
+            let moved = world.0 != player.world;
+            world.0 = player.world;
+            pos.0 = player.pos;
+            vel.0 = player.vel;
+
+            health.set_max(player.health.max());
+            health.set_health(player.health.health());
+            if let Some(&ObjOwner(effects)) = effects {
+                effects.set_active(player.effects.clone());
+            }
+
+            *inventory = player.inventory.clone();
+
+            if let (Some(mut current), Some(saved)) =
+                (hit_invulnerability, player.hit_invulnerability)
+            {
+                *current = saved;
+            }
+            if let (Some(mut current), Some(saved)) = (controller, &player.controller) {
+                *current = saved.clone();
+            }
+            if let (Some(mut current), Some(saved)) = (breaker, &player.breaker) {
+                *current = saved.clone();
+            }
+
+            match (&player.checkpoint, checkpoint.is_some()) {
+                (Some(saved), _) => {
+                    self.commands.entity(entity).insert(saved.clone());
+                }
+                (None, true) => {
+                    self.commands.entity(entity).remove::<ActiveCheckpoint>();
+                }
+                (None, false) => {}
+            }
+
+            // Dead players have no collider so players who died or respawned since the snapshot
+            // have theirs removed or given back like `sys_check_death` and `sys_tick_respawns` do.
+            // Players who went through a portal since then get theirs recreated in their old world.
+            match (&player.dead, player.collider, dead.is_some()) {
+                (Some(saved), _, _) => {
+                    self.commands
+                        .entity(entity)
+                        .remove::<(Collider, ObjOwner<TrackedCollider>, ColliderMoves)>()
+                        .insert(saved.clone());
+                }
+                (None, Some(aabb), true) => {
+                    self.commands.entity(entity).remove::<Dead>().insert((
+                        Collider(aabb),
+                        PrevPos(player.pos),
+                        ColliderMoves,
+                    ));
+                }
+                (None, Some(aabb), false) if moved => {
+                    self.commands
+                        .entity(entity)
+                        .remove::<(Collider, ObjOwner<TrackedCollider>)>()
+                        .insert((Collider(aabb), PrevPos(player.pos)));
+                }
+                (None, Some(aabb), false) => {
+                    if let Some(mut collider) = collider {
+                        collider.0 = aabb;
+                    }
+                }
+                (None, None, _) => {}
+            }
+        }
+
+        for (entity, ..) in self.bullets.iter() {
+            despawn_entity(entity);
+        }
+
+        for bullet in &snapshot.bullets {
+            let entity = spawn_entity(BulletBaseBundle {
+                pos: Pos(bullet.pos),
+                vel: Vel(bullet.vel),
+                world: InsideWorld(bullet.world),
+                collider: Collider(bullet.collider),
+                layers: BULLET_LAYERS,
+                moves: ColliderMoves,
+                continuous: ContinuousCollision,
+                listens: ColliderListens::with_mask(BULLET_LISTEN_MASK),
+                damage: bullet.damage,
+            });
+
+            entity.insert(TangibleMarker);
+        }
+
+        for (entity, ..) in self.enemies.iter() {
+            despawn_entity(entity);
+        }
+
+        for enemy in &snapshot.enemies {
+            if self.prefabs.get(&enemy.prefab).is_none() {
+                continue;
+            }
+
+            let entity = self.prefabs.spawn_prefab(
+                &enemy.prefab,
+                (
+                    Pos(enemy.pos),
+                    Vel(enemy.vel),
+                    InsideWorld(enemy.world),
+                    Collider(enemy.collider),
+                    enemy.enemy.clone(),
+                ),
+            );
+
+            if let Some(wave) = enemy.wave {
+                insert_bundle(entity, wave);
+            }
+
+            if let (Some(saved), Some(mut health)) = (&enemy.health, entity.try_get::<Health>()) {
+                health.set_max(saved.max());
+                health.set_health(saved.health());
+            }
+        }
+
+        for (entity, ..) in self.drops.iter() {
+            despawn_entity(entity);
+        }
+
+        for drop in &snapshot.drops {
+            spawn_entity(ItemDropBundle {
+                vel: Vel(drop.vel),
+                collider: Collider(drop.collider),
+                ..ItemDropBundle::new(InsideWorld(drop.world), drop.pos, drop.stack.clone())
+            });
+        }
+
+        for saved in &snapshot.platforms {
+            if let Ok((_, mut pos, mut vel, mut collider, mut platform)) =
+                self.platforms.get_mut(saved.entity)
+            {
+                pos.0 = saved.pos;
+                vel.0 = saved.vel;
+                collider.0 = saved.collider;
+                *platform = saved.platform.clone();
+            }
+        }
+
+        for &(entity, saved) in &snapshot.cooldowns {
+            if let Ok((_, mut cooldown)) = self.cooldowns.get_mut(entity) {
+                *cooldown = saved;
+            }
+        }
+
+        *self.stats = snapshot.stats.clone();
+        *self.outcome = snapshot.outcome.clone();
+        *self.clock = snapshot.clock.clone();
+        *self.director = snapshot.director.clone();
+    }
+}
+
+/// Collects the fill levels of every chunk of `world` which has partially filled liquid tiles.
+fn collect_fill_levels(world: Obj<TileWorld>) -> Vec<(u32, IVec2, Box<FillLevels>)> {
+    let mut fill_levels = Vec::new();
+
+    for (layer, data) in world_layers(world).into_iter().enumerate() {
+        for (pos, chunk) in data.chunks() {
+            if let Some(levels) = chunk.fill_levels() {
+                fill_levels.push((layer as u32, pos, Box::new(*levels)));
+            }
+        }
+    }
+
+    fill_levels
+}
+
+// === ReplayLog === //
+
+const REPLAY_MAGIC: &[u8; 4] = b"BDRL";
+const REPLAY_VERSION: u32 = 2;
+
+/// A compact recording of a session from which it can be reproduced exactly: the input of every
+/// tick, the state of the RNG when the session started, and the backup the session started from.
+#[derive(Debug, Clone)]
+pub struct ReplayLog {
+    pub origin: Option<String>,
+    pub rng: Rng,
+    pub frames: Vec<InputFrame>,
+}
+
+impl ReplayLog {
+    /// Encodes the log. Consecutive identical frames are stored as a single run since players
+    /// tend to hold the same inputs for many ticks at a time.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(REPLAY_MAGIC);
+        bytes.extend_from_slice(&REPLAY_VERSION.to_le_bytes());
+
+        let origin = self.origin.as_deref().unwrap_or_default();
+        bytes.extend_from_slice(&(origin.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(origin.as_bytes());
+
+        let streams = self.rng.stream_states().collect::<Vec<_>>();
+        bytes.extend_from_slice(&self.rng.seed().to_le_bytes());
+        bytes.extend_from_slice(&(streams.len() as u32).to_le_bytes());
+        for (channel, state) in streams {
+            bytes.push(channel as u8);
+            bytes.extend_from_slice(&state.to_le_bytes());
+        }
+
+        let mut frames = self.frames.iter().peekable();
+        while let Some(frame) = frames.next() {
+            let mut run = 1u32;
+            while frames.next_if_eq(&frame).is_some() {
+                run += 1;
+            }
+
+            bytes.extend_from_slice(&run.to_le_bytes());
+            encode_frame(&mut bytes, frame);
+        }
+
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> io::Result<Self> {
+        let mut reader = ByteReader { bytes };
+
+        if reader.take(4)? != REPLAY_MAGIC {
+            return Err(invalid_log("replay has a bad magic number"));
+        }
+
+        if reader.u32()? != REPLAY_VERSION {
+            return Err(invalid_log("replay has an unsupported version"));
+        }
+
+        let origin_len = reader.u32()? as usize;
+        let origin = std::str::from_utf8(reader.take(origin_len)?)
+            .map_err(|_| invalid_log("replay has a malformed origin"))?;
+
+        let seed = reader.u64()?;
+        let mut streams = Vec::new();
+        for _ in 0..reader.u32()? {
+            let channel = *RngChannel::VARIANTS
+                .get(reader.u8()? as usize)
+                .ok_or_else(|| invalid_log("replay has an unknown RNG channel"))?;
+
+            streams.push((channel, reader.u64()?));
+        }
+
+        let mut log = Self {
+            origin: (!origin.is_empty()).then(|| origin.to_string()),
+            rng: Rng::from_stream_states(seed, streams),
+            frames: Vec::new(),
+        };
+
+        while !reader.bytes.is_empty() {
+            let run = reader.u32()? as usize;
+            if run == 0 {
+                return Err(invalid_log("replay has an empty run of frames"));
+            }
+
+            // Recording stops at the frame limit so longer logs can't have been recorded by us.
+            if run > MAX_RECORDED_FRAMES - log.frames.len() {
+                return Err(invalid_log("replay has too many frames"));
+            }
+
+            let frame = decode_frame(&mut reader)?;
+            log.frames.resize(log.frames.len() + run, frame);
+        }
+
+        Ok(log)
+    }
+
+    pub fn read(path: &Path) -> io::Result<Self> {
+        Self::decode(&fs::read(path)?)
+    }
+
+    /// Writes the log to a temporary file before renaming it into place so that a crash never
+    /// leaves a truncated log behind.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let staged = path.with_extension("tmp");
+        fs::write(&staged, self.encode())?;
+        fs::rename(&staged, path)
+    }
+}
+
+pub(crate) fn encode_frame(bytes: &mut Vec<u8>, frame: &InputFrame) {
+    bytes.extend_from_slice(&frame.heading.x.to_le_bytes());
+    bytes.extend_from_slice(&frame.heading.y.to_le_bytes());
+
+    match frame.stroke {
+        Some(stroke) => {
+            bytes.push(match stroke.action {
+                StrokeAction::Mine => 1,
+                StrokeAction::Use => 2,
+            });
+
+            for value in [stroke.from.x, stroke.from.y, stroke.to.x, stroke.to.y] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        None => bytes.push(0),
+    }
+
+    bytes.push(frame.select.map_or(u8::MAX, |slot| slot as u8));
+    bytes.extend_from_slice(&frame.scroll.to_le_bytes());
+}
+
+pub(crate) fn decode_frame(reader: &mut ByteReader) -> io::Result<InputFrame> {
+    let heading = Vec2::new(reader.f32()?, reader.f32()?);
+
+    let action = match reader.u8()? {
+        0 => None,
+        1 => Some(StrokeAction::Mine),
+        2 => Some(StrokeAction::Use),
+        _ => return Err(invalid_log("unknown stroke action")),
+    };
+
+    let stroke = match action {
+        Some(action) => Some(InputStroke {
+            from: Vec2::new(reader.f32()?, reader.f32()?),
+            to: Vec2::new(reader.f32()?, reader.f32()?),
+            action,
+        }),
+        None => None,
+    };
+
+    let select = match reader.u8()? {
+        u8::MAX => None,
+        slot => Some(slot as usize),
+    };
+
+    Ok(InputFrame {
+        heading,
+        stroke,
+        select,
+        scroll: reader.i32()?,
+    })
+}
+
+fn invalid_log(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+pub(crate) struct ByteReader<'a> {
+    pub bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(invalid_log("data is truncated"));
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    pub fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    pub fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    pub fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    pub fn i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+
+    pub fn f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+}
+
+// === Systems === //
+
+pub fn sys_handle_replay_controls(
+    mut rand: RandomAccess<(
+        &mut TileWorld,
+        &mut TileChunk,
+        &TileLayers,
+        &TileGenerator,
+        &mut Health,
+        &mut StatusEffects,
+        &mut TangibleMarker,
+        &mut VirtualCamera,
+        &MaterialRegistry,
+        &TileEntityDescriptor,
+        SendsEvent<TileEntityCreated>,
+        SendsEvent<TileChanged>,
+        SendsEvent<WorldCreatedChunk>,
+    )>,
+    mut snapshots: SnapshotState,
+    mut state: ResMut<ReplayState>,
+    camera: Res<ActiveCamera>,
+    scene: Res<State<GameScene>>,
+    mut next_scene: ResMut<NextState<GameScene>>,
+) {
+    rand.provide(|| {
+        // Toggle the viewer
+        if is_key_pressed(KeyCode::F6) {
+            match state.viewer.take() {
+                Some(viewer) => {
+                    // Resume the live session from wherever the viewer left off, discarding the
+                    // rest of the recording and any log being played back.
+                    state.playback = None;
+                    state.recording = true;
+                    state.frames.truncate(viewer.cursor);
+                    state
+                        .snapshots
+                        .retain(|snapshot| snapshot.frame < viewer.cursor);
+                }
+                None if state.snapshots.is_empty() => {
+                    log::warn!("Nothing has been recorded; start the game with --record to do so");
+                }
+                None => {
+                    state.viewer = Some(ReplayViewer {
+                        cursor: state.frames.len(),
+                        paused: true,
+                        speed: 1.,
+                        budget: 0.,
+                        pending_updates: 0,
+                        follow_player: false,
+                        show_timeline: true,
+                        show_inputs: false,
+                    });
+                }
+            }
+        }
+
+        let frame_count = state.frames.len();
+        let Some(viewer) = &mut state.viewer else {
+            return;
+        };
+
+        // Handle playback controls
+        let mut seek_to = None;
+
+        if is_key_pressed(KeyCode::Space) {
+            viewer.paused = !viewer.paused;
+        }
+
+        if is_key_pressed(KeyCode::PageUp) {
+            viewer.speed = (viewer.speed * 2.).min(MAX_SPEED);
+        }
+
+        if is_key_pressed(KeyCode::PageDown) {
+            viewer.speed = (viewer.speed / 2.).max(MIN_SPEED);
+        }
+
+        if is_key_pressed(KeyCode::Left) {
+            seek_to = Some(viewer.cursor.saturating_sub(SEEK_STEP));
+        }
+
+        if is_key_pressed(KeyCode::Right) {
+            seek_to = Some(viewer.cursor + SEEK_STEP);
+        }
+
+        if is_key_pressed(KeyCode::Home) {
+            seek_to = Some(0);
+        }
+
+        if is_key_pressed(KeyCode::Period) && viewer.paused && viewer.cursor < frame_count {
+            viewer.pending_updates += 1;
+        }
+
+        // Handle overlay toggles
+        if is_key_pressed(KeyCode::F) {
+            viewer.follow_player = !viewer.follow_player;
+        }
+
+        if is_key_pressed(KeyCode::T) {
+            viewer.show_timeline = !viewer.show_timeline;
+        }
+
+        if is_key_pressed(KeyCode::I) {
+            viewer.show_inputs = !viewer.show_inputs;
+        }
+
+        // Move the free camera
+        if let (false, Some(mut camera)) = (viewer.follow_player, camera.camera) {
+            let mut delta = Vec2::ZERO;
+            if is_key_down(KeyCode::A) {
+                delta += Vec2::NEG_X;
+            }
+            if is_key_down(KeyCode::D) {
+                delta += Vec2::X;
+            }
+            if is_key_down(KeyCode::W) {
+                delta += Vec2::NEG_Y;
+            }
+            if is_key_down(KeyCode::S) {
+                delta += Vec2::Y;
+            }
+
+            let xform = camera.transform();
+            camera.set_transform(Affine2::from_translation(delta * FREE_CAMERA_SPEED) * xform);
+        }
+
+        // Determine how far to advance the playback
+        if !viewer.paused {
+            viewer.budget += viewer.speed;
+            let steps = viewer.budget.floor();
+            viewer.budget -= steps;
+            viewer.pending_updates += steps as u32;
+        }
+
+        let remaining = (frame_count - viewer.cursor) as u32;
+        if viewer.pending_updates >= remaining {
+            viewer.pending_updates = remaining;
+            viewer.paused = true;
+        }
+
+        // Handle seeking
+        if let Some(target) = seek_to {
+            if let Some((snapshot, updates)) = state.seek(target) {
+                snapshots.restore(&snapshot);
+
+                // Seeking to before the game ended resumes the simulation.
+                if *scene.get() == GameScene::GameOver && !snapshot.outcome.is_over() {
+                    next_scene.set(GameScene::InGame);
+                }
+
+                state.viewer.as_mut().unwrap().pending_updates = updates;
+            }
+        }
+    });
+}
+
+pub fn sys_record_replay_input(
+    mut rand: RandomAccess<(&TileWorld, &TileChunk, &TileLayers, &Health, &StatusEffects)>,
+    mut snapshots: SnapshotState,
+    mut state: ResMut<ReplayState>,
+    mut input: ResMut<PlayerInput>,
+    saves: Res<SaveState>,
+) {
+    let state = &mut *state;
+
+    // Feed recorded inputs while viewing...
+    if let Some(viewer) = &mut state.viewer {
+        input.frame = state.frames.get(viewer.cursor).copied().unwrap_or_default();
+        viewer.cursor = (viewer.cursor + 1).min(state.frames.len());
+        return;
+    }
+
+    if !state.recording {
+        return;
+    }
+
+    // ...or from the log being played back...
+    let frame = state.frames.len();
+
+    if let Some(playback) = &state.playback {
+        match playback.log.frames.get(frame) {
+            Some(&recorded) => {
+                input.frame = recorded;
+
+                if frame == 0 {
+                    *snapshots.rng = playback.log.rng.clone();
+                }
+            }
+            None => {
+                log::info!("Finished playing back the replay");
+                state.playback = None;
+            }
+        }
+    }
+
+    // ...and record them either way.
+    if frame == 0 {
+        state.origin = saves
+            .restored_from()
+            .and_then(|backup| backup.path.file_name())
+            .map(|name| name.to_string_lossy().into_owned());
+
+        state.start_rng = Some(snapshots.rng.clone());
+    }
+
+    if frame % state.snapshot_interval == 0 {
+        let snapshot = rand.provide(|| snapshots.take(frame));
+        state.push_snapshot(snapshot);
+    }
+
+    state.frames.push(input.frame);
+
+    if state.frames.len() >= MAX_RECORDED_FRAMES {
+        log::warn!("Stopped recording after {MAX_RECORDED_FRAMES} frames");
+        state.recording = false;
+    }
+}
+
+/// Loads the replay given by `--replay`. This runs before the worlds are created so that they're
+/// generated from the recorded world seed.
+pub fn sys_load_replay_log(
+    mut state: ResMut<ReplayState>,
+    mut saves: ResMut<SaveState>,
+    mut next_scene: ResMut<NextState<GameScene>>,
+    mut rng: ResMut<Rng>,
+    config: Res<SaveConfig>,
+) {
+    let args = std::env::args().collect::<Vec<_>>();
+    state.recording = args.iter().any(|arg| arg == "--record");
+    let Some(path) = args
+        .windows(2)
+        .find(|pair| pair[0] == "--replay")
+        .map(|pair| PathBuf::from(&pair[1]))
+    else {
+        return;
+    };
+
+    let log = match ReplayLog::read(&path) {
+        Ok(log) => log,
+        Err(err) => {
+            log::error!("Failed to read replay {}: {err}", path.display());
+            return;
+        }
+    };
+
+    // Start from the same backup as the recorded session, or from a fresh world if it didn't
+    // restore one.
+    saves.pending_restore = None;
+
+    if let Some(origin) = &log.origin {
+        let backup = SaveBackup::list(&config.slot_dir())
+            .unwrap_or_default()
+            .into_iter()
+            .find(|backup| {
+                backup
+                    .path
+                    .file_name()
+                    .is_some_and(|name| name == origin.as_str())
+            });
+
+        match backup {
+            Some(backup) => saves.pending_restore = Some(RestoreRequest::Backup(backup)),
+            None => log::warn!(
+                "Replay starts from backup {origin} which no longer exists; playback will desync"
+            ),
+        }
+    }
+
+    // Playback starts right away rather than waiting in the main menu.
+    next_scene.set(GameScene::InGame);
+    rng.reseed(log.rng.seed());
+
+    log::info!(
+        "Playing back {} ({} frames, seed {})",
+        path.display(),
+        log.frames.len(),
+        log.rng.seed(),
+    );
+    state.start_playback(log);
+}
+
+pub fn sys_write_replay_log(state: Res<ReplayState>) {
+    if state.frames.is_empty() {
+        return;
+    }
+
+    let path = Path::new(REPLAY_LOG_PATH);
+    match state.to_log().write(path) {
+        Ok(()) => log::info!("Wrote replay to {}", path.display()),
+        Err(err) => log::error!("Failed to write replay to {}: {err}", path.display()),
+    }
+}
+
+pub fn sys_render_replay_overlay(state: Res<ReplayState>, input: Res<PlayerInput>) {
+    if let (None, Some(playback)) = (&state.viewer, &state.playback) {
+        let status = format!(
+            "PLAYBACK | frame {}/{}",
+            state.frames.len(),
+            playback.log.frames.len(),
+        );
+        draw_text(&status, 15., 60., 20., YELLOW);
+    }
+
+    let Some(viewer) = &state.viewer else {
+        return;
+    };
+
+    let status = format!(
+        "REPLAY {} | frame {}/{} | {}x | camera: {}",
+        if viewer.paused { "paused" } else { "playing" },
+        viewer.cursor,
+        state.frames.len(),
+        viewer.speed,
+        if viewer.follow_player {
+            "follow"
+        } else {
+            "free"
+        },
+    );
+    draw_text(&status, 15., 60., 20., YELLOW);
+
+    if viewer.show_timeline {
+        let width = screen_width() - 30.;
+        let y = screen_height() - 50.;
+        draw_rectangle(15., y, width, 8., GRAY);
+
+        let progress = viewer.cursor as f32 / state.frames.len().max(1) as f32;
+        draw_rectangle(15., y, width * progress, 8., WHITE);
+
+        for snapshot in &state.snapshots {
+            let x = 15. + width * snapshot.frame as f32 / state.frames.len().max(1) as f32;
+            draw_line(x, y - 4., x, y + 12., 2., YELLOW);
+        }
+    }
+
+    if viewer.show_inputs {
+        let center = Vec2::new(screen_width() - 60., 100.);
+        let tip = center + input.frame.heading * 40.;
+        draw_rectangle(
+            center.x - 45.,
+            center.y - 45.,
+            90.,
+            90.,
+            Color::new(0., 0., 0., 0.5),
+        );
+        draw_line(center.x, center.y, tip.x, tip.y, 3., RED);
+
+        if let Some(stroke) = input.frame.stroke {
+            draw_text(
+                &format!("{:?}", stroke.action),
+                center.x - 40.,
+                center.y + 40.,
+                18.,
+                WHITE,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_log() -> ReplayLog {
+        let mut rng = Rng::new(42);
+        rng.stream(RngChannel::Waves).next_u64();
+
+        let held = InputFrame {
+            heading: Vec2::new(1., 0.),
+            ..Default::default()
+        };
+        let stroke = InputFrame {
+            heading: Vec2::new(-1., 1.),
+            stroke: Some(InputStroke {
+                from: Vec2::new(0.5, -2.),
+                to: Vec2::new(30., 12.25),
+                action: StrokeAction::Use,
+            }),
+            select: Some(3),
+            scroll: -2,
+        };
+
+        ReplayLog {
+            origin: Some("auto-1234".to_string()),
+            rng,
+            frames: vec![held, held, held, stroke, InputFrame::default()],
+        }
+    }
+
+    fn header() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(REPLAY_MAGIC);
+        bytes.extend_from_slice(&REPLAY_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&7u64.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes
+    }
+
+    fn with_run(run: u32) -> Vec<u8> {
+        let mut bytes = header();
+        bytes.extend_from_slice(&run.to_le_bytes());
+        encode_frame(&mut bytes, &InputFrame::default());
+        bytes
+    }
+
+    fn decode_err(bytes: &[u8]) -> String {
+        ReplayLog::decode(bytes).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn log_round_trips() {
+        let log = sample_log();
+        let decoded = ReplayLog::decode(&log.encode()).unwrap();
+
+        assert_eq!(decoded.origin, log.origin);
+        assert_eq!(decoded.rng.seed(), log.rng.seed());
+        assert_eq!(
+            decoded.rng.stream_states().collect::<Vec<_>>(),
+            log.rng.stream_states().collect::<Vec<_>>(),
+        );
+        assert_eq!(decoded.frames, log.frames);
+
+        let empty = ReplayLog {
+            origin: None,
+            rng: Rng::new(0),
+            frames: Vec::new(),
+        };
+        let decoded = ReplayLog::decode(&empty.encode()).unwrap();
+        assert_eq!(decoded.origin, None);
+        assert!(decoded.frames.is_empty());
+    }
+
+    #[test]
+    fn malformed_logs_are_rejected() {
+        let bytes = sample_log().encode();
+
+        assert!(decode_err(&bytes[..bytes.len() - 1]).contains("truncated"));
+        assert!(decode_err(&bytes[..6]).contains("truncated"));
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(decode_err(&bad_magic).contains("magic"));
+
+        let mut bad_version = bytes;
+        bad_version[4] = 0;
+        assert!(decode_err(&bad_version).contains("unsupported version"));
+
+        let mut bad_channel = header();
+        bad_channel.truncate(bad_channel.len() - 4);
+        bad_channel.extend_from_slice(&1u32.to_le_bytes());
+        bad_channel.push(u8::MAX);
+        bad_channel.extend_from_slice(&0u64.to_le_bytes());
+        assert!(decode_err(&bad_channel).contains("RNG channel"));
+
+        let mut bad_stroke = header();
+        bad_stroke.extend_from_slice(&1u32.to_le_bytes());
+        bad_stroke.extend_from_slice(&[0; 8]);
+        bad_stroke.push(9);
+        assert!(decode_err(&bad_stroke).contains("stroke action"));
+
+        assert!(decode_err(&with_run(0)).contains("empty run"));
+        assert!(decode_err(&with_run(MAX_RECORDED_FRAMES as u32 + 1)).contains("too many frames"));
+        assert_eq!(
+            ReplayLog::decode(&with_run(MAX_RECORDED_FRAMES as u32))
+                .unwrap()
+                .frames
+                .len(),
+            MAX_RECORDED_FRAMES,
+        );
+
+        // The limit applies to the whole log rather than to each run.
+        let mut split = with_run(MAX_RECORDED_FRAMES as u32);
+        split.extend_from_slice(&1u32.to_le_bytes());
+        encode_frame(&mut split, &InputFrame::default());
+        assert!(decode_err(&split).contains("too many frames"));
+    }
+}
@@ -0,0 +1,185 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy_ecs::system::{ResMut, Resource};
+use rustc_hash::FxHashMap;
+
+// === Rng === //
+
+/// The independent random number streams handed out by the [`Rng`]. Giving every subsystem its
+/// own stream means that drawing more numbers in one of them doesn't shift the numbers seen by the
+/// others.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum RngChannel {
+    Projectiles,
+    Particles,
+    Scripts,
+    Waves,
+}
+
+impl RngChannel {
+    pub const VARIANTS: [Self; 4] = [
+        Self::Projectiles,
+        Self::Particles,
+        Self::Scripts,
+        Self::Waves,
+    ];
+}
+
+/// The source of all gameplay randomness. Every stream is derived from a single seed so that a
+/// session can be reproduced exactly by starting it with the same seed and inputs. Replays clone
+/// the generator at every snapshot rather than reseeding it so that recording a session never
+/// changes its outcome.
+#[derive(Debug, Clone, Resource)]
+pub struct Rng {
+    seed: u64,
+    streams: FxHashMap<RngChannel, RngStream>,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+
+        Self::new(seed)
+    }
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: FxHashMap::default(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Restarts every stream from `seed`.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.streams.clear();
+    }
+
+    /// The state of every stream which has been drawn from so far.
+    pub fn stream_states(&self) -> impl Iterator<Item = (RngChannel, u64)> + '_ {
+        self.streams
+            .iter()
+            .map(|(&channel, stream)| (channel, stream.state))
+    }
+
+    /// Recreates a generator from its `seed` and the [states](Self::stream_states) of its streams.
+    pub fn from_stream_states(
+        seed: u64,
+        states: impl IntoIterator<Item = (RngChannel, u64)>,
+    ) -> Self {
+        Self {
+            seed,
+            streams: states
+                .into_iter()
+                .map(|(channel, state)| (channel, RngStream::new(state)))
+                .collect(),
+        }
+    }
+
+    pub fn stream(&mut self, channel: RngChannel) -> &mut RngStream {
+        let seed = self.seed;
+        self.streams.entry(channel).or_insert_with(|| {
+            let salt = (channel as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            RngStream::new(seed ^ salt)
+        })
+    }
+}
+
+// === RngStream === //
+
+/// A single SplitMix64 random number stream.
+#[derive(Debug, Clone)]
+pub struct RngStream {
+    state: u64,
+}
+
+impl RngStream {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Produces a uniformly distributed float in the range `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Produces a uniformly distributed value in the range `[low, high)`, or `low` if the range is
+    /// empty.
+    pub fn gen_range<T: SampleRange>(&mut self, low: T, high: T) -> T {
+        T::sample(self, low, high)
+    }
+
+    /// Returns `true` with probability `chance`.
+    pub fn gen_bool(&mut self, chance: f32) -> bool {
+        self.next_f32() < chance
+    }
+}
+
+pub trait SampleRange: Sized {
+    fn sample(rng: &mut RngStream, low: Self, high: Self) -> Self;
+}
+
+impl SampleRange for f32 {
+    fn sample(rng: &mut RngStream, low: Self, high: Self) -> Self {
+        if high <= low {
+            return low;
+        }
+
+        low + (high - low) * rng.next_f32()
+    }
+}
+
+macro_rules! impl_int_sample_range {
+    ($($ty:ty),*) => {$(
+        impl SampleRange for $ty {
+            fn sample(rng: &mut RngStream, low: Self, high: Self) -> Self {
+                if high <= low {
+                    return low;
+                }
+
+                let span = high.abs_diff(low) as u64;
+                low.wrapping_add((rng.next_u64() % span) as Self)
+            }
+        }
+    )*};
+}
+
+impl_int_sample_range!(i32, u32, usize);
+
+// === Systems === //
+
+/// Seeds the [`Rng`] from the `--seed` argument, if given, and logs the seed in use so that the
+/// session can be reproduced later.
+pub fn sys_seed_rng(mut rng: ResMut<Rng>) {
+    let args = std::env::args().collect::<Vec<_>>();
+    let seed = args
+        .windows(2)
+        .find(|pair| pair[0] == "--seed")
+        .map(|pair| &pair[1]);
+
+    if let Some(seed) = seed {
+        match seed.parse() {
+            Ok(seed) => rng.reseed(seed),
+            Err(err) => log::error!("Ignoring invalid seed {seed:?}: {err}"),
+        }
+    }
+
+    log::info!("RNG seed: {}", rng.seed());
+}
@@ -0,0 +1,275 @@
+use std::{error::Error, fmt, fs, io};
+
+use bevy_ecs::{
+    query::With,
+    schedule::NextState,
+    system::{Query, Res, ResMut, Resource},
+};
+use macroquad::{
+    color::{Color, GREEN, RED, WHITE},
+    shapes::draw_rectangle,
+    text::{draw_text, measure_text},
+    window::{screen_height, screen_width},
+};
+use rustc_hash::FxHashMap;
+
+use crate::{
+    game::{actor::health::Health, scene::GameScene, tile::data::TileWorld, time::TICK_DURATION},
+    util::arena::{ObjOwner, RandomAccess},
+};
+
+// === GameStats === //
+
+#[derive(Debug, Clone, Default, Resource)]
+pub struct GameStats {
+    elapsed: f32,
+    counters: FxHashMap<String, u64>,
+}
+
+impl GameStats {
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    pub fn get(&self, name: &str) -> u64 {
+        self.counters.get(name).copied().unwrap_or(0)
+    }
+
+    pub fn bump(&mut self, name: &str, by: u64) {
+        match self.counters.get_mut(name) {
+            Some(counter) => *counter += by,
+            None => {
+                self.counters.insert(name.to_string(), by);
+            }
+        }
+    }
+
+    pub fn counters(&self) -> impl Iterator<Item = (&str, u64)> + '_ {
+        self.counters
+            .iter()
+            .map(|(name, &count)| (name.as_str(), count))
+    }
+}
+
+// === GameRules === //
+
+/// The rules used when no `rules.txt` file is present in the working directory.
+pub const DEFAULT_RULES: &str = "\
+# Lose once the base is destroyed.
+defeat base_health_at_most 0
+# Win by surviving ten minutes or by digging out enough terrain.
+victory survive 600
+victory stat tiles_mined 2000
+";
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum Outcome {
+    Victory,
+    Defeat,
+}
+
+#[derive(Debug, Clone)]
+pub enum Condition {
+    /// Triggers once the game has been running for the given number of seconds.
+    Survive(f32),
+
+    /// Triggers once the health of any world's base drops to or below the given amount.
+    BaseHealthAtMost(f32),
+
+    /// Triggers once the named [`GameStats`] counter, such as `tiles_mined` or `items_collected`,
+    /// reaches the given value.
+    StatAtLeast(String, u64),
+}
+
+impl Condition {
+    pub fn describe(&self) -> String {
+        match self {
+            Condition::Survive(secs) => format!("Survived for {secs} seconds"),
+            Condition::BaseHealthAtMost(health) => format!("Base health fell to {health}"),
+            Condition::StatAtLeast(stat, value) => format!("Reached {value} {stat}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub outcome: Outcome,
+    pub condition: Condition,
+}
+
+#[derive(Debug, Clone, Resource)]
+pub struct GameRules {
+    pub rules: Vec<Rule>,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self::parse(DEFAULT_RULES).unwrap()
+    }
+}
+
+impl GameRules {
+    /// Parses a rule file. Each non-empty line not starting with `#` has the form
+    /// `<victory|defeat> <condition> <args...>`.
+    pub fn parse(text: &str) -> Result<Self, RuleParseError> {
+        let mut rules = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let err = |message: &str| RuleParseError {
+                line: i + 1,
+                message: message.to_string(),
+            };
+
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+
+            let outcome = match parts[0] {
+                "victory" => Outcome::Victory,
+                "defeat" => Outcome::Defeat,
+                _ => return Err(err("expected `victory` or `defeat`")),
+            };
+
+            let condition = match parts[1..] {
+                ["survive", secs] => {
+                    Condition::Survive(secs.parse().map_err(|_| err("invalid duration"))?)
+                }
+                ["base_health_at_most", health] => {
+                    Condition::BaseHealthAtMost(health.parse().map_err(|_| err("invalid health"))?)
+                }
+                ["stat", stat, value] => Condition::StatAtLeast(
+                    stat.to_string(),
+                    value.parse().map_err(|_| err("invalid stat value"))?,
+                ),
+                _ => return Err(err("unknown condition")),
+            };
+
+            rules.push(Rule { outcome, condition });
+        }
+
+        Ok(Self { rules })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RuleParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RuleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rule error on line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for RuleParseError {}
+
+// === GameOutcome === //
+
+#[derive(Debug, Clone, Default, Resource)]
+pub struct GameOutcome {
+    pub result: Option<(Outcome, String)>,
+}
+
+impl GameOutcome {
+    pub fn is_over(&self) -> bool {
+        self.result.is_some()
+    }
+}
+
+// === Systems === //
+
+pub fn sys_load_game_rules(mut rules: ResMut<GameRules>) {
+    let text = match fs::read_to_string("rules.txt") {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return,
+        Err(err) => {
+            log::error!("Failed to read rules.txt: {err}");
+            return;
+        }
+    };
+
+    match GameRules::parse(&text) {
+        Ok(parsed) => *rules = parsed,
+        Err(err) => log::error!("Failed to parse rules.txt, using the default rules: {err}"),
+    }
+}
+
+pub fn sys_evaluate_game_rules(
+    mut rand: RandomAccess<&Health>,
+    query: Query<&ObjOwner<Health>, With<ObjOwner<TileWorld>>>,
+    rules: Res<GameRules>,
+    mut stats: ResMut<GameStats>,
+    mut outcome: ResMut<GameOutcome>,
+    mut next_scene: ResMut<NextState<GameScene>>,
+) {
+    if outcome.is_over() {
+        return;
+    }
+
+    // Count simulated rather than real time so that headless runs and replays agree with the
+    // original session.
+    stats.elapsed += TICK_DURATION;
+
+    rand.provide(|| {
+        for rule in &rules.rules {
+            let triggered = match &rule.condition {
+                Condition::Survive(secs) => stats.elapsed >= *secs,
+                Condition::BaseHealthAtMost(health) => {
+                    query.iter().any(|&ObjOwner(hp)| hp.health() <= *health)
+                }
+                Condition::StatAtLeast(stat, value) => stats.get(stat) >= *value,
+            };
+
+            if triggered {
+                log::info!("Game ended with {:?}: {:?}", rule.outcome, rule.condition);
+                outcome.result = Some((rule.outcome, rule.condition.describe()));
+                next_scene.set(GameScene::GameOver);
+                return;
+            }
+        }
+    });
+}
+
+pub fn sys_render_game_summary(outcome: Res<GameOutcome>, stats: Res<GameStats>) {
+    let Some((result, reason)) = &outcome.result else {
+        return;
+    };
+
+    draw_rectangle(
+        0.,
+        0.,
+        screen_width(),
+        screen_height(),
+        Color::new(0., 0., 0., 0.6),
+    );
+
+    let (title, color) = match result {
+        Outcome::Victory => ("VICTORY", GREEN),
+        Outcome::Defeat => ("DEFEAT", RED),
+    };
+
+    let center_x = screen_width() / 2.;
+    let mut y = screen_height() / 3.;
+
+    let mut draw_centered = |text: &str, size: f32, color: Color| {
+        let dims = measure_text(text, None, size as u16, 1.);
+        draw_text(text, center_x - dims.width / 2., y, size, color);
+        y += size + 5.;
+    };
+
+    draw_centered(title, 64., color);
+    draw_centered(reason, 24., WHITE);
+    draw_centered(&format!("Time: {:.1}s", stats.elapsed()), 20., WHITE);
+
+    let mut counters = stats.counters().collect::<Vec<_>>();
+    counters.sort();
+
+    for (name, count) in counters {
+        draw_centered(&format!("{name}: {count}"), 20., WHITE);
+    }
+}
@@ -0,0 +1,1890 @@
+use std::{
+    cmp::Reverse,
+    fs, io, mem,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, SyncSender, TrySendError},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use bevy_ecs::{
+    component::Component,
+    system::{Query, Res, ResMut, Resource, SystemParam},
+};
+use bevy_tasks::{block_on, IoTaskPool, Task, TaskPool};
+use macroquad::{
+    color::{WHITE, YELLOW},
+    input::{is_key_pressed, KeyCode},
+    math::IVec2,
+    text::draw_text,
+    texture::{FilterMode, Texture2D},
+    window::screen_height,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    game::{
+        actor::camera::ActiveCamera,
+        minimap::{capture_thumbnail, ThumbnailAccess},
+        rng::Rng,
+        rules::GameStats,
+        tile::{
+            data::{
+                ChunkDirtyKind, ExploredBits, TileChanged, TileChunk, TileLayerConfig, TileLayers,
+                TileWorld, WorldCreatedChunk,
+            },
+            edit_log::TileEditLog,
+            generator::TileGenerator,
+            material::MaterialRegistry,
+            tile_entity::{TileEntityCreated, TileEntityDescriptor},
+        },
+    },
+    util::{
+        arena::{Obj, ObjOwner, RandomAccess, RandomEntityExt, SendsEvent},
+        parallel::par_map,
+    },
+};
+
+// === SaveConfig === //
+
+#[derive(Debug, Clone, Resource)]
+pub struct SaveConfig {
+    /// The directory containing every save slot.
+    pub root: PathBuf,
+
+    /// The name of the active save slot.
+    pub slot: String,
+
+    /// The number of automatic saves to keep per slot.
+    pub keep_autosaves: usize,
+
+    /// The number of manual saves to keep per slot.
+    pub keep_manual: usize,
+
+    pub autosave_interval: Duration,
+}
+
+impl Default for SaveConfig {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::from("saves"),
+            slot: "default".to_string(),
+            keep_autosaves: 5,
+            keep_manual: 10,
+            autosave_interval: Duration::from_secs(120),
+        }
+    }
+}
+
+impl SaveConfig {
+    pub fn slot_dir(&self) -> PathBuf {
+        self.root.join(&self.slot)
+    }
+
+    pub fn keep_count(&self, kind: SaveKind) -> usize {
+        match kind {
+            SaveKind::Auto => self.keep_autosaves,
+            SaveKind::Manual => self.keep_manual,
+        }
+    }
+}
+
+// === SaveBackup === //
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum SaveKind {
+    Auto,
+    Manual,
+}
+
+impl SaveKind {
+    pub fn prefix(self) -> &'static str {
+        match self {
+            SaveKind::Auto => "auto",
+            SaveKind::Manual => "manual",
+        }
+    }
+
+    pub fn from_prefix(prefix: &str) -> Option<Self> {
+        match prefix {
+            "auto" => Some(SaveKind::Auto),
+            "manual" => Some(SaveKind::Manual),
+            _ => None,
+        }
+    }
+}
+
+/// A single timestamped save directory within a slot.
+#[derive(Debug, Clone)]
+pub struct SaveBackup {
+    pub kind: SaveKind,
+    pub timestamp: u64,
+    pub path: PathBuf,
+}
+
+impl SaveBackup {
+    /// Lists every backup in the slot directory, newest first.
+    pub fn list(slot_dir: &Path) -> io::Result<Vec<Self>> {
+        let mut backups = Vec::new();
+
+        let entries = match fs::read_dir(slot_dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(backups),
+            Err(err) => return Err(err),
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+            if let Some(backup) = Self::parse(path) {
+                backups.push(backup);
+            }
+        }
+
+        backups.sort_by_key(|backup| Reverse(backup.timestamp));
+        Ok(backups)
+    }
+
+    fn parse(path: PathBuf) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        let (prefix, timestamp) = name.split_once('-')?;
+
+        Some(Self {
+            kind: SaveKind::from_prefix(prefix)?,
+            timestamp: timestamp.parse().ok()?,
+            path,
+        })
+    }
+
+    pub fn label(&self) -> String {
+        format!("{} save @ {}ms", self.kind.prefix(), self.timestamp)
+    }
+
+    /// Reads the backup's metadata. Backups written before metadata was recorded, or whose
+    /// metadata is unreadable, only report their timestamp.
+    pub fn metadata(&self) -> SaveMetadata {
+        fs::read_to_string(self.path.join(METADATA_NAME))
+            .and_then(|text| SaveMetadata::decode(&text))
+            .unwrap_or(SaveMetadata {
+                timestamp: self.timestamp,
+                playtime: 0.,
+                seed: None,
+            })
+    }
+
+    pub fn thumbnail(&self) -> io::Result<Option<SaveThumbnail>> {
+        match fs::read(self.path.join(THUMBNAIL_NAME)) {
+            Ok(bytes) => SaveThumbnail::decode(&bytes).map(Some),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Deletes the oldest backups of the given kind such that at most `keep` of them remain.
+pub fn rotate_backups(slot_dir: &Path, kind: SaveKind, keep: usize) -> io::Result<()> {
+    let stale = SaveBackup::list(slot_dir)?
+        .into_iter()
+        .filter(|backup| backup.kind == kind)
+        .skip(keep);
+
+    for backup in stale {
+        log::info!("Removing stale backup {}", backup.path.display());
+        fs::remove_dir_all(&backup.path)?;
+    }
+
+    Ok(())
+}
+
+// === RegionData === //
+
+const REGION_MAGIC: &[u8; 4] = b"BDRG";
+const REGION_HEADER_LEN: usize = 20;
+
+/// Version 1 regions store every tile verbatim.
+const REGION_VERSION_RAW: u32 = 1;
+
+/// Version 2 regions store tiles as a sequence of `(run length, tile)` pairs.
+const REGION_VERSION_RLE: u32 = 2;
+
+/// Version 3 regions are version 2 regions followed by the chunk's explored bits.
+const REGION_VERSION_RLE_EXPLORED: u32 = 3;
+
+/// A region of a saved world, as the world's [`SavedWorld`] id, the layer, and the chunk position.
+pub type RegionKey = (u32, u32, IVec2);
+
+/// The name of the file a region is saved to.
+pub fn region_file_name((world, layer, pos): RegionKey) -> String {
+    format!("region_{world}_{layer}_{}_{}.bin", pos.x, pos.y)
+}
+
+/// The serialized contents of a single chunk of a single tile layer.
+#[derive(Debug, Clone)]
+pub struct RegionData {
+    /// The [`SavedWorld`] id of the world the region belongs to. It isn't part of the encoded region
+    /// since saves record it in their manifest and packets send it alongside the region.
+    pub world: u32,
+    pub layer: u32,
+    pub pos: IVec2,
+    pub tiles: Box<[u16; TileLayerConfig::CHUNK_AREA as usize]>,
+
+    /// The tiles of the chunk which players have explored. Regions without them, such as those
+    /// sent to clients, leave the exploration of the chunk they're applied to alone.
+    pub explored: Option<Box<ExploredBits>>,
+}
+
+impl RegionData {
+    pub fn key(&self) -> RegionKey {
+        (self.world, self.layer, self.pos)
+    }
+
+    pub fn file_name(&self) -> String {
+        region_file_name(self.key())
+    }
+
+    /// Encodes the region in the run-length compressed format. Chunks tend to consist of a handful
+    /// of long runs of the same material so this is usually a fraction of the raw size.
+    pub fn encode(&self) -> Vec<u8> {
+        let version = match self.explored {
+            Some(_) => REGION_VERSION_RLE_EXPLORED,
+            None => REGION_VERSION_RLE,
+        };
+
+        let mut bytes = Vec::with_capacity(REGION_HEADER_LEN + 64);
+        bytes.extend_from_slice(REGION_MAGIC);
+        bytes.extend_from_slice(&version.to_le_bytes());
+        bytes.extend_from_slice(&self.layer.to_le_bytes());
+        bytes.extend_from_slice(&self.pos.x.to_le_bytes());
+        bytes.extend_from_slice(&self.pos.y.to_le_bytes());
+
+        let mut tiles = self.tiles.iter().copied().peekable();
+        while let Some(tile) = tiles.next() {
+            let mut run = 1u16;
+            while tiles.next_if_eq(&tile).is_some() {
+                run += 1;
+            }
+
+            bytes.extend_from_slice(&run.to_le_bytes());
+            bytes.extend_from_slice(&tile.to_le_bytes());
+        }
+
+        for word in self.explored.iter().flat_map(|explored| explored.iter()) {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let word = |at: usize| -> [u8; 4] { bytes[at..at + 4].try_into().unwrap() };
+
+        if bytes.len() < REGION_HEADER_LEN {
+            return Err(invalid("region file is truncated"));
+        }
+
+        if &bytes[0..4] != REGION_MAGIC {
+            return Err(invalid("region file has a bad magic number"));
+        }
+
+        let mut body = &bytes[REGION_HEADER_LEN..];
+        let mut tiles = Box::new([0; TileLayerConfig::CHUNK_AREA as usize]);
+        let mut explored = None;
+
+        let version = u32::from_le_bytes(word(4));
+        if version == REGION_VERSION_RLE_EXPLORED {
+            let len = mem::size_of::<ExploredBits>();
+            let Some(split) = body.len().checked_sub(len) else {
+                return Err(invalid("region file is missing its explored bits"));
+            };
+
+            let mut bits = Box::new([0; TileLayerConfig::CHUNK_AREA as usize / 64]);
+            for (word, raw) in bits.iter_mut().zip(body[split..].chunks_exact(8)) {
+                *word = u64::from_le_bytes(raw.try_into().unwrap());
+            }
+
+            explored = Some(bits);
+            body = &body[..split];
+        }
+
+        match version {
+            REGION_VERSION_RAW => {
+                if body.len() != tiles.len() * 2 {
+                    return Err(invalid("region file has the wrong size"));
+                }
+
+                for (tile, raw) in tiles.iter_mut().zip(body.chunks_exact(2)) {
+                    *tile = u16::from_le_bytes([raw[0], raw[1]]);
+                }
+            }
+            REGION_VERSION_RLE | REGION_VERSION_RLE_EXPLORED => {
+                if body.len() % 4 != 0 {
+                    return Err(invalid("region file has a truncated run"));
+                }
+
+                let mut cursor = 0;
+                for run in body.chunks_exact(4) {
+                    let len = u16::from_le_bytes([run[0], run[1]]) as usize;
+                    let tile = u16::from_le_bytes([run[2], run[3]]);
+
+                    let Some(dest) = tiles.get_mut(cursor..cursor + len) else {
+                        return Err(invalid("region file has too many tiles"));
+                    };
+                    dest.fill(tile);
+                    cursor += len;
+                }
+
+                if cursor != tiles.len() {
+                    return Err(invalid("region file has too few tiles"));
+                }
+            }
+            _ => return Err(invalid("region file has an unsupported version")),
+        }
+
+        Ok(Self {
+            world: 0,
+            layer: u32::from_le_bytes(word(8)),
+            pos: IVec2::new(i32::from_le_bytes(word(12)), i32::from_le_bytes(word(16))),
+            tiles,
+            explored,
+        })
+    }
+}
+
+/// A 64-bit FNV-1a hash used to detect corrupted region files.
+pub fn checksum(bytes: &[u8]) -> u64 {
+    let mut hash = 0xCBF2_9CE4_8422_2325_u64;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+    }
+    hash
+}
+
+// === SaveManifest === //
+
+const MANIFEST_NAME: &str = "manifest.txt";
+
+#[derive(Debug, Clone, Default)]
+pub struct SaveManifest {
+    pub regions: Vec<ManifestEntry>,
+}
+
+/// A region file of a save along with the world it belongs to and the checksum of its contents.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct ManifestEntry {
+    pub world: u32,
+    pub name: String,
+    pub checksum: u64,
+}
+
+impl SaveManifest {
+    pub fn encode(&self) -> String {
+        let mut text = String::new();
+        for entry in &self.regions {
+            text.push_str(&format!(
+                "region {} {:016x} {}\n",
+                entry.name, entry.checksum, entry.world
+            ));
+        }
+        text
+    }
+
+    pub fn decode(text: &str) -> io::Result<Self> {
+        let invalid = |line: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("malformed manifest line: {line:?}"),
+            )
+        };
+        let mut manifest = Self::default();
+
+        for line in text.lines() {
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+
+            // Saves from before there were several worlds only had the main one.
+            let (name, sum, world) = match parts[..] {
+                ["region", name, sum] => (name, sum, "0"),
+                ["region", name, sum, world] => (name, sum, world),
+                _ => return Err(invalid(line)),
+            };
+
+            let checksum = u64::from_str_radix(sum, 16)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            let world = world.parse().map_err(|_| invalid(line))?;
+
+            manifest.regions.push(ManifestEntry {
+                world,
+                name: name.to_string(),
+                checksum,
+            });
+        }
+
+        Ok(manifest)
+    }
+
+    pub fn read(dir: &Path) -> io::Result<Self> {
+        Self::decode(&fs::read_to_string(dir.join(MANIFEST_NAME))?)
+    }
+}
+
+// === SaveMetadata === //
+
+const METADATA_NAME: &str = "meta.txt";
+
+/// Information about a save which can be shown without reading its regions.
+#[derive(Debug, Clone, Default)]
+pub struct SaveMetadata {
+    /// The time at which the save was written, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+
+    /// The total time spent playing the game up to this save, in seconds.
+    pub playtime: f32,
+
+    /// The world seed from which the regions missing from the save are generated. Saves written
+    /// before seeds were recorded don't have one.
+    pub seed: Option<u64>,
+}
+
+impl SaveMetadata {
+    pub fn encode(&self) -> String {
+        let mut text = format!(
+            "timestamp {}\nplaytime {:.3}\n",
+            self.timestamp, self.playtime
+        );
+        if let Some(seed) = self.seed {
+            text.push_str(&format!("seed {seed}\n"));
+        }
+        text
+    }
+
+    pub fn decode(text: &str) -> io::Result<Self> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let mut meta = Self::default();
+
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once(' ') else {
+                return Err(invalid(format!("malformed metadata line: {line:?}")));
+            };
+
+            match key {
+                "timestamp" => {
+                    meta.timestamp = value
+                        .parse()
+                        .map_err(|_| invalid(format!("bad timestamp: {value:?}")))?;
+                }
+                "playtime" => {
+                    meta.playtime = value
+                        .parse()
+                        .map_err(|_| invalid(format!("bad playtime: {value:?}")))?;
+                }
+                "seed" => {
+                    meta.seed = Some(
+                        value
+                            .parse()
+                            .map_err(|_| invalid(format!("bad seed: {value:?}")))?,
+                    );
+                }
+                // Newer versions may record more information.
+                _ => {}
+            }
+        }
+
+        Ok(meta)
+    }
+
+    /// Describes how long ago the save was written, e.g. `"5 min ago"`.
+    pub fn age_label(&self) -> String {
+        let secs = unix_millis().saturating_sub(self.timestamp) / 1000;
+
+        match secs {
+            0..=59 => "just now".to_string(),
+            60..=3599 => format!("{} min ago", secs / 60),
+            3600..=86399 => format!("{} h ago", secs / 3600),
+            _ => format!("{} days ago", secs / 86400),
+        }
+    }
+
+    /// Formats the playtime as hours and minutes, e.g. `"1h 05m"`.
+    pub fn playtime_label(&self) -> String {
+        let mins = (self.playtime / 60.) as u64;
+        format!("{}h {:02}m", mins / 60, mins % 60)
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+// === SaveThumbnail === //
+
+const THUMBNAIL_NAME: &str = "thumbnail.bin";
+const THUMBNAIL_MAGIC: &[u8; 4] = b"BDTN";
+const THUMBNAIL_HEADER_LEN: usize = 8;
+
+/// A small picture of the world around the camera, drawn in the minimap's colors. Stored as a
+/// header holding the width and height as `u16`s followed by the raw RGBA pixels.
+#[derive(Debug, Clone)]
+pub struct SaveThumbnail {
+    pub width: u16,
+    pub height: u16,
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl SaveThumbnail {
+    /// The width and height of captured thumbnails, in tiles. Each tile becomes a single pixel.
+    pub const SIZE: u16 = 96;
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(THUMBNAIL_HEADER_LEN + self.pixels.len() * 4);
+        bytes.extend_from_slice(THUMBNAIL_MAGIC);
+        bytes.extend_from_slice(&self.width.to_le_bytes());
+        bytes.extend_from_slice(&self.height.to_le_bytes());
+        bytes.extend(self.pixels.iter().flatten());
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        if bytes.len() < THUMBNAIL_HEADER_LEN {
+            return Err(invalid("thumbnail file is truncated"));
+        }
+
+        if &bytes[0..4] != THUMBNAIL_MAGIC {
+            return Err(invalid("thumbnail file has a bad magic number"));
+        }
+
+        let width = u16::from_le_bytes([bytes[4], bytes[5]]);
+        let height = u16::from_le_bytes([bytes[6], bytes[7]]);
+        let body = &bytes[THUMBNAIL_HEADER_LEN..];
+
+        if body.len() != width as usize * height as usize * 4 {
+            return Err(invalid("thumbnail file has the wrong size"));
+        }
+
+        Ok(Self {
+            width,
+            height,
+            pixels: body
+                .chunks_exact(4)
+                .map(|pixel| pixel.try_into().unwrap())
+                .collect(),
+        })
+    }
+
+    /// Uploads the thumbnail to the GPU. Must be called from the render thread.
+    pub fn to_texture(&self) -> Texture2D {
+        let bytes = self.pixels.iter().flatten().copied().collect::<Vec<_>>();
+        let texture = Texture2D::from_rgba8(self.width, self.height, &bytes);
+        texture.set_filter(FilterMode::Nearest);
+        texture
+    }
+}
+
+// === SaveIndex === //
+
+const INDEX_NAME: &str = "index.txt";
+
+/// The most recent save of a single slot, as recorded in the [`SaveIndex`].
+#[derive(Debug, Clone)]
+pub struct SlotEntry {
+    pub name: String,
+    pub latest: SaveMetadata,
+}
+
+/// Lists every save slot along with the metadata of its most recent save so that the load menu
+/// doesn't have to scan each slot's backups. Kept in the save root and updated after every save.
+#[derive(Debug, Clone, Default)]
+pub struct SaveIndex {
+    /// The slots, most recently saved first.
+    pub slots: Vec<SlotEntry>,
+}
+
+impl SaveIndex {
+    pub fn encode(&self) -> String {
+        let mut text = String::new();
+        for slot in &self.slots {
+            text.push_str(&format!(
+                "slot {} {:.3} {}\n",
+                slot.latest.timestamp, slot.latest.playtime, slot.name
+            ));
+        }
+        text
+    }
+
+    pub fn decode(text: &str) -> io::Result<Self> {
+        let mut index = Self::default();
+
+        for line in text.lines() {
+            let parts = line.splitn(4, ' ').collect::<Vec<_>>();
+            let parsed = match parts[..] {
+                ["slot", timestamp, playtime, name] => timestamp
+                    .parse()
+                    .ok()
+                    .zip(playtime.parse().ok())
+                    .map(|(timestamp, playtime)| (timestamp, playtime, name)),
+                _ => None,
+            };
+
+            let Some((timestamp, playtime, name)) = parsed else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("malformed index line: {line:?}"),
+                ));
+            };
+
+            index.slots.push(SlotEntry {
+                name: name.to_string(),
+                latest: SaveMetadata {
+                    timestamp,
+                    playtime,
+                    seed: None,
+                },
+            });
+        }
+
+        Ok(index)
+    }
+
+    /// Reads the index in `root`, rebuilding it from the slots on disk if it is missing or
+    /// corrupted.
+    pub fn load(root: &Path) -> io::Result<Self> {
+        match fs::read_to_string(root.join(INDEX_NAME)) {
+            Ok(text) => match Self::decode(&text) {
+                Ok(index) => return Ok(index),
+                Err(err) => log::warn!("Save index is corrupted ({err}); rebuilding it"),
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+
+        Self::rebuild(root)
+    }
+
+    /// Scans every slot directory in `root` for its most recent backup.
+    pub fn rebuild(root: &Path) -> io::Result<Self> {
+        let mut index = Self::default();
+
+        let entries = match fs::read_dir(root) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(index),
+            Err(err) => return Err(err),
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+
+            if !path.is_dir() {
+                continue;
+            }
+
+            if let Some(backup) = SaveBackup::list(&path)?.into_iter().next() {
+                index.record(name, backup.metadata());
+            }
+        }
+
+        Ok(index)
+    }
+
+    pub fn write(&self, root: &Path) -> io::Result<()> {
+        // Like saves, the index is written next to its final path and then renamed into place.
+        let path = root.join(INDEX_NAME);
+        let staging = path.with_extension(STAGING_EXTENSION);
+        fs::create_dir_all(root)?;
+        fs::write(&staging, self.encode())?;
+        fs::rename(&staging, &path)
+    }
+
+    pub fn slot(&self, name: &str) -> Option<&SlotEntry> {
+        self.slots.iter().find(|slot| slot.name == name)
+    }
+
+    /// Records a new save in the given slot, moving it to the front of the list.
+    pub fn record(&mut self, name: &str, latest: SaveMetadata) {
+        self.slots.retain(|slot| slot.name != name);
+        self.slots.push(SlotEntry {
+            name: name.to_string(),
+            latest,
+        });
+        self.slots
+            .sort_by_key(|slot| Reverse(slot.latest.timestamp));
+    }
+
+    /// Picks a name for a new slot which isn't used by any existing one.
+    pub fn fresh_slot_name(&self) -> String {
+        (1..)
+            .map(|i| format!("game-{i}"))
+            .find(|name| self.slot(name).is_none())
+            .unwrap()
+    }
+}
+
+// === Reading & Writing === //
+
+/// The maximum number of tasks encoding and writing regions at once. Each task only holds a single
+/// encoded region in memory at a time.
+pub const MAX_SAVE_WORKERS: usize = 8;
+
+/// The number of regions below which a save is written entirely on the calling thread since
+/// scheduling tasks would cost more than it saves.
+const PARALLEL_SAVE_THRESHOLD: usize = 16;
+
+/// The extension of the directories into which saves are written before being moved into place.
+const STAGING_EXTENSION: &str = "tmp";
+
+/// Region files of an earlier backup which a new backup carries over unchanged.
+#[derive(Debug, Clone)]
+pub struct ReusedRegions {
+    /// The directory of the earlier backup.
+    pub dir: PathBuf,
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// Writes a new backup into the slot directory, returning it along with its manifest. The regions
+/// arrive in `batches` so that the caller can copy them out of the world as the writer catches up
+/// rather than all at once. `playtime` and the world `seed` are recorded in the backup's metadata
+/// along with the time of the save.
+pub fn write_save(
+    slot_dir: &Path,
+    kind: SaveKind,
+    batches: impl IntoIterator<Item = Vec<RegionData>>,
+    reused: Option<&ReusedRegions>,
+    playtime: f32,
+    seed: u64,
+    thumbnail: Option<&SaveThumbnail>,
+) -> io::Result<(SaveBackup, SaveManifest)> {
+    let timestamp = unix_millis();
+
+    remove_staging_dirs(slot_dir)?;
+
+    // Saves are written into a staging directory which is only renamed to its final name once
+    // complete. Renames are atomic so a crash mid-save never leaves a partial backup behind.
+    let dir = slot_dir.join(format!("{}-{timestamp}", kind.prefix()));
+    let staging = dir.with_extension(STAGING_EXTENSION);
+    fs::create_dir_all(&staging)?;
+
+    let mut manifest = SaveManifest::default();
+    for batch in batches {
+        let sums = write_regions(&staging, &batch)?;
+        manifest.regions.extend(
+            batch
+                .iter()
+                .zip(sums)
+                .map(|(region, checksum)| ManifestEntry {
+                    world: region.world,
+                    name: region.file_name(),
+                    checksum,
+                }),
+        );
+    }
+
+    if let Some(reused) = reused {
+        link_regions(&staging, reused)?;
+        manifest.regions.extend_from_slice(&reused.entries);
+    }
+
+    let metadata = SaveMetadata {
+        timestamp,
+        playtime,
+        seed: Some(seed),
+    };
+
+    fs::write(staging.join(MANIFEST_NAME), manifest.encode())?;
+    fs::write(staging.join(METADATA_NAME), metadata.encode())?;
+    if let Some(thumbnail) = thumbnail {
+        fs::write(staging.join(THUMBNAIL_NAME), thumbnail.encode())?;
+    }
+    fs::rename(&staging, &dir)?;
+
+    let backup = SaveBackup {
+        kind,
+        timestamp,
+        path: dir,
+    };
+
+    Ok((backup, manifest))
+}
+
+/// Records the most recent save of a slot in the index of its save root.
+fn update_index(root: &Path, slot: &str, latest: SaveMetadata) -> io::Result<()> {
+    let mut index = SaveIndex::load(root)?;
+    index.record(slot, latest);
+    index.write(root)
+}
+
+/// Deletes the staging directories of saves which were interrupted before they could complete.
+fn remove_staging_dirs(slot_dir: &Path) -> io::Result<()> {
+    let entries = match fs::read_dir(slot_dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() && path.extension().is_some_and(|ext| ext == STAGING_EXTENSION) {
+            log::info!("Removing interrupted save {}", path.display());
+            fs::remove_dir_all(&path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Encodes and writes every region into `dir` on the compute task pool, returning the
+/// checksum of each region in the same order as `regions`.
+fn write_regions(dir: &Path, regions: &[RegionData]) -> io::Result<Vec<u64>> {
+    par_map(
+        regions,
+        MAX_SAVE_WORKERS,
+        PARALLEL_SAVE_THRESHOLD,
+        |region| {
+            let bytes = region.encode();
+            fs::write(dir.join(region.file_name()), &bytes)?;
+            Ok(checksum(&bytes))
+        },
+    )
+    .into_iter()
+    .collect()
+}
+
+/// Carries the reused region files over into `dir`. Backups never modify their files once written
+/// so they're hard-linked where possible rather than copied.
+fn link_regions(dir: &Path, reused: &ReusedRegions) -> io::Result<()> {
+    for entry in &reused.entries {
+        let from = reused.dir.join(&entry.name);
+        let to = dir.join(&entry.name);
+
+        if fs::hard_link(&from, &to).is_err() {
+            fs::copy(&from, &to)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads every region of a save, verifying each against the checksum recorded in its manifest.
+pub fn read_save(dir: &Path) -> io::Result<Vec<RegionData>> {
+    let manifest = SaveManifest::read(dir)?;
+    let mut regions = Vec::with_capacity(manifest.regions.len());
+
+    for entry in &manifest.regions {
+        let bytes = fs::read(dir.join(&entry.name))?;
+        if checksum(&bytes) != entry.checksum {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("region {} failed its checksum", entry.name),
+            ));
+        }
+
+        regions.push(RegionData {
+            world: entry.world,
+            ..RegionData::decode(&bytes)?
+        });
+    }
+
+    Ok(regions)
+}
+
+/// Loads the most recent backup in the slot which passes verification, skipping over corrupted
+/// ones.
+pub fn read_latest_valid_save(slot_dir: &Path) -> Option<(SaveBackup, Vec<RegionData>)> {
+    let backups = match SaveBackup::list(slot_dir) {
+        Ok(backups) => backups,
+        Err(err) => {
+            log::error!("Failed to list backups in {}: {err}", slot_dir.display());
+            return None;
+        }
+    };
+
+    for backup in backups {
+        match read_save(&backup.path) {
+            Ok(regions) => return Some((backup, regions)),
+            Err(err) => log::warn!(
+                "Backup {} is corrupted ({err}); falling back to an older one",
+                backup.path.display()
+            ),
+        }
+    }
+
+    None
+}
+
+// === Systems === //
+
+/// Marks a world whose tiles are saved. The id tells the worlds apart in saves and over the network
+/// so every saved world must have a different one. The main world has id `0`, which is also the
+/// world that saves from before there were several worlds belong to.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Component)]
+pub struct SavedWorld(pub u32);
+
+impl SavedWorld {
+    pub const MAIN: Self = Self(0);
+}
+
+#[derive(Debug, Clone)]
+pub enum RestoreRequest {
+    Latest,
+    Backup(SaveBackup),
+}
+
+#[derive(Debug, Default, Resource)]
+pub struct SaveState {
+    pub pending_save: Option<SaveKind>,
+    pub pending_restore: Option<RestoreRequest>,
+    restored_from: Option<SaveBackup>,
+    browser: Option<SaveBrowser>,
+
+    /// The playtime of the restored save minus the time elapsed in this session when it was
+    /// restored.
+    playtime_offset: f32,
+}
+
+impl SaveState {
+    /// The backup which was most recently restored into the world, if any.
+    pub fn restored_from(&self) -> Option<&SaveBackup> {
+        self.restored_from.as_ref()
+    }
+
+    /// The total time spent playing the current game, including the sessions before the save it
+    /// was restored from.
+    pub fn playtime(&self, stats: &GameStats) -> f32 {
+        stats.elapsed() + self.playtime_offset
+    }
+
+    /// Starts counting playtime from zero for a game which wasn't restored from a save.
+    pub fn reset_playtime(&mut self, stats: &GameStats) {
+        self.playtime_offset = -stats.elapsed();
+    }
+}
+
+/// The number of regions copied out of the world at a time while saving.
+const SAVE_BATCH_SIZE: usize = 256;
+
+/// The number of copied batches which may wait for the writer at once. Together with the batch
+/// size, this bounds the memory a save holds on to no matter how much of the world changed.
+const MAX_BATCHES_IN_FLIGHT: usize = 2;
+
+/// Schedules autosaves and writes every save in a task on the [`IoTaskPool`] so that large worlds
+/// don't stall the game. Only one save is written at a time; saves requested in the meantime stay
+/// pending until the current one finishes.
+///
+/// Only the chunks which changed since the last save or restore are written again. The files of
+/// every other chunk are carried over from the backup they were last saved to.
+#[derive(Debug, Default, Resource)]
+pub struct AutosaveManager {
+    last_autosave: Option<Instant>,
+    job: Option<SaveJob>,
+
+    /// The backup holding the current contents of every chunk which isn't
+    /// [dirty](ChunkDirtyKind::Save), if it's known.
+    base: Option<SaveBase>,
+}
+
+#[derive(Debug)]
+struct SaveJob {
+    started: Instant,
+
+    /// The regions which still have to be copied out of the world, in reverse order.
+    pending: Vec<PendingRegion>,
+
+    /// A batch which was copied but didn't fit into the channel yet.
+    ready: Option<Vec<RegionData>>,
+
+    /// Hands batches over to the writer. Dropped once every batch has been sent so that the writer
+    /// knows to finish the save.
+    batches: Option<SyncSender<Vec<RegionData>>>,
+
+    /// Resolves to the new base if the save succeeded.
+    task: Task<Option<SaveBase>>,
+}
+
+#[derive(Debug)]
+struct PendingRegion {
+    key: RegionKey,
+    layer: Obj<TileWorld>,
+}
+
+#[derive(Debug)]
+struct SaveBase {
+    path: PathBuf,
+    regions: FxHashMap<String, ManifestEntry>,
+}
+
+impl SaveBase {
+    fn new(path: PathBuf, manifest: SaveManifest) -> Self {
+        Self {
+            path,
+            regions: manifest
+                .regions
+                .into_iter()
+                .map(|entry| (entry.name.clone(), entry))
+                .collect(),
+        }
+    }
+}
+
+impl AutosaveManager {
+    pub fn is_saving(&self) -> bool {
+        self.job.is_some()
+    }
+
+    /// Writes the chunks of every saved world in a background task. The chunks are copied out of
+    /// the world in batches over the next few ticks as the task catches up, so this and
+    /// [`poll`](Self::poll) must be called within a [`RandomAccess`] providing mutable access to
+    /// the chunks of the saved worlds.
+    fn start(
+        &mut self,
+        config: &SaveConfig,
+        kind: SaveKind,
+        query: &Query<(&ObjOwner<TileWorld>, &SavedWorld)>,
+        playtime: f32,
+        seed: u64,
+        thumbnail: Option<SaveThumbnail>,
+    ) {
+        debug_assert!(self.job.is_none());
+
+        let root = config.root.clone();
+        let slot = config.slot.clone();
+        let slot_dir = config.slot_dir();
+        let keep = config.keep_count(kind);
+
+        // Sort out the chunks which can be carried over from the base.
+        let mut pending = Vec::new();
+        let mut reused = Vec::new();
+
+        for (&ObjOwner(world), &SavedWorld(id)) in query.iter() {
+            for (layer_idx, layer) in world_layers(world).into_iter().enumerate() {
+                for (pos, chunk) in layer.chunks() {
+                    let key = (id, layer_idx as u32, pos);
+                    let entry = self
+                        .base
+                        .as_ref()
+                        .and_then(|base| base.regions.get(&region_file_name(key)));
+
+                    match entry {
+                        Some(entry) if !chunk.is_dirty(ChunkDirtyKind::Save) => {
+                            reused.push(entry.clone());
+                        }
+                        _ => pending.push(PendingRegion { key, layer }),
+                    }
+                }
+            }
+        }
+
+        let reused = self.base.as_ref().map(|base| ReusedRegions {
+            dir: base.path.clone(),
+            entries: reused,
+        });
+
+        let (sender, receiver) = mpsc::sync_channel(MAX_BATCHES_IN_FLIGHT);
+
+        let write = move || {
+            let start = Instant::now();
+            let saved = match write_save(
+                &slot_dir,
+                kind,
+                receiver,
+                reused.as_ref(),
+                playtime,
+                seed,
+                thumbnail.as_ref(),
+            ) {
+                Ok((backup, manifest)) => {
+                    log::info!(
+                        "Saved {} region(s), {} of them unchanged, to {} in {:?}",
+                        manifest.regions.len(),
+                        reused.as_ref().map_or(0, |reused| reused.entries.len()),
+                        backup.path.display(),
+                        start.elapsed(),
+                    );
+
+                    let latest = SaveMetadata {
+                        timestamp: backup.timestamp,
+                        playtime,
+                        seed: Some(seed),
+                    };
+                    if let Err(err) = update_index(&root, &slot, latest) {
+                        log::error!("Failed to update the save index: {err}");
+                    }
+
+                    Some(SaveBase::new(backup.path, manifest))
+                }
+                Err(err) => {
+                    log::error!("Failed to save the world: {err}");
+                    None
+                }
+            };
+
+            if let Err(err) = rotate_backups(&slot_dir, kind, keep) {
+                log::error!("Failed to rotate backups: {err}");
+            }
+
+            // Slots which keep no backups of this kind have just rotated the new one away.
+            saved.filter(|base| base.path.exists())
+        };
+
+        let task = IoTaskPool::get_or_init(TaskPool::default).spawn(async move {
+            panic::catch_unwind(AssertUnwindSafe(write)).unwrap_or_else(|_| {
+                log::error!("Save task panicked");
+                None
+            })
+        });
+
+        pending.reverse();
+        let mut job = SaveJob {
+            started: Instant::now(),
+            pending,
+            ready: None,
+            batches: Some(sender),
+            task,
+        };
+        job.feed(false);
+
+        self.job = Some(job);
+    }
+
+    /// Reaps the current save if it has finished, or waits for it to finish if `block` is set.
+    fn poll(&mut self, block: bool) {
+        let Some(mut job) = self.job.take() else {
+            return;
+        };
+
+        job.feed(block);
+
+        if !block && !job.task.is_finished() {
+            self.job = Some(job);
+            return;
+        }
+
+        // The regions whose dirty flags were taken by a failed save weren't written anywhere so
+        // the next save has to write everything.
+        self.base = block_on(job.task);
+    }
+}
+
+impl SaveJob {
+    /// Copies regions out of the world and hands them to the writer for as long as it has room for
+    /// them, or until every region has been handed over if `block` is set.
+    fn feed(&mut self, block: bool) {
+        loop {
+            let Some(batches) = &self.batches else {
+                return;
+            };
+
+            let batch = match self.ready.take() {
+                Some(batch) => batch,
+                None if self.pending.is_empty() => {
+                    // Hanging up lets the writer finish the save.
+                    self.batches = None;
+                    return;
+                }
+                None => {
+                    let start = self.pending.len().saturating_sub(SAVE_BATCH_SIZE);
+                    self.pending
+                        .drain(start..)
+                        .filter_map(copy_region)
+                        .collect()
+                }
+            };
+
+            let sent = if block {
+                batches.send(batch).map_err(|_| ())
+            } else {
+                match batches.try_send(batch) {
+                    Ok(()) => Ok(()),
+                    Err(TrySendError::Full(batch)) => {
+                        self.ready = Some(batch);
+                        return;
+                    }
+                    Err(TrySendError::Disconnected(_)) => Err(()),
+                }
+            };
+
+            // The writer gives up on the first error.
+            if sent.is_err() {
+                self.pending.clear();
+                self.batches = None;
+                return;
+            }
+        }
+    }
+}
+
+/// Copies a region out of the world for saving. The chunk counts as saved from here on, so any
+/// later change makes it dirty again.
+fn copy_region(PendingRegion { key, layer }: PendingRegion) -> Option<RegionData> {
+    // The chunk may have been unloaded since the save started.
+    if !layer.is_alive() {
+        return None;
+    }
+
+    let (world, layer_idx, pos) = key;
+    let mut chunk = layer.chunk(pos)?;
+    chunk.take_dirty(ChunkDirtyKind::Save);
+
+    Some(RegionData {
+        world,
+        layer: layer_idx,
+        pos,
+        tiles: Box::new(*chunk.raw_tiles()),
+        explored: Some(Box::new(*chunk.explored_bits())),
+    })
+}
+
+#[derive(Debug)]
+struct SaveBrowser {
+    backups: Vec<SaveBackup>,
+    selected: usize,
+}
+
+pub fn sys_request_initial_restore(mut state: ResMut<SaveState>) {
+    state.pending_restore = Some(RestoreRequest::Latest);
+}
+
+pub fn sys_request_exit_autosave(mut state: ResMut<SaveState>) {
+    state.pending_save = Some(SaveKind::Auto);
+}
+
+pub fn sys_handle_save_input(
+    mut state: ResMut<SaveState>,
+    mut autosave: ResMut<AutosaveManager>,
+    config: Res<SaveConfig>,
+) {
+    // Handle autosaves
+    let last_autosave = *autosave.last_autosave.get_or_insert_with(Instant::now);
+    if last_autosave.elapsed() >= config.autosave_interval {
+        autosave.last_autosave = Some(Instant::now());
+        state.pending_save.get_or_insert(SaveKind::Auto);
+    }
+
+    // Handle manual saves
+    if is_key_pressed(KeyCode::F5) {
+        state.pending_save = Some(SaveKind::Manual);
+    }
+
+    // Handle the restore browser
+    if is_key_pressed(KeyCode::F9) {
+        state.browser = match state.browser {
+            Some(_) => None,
+            None => match SaveBackup::list(&config.slot_dir()) {
+                Ok(backups) => Some(SaveBrowser {
+                    backups,
+                    selected: 0,
+                }),
+                Err(err) => {
+                    log::error!("Failed to list backups: {err}");
+                    None
+                }
+            },
+        };
+    }
+
+    let Some(browser) = &mut state.browser else {
+        return;
+    };
+
+    if is_key_pressed(KeyCode::Up) {
+        browser.selected = browser.selected.saturating_sub(1);
+    }
+
+    if is_key_pressed(KeyCode::Down) {
+        browser.selected = (browser.selected + 1).min(browser.backups.len().saturating_sub(1));
+    }
+
+    if is_key_pressed(KeyCode::Enter) {
+        if let Some(backup) = browser.backups.get(browser.selected).cloned() {
+            state.pending_restore = Some(RestoreRequest::Backup(backup));
+            state.browser = None;
+        }
+    }
+}
+
+/// The saved worlds and the resources needed to save them.
+#[derive(SystemParam)]
+pub struct SaveContext<'w, 's> {
+    pub query: Query<'w, 's, (&'static ObjOwner<TileWorld>, &'static SavedWorld)>,
+    pub state: ResMut<'w, SaveState>,
+    pub autosave: ResMut<'w, AutosaveManager>,
+    pub config: Res<'w, SaveConfig>,
+    pub stats: Res<'w, GameStats>,
+    pub camera: Res<'w, ActiveCamera>,
+    pub rng: ResMut<'w, Rng>,
+}
+
+impl SaveContext<'_, '_> {
+    /// Starts writing the pending save unless another one is still being written. Must be called
+    /// within a [`RandomAccess`] providing mutable access to the saved worlds' chunks.
+    fn start_pending_save(&mut self) {
+        if self.autosave.is_saving() {
+            return;
+        }
+
+        let Some(kind) = self.state.pending_save.take() else {
+            return;
+        };
+
+        self.autosave.start(
+            &self.config,
+            kind,
+            &self.query,
+            self.state.playtime(&self.stats),
+            self.rng.seed(),
+            snapshot_thumbnail(&self.query, &self.camera),
+        );
+    }
+}
+
+pub fn sys_process_saves(
+    mut rand: RandomAccess<(
+        &mut TileWorld,
+        &mut TileChunk,
+        &mut TileGenerator,
+        &TileLayers,
+        &MaterialRegistry,
+        &TileEntityDescriptor,
+        SendsEvent<TileEntityCreated>,
+        SendsEvent<TileChanged>,
+        SendsEvent<WorldCreatedChunk>,
+        ThumbnailAccess,
+    )>,
+    mut cx: SaveContext,
+    mut edits: ResMut<TileEditLog>,
+) {
+    rand.provide(|| {
+        let slot_dir = cx.config.slot_dir();
+
+        cx.autosave.poll(false);
+        cx.start_pending_save();
+
+        if let Some(request) = cx.state.pending_restore.take() {
+            let loaded = match request {
+                RestoreRequest::Latest => read_latest_valid_save(&slot_dir),
+                RestoreRequest::Backup(backup) => match read_save(&backup.path) {
+                    Ok(regions) => Some((backup, regions)),
+                    Err(err) => {
+                        log::warn!(
+                            "Backup {} is corrupted ({err}); falling back to the latest valid one",
+                            backup.path.display()
+                        );
+                        read_latest_valid_save(&slot_dir)
+                    }
+                },
+            };
+
+            let Some((backup, regions)) = loaded else {
+                log::info!("No valid save to restore in {}", slot_dir.display());
+                return;
+            };
+
+            // Chunks restored while a save is still copying them would end up in the wrong backup.
+            cx.autosave.poll(true);
+
+            // Saves of another world seed must also generate the regions they're missing from it.
+            let metadata = backup.metadata();
+            let reseed = metadata.seed.filter(|&seed| seed != cx.rng.seed());
+
+            for (&ObjOwner(world), &SavedWorld(id)) in cx.query.iter() {
+                let regions = regions
+                    .iter()
+                    .filter(|region| region.world == id)
+                    .collect::<Vec<_>>();
+
+                if let Some(seed) = reseed {
+                    reseed_world(world, seed, &regions);
+                }
+
+                apply_regions(world, regions.iter().copied());
+                mark_saved(world, &regions);
+            }
+
+            // The restored chunks match the backup's files so the next save can carry them over.
+            cx.autosave.base = SaveManifest::read(&backup.path)
+                .ok()
+                .map(|manifest| SaveBase::new(backup.path.clone(), manifest));
+
+            if let Some(seed) = reseed {
+                log::info!("Switched to the world seed {seed} of the save");
+                cx.rng.reseed(seed);
+            }
+
+            // The restored tiles no longer match the recorded edits.
+            edits.clear();
+
+            log::info!("Restored {}", backup.path.display());
+            cx.state.playtime_offset = metadata.playtime - cx.stats.elapsed();
+            cx.state.restored_from = Some(backup);
+        }
+    });
+}
+
+/// Waits for the current save to finish and then writes any pending save before returning.
+pub fn sys_flush_saves(
+    mut rand: RandomAccess<(
+        &TileWorld,
+        &mut TileChunk,
+        &TileLayers,
+        &MaterialRegistry,
+        ThumbnailAccess,
+    )>,
+    mut cx: SaveContext,
+) {
+    rand.provide(|| {
+        cx.autosave.poll(true);
+        cx.start_pending_save();
+        cx.autosave.poll(true);
+    });
+}
+
+/// Captures the area around the active camera if it shows a saved world.
+fn snapshot_thumbnail(
+    query: &Query<(&ObjOwner<TileWorld>, &SavedWorld)>,
+    camera: &ActiveCamera,
+) -> Option<SaveThumbnail> {
+    let active = camera.camera?;
+    let (&ObjOwner(world), _) = query.get(active.entity()).ok()?;
+    let center = active.transform().translation / world.config().size;
+
+    Some(capture_thumbnail(world, center, SaveThumbnail::SIZE))
+}
+
+pub(crate) fn world_layers(world: Obj<TileWorld>) -> Vec<Obj<TileWorld>> {
+    match world.entity().try_get::<TileLayers>() {
+        Some(layers) => layers.iter().collect(),
+        None => vec![world],
+    }
+}
+
+pub(crate) fn collect_regions(id: u32, world: Obj<TileWorld>) -> Vec<RegionData> {
+    let mut regions = Vec::new();
+
+    for (layer, data) in world_layers(world).into_iter().enumerate() {
+        for (pos, chunk) in data.chunks() {
+            regions.push(RegionData {
+                world: id,
+                layer: layer as u32,
+                pos,
+                tiles: Box::new(*chunk.raw_tiles()),
+                explored: Some(Box::new(*chunk.explored_bits())),
+            });
+        }
+    }
+
+    regions
+}
+
+/// Reseeds the generators of every layer of the world and regenerates the chunks which were
+/// generated from the old seed, save for those about to be overwritten by `regions`.
+fn reseed_world(world: Obj<TileWorld>, seed: u64, regions: &[&RegionData]) {
+    let saved = regions
+        .iter()
+        .map(|region| (region.layer, region.pos))
+        .collect::<FxHashSet<_>>();
+
+    for (layer_idx, layer) in world_layers(world).into_iter().enumerate() {
+        let Some(mut generator) = layer.entity().try_get::<TileGenerator>() else {
+            continue;
+        };
+
+        generator.reseed(seed);
+
+        for (pos, mut chunk) in layer.chunks() {
+            if chunk.is_generated() && !saved.contains(&(layer_idx as u32, pos)) {
+                generator.regenerate(&mut chunk);
+            }
+        }
+    }
+}
+
+/// Marks the chunks which were just restored from `regions` as matching their save.
+fn mark_saved(world: Obj<TileWorld>, regions: &[&RegionData]) {
+    let layers = world_layers(world);
+
+    for region in regions {
+        let chunk = layers
+            .get(region.layer as usize)
+            .and_then(|layer| layer.chunk(region.pos));
+
+        if let Some(mut chunk) = chunk {
+            chunk.take_dirty(ChunkDirtyKind::Save);
+        }
+    }
+}
+
+pub(crate) fn apply_regions<'a>(
+    world: Obj<TileWorld>,
+    regions: impl IntoIterator<Item = &'a RegionData>,
+) {
+    let layers = world_layers(world);
+
+    for region in regions {
+        let Some(&layer) = layers.get(region.layer as usize) else {
+            log::warn!("Save references unknown layer {}", region.layer);
+            continue;
+        };
+
+        let mut chunk = layer.chunk_or_create(region.pos);
+        *chunk.raw_tiles_mut() = *region.tiles;
+        if let Some(explored) = &region.explored {
+            chunk.set_explored_bits(**explored);
+        }
+        chunk.sync_tile_entities();
+        chunk.mark_generated();
+    }
+}
+
+pub fn sys_render_save_indicator(autosave: Res<AutosaveManager>) {
+    let Some(job) = &autosave.job else {
+        return;
+    };
+
+    let dots = 1 + (job.started.elapsed().as_millis() / 300 % 3) as usize;
+    draw_text(
+        &format!("Saving{}", ".".repeat(dots)),
+        15.,
+        screen_height() - 15.,
+        24.,
+        WHITE,
+    );
+}
+
+pub fn sys_render_save_browser(state: Res<SaveState>) {
+    let Some(browser) = &state.browser else {
+        return;
+    };
+
+    draw_text("Backups (enter to restore)", 15., 80., 24., WHITE);
+
+    if browser.backups.is_empty() {
+        draw_text("No backups", 15., 105., 20., WHITE);
+    }
+
+    for (i, backup) in browser.backups.iter().enumerate() {
+        let color = if i == browser.selected { YELLOW } else { WHITE };
+        draw_text(&backup.label(), 15., 105. + i as f32 * 20., 20., color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    const AREA: usize = TileLayerConfig::CHUNK_AREA as usize;
+
+    fn sample_region(explored: bool) -> RegionData {
+        let mut tiles = Box::new([0; AREA]);
+        for (i, tile) in tiles.iter_mut().enumerate() {
+            // A few long runs with some single tiles mixed in, like a real chunk.
+            *tile = match i {
+                0..=99 => 0,
+                100..=149 => 3,
+                _ if i % 7 == 0 => 9,
+                _ => 1,
+            };
+        }
+
+        RegionData {
+            world: 0,
+            layer: 2,
+            pos: IVec2::new(-3, 7),
+            tiles,
+            explored: explored.then(|| Box::new([0xDEAD_BEEF, 0, u64::MAX, 1 << 63])),
+        }
+    }
+
+    fn header(version: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(REGION_MAGIC);
+        bytes.extend_from_slice(&version.to_le_bytes());
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        bytes.extend_from_slice(&(-3i32).to_le_bytes());
+        bytes.extend_from_slice(&7i32.to_le_bytes());
+        bytes
+    }
+
+    fn assert_same_region(a: &RegionData, b: &RegionData) {
+        assert_eq!(a.world, b.world);
+        assert_eq!(a.layer, b.layer);
+        assert_eq!(a.pos, b.pos);
+        assert_eq!(a.tiles[..], b.tiles[..]);
+        assert_eq!(a.explored, b.explored);
+    }
+
+    fn decode_err(bytes: &[u8]) -> String {
+        RegionData::decode(bytes).unwrap_err().to_string()
+    }
+
+    /// A fresh directory under the system's temporary directory, unique to this test and process.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("bevy-demo-save-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn raw_region_decodes() {
+        let region = sample_region(false);
+        let mut bytes = header(REGION_VERSION_RAW);
+        for tile in region.tiles.iter() {
+            bytes.extend_from_slice(&tile.to_le_bytes());
+        }
+
+        assert_same_region(&RegionData::decode(&bytes).unwrap(), &region);
+    }
+
+    #[test]
+    fn rle_region_round_trips() {
+        let region = sample_region(false);
+        let bytes = region.encode();
+        assert_eq!(
+            u32::from_le_bytes(bytes[4..8].try_into().unwrap()),
+            REGION_VERSION_RLE
+        );
+        assert!(bytes.len() < REGION_HEADER_LEN + AREA * 2);
+
+        assert_same_region(&RegionData::decode(&bytes).unwrap(), &region);
+    }
+
+    #[test]
+    fn explored_region_round_trips() {
+        let region = sample_region(true);
+        let bytes = region.encode();
+        assert_eq!(
+            u32::from_le_bytes(bytes[4..8].try_into().unwrap()),
+            REGION_VERSION_RLE_EXPLORED
+        );
+
+        assert_same_region(&RegionData::decode(&bytes).unwrap(), &region);
+    }
+
+    #[test]
+    fn malformed_regions_are_rejected() {
+        let bytes = sample_region(false).encode();
+
+        assert!(decode_err(&bytes[..REGION_HEADER_LEN - 1]).contains("truncated"));
+        assert!(decode_err(&bytes[..bytes.len() - 2]).contains("truncated run"));
+        assert!(decode_err(&bytes[..bytes.len() - 4]).contains("too few tiles"));
+
+        let mut overlong = bytes.clone();
+        overlong.extend_from_slice(&1u16.to_le_bytes());
+        overlong.extend_from_slice(&5u16.to_le_bytes());
+        assert!(decode_err(&overlong).contains("too many tiles"));
+
+        let mut single = header(REGION_VERSION_RLE);
+        single.extend_from_slice(&(AREA as u16 + 1).to_le_bytes());
+        single.extend_from_slice(&5u16.to_le_bytes());
+        assert!(decode_err(&single).contains("too many tiles"));
+
+        assert!(decode_err(&header(REGION_VERSION_RLE_EXPLORED)).contains("explored bits"));
+        assert!(decode_err(&header(REGION_VERSION_RAW)).contains("wrong size"));
+        assert!(decode_err(&header(4)).contains("unsupported version"));
+
+        let mut bad_magic = bytes;
+        bad_magic[0] = b'X';
+        assert!(decode_err(&bad_magic).contains("magic"));
+    }
+
+    #[test]
+    fn manifest_round_trips() {
+        let manifest = SaveManifest {
+            regions: vec![
+                ManifestEntry {
+                    world: 0,
+                    name: "region_0_0_0_0.bin".to_string(),
+                    checksum: 0,
+                },
+                ManifestEntry {
+                    world: 1,
+                    name: "region_1_1_-2_5.bin".to_string(),
+                    checksum: u64::MAX,
+                },
+            ],
+        };
+
+        let decoded = SaveManifest::decode(&manifest.encode()).unwrap();
+        assert_eq!(decoded.regions, manifest.regions);
+
+        // Manifests from before there were several worlds only list regions of the main world.
+        let legacy = SaveManifest::decode("region region_0_0_0.bin 000000000000002a\n").unwrap();
+        assert_eq!(
+            legacy.regions,
+            [ManifestEntry {
+                world: 0,
+                name: "region_0_0_0.bin".to_string(),
+                checksum: 42,
+            }]
+        );
+
+        assert!(SaveManifest::decode("region foo.bin").is_err());
+        assert!(SaveManifest::decode("region foo.bin nothex").is_err());
+        assert!(SaveManifest::decode("region foo.bin 2a world").is_err());
+    }
+
+    #[test]
+    fn index_round_trips() {
+        let mut index = SaveIndex::default();
+        index.record(
+            "game-1",
+            SaveMetadata {
+                timestamp: 100,
+                playtime: 12.5,
+                seed: None,
+            },
+        );
+        index.record(
+            "my world",
+            SaveMetadata {
+                timestamp: 200,
+                playtime: 0.25,
+                seed: None,
+            },
+        );
+
+        let decoded = SaveIndex::decode(&index.encode()).unwrap();
+        let slots = decoded
+            .slots
+            .iter()
+            .map(|slot| {
+                (
+                    slot.name.as_str(),
+                    slot.latest.timestamp,
+                    slot.latest.playtime,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(slots, [("my world", 200, 0.25), ("game-1", 100, 12.5)]);
+        assert_eq!(decoded.fresh_slot_name(), "game-2");
+
+        assert!(SaveIndex::decode("slot soon 1.0 game-1").is_err());
+    }
+
+    #[test]
+    fn thumbnail_round_trips() {
+        let thumbnail = SaveThumbnail {
+            width: 3,
+            height: 2,
+            pixels: (0..6u8).map(|i| [i, i * 2, i * 3, 255]).collect(),
+        };
+
+        let bytes = thumbnail.encode();
+        let decoded = SaveThumbnail::decode(&bytes).unwrap();
+        assert_eq!((decoded.width, decoded.height), (3, 2));
+        assert_eq!(decoded.pixels, thumbnail.pixels);
+
+        assert!(SaveThumbnail::decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(SaveThumbnail::decode(&bytes[..THUMBNAIL_HEADER_LEN - 1]).is_err());
+    }
+
+    #[test]
+    fn corrupted_backup_falls_back_to_previous() {
+        let slot_dir = temp_dir("fallback");
+
+        // The world a region belongs to only survives through the manifest.
+        let older = RegionData {
+            world: 1,
+            ..sample_region(true)
+        };
+        let mut newer = older.clone();
+        newer.tiles.fill(4);
+
+        let (first, _) = write_save(
+            &slot_dir,
+            SaveKind::Auto,
+            [vec![older.clone()]],
+            None,
+            1.,
+            7,
+            None,
+        )
+        .unwrap();
+        // Backups are named after their timestamp in milliseconds.
+        thread::sleep(Duration::from_millis(5));
+        let (second, _) = write_save(
+            &slot_dir,
+            SaveKind::Auto,
+            [vec![newer.clone()]],
+            None,
+            2.,
+            7,
+            None,
+        )
+        .unwrap();
+
+        let (latest, regions) = read_latest_valid_save(&slot_dir).unwrap();
+        assert_eq!(latest.path, second.path);
+        assert_eq!(latest.metadata().seed, Some(7));
+        assert_same_region(&regions[0], &newer);
+
+        let region_path = second.path.join(newer.file_name());
+        let mut bytes = fs::read(&region_path).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        fs::write(&region_path, bytes).unwrap();
+
+        let (latest, regions) = read_latest_valid_save(&slot_dir).unwrap();
+        assert_eq!(latest.path, first.path);
+        assert_same_region(&regions[0], &older);
+
+        fs::remove_dir_all(&slot_dir).unwrap();
+    }
+
+    #[test]
+    fn unchanged_regions_are_carried_over() {
+        let slot_dir = temp_dir("carry-over");
+
+        let unchanged = sample_region(true);
+        let mut changed = RegionData {
+            pos: IVec2::new(5, 5),
+            ..sample_region(false)
+        };
+
+        let (first, manifest) = write_save(
+            &slot_dir,
+            SaveKind::Auto,
+            [vec![unchanged.clone()], vec![changed.clone()]],
+            None,
+            1.,
+            7,
+            None,
+        )
+        .unwrap();
+        assert_eq!(manifest.regions.len(), 2);
+
+        changed.tiles.fill(4);
+        let reused = ReusedRegions {
+            dir: first.path.clone(),
+            entries: vec![manifest.regions[0].clone()],
+        };
+
+        thread::sleep(Duration::from_millis(5));
+        let (second, manifest) = write_save(
+            &slot_dir,
+            SaveKind::Manual,
+            [vec![changed.clone()]],
+            Some(&reused),
+            2.,
+            7,
+            None,
+        )
+        .unwrap();
+        assert_eq!(manifest.regions.len(), 2);
+
+        // The carried over file outlives the backup it came from.
+        fs::remove_dir_all(&first.path).unwrap();
+
+        let mut regions = read_save(&second.path).unwrap();
+        regions.sort_by_key(|region| region.pos.x);
+        assert_same_region(&regions[0], &unchanged);
+        assert_same_region(&regions[1], &changed);
+
+        fs::remove_dir_all(&slot_dir).unwrap();
+    }
+}
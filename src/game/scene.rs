@@ -0,0 +1,72 @@
+use bevy_app::{App, Update};
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    schedule::{common_conditions::in_state, IntoSystemSetConfigs, OnEnter, States, SystemSet},
+    system::{Commands, Query},
+};
+
+use crate::{PreFrame, Render};
+
+// === GameScene === //
+
+#[derive(Debug, Copy, Clone, Default, Hash, Eq, PartialEq, States)]
+pub enum GameScene {
+    #[default]
+    MainMenu,
+    /// Headless apps start here since they have no way of leaving the main menu.
+    InGame,
+    /// Overlays a running game. Entities scoped to [`GameScene::InGame`] are kept alive while
+    /// paused but the simulation stops.
+    Paused,
+    /// Overlays a game which one of the [game rules](super::rules::GameRules) ended with its
+    /// outcome. Like [`GameScene::Paused`], the game's entities are kept alive but the simulation
+    /// stops.
+    GameOver,
+}
+
+impl GameScene {
+    pub const VARIANTS: [Self; 4] = [Self::MainMenu, Self::InGame, Self::Paused, Self::GameOver];
+
+    /// Whether entities [scoped](SceneScoped) to `scope` survive while this scene is active.
+    pub fn keeps_alive(self, scope: GameScene) -> bool {
+        self == scope || (matches!(self, Self::Paused | Self::GameOver) && scope == Self::InGame)
+    }
+}
+
+/// Systems in this set only run while the given scene is active. The set is configured in the
+/// `PreFrame`, `Update`, and `Render` schedules.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, SystemSet)]
+pub struct SceneSet(pub GameScene);
+
+/// Despawns the entity once a scene which doesn't [keep it alive](GameScene::keeps_alive) is
+/// entered.
+#[derive(Debug, Copy, Clone, Component)]
+pub struct SceneScoped(pub GameScene);
+
+// === Plugin === //
+
+pub fn plugin(app: &mut App) {
+    app.init_state::<GameScene>();
+
+    for scene in GameScene::VARIANTS {
+        app.configure_sets(PreFrame, SceneSet(scene).run_if(in_state(scene)));
+        app.configure_sets(Update, SceneSet(scene).run_if(in_state(scene)));
+        app.configure_sets(Render, SceneSet(scene).run_if(in_state(scene)));
+        app.add_systems(OnEnter(scene), make_scene_teardown_system(scene));
+    }
+}
+
+// === Systems === //
+
+fn make_scene_teardown_system(
+    scene: GameScene,
+) -> impl 'static + Send + Sync + Fn(Query<(Entity, &SceneScoped)>, Commands) {
+    move |query, mut commands| {
+        for (entity, &SceneScoped(scope)) in query.iter() {
+            if !scene.keeps_alive(scope) {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
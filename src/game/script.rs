@@ -0,0 +1,437 @@
+use std::{
+    fs, io,
+    sync::{Arc, Mutex},
+};
+
+use bevy_ecs::{
+    entity::Entity,
+    system::{Query, Res, ResMut, Resource},
+};
+use macroquad::math::{IVec2, Vec2};
+use rhai::{
+    CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs, ImmutableString, Scope, AST, FLOAT,
+    INT,
+};
+
+use crate::{
+    game::{
+        actor::{
+            death::ActorDied,
+            health::{Health, HealthChangeCause},
+            kinematic::Pos,
+        },
+        event_bus::{BusContext, EventBus, EventBusAccess},
+        prefab::{PrefabInstance, PrefabRegistry},
+        rng::{Rng, RngChannel, RngStream},
+        tile::{
+            breaking::TileBroken,
+            collider::InsideWorld,
+            data::TileWorld,
+            material::{BaseMaterialDescriptor, MaterialRegistry},
+        },
+        time::WorldClock,
+    },
+    util::arena::{Obj, RandomAccess, RandomEntityExt},
+};
+
+// === Scripts === //
+
+/// The script file, relative to the working directory.
+pub const SCRIPTS_PATH: &str = "scripts.rhai";
+
+/// The hook run whenever a tile is mined, with the name of its material as its only argument.
+pub const ON_TILE_BROKEN: &str = "on_tile_broken";
+
+/// The hook run whenever an actor dies.
+pub const ON_ACTOR_DIED: &str = "on_actor_died";
+
+/// The number of operations after which a hook is aborted so that a runaway loop can't freeze the
+/// game.
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// A [Rhai](https://rhai.rs) script loaded from [`SCRIPTS_PATH`] at startup so that tile
+/// interactions and enemy behaviors can be tweaked without recompiling the game.
+///
+/// The script reacts to events by defining hook functions: [`ON_TILE_BROKEN`], [`ON_ACTOR_DIED`],
+/// and any function registered from its top level with `every(ticks, prefab, hook)`, which is run
+/// every given number of ticks for every instance of the named prefab. Hooks are bound to a context
+/// as `this` which exposes the actor involved in the event and its surroundings:
+///
+/// - `this.tile(dx, dy)` and `this.set_tile(dx, dy, material)` read and replace tiles by material
+///   name, relative to the tile containing the actor.
+/// - `this.spawn(prefab)` and `this.spawn(prefab, dx, dy)` spawn a prefab relative to the actor.
+/// - `this.health` is the actor's health, or `()` if it has none, which `this.damage(amount)` and
+///   `this.heal(amount)` change.
+/// - `this.chance(odds)` rolls the game's random number generator so that replays stay in sync.
+///
+/// ```text
+/// every(60, "slime", "regenerate");
+///
+/// fn on_tile_broken(material) {
+///     if material == "gold" && this.chance(0.25) {
+///         this.spawn("slime", 0.0, -8.0);
+///     }
+/// }
+///
+/// fn regenerate() {
+///     if this.health < 5.0 {
+///         this.heal(1.0);
+///     }
+/// }
+/// ```
+///
+/// `print` writes to the log.
+#[derive(Debug, Default, Resource)]
+pub struct Scripts {
+    engine: Engine,
+    ast: AST,
+    timers: Vec<ScriptTimer>,
+}
+
+/// A hook registered through `every`.
+#[derive(Debug, Clone)]
+pub struct ScriptTimer {
+    pub ticks: u32,
+    pub prefab: String,
+    pub hook: String,
+}
+
+impl Scripts {
+    /// Compiles a script and runs its top level to collect the hooks it registers.
+    pub fn compile(text: &str) -> Result<Self, Box<EvalAltResult>> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.on_print(|text| log::info!("Script: {text}"));
+
+        engine
+            .register_type_with_name::<ScriptContext>("Context")
+            .register_fn("tile", ScriptContext::tile)
+            .register_fn("set_tile", ScriptContext::set_tile)
+            .register_fn("spawn", ScriptContext::spawn)
+            .register_fn("spawn", ScriptContext::spawn_at)
+            .register_get("health", ScriptContext::health)
+            .register_fn("damage", ScriptContext::damage)
+            .register_fn("heal", ScriptContext::heal)
+            .register_fn("chance", ScriptContext::chance);
+
+        // Timers can only be registered while the top level runs. Afterwards, `every` reports an
+        // error instead.
+        let timers = Arc::new(Mutex::new(Some(Vec::new())));
+        engine.register_fn("every", {
+            let timers = timers.clone();
+            move |ticks: INT, prefab: &str, hook: &str| -> Result<(), Box<EvalAltResult>> {
+                let Some(timers) = &mut *timers.lock().unwrap() else {
+                    return Err("`every` can only be called from the top level".into());
+                };
+
+                let ticks = u32::try_from(ticks)
+                    .ok()
+                    .filter(|&ticks| ticks > 0)
+                    .ok_or_else(|| format!("invalid tick interval {ticks}"))?;
+
+                timers.push(ScriptTimer {
+                    ticks,
+                    prefab: prefab.to_string(),
+                    hook: hook.to_string(),
+                });
+                Ok(())
+            }
+        });
+
+        let ast = engine.compile(text)?;
+        engine.run_ast(&ast)?;
+        let timers = timers.lock().unwrap().take().unwrap();
+
+        Ok(Self {
+            engine,
+            ast,
+            timers,
+        })
+    }
+
+    pub fn timers(&self) -> &[ScriptTimer] {
+        &self.timers
+    }
+
+    /// Whether the script defines a hook with the given name and number of arguments.
+    pub fn has_hook(&self, name: &str, arity: usize) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|func| func.name == name && func.params.len() == arity)
+    }
+
+    /// Checks that the prefabs and hooks which timers refer to exist so that typos are reported
+    /// when the script is loaded rather than when it first runs.
+    pub fn validate(&self, prefabs: &PrefabRegistry) -> Result<(), String> {
+        for timer in &self.timers {
+            if prefabs.get(&timer.prefab).is_none() {
+                return Err(format!("unknown prefab {:?}", timer.prefab));
+            }
+
+            if !self.has_hook(&timer.hook, 0) {
+                return Err(format!("unknown hook {:?}", timer.hook));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs a hook against `target`, spawning whatever it asked for once it returns. Errors are
+    /// logged rather than propagated so that a broken hook doesn't stop the others.
+    fn run_hook(
+        &self,
+        name: &str,
+        args: impl FuncArgs,
+        target: ScriptTarget,
+        prefabs: &PrefabRegistry,
+        rng: &mut RngStream,
+    ) {
+        let mut this = Dynamic::from(ScriptContext {
+            target,
+            rng: rng.clone(),
+            spawns: Vec::new(),
+        });
+
+        let result = self.engine.call_fn_with_options::<()>(
+            CallFnOptions::new()
+                .eval_ast(false)
+                .bind_this_ptr(&mut this),
+            &mut Scope::new(),
+            &self.ast,
+            name,
+            args,
+        );
+
+        if let Err(err) = result {
+            log::warn!("Script hook {name} failed: {err}");
+        }
+
+        let Some(ctx) = this.try_cast::<ScriptContext>() else {
+            log::warn!("Script hook {name} replaced `this`");
+            return;
+        };
+
+        *rng = ctx.rng;
+
+        for (prefab, offset) in ctx.spawns {
+            if prefabs.get(&prefab).is_some() {
+                prefabs.spawn_prefab(
+                    &prefab,
+                    (Pos(target.pos + offset), InsideWorld(target.world)),
+                );
+            } else {
+                log::warn!("Script hook {name} spawned unknown prefab {prefab:?}");
+            }
+        }
+    }
+}
+
+// === ScriptContext === //
+
+/// What a hook runs against.
+#[derive(Debug, Copy, Clone)]
+struct ScriptTarget {
+    world: Obj<TileWorld>,
+    actor: Option<Entity>,
+    pos: Vec2,
+}
+
+/// The value hooks are bound to as `this`. Tiles and health are changed right away while spawns are
+/// queued up until the hook returns.
+#[derive(Debug, Clone)]
+struct ScriptContext {
+    target: ScriptTarget,
+    rng: RngStream,
+    spawns: Vec<(String, Vec2)>,
+}
+
+impl ScriptContext {
+    fn tile_pos(&self, dx: INT, dy: INT) -> IVec2 {
+        let world = self.target.world;
+        world.config().actor_to_tile(self.target.pos) + IVec2::new(dx as i32, dy as i32)
+    }
+
+    fn health_obj(&self) -> Option<Obj<Health>> {
+        self.target
+            .actor
+            .and_then(|actor| actor.try_get::<Health>())
+    }
+
+    fn tile(&mut self, dx: INT, dy: INT) -> String {
+        let world = self.target.world;
+        let registry = world.entity().get::<MaterialRegistry>();
+        let material = world.tile(self.tile_pos(dx, dy));
+
+        registry
+            .lookup(material)
+            .get::<BaseMaterialDescriptor>()
+            .name
+            .clone()
+    }
+
+    fn set_tile(&mut self, dx: INT, dy: INT, material: &str) -> Result<(), Box<EvalAltResult>> {
+        let world = self.target.world;
+        let registry = world.entity().get::<MaterialRegistry>();
+        let material = registry
+            .lookup_by_name(material)
+            .ok_or_else(|| format!("unknown material {material:?}"))?;
+
+        world.set_tile(self.tile_pos(dx, dy), material);
+        Ok(())
+    }
+
+    fn spawn(&mut self, prefab: ImmutableString) {
+        self.spawns.push((prefab.to_string(), Vec2::ZERO));
+    }
+
+    fn spawn_at(&mut self, prefab: ImmutableString, dx: FLOAT, dy: FLOAT) {
+        self.spawns
+            .push((prefab.to_string(), Vec2::new(dx as f32, dy as f32)));
+    }
+
+    fn health(&mut self) -> Dynamic {
+        self.health_obj().map_or(Dynamic::UNIT, |health| {
+            Dynamic::from_float(health.health() as FLOAT)
+        })
+    }
+
+    fn damage(&mut self, amount: FLOAT) {
+        if let Some(health) = self.health_obj() {
+            health.damage(amount as f32, HealthChangeCause::Script);
+        }
+    }
+
+    fn heal(&mut self, amount: FLOAT) {
+        if let Some(health) = self.health_obj() {
+            health.change_health(amount as f32, HealthChangeCause::Script);
+        }
+    }
+
+    fn chance(&mut self, odds: FLOAT) -> bool {
+        self.rng.gen_bool(odds as f32)
+    }
+}
+
+// === Handlers === //
+
+fn on_tile_broken(event: &TileBroken, cx: &mut BusContext) {
+    let Some(world) = event.world.try_get::<TileWorld>() else {
+        return;
+    };
+
+    let registry = world.entity().get::<MaterialRegistry>();
+    let material = registry
+        .lookup(event.material)
+        .get::<BaseMaterialDescriptor>()
+        .name
+        .clone();
+
+    let target = ScriptTarget {
+        world,
+        actor: Some(event.breaker),
+        pos: world.config().tile_to_actor_rect(event.pos).center(),
+    };
+    let rng = cx.rng.stream(RngChannel::Scripts);
+    cx.scripts
+        .run_hook(ON_TILE_BROKEN, (material,), target, &cx.prefabs, rng);
+}
+
+fn on_actor_died(event: &ActorDied, cx: &mut BusContext) {
+    let Ok((&InsideWorld(world), &Pos(pos))) = cx.actors.get(event.entity) else {
+        return;
+    };
+
+    let target = ScriptTarget {
+        world,
+        actor: Some(event.entity),
+        pos,
+    };
+    let rng = cx.rng.stream(RngChannel::Scripts);
+    cx.scripts
+        .run_hook(ON_ACTOR_DIED, (), target, &cx.prefabs, rng);
+}
+
+// === Systems === //
+
+pub fn sys_load_scripts(
+    mut scripts: ResMut<Scripts>,
+    mut bus: ResMut<EventBus>,
+    prefabs: Res<PrefabRegistry>,
+) {
+    let text = match fs::read_to_string(SCRIPTS_PATH) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return,
+        Err(err) => {
+            log::error!("Failed to read {SCRIPTS_PATH}: {err}");
+            return;
+        }
+    };
+
+    let loaded = match Scripts::compile(&text) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            log::error!("Failed to compile {SCRIPTS_PATH}: {err}");
+            return;
+        }
+    };
+
+    if let Err(err) = loaded.validate(&prefabs) {
+        log::error!("Failed to load {SCRIPTS_PATH}: {err}");
+        return;
+    }
+
+    // Event hooks are run by the event bus.
+    if loaded.has_hook(ON_TILE_BROKEN, 1) {
+        bus.subscribe(on_tile_broken);
+    }
+
+    if loaded.has_hook(ON_ACTOR_DIED, 0) {
+        bus.subscribe(on_actor_died);
+    }
+
+    log::info!(
+        "Loaded {} script function(s) from {SCRIPTS_PATH}",
+        loaded.ast.iter_functions().count()
+    );
+    *scripts = loaded;
+}
+
+/// Runs the hooks registered with `every`. Timers follow the [`WorldClock`] so that replays which
+/// rewind it fire them on the same ticks again.
+pub fn sys_run_scripts(
+    mut rand: RandomAccess<EventBusAccess>,
+    instances: Query<(Entity, &PrefabInstance, &InsideWorld, &Pos)>,
+    scripts: Res<Scripts>,
+    prefabs: Res<PrefabRegistry>,
+    mut rng: ResMut<Rng>,
+    clock: Res<WorldClock>,
+) {
+    let tick = clock.ticks();
+
+    if scripts.timers().is_empty() {
+        return;
+    }
+
+    let rng = rng.stream(RngChannel::Scripts);
+
+    rand.provide(|| {
+        for timer in scripts.timers() {
+            if tick % timer.ticks as u64 != 0 {
+                continue;
+            }
+
+            for (actor, instance, &InsideWorld(world), &Pos(pos)) in instances.iter() {
+                if instance.0 != timer.prefab {
+                    continue;
+                }
+
+                let target = ScriptTarget {
+                    world,
+                    actor: Some(actor),
+                    pos,
+                };
+                scripts.run_hook(&timer.hook, (), target, &prefabs, rng);
+            }
+        }
+    });
+}
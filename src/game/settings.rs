@@ -0,0 +1,285 @@
+use std::{fs, io, path::Path};
+
+use bevy_ecs::{
+    change_detection::{DetectChanges, DetectChangesMut},
+    system::{Local, Res, ResMut, Resource},
+};
+use macroquad::{
+    input::{is_key_down, is_key_pressed, KeyCode},
+    math::UVec2,
+    miniquad::conf::Platform,
+    window::{request_new_screen_size, set_fullscreen, Conf},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    actor::camera::{DynamicResolution, PixelPerfect},
+    input::InputMap,
+    minimap::Minimap,
+};
+
+// === Settings === //
+
+/// The settings file, relative to the working directory.
+pub const SETTINGS_PATH: &str = "settings.toml";
+
+/// User preferences loaded from [`SETTINGS_PATH`] at startup. Pressing `Ctrl+R` reloads the file
+/// and changes made through [`request_save`](Settings::request_save) are written back to it.
+///
+/// Settings are applied to the resources they configure whenever they change so hotkeys such as
+/// the minimap toggle still only affect the current session.
+#[derive(Debug, Clone, Default, Resource, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub window: WindowSettings,
+    pub audio: AudioSettings,
+    pub debug: DebugSettings,
+    #[serde(with = "bindings")]
+    pub bindings: InputMap,
+    #[serde(skip)]
+    save_requested: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowSettings {
+    pub width: i32,
+    pub height: i32,
+    pub fullscreen: bool,
+
+    /// Only takes effect once the game is restarted.
+    pub vsync: bool,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            fullscreen: false,
+            vsync: true,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioSettings {
+    /// The master volume, from `0` to `1`. Nothing plays sound yet but the setting is kept so that
+    /// existing settings files keep working once something does.
+    pub volume: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self { volume: 1. }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DebugSettings {
+    pub pixel_perfect: bool,
+
+    /// The width and height of the virtual screen rendered in pixel-perfect mode.
+    pub pixel_resolution: [u32; 2],
+    pub dynamic_resolution: bool,
+    pub minimap: bool,
+}
+
+impl Default for DebugSettings {
+    fn default() -> Self {
+        Self {
+            pixel_perfect: PixelPerfect::default().enabled,
+            pixel_resolution: PixelPerfect::default().resolution.to_array(),
+            dynamic_resolution: DynamicResolution::default().enabled,
+            minimap: true,
+        }
+    }
+}
+
+impl Settings {
+    /// Parses a TOML settings file with a `[window]`, `[audio]`, `[debug]`, and `[bindings]`
+    /// table. Settings left unspecified keep their default value.
+    ///
+    /// Bindings are listed under `[bindings]` by action, e.g. `jump = ["key:W", "pad:South"]`. See
+    /// [`InputBinding::parse`](super::input::InputBinding::parse) for the format of each binding.
+    pub fn parse(text: &str) -> Result<Self, toml::de::Error> {
+        let mut settings = toml::from_str::<Self>(text)?;
+        settings.window.width = settings.window.width.max(1);
+        settings.window.height = settings.window.height.max(1);
+        settings.audio.volume = settings.audio.volume.clamp(0., 1.);
+        settings.debug.pixel_resolution = settings.debug.pixel_resolution.map(|edge| edge.max(1));
+
+        Ok(settings)
+    }
+
+    /// Serializes the settings in the format accepted by [`parse`](Self::parse).
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
+
+    /// Reads the settings file at `path`, returning `None` if it doesn't exist or can't be parsed.
+    /// Failures other than the file being missing are logged.
+    pub fn load(path: impl AsRef<Path>) -> Option<Self> {
+        let path = path.as_ref();
+
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                log::error!("Failed to read {}: {err}", path.display());
+                return None;
+            }
+        };
+
+        match Self::parse(&text) {
+            Ok(settings) => Some(settings),
+            Err(err) => {
+                log::error!("Failed to parse {}: {err}", path.display());
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_toml().map_err(io::Error::other)?)
+    }
+
+    /// Asks for the settings to be written back to [`SETTINGS_PATH`] at the end of the frame.
+    pub fn request_save(&mut self) {
+        self.save_requested = true;
+    }
+
+    /// The window configuration to create the game's window with.
+    pub fn window_conf(&self, title: &str) -> Conf {
+        Conf {
+            window_title: title.to_string(),
+            window_width: self.window.width,
+            window_height: self.window.height,
+            fullscreen: self.window.fullscreen,
+            platform: Platform {
+                swap_interval: Some(self.window.vsync as i32),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+}
+
+/// Stores an [`InputMap`] as a table from each action's id to the list of its bindings. Actions
+/// left out of the table keep their default bindings.
+mod bindings {
+    use rustc_hash::FxHashMap;
+    use serde::{de::Error, ser::SerializeMap, Deserialize, Deserializer, Serializer};
+
+    use crate::game::input::{InputAction, InputBinding, InputMap};
+
+    pub fn serialize<S: Serializer>(map: &InputMap, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut table = serializer.serialize_map(Some(InputAction::ALL.len()))?;
+
+        for action in InputAction::ALL {
+            let list = map
+                .bindings(action)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+
+            table.serialize_entry(action.id(), &list)?;
+        }
+
+        table.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<InputMap, D::Error> {
+        let mut map = InputMap::default();
+
+        for (action, list) in FxHashMap::<String, Vec<String>>::deserialize(deserializer)? {
+            let action = InputAction::from_id(&action)
+                .ok_or_else(|| D::Error::custom(format!("unknown action {action:?}")))?;
+
+            let bindings = list
+                .iter()
+                .map(|binding| {
+                    InputBinding::parse(binding)
+                        .ok_or_else(|| D::Error::custom(format!("unknown binding {binding:?}")))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            map.set_bindings(action, bindings);
+        }
+
+        Ok(map)
+    }
+}
+
+// === Systems === //
+
+fn load_settings(settings: &mut Settings) {
+    if let Some(loaded) = Settings::load(SETTINGS_PATH) {
+        *settings = loaded;
+        log::info!("Loaded settings from {SETTINGS_PATH}");
+    }
+}
+
+pub fn sys_load_settings(mut settings: ResMut<Settings>) {
+    load_settings(&mut settings);
+}
+
+pub fn sys_reload_settings(mut settings: ResMut<Settings>) {
+    let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+    if ctrl && is_key_pressed(KeyCode::R) {
+        load_settings(&mut settings);
+    }
+}
+
+/// Pushes the settings into the resources they configure whenever they change.
+pub fn sys_apply_settings(
+    settings: Res<Settings>,
+    mut input: ResMut<InputMap>,
+    mut pixel: ResMut<PixelPerfect>,
+    mut dynamic: ResMut<DynamicResolution>,
+    mut minimap: ResMut<Minimap>,
+    mut applied_window: Local<Option<WindowSettings>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    *input = settings.bindings.clone();
+    pixel.enabled = settings.debug.pixel_perfect;
+    pixel.resolution = UVec2::from_array(settings.debug.pixel_resolution);
+    dynamic.enabled = settings.debug.dynamic_resolution;
+    minimap.enabled = settings.debug.minimap;
+
+    // The window was created from the settings file so there's nothing to apply the first time.
+    let window = settings.window;
+    let Some(applied) = applied_window.replace(window) else {
+        return;
+    };
+
+    if window.fullscreen != applied.fullscreen {
+        set_fullscreen(window.fullscreen);
+    }
+
+    if !window.fullscreen && (window.width, window.height) != (applied.width, applied.height) {
+        request_new_screen_size(window.width as f32, window.height as f32);
+    }
+
+    if window.vsync != applied.vsync {
+        log::info!("The vsync setting will take effect once the game is restarted");
+    }
+}
+
+pub fn sys_persist_settings(mut settings: ResMut<Settings>) {
+    if !settings.save_requested {
+        return;
+    }
+
+    settings.bypass_change_detection().save_requested = false;
+
+    match settings.save(SETTINGS_PATH) {
+        Ok(()) => log::info!("Saved settings to {SETTINGS_PATH}"),
+        Err(err) => log::error!("Failed to save {SETTINGS_PATH}: {err}"),
+    }
+}
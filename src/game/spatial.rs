@@ -0,0 +1,159 @@
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    schedule::SystemSet,
+    system::{Local, Query},
+};
+use macroquad::math::{Affine2, Vec2};
+use rustc_hash::FxHashMap;
+
+use super::actor::kinematic::Pos;
+
+// === Spatial === //
+
+/// A node in the transform hierarchy. Each node's global transform is its parent's global
+/// transform composed with its own local transform.
+#[derive(Debug, Clone, Component)]
+pub struct Spatial {
+    pub parent: Option<Entity>,
+    pub local: Affine2,
+    global: Affine2,
+}
+
+impl Spatial {
+    pub fn new(local: Affine2) -> Self {
+        Self {
+            parent: None,
+            local,
+            global: local,
+        }
+    }
+
+    pub fn new_at(pos: Vec2) -> Self {
+        Self::new(Affine2::from_translation(pos))
+    }
+
+    pub fn with_parent(mut self, parent: Entity) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    /// The global transform as of the last propagation pass.
+    pub fn global(&self) -> Affine2 {
+        self.global
+    }
+
+    pub fn global_pos(&self) -> Vec2 {
+        self.global.translation
+    }
+}
+
+/// Determines how an actor's [`Pos`] relates to its [`Spatial`] node.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Component)]
+pub enum SpatialSync {
+    /// The actor's `Pos` drives the node's local translation. Used for roots simulated by the
+    /// kinematics systems, such as players.
+    FromPos,
+
+    /// The node's global translation overwrites the actor's `Pos`. Used for leaves attached to a
+    /// moving parent, such as held items.
+    ToPos,
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, SystemSet)]
+pub struct SpatialSyncSet;
+
+/// Checks that every node's ancestors exist and that the hierarchy has no cycles. Mismatches are
+/// described in `problems`.
+pub fn check_spatial_links(query: &Query<(Entity, &Spatial)>, problems: &mut Vec<String>) {
+    let node_count = query.iter().len();
+
+    for (entity, spatial) in query.iter() {
+        let mut cursor = spatial.parent;
+        let mut depth = 0;
+
+        while let Some(parent) = cursor {
+            if parent == entity || depth > node_count {
+                problems.push(format!(
+                    "Spatial node {entity:?} has a cycle among its ancestors"
+                ));
+                break;
+            }
+
+            let Ok((_, parent_spatial)) = query.get(parent) else {
+                problems.push(format!(
+                    "Spatial node {entity:?} has ancestor {parent:?} which is not a node",
+                ));
+                break;
+            };
+
+            cursor = parent_spatial.parent;
+            depth += 1;
+        }
+    }
+}
+
+// === Systems === //
+
+pub fn sys_sync_pos_to_spatial(mut query: Query<(&SpatialSync, &Pos, &mut Spatial)>) {
+    for (&sync, &Pos(pos), mut spatial) in query.iter_mut() {
+        if sync == SpatialSync::FromPos {
+            spatial.local.translation = pos;
+        }
+    }
+}
+
+pub fn sys_propagate_spatial(
+    mut query: Query<(Entity, &mut Spatial)>,
+    mut globals: Local<FxHashMap<Entity, Affine2>>,
+) {
+    globals.clear();
+
+    let nodes = query
+        .iter()
+        .map(|(entity, spatial)| (entity, (spatial.parent, spatial.local)))
+        .collect::<FxHashMap<_, _>>();
+
+    fn resolve(
+        nodes: &FxHashMap<Entity, (Option<Entity>, Affine2)>,
+        globals: &mut FxHashMap<Entity, Affine2>,
+        entity: Entity,
+        depth: usize,
+    ) -> Affine2 {
+        if let Some(&global) = globals.get(&entity) {
+            return global;
+        }
+
+        let Some(&(parent, local)) = nodes.get(&entity) else {
+            // Dangling parents are treated as the origin.
+            return Affine2::IDENTITY;
+        };
+
+        let global = match parent {
+            // Bound the recursion so that a parent cycle cannot overflow the stack.
+            Some(parent) if depth < nodes.len() => {
+                resolve(nodes, globals, parent, depth + 1) * local
+            }
+            Some(_) => {
+                log::warn!("Spatial hierarchy contains a cycle involving {entity:?}");
+                local
+            }
+            None => local,
+        };
+
+        globals.insert(entity, global);
+        global
+    }
+
+    for (entity, mut spatial) in query.iter_mut() {
+        spatial.global = resolve(&nodes, &mut globals, entity, 0);
+    }
+}
+
+pub fn sys_sync_spatial_to_pos(mut query: Query<(&SpatialSync, &Spatial, &mut Pos)>) {
+    for (&sync, spatial, mut pos) in query.iter_mut() {
+        if sync == SpatialSync::ToPos {
+            pos.0 = spatial.global_pos();
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use macroquad::{color::Color, math::IVec2};
+
+use crate::{game::math::aabb::Aabb, random_component};
+
+use super::render::TileAtlas;
+
+random_component!(AutoTileMaterial);
+
+// === AutoTileMask === //
+
+/// The set of neighbors a tile connects to, with one bit per direction.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Default)]
+pub struct AutoTileMask(pub u8);
+
+impl AutoTileMask {
+    pub const TOP: u8 = 1 << 0;
+    pub const RIGHT: u8 = 1 << 1;
+    pub const BOTTOM: u8 = 1 << 2;
+    pub const LEFT: u8 = 1 << 3;
+    pub const TOP_RIGHT: u8 = 1 << 4;
+    pub const BOTTOM_RIGHT: u8 = 1 << 5;
+    pub const BOTTOM_LEFT: u8 = 1 << 6;
+    pub const TOP_LEFT: u8 = 1 << 7;
+
+    /// The offset of the neighbor represented by each bit, in bit order. The four edge neighbors
+    /// come first.
+    pub const OFFSETS: [IVec2; 8] = [
+        IVec2::new(0, -1),
+        IVec2::new(1, 0),
+        IVec2::new(0, 1),
+        IVec2::new(-1, 0),
+        IVec2::new(1, -1),
+        IVec2::new(1, 1),
+        IVec2::new(-1, 1),
+        IVec2::new(-1, -1),
+    ];
+
+    pub fn has(self, bits: u8) -> bool {
+        self.0 & bits == bits
+    }
+
+    /// Clears every corner bit whose two adjacent edges aren't both connected. Such corners look the
+    /// same either way since they're covered by the borders of the unconnected edges.
+    pub const fn reduce_corners(self) -> Self {
+        const CORNERS: [(u8, u8); 4] = [
+            (
+                AutoTileMask::TOP_RIGHT,
+                AutoTileMask::TOP | AutoTileMask::RIGHT,
+            ),
+            (
+                AutoTileMask::BOTTOM_RIGHT,
+                AutoTileMask::BOTTOM | AutoTileMask::RIGHT,
+            ),
+            (
+                AutoTileMask::BOTTOM_LEFT,
+                AutoTileMask::BOTTOM | AutoTileMask::LEFT,
+            ),
+            (
+                AutoTileMask::TOP_LEFT,
+                AutoTileMask::TOP | AutoTileMask::LEFT,
+            ),
+        ];
+
+        let mut mask = self.0;
+        let mut i = 0;
+        while i < CORNERS.len() {
+            let (corner, edges) = CORNERS[i];
+            if mask & edges != edges {
+                mask &= !corner;
+            }
+            i += 1;
+        }
+
+        Self(mask)
+    }
+}
+
+// === AutoTileLayout === //
+
+/// The number of distinct masks once their corners are reduced.
+const BLOB_SPRITES: usize = 47;
+
+/// Maps every mask to its blob sprite and every blob sprite back to its reduced mask.
+const BLOB_TABLES: ([u8; 256], [u8; BLOB_SPRITES]) = {
+    let mut sprites = [0; 256];
+    let mut masks = [0; BLOB_SPRITES];
+    let mut count = 0;
+
+    // Reducing a mask only ever clears bits so every mask comes after its reduced form.
+    let mut mask = 0;
+    while mask < 256 {
+        let reduced = AutoTileMask(mask as u8).reduce_corners().0;
+        if reduced as usize == mask {
+            masks[count] = reduced;
+            sprites[mask] = count as u8;
+            count += 1;
+        } else {
+            sprites[mask] = sprites[reduced as usize];
+        }
+        mask += 1;
+    }
+
+    assert!(count == BLOB_SPRITES);
+    (sprites, masks)
+};
+
+/// How the sprites of an [`AutoTileMaterial`] are laid out in its atlas.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum AutoTileLayout {
+    /// Only the four edge neighbors are considered, giving one sprite for each of the 16 masks in
+    /// mask order.
+    Edges,
+
+    /// All eight neighbors are considered so that inner corners can be drawn. There is one sprite
+    /// for each of the 47 masks left after [`reducing`](AutoTileMask::reduce_corners) them, in
+    /// increasing mask order.
+    Blob,
+}
+
+impl AutoTileLayout {
+    pub fn sprite_count(self) -> u32 {
+        match self {
+            Self::Edges => 16,
+            Self::Blob => BLOB_SPRITES as u32,
+        }
+    }
+
+    pub fn uses_corners(self) -> bool {
+        matches!(self, Self::Blob)
+    }
+
+    /// Computes the mask of a tile given a function telling whether it connects to the neighbor at
+    /// the given relative offset. Corners are only sampled if the layout uses them.
+    pub fn sample_mask(self, mut connects: impl FnMut(IVec2) -> bool) -> AutoTileMask {
+        let sampled = if self.uses_corners() { 8 } else { 4 };
+        let mut mask = 0;
+
+        for (bit, &offset) in AutoTileMask::OFFSETS[..sampled].iter().enumerate() {
+            if connects(offset) {
+                mask |= 1 << bit;
+            }
+        }
+
+        AutoTileMask(mask)
+    }
+
+    /// The index of the sprite drawn for `mask`, relative to the layout's first sprite.
+    pub fn sprite(self, mask: AutoTileMask) -> u32 {
+        match self {
+            Self::Edges => (mask.0 & 0xF) as u32,
+            Self::Blob => BLOB_TABLES.0[mask.0 as usize] as u32,
+        }
+    }
+
+    /// The mask represented by the given sprite. This is useful when painting atlases.
+    pub fn mask(self, sprite: u32) -> AutoTileMask {
+        match self {
+            Self::Edges => AutoTileMask(sprite as u8 & 0xF),
+            Self::Blob => AutoTileMask(BLOB_TABLES.1[sprite as usize]),
+        }
+    }
+}
+
+// === AutoTileMaterial === //
+
+/// A material descriptor whose tiles pick their sprite depending on which of their neighbors they
+/// connect to so that the edges of a region of tiles look continuous rather than like a grid of
+/// identical squares. This takes precedence over the other tile materials.
+#[derive(Debug)]
+pub struct AutoTileMaterial {
+    pub atlas: TileAtlas,
+    pub layout: AutoTileLayout,
+
+    /// The atlas index of the layout's first sprite. The rest of its sprites follow in order.
+    pub first_sprite: u32,
+    pub tint: Color,
+
+    /// Whether tiles connect to every opaque tile rather than only to tiles of the same material.
+    pub connect_to_solids: bool,
+}
+
+impl AutoTileMaterial {
+    /// Computes the mask of a tile given a function telling whether it connects to the neighbor at
+    /// the given relative offset. See [`AutoTileLayout::sample_mask`].
+    pub fn mask(&self, connects: impl FnMut(IVec2) -> bool) -> AutoTileMask {
+        self.layout.sample_mask(connects)
+    }
+
+    pub fn uv(&self, mask: AutoTileMask) -> Aabb {
+        self.atlas.uv(self.first_sprite + self.layout.sprite(mask))
+    }
+}
@@ -0,0 +1,181 @@
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    event::Event,
+    system::{Query, Res},
+};
+use cbit::cbit;
+use macroquad::{
+    color::Color,
+    math::{IVec2, Vec2},
+    shapes::draw_line,
+};
+
+use crate::{
+    game::{
+        actor::camera::ActiveCamera,
+        math::{draw::draw_rectangle_aabb, noise::hash_unit},
+    },
+    random_event,
+    util::arena::{send_event, Obj, RandomAccess, RandomEntityExt},
+};
+
+use super::{
+    data::TileWorld,
+    material::{BaseMaterialDescriptor, MaterialId, MaterialRegistry},
+};
+
+// === TileBroken === //
+
+random_event!(TileBroken);
+
+/// Sent whenever a [`TileBreaker`] finishes mining a tile.
+#[derive(Debug, Clone, Event)]
+pub struct TileBroken {
+    pub world: Entity,
+    pub breaker: Entity,
+    pub pos: IVec2,
+    pub material: MaterialId,
+}
+
+// === TileBreaker === //
+
+/// Tracks an actor's progress in mining a single tile. Switching to a different tile or stopping
+/// resets the progress.
+#[derive(Debug, Clone, Default, Component)]
+pub struct TileBreaker {
+    target: Option<(Obj<TileWorld>, IVec2, MaterialId)>,
+    progress: f32,
+    hardness: f32,
+}
+
+impl TileBreaker {
+    /// The amount of progress made every tick spent mining.
+    pub const SPEED: f32 = 1.;
+
+    pub fn target(&self) -> Option<(Obj<TileWorld>, IVec2)> {
+        self.target.map(|(world, pos, _)| (world, pos))
+    }
+
+    /// The fraction of the target tile which has been mined, from zero to one.
+    pub fn fraction(&self) -> f32 {
+        if self.hardness <= 0. {
+            1.
+        } else {
+            (self.progress / self.hardness).min(1.)
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.target = None;
+        self.progress = 0.;
+        self.hardness = 0.;
+    }
+
+    /// Mines the first solid tile along the segment from `from` to `to` for a single tick, breaking
+    /// it and sending a [`TileBroken`] event if it has been mined for long enough. Returns the
+    /// position and former material of the broken tile, if any.
+    pub fn mine(
+        &mut self,
+        me: Entity,
+        world: Obj<TileWorld>,
+        from: Vec2,
+        to: Vec2,
+    ) -> Option<(IVec2, MaterialId)> {
+        let config = world.config();
+        let registry = world.entity().get::<MaterialRegistry>();
+
+        let mut found = None;
+        cbit! {
+            for pos in config.step_ray_tiles(from, to) {
+                let material = world.tile(pos);
+                if material != MaterialId::AIR {
+                    found = Some((pos, material));
+                    break;
+                }
+            }
+        }
+
+        let Some((pos, material)) = found else {
+            self.reset();
+            return None;
+        };
+
+        // Restart if the target changed, including if it was replaced by another material.
+        if self.target != Some((world, pos, material)) {
+            self.target = Some((world, pos, material));
+            self.progress = 0.;
+            self.hardness = registry
+                .lookup(material)
+                .get::<BaseMaterialDescriptor>()
+                .hardness;
+        }
+
+        self.progress += Self::SPEED;
+        if self.progress < self.hardness {
+            return None;
+        }
+
+        world.set_tile(pos, MaterialId::AIR);
+        send_event(TileBroken {
+            world: world.entity(),
+            breaker: me,
+            pos,
+            material,
+        });
+        self.reset();
+
+        Some((pos, material))
+    }
+}
+
+// === Systems === //
+
+pub fn sys_render_break_progress(
+    mut rand: RandomAccess<&TileWorld>,
+    query: Query<&TileBreaker>,
+    camera: Res<ActiveCamera>,
+) {
+    /// The number of cracks drawn once the tile is about to break.
+    const MAX_CRACKS: u32 = 6;
+
+    let _guard = camera.apply();
+
+    rand.provide(|| {
+        for breaker in query.iter() {
+            let Some((world, pos)) = breaker.target() else {
+                continue;
+            };
+
+            if !camera.shows(world) {
+                continue;
+            }
+
+            let aabb = world.config().tile_to_actor_rect(pos);
+            let fraction = breaker.fraction();
+
+            draw_rectangle_aabb(aabb, Color::new(0., 0., 0., 0.4 * fraction));
+
+            // Cracks radiate out from the center of the tile, with more appearing as the tile
+            // weakens.
+            let cracks = (fraction * MAX_CRACKS as f32).ceil() as u32;
+            let center = aabb.center();
+            let radius = aabb.w().min(aabb.h()) / 2.;
+
+            for i in 0..cracks {
+                let angle = hash_unit(i, pos) * std::f32::consts::TAU;
+                let length = radius * (0.5 + 0.5 * hash_unit(i + MAX_CRACKS, pos));
+                let end = center + Vec2::from_angle(angle) * length;
+
+                draw_line(
+                    center.x,
+                    center.y,
+                    end.x,
+                    end.y,
+                    2.,
+                    Color::new(0., 0., 0., 0.8),
+                );
+            }
+        }
+    });
+}
@@ -0,0 +1,167 @@
+use std::ops::ControlFlow;
+
+use bevy_ecs::entity::Entity;
+use macroquad::math::IVec2;
+use rustc_hash::FxHashMap;
+use smallvec::SmallVec;
+
+use crate::game::math::aabb::{Aabb, AabbI};
+
+// === ColliderGrid === //
+
+/// A uniform-grid broadphase over a set of AABBs. Every entry is stored in each cell its AABB
+/// overlaps so queries only have to visit the cells overlapping the query rect.
+#[derive(Debug)]
+pub struct ColliderGrid {
+    cell_size: f32,
+    cells: FxHashMap<IVec2, SmallVec<[usize; 4]>>,
+    entries: Vec<Option<GridEntry>>,
+    free: Vec<usize>,
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct GridHandle(usize);
+
+#[derive(Debug, Copy, Clone)]
+struct GridEntry {
+    entity: Entity,
+    aabb: Aabb,
+    membership: u32,
+    cells: AabbI,
+}
+
+impl ColliderGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: FxHashMap::default(),
+            entries: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The range of cells overlapped by `aabb`, with an exclusive `max`.
+    fn cells_of(&self, aabb: Aabb) -> AabbI {
+        let aabb = aabb.normalized();
+        let min = (aabb.min / self.cell_size).floor().as_ivec2();
+        let max = (aabb.max / self.cell_size).floor().as_ivec2();
+        AabbI { min, max }.inclusive()
+    }
+
+    pub fn insert(&mut self, entity: Entity, aabb: Aabb, membership: u32) -> GridHandle {
+        let cells = self.cells_of(aabb);
+        let entry = GridEntry {
+            entity,
+            aabb,
+            membership,
+            cells,
+        };
+
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.entries[index] = Some(entry);
+                index
+            }
+            None => {
+                self.entries.push(Some(entry));
+                self.entries.len() - 1
+            }
+        };
+
+        self.link(index, cells);
+        GridHandle(index)
+    }
+
+    /// Moves an entry to `aabb`, only touching the cell lists if the set of overlapped cells
+    /// changed.
+    pub fn update(&mut self, GridHandle(index): GridHandle, aabb: Aabb) {
+        let new_cells = self.cells_of(aabb);
+        let entry = self.entries[index].as_mut().expect("stale grid handle");
+        let old_cells = entry.cells;
+
+        entry.aabb = aabb;
+        entry.cells = new_cells;
+
+        if old_cells != new_cells {
+            self.unlink(index, old_cells);
+            self.link(index, new_cells);
+        }
+    }
+
+    /// Removes an entry from the grid, returning its membership.
+    pub fn remove(&mut self, GridHandle(index): GridHandle) -> u32 {
+        let entry = self.entries[index].take().expect("stale grid handle");
+        self.unlink(index, entry.cells);
+        self.free.push(index);
+        entry.membership
+    }
+
+    fn link(&mut self, index: usize, cells: AabbI) {
+        for cell in cells.iter() {
+            self.cells.entry(cell).or_default().push(index);
+        }
+    }
+
+    fn unlink(&mut self, index: usize, cells: AabbI) {
+        for cell in cells.iter() {
+            let Some(list) = self.cells.get_mut(&cell) else {
+                continue;
+            };
+
+            if let Some(pos) = list.iter().position(|&other| other == index) {
+                list.swap_remove(pos);
+            }
+
+            if list.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    /// Iterates over every entry intersecting `aabb` whose membership overlaps `mask`. Each entry
+    /// is reported exactly once without allocating.
+    pub fn query<B>(
+        &self,
+        aabb: Aabb,
+        mask: u32,
+        mut f: impl FnMut((Entity, Aabb)) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        let query_cells = self.cells_of(aabb);
+
+        for cell in query_cells.iter() {
+            let Some(list) = self.cells.get(&cell) else {
+                continue;
+            };
+
+            for &index in list {
+                let entry = self.entries[index].as_ref().unwrap();
+
+                // An entry spanning several cells is only reported from the first cell shared by
+                // both it and the query.
+                if cell != entry.cells.min.max(query_cells.min) {
+                    continue;
+                }
+
+                if entry.membership & mask == 0 || !aabb.intersects(entry.aabb) {
+                    continue;
+                }
+
+                f((entry.entity, entry.aabb))?;
+            }
+        }
+
+        ControlFlow::Continue(())
+    }
+}
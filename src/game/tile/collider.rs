@@ -5,11 +5,9 @@ use bevy_ecs::{
     entity::Entity,
     event::EventReader,
     query::{Added, Changed},
-    removal_detection::RemovedComponents,
     system::Query,
 };
 use macroquad::math::{IVec2, Vec2};
-use rustc_hash::FxHashSet;
 
 use crate::{
     game::math::aabb::Aabb,
@@ -17,57 +15,193 @@ use crate::{
     util::arena::{Obj, ObjOwner, RandomAccess, RandomEntityExt, SendsEvent},
 };
 
-use super::data::{TileChunk, TileLayerConfig, TileWorld, WorldCreatedChunk};
+use super::{
+    broadphase::{ColliderGrid, GridHandle},
+    data::{TileChunk, TileLayerConfig, TileWorld, WorldCreatedChunk},
+};
+
+random_component!(WorldColliders, TrackedColliderChunk);
 
-random_component!(WorldColliders, TrackedColliderChunk, TrackedCollider);
+random_component!(TrackedCollider {
+    on_remove: (
+        &'static mut TrackedColliderChunk,
+        &'static TileWorld,
+        &'static mut WorldColliders,
+    ) => TrackedCollider::unlink,
+});
 
 // === Collider === //
 
 #[derive(Debug, Component)]
 pub struct InsideWorld(pub Obj<TileWorld>);
 
-#[derive(Debug, Component)]
+#[derive(Debug, Clone, Component)]
 pub struct Collider(pub Aabb);
 
+/// Determines which colliders interact with one another. A collider is only reported to a query
+/// if its `membership` shares a bit with the query's mask.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Component)]
+pub struct CollisionLayers {
+    /// The layers this collider belongs to.
+    pub membership: u32,
+
+    /// The layers this collider interacts with when moving.
+    pub mask: u32,
+}
+
+impl Default for CollisionLayers {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl CollisionLayers {
+    pub const TILES: u32 = 1 << 0;
+    pub const ACTORS: u32 = 1 << 1;
+    pub const PLAYERS: u32 = 1 << 2;
+    pub const PROJECTILES: u32 = 1 << 3;
+    pub const TRIGGERS: u32 = 1 << 4;
+    pub const PLATFORMS: u32 = 1 << 5;
+    pub const ITEMS: u32 = 1 << 6;
+    pub const ALL: u32 = u32::MAX;
+
+    /// The layers of colliders without a `CollisionLayers` component: generic actors which only
+    /// collide with tiles.
+    pub const DEFAULT: Self = Self::new(Self::ACTORS, Self::TILES);
+
+    pub const fn new(membership: u32, mask: u32) -> Self {
+        Self { membership, mask }
+    }
+
+    pub fn interacts_with(self, other: Self) -> bool {
+        self.mask & other.membership != 0
+    }
+}
+
 // === WorldCollisions === //
 
 #[derive(Debug)]
 pub struct WorldColliders {
-    data: Obj<TileWorld>,
+    grid: ColliderGrid,
 }
 
 impl WorldColliders {
+    /// The width of a broadphase cell, in tiles.
+    pub const CELL_TILES: f32 = 4.;
+
     pub fn new(data: Obj<TileWorld>) -> Self {
-        Self { data }
+        Self {
+            grid: ColliderGrid::new(data.config().size * Self::CELL_TILES),
+        }
     }
 
+    pub fn grid(&self) -> &ColliderGrid {
+        &self.grid
+    }
+
+    /// Iterates over every tracked collider intersecting `aabb` whose membership overlaps `mask`.
     pub fn collisions<B>(
         &self,
         aabb: Aabb,
+        mask: u32,
+        f: impl FnMut((Entity, Aabb)) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.grid.query(aabb, mask, f)
+    }
+
+    /// Iterates over every tracked collider containing `point` whose membership overlaps `mask`.
+    pub fn colliders_at<B>(
+        &self,
+        point: Vec2,
+        mask: u32,
         mut f: impl FnMut((Entity, Aabb)) -> ControlFlow<B>,
     ) -> ControlFlow<B> {
-        let config = self.data.config();
+        self.grid.query(
+            Aabb::new_sized(point, Vec2::ZERO),
+            mask,
+            |(entity, aabb)| {
+                if aabb.contains(point) {
+                    f((entity, aabb))
+                } else {
+                    ControlFlow::Continue(())
+                }
+            },
+        )
+    }
 
-        let mut chunks = FxHashSet::default();
+    /// Finds the nearest tracked collider whose membership overlaps `mask` and which is accepted by
+    /// `filter` along the segment from `from` to `to`. Unlike the [`KinematicApi`] casts, tiles are
+    /// ignored entirely, which makes this much cheaper for things like melee hits and sightlines
+    /// between actors.
+    ///
+    /// [`KinematicApi`]: super::kinematic::KinematicApi
+    pub fn segment_cast(
+        &self,
+        from: Vec2,
+        to: Vec2,
+        mask: u32,
+        mut filter: impl FnMut(Entity) -> bool,
+    ) -> Option<ColliderHit> {
+        let bounds = Aabb {
+            min: from.min(to),
+            max: from.max(to),
+        };
+
+        let mut nearest = None;
+        let _ = self.grid.query(bounds, mask, |(entity, aabb)| {
+            ColliderHit::keep_nearest(&mut nearest, from, to, entity, aabb, &mut filter);
+            ControlFlow::<()>::Continue(())
+        });
+
+        nearest
+    }
+}
 
-        for chunk in config
-            .actor_aabb_to_tile(aabb.grow(Vec2::splat(10.)))
-            .inclusive()
-            .iter()
-        {
-            chunks.insert(TileLayerConfig::decompose_world_pos(chunk).0);
-        }
+/// A collider hit by a segment cast.
+#[derive(Debug, Copy, Clone)]
+pub struct ColliderHit {
+    pub entity: Entity,
+    pub aabb: Aabb,
 
-        for &chunk in &chunks {
-            let chunk =
-                get_collider_chunk_or_insert(self.data, self.data.chunk_or_create(chunk).entity());
+    /// The fraction of the segment travelled before hitting the collider, from `0` to `1`.
+    pub fraction: f32,
+    pub point: Vec2,
 
-            for isect in chunk.intersections(aabb) {
-                f(isect)?;
-            }
+    /// The normal of the face through which the segment entered the collider. This is zero for
+    /// segments starting inside of it.
+    pub normal: Vec2,
+}
+
+impl ColliderHit {
+    /// Replaces `nearest` with the hit against `aabb` if the segment hits it any sooner.
+    fn keep_nearest(
+        nearest: &mut Option<Self>,
+        from: Vec2,
+        to: Vec2,
+        entity: Entity,
+        aabb: Aabb,
+        filter: impl FnOnce(Entity) -> bool,
+    ) {
+        let delta = to - from;
+        let Some((fraction, normal)) = aabb.ray_cast(from, delta) else {
+            return;
+        };
+
+        if fraction > 1. || nearest.is_some_and(|nearest| nearest.fraction <= fraction) {
+            return;
         }
 
-        ControlFlow::Continue(())
+        if !filter(entity) {
+            return;
+        }
+
+        *nearest = Some(Self {
+            entity,
+            aabb,
+            fraction,
+            point: from + delta * fraction,
+            normal,
+        });
     }
 }
 
@@ -87,6 +221,7 @@ pub struct TrackedColliderChunk {
 pub struct TrackedCollider {
     chunk: Obj<TrackedColliderChunk>,
     index: usize,
+    grid: Option<GridHandle>,
 }
 
 impl TrackedColliderChunk {
@@ -110,6 +245,42 @@ impl TrackedColliderChunk {
         self.aabbs[collider.index] = aabb;
     }
 
+    /// Checks that every collider registered in the chunk points back to it at the right index.
+    /// Mismatches are described in `problems`.
+    pub fn check_links(self: Obj<Self>, problems: &mut Vec<String>) {
+        if self.aabbs.len() != self.handles.len() {
+            problems.push(format!(
+                "{self:?} has {} AABBs but {} handles",
+                self.aabbs.len(),
+                self.handles.len(),
+            ));
+        }
+
+        for (index, &handle) in self.handles.iter().enumerate() {
+            if !handle.is_alive() {
+                problems.push(format!("{self:?} has dead collider {handle:?} at {index}"));
+                continue;
+            }
+
+            if handle.chunk != self || handle.index != index {
+                problems.push(format!(
+                    "{self:?} has {handle:?} at {index} but it points to {:?} at {}",
+                    handle.chunk, handle.index,
+                ));
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handles.is_empty()
+    }
+
+    /// Releases the memory of the collider lists which isn't currently in use.
+    pub fn shrink_to_fit(&mut self) {
+        self.aabbs.shrink_to_fit();
+        self.handles.shrink_to_fit();
+    }
+
     pub fn aabbs(&self) -> impl ExactSizeIterator<Item = (Entity, Aabb)> + '_ {
         self.handles
             .iter()
@@ -118,9 +289,124 @@ impl TrackedColliderChunk {
             .zip(self.aabbs.iter().copied())
     }
 
-    pub fn intersections(&self, aabb: Aabb) -> impl Iterator<Item = (Entity, Aabb)> + '_ {
-        self.aabbs()
-            .filter(move |&(_, other)| aabb.intersects(other))
+    /// Iterates over every collider of the chunk containing `point`.
+    pub fn colliders_at(&self, point: Vec2) -> impl Iterator<Item = (Entity, Aabb)> + '_ {
+        self.aabbs().filter(move |(_, aabb)| aabb.contains(point))
+    }
+
+    /// Finds the nearest collider of this chunk accepted by `filter` along the segment from `from`
+    /// to `to`. Only colliders whose center lies within the chunk are considered and collision
+    /// layers are ignored; see [`WorldColliders::segment_cast`] for a cast through the whole world.
+    pub fn segment_cast(
+        &self,
+        from: Vec2,
+        to: Vec2,
+        mut filter: impl FnMut(Entity) -> bool,
+    ) -> Option<ColliderHit> {
+        let mut nearest = None;
+        for (entity, aabb) in self.aabbs() {
+            ColliderHit::keep_nearest(&mut nearest, from, to, entity, aabb, &mut filter);
+        }
+        nearest
+    }
+}
+
+impl TrackedCollider {
+    /// Checks that the chunk the collider points to has it registered at the right index.
+    /// Mismatches are described in `problems`.
+    pub fn check_links(self: Obj<Self>, problems: &mut Vec<String>) {
+        if !self.chunk.is_alive() {
+            problems.push(format!("{self:?} points to dead chunk {:?}", self.chunk));
+            return;
+        }
+
+        let registered = self.chunk.handles.get(self.index).copied();
+        if registered != Some(self) {
+            problems.push(format!(
+                "{self:?} thinks it is at {} in {:?} but the chunk has {registered:?}",
+                self.index, self.chunk,
+            ));
+        }
+    }
+
+    /// Moves the collider to `aabb` in the world's broadphase and chunks. This is done for every
+    /// changed [`Collider`] at the end of each update but can be called directly by systems whose
+    /// colliders must be up to date before then.
+    pub fn move_to(self: Obj<Self>, aabb: Aabb) {
+        let old_chunk = self.chunk;
+        let config = old_chunk.config;
+        let world = old_chunk.world;
+        let old_pos = old_chunk.pos;
+
+        // Update the broadphase
+        if let Some(handle) = self.grid {
+            world
+                .map_entity::<WorldColliders>()
+                .grid
+                .update(handle, aabb);
+        }
+
+        // Ensure that we moved to a new chunk
+        let new_pos_world = aabb.center();
+        let new_pos = config.actor_to_decomposed(new_pos_world).0;
+
+        if new_pos == old_pos {
+            old_chunk.deref_mut().aabbs[self.index] = aabb;
+        } else {
+            // Remove from the previous chunk
+            old_chunk.unregister(self);
+
+            // Move them to a new chunk
+            let new_chunk = get_collider_chunk_or_insert(world, world.chunk_or_create(new_pos));
+
+            new_chunk.register(self, aabb);
+        }
+    }
+
+    /// Removes the collider from the world's broadphase so that collision queries stop reporting
+    /// it until it's [transferred](Self::transfer_to) back in. The collider stays registered in its
+    /// chunk.
+    pub fn disable(mut self: Obj<Self>) {
+        if let Some(handle) = self.grid.take() {
+            self.chunk
+                .world
+                .map_entity::<WorldColliders>()
+                .grid
+                .remove(handle);
+        }
+    }
+
+    /// Moves the collider into another world to `aabb`, e.g. when its actor goes through a portal.
+    /// The actor's [`InsideWorld`] and [`Collider`] must be updated to match by the caller.
+    pub fn transfer_to(
+        mut self: Obj<Self>,
+        world: Obj<TileWorld>,
+        aabb: Aabb,
+        layers: CollisionLayers,
+    ) {
+        self.unlink();
+
+        let chunk = world.chunk_or_create(world.config().actor_to_decomposed(aabb.center()).0);
+        let chunk = get_collider_chunk_or_insert(world, chunk);
+        let entity = self.entity();
+
+        self.grid = world
+            .try_map_entity::<WorldColliders>()
+            .map(|mut colliders| colliders.grid.insert(entity, aabb, layers.membership));
+
+        chunk.register(self, aabb);
+    }
+
+    fn unlink(self: Obj<Self>) {
+        self.chunk.unregister(self);
+
+        if let Some(handle) = self.grid {
+            self.chunk
+                .world
+                .map_entity::<WorldColliders>()
+                .grid
+                .remove(handle);
+        }
     }
 }
 
@@ -132,16 +418,26 @@ pub fn sys_add_tracked_collider_to_collider(
         &mut TrackedCollider,
         &mut TileWorld,
         &mut TileChunk,
+        &mut WorldColliders,
         SendsEvent<WorldCreatedChunk>,
     )>,
-    mut query: Query<(Entity, &Collider, &InsideWorld), Added<Collider>>,
+    mut query: Query<(Entity, &Collider, &InsideWorld, Option<&CollisionLayers>), Added<Collider>>,
 ) {
     rand.provide(|| {
-        for (entity, &Collider(aabb), &InsideWorld(world)) in query.iter_mut() {
+        for (entity, &Collider(aabb), &InsideWorld(world), layers) in query.iter_mut() {
             let chunk = world.chunk_or_create(world.config().actor_to_decomposed(aabb.center()).0);
-            let chunk = get_collider_chunk_or_insert(world, chunk.entity());
-
-            let obj = entity.insert(TrackedCollider { chunk, index: 0 });
+            let chunk = get_collider_chunk_or_insert(world, chunk);
+            let layers = layers.copied().unwrap_or_default();
+
+            let grid = world
+                .try_map_entity::<WorldColliders>()
+                .map(|mut colliders| colliders.grid.insert(entity, aabb, layers.membership));
+
+            let obj = entity.insert(TrackedCollider {
+                chunk,
+                index: 0,
+                grid,
+            });
             chunk.register(obj, aabb);
         }
     });
@@ -153,45 +449,14 @@ pub fn sys_move_tracked_colliders(
         &mut TrackedCollider,
         &mut TileWorld,
         &mut TileChunk,
+        &mut WorldColliders,
         SendsEvent<WorldCreatedChunk>,
     )>,
     mut query: Query<(&Collider, &ObjOwner<TrackedCollider>), Changed<Collider>>,
 ) {
     rand.provide(|| {
         for (&Collider(aabb), &ObjOwner(tracked)) in query.iter_mut() {
-            // Ensure that we moved to a new chunk
-            let old_chunk = tracked.chunk;
-            let config = old_chunk.config;
-            let world = old_chunk.world;
-            let old_pos = old_chunk.pos;
-
-            let new_pos_world = aabb.center();
-            let new_pos = config.actor_to_decomposed(new_pos_world).0;
-
-            if new_pos == old_pos {
-                old_chunk.deref_mut().aabbs[tracked.index] = aabb;
-            } else {
-                // Remove from the previous chunk
-                old_chunk.unregister(tracked);
-
-                // Move them to a new chunk
-                let new_chunk = world.chunk_or_create(new_pos).entity();
-                let new_chunk = get_collider_chunk_or_insert(world, new_chunk);
-
-                new_chunk.register(tracked, aabb);
-            }
-        }
-    });
-}
-
-pub fn sys_remove_tracked_collider(
-    mut removed: RemovedComponents<ObjOwner<TrackedCollider>>,
-    mut rand: RandomAccess<(&mut TrackedColliderChunk, &mut TrackedCollider)>,
-) {
-    rand.provide(|| {
-        for collider in removed.read() {
-            let collider = collider.get::<TrackedCollider>();
-            collider.chunk.unregister(collider);
+            tracked.move_to(aabb);
         }
     });
 }
@@ -199,28 +464,26 @@ pub fn sys_remove_tracked_collider(
 pub fn sys_add_collider_to_new_chunk(
     mut events: EventReader<WorldCreatedChunk>,
     mut rand: RandomAccess<(&TileWorld, &TileChunk, &mut TrackedColliderChunk)>,
-    query: Query<(&ObjOwner<TileWorld>,)>,
+    query: Query<(&ObjOwner<TileWorld>, &ObjOwner<WorldColliders>)>,
 ) {
     rand.provide(|| {
         let events = events.read().filter(|e| query.contains(e.world));
 
         for &WorldCreatedChunk { world, chunk } in events {
-            get_collider_chunk_or_insert(world.get::<TileWorld>(), chunk);
+            get_collider_chunk_or_insert(world.get::<TileWorld>(), chunk.get::<TileChunk>());
         }
     });
 }
 
 pub fn get_collider_chunk_or_insert(
     world: Obj<TileWorld>,
-    chunk: Entity,
+    chunk: Obj<TileChunk>,
 ) -> Obj<TrackedColliderChunk> {
-    chunk.try_get::<TrackedColliderChunk>().unwrap_or_else(|| {
-        chunk.insert(TrackedColliderChunk {
-            world,
-            pos: chunk.get::<TileChunk>().pos(),
-            config: world.config(),
-            aabbs: Vec::new(),
-            handles: Vec::new(),
-        })
+    chunk.entity().get_or_insert_with(|| TrackedColliderChunk {
+        world,
+        pos: chunk.pos(),
+        config: world.config(),
+        aabbs: Vec::new(),
+        handles: Vec::new(),
     })
 }
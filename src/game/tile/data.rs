@@ -1,4 +1,4 @@
-use std::ops::ControlFlow;
+use std::{borrow::Cow, ops::ControlFlow};
 
 use bevy_ecs::{entity::Entity, event::Event, removal_detection::RemovedComponents};
 use macroquad::math::{IVec2, Vec2};
@@ -12,15 +12,18 @@ use crate::{
         scalar::ilerp_f32,
     },
     random_component, random_event,
-    util::arena::{send_event, spawn_entity, Obj, ObjOwner, RandomAccess, RandomEntityExt},
+    util::arena::{
+        despawn_entity, send_event, spawn_entities_batch, spawn_entity, Obj, ObjOwner,
+        RandomAccess, RandomEntityExt,
+    },
 };
 
-use super::material::MaterialId;
+use super::{liquid::MAX_FILL, material::MaterialId, tile_entity::spawn_tile_entity};
 
 // === Definition === //
 
-random_component!(TileWorld, TileChunk);
-random_event!(WorldCreatedChunk);
+random_component!(TileWorld, TileChunk, TileLayers);
+random_event!(WorldCreatedChunk, TileChanged);
 
 #[derive(Event)]
 pub struct WorldCreatedChunk {
@@ -28,6 +31,85 @@ pub struct WorldCreatedChunk {
     pub chunk: Entity,
 }
 
+/// Sent whenever [`TileChunk::set_tile`] changes the material of a tile in a generated chunk.
+/// Wholesale replacements through [`TileChunk::raw_tiles_mut`] and the initial generation of a
+/// chunk don't send this event. Consumers which need to see every change should use the chunk's
+/// [dirty regions](TileChunk::take_dirty) instead.
+#[derive(Debug, Event)]
+pub struct TileChanged {
+    pub world: Obj<TileWorld>,
+    pub chunk: Obj<TileChunk>,
+
+    /// The position of the tile relative to the chunk's origin.
+    pub pos: IVec2,
+    pub old: MaterialId,
+    pub new: MaterialId,
+}
+
+impl TileChanged {
+    pub fn world_pos(&self) -> IVec2 {
+        self.chunk.pos() * TileLayerConfig::CHUNK_EDGE + self.pos
+    }
+}
+
+// === WorldBounds === //
+
+/// The region of a [`TileWorld`] in which actors may exist, in inclusive tile coordinates. Edges
+/// which are `None` extend infinitely.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct WorldBounds {
+    pub min_x: Option<i32>,
+    pub max_x: Option<i32>,
+    pub min_y: Option<i32>,
+    pub max_y: Option<i32>,
+}
+
+impl WorldBounds {
+    pub const INFINITE: Self = Self {
+        min_x: None,
+        max_x: None,
+        min_y: None,
+        max_y: None,
+    };
+
+    pub fn is_infinite(&self) -> bool {
+        *self == Self::INFINITE
+    }
+
+    pub fn contains_tile(&self, IVec2 { x, y }: IVec2) -> bool {
+        self.min_x.map_or(true, |min| min <= x)
+            && self.max_x.map_or(true, |max| x <= max)
+            && self.min_y.map_or(true, |min| min <= y)
+            && self.max_y.map_or(true, |max| y <= max)
+    }
+
+    /// The bounds in actor space. Infinite edges lie at infinity.
+    pub fn actor_rect(&self, config: TileLayerConfig) -> Aabb {
+        let min = |edge: Option<i32>| edge.map_or(f32::NEG_INFINITY, |v| v as f32 * config.size);
+        let max = |edge: Option<i32>| edge.map_or(f32::INFINITY, |v| (v + 1) as f32 * config.size);
+
+        Aabb {
+            min: Vec2::new(min(self.min_x), min(self.min_y)),
+            max: Vec2::new(max(self.max_x), max(self.max_y)),
+        }
+    }
+
+    /// The chunks overlapping the bounds in chunk coordinates, with an exclusive `max`.
+    pub fn chunk_rect(&self) -> AabbI {
+        let chunk = |edge: Option<i32>, infinite: i32| {
+            edge.map_or(infinite, |v| v.div_euclid(TileLayerConfig::CHUNK_EDGE))
+        };
+
+        AabbI {
+            min: IVec2::new(chunk(self.min_x, i32::MIN), chunk(self.min_y, i32::MIN)),
+            max: IVec2::new(
+                chunk(self.max_x, i32::MAX - 1),
+                chunk(self.max_y, i32::MAX - 1),
+            ) + IVec2::ONE,
+        }
+    }
+}
+
 // === TileLayerConfig === //
 
 #[derive(Debug, Copy, Clone)]
@@ -152,27 +234,64 @@ impl TileLayerConfig {
         intersections
     }
 
-    pub fn step_ray_tiles<B>(
+    /// Visits every tile boundary crossed by the segment from `src` to `dst` in order using the
+    /// Amanatides & Woo traversal. Unlike [`step_ray`](Self::step_ray), this never allocates and
+    /// walks arbitrarily long rays in a single pass.
+    pub fn traverse_ray<B>(
         &self,
         src: Vec2,
         dst: Vec2,
-        mut f: impl FnMut(IVec2) -> ControlFlow<B>,
+        mut f: impl FnMut(RayIntersection) -> ControlFlow<B>,
     ) -> ControlFlow<B> {
-        let mut origin = src;
-        let mut length = (dst - src).length();
-        let delta = (dst - src) / length;
-
-        if !delta.is_nan() {
-            while length > 0. {
-                let step_size = length.min(self.size);
-                for isect in self.step_ray(origin, delta * step_size) {
-                    f(isect.entered_tile)?;
-                }
-                length -= step_size;
-                origin += delta * step_size;
-            }
+        let delta = dst - src;
+        let length = delta.length();
+        let mut tile = self.actor_to_tile(src);
+
+        // Count the crossings up-front so that rounding errors can never make us overshoot.
+        let dst_tile = self.actor_to_tile(dst);
+        let mut remaining = (dst_tile - tile).abs().to_array();
+
+        let faces =
+            Axis2::AXES.map(|axis| TileFace::compose(axis, Sign::of_biased(delta.get_axis(axis))));
+
+        // The ray parameter at which we cross the next boundary on each axis and the parameter
+        // between two consecutive boundaries on that axis.
+        let t_step = Axis2::AXES.map(|axis| self.size / delta.get_axis(axis).abs());
+        let mut t_next = Axis2::AXES.map(|axis| {
+            let boundary = self.tile_edge_line(tile, faces[axis as usize]).norm;
+            (boundary - src.get_axis(axis)) / delta.get_axis(axis)
+        });
+
+        while remaining != [0, 0] {
+            let axis = if remaining[1] == 0 || (remaining[0] > 0 && t_next[0] < t_next[1]) {
+                0
+            } else {
+                1
+            };
+
+            let t = t_next[axis];
+            t_next[axis] += t_step[axis];
+            remaining[axis] -= 1;
+            tile += faces[axis].as_ivec();
+
+            f(RayIntersection {
+                face: faces[axis],
+                entered_tile: tile,
+                isect_pos: src + delta * t,
+                dist: length * t,
+            })?;
         }
 
+        ControlFlow::Continue(())
+    }
+
+    pub fn step_ray_tiles<B>(
+        &self,
+        src: Vec2,
+        dst: Vec2,
+        mut f: impl FnMut(IVec2) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.traverse_ray(src, dst, |isect| f(isect.entered_tile))?;
         f(self.actor_to_tile(dst))?;
 
         ControlFlow::Continue(())
@@ -192,6 +311,7 @@ pub struct RayIntersection {
 #[derive(Debug)]
 pub struct TileWorld {
     config: TileLayerConfig,
+    bounds: WorldBounds,
     chunks: FxHashMap<IVec2, Obj<TileChunk>>,
 }
 
@@ -199,10 +319,16 @@ impl TileWorld {
     pub fn new(config: TileLayerConfig) -> Self {
         Self {
             config,
+            bounds: WorldBounds::INFINITE,
             chunks: FxHashMap::default(),
         }
     }
 
+    pub fn with_bounds(mut self, bounds: WorldBounds) -> Self {
+        self.bounds = bounds;
+        self
+    }
+
     fn insert_chunk(mut self: Obj<Self>, pos: IVec2, mut chunk: Obj<TileChunk>) {
         chunk.world = Some(self);
         chunk.pos = pos;
@@ -223,6 +349,14 @@ impl TileWorld {
         self.config
     }
 
+    pub fn bounds(&self) -> WorldBounds {
+        self.bounds
+    }
+
+    pub fn set_bounds(&mut self, bounds: WorldBounds) {
+        self.bounds = bounds;
+    }
+
     pub fn chunk_or_create(self: Obj<Self>, pos: IVec2) -> Obj<TileChunk> {
         if let Some(&chunk) = self.chunks.get(&pos) {
             return chunk;
@@ -238,6 +372,38 @@ impl TileWorld {
         chunk_obj
     }
 
+    /// Creates every chunk in `positions` which doesn't exist yet. The new chunks are spawned in a
+    /// single batch, making this cheaper than repeated calls to [`chunk_or_create`] when loading
+    /// large regions. `positions` must not contain duplicates.
+    ///
+    /// [`chunk_or_create`]: Self::chunk_or_create
+    pub fn create_chunks(self: Obj<Self>, positions: impl IntoIterator<Item = IVec2>) {
+        let missing = positions
+            .into_iter()
+            .filter(|pos| !self.chunks.contains_key(pos))
+            .collect::<Vec<_>>();
+
+        if missing.is_empty() {
+            return;
+        }
+
+        let chunks = spawn_entities_batch(missing.iter().map(|_| ()));
+        let chunk_objs =
+            Obj::insert_batch(chunks.iter().map(|&chunk| (chunk, TileChunk::default())));
+
+        for ((pos, chunk), chunk_obj) in missing.into_iter().zip(chunks).zip(chunk_objs) {
+            self.insert_chunk(pos, chunk_obj);
+            send_event(WorldCreatedChunk {
+                world: self.entity(),
+                chunk,
+            });
+        }
+    }
+
+    pub fn chunk(&self, pos: IVec2) -> Option<Obj<TileChunk>> {
+        self.chunks.get(&pos).copied()
+    }
+
     pub fn tile(&self, pos: IVec2) -> MaterialId {
         let (chunk, block) = TileLayerConfig::decompose_world_pos(pos);
         self.chunks
@@ -249,16 +415,285 @@ impl TileWorld {
         let (chunk, block) = TileLayerConfig::decompose_world_pos(pos);
         self.chunk_or_create(chunk).set_tile(block, data);
     }
+
+    /// Returns the tile entity owned by the tile at `pos`, if any.
+    pub fn tile_entity(&self, pos: IVec2) -> Option<Entity> {
+        let (chunk, block) = TileLayerConfig::decompose_world_pos(pos);
+        self.chunks.get(&chunk)?.tile_entity(block)
+    }
+
+    /// Returns the fill level of the liquid tile at `pos`. Tiles in missing chunks are full.
+    pub fn fill_level(&self, pos: IVec2) -> u8 {
+        let (chunk, block) = TileLayerConfig::decompose_world_pos(pos);
+        self.chunks
+            .get(&chunk)
+            .map_or(MAX_FILL, |chunk| chunk.fill_level(block))
+    }
+
+    /// Iterates over every tile overlapping or touching the actor-space `aabb` along with its
+    /// material. See [`query_materials_in_tiles`](Self::query_materials_in_tiles) for details.
+    pub fn query_materials_in(&self, aabb: Aabb) -> impl Iterator<Item = (IVec2, MaterialId)> + '_ {
+        self.query_materials_in_tiles(self.config.actor_aabb_to_tile(aabb).inclusive())
+    }
+
+    /// Iterates over every tile in the tile-space rect `tiles` along with its material. Tiles in
+    /// missing chunks are reported as air. Tiles are visited one chunk at a time so that each chunk
+    /// is only looked up once.
+    pub fn query_materials_in_tiles(
+        &self,
+        tiles: AabbI,
+    ) -> impl Iterator<Item = (IVec2, MaterialId)> + '_ {
+        let tiles = tiles.normalized();
+
+        // An empty rect must not produce a reversed chunk range since iteration would flip it.
+        let first = TileLayerConfig::decompose_world_pos(tiles.min).0;
+        let last = TileLayerConfig::decompose_world_pos(tiles.max - IVec2::ONE).0;
+        let chunks = AabbI {
+            min: first,
+            max: (last + IVec2::ONE).max(first),
+        };
+
+        chunks.iter().flat_map(move |chunk_pos| {
+            let chunk = self.chunk(chunk_pos);
+            let origin = chunk_pos * TileLayerConfig::CHUNK_EDGE;
+            // Edge chunks only overlap part of the rect so we clamp it to the chunk's own tiles.
+            let local = tiles.translated(-origin).intersection(TileChunk::BOUNDS);
+
+            local.iter().map(move |pos| {
+                let material = chunk.map_or(MaterialId::AIR, |chunk| chunk.tile(pos));
+                (origin + pos, material)
+            })
+        })
+    }
+
+    /// Iterates over every tile whose center lies within `radius` of the actor-space `center`
+    /// along with its material.
+    pub fn query_materials_in_circle(
+        &self,
+        center: Vec2,
+        radius: f32,
+    ) -> impl Iterator<Item = (IVec2, MaterialId)> + '_ {
+        let config = self.config;
+        let aabb = Aabb::new_centered(center, Vec2::splat(radius * 2.));
+
+        self.query_materials_in(aabb).filter(move |&(pos, _)| {
+            config
+                .tile_to_actor_rect(pos)
+                .center()
+                .distance_squared(center)
+                <= radius * radius
+        })
+    }
+
+    /// Counts the tiles overlapping or touching the actor-space `aabb` whose material is accepted
+    /// by `filter`.
+    pub fn count_materials_in(
+        &self,
+        aabb: Aabb,
+        mut filter: impl FnMut(MaterialId) -> bool,
+    ) -> usize {
+        self.query_materials_in(aabb)
+            .filter(|&(_, material)| filter(material))
+            .count()
+    }
+
+    /// Counts the tiles of `material` overlapping or touching the actor-space `aabb`.
+    pub fn count_material_in(&self, aabb: Aabb, material: MaterialId) -> usize {
+        self.count_materials_in(aabb, |other| other == material)
+    }
+
+    /// Determines whether any tile of `material` overlaps or touches the actor-space `aabb`.
+    pub fn contains_material_in(&self, aabb: Aabb, material: MaterialId) -> bool {
+        self.query_materials_in(aabb)
+            .any(|(_, other)| other == material)
+    }
+
+    /// Checks that every chunk in the world agrees with it about the chunk's position and that
+    /// their neighbor links are symmetric. Mismatches are described in `problems`.
+    pub fn check_links(self: Obj<Self>, problems: &mut Vec<String>) {
+        for (&pos, &chunk) in &self.chunks {
+            if !chunk.is_alive() {
+                problems.push(format!("{self:?} maps {pos} to dead chunk {chunk:?}"));
+                continue;
+            }
+
+            if chunk.world != Some(self) {
+                problems.push(format!(
+                    "{chunk:?} at {pos} is in {self:?} but points to {:?}",
+                    chunk.world,
+                ));
+            }
+
+            if chunk.pos != pos {
+                problems.push(format!(
+                    "{chunk:?} is mapped to {pos} but thinks it is at {}",
+                    chunk.pos,
+                ));
+            }
+
+            for face in TileFace::VARIANTS {
+                let expected = self.chunks.get(&(pos + face.as_ivec())).copied();
+                let actual = chunk.neighbors[face as usize];
+
+                if actual != expected {
+                    problems.push(format!(
+                        "{chunk:?} at {pos} has {face:?} neighbor {actual:?} but the world has \
+                         {expected:?}",
+                    ));
+                }
+
+                let Some(neighbor) = actual.filter(|neighbor| neighbor.is_alive()) else {
+                    continue;
+                };
+
+                let back = neighbor.neighbors[face.invert() as usize];
+                if back != Some(chunk) {
+                    problems.push(format!(
+                        "{chunk:?} at {pos} has {face:?} neighbor {neighbor:?} which points back \
+                         to {back:?}",
+                    ));
+                }
+            }
+        }
+    }
+
+    pub fn chunks(&self) -> impl ExactSizeIterator<Item = (IVec2, Obj<TileChunk>)> + '_ {
+        self.chunks.iter().map(|(&pos, &chunk)| (pos, chunk))
+    }
+}
+
+// === TileLayers === //
+
+/// An ordered set of [`TileWorld`] layers that make up a single visual world. Each layer has its own
+/// [`TileLayerConfig`] and chunk set. Only the layer referenced by the world's `KinematicApi` takes
+/// part in collisions.
+#[derive(Debug)]
+pub struct TileLayers {
+    layers: Vec<(i32, Obj<TileWorld>)>,
+}
+
+impl TileLayers {
+    pub const BACKGROUND: i32 = -1;
+    pub const FOREGROUND: i32 = 0;
+
+    pub fn new(foreground: Obj<TileWorld>) -> Self {
+        Self {
+            layers: vec![(Self::FOREGROUND, foreground)],
+        }
+    }
+
+    pub fn insert(&mut self, depth: i32, layer: Obj<TileWorld>) {
+        let index = self.layers.partition_point(|&(other, _)| other <= depth);
+        self.layers.insert(index, (depth, layer));
+    }
+
+    pub fn remove(&mut self, layer: Obj<TileWorld>) {
+        self.layers.retain(|&(_, other)| other != layer);
+    }
+
+    /// Iterates through every layer from back to front.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = Obj<TileWorld>> + '_ {
+        self.layers.iter().map(|&(_, layer)| layer)
+    }
 }
 
 // === TileChunk === //
 
+/// The consumers of a chunk's dirty regions. Each of them keeps track of its own region so that
+/// they can catch up independently of each other.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum ChunkDirtyKind {
+    Lighting,
+    Render,
+    Colliders,
+
+    /// Saves, which also count newly explored tiles as changes.
+    Save,
+}
+
+impl ChunkDirtyKind {
+    pub const COUNT: usize = 4;
+}
+
+type RawTiles = [u16; TileLayerConfig::CHUNK_AREA as usize];
+
+/// A bit for every tile of a chunk, indexed like the chunk's tiles.
+pub type ExploredBits = [u64; TileLayerConfig::CHUNK_AREA as usize / 64];
+
+/// The fill level of every tile of a chunk, indexed like the chunk's tiles.
+pub type FillLevels = [u8; TileLayerConfig::CHUNK_AREA as usize];
+
+/// The tiles of a chunk, stored verbatim while the chunk is hot and run-length encoded while it
+/// is cold.
+#[derive(Debug, Clone)]
+enum ChunkTiles {
+    Hot(Box<RawTiles>),
+
+    /// Runs of identical tiles as `(end, tile)` pairs, where `end` is the index one past the run's
+    /// last tile. Runs are sorted by `end` so that tiles can be found with a binary search.
+    Cold(Box<[(u16, u16)]>),
+}
+
+impl ChunkTiles {
+    fn get(&self, index: usize) -> u16 {
+        match self {
+            ChunkTiles::Hot(tiles) => tiles[index],
+            ChunkTiles::Cold(runs) => {
+                let run = runs.partition_point(|&(end, _)| end as usize <= index);
+                runs[run].1
+            }
+        }
+    }
+
+    fn compress(tiles: &RawTiles) -> Box<[(u16, u16)]> {
+        let mut runs = Vec::<(u16, u16)>::new();
+
+        for (index, &tile) in tiles.iter().enumerate() {
+            match runs.last_mut() {
+                Some((end, last)) if *last == tile => *end = index as u16 + 1,
+                _ => runs.push((index as u16 + 1, tile)),
+            }
+        }
+
+        runs.into_boxed_slice()
+    }
+
+    fn decompress(runs: &[(u16, u16)]) -> Box<RawTiles> {
+        let mut tiles = Box::new([0; TileLayerConfig::CHUNK_AREA as usize]);
+        let mut start = 0;
+
+        for &(end, tile) in runs {
+            tiles[start..end as usize].fill(tile);
+            start = end as usize;
+        }
+
+        tiles
+    }
+}
+
 #[derive(Debug)]
 pub struct TileChunk {
     world: Option<Obj<TileWorld>>,
     neighbors: [Option<Obj<TileChunk>>; 4],
     pos: IVec2,
-    tiles: Box<[u16; TileLayerConfig::CHUNK_AREA as usize]>,
+    generated: bool,
+    version: u32,
+    tiles: ChunkTiles,
+
+    /// The chunk-local bounds of the tiles modified since each [`ChunkDirtyKind`] last took them.
+    dirty: [Option<AabbI>; ChunkDirtyKind::COUNT],
+
+    /// The fill level of every liquid tile, only allocated once some tile becomes partially
+    /// filled.
+    fill_levels: Option<Box<FillLevels>>,
+
+    /// The tile entities owned by the chunk's tiles, keyed by their chunk-local position.
+    tile_entities: FxHashMap<IVec2, Entity>,
+
+    /// The tiles which players have seen at some point. See [`WorldVisibility`].
+    ///
+    /// [`WorldVisibility`]: super::visibility::WorldVisibility
+    explored: ExploredBits,
 }
 
 impl Default for TileChunk {
@@ -267,22 +702,341 @@ impl Default for TileChunk {
             world: None,
             neighbors: [None; 4],
             pos: IVec2::ZERO,
-            tiles: Box::new([0; TileLayerConfig::CHUNK_AREA as usize]),
+            generated: false,
+            version: 1,
+            tiles: ChunkTiles::Hot(Box::new([0; TileLayerConfig::CHUNK_AREA as usize])),
+            dirty: [Some(TileChunk::BOUNDS); ChunkDirtyKind::COUNT],
+            fill_levels: None,
+            tile_entities: FxHashMap::default(),
+            explored: [0; TileLayerConfig::CHUNK_AREA as usize / 64],
         }
     }
 }
 
 impl TileChunk {
+    /// The chunk-local bounds of every tile in a chunk.
+    pub const BOUNDS: AabbI = AabbI::new(
+        0,
+        0,
+        TileLayerConfig::CHUNK_EDGE,
+        TileLayerConfig::CHUNK_EDGE,
+    );
+
     pub fn pos(&self) -> IVec2 {
         self.pos
     }
 
     pub fn tile(&self, pos: IVec2) -> MaterialId {
-        MaterialId(self.tiles[TileLayerConfig::to_tile_index(pos) as usize])
+        debug_assert!(
+            Self::BOUNDS.contains(pos),
+            "{pos} lies outside of the chunk"
+        );
+        MaterialId(self.tiles.get(TileLayerConfig::to_tile_index(pos) as usize))
+    }
+
+    pub fn neighbor(&self, face: TileFace) -> Option<Obj<TileChunk>> {
+        self.neighbors[face as usize]
+    }
+
+    /// Looks up a tile by its position relative to the chunk's origin, following neighbor links for
+    /// positions in any of the eight chunks around it. This is cheaper than going through the
+    /// world's chunk map when sampling the tiles surrounding a tile. Tiles of missing chunks are air.
+    pub fn tile_around(&self, pos: IVec2) -> MaterialId {
+        let (offset, block) = TileLayerConfig::decompose_world_pos(pos);
+        debug_assert!(offset.x.abs() <= 1 && offset.y.abs() <= 1);
+
+        let horizontal = TileFace::VARIANTS[..2]
+            .iter()
+            .copied()
+            .find(|face| face.as_ivec().x == offset.x);
+        let vertical = TileFace::VARIANTS[2..]
+            .iter()
+            .copied()
+            .find(|face| face.as_ivec().y == offset.y);
+
+        let chunk = match (horizontal, vertical) {
+            (None, None) => return self.tile(block),
+            (Some(face), None) | (None, Some(face)) => self.neighbor(face),
+            // Diagonal chunks can be reached through either of the chunks they share an edge with.
+            (Some(horizontal), Some(vertical)) => self
+                .neighbor(horizontal)
+                .and_then(|chunk| chunk.neighbor(vertical))
+                .or_else(|| {
+                    self.neighbor(vertical)
+                        .and_then(|chunk| chunk.neighbor(horizontal))
+                }),
+        };
+
+        chunk.map_or(MaterialId::AIR, |chunk| chunk.tile(block))
     }
 
+    /// Replaces the tile at `pos`, resetting its fill level to [`MAX_FILL`]. If the material
+    /// changes, the old tile's tile entity is despawned, a new one is spawned if the new material
+    /// calls for it, and a [`TileChanged`] event is sent.
     pub fn set_tile(&mut self, pos: IVec2, data: MaterialId) {
-        self.tiles[TileLayerConfig::to_tile_index(pos) as usize] = data.0;
+        let index = TileLayerConfig::to_tile_index(pos) as usize;
+        let old = MaterialId(self.tiles.get(index));
+        if old == data {
+            return;
+        }
+
+        self.hot_tiles()[index] = data.0;
+        self.replace_tile_entity(pos, data);
+        self.send_tile_changed(pos, old, data);
+
+        if let Some(fill_levels) = &mut self.fill_levels {
+            fill_levels[index] = MAX_FILL;
+        }
+
+        self.mark_dirty(AabbI::new_sized(pos, IVec2::ONE));
+        self.version = self.version.wrapping_add(1);
+    }
+
+    fn send_tile_changed(&self, pos: IVec2, old: MaterialId, new: MaterialId) {
+        // The tiles of chunks still being generated aren't changes anyone could have observed.
+        if !self.generated {
+            return;
+        }
+
+        let Some(world) = self.world else {
+            return;
+        };
+
+        let Some(chunk) = world.chunk(self.pos) else {
+            return;
+        };
+
+        send_event(TileChanged {
+            world,
+            chunk,
+            pos,
+            old,
+            new,
+        });
+    }
+
+    pub fn tile_entity(&self, pos: IVec2) -> Option<Entity> {
+        self.tile_entities.get(&pos).copied()
+    }
+
+    /// Spawns the tile entities of every tile which should have one but doesn't. This must be
+    /// called after replacing the chunk's tiles through [`raw_tiles_mut`](Self::raw_tiles_mut).
+    pub fn sync_tile_entities(&mut self) {
+        for y in 0..TileLayerConfig::CHUNK_EDGE {
+            for x in 0..TileLayerConfig::CHUNK_EDGE {
+                let pos = IVec2::new(x, y);
+                if !self.tile_entities.contains_key(&pos) {
+                    self.replace_tile_entity(pos, self.tile(pos));
+                }
+            }
+        }
+    }
+
+    fn replace_tile_entity(&mut self, pos: IVec2, material: MaterialId) {
+        if let Some(entity) = self.tile_entities.remove(&pos) {
+            despawn_entity(entity);
+        }
+
+        let Some(world) = self.world else {
+            return;
+        };
+
+        let world_pos = self.pos * TileLayerConfig::CHUNK_EDGE + pos;
+        if let Some(entity) = spawn_tile_entity(world, world_pos, material) {
+            self.tile_entities.insert(pos, entity);
+        }
+    }
+
+    fn despawn_tile_entities(&mut self) {
+        for (_, entity) in self.tile_entities.drain() {
+            despawn_entity(entity);
+        }
+    }
+
+    /// Returns how full the liquid tile at `pos` is, from 1 to [`MAX_FILL`]. The level of non-liquid
+    /// tiles is meaningless.
+    pub fn fill_level(&self, pos: IVec2) -> u8 {
+        self.fill_levels.as_ref().map_or(MAX_FILL, |fill_levels| {
+            fill_levels[TileLayerConfig::to_tile_index(pos) as usize]
+        })
+    }
+
+    pub fn set_fill_level(&mut self, pos: IVec2, level: u8) {
+        let fill_levels = self
+            .fill_levels
+            .get_or_insert_with(|| Box::new([MAX_FILL; TileLayerConfig::CHUNK_AREA as usize]));
+
+        fill_levels[TileLayerConfig::to_tile_index(pos) as usize] = level;
+        self.mark_dirty(AabbI::new_sized(pos, IVec2::ONE));
+        self.version = self.version.wrapping_add(1);
+    }
+
+    /// The fill level of every tile, or `None` if every liquid tile in the chunk is full.
+    pub fn fill_levels(&self) -> Option<&FillLevels> {
+        self.fill_levels.as_deref()
+    }
+
+    /// Replaces the fill level of every tile, e.g. after replacing the chunk's tiles through
+    /// [`raw_tiles_mut`](Self::raw_tiles_mut).
+    pub fn set_fill_levels(&mut self, fill_levels: Option<Box<FillLevels>>) {
+        self.fill_levels = fill_levels;
+        self.mark_dirty(Self::BOUNDS);
+        self.version = self.version.wrapping_add(1);
+    }
+
+    /// Returns the chunk's tiles, decompressing a copy of them if the chunk is cold.
+    pub fn raw_tiles(&self) -> Cow<'_, RawTiles> {
+        match &self.tiles {
+            ChunkTiles::Hot(tiles) => Cow::Borrowed(tiles),
+            ChunkTiles::Cold(runs) => Cow::Owned(*ChunkTiles::decompress(runs)),
+        }
+    }
+
+    /// Gives direct access to the chunk's tiles. Since the tiles may be replaced wholesale, every
+    /// liquid tile is reset to being full and every tile entity is despawned. Use
+    /// [`sync_tile_entities`](Self::sync_tile_entities) to respawn them.
+    pub fn raw_tiles_mut(&mut self) -> &mut RawTiles {
+        self.mark_dirty(Self::BOUNDS);
+        self.version = self.version.wrapping_add(1);
+        self.fill_levels = None;
+        self.despawn_tile_entities();
+        self.hot_tiles()
+    }
+
+    /// Whether the chunk's tiles are currently [compressed](Self::freeze).
+    pub fn is_cold(&self) -> bool {
+        matches!(self.tiles, ChunkTiles::Cold(_))
+    }
+
+    /// Run-length encodes the chunk's tiles to save memory while it's far away from the action.
+    /// Tiles can still be read from cold chunks, albeit more slowly, and modifying them
+    /// transparently [thaws](Self::thaw) the chunk. This doesn't count as a change to the chunk's
+    /// contents so its [version](Self::version) stays the same.
+    pub fn freeze(&mut self) {
+        if let ChunkTiles::Hot(tiles) = &self.tiles {
+            self.tiles = ChunkTiles::Cold(ChunkTiles::compress(tiles));
+        }
+    }
+
+    /// Decompresses the tiles of a cold chunk so that they can be accessed at full speed again.
+    pub fn thaw(&mut self) {
+        self.hot_tiles();
+    }
+
+    /// The approximate number of bytes used to store the chunk's tiles.
+    pub fn tile_memory(&self) -> usize {
+        match &self.tiles {
+            ChunkTiles::Hot(tiles) => std::mem::size_of_val(&**tiles),
+            ChunkTiles::Cold(runs) => std::mem::size_of_val(&**runs),
+        }
+    }
+
+    fn hot_tiles(&mut self) -> &mut RawTiles {
+        if let ChunkTiles::Cold(runs) = &self.tiles {
+            self.tiles = ChunkTiles::Hot(ChunkTiles::decompress(runs));
+        }
+
+        match &mut self.tiles {
+            ChunkTiles::Hot(tiles) => tiles,
+            ChunkTiles::Cold(_) => unreachable!(),
+        }
+    }
+
+    /// A counter which changes every time the chunk's tiles may have been modified. Caches derived
+    /// from the chunk's contents can compare it against the version they were built from to find
+    /// out whether they're stale.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Marks the chunk-local `region` as modified for every [`ChunkDirtyKind`].
+    pub fn mark_dirty(&mut self, region: AabbI) {
+        for dirty in &mut self.dirty {
+            *dirty = Some(dirty.map_or(region, |dirty| dirty.union(region)));
+        }
+    }
+
+    /// Marks the chunk-local `region` as modified for `kind` alone.
+    pub fn mark_dirty_for(&mut self, kind: ChunkDirtyKind, region: AabbI) {
+        let dirty = &mut self.dirty[kind as usize];
+        *dirty = Some(dirty.map_or(region, |dirty| dirty.union(region)));
+    }
+
+    /// Returns the chunk-local bounds of the tiles modified since the last time this was called for
+    /// `kind`, or `None` if nothing changed. New chunks start out entirely dirty.
+    ///
+    /// The region only covers the modified tiles themselves. Consumers whose output depends on the
+    /// surroundings of a tile, such as lighting or auto-tiling, must expand it accordingly.
+    pub fn take_dirty(&mut self, kind: ChunkDirtyKind) -> Option<AabbI> {
+        self.dirty[kind as usize].take()
+    }
+
+    pub fn is_dirty(&self, kind: ChunkDirtyKind) -> bool {
+        self.dirty[kind as usize].is_some()
+    }
+
+    /// Whether the chunk's contents have already been produced, either by a world generator or by
+    /// loading them from a save.
+    pub fn is_generated(&self) -> bool {
+        self.generated
+    }
+
+    pub fn mark_generated(&mut self) {
+        self.generated = true;
+    }
+
+    /// Whether the tile at the chunk-local `pos` has ever been seen by a player.
+    pub fn is_explored(&self, pos: IVec2) -> bool {
+        let index = TileLayerConfig::to_tile_index(pos) as usize;
+        self.explored[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    /// Marks the tile at the chunk-local `pos` as explored. Exploration isn't a change to the
+    /// chunk's tiles so this leaves its version alone.
+    pub fn explore(&mut self, pos: IVec2) {
+        let index = TileLayerConfig::to_tile_index(pos) as usize;
+        let bit = 1 << (index % 64);
+
+        if self.explored[index / 64] & bit == 0 {
+            self.explored[index / 64] |= bit;
+            self.mark_dirty_for(ChunkDirtyKind::Save, AabbI::new_sized(pos, IVec2::ONE));
+        }
+    }
+
+    pub fn explored_bits(&self) -> &ExploredBits {
+        &self.explored
+    }
+
+    pub fn set_explored_bits(&mut self, bits: ExploredBits) {
+        self.explored = bits;
+        self.mark_dirty_for(ChunkDirtyKind::Save, Self::BOUNDS);
+    }
+
+    /// Checks that the chunk is registered with the world it points to. Mismatches are described in
+    /// `problems`. See [`TileWorld::check_links`] for the checks on chunks within a world.
+    pub fn check_links(self: Obj<Self>, problems: &mut Vec<String>) {
+        match self.world {
+            Some(world) if !world.is_alive() => {
+                problems.push(format!("{self:?} points to dead world {world:?}"));
+            }
+            Some(world) => {
+                let registered = world.chunks.get(&self.pos).copied();
+                if registered != Some(self) {
+                    problems.push(format!(
+                        "{self:?} thinks it is at {} in {world:?} but the world has {registered:?}",
+                        self.pos,
+                    ));
+                }
+            }
+            None => {
+                if self.neighbors.iter().any(Option::is_some) {
+                    problems.push(format!(
+                        "{self:?} is not in a world but still has neighbors {:?}",
+                        self.neighbors,
+                    ));
+                }
+            }
+        }
     }
 
     fn remove_from_world(mut self: Obj<Self>) {
@@ -292,6 +1046,7 @@ impl TileChunk {
 
         self.world = None;
         world.chunks.remove(&self.pos);
+        self.despawn_tile_entities();
 
         for (face, neighbor) in self.neighbors.into_iter().enumerate() {
             let face = TileFace::VARIANTS[face];
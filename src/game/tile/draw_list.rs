@@ -0,0 +1,338 @@
+use macroquad::{
+    color::Color,
+    math::{IVec2, Vec2},
+};
+use rustc_hash::FxHashMap;
+
+use crate::{
+    game::math::aabb::{Aabb, AabbI},
+    util::{arena::Obj, parallel::par_map},
+};
+
+use super::{
+    autotile::AutoTileLayout,
+    data::{TileLayerConfig, TileWorld},
+    material::MaterialId,
+    render::{AO_STRENGTH, AO_WIDTH},
+};
+
+// === TileStyles === //
+
+/// How a material is drawn. Styles are resolved from the material descriptors on the main thread
+/// so that draw lists can be prepared on worker threads without touching the arenas.
+#[derive(Debug, Clone)]
+pub enum TileStyle {
+    /// Air and materials without a renderer.
+    Invisible,
+
+    /// Fluids are merged across chunks and drawn separately.
+    Fluid,
+
+    Solid(Color),
+
+    Textured {
+        texture: usize,
+        uv: Aabb,
+        tint: Color,
+    },
+
+    AutoTile(AutoTileStyle),
+}
+
+#[derive(Debug, Clone)]
+pub struct AutoTileStyle {
+    pub texture: usize,
+    pub layout: AutoTileLayout,
+
+    /// The UV rect of each of the layout's sprites.
+    pub uvs: Vec<Aabb>,
+    pub tint: Color,
+    pub connect_to_solids: bool,
+}
+
+impl TileStyle {
+    /// Whether the tile hides what's behind it, which determines where ambient occlusion is drawn.
+    pub fn is_opaque(&self) -> bool {
+        matches!(
+            self,
+            TileStyle::Solid(_) | TileStyle::Textured { .. } | TileStyle::AutoTile(_)
+        )
+    }
+}
+
+/// The style of every registered material, indexed by [`MaterialId`].
+#[derive(Debug, Clone, Default)]
+pub struct TileStyles {
+    styles: Vec<TileStyle>,
+}
+
+impl TileStyles {
+    /// Creates the table from the style of every material, in [`MaterialId`] order.
+    pub fn from_styles(styles: Vec<TileStyle>) -> Self {
+        Self { styles }
+    }
+
+    pub fn get(&self, material: MaterialId) -> &TileStyle {
+        self.styles
+            .get(material.0 as usize)
+            .unwrap_or(&TileStyle::Invisible)
+    }
+
+    fn is_opaque(&self, material: MaterialId) -> bool {
+        self.get(material).is_opaque()
+    }
+}
+
+// === LayerSnapshot === //
+
+type RawTiles = [u16; TileLayerConfig::CHUNK_AREA as usize];
+
+/// A copy of the tiles of a layer's chunks which overlap some region, including the ring of chunks
+/// around them so that tiles on the region's edges can look at their neighbors.
+#[derive(Debug, Clone)]
+pub struct LayerSnapshot {
+    config: TileLayerConfig,
+
+    /// The chunks overlapping the region itself, in row-major order.
+    region: Vec<IVec2>,
+    chunks: FxHashMap<IVec2, Box<RawTiles>>,
+}
+
+impl LayerSnapshot {
+    /// Copies the chunks overlapping the given region of tiles. Must be called within a
+    /// [`RandomAccess`](crate::util::arena::RandomAccess) providing the world and its chunks.
+    pub fn capture(world: Obj<TileWorld>, tiles: AabbI) -> Self {
+        let mut snapshot = Self::new(world.config(), tiles);
+
+        for pos in Self::chunk_bounds(tiles).grow(IVec2::ONE).iter() {
+            if let Some(chunk) = world.chunk(pos) {
+                snapshot.chunks.insert(pos, Box::new(*chunk.raw_tiles()));
+            }
+        }
+
+        snapshot
+    }
+
+    /// Creates an empty snapshot of the given region, whose chunks are added with
+    /// [`insert`](Self::insert).
+    pub fn new(config: TileLayerConfig, tiles: AabbI) -> Self {
+        Self {
+            config,
+            region: Self::chunk_bounds(tiles).iter().collect(),
+            chunks: FxHashMap::default(),
+        }
+    }
+
+    pub fn insert(&mut self, pos: IVec2, tiles: Box<RawTiles>) {
+        self.chunks.insert(pos, tiles);
+    }
+
+    fn chunk_bounds(tiles: AabbI) -> AabbI {
+        AabbI {
+            min: TileLayerConfig::decompose_world_pos(tiles.min).0,
+            max: TileLayerConfig::decompose_world_pos(tiles.max - IVec2::ONE).0,
+        }
+        .inclusive()
+    }
+
+    /// Looks up a tile by its world-space tile position. Tiles of chunks which weren't captured are
+    /// air.
+    pub fn tile(&self, pos: IVec2) -> MaterialId {
+        let (chunk, block) = TileLayerConfig::decompose_world_pos(pos);
+        self.chunks.get(&chunk).map_or(MaterialId::AIR, |tiles| {
+            MaterialId(tiles[TileLayerConfig::to_tile_index(block) as usize])
+        })
+    }
+}
+
+// === ChunkDrawList === //
+
+/// The quads drawn for the tiles of a single chunk, in the order they must be submitted.
+#[derive(Debug, Clone, Default)]
+pub struct ChunkDrawList {
+    pub solid: Vec<(Aabb, Color)>,
+
+    /// Textured quads as `(texture, rect, uv, tint)`, with textures given by their index in the
+    /// list passed to [`RenderableWorld::resolve_styles`].
+    ///
+    /// [`RenderableWorld::resolve_styles`]: super::render::RenderableWorld::resolve_styles
+    pub textured: Vec<(usize, Aabb, Aabb, Color)>,
+
+    /// Ambient occlusion gradients as `(corners, colors)`.
+    pub occlusion: Vec<([Vec2; 4], [Color; 4])>,
+}
+
+impl ChunkDrawList {
+    pub fn len(&self) -> usize {
+        self.solid.len() + self.textured.len() + self.occlusion.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The maximum number of tasks preparing draw lists at once.
+pub const MAX_PREPARE_WORKERS: usize = 8;
+
+/// The number of chunks below which draw lists are prepared entirely on the calling thread.
+const PARALLEL_PREPARE_THRESHOLD: usize = 8;
+
+/// Prepares the draw lists of every chunk overlapping `tiles` on the compute task pool,
+/// returning them in the snapshot's chunk order.
+pub fn prepare_draw_lists(
+    snapshot: &LayerSnapshot,
+    styles: &TileStyles,
+    tiles: AabbI,
+) -> Vec<ChunkDrawList> {
+    par_map(
+        &snapshot.region,
+        MAX_PREPARE_WORKERS,
+        PARALLEL_PREPARE_THRESHOLD,
+        |&chunk| prepare_chunk(snapshot, styles, chunk, tiles),
+    )
+}
+
+/// Like [`prepare_draw_lists`] but entirely on the calling thread.
+pub fn prepare_draw_lists_serial(
+    snapshot: &LayerSnapshot,
+    styles: &TileStyles,
+    tiles: AabbI,
+) -> Vec<ChunkDrawList> {
+    snapshot
+        .region
+        .iter()
+        .map(|&chunk| prepare_chunk(snapshot, styles, chunk, tiles))
+        .collect()
+}
+
+/// Builds the draw list of the tiles of `chunk` within `tiles`.
+fn prepare_chunk(
+    snapshot: &LayerSnapshot,
+    styles: &TileStyles,
+    chunk: IVec2,
+    tiles: AabbI,
+) -> ChunkDrawList {
+    let mut list = ChunkDrawList::default();
+
+    // Chunks without tiles still have to shade the edges of their neighbors' solid tiles.
+    let origin = chunk * TileLayerConfig::CHUNK_EDGE;
+    let bounds = AabbI {
+        min: origin,
+        max: origin + IVec2::splat(TileLayerConfig::CHUNK_EDGE),
+    }
+    .intersection(tiles);
+
+    for tile in bounds.iter() {
+        let material = snapshot.tile(tile);
+        let rect = snapshot.config.tile_to_actor_rect(tile);
+
+        match styles.get(material) {
+            TileStyle::Invisible | TileStyle::Fluid => {
+                push_occlusion(&mut list, snapshot, styles, tile);
+            }
+            TileStyle::Solid(color) => list.solid.push((rect, *color)),
+            TileStyle::Textured { texture, uv, tint } => {
+                list.textured.push((*texture, rect, *uv, *tint));
+            }
+            TileStyle::AutoTile(autotile) => {
+                let mask = autotile.layout.sample_mask(|rel| {
+                    let other = snapshot.tile(tile + rel);
+                    other == material || (autotile.connect_to_solids && styles.is_opaque(other))
+                });
+
+                let uv = autotile.uvs[autotile.layout.sprite(mask) as usize];
+                list.textured
+                    .push((autotile.texture, rect, uv, autotile.tint));
+            }
+        }
+    }
+
+    list
+}
+
+/// Shades the parts of a see-through tile which border opaque tiles.
+fn push_occlusion(
+    list: &mut ChunkDrawList,
+    snapshot: &LayerSnapshot,
+    styles: &TileStyles,
+    tile: IVec2,
+) {
+    let config = snapshot.config;
+    let shade = Color::new(0., 0., 0., AO_STRENGTH);
+    let clear = Color::new(0., 0., 0., 0.);
+    let width = config.size * AO_WIDTH;
+
+    let is_solid = |rel: IVec2| styles.is_opaque(snapshot.tile(tile + rel));
+
+    let left = is_solid(IVec2::NEG_X);
+    let right = is_solid(IVec2::X);
+    let top = is_solid(IVec2::NEG_Y);
+    let bottom = is_solid(IVec2::Y);
+
+    let rect = config.tile_to_actor_rect(tile);
+    let mut shade_part = |min: Vec2, size: Vec2, colors: [Color; 4]| {
+        list.occlusion
+            .push((Aabb::new_sized(min, size).corners(), colors));
+    };
+
+    // Shade edges which border solid tiles. Corners are listed clockwise from the top-left.
+    if left {
+        shade_part(
+            rect.min,
+            Vec2::new(width, rect.h()),
+            [shade, clear, clear, shade],
+        );
+    }
+
+    if right {
+        shade_part(
+            Vec2::new(rect.max.x - width, rect.min.y),
+            Vec2::new(width, rect.h()),
+            [clear, shade, shade, clear],
+        );
+    }
+
+    if top {
+        shade_part(
+            rect.min,
+            Vec2::new(rect.w(), width),
+            [shade, shade, clear, clear],
+        );
+    }
+
+    if bottom {
+        shade_part(
+            Vec2::new(rect.min.x, rect.max.y - width),
+            Vec2::new(rect.w(), width),
+            [clear, clear, shade, shade],
+        );
+    }
+
+    // Shade inner corners which only touch a solid tile diagonally.
+    let corner = Vec2::splat(width);
+
+    if !left && !top && is_solid(IVec2::new(-1, -1)) {
+        shade_part(rect.min, corner, [shade, clear, clear, clear]);
+    }
+
+    if !right && !top && is_solid(IVec2::new(1, -1)) {
+        shade_part(
+            Vec2::new(rect.max.x - width, rect.min.y),
+            corner,
+            [clear, shade, clear, clear],
+        );
+    }
+
+    if !right && !bottom && is_solid(IVec2::new(1, 1)) {
+        shade_part(rect.max - corner, corner, [clear, clear, shade, clear]);
+    }
+
+    if !left && !bottom && is_solid(IVec2::new(-1, 1)) {
+        shade_part(
+            Vec2::new(rect.min.x, rect.max.y - width),
+            corner,
+            [clear, clear, clear, shade],
+        );
+    }
+}
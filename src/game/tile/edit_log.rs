@@ -0,0 +1,161 @@
+use bevy_ecs::system::{ResMut, Resource};
+use macroquad::{
+    input::{is_key_down, is_key_pressed, KeyCode},
+    math::IVec2,
+};
+
+use crate::util::arena::{Obj, RandomAccess, SendsEvent};
+
+use super::{
+    data::{TileChanged, TileChunk, TileWorld, WorldCreatedChunk},
+    material::{MaterialId, MaterialRegistry},
+    tile_entity::{TileEntityCreated, TileEntityDescriptor},
+};
+
+// === TileEditLog === //
+
+/// A single tile replaced by an edit.
+#[derive(Debug, Copy, Clone)]
+pub struct TileEdit {
+    pub world: Obj<TileWorld>,
+    pub pos: IVec2,
+    pub old: MaterialId,
+    pub new: MaterialId,
+}
+
+/// A group of edits which are undone and redone together.
+#[derive(Debug, Clone, Default)]
+pub struct TileTransaction {
+    pub edits: Vec<TileEdit>,
+}
+
+/// A history of tile edits which can be undone and redone. Only edits made through the log are
+/// recorded; changes made by the simulation itself, such as flowing liquids, are not.
+///
+/// Edits are grouped into the currently open transaction until it's [committed](Self::commit).
+#[derive(Debug, Default, Resource)]
+pub struct TileEditLog {
+    undo: Vec<TileTransaction>,
+    redo: Vec<TileTransaction>,
+    open: TileTransaction,
+}
+
+impl TileEditLog {
+    /// The maximum number of transactions remembered. The oldest ones are forgotten first.
+    pub const MAX_TRANSACTIONS: usize = 100;
+
+    /// Replaces the tile at `pos` and records the edit.
+    pub fn set_tile(&mut self, world: Obj<TileWorld>, pos: IVec2, material: MaterialId) {
+        let old = world.tile(pos);
+        if old == material {
+            return;
+        }
+
+        world.set_tile(pos, material);
+        self.record(TileEdit {
+            world,
+            pos,
+            old,
+            new: material,
+        });
+    }
+
+    /// Records an edit which has already been applied to the world.
+    pub fn record(&mut self, edit: TileEdit) {
+        self.open.edits.push(edit);
+        self.redo.clear();
+    }
+
+    /// Closes the open transaction so that later edits are undone separately.
+    pub fn commit(&mut self) {
+        if self.open.edits.is_empty() {
+            return;
+        }
+
+        self.undo.push(std::mem::take(&mut self.open));
+
+        if self.undo.len() > Self::MAX_TRANSACTIONS {
+            self.undo.remove(0);
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || !self.open.edits.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Reverts the most recent transaction, committing the open one first. Returns whether there
+    /// was anything to undo.
+    pub fn undo(&mut self) -> bool {
+        self.commit();
+
+        let Some(transaction) = self.undo.pop() else {
+            return false;
+        };
+
+        for edit in transaction.edits.iter().rev() {
+            edit.world.set_tile(edit.pos, edit.old);
+        }
+
+        self.redo.push(transaction);
+        true
+    }
+
+    /// Reapplies the most recently undone transaction. Returns whether there was anything to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(transaction) = self.redo.pop() else {
+            return false;
+        };
+
+        for edit in &transaction.edits {
+            edit.world.set_tile(edit.pos, edit.new);
+        }
+
+        self.undo.push(transaction);
+        true
+    }
+
+    /// Forgets every recorded edit.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.open = TileTransaction::default();
+    }
+}
+
+// === Systems === //
+
+pub fn sys_handle_edit_history_input(
+    mut rand: RandomAccess<(
+        &mut TileWorld,
+        &mut TileChunk,
+        &MaterialRegistry,
+        &TileEntityDescriptor,
+        SendsEvent<TileEntityCreated>,
+        SendsEvent<TileChanged>,
+        SendsEvent<WorldCreatedChunk>,
+    )>,
+    mut log: ResMut<TileEditLog>,
+) {
+    let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+    let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+    if !ctrl {
+        return;
+    }
+
+    let undo = is_key_pressed(KeyCode::Z) && !shift;
+    let redo = is_key_pressed(KeyCode::Y) || (is_key_pressed(KeyCode::Z) && shift);
+
+    rand.provide(|| {
+        if undo && !log.undo() {
+            log::info!("Nothing to undo");
+        }
+
+        if redo && !log.redo() {
+            log::info!("Nothing to redo");
+        }
+    });
+}
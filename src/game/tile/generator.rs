@@ -0,0 +1,610 @@
+use std::fmt;
+
+use bevy_ecs::{
+    component::Component,
+    event::EventReader,
+    query::With,
+    system::{Query, Res},
+};
+use macroquad::math::IVec2;
+
+use crate::{
+    game::{
+        actor::camera::{CameraStack, VirtualCamera},
+        math::{
+            aabb::AabbI,
+            noise::{fbm_1d, fbm_2d, hash_ivec2, hash_unit, value_noise_1d},
+        },
+        rng::RngStream,
+    },
+    random_component,
+    util::arena::{ObjOwner, RandomAccess, RandomEntityExt, SendsEvent},
+};
+
+use super::{
+    data::{TileChanged, TileChunk, TileLayerConfig, TileWorld, WorldCreatedChunk},
+    material::{MaterialId, MaterialRegistry},
+    schematic::Schematic,
+    tile_entity::{TileEntityCreated, TileEntityDescriptor},
+};
+
+random_component!(TileGenerator);
+
+// === WorldGenerator === //
+
+pub trait WorldGenerator: 'static + fmt::Debug + Send + Sync {
+    /// Determines the material of the tile at the given world-space tile position.
+    fn tile(&self, pos: IVec2) -> MaterialId;
+
+    /// Determines the y coordinate of the topmost solid tile in the given column, if this
+    /// generator has a well-defined surface.
+    fn surface_height(&self, x: i32) -> Option<i32> {
+        let _ = x;
+        None
+    }
+
+    /// Replaces the seed of this generator, if it has one.
+    fn reseed(&mut self, seed: u32) {
+        let _ = seed;
+    }
+}
+
+/// A secondary generation step run over an entire chunk after the primary [`WorldGenerator`] has
+/// filled it in. Passes must only depend on the world seed and tile positions so that chunks can be
+/// generated in any order.
+pub trait GenerationPass: 'static + fmt::Debug + Send + Sync {
+    fn apply(&self, primary: &dyn WorldGenerator, buffer: &mut ChunkBuffer);
+
+    /// Replaces the seed of this pass, if it has one.
+    fn reseed(&mut self, seed: u32) {
+        let _ = seed;
+    }
+}
+
+#[derive(Debug)]
+pub struct TileGenerator {
+    generator: Box<dyn WorldGenerator>,
+    passes: Vec<Box<dyn GenerationPass>>,
+    salt: u32,
+}
+
+impl TileGenerator {
+    pub fn new(generator: impl WorldGenerator) -> Self {
+        Self {
+            generator: Box::new(generator),
+            passes: Vec::new(),
+            salt: 0,
+        }
+    }
+
+    pub fn with_pass(mut self, pass: impl GenerationPass) -> Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    /// Derives the seeds of the generator and of its passes from the world seed, so it must come
+    /// after every [`with_pass`](Self::with_pass). The `salt` sets apart the worlds generated from
+    /// the same world seed. Layers which must line up with each other, such as a world and its
+    /// background, share a salt.
+    pub fn with_seed(mut self, world_seed: u64, salt: u32) -> Self {
+        self.salt = salt;
+        self.reseed(world_seed);
+        self
+    }
+
+    /// Derives the seeds of the generator and of its passes from a new world seed. Chunks which
+    /// were already generated keep their tiles until they're [regenerated](Self::regenerate).
+    pub fn reseed(&mut self, world_seed: u64) {
+        let salt = (self.salt as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        let mut stream = RngStream::new(world_seed ^ salt);
+
+        self.generator.reseed(stream.next_u64() as u32);
+        for pass in &mut self.passes {
+            pass.reseed(stream.next_u64() as u32);
+        }
+    }
+
+    /// Determines the y coordinate of the topmost solid tile in the given column according to the
+    /// primary generator, if it has a well-defined surface.
+    pub fn surface_height(&self, x: i32) -> Option<i32> {
+        self.generator.surface_height(x)
+    }
+
+    pub fn generate(&self, chunk: &mut TileChunk) {
+        if chunk.is_generated() {
+            return;
+        }
+
+        self.fill(chunk);
+        chunk.mark_generated();
+    }
+
+    /// Regenerates the chunk from scratch, discarding any tiles placed into it.
+    pub fn regenerate(&self, chunk: &mut TileChunk) {
+        chunk.raw_tiles_mut().fill(MaterialId::AIR.0);
+        self.fill(chunk);
+        chunk.mark_generated();
+    }
+
+    fn fill(&self, chunk: &mut TileChunk) {
+        let mut buffer = ChunkBuffer::new(chunk.pos() * TileLayerConfig::CHUNK_EDGE);
+
+        for pos in buffer.bounds().iter() {
+            buffer.set(pos, self.generator.tile(pos));
+        }
+
+        for pass in &self.passes {
+            pass.apply(&*self.generator, &mut buffer);
+        }
+
+        for (pos, material) in buffer.iter() {
+            let rel = pos - buffer.origin();
+
+            // Tiles may have been placed into the chunk before we got the chance to generate it so
+            // we only ever fill in air.
+            if chunk.tile(rel) != MaterialId::AIR {
+                continue;
+            }
+
+            chunk.set_tile(rel, material);
+        }
+    }
+}
+
+// === ChunkBuffer === //
+
+/// The scratch tiles of a chunk being generated, addressed in world-space tile coordinates.
+#[derive(Debug, Clone)]
+pub struct ChunkBuffer {
+    origin: IVec2,
+    tiles: [MaterialId; TileLayerConfig::CHUNK_AREA as usize],
+}
+
+impl ChunkBuffer {
+    pub fn new(origin: IVec2) -> Self {
+        Self {
+            origin,
+            tiles: [MaterialId::AIR; TileLayerConfig::CHUNK_AREA as usize],
+        }
+    }
+
+    pub fn origin(&self) -> IVec2 {
+        self.origin
+    }
+
+    pub fn bounds(&self) -> AabbI {
+        AabbI::new_sized(self.origin, IVec2::splat(TileLayerConfig::CHUNK_EDGE))
+    }
+
+    pub fn get(&self, pos: IVec2) -> Option<MaterialId> {
+        self.bounds()
+            .contains(pos)
+            .then(|| self.tiles[TileLayerConfig::to_tile_index(pos - self.origin) as usize])
+    }
+
+    /// Iterates over every tile of the chunk alongside its world-space position.
+    pub fn iter(&self) -> impl Iterator<Item = (IVec2, MaterialId)> + '_ {
+        self.bounds().iter().map(|pos| {
+            (
+                pos,
+                self.tiles[TileLayerConfig::to_tile_index(pos - self.origin) as usize],
+            )
+        })
+    }
+
+    /// Sets the tile at `pos`, silently ignoring positions outside of this chunk.
+    pub fn set(&mut self, pos: IVec2, material: MaterialId) {
+        if self.bounds().contains(pos) {
+            self.tiles[TileLayerConfig::to_tile_index(pos - self.origin) as usize] = material;
+        }
+    }
+}
+
+// === Generators === //
+
+#[derive(Debug, Clone)]
+pub struct FlatlandGenerator {
+    pub height: i32,
+    pub surface: MaterialId,
+    pub ground: MaterialId,
+}
+
+impl WorldGenerator for FlatlandGenerator {
+    fn tile(&self, pos: IVec2) -> MaterialId {
+        // N.B. we use a y-down system
+        if pos.y == self.height {
+            self.surface
+        } else if pos.y > self.height {
+            self.ground
+        } else {
+            MaterialId::AIR
+        }
+    }
+
+    fn surface_height(&self, _x: i32) -> Option<i32> {
+        Some(self.height)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HillsGenerator {
+    pub seed: u32,
+    pub base_height: i32,
+    pub amplitude: f32,
+    pub wavelength: f32,
+    pub surface: MaterialId,
+    pub ground: MaterialId,
+}
+
+impl HillsGenerator {
+    pub fn height_at(&self, x: i32) -> i32 {
+        let noise = fbm_1d(self.seed, x as f32 / self.wavelength, 4) * 2. - 1.;
+        self.base_height + (noise * self.amplitude) as i32
+    }
+}
+
+impl WorldGenerator for HillsGenerator {
+    fn tile(&self, pos: IVec2) -> MaterialId {
+        let height = self.height_at(pos.x);
+
+        if pos.y == height {
+            self.surface
+        } else if pos.y > height {
+            self.ground
+        } else {
+            MaterialId::AIR
+        }
+    }
+
+    fn surface_height(&self, x: i32) -> Option<i32> {
+        Some(self.height_at(x))
+    }
+
+    fn reseed(&mut self, seed: u32) {
+        self.seed = seed;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CavesGenerator<G> {
+    pub seed: u32,
+    pub inner: G,
+    pub scale: f32,
+    pub threshold: f32,
+}
+
+impl<G: WorldGenerator> WorldGenerator for CavesGenerator<G> {
+    fn tile(&self, pos: IVec2) -> MaterialId {
+        let material = self.inner.tile(pos);
+        if material == MaterialId::AIR {
+            return material;
+        }
+
+        let density = fbm_2d(self.seed, pos.as_vec2() / self.scale, 3);
+        if density > self.threshold {
+            MaterialId::AIR
+        } else {
+            material
+        }
+    }
+
+    fn surface_height(&self, x: i32) -> Option<i32> {
+        self.inner.surface_height(x)
+    }
+
+    fn reseed(&mut self, seed: u32) {
+        self.seed = seed;
+        self.inner.reseed(hash_ivec2(seed, IVec2::ZERO));
+    }
+}
+
+// === FloodPass === //
+
+/// Fills the open air above the primary generator's surface with a fluid up to a fixed level.
+#[derive(Debug, Clone)]
+pub struct FloodPass {
+    /// The y coordinate of the topmost fluid tile. Remember that y points downwards.
+    pub level: i32,
+    pub fluid: MaterialId,
+}
+
+impl GenerationPass for FloodPass {
+    fn apply(&self, primary: &dyn WorldGenerator, buffer: &mut ChunkBuffer) {
+        for pos in buffer.bounds().iter() {
+            let is_above_surface = primary
+                .surface_height(pos.x)
+                .is_some_and(|surface| pos.y < surface);
+
+            if pos.y >= self.level && is_above_surface && buffer.get(pos) == Some(MaterialId::AIR) {
+                buffer.set(pos, self.fluid);
+            }
+        }
+    }
+}
+
+// === UndergroundPass === //
+
+/// Per-biome parameters for the [`UndergroundPass`].
+#[derive(Debug, Clone)]
+pub struct UndergroundBiome {
+    /// The probability that a tile starts out as open space before smoothing.
+    pub cave_fill: f32,
+
+    /// The number of tiles below the surface above which caves are never carved.
+    pub cave_min_depth: i32,
+
+    pub ore: MaterialId,
+
+    /// The probability that an ore vein starts in any given ore cell.
+    pub ore_chance: f32,
+    pub ore_vein_length: u32,
+
+    pub ruins: Vec<Schematic>,
+
+    /// The probability that a ruin is placed in any given ruin column.
+    pub ruin_chance: f32,
+}
+
+/// Carves cellular-automata caves and places ore veins and ruins below the surface of the primary
+/// generator.
+#[derive(Debug, Clone)]
+pub struct UndergroundPass {
+    pub seed: u32,
+    pub biomes: Vec<UndergroundBiome>,
+
+    /// The approximate width, in tiles, of a single biome.
+    pub biome_width: f32,
+
+    /// The number of smoothing steps applied to the initial cave noise.
+    pub cave_iterations: u32,
+}
+
+impl UndergroundPass {
+    const CAVE_SALT: u32 = 0x1F3A_92C5;
+    const ORE_SALT: u32 = 0x6B2E_07D1;
+    const RUIN_SALT: u32 = 0x3C91_E4A7;
+    const BIOME_SALT: u32 = 0x58D0_1B63;
+
+    /// The edge length of the square cells in which at most one ore vein may start.
+    const ORE_CELL: i32 = 16;
+
+    /// The width of the columns in which at most one ruin may be placed.
+    const RUIN_CELL: i32 = 64;
+
+    pub fn biome_at(&self, x: i32) -> &UndergroundBiome {
+        let t = value_noise_1d(self.seed ^ Self::BIOME_SALT, x as f32 / self.biome_width);
+        let index = (t * self.biomes.len() as f32) as usize;
+        &self.biomes[index.min(self.biomes.len() - 1)]
+    }
+
+    fn depth_at(primary: &dyn WorldGenerator, pos: IVec2) -> Option<i32> {
+        primary.surface_height(pos.x).map(|surface| pos.y - surface)
+    }
+
+    fn carve_caves(&self, primary: &dyn WorldGenerator, buffer: &mut ChunkBuffer) {
+        // The automaton is simulated over a padded region so that the edge effects, which travel
+        // one tile per iteration, never reach the chunk itself. This makes the result independent
+        // of which chunks have been generated.
+        let region = buffer
+            .bounds()
+            .grow(IVec2::splat(self.cave_iterations as i32 + 1));
+
+        let size = region.size();
+        let index = |pos: IVec2| {
+            debug_assert!(region.contains(pos));
+            ((pos.y - region.min.y) * size.x + pos.x - region.min.x) as usize
+        };
+
+        let mut open = region
+            .iter()
+            .map(|pos| hash_unit(self.seed ^ Self::CAVE_SALT, pos) < self.biome_at(pos.x).cave_fill)
+            .collect::<Vec<_>>();
+
+        let mut next = open.clone();
+
+        for _ in 0..self.cave_iterations {
+            for pos in region.iter() {
+                let walls = AabbI::new_sized(pos - IVec2::ONE, IVec2::splat(3))
+                    .iter()
+                    .filter(|&other| other != pos)
+                    .filter(|&other| !region.contains(other) || !open[index(other)])
+                    .count();
+
+                next[index(pos)] = walls < 4 || (walls == 4 && open[index(pos)]);
+            }
+
+            std::mem::swap(&mut open, &mut next);
+        }
+
+        for pos in buffer.bounds().iter() {
+            if !open[index(pos)] {
+                continue;
+            }
+
+            let Some(depth) = Self::depth_at(primary, pos) else {
+                continue;
+            };
+
+            if depth >= self.biome_at(pos.x).cave_min_depth {
+                buffer.set(pos, MaterialId::AIR);
+            }
+        }
+    }
+
+    fn place_ores(&self, primary: &dyn WorldGenerator, buffer: &mut ChunkBuffer) {
+        let reach = self
+            .biomes
+            .iter()
+            .map(|biome| biome.ore_vein_length as i32)
+            .max()
+            .unwrap_or(0);
+
+        let search = buffer.bounds().grow(IVec2::splat(reach));
+        let cells = AabbI {
+            min: search.min.div_euclid(IVec2::splat(Self::ORE_CELL)),
+            max: search.max.div_euclid(IVec2::splat(Self::ORE_CELL)),
+        };
+
+        for cell in cells.inclusive().iter() {
+            let seed = self.seed ^ Self::ORE_SALT;
+            let hash = hash_ivec2(seed, cell);
+            let mut pos = cell * Self::ORE_CELL
+                + IVec2::new(
+                    (hash % Self::ORE_CELL as u32) as i32,
+                    (hash / Self::ORE_CELL as u32 % Self::ORE_CELL as u32) as i32,
+                );
+
+            let biome = self.biome_at(pos.x);
+            if hash_unit(seed.wrapping_add(1), cell) >= biome.ore_chance {
+                continue;
+            }
+
+            for step in 0..biome.ore_vein_length {
+                let is_underground = Self::depth_at(primary, pos).is_some_and(|depth| depth > 0);
+                let is_solid = buffer
+                    .get(pos)
+                    .is_some_and(|material| material != MaterialId::AIR);
+
+                if is_underground && is_solid {
+                    buffer.set(pos, biome.ore);
+                }
+
+                pos += match hash_ivec2(seed.wrapping_add(step + 2), cell) % 4 {
+                    0 => IVec2::X,
+                    1 => IVec2::NEG_X,
+                    2 => IVec2::Y,
+                    _ => IVec2::NEG_Y,
+                };
+            }
+        }
+    }
+
+    fn place_ruins(&self, primary: &dyn WorldGenerator, buffer: &mut ChunkBuffer) {
+        let reach = self
+            .biomes
+            .iter()
+            .flat_map(|biome| &biome.ruins)
+            .map(|ruin| ruin.size().x)
+            .max()
+            .unwrap_or(0);
+
+        let bounds = buffer.bounds();
+        let min_cell = (bounds.min.x - reach).div_euclid(Self::RUIN_CELL);
+        let max_cell = bounds.max.x.div_euclid(Self::RUIN_CELL);
+
+        for cell in min_cell..=max_cell {
+            let seed = self.seed ^ Self::RUIN_SALT;
+            let hash = hash_ivec2(seed, IVec2::new(cell, 0));
+            let biome = self.biome_at(cell * Self::RUIN_CELL);
+
+            if biome.ruins.is_empty() || hash_unit(seed, IVec2::new(cell, 1)) >= biome.ruin_chance {
+                continue;
+            }
+
+            let ruin = &biome.ruins[hash as usize % biome.ruins.len()];
+            let x = cell * Self::RUIN_CELL
+                + (hash / 7 % (Self::RUIN_CELL - ruin.size().x).max(1) as u32) as i32;
+
+            let Some(surface) = primary.surface_height(x) else {
+                continue;
+            };
+
+            let depth = biome.cave_min_depth + (hash / 13 % 24) as i32;
+            let origin = IVec2::new(x, surface + depth);
+
+            for (rel, material) in ruin.iter() {
+                buffer.set(origin + rel, material);
+            }
+        }
+    }
+}
+
+impl GenerationPass for UndergroundPass {
+    fn apply(&self, primary: &dyn WorldGenerator, buffer: &mut ChunkBuffer) {
+        if self.biomes.is_empty() {
+            return;
+        }
+
+        self.carve_caves(primary, buffer);
+        self.place_ores(primary, buffer);
+        self.place_ruins(primary, buffer);
+    }
+
+    fn reseed(&mut self, seed: u32) {
+        self.seed = seed;
+    }
+}
+
+// === StreamingBounds === //
+
+/// Limits the chunks streamed in around the cameras viewing a world to a region, in chunk
+/// coordinates. This is useful for small, finite worlds such as those reached through portals.
+#[derive(Debug, Copy, Clone, Component)]
+pub struct StreamingBounds(pub AabbI);
+
+// === Systems === //
+
+/// Creates the chunks around every camera in its own world. Worlds which aren't viewed by any
+/// camera don't stream in new chunks.
+pub fn sys_load_visible_chunks(
+    mut rand: RandomAccess<(
+        &mut TileWorld,
+        &mut TileChunk,
+        &VirtualCamera,
+        SendsEvent<WorldCreatedChunk>,
+    )>,
+    query: Query<(&ObjOwner<TileWorld>, Option<&StreamingBounds>), With<ObjOwner<TileGenerator>>>,
+    cameras: Res<CameraStack>,
+) {
+    rand.provide(|| {
+        for camera in cameras.cameras() {
+            let Ok((&ObjOwner(world), bounds)) = query.get(camera.entity()) else {
+                continue;
+            };
+
+            let config = world.config();
+            let visible = config.actor_aabb_to_tile(camera.visible_aabb());
+            let mut visible = AabbI {
+                min: TileLayerConfig::decompose_world_pos(visible.min).0 - IVec2::ONE,
+                max: TileLayerConfig::decompose_world_pos(visible.max).0 + IVec2::ONE,
+            }
+            .inclusive();
+
+            if let Some(&StreamingBounds(bounds)) = bounds {
+                visible = visible.intersection(bounds);
+            }
+
+            // Nothing can exist outside of the world's bounds so there's no point generating it.
+            visible = visible.intersection(world.bounds().chunk_rect());
+
+            world.create_chunks(visible.iter());
+        }
+    });
+}
+
+pub fn sys_generate_new_chunks(
+    mut events: EventReader<WorldCreatedChunk>,
+    mut rand: RandomAccess<(
+        &TileGenerator,
+        &TileWorld,
+        &mut TileChunk,
+        &MaterialRegistry,
+        &TileEntityDescriptor,
+        SendsEvent<TileEntityCreated>,
+        SendsEvent<TileChanged>,
+    )>,
+) {
+    rand.provide(|| {
+        for &WorldCreatedChunk { world, chunk } in events.read() {
+            let Some(generator) = world.try_get::<TileGenerator>() else {
+                continue;
+            };
+
+            let Some(mut chunk) = chunk.try_get::<TileChunk>() else {
+                continue;
+            };
+
+            generator.generate(&mut chunk);
+        }
+    });
+}
@@ -0,0 +1,167 @@
+use bevy_ecs::{
+    entity::Entity,
+    system::{Query, Res, ResMut, Resource},
+};
+use macroquad::math::IVec2;
+use rustc_hash::FxHashMap;
+
+use crate::{
+    game::{
+        actor::camera::{CameraStack, VirtualCamera},
+        math::aabb::AabbI,
+        save::world_layers,
+    },
+    util::arena::{ObjOwner, RandomAccess, RandomEntityExt},
+};
+
+use super::{
+    collider::TrackedColliderChunk,
+    data::{TileChunk, TileLayerConfig, TileLayers, TileWorld},
+};
+
+// === ChunkHibernation === //
+
+/// Compresses the tiles of chunks far away from every camera and releases the storage of their
+/// empty collider lists to cut down on the memory used by large explored worlds. Cold chunks are
+/// thawed as soon as a camera comes close again or their tiles are modified.
+#[derive(Debug, Resource)]
+pub struct ChunkHibernation {
+    pub enabled: bool,
+
+    /// The number of chunks around the chunks visible to a camera which are kept hot.
+    pub active_radius: i32,
+
+    /// The number of ticks between two sweeps over every chunk.
+    pub sweep_interval: u32,
+
+    ticks_until_sweep: u32,
+
+    /// The version of every chunk as of the last sweep. Chunks are only frozen once they've gone an
+    /// entire sweep without changing so that distant chunks which are still being simulated aren't
+    /// thawed and frozen over and over.
+    versions: FxHashMap<Entity, u32>,
+
+    stats: HibernationStats,
+}
+
+/// The state of every chunk as of the last sweep.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct HibernationStats {
+    pub hot: usize,
+    pub cold: usize,
+
+    /// The number of bytes used to store the tiles of cold chunks.
+    pub cold_bytes: usize,
+}
+
+impl Default for ChunkHibernation {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            active_radius: 4,
+            sweep_interval: 60,
+            ticks_until_sweep: 0,
+            versions: FxHashMap::default(),
+            stats: HibernationStats::default(),
+        }
+    }
+}
+
+impl ChunkHibernation {
+    pub fn stats(&self) -> HibernationStats {
+        self.stats
+    }
+}
+
+// === Systems === //
+
+pub fn sys_hibernate_distant_chunks(
+    mut rand: RandomAccess<(
+        &TileWorld,
+        &mut TileChunk,
+        &TileLayers,
+        &VirtualCamera,
+        &mut TrackedColliderChunk,
+    )>,
+    worlds: Query<&ObjOwner<TileWorld>>,
+    cameras: Res<CameraStack>,
+    mut hibernation: ResMut<ChunkHibernation>,
+) {
+    if !hibernation.enabled {
+        return;
+    }
+
+    if hibernation.ticks_until_sweep > 0 {
+        hibernation.ticks_until_sweep -= 1;
+        return;
+    }
+
+    let hibernation = &mut *hibernation;
+    hibernation.ticks_until_sweep = hibernation.sweep_interval;
+
+    rand.provide(|| {
+        // Determine the chunks which must be kept hot in every world, including the other layers of
+        // the worlds viewed by the cameras.
+        let mut active = FxHashMap::<Entity, Vec<AabbI>>::default();
+
+        for camera in cameras.cameras() {
+            let Ok(&ObjOwner(world)) = worlds.get(camera.entity()) else {
+                continue;
+            };
+
+            let visible = world.config().actor_aabb_to_tile(camera.visible_aabb());
+            let visible = AabbI {
+                min: TileLayerConfig::decompose_world_pos(visible.min).0,
+                max: TileLayerConfig::decompose_world_pos(visible.max).0,
+            }
+            .grow(IVec2::splat(hibernation.active_radius))
+            .inclusive();
+
+            for layer in world_layers(world) {
+                active.entry(layer.entity()).or_default().push(visible);
+            }
+        }
+
+        // Freeze idle chunks outside of those regions and thaw the cold ones within them.
+        let mut versions = FxHashMap::default();
+        let mut stats = HibernationStats::default();
+
+        for &ObjOwner(world) in worlds.iter() {
+            let active = active.get(&world.entity()).map_or(&[][..], Vec::as_slice);
+
+            for (pos, mut chunk) in world.chunks() {
+                let entity = chunk.entity();
+                let is_active = active.iter().any(|region| region.contains(pos));
+
+                if is_active {
+                    chunk.thaw();
+                } else if !chunk.is_cold()
+                    && hibernation.versions.get(&entity) == Some(&chunk.version())
+                {
+                    chunk.freeze();
+
+                    // The collider list itself must outlive the freeze since removing it is
+                    // deferred and colliders entering the chunk in the meantime would be
+                    // registered into a list which is about to be destroyed.
+                    if let Some(mut colliders) = entity.try_get::<TrackedColliderChunk>() {
+                        if colliders.is_empty() {
+                            colliders.shrink_to_fit();
+                        }
+                    }
+                }
+
+                versions.insert(entity, chunk.version());
+
+                if chunk.is_cold() {
+                    stats.cold += 1;
+                    stats.cold_bytes += chunk.tile_memory();
+                } else {
+                    stats.hot += 1;
+                }
+            }
+        }
+
+        hibernation.versions = versions;
+        hibernation.stats = stats;
+    });
+}
@@ -7,7 +7,7 @@ use smallvec::SmallVec;
 
 use crate::{
     game::math::{
-        aabb::Aabb,
+        aabb::{Aabb, AabbI},
         glam::{add_magnitude, Axis2, BVec2Ext, Sign, Vec2Ext},
     },
     random_component,
@@ -15,12 +15,13 @@ use crate::{
 };
 
 use super::{
-    collider::WorldColliders,
+    collider::{CollisionLayers, WorldColliders},
     data::TileWorld,
+    liquid::{LiquidMaterial, MAX_FILL},
     material::{MaterialCache, MaterialId, MaterialRegistry},
 };
 
-random_component!(TileColliderDescriptor, KinematicApi);
+random_component!(TileColliderDescriptor, TilePhysicsDescriptor, KinematicApi);
 
 // === TileColliderDescriptor === //
 
@@ -37,6 +38,33 @@ impl TileColliderDescriptor {
     }
 }
 
+// === TilePhysicsDescriptor === //
+
+/// Describes how actors respond to touching a tile. Materials without this descriptor behave like
+/// [`TilePhysicsDescriptor::DEFAULT`].
+#[derive(Debug, Copy, Clone)]
+pub struct TilePhysicsDescriptor {
+    /// The fraction of an actor's velocity along the surface which is lost every tick it spends in
+    /// contact with this tile.
+    pub friction: f32,
+
+    /// The fraction of an actor's velocity into the surface which is reflected back out of it.
+    pub bounciness: f32,
+}
+
+impl Default for TilePhysicsDescriptor {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl TilePhysicsDescriptor {
+    pub const DEFAULT: Self = Self {
+        friction: 0.1,
+        bounciness: 0.,
+    };
+}
+
 // === AnyCollision === //
 
 #[derive(Debug, Copy, Clone)]
@@ -54,6 +82,46 @@ impl AnyCollision {
     }
 }
 
+// === RayHit === //
+
+#[derive(Debug, Copy, Clone)]
+pub struct RayHit {
+    pub collision: AnyCollision,
+    pub pos: Vec2,
+    pub normal: Vec2,
+    pub dist: f32,
+}
+
+impl RayHit {
+    pub fn entity(&self) -> Option<Entity> {
+        match self.collision {
+            AnyCollision::Tile(..) => None,
+            AnyCollision::Collider(entity, _) => Some(entity),
+        }
+    }
+
+    pub fn material(&self) -> Option<MaterialId> {
+        match self.collision {
+            AnyCollision::Tile(_, material, _) => Some(material),
+            AnyCollision::Collider(..) => None,
+        }
+    }
+}
+
+// === KinematicTrace === //
+
+/// A record of the queries performed by the movement routines of a [`KinematicApi`] since the trace
+/// was last taken, used to visualize them for debugging.
+#[derive(Debug, Clone, Default)]
+pub struct KinematicTrace {
+    /// Every AABB checked for obstacles by [`KinematicApi::move_by`] and
+    /// [`KinematicApi::move_by_swept`].
+    pub check_aabbs: Vec<Aabb>,
+
+    /// The bounds of every tile which was considered as an obstacle during those checks.
+    pub touched_tiles: Vec<Aabb>,
+}
+
 // === KinematicApi === //
 
 #[derive(Debug)]
@@ -62,11 +130,22 @@ pub struct KinematicApi {
     registry: Obj<MaterialRegistry>,
     colliders: Obj<WorldColliders>,
     cache: MaterialCache<TileColliderDescriptor>,
+    physics_cache: MaterialCache<TilePhysicsDescriptor>,
+    liquid_cache: MaterialCache<LiquidMaterial>,
+    trace: Option<KinematicTrace>,
 }
 
 impl KinematicApi {
     pub const TOLERANCE: f32 = 0.01;
 
+    /// The maximum number of times [`move_by_swept`](Self::move_by_swept) will slide along a
+    /// surface after hitting it.
+    pub const MAX_SWEEP_ITERATIONS: usize = 3;
+
+    /// Rebounds slower than this are absorbed entirely so that bouncy actors eventually come to
+    /// rest.
+    pub const MIN_BOUNCE_SPEED: f32 = 0.5;
+
     pub fn new(
         data: Obj<TileWorld>,
         registry: Obj<MaterialRegistry>,
@@ -77,17 +156,52 @@ impl KinematicApi {
             registry,
             colliders,
             cache: MaterialCache::default(),
+            physics_cache: MaterialCache::default(),
+            liquid_cache: MaterialCache::default(),
+            trace: None,
         }
     }
 
+    /// Enables or disables the recording of a [`KinematicTrace`]. Disabling tracing discards the
+    /// current trace.
+    pub fn set_tracing(&mut self, enabled: bool) {
+        if enabled != self.trace.is_some() {
+            self.trace = enabled.then(KinematicTrace::default);
+        }
+    }
+
+    pub fn is_tracing(&self) -> bool {
+        self.trace.is_some()
+    }
+
+    /// Returns everything recorded since the last call and starts a fresh trace.
+    pub fn take_trace(&mut self) -> KinematicTrace {
+        self.trace.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    fn record_trace(&mut self, check_aabb: Aabb, touched_tiles: &[Aabb]) {
+        if let Some(trace) = &mut self.trace {
+            trace.check_aabbs.push(check_aabb);
+            trace.touched_tiles.extend_from_slice(touched_tiles);
+        }
+    }
+
+    /// Iterates over every tile and tracked collider intersecting `check_aabb` which belongs to a
+    /// layer in `mask`. Tiles belong to [`CollisionLayers::TILES`].
     pub fn iter_colliders_in<B>(
         &mut self,
         check_aabb: Aabb,
+        mask: u32,
         mut f: impl FnMut(AnyCollision) -> ControlFlow<B>,
     ) -> ControlFlow<B> {
         let config = self.data.config();
+        let tiles = if mask & CollisionLayers::TILES != 0 {
+            config.actor_aabb_to_tile(check_aabb).inclusive()
+        } else {
+            AabbI::ZERO
+        };
 
-        for tile in config.actor_aabb_to_tile(check_aabb).inclusive().iter() {
+        for tile in tiles.iter() {
             let offset = config.tile_to_actor_rect(tile).min;
             let material = self.data.tile(tile);
 
@@ -115,7 +229,7 @@ impl KinematicApi {
         }
 
         cbit! {
-            for (actor, collider) in self.colliders.collisions(check_aabb) {
+            for (actor, collider) in self.colliders.collisions(check_aabb, mask) {
                 f(AnyCollision::Collider(actor, collider))?;
             }
         }
@@ -126,9 +240,10 @@ impl KinematicApi {
     pub fn has_colliders_in(
         &mut self,
         check_aabb: Aabb,
+        mask: u32,
         mut filter: impl FnMut(AnyCollision) -> bool,
     ) -> bool {
-        cbit!(for collider in self.iter_colliders_in(check_aabb) {
+        cbit!(for collider in self.iter_colliders_in(check_aabb, mask) {
             if filter(collider) {
                 return true;
             }
@@ -137,29 +252,217 @@ impl KinematicApi {
         false
     }
 
+    /// Finds the closest tile or actor collider accepted by `filter` along the ray starting at
+    /// `origin` and travelling at most `max_dist` in the direction `dir`.
+    pub fn cast_ray(
+        &mut self,
+        origin: Vec2,
+        dir: Vec2,
+        max_dist: f32,
+        mask: u32,
+        mut filter: impl FnMut(AnyCollision) -> bool,
+    ) -> Option<RayHit> {
+        let dir = dir.normalize_or_zero();
+        if dir == Vec2::ZERO {
+            return None;
+        }
+
+        // We walk the ray in short segments so that we only ever visit colliders near it. A hit
+        // closer than the end of the current segment must lie within that segment's bounds so
+        // we can stop at the first segment producing such a hit.
+        let segment_len = self.data.config().size * 4.;
+        let mut best = None::<RayHit>;
+        let mut seg_start = 0.;
+
+        while seg_start < max_dist {
+            let seg_end = (seg_start + segment_len).min(max_dist);
+            let check_aabb = Aabb {
+                min: origin + dir * seg_start,
+                max: origin + dir * seg_end,
+            }
+            .normalized();
+
+            cbit!(for collision in self.iter_colliders_in(check_aabb, mask) {
+                if !filter(collision) {
+                    continue;
+                }
+
+                let Some((dist, normal)) = collision.aabb().ray_cast(origin, dir) else {
+                    continue;
+                };
+
+                if dist > max_dist || best.is_some_and(|best| best.dist <= dist) {
+                    continue;
+                }
+
+                best = Some(RayHit {
+                    collision,
+                    pos: origin + dir * dist,
+                    normal,
+                    dist,
+                });
+            });
+
+            if best.is_some_and(|best| best.dist <= seg_end) {
+                break;
+            }
+
+            seg_start = seg_end;
+        }
+
+        best
+    }
+
+    pub fn has_line_of_sight(
+        &mut self,
+        from: Vec2,
+        to: Vec2,
+        mask: u32,
+        filter: impl FnMut(AnyCollision) -> bool,
+    ) -> bool {
+        let delta = to - from;
+        self.cast_ray(from, delta, delta.length(), mask, filter)
+            .is_none()
+    }
+
+    /// Determines whether the tile at `tile` has any colliders which could obstruct an actor.
+    pub fn is_solid_tile(&mut self, tile: IVec2) -> bool {
+        let material = self.data.tile(tile);
+        if material == MaterialId::AIR {
+            return false;
+        }
+
+        self.cache
+            .get(&self.registry, material)
+            .is_some_and(|colliders| !colliders.aabbs.is_empty())
+    }
+
     pub fn get_clip_mask(
         &mut self,
         aabb: Aabb,
         by: Vec2,
+        mask: u32,
         mut filter: impl FnMut(AnyCollision) -> bool,
     ) -> BVec2 {
-        let mut mask = BVec2::default();
+        let mut clip = BVec2::default();
 
         for axis in Axis2::iter() {
             let signed_delta = by.get_axis(axis);
             let check_aabb =
                 aabb.translate_extend(axis.unit_mag((Self::TOLERANCE * 2.).copysign(signed_delta)));
 
-            mask.set_axis(axis, !self.has_colliders_in(check_aabb, &mut filter));
+            clip.set_axis(axis, !self.has_colliders_in(check_aabb, mask, &mut filter));
         }
 
-        mask
+        clip
+    }
+
+    pub fn physics_of(&mut self, material: MaterialId) -> TilePhysicsDescriptor {
+        self.physics_cache
+            .get(&self.registry, material)
+            .map_or(TilePhysicsDescriptor::DEFAULT, |physics| *physics)
+    }
+
+    /// Applies friction and bounciness to the velocity `vel` of an actor with bounding box `aabb`
+    /// for every axis along which it is touching a collider. The physical properties of all touched
+    /// tiles on a given side are averaged, with non-tile colliders using the default descriptor.
+    pub fn apply_contact_response(
+        &mut self,
+        aabb: Aabb,
+        vel: Vec2,
+        mask: u32,
+        mut filter: impl FnMut(AnyCollision) -> bool,
+    ) -> Vec2 {
+        let mut vel = vel;
+
+        for axis in Axis2::iter() {
+            let signed_delta = vel.get_axis(axis);
+            let check_aabb =
+                aabb.translate_extend(axis.unit_mag((Self::TOLERANCE * 2.).copysign(signed_delta)));
+
+            let mut contacts = SmallVec::<[Option<MaterialId>; 4]>::new();
+            cbit!(for collider in self.iter_colliders_in(check_aabb, mask) {
+                if !filter(collider) {
+                    continue;
+                }
+
+                contacts.push(match collider {
+                    AnyCollision::Tile(_, material, _) => Some(material),
+                    AnyCollision::Collider(..) => None,
+                });
+            });
+
+            if contacts.is_empty() {
+                continue;
+            }
+
+            let mut friction = 0.;
+            let mut bounciness = 0.;
+            for &material in &contacts {
+                let physics = material.map_or(TilePhysicsDescriptor::DEFAULT, |material| {
+                    self.physics_of(material)
+                });
+                friction += physics.friction;
+                bounciness += physics.bounciness;
+            }
+            friction /= contacts.len() as f32;
+            bounciness /= contacts.len() as f32;
+
+            let mut normal = vel.mask_in_axis(axis) * -bounciness;
+            if normal.length() < Self::MIN_BOUNCE_SPEED {
+                normal = Vec2::ZERO;
+            }
+
+            let tangent = vel.mask_out_axis(axis) * (1. - friction).clamp(0., 1.);
+            vel = normal + tangent;
+        }
+
+        vel
+    }
+
+    /// Applies the buoyancy and drag of every liquid overlapping `aabb` to the velocity `vel` of an
+    /// actor accelerated downwards by `gravity`. Both forces are scaled by the fraction of the
+    /// actor's bounding box which is submerged.
+    pub fn apply_liquid_forces(&mut self, aabb: Aabb, vel: Vec2, gravity: f32) -> Vec2 {
+        let config = self.data.config();
+        let area = aabb.w() * aabb.h();
+        if area <= 0. {
+            return vel;
+        }
+
+        let mut buoyancy = 0.;
+        let mut drag = 0.;
+
+        for (tile, material) in self.data.query_materials_in(aabb) {
+            if material == MaterialId::AIR {
+                continue;
+            }
+
+            let Some(liquid) = self.liquid_cache.get(&self.registry, material) else {
+                continue;
+            };
+
+            // Partially filled tiles only hold liquid in their bottom portion.
+            let mut rect = config.tile_to_actor_rect(tile);
+            rect.min.y =
+                rect.max.y - rect.h() * self.data.fill_level(tile) as f32 / MAX_FILL as f32;
+
+            let overlap = (aabb.max.min(rect.max) - aabb.min.max(rect.min)).max(Vec2::ZERO);
+            let submerged = overlap.x * overlap.y / area;
+
+            buoyancy += liquid.buoyancy * submerged;
+            drag += liquid.drag * submerged;
+        }
+
+        let vel = Vec2::new(vel.x, vel.y - buoyancy * gravity);
+        vel * (1. - drag).clamp(0., 1.)
     }
 
     pub fn move_by(
         &mut self,
         aabb: Aabb,
         by: Vec2,
+        mask: u32,
         mut filter: impl FnMut(AnyCollision) -> bool,
     ) -> Vec2 {
         let mut aabb = aabb;
@@ -171,13 +474,19 @@ impl KinematicApi {
                 aabb.translate_extend(axis.unit_mag(add_magnitude(signed_delta, Self::TOLERANCE)));
 
             let mut delta = signed_delta.abs();
+            let tracing = self.is_tracing();
+            let mut touched = Vec::new();
 
-            cbit!(for collider in self.iter_colliders_in(check_aabb) {
+            cbit!(for collider in self.iter_colliders_in(check_aabb, mask) {
                 let collider_aabb = collider.aabb();
                 if !filter(collider) {
                     continue;
                 }
 
+                if tracing && matches!(collider, AnyCollision::Tile(..)) {
+                    touched.push(collider_aabb);
+                }
+
                 let acceptable_delta = if signed_delta < 0. {
                     // We're moving to the left/top so we're presumably right/below the target.
                     aabb.min.get_axis(axis) - collider_aabb.max.get_axis(axis)
@@ -191,6 +500,8 @@ impl KinematicApi {
                 delta = delta.min(acceptable_delta.max(0.));
             });
 
+            self.record_trace(check_aabb, &touched);
+
             let delta = axis.unit_mag(Sign::of_biased(signed_delta).unit_mag(delta));
 
             total_by += delta;
@@ -199,6 +510,80 @@ impl KinematicApi {
 
         total_by
     }
+
+    /// Like [`move_by`](Self::move_by) but sweeps the AABB along the full motion vector instead of
+    /// resolving each axis independently. This is more expensive but ensures that fast movers
+    /// cannot tunnel through thin walls or slip between diagonally adjacent tiles.
+    pub fn move_by_swept(
+        &mut self,
+        aabb: Aabb,
+        by: Vec2,
+        mask: u32,
+        mut filter: impl FnMut(AnyCollision) -> bool,
+    ) -> Vec2 {
+        let mut aabb = aabb;
+        let mut remaining = by;
+        let mut total_by = Vec2::ZERO;
+
+        for _ in 0..Self::MAX_SWEEP_ITERATIONS {
+            let length = remaining.length();
+            if length == 0. {
+                break;
+            }
+
+            // Sweeping a box against a box is equivalent to casting its center against the
+            // obstacle grown by the box's extents.
+            let check_aabb = aabb
+                .translate_extend(remaining)
+                .grow(Vec2::splat(Self::TOLERANCE * 2.));
+
+            let mut hit = None::<(f32, Vec2)>;
+            let tracing = self.is_tracing();
+            let mut touched = Vec::new();
+
+            cbit!(for collider in self.iter_colliders_in(check_aabb, mask) {
+                if !filter(collider) {
+                    continue;
+                }
+
+                if tracing && matches!(collider, AnyCollision::Tile(..)) {
+                    touched.push(collider.aabb());
+                }
+
+                let expanded = collider.aabb().grow(aabb.size());
+                let Some((t, normal)) = expanded.ray_cast(aabb.center(), remaining) else {
+                    continue;
+                };
+
+                // Ignore obstacles we already overlap so that we can always move out of them.
+                if t > 1. || normal == Vec2::ZERO {
+                    continue;
+                }
+
+                if hit.map_or(true, |(best, _)| t < best) {
+                    hit = Some((t, normal));
+                }
+            });
+
+            self.record_trace(check_aabb, &touched);
+
+            let Some((t, normal)) = hit else {
+                total_by += remaining;
+                break;
+            };
+
+            // Stop just short of the obstacle...
+            let travel = remaining / length * (length * t - Self::TOLERANCE).max(0.);
+            total_by += travel;
+            aabb = aabb.translated(travel);
+
+            // ...and slide along it with whatever motion remains.
+            remaining *= 1. - t;
+            remaining -= normal * remaining.dot(normal);
+        }
+
+        total_by
+    }
 }
 
 // === Filters === //
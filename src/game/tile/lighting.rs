@@ -0,0 +1,333 @@
+use std::mem;
+
+use bevy_ecs::{
+    component::Component,
+    system::{Query, Res},
+};
+use macroquad::{color::Color, math::IVec2};
+use rustc_hash::FxHashMap;
+
+use crate::{
+    game::{
+        actor::camera::{ActiveCamera, CameraStack, VirtualCamera},
+        math::{aabb::AabbI, draw::QuadBatch},
+    },
+    util::{
+        arena::{Obj, ObjOwner, RandomAccess, RandomEntityExt},
+        lang::ensure_index,
+    },
+};
+
+use super::{
+    data::{TileChunk, TileLayerConfig, TileWorld},
+    generator::TileGenerator,
+    material::{BaseMaterialDescriptor, MaterialId, MaterialRegistry},
+    render::FluidTileMaterial,
+};
+
+// === WorldLighting === //
+
+/// The brightest light level, emitted by the sky.
+pub const MAX_LIGHT: u8 = 15;
+
+/// The amount of light lost when light enters an air or fluid tile.
+pub const AIR_FALLOFF: u8 = 1;
+
+/// The amount of light lost when light enters any other tile. This is large enough that light only
+/// bleeds into the first few tiles of a wall.
+pub const SOLID_FALLOFF: u8 = 4;
+
+/// The opacity of the darkness overlay drawn over tiles which receive no light at all.
+pub const MAX_DARKNESS: f32 = 0.92;
+
+/// Light can never travel further than this many tiles from its source. Because this is less than
+/// the size of a chunk, a chunk's lighting only depends on the chunk itself and its eight
+/// neighbors.
+const LIGHT_RADIUS: i32 = (MAX_LIGHT / AIR_FALLOFF) as i32;
+
+/// The light levels of a world's chunks, recomputed lazily for the chunks that cameras can see.
+/// Air and fluid tiles above the world generator's surface are lit by the sky and materials with a non-zero
+/// [`light`](BaseMaterialDescriptor::light) level glow.
+#[derive(Debug, Default, Component)]
+pub struct WorldLighting {
+    chunks: FxHashMap<IVec2, ChunkLight>,
+}
+
+#[derive(Debug)]
+struct ChunkLight {
+    levels: Box<[u8; TileLayerConfig::CHUNK_AREA as usize]>,
+
+    /// The versions of the chunk and its neighbors from which `levels` were computed, in the order
+    /// produced by [`neighborhood_versions`].
+    versions: [u32; 9],
+}
+
+impl WorldLighting {
+    /// The maximum number of chunks relit in a single update so that large edits can't stall the
+    /// game for a frame.
+    pub const CHUNKS_PER_UPDATE: usize = 16;
+
+    /// Returns the light level of the given tile, or `None` if it hasn't been computed yet.
+    pub fn light(&self, pos: IVec2) -> Option<u8> {
+        let (chunk, block) = TileLayerConfig::decompose_world_pos(pos);
+        let chunk = self.chunks.get(&chunk)?;
+        Some(chunk.levels[TileLayerConfig::to_tile_index(block) as usize])
+    }
+
+    fn is_stale(&self, pos: IVec2, versions: &[u32; 9]) -> bool {
+        self.chunks
+            .get(&pos)
+            .map_or(true, |chunk| chunk.versions != *versions)
+    }
+}
+
+fn neighborhood_versions(world: &TileWorld, chunk: IVec2) -> [u32; 9] {
+    let mut versions = [0; 9];
+    let mut i = 0;
+
+    for y in -1..=1 {
+        for x in -1..=1 {
+            // Chunks start at version 1 so 0 is free to represent missing chunks.
+            versions[i] = world
+                .chunk(chunk + IVec2::new(x, y))
+                .map_or(0, |chunk| chunk.version());
+            i += 1;
+        }
+    }
+
+    versions
+}
+
+#[derive(Debug, Copy, Clone)]
+struct MaterialLight {
+    emission: u8,
+    translucent: bool,
+}
+
+/// Caches the lighting properties of each material.
+#[derive(Default)]
+struct MaterialLightCache {
+    cache: Vec<Option<MaterialLight>>,
+}
+
+impl MaterialLightCache {
+    fn get(&mut self, registry: &MaterialRegistry, id: MaterialId) -> MaterialLight {
+        *ensure_index(&mut self.cache, id.0 as usize).get_or_insert_with(|| {
+            let descriptor = registry.lookup(id);
+
+            MaterialLight {
+                emission: descriptor
+                    .get::<BaseMaterialDescriptor>()
+                    .light
+                    .min(MAX_LIGHT),
+                translucent: id == MaterialId::AIR
+                    || descriptor.try_get::<FluidTileMaterial>().is_some(),
+            }
+        })
+    }
+}
+
+/// Computes the light levels of a single chunk by flood-filling light from every source within
+/// [`LIGHT_RADIUS`] of it.
+fn compute_chunk_light(
+    world: &TileWorld,
+    registry: &MaterialRegistry,
+    generator: Option<Obj<TileGenerator>>,
+    cache: &mut MaterialLightCache,
+    chunk: IVec2,
+) -> Box<[u8; TileLayerConfig::CHUNK_AREA as usize]> {
+    let origin = chunk * TileLayerConfig::CHUNK_EDGE;
+    let region = AabbI {
+        min: origin - IVec2::splat(LIGHT_RADIUS),
+        max: origin + IVec2::splat(TileLayerConfig::CHUNK_EDGE + LIGHT_RADIUS),
+    };
+    let size = region.size();
+    let index = |pos: IVec2| {
+        let rel = pos - region.min;
+        (rel.y * size.x + rel.x) as usize
+    };
+
+    let mut translucent = vec![true; (size.x * size.y) as usize];
+    let mut levels = vec![0u8; translucent.len()];
+
+    // Light is processed from brightest to dimmest so that every tile is finalized the first time
+    // it's visited, like Dijkstra's algorithm with a bucket queue.
+    let mut buckets = vec![Vec::<IVec2>::new(); MAX_LIGHT as usize + 1];
+
+    let positions = (region.min.y..region.max.y)
+        .flat_map(|y| (region.min.x..region.max.x).map(move |x| IVec2::new(x, y)));
+
+    for pos in positions {
+        let material = cache.get(registry, world.tile(pos));
+        translucent[index(pos)] = material.translucent;
+
+        let is_sky = material.translucent
+            && generator
+                .and_then(|generator| generator.surface_height(pos.x))
+                .is_some_and(|surface| pos.y < surface);
+
+        let level = if is_sky { MAX_LIGHT } else { material.emission };
+
+        if level > 0 {
+            levels[index(pos)] = level;
+            buckets[level as usize].push(pos);
+        }
+    }
+
+    for level in (1..=MAX_LIGHT).rev() {
+        for pos in mem::take(&mut buckets[level as usize]) {
+            // This tile has since been lit more brightly by another source.
+            if levels[index(pos)] != level {
+                continue;
+            }
+
+            for offset in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+                let neighbor = pos + offset;
+                if !region.contains(neighbor) {
+                    continue;
+                }
+
+                let falloff = if translucent[index(neighbor)] {
+                    AIR_FALLOFF
+                } else {
+                    SOLID_FALLOFF
+                };
+
+                let lit = level.saturating_sub(falloff);
+                if lit > levels[index(neighbor)] {
+                    levels[index(neighbor)] = lit;
+                    buckets[lit as usize].push(neighbor);
+                }
+            }
+        }
+    }
+
+    let mut out = Box::new([0; TileLayerConfig::CHUNK_AREA as usize]);
+    for y in 0..TileLayerConfig::CHUNK_EDGE {
+        for x in 0..TileLayerConfig::CHUNK_EDGE {
+            let block = IVec2::new(x, y);
+            out[TileLayerConfig::to_tile_index(block) as usize] = levels[index(origin + block)];
+        }
+    }
+
+    out
+}
+
+// === Systems === //
+
+pub fn sys_update_lighting(
+    mut rand: RandomAccess<(
+        &TileWorld,
+        &TileChunk,
+        &TileGenerator,
+        &MaterialRegistry,
+        &BaseMaterialDescriptor,
+        &FluidTileMaterial,
+        &VirtualCamera,
+    )>,
+    mut query: Query<(&ObjOwner<TileWorld>, &mut WorldLighting)>,
+    cameras: Res<CameraStack>,
+) {
+    rand.provide(|| {
+        for (&ObjOwner(world), mut lighting) in query.iter_mut() {
+            let config = world.config();
+            let registry = world.entity().get::<MaterialRegistry>();
+            let generator = world.entity().try_get::<TileGenerator>();
+            let mut cache = MaterialLightCache::default();
+
+            // Forget about chunks which have been unloaded.
+            lighting.chunks.retain(|&pos, _| world.chunk(pos).is_some());
+
+            // Relight stale chunks which are visible to a camera.
+            let mut budget = WorldLighting::CHUNKS_PER_UPDATE;
+
+            for camera in cameras.cameras() {
+                if camera.entity() != world.entity() {
+                    continue;
+                }
+
+                let visible = config.actor_aabb_to_tile(camera.visible_aabb());
+                let visible = AabbI {
+                    min: TileLayerConfig::decompose_world_pos(visible.min).0,
+                    max: TileLayerConfig::decompose_world_pos(visible.max).0,
+                };
+
+                for pos in visible.inclusive().iter() {
+                    if budget == 0 {
+                        return;
+                    }
+
+                    if world.chunk(pos).is_none() {
+                        continue;
+                    }
+
+                    let versions = neighborhood_versions(&world, pos);
+                    if !lighting.is_stale(pos, &versions) {
+                        continue;
+                    }
+
+                    let levels = compute_chunk_light(&world, &registry, generator, &mut cache, pos);
+
+                    lighting.chunks.insert(pos, ChunkLight { levels, versions });
+                    budget -= 1;
+                }
+            }
+        }
+    });
+}
+
+pub fn sys_render_lighting(
+    mut rand: RandomAccess<(&TileWorld, &VirtualCamera)>,
+    query: Query<(&ObjOwner<TileWorld>, &WorldLighting)>,
+    camera: Res<ActiveCamera>,
+) {
+    let _guard = camera.apply();
+
+    rand.provide(|| {
+        let Some(active) = camera.camera else {
+            return;
+        };
+
+        let Ok((&ObjOwner(world), lighting)) = query.get(active.entity()) else {
+            return;
+        };
+
+        let config = world.config();
+        let visible = config.actor_aabb_to_tile(active.visible_aabb()).inclusive();
+
+        // Tiles whose lighting hasn't been computed yet are left fully lit rather than flashing
+        // black while we catch up.
+        let light_at = |pos: IVec2| lighting.light(pos).unwrap_or(MAX_LIGHT) as f32;
+
+        // Each corner is shaded by the average of the four tiles around it so that the darkness
+        // fades smoothly from tile to tile.
+        let corner_darkness = |corner: IVec2| {
+            let sum = light_at(corner)
+                + light_at(corner - IVec2::X)
+                + light_at(corner - IVec2::Y)
+                + light_at(corner - IVec2::ONE);
+
+            let light = sum / (4. * MAX_LIGHT as f32);
+            Color::new(0., 0., 0., (1. - light) * MAX_DARKNESS)
+        };
+
+        let mut batch = QuadBatch::default();
+
+        for tile in visible.iter() {
+            let colors = [
+                corner_darkness(tile),
+                corner_darkness(tile + IVec2::X),
+                corner_darkness(tile + IVec2::ONE),
+                corner_darkness(tile + IVec2::Y),
+            ];
+
+            if colors.iter().all(|color| color.a <= 0.) {
+                continue;
+            }
+
+            batch.push_gradient(config.tile_to_actor_rect(tile).corners(), colors);
+        }
+
+        batch.flush();
+    });
+}
@@ -0,0 +1,266 @@
+use bevy_ecs::{component::Component, system::Query};
+use macroquad::math::IVec2;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    random_component,
+    util::arena::{Obj, ObjOwner, RandomAccess, SendsEvent},
+};
+
+use super::{
+    data::{TileChanged, TileChunk, TileLayerConfig, TileWorld},
+    material::{MaterialCache, MaterialId, MaterialRegistry},
+    tile_entity::{TileEntityCreated, TileEntityDescriptor},
+};
+
+random_component!(LiquidMaterial);
+
+// === LiquidMaterial === //
+
+/// The fill level of a completely full liquid tile.
+pub const MAX_FILL: u8 = 16;
+
+/// A material descriptor for liquids which flow down and spread sideways through the air. Liquids
+/// are drawn using their [`FluidTileMaterial`](super::render::FluidTileMaterial).
+#[derive(Debug, Copy, Clone)]
+pub struct LiquidMaterial {
+    /// The upwards acceleration applied to a fully submerged actor as a multiple of its gravity.
+    /// Actors float in liquids with a buoyancy above one.
+    pub buoyancy: f32,
+
+    /// The fraction of a fully submerged actor's velocity which is lost every tick.
+    pub drag: f32,
+}
+
+// === WorldLiquids === //
+
+/// The state of the liquid simulation in a world. The simulation is a cellular automaton which only
+/// steps the chunks whose contents changed in the previous step so that settled liquids cost
+/// nothing.
+#[derive(Debug, Default, Component)]
+pub struct WorldLiquids {
+    ticks: u32,
+
+    /// The version of every chunk as of the end of the last step.
+    versions: FxHashMap<IVec2, u32>,
+}
+
+impl WorldLiquids {
+    /// The number of ticks between two steps of the simulation.
+    pub const TICKS_PER_STEP: u32 = 4;
+
+    /// The number of ticks the simulation has run for.
+    pub fn ticks(&self) -> u32 {
+        self.ticks
+    }
+
+    /// Rewinds the simulation to `ticks`, waking every chunk on the next step.
+    pub fn rewind(&mut self, ticks: u32) {
+        self.ticks = ticks;
+        self.versions.clear();
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum LiquidCell {
+    Blocked,
+    Empty,
+    Liquid(MaterialId, u8),
+}
+
+struct LiquidFlow {
+    world: Obj<TileWorld>,
+    registry: Obj<MaterialRegistry>,
+    cache: MaterialCache<LiquidMaterial>,
+
+    /// Tiles which received liquid during this step. These aren't stepped again until the next step
+    /// so that liquid can't race through several tiles at once.
+    filled: FxHashSet<IVec2>,
+
+    /// Whether tiles are visited and spread to from right to left. This alternates every step to
+    /// avoid biasing the flow towards one side.
+    leftwards: bool,
+}
+
+impl LiquidFlow {
+    fn cell(&mut self, pos: IVec2) -> LiquidCell {
+        let (chunk, block) = TileLayerConfig::decompose_world_pos(pos);
+
+        // Liquids never flow into chunks which haven't been loaded yet.
+        let Some(chunk) = self.world.chunk(chunk).filter(|chunk| chunk.is_generated()) else {
+            return LiquidCell::Blocked;
+        };
+
+        let material = chunk.tile(block);
+        if material == MaterialId::AIR {
+            LiquidCell::Empty
+        } else if self.cache.get(&self.registry, material).is_some() {
+            LiquidCell::Liquid(material, chunk.fill_level(block))
+        } else {
+            LiquidCell::Blocked
+        }
+    }
+
+    fn fill(&mut self, pos: IVec2, material: MaterialId, level: u8) {
+        let (chunk, block) = TileLayerConfig::decompose_world_pos(pos);
+        let mut chunk = self.world.chunk(chunk).unwrap();
+
+        if level == 0 {
+            chunk.set_tile(block, MaterialId::AIR);
+            return;
+        }
+
+        if chunk.tile(block) != material {
+            chunk.set_tile(block, material);
+        }
+
+        if chunk.fill_level(block) != level {
+            chunk.set_fill_level(block, level);
+        }
+
+        self.filled.insert(pos);
+    }
+
+    fn step_chunk(&mut self, chunk: IVec2) {
+        let origin = chunk * TileLayerConfig::CHUNK_EDGE;
+
+        // Rows are visited from the bottom up so that liquid can only fall a single tile per step.
+        for y in (0..TileLayerConfig::CHUNK_EDGE).rev() {
+            for i in 0..TileLayerConfig::CHUNK_EDGE {
+                let x = if self.leftwards {
+                    TileLayerConfig::CHUNK_EDGE - 1 - i
+                } else {
+                    i
+                };
+
+                self.step_tile(origin + IVec2::new(x, y));
+            }
+        }
+    }
+
+    fn step_tile(&mut self, pos: IVec2) {
+        if self.filled.contains(&pos) {
+            return;
+        }
+
+        let LiquidCell::Liquid(material, start_level) = self.cell(pos) else {
+            return;
+        };
+        let mut level = start_level;
+
+        // Fall into the tile below
+        let below = pos + IVec2::Y;
+        match self.cell(below) {
+            LiquidCell::Empty => {
+                self.fill(below, material, level);
+                level = 0;
+            }
+            LiquidCell::Liquid(other, other_level) if other == material => {
+                let moved = level.min(MAX_FILL - other_level);
+                if moved > 0 {
+                    self.fill(below, material, other_level + moved);
+                    level -= moved;
+                }
+            }
+            _ => {}
+        }
+
+        // Spread to the sides. Liquid only spreads towards tiles at least two levels lower than
+        // itself so that the flow always settles.
+        let sides = if self.leftwards {
+            [IVec2::NEG_X, IVec2::X]
+        } else {
+            [IVec2::X, IVec2::NEG_X]
+        };
+
+        for side in sides {
+            let neighbor = pos + side;
+            let neighbor_level = match self.cell(neighbor) {
+                LiquidCell::Empty => 0,
+                LiquidCell::Liquid(other, other_level) if other == material => other_level,
+                _ => continue,
+            };
+
+            if level < neighbor_level + 2 {
+                continue;
+            }
+
+            let moved = (level - neighbor_level) / 2;
+            self.fill(neighbor, material, neighbor_level + moved);
+            level -= moved;
+        }
+
+        if level != start_level {
+            self.fill(pos, material, level);
+        }
+    }
+}
+
+// === Systems === //
+
+pub fn sys_flow_liquids(
+    mut rand: RandomAccess<(
+        &TileWorld,
+        &mut TileChunk,
+        &MaterialRegistry,
+        &LiquidMaterial,
+        &TileEntityDescriptor,
+        SendsEvent<TileEntityCreated>,
+        SendsEvent<TileChanged>,
+    )>,
+    mut query: Query<(
+        &ObjOwner<TileWorld>,
+        &ObjOwner<MaterialRegistry>,
+        &mut WorldLiquids,
+    )>,
+) {
+    rand.provide(|| {
+        for (&ObjOwner(world), &ObjOwner(registry), mut liquids) in query.iter_mut() {
+            liquids.ticks += 1;
+            if liquids.ticks % WorldLiquids::TICKS_PER_STEP != 0 {
+                continue;
+            }
+
+            // Wake every chunk which changed since the last step along with its neighbors since
+            // liquid may now be able to flow into or out of them.
+            let mut awake = FxHashSet::default();
+
+            for (pos, chunk) in world.chunks() {
+                if liquids.versions.get(&pos) == Some(&chunk.version()) {
+                    continue;
+                }
+
+                for y in -1..=1 {
+                    for x in -1..=1 {
+                        awake.insert(pos + IVec2::new(x, y));
+                    }
+                }
+            }
+
+            let mut awake = awake
+                .into_iter()
+                .filter(|&pos| world.chunk(pos).is_some_and(|chunk| chunk.is_generated()))
+                .collect::<Vec<_>>();
+
+            // Chunks are stepped from the bottom up for the same reason as their rows.
+            awake.sort_by_key(|pos| (-pos.y, pos.x));
+
+            let mut flow = LiquidFlow {
+                world,
+                registry,
+                cache: MaterialCache::default(),
+                filled: FxHashSet::default(),
+                leftwards: (liquids.ticks / WorldLiquids::TICKS_PER_STEP) % 2 == 0,
+            };
+
+            for pos in awake {
+                flow.step_chunk(pos);
+            }
+
+            liquids.versions = world
+                .chunks()
+                .map(|(pos, chunk)| (pos, chunk.version()))
+                .collect();
+        }
+    });
+}
@@ -15,7 +15,7 @@ random_component!(MaterialRegistry, BaseMaterialDescriptor);
 
 // === MaterialRegistry === //
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct MaterialRegistry {
     name_map: FxHashMap<String, MaterialId>,
     descriptors: Vec<Entity>,
@@ -27,7 +27,13 @@ impl MaterialRegistry {
         let did = MaterialId(self.descriptors.len() as u16);
         self.name_map.insert(name.clone(), did);
         self.descriptors.push(entity);
-        entity.insert(BaseMaterialDescriptor { id: did, name });
+        entity.insert(BaseMaterialDescriptor {
+            id: did,
+            name,
+            label: None,
+            hardness: BaseMaterialDescriptor::DEFAULT_HARDNESS,
+            light: 0,
+        });
         did
     }
 
@@ -38,6 +44,11 @@ impl MaterialRegistry {
     pub fn lookup_by_name(&self, name: &str) -> Option<MaterialId> {
         self.name_map.get(name).copied()
     }
+
+    /// Iterates over the ids of every registered material, in registration order.
+    pub fn ids(&self) -> impl ExactSizeIterator<Item = MaterialId> {
+        (0..self.descriptors.len() as u16).map(MaterialId)
+    }
 }
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
@@ -51,6 +62,26 @@ impl MaterialId {
 pub struct BaseMaterialDescriptor {
     pub id: MaterialId,
     pub name: String,
+
+    /// A human-readable name for the material, defaulting to its registered name.
+    pub label: Option<String>,
+
+    /// The number of ticks it takes to mine a tile of this material. Materials with a hardness of
+    /// zero break instantly.
+    pub hardness: f32,
+
+    /// The light level emitted by tiles of this material, up to [`MAX_LIGHT`].
+    ///
+    /// [`MAX_LIGHT`]: super::lighting::MAX_LIGHT
+    pub light: u8,
+}
+
+impl BaseMaterialDescriptor {
+    pub const DEFAULT_HARDNESS: f32 = 10.;
+
+    pub fn label(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.name)
+    }
 }
 
 pub struct MaterialCache<T> {
@@ -0,0 +1,296 @@
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
+
+use bevy_ecs::system::{Query, ResMut, Resource};
+use macroquad::{
+    color::Color,
+    input::{is_key_pressed, KeyCode},
+    math::Vec2,
+};
+
+use crate::{
+    game::math::aabb::Aabb,
+    util::arena::{spawn_entity, Obj, ObjOwner, RandomAccess, RandomEntityExt},
+};
+
+use super::{
+    kinematic::{TileColliderDescriptor, TilePhysicsDescriptor},
+    lighting::MAX_LIGHT,
+    material::{BaseMaterialDescriptor, MaterialId, MaterialRegistry},
+    render::SolidTileMaterial,
+};
+
+// === MaterialDef === //
+
+/// A material definition loaded from a data file. Properties left unspecified keep whatever value
+/// the material was registered with in code.
+#[derive(Debug, Clone, Default)]
+pub struct MaterialDef {
+    pub name: String,
+    pub label: Option<String>,
+    pub color: Option<Color>,
+    pub colliders: Option<Vec<Aabb>>,
+    pub friction: Option<f32>,
+    pub bounciness: Option<f32>,
+    pub hardness: Option<f32>,
+    pub light: Option<u8>,
+}
+
+impl MaterialDef {
+    /// Parses a material file. Each `material <name>` line starts a new definition and every
+    /// following line up until the next definition sets one of its properties:
+    ///
+    /// - `label <text...>`
+    /// - `color <r> <g> <b> [a]`
+    /// - `collider <x0> <y0> <x1> <y1>`, which may be repeated, or `collider none`
+    /// - `friction <amount>`
+    /// - `bounciness <amount>`
+    /// - `hardness <ticks>`
+    /// - `light <level>`
+    ///
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn parse_file(text: &str) -> Result<Vec<Self>, MaterialParseError> {
+        let mut defs = Vec::<Self>::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let err = |message: &str| MaterialParseError {
+                line: i + 1,
+                message: message.to_string(),
+            };
+
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+
+            if let ["material", name] = parts[..] {
+                defs.push(Self {
+                    name: name.to_string(),
+                    ..Default::default()
+                });
+                continue;
+            }
+
+            let Some(def) = defs.last_mut() else {
+                return Err(err("expected `material <name>` before any properties"));
+            };
+
+            let float = |v: &str| v.parse::<f32>().map_err(|_| err("invalid number"));
+
+            match parts[..] {
+                ["label", ..] => def.label = Some(parts[1..].join(" ")),
+                ["color", r, g, b] => {
+                    def.color = Some(Color::new(float(r)?, float(g)?, float(b)?, 1.));
+                }
+                ["color", r, g, b, a] => {
+                    def.color = Some(Color::new(float(r)?, float(g)?, float(b)?, float(a)?));
+                }
+                ["collider", "none"] => def.colliders = Some(Vec::new()),
+                ["collider", x0, y0, x1, y1] => {
+                    def.colliders.get_or_insert_with(Vec::new).push(Aabb {
+                        min: Vec2::new(float(x0)?, float(y0)?),
+                        max: Vec2::new(float(x1)?, float(y1)?),
+                    });
+                }
+                ["friction", amount] => def.friction = Some(float(amount)?),
+                ["bounciness", amount] => def.bounciness = Some(float(amount)?),
+                ["hardness", ticks] => def.hardness = Some(float(ticks)?),
+                ["light", level] => {
+                    let level = level
+                        .parse::<u8>()
+                        .map_err(|_| err("invalid light level"))?;
+                    def.light = Some(level.min(MAX_LIGHT));
+                }
+                _ => return Err(err("unknown property")),
+            }
+        }
+
+        Ok(defs)
+    }
+
+    /// Applies the definition to the registry, registering a new material if none exists by that
+    /// name yet.
+    pub fn apply(&self, mut registry: Obj<MaterialRegistry>) -> MaterialId {
+        let id = match registry.lookup_by_name(&self.name) {
+            Some(id) => id,
+            None => registry.register(self.name.clone(), spawn_entity(())),
+        };
+        let descriptor = registry.lookup(id);
+
+        if let Some(label) = &self.label {
+            descriptor.get::<BaseMaterialDescriptor>().label = Some(label.clone());
+        }
+
+        if let Some(hardness) = self.hardness {
+            descriptor.get::<BaseMaterialDescriptor>().hardness = hardness;
+        }
+
+        if let Some(light) = self.light {
+            descriptor.get::<BaseMaterialDescriptor>().light = light;
+        }
+
+        if let Some(color) = self.color {
+            match descriptor.try_get::<SolidTileMaterial>() {
+                Some(mut solid) => solid.color = color,
+                None => {
+                    descriptor.insert(SolidTileMaterial { color });
+                }
+            }
+        }
+
+        if let Some(colliders) = &self.colliders {
+            descriptor.insert(TileColliderDescriptor::new(colliders.iter().copied()));
+        }
+
+        if self.friction.is_some() || self.bounciness.is_some() {
+            let mut physics = descriptor
+                .try_get::<TilePhysicsDescriptor>()
+                .map_or(TilePhysicsDescriptor::DEFAULT, |physics| *physics);
+
+            if let Some(friction) = self.friction {
+                physics.friction = friction;
+            }
+
+            if let Some(bounciness) = self.bounciness {
+                physics.bounciness = bounciness;
+            }
+
+            descriptor.insert(physics);
+        }
+
+        id
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MaterialParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for MaterialParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "material error on line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for MaterialParseError {}
+
+// === MaterialHotReload === //
+
+#[derive(Debug, Resource)]
+pub struct MaterialHotReload {
+    /// The material file to load. Missing files are silently ignored.
+    pub path: PathBuf,
+
+    /// How often the file's modification time is checked for changes.
+    pub poll_interval: Duration,
+
+    last_poll: Option<Instant>,
+    last_modified: Option<SystemTime>,
+}
+
+impl Default for MaterialHotReload {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("materials.txt"),
+            poll_interval: Duration::from_secs(1),
+            last_poll: None,
+            last_modified: None,
+        }
+    }
+}
+
+impl MaterialHotReload {
+    fn modified(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path)
+            .and_then(|meta| meta.modified())
+            .ok()
+    }
+
+    /// Loads the material file into every registry.
+    fn load(&mut self, registries: impl IntoIterator<Item = Obj<MaterialRegistry>>) {
+        self.last_modified = self.modified();
+
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return,
+            Err(err) => {
+                log::error!("Failed to read {}: {err}", self.path.display());
+                return;
+            }
+        };
+
+        let defs = match MaterialDef::parse_file(&text) {
+            Ok(defs) => defs,
+            Err(err) => {
+                log::error!("Failed to parse {}: {err}", self.path.display());
+                return;
+            }
+        };
+
+        for registry in registries {
+            for def in &defs {
+                def.apply(registry);
+            }
+        }
+
+        log::info!(
+            "Loaded {} material definitions from {}",
+            defs.len(),
+            self.path.display()
+        );
+    }
+}
+
+// === Systems === //
+
+type MaterialDefAccess<'a> = (
+    &'a mut MaterialRegistry,
+    &'a mut BaseMaterialDescriptor,
+    &'a mut SolidTileMaterial,
+    &'a mut TileColliderDescriptor,
+    &'a mut TilePhysicsDescriptor,
+);
+
+pub fn sys_load_material_defs(
+    mut rand: RandomAccess<MaterialDefAccess>,
+    query: Query<&ObjOwner<MaterialRegistry>>,
+    mut reload: ResMut<MaterialHotReload>,
+) {
+    rand.provide(|| {
+        reload.load(query.iter().map(|&ObjOwner(registry)| registry));
+    });
+}
+
+pub fn sys_hot_reload_material_defs(
+    mut rand: RandomAccess<MaterialDefAccess>,
+    query: Query<&ObjOwner<MaterialRegistry>>,
+    mut reload: ResMut<MaterialHotReload>,
+) {
+    let forced = is_key_pressed(KeyCode::F7);
+
+    let poll_due = reload
+        .last_poll
+        .map_or(true, |last| last.elapsed() >= reload.poll_interval);
+
+    if !forced && !poll_due {
+        return;
+    }
+
+    reload.last_poll = Some(Instant::now());
+
+    if !forced && reload.modified() == reload.last_modified {
+        return;
+    }
+
+    rand.provide(|| {
+        reload.load(query.iter().map(|&ObjOwner(registry)| registry));
+    });
+}
@@ -1,5 +1,19 @@
+pub mod autotile;
+pub mod breaking;
+pub mod broadphase;
 pub mod collider;
 pub mod data;
+pub mod draw_list;
+pub mod edit_log;
+pub mod generator;
+pub mod hibernation;
 pub mod kinematic;
+pub mod lighting;
+pub mod liquid;
 pub mod material;
+pub mod material_defs;
+pub mod pathfind;
 pub mod render;
+pub mod schematic;
+pub mod tile_entity;
+pub mod visibility;
@@ -0,0 +1,201 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use macroquad::math::IVec2;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use super::kinematic::KinematicApi;
+
+// === Pathfinding === //
+
+/// The cost of moving to an orthogonally adjacent tile. Diagonal moves cost
+/// [`DIAGONAL_COST`], approximating `sqrt(2)` in integer arithmetic.
+pub const STRAIGHT_COST: u32 = 10;
+pub const DIAGONAL_COST: u32 = 14;
+
+const NEIGHBORS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(0, -1),
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+];
+
+/// The octile distance between two tiles, which never overestimates the true cost of a path
+/// between them.
+fn heuristic(a: IVec2, b: IVec2) -> u32 {
+    let delta = (a - b).abs();
+    let (min, max) = (delta.x.min(delta.y) as u32, delta.x.max(delta.y) as u32);
+    DIAGONAL_COST * min + STRAIGHT_COST * (max - min)
+}
+
+/// Finds the cheapest path of non-solid tiles from `start` to `goal` using A*, moving in all eight
+/// directions. Diagonal moves are only allowed if both tiles they cut across are free so that
+/// actors never clip the corners of solid tiles.
+///
+/// The returned path starts at the tile after `start` and ends at `goal`. Returns `None` if no path
+/// was found after expanding `max_nodes` tiles.
+pub fn find_path(
+    kinematics: &mut KinematicApi,
+    start: IVec2,
+    goal: IVec2,
+    max_nodes: usize,
+) -> Option<Vec<IVec2>> {
+    search(
+        kinematics,
+        start,
+        goal,
+        max_nodes,
+        &mut FxHashMap::default(),
+        |_| {},
+    )
+}
+
+/// Like [`find_path`] but records the state of the search into `trace` so that it can be
+/// visualized.
+pub fn find_path_traced(
+    kinematics: &mut KinematicApi,
+    start: IVec2,
+    goal: IVec2,
+    max_nodes: usize,
+    trace: &mut PathTrace,
+) -> Option<Vec<IVec2>> {
+    trace.start = start;
+    trace.goal = goal;
+    trace.costs.clear();
+    trace.closed.clear();
+
+    let path = search(
+        kinematics,
+        start,
+        goal,
+        max_nodes,
+        &mut trace.costs,
+        |tile| {
+            trace.closed.insert(tile);
+        },
+    );
+
+    trace.path.clone_from(&path);
+    path
+}
+
+fn search(
+    kinematics: &mut KinematicApi,
+    start: IVec2,
+    goal: IVec2,
+    max_nodes: usize,
+    costs: &mut FxHashMap<IVec2, u32>,
+    mut on_expand: impl FnMut(IVec2),
+) -> Option<Vec<IVec2>> {
+    if kinematics.is_solid_tile(goal) {
+        return None;
+    }
+
+    let mut open = BinaryHeap::new();
+    let mut came_from = FxHashMap::<IVec2, IVec2>::default();
+
+    // `IVec2` isn't `Ord` so we store its components in the heap instead.
+    open.push((Reverse(heuristic(start, goal)), start.x, start.y));
+    costs.insert(start, 0);
+
+    let mut expanded = 0;
+
+    while let Some((_, x, y)) = open.pop() {
+        let tile = IVec2::new(x, y);
+
+        if tile == goal {
+            let mut path = vec![goal];
+            let mut curr = goal;
+            while let Some(&prev) = came_from.get(&curr) {
+                if prev == start {
+                    break;
+                }
+                path.push(prev);
+                curr = prev;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        expanded += 1;
+        if expanded > max_nodes {
+            return None;
+        }
+
+        on_expand(tile);
+
+        let cost = costs[&tile];
+
+        for offset in NEIGHBORS {
+            let neighbor = tile + offset;
+            if kinematics.is_solid_tile(neighbor) {
+                continue;
+            }
+
+            let is_diagonal = offset.x != 0 && offset.y != 0;
+            if is_diagonal
+                && (kinematics.is_solid_tile(tile + IVec2::new(offset.x, 0))
+                    || kinematics.is_solid_tile(tile + IVec2::new(0, offset.y)))
+            {
+                continue;
+            }
+
+            let new_cost = cost
+                + if is_diagonal {
+                    DIAGONAL_COST
+                } else {
+                    STRAIGHT_COST
+                };
+            if costs.get(&neighbor).is_some_and(|&old| old <= new_cost) {
+                continue;
+            }
+
+            costs.insert(neighbor, new_cost);
+            came_from.insert(neighbor, tile);
+            open.push((
+                Reverse(new_cost + heuristic(neighbor, goal)),
+                neighbor.x,
+                neighbor.y,
+            ));
+        }
+    }
+
+    None
+}
+
+// === PathTrace === //
+
+/// The state of a path search at the moment it ended, as recorded by [`find_path_traced`].
+#[derive(Debug, Clone, Default)]
+pub struct PathTrace {
+    pub start: IVec2,
+    pub goal: IVec2,
+
+    /// The cost of the cheapest known path from `start` to every tile the search reached.
+    pub costs: FxHashMap<IVec2, u32>,
+
+    /// The tiles whose neighbors were explored.
+    pub closed: FxHashSet<IVec2>,
+
+    /// The path which was found, if any.
+    pub path: Option<Vec<IVec2>>,
+}
+
+impl PathTrace {
+    /// The tiles which were reached but never explored.
+    pub fn open(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.costs
+            .keys()
+            .copied()
+            .filter(|tile| !self.closed.contains(tile))
+    }
+
+    /// The estimated cost of the path from `tile` to the goal which the search used to decide
+    /// which tiles to explore first.
+    pub fn estimate(&self, tile: IVec2) -> u32 {
+        heuristic(tile, self.goal)
+    }
+}
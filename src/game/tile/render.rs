@@ -1,50 +1,226 @@
+use std::f32::consts::TAU;
+
 use bevy_ecs::{
     component::Component,
     system::{Query, Res},
 };
-use macroquad::color::Color;
+use macroquad::{
+    color::Color,
+    math::{IVec2, UVec2, Vec2},
+    texture::{FilterMode, Image, Texture2D},
+    time::get_time,
+};
 
 use crate::{
     game::{
         actor::camera::{ActiveCamera, VirtualCamera},
-        math::draw::draw_rectangle_aabb,
+        math::{aabb::Aabb, draw::QuadBatch},
     },
     random_component,
-    util::arena::{ObjOwner, RandomAccess},
+    util::arena::{Obj, ObjOwner, RandomAccess},
 };
 
 use super::{
-    data::{TileChunk, TileWorld},
+    autotile::AutoTileMaterial,
+    data::{TileChunk, TileLayers, TileWorld},
+    draw_list::{prepare_draw_lists, AutoTileStyle, LayerSnapshot, TileStyle, TileStyles},
+    liquid::MAX_FILL,
     material::{MaterialCache, MaterialId, MaterialRegistry},
 };
 
 // === RenderableWorld === //
 
-random_component!(SolidTileMaterial);
+random_component!(SolidTileMaterial, TexturedTileMaterial, FluidTileMaterial);
 
 #[derive(Debug, Default, Component)]
 pub struct RenderableWorld {
-    cache: MaterialCache<SolidTileMaterial>,
+    autotile_cache: MaterialCache<AutoTileMaterial>,
+    solid_cache: MaterialCache<SolidTileMaterial>,
+    textured_cache: MaterialCache<TexturedTileMaterial>,
+    fluid_cache: MaterialCache<FluidTileMaterial>,
+}
+
+impl RenderableWorld {
+    /// Resolves the style of every material in `registry`, pushing the textures they use into
+    /// `textures`. Styles refer to their textures by their index in that list.
+    pub fn resolve_styles(
+        &mut self,
+        registry: &MaterialRegistry,
+        textures: &mut Vec<Texture2D>,
+    ) -> TileStyles {
+        let mut add_texture = |texture: &Texture2D| {
+            let index = textures.iter().position(|other| other == texture);
+            index.unwrap_or_else(|| {
+                textures.push(texture.clone());
+                textures.len() - 1
+            })
+        };
+
+        let styles = registry
+            .ids()
+            .map(|material| {
+                if material == MaterialId::AIR {
+                    TileStyle::Invisible
+                } else if self.fluid_cache.get(registry, material).is_some() {
+                    TileStyle::Fluid
+                } else if let Some(autotile) = self.autotile_cache.get(registry, material) {
+                    TileStyle::AutoTile(AutoTileStyle {
+                        texture: add_texture(autotile.atlas.texture()),
+                        layout: autotile.layout,
+                        uvs: (0..autotile.layout.sprite_count())
+                            .map(|sprite| autotile.atlas.uv(autotile.first_sprite + sprite))
+                            .collect(),
+                        tint: autotile.tint,
+                        connect_to_solids: autotile.connect_to_solids,
+                    })
+                } else if let Some(textured) = self.textured_cache.get(registry, material) {
+                    TileStyle::Textured {
+                        texture: add_texture(&textured.texture),
+                        uv: textured.uv,
+                        tint: textured.tint,
+                    }
+                } else if let Some(solid) = self.solid_cache.get(registry, material) {
+                    TileStyle::Solid(solid.color)
+                } else {
+                    TileStyle::Invisible
+                }
+            })
+            .collect();
+
+        TileStyles::from_styles(styles)
+    }
 }
 
+/// A material descriptor rendered as a flat colored square. Material descriptors may carry either
+/// this or a [`TexturedTileMaterial`], with the latter taking precedence if both are present.
 #[derive(Debug)]
 pub struct SolidTileMaterial {
     pub color: Color,
 }
 
+#[derive(Debug)]
+pub struct TexturedTileMaterial {
+    pub texture: Texture2D,
+    pub uv: Aabb,
+    pub tint: Color,
+}
+
+/// A material descriptor for fluids. Horizontal runs of fluid tiles are merged into single
+/// translucent quads and runs exposed to air are given an animated wavy surface.
+#[derive(Debug, Clone)]
+pub struct FluidTileMaterial {
+    pub color: Color,
+
+    /// The height of the surface waves as a fraction of the tile size.
+    pub wave_amplitude: f32,
+
+    /// The length of a single wave, in tiles.
+    pub wave_length: f32,
+
+    /// The speed at which waves travel, in tiles per second.
+    pub wave_speed: f32,
+}
+
+impl FluidTileMaterial {
+    /// The number of segments used to approximate the wavy surface of a single tile.
+    pub const SEGMENTS_PER_TILE: i32 = 4;
+
+    /// Computes how far below the top of its tile the surface lies at the given tile-space x
+    /// coordinate.
+    pub fn surface_offset(&self, x: f32, time: f32) -> f32 {
+        let phase = (x - time * self.wave_speed) / self.wave_length;
+        self.wave_amplitude * (1. + (phase * TAU).sin()) / 2.
+    }
+}
+
+// === TileAtlas === //
+
+/// A texture composed of a grid of equally-sized tile sprites.
+#[derive(Debug, Clone)]
+pub struct TileAtlas {
+    texture: Texture2D,
+    tile_size: UVec2,
+}
+
+impl TileAtlas {
+    pub fn new(texture: Texture2D, tile_size: UVec2) -> Self {
+        texture.set_filter(FilterMode::Nearest);
+        Self { texture, tile_size }
+    }
+
+    pub fn from_image(image: &Image, tile_size: UVec2) -> Self {
+        Self::new(Texture2D::from_image(image), tile_size)
+    }
+
+    pub fn texture(&self) -> &Texture2D {
+        &self.texture
+    }
+
+    pub fn columns(&self) -> u32 {
+        (self.texture.width() as u32 / self.tile_size.x).max(1)
+    }
+
+    /// Computes the normalized UV rect of the sprite at the given row-major index.
+    pub fn uv(&self, index: u32) -> Aabb {
+        let texture_size = self.texture.size();
+        let tile_size = self.tile_size.as_vec2();
+        let origin =
+            UVec2::new(index % self.columns(), index / self.columns()).as_vec2() * tile_size;
+
+        Aabb::new_sized(origin / texture_size, tile_size / texture_size)
+    }
+
+    pub fn material(&self, index: u32, tint: Color) -> TexturedTileMaterial {
+        TexturedTileMaterial {
+            texture: self.texture.clone(),
+            uv: self.uv(index),
+            tint,
+        }
+    }
+}
+
+/// Builds an atlas image by invoking `pixel` for every texel of every sprite.
+pub fn paint_atlas_image(
+    sprites: u32,
+    tile_size: UVec2,
+    mut pixel: impl FnMut(u32, UVec2) -> Color,
+) -> Image {
+    let mut image = Image::gen_image_color(
+        (tile_size.x * sprites) as u16,
+        tile_size.y as u16,
+        Color::new(0., 0., 0., 0.),
+    );
+
+    for sprite in 0..sprites {
+        for y in 0..tile_size.y {
+            for x in 0..tile_size.x {
+                let color = pixel(sprite, UVec2::new(x, y));
+                image.set_pixel(sprite * tile_size.x + x, y, color);
+            }
+        }
+    }
+
+    image
+}
+
 // === Systems === //
 
 pub fn sys_render_chunks(
     mut query: Query<(
         &ObjOwner<TileWorld>,
         &ObjOwner<MaterialRegistry>,
+        Option<&ObjOwner<TileLayers>>,
         &mut RenderableWorld,
     )>,
     mut rand: RandomAccess<(
         &TileWorld,
         &TileChunk,
+        &TileLayers,
         &MaterialRegistry,
         &SolidTileMaterial,
+        &TexturedTileMaterial,
+        &FluidTileMaterial,
+        &AutoTileMaterial,
         &VirtualCamera,
     )>,
     camera: Res<ActiveCamera>,
@@ -52,30 +228,174 @@ pub fn sys_render_chunks(
     let _guard = camera.apply();
 
     rand.provide(|| {
-        let camera = camera.camera.unwrap();
+        let visible = camera.camera.unwrap().visible_aabb();
+        let mut batches = LayerBatches::default();
+
+        for (&ObjOwner(world), &ObjOwner(registry), layers, mut renderable) in query.iter_mut() {
+            if !camera.shows(world) {
+                continue;
+            }
 
-        for (&ObjOwner(world), &ObjOwner(registry), mut cache) in query.iter_mut() {
-            let config = world.config();
             let registry = &*registry;
-            let cache = &mut cache.cache;
 
-            for tile in config
-                .actor_aabb_to_tile(camera.visible_aabb())
-                .inclusive()
-                .iter()
-            {
-                let material = world.tile(tile);
+            if let Some(&ObjOwner(layers)) = layers {
+                for layer in layers.iter() {
+                    render_layer(&mut batches, layer, registry, &mut renderable, visible);
+                }
+            } else {
+                render_layer(&mut batches, world, registry, &mut renderable, visible);
+            }
+        }
+    });
+}
 
-                if material == MaterialId::AIR {
+/// The opacity of the ambient occlusion shading at a seam between a solid tile and air.
+pub const AO_STRENGTH: f32 = 0.35;
+
+/// The width of the ambient occlusion shading as a fraction of the tile size.
+pub const AO_WIDTH: f32 = 0.3;
+
+#[derive(Default)]
+struct LayerBatches {
+    solid: QuadBatch,
+    textured: QuadBatch,
+    occlusion: QuadBatch,
+    fluid: QuadBatch,
+}
+
+fn render_layer(
+    batches: &mut LayerBatches,
+    world: Obj<TileWorld>,
+    registry: &MaterialRegistry,
+    renderable: &mut RenderableWorld,
+    visible: Aabb,
+) {
+    let tiles = world.config().actor_aabb_to_tile(visible).inclusive();
+
+    // Copy everything the tile pass reads out of the arenas so that the draw lists can be prepared
+    // in parallel, then submit them in order on the main thread.
+    let mut textures = Vec::new();
+    let styles = renderable.resolve_styles(registry, &mut textures);
+    let snapshot = LayerSnapshot::capture(world, tiles);
+
+    for list in prepare_draw_lists(&snapshot, &styles, tiles) {
+        for (rect, color) in list.solid {
+            batches.solid.push_rect(rect, color);
+        }
+
+        for (texture, rect, uv, tint) in list.textured {
+            batches
+                .textured
+                .push_textured(&textures[texture], rect, uv, tint);
+        }
+
+        for (corners, colors) in list.occlusion {
+            batches.occlusion.push_gradient(corners, colors);
+        }
+    }
+
+    render_fluids(&mut batches.fluid, world, registry, renderable, visible);
+
+    // Each layer must be fully submitted before the next one to preserve depth ordering.
+    batches.solid.flush();
+    batches.textured.flush();
+    batches.occlusion.flush();
+    batches.fluid.flush();
+}
+
+fn render_fluids(
+    batch: &mut QuadBatch,
+    world: Obj<TileWorld>,
+    registry: &MaterialRegistry,
+    renderable: &mut RenderableWorld,
+    visible: Aabb,
+) {
+    let config = world.config();
+    let tiles = config.actor_aabb_to_tile(visible).inclusive();
+    let time = get_time() as f32;
+
+    for y in tiles.min.y..tiles.max.y {
+        // Merge horizontal runs of the same fluid which are either all exposed to air or all
+        // submerged.
+        let mut run = None::<(i32, MaterialId, bool)>;
+
+        for x in tiles.min.x..=tiles.max.x {
+            let pos = IVec2::new(x, y);
+            let material = world.tile(pos);
+            let is_fluid = x < tiles.max.x
+                && material != MaterialId::AIR
+                && renderable.fluid_cache.get(registry, material).is_some();
+
+            // Partially filled tiles can't be merged with their neighbors.
+            let level = if is_fluid {
+                world.fill_level(pos)
+            } else {
+                MAX_FILL
+            };
+            let is_partial = level < MAX_FILL;
+
+            let is_surface = is_fluid && world.tile(pos - IVec2::Y) != material;
+
+            if let Some((start, run_material, run_surface)) = run {
+                if is_fluid && !is_partial && run_material == material && run_surface == is_surface
+                {
                     continue;
                 }
 
-                let Some(material) = cache.get(registry, material) else {
-                    continue;
-                };
+                let fluid = renderable.fluid_cache.get(registry, run_material).unwrap();
+                let rect = config
+                    .tile_to_actor_rect(IVec2::new(start, y))
+                    .translate_extend(Vec2::X * (x - start - 1) as f32 * config.size);
+
+                if run_surface {
+                    push_fluid_surface(batch, &fluid, rect, config.size, time);
+                } else {
+                    batch.push_rect(rect, fluid.color);
+                }
+
+                run = None;
+            }
 
-                draw_rectangle_aabb(config.tile_to_actor_rect(tile), material.color);
+            if is_partial {
+                let fluid = renderable.fluid_cache.get(registry, material).unwrap();
+                let mut rect = config.tile_to_actor_rect(pos);
+                rect.min.y = rect.max.y - rect.h() * level as f32 / MAX_FILL as f32;
+                batch.push_rect(rect, fluid.color);
+            } else if is_fluid {
+                run = Some((x, material, is_surface));
             }
         }
-    });
+    }
+}
+
+fn push_fluid_surface(
+    batch: &mut QuadBatch,
+    fluid: &FluidTileMaterial,
+    rect: Aabb,
+    tile_size: f32,
+    time: f32,
+) {
+    let segments =
+        ((rect.w() / tile_size).round() as i32 * FluidTileMaterial::SEGMENTS_PER_TILE).max(1);
+
+    let surface_at = |i: i32| {
+        let x = rect.min.x + rect.w() * i as f32 / segments as f32;
+        let y = rect.min.y + fluid.surface_offset(x / tile_size, time) * tile_size;
+        Vec2::new(x, y)
+    };
+
+    for i in 0..segments {
+        let left = surface_at(i);
+        let right = surface_at(i + 1);
+
+        batch.push_corners(
+            [
+                left,
+                right,
+                Vec2::new(right.x, rect.max.y),
+                Vec2::new(left.x, rect.max.y),
+            ],
+            fluid.color,
+        );
+    }
 }
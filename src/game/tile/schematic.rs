@@ -0,0 +1,65 @@
+use macroquad::math::IVec2;
+
+use crate::game::math::aabb::AabbI;
+
+use super::material::MaterialId;
+
+// === Schematic === //
+
+/// A reusable, hand-authored arrangement of tiles which can be stamped into a world. Cells set to
+/// `None` leave the underlying tile untouched.
+#[derive(Debug, Clone)]
+pub struct Schematic {
+    size: IVec2,
+    tiles: Vec<Option<MaterialId>>,
+}
+
+impl Schematic {
+    /// Parses a schematic from rows of characters, mapping each character through `palette`.
+    /// Characters not present in the palette are treated as empty cells.
+    pub fn from_rows(rows: &[&str], palette: &[(char, MaterialId)]) -> Self {
+        let width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+        let size = IVec2::new(width as i32, rows.len() as i32);
+        let mut tiles = vec![None; width * rows.len()];
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, ch) in row.chars().enumerate() {
+                tiles[y * width + x] = palette
+                    .iter()
+                    .find(|(key, _)| *key == ch)
+                    .map(|&(_, material)| material);
+            }
+        }
+
+        Self { size, tiles }
+    }
+
+    pub fn size(&self) -> IVec2 {
+        self.size
+    }
+
+    /// The tile-space rect covered by this schematic when placed with its top-left corner at
+    /// `origin`.
+    pub fn bounds(&self, origin: IVec2) -> AabbI {
+        AabbI::new_sized(origin, self.size)
+    }
+
+    pub fn get(&self, rel: IVec2) -> Option<MaterialId> {
+        if !AabbI::new_sized(IVec2::ZERO, self.size).contains(rel) {
+            return None;
+        }
+
+        self.tiles[(rel.y * self.size.x + rel.x) as usize]
+    }
+
+    /// Iterates over every non-empty cell as a `(relative position, material)` pair.
+    pub fn iter(&self) -> impl Iterator<Item = (IVec2, MaterialId)> + '_ {
+        AabbI::new_sized(IVec2::ZERO, self.size)
+            .iter()
+            .filter_map(|rel| Some((rel, self.get(rel)?)))
+    }
+}
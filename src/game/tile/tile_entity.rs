@@ -0,0 +1,75 @@
+use bevy_ecs::{component::Component, entity::Entity, event::Event};
+use macroquad::math::IVec2;
+
+use crate::{
+    random_component, random_event,
+    util::arena::{send_event, spawn_entity, Obj, RandomEntityExt},
+};
+
+use super::{
+    data::TileWorld,
+    material::{MaterialId, MaterialRegistry},
+};
+
+random_component!(TileEntityDescriptor);
+random_event!(TileEntityCreated);
+
+// === TileEntityDescriptor === //
+
+/// A material descriptor for tiles which need state of their own, such as chests, spawners, and
+/// doors. Every tile of such a material is given its own [`TileEntity`] when it is placed, which is
+/// despawned again once the tile is replaced or its chunk is unloaded.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct TileEntityDescriptor;
+
+// === TileEntity === //
+
+/// The tile owning a tile entity.
+#[derive(Debug, Copy, Clone, Component)]
+pub struct TileEntity {
+    pub world: Obj<TileWorld>,
+    pub pos: IVec2,
+    pub material: MaterialId,
+}
+
+/// Sent whenever a tile entity is spawned so that the material's state can be attached to it.
+#[derive(Debug, Event)]
+pub struct TileEntityCreated {
+    pub world: Obj<TileWorld>,
+    pub pos: IVec2,
+    pub material: MaterialId,
+    pub entity: Entity,
+}
+
+/// Spawns the tile entity for a tile of `material` at the world-space tile position `pos`, returning
+/// `None` if the material doesn't have tile entities.
+pub(super) fn spawn_tile_entity(
+    world: Obj<TileWorld>,
+    pos: IVec2,
+    material: MaterialId,
+) -> Option<Entity> {
+    if material == MaterialId::AIR {
+        return None;
+    }
+
+    // Layers without a registry, such as backgrounds, never have tile entities.
+    let registry = world.entity().try_get::<MaterialRegistry>()?;
+    registry
+        .lookup(material)
+        .try_get::<TileEntityDescriptor>()?;
+
+    let entity = spawn_entity(TileEntity {
+        world,
+        pos,
+        material,
+    });
+
+    send_event(TileEntityCreated {
+        world,
+        pos,
+        material,
+        entity,
+    });
+
+    Some(entity)
+}
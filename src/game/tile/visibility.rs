@@ -0,0 +1,276 @@
+use bevy_ecs::{
+    component::Component,
+    system::{Query, Res},
+};
+use macroquad::{color::Color, math::IVec2};
+use rustc_hash::FxHashSet;
+
+use crate::{
+    game::{
+        actor::{
+            camera::{ActiveCamera, VirtualCamera},
+            kinematic::Pos,
+        },
+        math::draw::QuadBatch,
+    },
+    util::{
+        arena::{ObjOwner, RandomAccess, RandomEntityExt},
+        lang::ensure_index,
+    },
+};
+
+use super::{
+    collider::InsideWorld,
+    data::{TileChunk, TileLayerConfig, TileWorld},
+    material::{MaterialId, MaterialRegistry},
+    render::FluidTileMaterial,
+};
+
+// === WorldVisibility === //
+
+/// The opacity of the overlay drawn over explored tiles which aren't currently in sight.
+pub const REMEMBERED_DARKNESS: f32 = 0.6;
+
+/// Fog of war for a world. Every tick, the tiles in sight of the world's [`Viewer`]s are found by
+/// shadow casting over its opaque tiles and marked as [explored](TileChunk::is_explored) in their
+/// chunks. Unexplored tiles are drawn black and explored tiles which are out of sight are dimmed.
+#[derive(Debug, Default, Component)]
+pub struct WorldVisibility {
+    visible: FxHashSet<IVec2>,
+}
+
+impl WorldVisibility {
+    /// Whether a viewer could see the tile at `pos` as of the last update.
+    pub fn is_visible(&self, pos: IVec2) -> bool {
+        self.visible.contains(&pos)
+    }
+}
+
+/// Lets an actor reveal the tiles in its line of sight within `radius` tiles of it.
+#[derive(Debug, Copy, Clone, Component)]
+pub struct Viewer {
+    pub radius: i32,
+}
+
+impl Default for Viewer {
+    fn default() -> Self {
+        Self { radius: 20 }
+    }
+}
+
+/// Caches whether each material blocks sight. Like light, sight passes through air and fluids.
+#[derive(Default)]
+struct MaterialOpacityCache {
+    cache: Vec<Option<bool>>,
+}
+
+impl MaterialOpacityCache {
+    fn get(&mut self, registry: &MaterialRegistry, id: MaterialId) -> bool {
+        *ensure_index(&mut self.cache, id.0 as usize).get_or_insert_with(|| {
+            id != MaterialId::AIR && registry.lookup(id).try_get::<FluidTileMaterial>().is_none()
+        })
+    }
+}
+
+/// A row of tiles scanned by [`cast_sight`], `depth` tiles away from the origin and spanning the
+/// slopes from `start` to `end`.
+#[derive(Debug, Copy, Clone)]
+struct SightRow {
+    depth: i32,
+    start: f32,
+    end: f32,
+}
+
+impl SightRow {
+    fn columns(&self) -> impl Iterator<Item = i32> {
+        let depth = self.depth as f32;
+        let min = (depth * self.start + 0.5).floor() as i32;
+        let max = (depth * self.end - 0.5).ceil() as i32;
+        min..=max
+    }
+
+    /// Whether the tile at `col` lies within the row's slopes rather than merely overlapping them.
+    /// Only revealing floor tiles like these keeps sight symmetric.
+    fn is_symmetric(&self, col: i32) -> bool {
+        let (depth, col) = (self.depth as f32, col as f32);
+        col >= depth * self.start && col <= depth * self.end
+    }
+
+    fn next(&self) -> Self {
+        Self {
+            depth: self.depth + 1,
+            ..*self
+        }
+    }
+}
+
+fn slope(depth: i32, col: i32) -> f32 {
+    (2 * col - 1) as f32 / (2 * depth) as f32
+}
+
+/// Calls `reveal` for every tile in sight of `origin` using symmetric shadow casting, where
+/// `is_opaque` tells which tiles block sight. Opaque tiles are revealed themselves but hide the
+/// tiles behind them.
+fn cast_sight(
+    origin: IVec2,
+    radius: i32,
+    mut is_opaque: impl FnMut(IVec2) -> bool,
+    mut reveal: impl FnMut(IVec2),
+) {
+    reveal(origin);
+
+    let mut rows = Vec::new();
+
+    for dir in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+        // Each quadrant is scanned in rows moving away from the origin along `dir`.
+        let tile_at = |depth: i32, col: i32| origin + dir * depth + dir.perp() * col;
+
+        rows.push(SightRow {
+            depth: 1,
+            start: -1.,
+            end: 1.,
+        });
+
+        while let Some(mut row) = rows.pop() {
+            if row.depth > radius {
+                continue;
+            }
+
+            let mut prev_opaque = None;
+
+            for col in row.columns() {
+                let tile = tile_at(row.depth, col);
+                let opaque = is_opaque(tile);
+
+                if (opaque || row.is_symmetric(col))
+                    && row.depth * row.depth + col * col <= radius * radius
+                {
+                    reveal(tile);
+                }
+
+                match (prev_opaque, opaque) {
+                    (Some(true), false) => row.start = slope(row.depth, col),
+                    (Some(false), true) => rows.push(SightRow {
+                        end: slope(row.depth, col),
+                        ..row.next()
+                    }),
+                    _ => {}
+                }
+
+                prev_opaque = Some(opaque);
+            }
+
+            if prev_opaque == Some(false) {
+                rows.push(row.next());
+            }
+        }
+    }
+}
+
+// === Systems === //
+
+pub fn sys_update_visibility(
+    mut rand: RandomAccess<(
+        &TileWorld,
+        &mut TileChunk,
+        &MaterialRegistry,
+        &FluidTileMaterial,
+    )>,
+    mut query: Query<(&ObjOwner<TileWorld>, &mut WorldVisibility)>,
+    viewers: Query<(&InsideWorld, &Pos, &Viewer)>,
+) {
+    rand.provide(|| {
+        for (&ObjOwner(world), mut visibility) in query.iter_mut() {
+            let config = world.config();
+            let registry = world.entity().get::<MaterialRegistry>();
+            let mut cache = MaterialOpacityCache::default();
+
+            visibility.visible.clear();
+
+            for (&InsideWorld(viewer_world), &Pos(pos), viewer) in viewers.iter() {
+                if viewer_world != world {
+                    continue;
+                }
+
+                cast_sight(
+                    config.actor_to_tile(pos),
+                    viewer.radius,
+                    |tile| cache.get(&registry, world.tile(tile)),
+                    |tile| {
+                        visibility.visible.insert(tile);
+                    },
+                );
+            }
+
+            for &tile in &visibility.visible {
+                let (chunk, block) = TileLayerConfig::decompose_world_pos(tile);
+                if let Some(mut chunk) = world.chunk(chunk) {
+                    chunk.explore(block);
+                }
+            }
+        }
+    });
+}
+
+pub fn sys_render_fog_of_war(
+    mut rand: RandomAccess<(&TileWorld, &TileChunk, &VirtualCamera)>,
+    query: Query<(&ObjOwner<TileWorld>, &WorldVisibility)>,
+    camera: Res<ActiveCamera>,
+) {
+    let _guard = camera.apply();
+
+    rand.provide(|| {
+        let Some(active) = camera.camera else {
+            return;
+        };
+
+        let Ok((&ObjOwner(world), visibility)) = query.get(active.entity()) else {
+            return;
+        };
+
+        let config = world.config();
+        let visible = config.actor_aabb_to_tile(active.visible_aabb()).inclusive();
+
+        let fog_at = |pos: IVec2| {
+            if visibility.is_visible(pos) {
+                return 0.;
+            }
+
+            let (chunk, block) = TileLayerConfig::decompose_world_pos(pos);
+            match world.chunk(chunk) {
+                Some(chunk) if chunk.is_explored(block) => REMEMBERED_DARKNESS,
+                _ => 1.,
+            }
+        };
+
+        // Like the lighting overlay, each corner is shaded by the average of the four tiles around
+        // it so that the edge of the player's sight fades out smoothly.
+        let corner_fog = |corner: IVec2| {
+            let sum = fog_at(corner)
+                + fog_at(corner - IVec2::X)
+                + fog_at(corner - IVec2::Y)
+                + fog_at(corner - IVec2::ONE);
+
+            Color::new(0., 0., 0., sum / 4.)
+        };
+
+        let mut batch = QuadBatch::default();
+
+        for tile in visible.iter() {
+            let colors = [
+                corner_fog(tile),
+                corner_fog(tile + IVec2::X),
+                corner_fog(tile + IVec2::ONE),
+                corner_fog(tile + IVec2::Y),
+            ];
+
+            if colors.iter().all(|color| color.a <= 0.) {
+                continue;
+            }
+
+            batch.push_gradient(config.tile_to_actor_rect(tile).corners(), colors);
+        }
+
+        batch.flush();
+    });
+}
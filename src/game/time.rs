@@ -0,0 +1,344 @@
+use std::f32::consts::TAU;
+
+use bevy_ecs::{
+    event::{Event, EventWriter},
+    schedule::State,
+    system::{Res, ResMut, Resource},
+};
+use macroquad::{
+    color::{Color, YELLOW},
+    input::{is_key_pressed, KeyCode},
+    math::Vec2,
+    text::{draw_text, measure_text},
+    window::screen_width,
+};
+
+use crate::util::arena::RandomAccess;
+
+use super::{
+    actor::{
+        camera::{ActiveCamera, VirtualCamera},
+        inspector::Inspector,
+    },
+    math::draw::draw_rectangle_aabb,
+    replay::ReplayState,
+    scene::GameScene,
+};
+
+// === GameTime === //
+
+/// The amount of game time simulated by a single run of the `Update` schedule, in seconds.
+pub const TICK_DURATION: f32 = 1. / 60.;
+
+pub const MIN_TIME_SCALE: f32 = 1. / 16.;
+pub const MAX_TIME_SCALE: f32 = 4.;
+
+/// The most updates run during a single rendered frame. Frames which take longer than this drop
+/// the excess time rather than trying to catch up, which would only make the next frame slower.
+pub const MAX_UPDATES_PER_FRAME: u32 = 5;
+
+/// Controls how many times the `Update` schedule runs during each rendered frame of a live
+/// session. Updates run at a fixed rate of one per [`TICK_DURATION`] of real time regardless of the
+/// display's refresh rate. The replay viewer has its own playback controls and ignores this
+/// resource.
+#[derive(Debug, Resource)]
+pub struct GameTime {
+    pub paused: bool,
+
+    /// The rate at which game time passes relative to real time.
+    pub time_scale: f32,
+
+    budget: f32,
+    pending_steps: u32,
+}
+
+impl Default for GameTime {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            time_scale: 1.,
+            budget: 0.,
+            pending_steps: 0,
+        }
+    }
+}
+
+impl GameTime {
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.time_scale = scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+        self.budget = 0.;
+    }
+
+    /// Requests a single update to be run while paused.
+    pub fn step(&mut self) {
+        self.pending_steps += 1;
+    }
+
+    pub fn is_normal_speed(&self) -> bool {
+        !self.paused && self.time_scale == 1.
+    }
+
+    /// Determines how many times the `Update` schedule should run during a rendered frame which
+    /// took `frame_time` seconds.
+    pub fn take_updates(&mut self, frame_time: f32) -> u32 {
+        if self.paused {
+            return std::mem::take(&mut self.pending_steps);
+        }
+
+        self.pending_steps = 0;
+        self.budget += frame_time / TICK_DURATION * self.time_scale;
+        let steps = self.budget.floor();
+        self.budget -= steps;
+
+        if steps > MAX_UPDATES_PER_FRAME as f32 {
+            self.budget = 0.;
+            return MAX_UPDATES_PER_FRAME;
+        }
+
+        steps as u32
+    }
+
+    /// The fraction of a tick which has elapsed since the last update, from zero to one. Rendering
+    /// actors this far between their previous and current positions hides the difference between
+    /// the tick rate and the display's refresh rate.
+    pub fn interpolation_alpha(&self) -> f32 {
+        if self.paused {
+            1.
+        } else {
+            self.budget
+        }
+    }
+}
+
+/// How far between the previous and current tick the world is being rendered this frame. See
+/// [`GameTime::interpolation_alpha`].
+#[derive(Debug, Copy, Clone, Resource)]
+pub struct RenderAlpha(pub f32);
+
+impl Default for RenderAlpha {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+impl RenderAlpha {
+    pub fn lerp(self, prev: Vec2, curr: Vec2) -> Vec2 {
+        prev.lerp(curr, self.0)
+    }
+}
+
+// === WorldClock === //
+
+/// The length of a full day and night, in seconds of game time.
+pub const DAY_LENGTH: f32 = 600.;
+
+/// The time of day at which the sun rises. Times of day go from `0` at midnight to `1` at the next
+/// midnight and the sun sets at `1 - DAWN`.
+pub const DAWN: f32 = 0.25;
+
+/// The tint drawn over the world in the middle of the night.
+const NIGHT_TINT: Color = Color::new(0.02, 0.03, 0.12, 0.55);
+
+/// The tint drawn over the world while the sun is crossing the horizon.
+const TWILIGHT_TINT: Color = Color::new(0.9, 0.45, 0.2, 0.2);
+
+/// The time of day shared by every world. It only advances while the simulation does so that it
+/// stays in sync with everything else under replays and time controls.
+#[derive(Debug, Clone, Resource)]
+pub struct WorldClock {
+    /// The number of days that have fully elapsed.
+    day: u32,
+    tick: u32,
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        let mut clock = Self { day: 0, tick: 0 };
+        clock.set_time_of_day(0.3);
+        clock
+    }
+}
+
+impl WorldClock {
+    /// The number of ticks in a full day.
+    pub fn day_ticks() -> u32 {
+        (DAY_LENGTH / TICK_DURATION) as u32
+    }
+
+    pub fn day(&self) -> u32 {
+        self.day
+    }
+
+    /// The number of ticks since midnight of the first day.
+    pub fn ticks(&self) -> u64 {
+        self.day as u64 * Self::day_ticks() as u64 + self.tick as u64
+    }
+
+    /// The fraction of the current day which has elapsed, starting at midnight.
+    pub fn time_of_day(&self) -> f32 {
+        self.tick as f32 / Self::day_ticks() as f32
+    }
+
+    pub fn set_time_of_day(&mut self, time: f32) {
+        self.tick = (time.rem_euclid(1.) * Self::day_ticks() as f32) as u32 % Self::day_ticks();
+    }
+
+    pub fn phase(&self) -> DayPhase {
+        if (DAWN..1. - DAWN).contains(&self.time_of_day()) {
+            DayPhase::Day
+        } else {
+            DayPhase::Night
+        }
+    }
+
+    pub fn is_night(&self) -> bool {
+        self.phase() == DayPhase::Night
+    }
+
+    /// How bright the sky is, from `0` at night to `1` during the day. This fades smoothly as the
+    /// sun crosses the horizon.
+    pub fn daylight(&self) -> f32 {
+        // The sun's height follows a sine wave which crosses the horizon at dawn and dusk.
+        let height = (self.time_of_day() - DAWN) * TAU;
+        let t = (height.sin() / 0.4 + 0.5).clamp(0., 1.);
+        t * t * (3. - 2. * t)
+    }
+
+    /// The color drawn over the world to darken it at night and redden it at dawn and dusk.
+    pub fn tint(&self) -> Color {
+        let daylight = self.daylight();
+        let twilight = 1. - (daylight * 2. - 1.).abs();
+
+        let rgb = NIGHT_TINT
+            .to_vec()
+            .truncate()
+            .lerp(TWILIGHT_TINT.to_vec().truncate(), twilight);
+        let alpha = NIGHT_TINT.a * (1. - daylight) + TWILIGHT_TINT.a * twilight;
+
+        Color::from_vec(rgb.extend(alpha))
+    }
+
+    /// Advances the clock by a single tick, returning the new phase if the sun just rose or set.
+    pub fn tick(&mut self) -> Option<DayPhase> {
+        let before = self.phase();
+
+        self.tick += 1;
+        if self.tick >= Self::day_ticks() {
+            self.tick = 0;
+            self.day += 1;
+        }
+
+        let after = self.phase();
+        (after != before).then_some(after)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum DayPhase {
+    Day,
+    Night,
+}
+
+/// Sent at dawn and dusk.
+#[derive(Debug, Event)]
+pub struct DayPhaseChanged {
+    pub phase: DayPhase,
+    pub day: u32,
+}
+
+// === Systems === //
+
+pub fn sys_advance_world_clock(
+    mut clock: ResMut<WorldClock>,
+    mut events: EventWriter<DayPhaseChanged>,
+) {
+    if let Some(phase) = clock.tick() {
+        events.send(DayPhaseChanged {
+            phase,
+            day: clock.day(),
+        });
+    }
+}
+
+pub fn sys_render_day_night_tint(
+    mut rand: RandomAccess<&VirtualCamera>,
+    clock: Res<WorldClock>,
+    camera: Res<ActiveCamera>,
+) {
+    let tint = clock.tint();
+    if tint.a <= 0. {
+        return;
+    }
+
+    let _guard = camera.apply();
+
+    rand.provide(|| {
+        if let Some(active) = camera.camera {
+            draw_rectangle_aabb(active.visible_aabb(), tint);
+        }
+    });
+}
+
+pub fn sys_update_render_alpha(
+    mut alpha: ResMut<RenderAlpha>,
+    time: Res<GameTime>,
+    replay: Res<ReplayState>,
+    scene: Res<State<GameScene>>,
+) {
+    // The replay viewer runs updates in bursts and the simulation doesn't run at all outside of the
+    // game so there's nothing to interpolate between.
+    alpha.0 = if replay.is_viewing() || *scene.get() != GameScene::InGame {
+        1.
+    } else {
+        time.interpolation_alpha()
+    };
+}
+
+pub fn sys_handle_time_controls(
+    mut time: ResMut<GameTime>,
+    replay: Res<ReplayState>,
+    inspector: Res<Inspector>,
+) {
+    // The replay viewer has its own playback controls.
+    if replay.is_viewing() {
+        return;
+    }
+
+    if is_key_pressed(KeyCode::P) {
+        time.paused = !time.paused;
+    }
+
+    if is_key_pressed(KeyCode::Period) && time.paused {
+        time.step();
+    }
+
+    // The inspector uses these keys to edit fields while it's open.
+    if inspector.enabled {
+        return;
+    }
+
+    if is_key_pressed(KeyCode::Minus) {
+        let scale = time.time_scale / 2.;
+        time.set_time_scale(scale);
+    }
+
+    if is_key_pressed(KeyCode::Equal) {
+        let scale = time.time_scale * 2.;
+        time.set_time_scale(scale);
+    }
+}
+
+pub fn sys_render_time_indicator(time: Res<GameTime>, replay: Res<ReplayState>) {
+    if replay.is_viewing() || time.is_normal_speed() {
+        return;
+    }
+
+    let text = if time.paused {
+        "PAUSED (P to resume, . to step)".to_string()
+    } else {
+        format!("Time scale: {:.3}x", time.time_scale)
+    };
+
+    let size = measure_text(&text, None, 24, 1.);
+    draw_text(&text, (screen_width() - size.width) / 2., 30., 24., YELLOW);
+}
@@ -0,0 +1,233 @@
+use bevy_app::App;
+use bevy_ecs::{
+    system::{BoxedSystem, IntoSystem, Resource},
+    world::{Mut, World},
+};
+use macroquad::math::Vec2;
+use rustc_hash::FxHashMap;
+
+use crate::game::{
+    actor::{
+        camera::sys_render_resolution_metrics,
+        inventory::{sys_render_hotbar, sys_render_weapon_cooldown, HOTBAR_SIZE},
+        player::sys_render_health_bar,
+        wave::sys_render_wave_counter,
+    },
+    math::aabb::Aabb,
+    minimap::sys_render_minimap,
+};
+
+use super::layout::{screen_rect, Anchor, Edges, Size, UiRect};
+
+// === HudWidget === //
+
+/// Describes where a piece of the HUD is placed on the screen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HudWidget {
+    pub name: &'static str,
+
+    /// The corner or edge of the screen the widget is attached to. Widgets sharing a slot are
+    /// stacked away from the screen's edge, bottom slots growing upwards and the others downwards.
+    pub slot: Anchor,
+    pub width: Size,
+    pub height: Size,
+
+    /// Extra space kept between the widget and the screen's edges, on top of [`Hud::MARGIN`].
+    pub margin: Edges,
+
+    /// Widgets with a lower order are drawn first and placed closer to their slot's edge.
+    pub order: i32,
+
+    /// Hidden widgets are neither drawn nor given any room in their slot.
+    pub visible: bool,
+}
+
+impl HudWidget {
+    pub fn new(name: &'static str, slot: Anchor, width: Size, height: Size) -> Self {
+        Self {
+            name,
+            slot,
+            width,
+            height,
+            margin: Edges::ZERO,
+            order: 0,
+            visible: true,
+        }
+    }
+
+    pub fn with_margin(mut self, margin: Edges) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    pub fn with_order(mut self, order: i32) -> Self {
+        self.order = order;
+        self
+    }
+}
+
+// === Hud === //
+
+/// The widgets making up the HUD. Each one is drawn by its own system, which receives the
+/// rectangle the widget was placed in as its input.
+#[derive(Default, Resource)]
+pub struct Hud {
+    widgets: Vec<(HudWidget, BoxedSystem<Aabb>)>,
+}
+
+impl Hud {
+    /// The space kept between widgets and the edges of the screen.
+    pub const MARGIN: f32 = 15.;
+
+    /// The space kept between two widgets stacked in the same slot.
+    pub const SPACING: f32 = 20.;
+
+    pub fn widgets(&self) -> impl Iterator<Item = &HudWidget> + '_ {
+        self.widgets.iter().map(|(widget, _)| widget)
+    }
+
+    pub fn widget(&self, name: &str) -> Option<&HudWidget> {
+        self.widgets().find(|widget| widget.name == name)
+    }
+
+    /// Gives access to a widget's layout so that it can be moved, resized, or hidden.
+    pub fn widget_mut(&mut self, name: &str) -> Option<&mut HudWidget> {
+        self.widgets
+            .iter_mut()
+            .map(|(widget, _)| widget)
+            .find(|widget| widget.name == name)
+    }
+
+    /// Places every widget within `screen`, in draw order.
+    pub fn layout(&self, screen: Aabb) -> Vec<Aabb> {
+        let area = Edges::uniform(Self::MARGIN).inset(screen);
+        let mut stacked = FxHashMap::<Anchor, f32>::default();
+
+        self.widgets()
+            .map(|widget| {
+                let offset = stacked.entry(widget.slot).or_default();
+                let rect = UiRect::new(widget.slot, widget.width, widget.height)
+                    .with_margin(widget.margin)
+                    .place(area);
+
+                let dir = if widget.slot.factor().y == 1. {
+                    -1.
+                } else {
+                    1.
+                };
+                let rect = rect.translated(Vec2::new(0., *offset * dir));
+
+                if widget.visible {
+                    *offset += rect.h() + Self::SPACING;
+                }
+
+                rect
+            })
+            .collect()
+    }
+
+    fn insert(&mut self, widget: HudWidget, system: BoxedSystem<Aabb>) {
+        // Keep the widgets sorted by order, placing ties in registration order.
+        let index = self
+            .widgets
+            .partition_point(|(other, _)| other.order <= widget.order);
+
+        self.widgets.insert(index, (widget, system));
+    }
+}
+
+pub trait HudAppExt {
+    /// Adds a widget to the [`Hud`], drawn by `system`.
+    fn add_hud_widget<M>(
+        &mut self,
+        widget: HudWidget,
+        system: impl IntoSystem<Aabb, (), M>,
+    ) -> &mut Self;
+}
+
+impl HudAppExt for App {
+    fn add_hud_widget<M>(
+        &mut self,
+        widget: HudWidget,
+        system: impl IntoSystem<Aabb, (), M>,
+    ) -> &mut Self {
+        let mut system: BoxedSystem<Aabb> = Box::new(IntoSystem::into_system(system));
+        system.initialize(&mut self.world);
+
+        self.world
+            .get_resource_or_insert_with(Hud::default)
+            .insert(widget, system);
+
+        self
+    }
+}
+
+// === Plugin === //
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<Hud>();
+
+    app.add_hud_widget(
+        HudWidget::new(
+            "health",
+            Anchor::BottomCenter,
+            Size::Percent(80.),
+            Size::Px(10.),
+        ),
+        sys_render_health_bar,
+    );
+    app.add_hud_widget(
+        HudWidget::new(
+            "hotbar",
+            Anchor::BottomCenter,
+            Size::Px(HOTBAR_SIZE.x),
+            Size::Px(HOTBAR_SIZE.y),
+        )
+        .with_order(1),
+        sys_render_hotbar,
+    );
+    app.add_hud_widget(
+        HudWidget::new("weapon", Anchor::BottomRight, Size::Px(160.), Size::Px(36.)),
+        sys_render_weapon_cooldown,
+    );
+    app.add_hud_widget(
+        HudWidget::new("minimap", Anchor::TopRight, Size::Px(200.), Size::Px(200.)),
+        sys_render_minimap,
+    );
+    app.add_hud_widget(
+        HudWidget::new("waves", Anchor::TopCenter, Size::Px(300.), Size::Px(20.))
+            // Leave room for the time indicator.
+            .with_margin(Edges {
+                top: 30.,
+                ..Edges::ZERO
+            }),
+        sys_render_wave_counter,
+    );
+    app.add_hud_widget(
+        HudWidget::new("fps", Anchor::TopLeft, Size::Px(600.), Size::Px(20.))
+            // Leave room for the entity and draw call counters drawn after every frame.
+            .with_margin(Edges {
+                top: 20.,
+                ..Edges::ZERO
+            }),
+        sys_render_resolution_metrics,
+    );
+}
+
+// === Systems === //
+
+/// Lays out the [`Hud`] and draws every visible widget in order.
+pub fn sys_render_hud(world: &mut World) {
+    world.resource_scope(|world, mut hud: Mut<Hud>| {
+        let rects = hud.layout(screen_rect());
+
+        for ((widget, system), rect) in hud.widgets.iter_mut().zip(rects) {
+            if !widget.visible {
+                continue;
+            }
+
+            system.run(rect, world);
+            system.apply_deferred(world);
+        }
+    });
+}
@@ -0,0 +1,162 @@
+use macroquad::{
+    math::Vec2,
+    window::{screen_height, screen_width},
+};
+
+use crate::game::math::aabb::Aabb;
+
+// === Anchor === //
+
+/// The point of a parent rectangle to which a child rectangle is attached.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    TopCenter,
+    TopRight,
+    CenterLeft,
+    Center,
+    CenterRight,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+
+impl Anchor {
+    /// The anchor's position within a rectangle, where `(0, 0)` is its top-left corner and `(1, 1)`
+    /// is its bottom-right corner.
+    pub fn factor(self) -> Vec2 {
+        use Anchor::*;
+
+        match self {
+            TopLeft => Vec2::new(0., 0.),
+            TopCenter => Vec2::new(0.5, 0.),
+            TopRight => Vec2::new(1., 0.),
+            CenterLeft => Vec2::new(0., 0.5),
+            Center => Vec2::new(0.5, 0.5),
+            CenterRight => Vec2::new(1., 0.5),
+            BottomLeft => Vec2::new(0., 1.),
+            BottomCenter => Vec2::new(0.5, 1.),
+            BottomRight => Vec2::new(1., 1.),
+        }
+    }
+}
+
+// === Size === //
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Size {
+    /// A fixed size in pixels.
+    Px(f32),
+
+    /// A percentage of the parent's size, from `0` to `100`.
+    Percent(f32),
+}
+
+impl Size {
+    pub fn resolve(self, parent: f32) -> f32 {
+        match self {
+            Size::Px(px) => px,
+            Size::Percent(percent) => parent * percent / 100.,
+        }
+    }
+}
+
+// === Edges === //
+
+/// Per-side distances used for margins and padding.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Edges {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl Edges {
+    pub const ZERO: Self = Self::uniform(0.);
+
+    pub const fn uniform(v: f32) -> Self {
+        Self {
+            left: v,
+            right: v,
+            top: v,
+            bottom: v,
+        }
+    }
+
+    pub const fn symmetric(horizontal: f32, vertical: f32) -> Self {
+        Self {
+            left: horizontal,
+            right: horizontal,
+            top: vertical,
+            bottom: vertical,
+        }
+    }
+
+    /// Shrinks `aabb` inwards by these edges.
+    pub fn inset(self, aabb: Aabb) -> Aabb {
+        Aabb {
+            min: aabb.min + Vec2::new(self.left, self.top),
+            max: aabb.max - Vec2::new(self.right, self.bottom),
+        }
+    }
+
+    /// Grows `aabb` outwards by these edges.
+    pub fn outset(self, aabb: Aabb) -> Aabb {
+        Aabb {
+            min: aabb.min - Vec2::new(self.left, self.top),
+            max: aabb.max + Vec2::new(self.right, self.bottom),
+        }
+    }
+}
+
+// === UiRect === //
+
+/// Describes where a rectangle should be placed relative to its parent.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct UiRect {
+    pub anchor: Anchor,
+    pub width: Size,
+    pub height: Size,
+
+    /// The space kept between the rectangle and its parent's edges. Percentage sizes are resolved
+    /// against the parent after the margin is removed.
+    pub margin: Edges,
+}
+
+impl UiRect {
+    pub fn new(anchor: Anchor, width: Size, height: Size) -> Self {
+        Self {
+            anchor,
+            width,
+            height,
+            margin: Edges::ZERO,
+        }
+    }
+
+    pub fn with_margin(mut self, margin: Edges) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Resolves the rectangle within `parent`.
+    pub fn place(self, parent: Aabb) -> Aabb {
+        let inner = self.margin.inset(parent);
+        let size = Vec2::new(
+            self.width.resolve(inner.w()),
+            self.height.resolve(inner.h()),
+        );
+        let min = inner.min + (inner.size() - size) * self.anchor.factor();
+
+        Aabb::new_sized(min, size)
+    }
+
+    pub fn place_on_screen(self) -> Aabb {
+        self.place(screen_rect())
+    }
+}
+
+/// The whole window in screen space.
+pub fn screen_rect() -> Aabb {
+    Aabb::new(0., 0., screen_width(), screen_height())
+}
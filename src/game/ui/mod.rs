@@ -0,0 +1,3 @@
+pub mod hud;
+pub mod layout;
+pub mod widget;
@@ -0,0 +1,102 @@
+use macroquad::{
+    color::{Color, WHITE},
+    input::{is_mouse_button_pressed, mouse_position, MouseButton},
+    math::Vec2,
+    text::{draw_text, measure_text},
+    window::screen_width,
+};
+
+use crate::game::math::{
+    aabb::Aabb,
+    draw::{draw_rectangle_aabb, stroke_rectangle_aabb},
+};
+
+// === Widgets === //
+
+pub const BUTTON_SIZE: Vec2 = Vec2::new(240., 44.);
+pub const BUTTON_GAP: f32 = 12.;
+
+pub fn mouse_pos() -> Vec2 {
+    Vec2::from(mouse_position())
+}
+
+/// A clickable button hit-tested against the mouse in screen space.
+#[derive(Debug, Copy, Clone)]
+pub struct Button<'a> {
+    pub bounds: Aabb,
+    pub label: &'a str,
+}
+
+impl<'a> Button<'a> {
+    pub fn new(bounds: Aabb, label: &'a str) -> Self {
+        Self { bounds, label }
+    }
+
+    pub fn is_hovered(&self) -> bool {
+        self.bounds.contains(mouse_pos())
+    }
+
+    pub fn is_clicked(&self) -> bool {
+        self.is_hovered() && is_mouse_button_pressed(MouseButton::Left)
+    }
+
+    pub fn draw(&self) {
+        let fill = if self.is_hovered() {
+            Color::new(0.3, 0.3, 0.4, 0.9)
+        } else {
+            Color::new(0.1, 0.1, 0.15, 0.9)
+        };
+
+        draw_rectangle_aabb(self.bounds, fill);
+        stroke_rectangle_aabb(self.bounds, 2., WHITE);
+        Label::new(self.label, self.bounds.center(), 24, WHITE).draw();
+    }
+}
+
+/// A line of text centered on a point in screen space.
+#[derive(Debug, Copy, Clone)]
+pub struct Label<'a> {
+    pub text: &'a str,
+    pub center: Vec2,
+    pub font_size: u16,
+    pub color: Color,
+}
+
+impl<'a> Label<'a> {
+    pub fn new(text: &'a str, center: Vec2, font_size: u16, color: Color) -> Self {
+        Self {
+            text,
+            center,
+            font_size,
+            color,
+        }
+    }
+
+    pub fn draw(&self) {
+        let size = measure_text(self.text, None, self.font_size, 1.);
+        draw_text(
+            self.text,
+            self.center.x - size.width / 2.,
+            self.center.y + size.offset_y / 2.,
+            self.font_size as f32,
+            self.color,
+        );
+    }
+}
+
+/// Lays out one button per label in a column centered horizontally on the screen, starting at
+/// `top`.
+pub fn button_column<'a>(labels: &[&'a str], top: f32) -> Vec<Button<'a>> {
+    labels
+        .iter()
+        .enumerate()
+        .map(|(i, &label)| {
+            let min = Vec2::new(
+                (screen_width() - BUTTON_SIZE.x) / 2.,
+                top + i as f32 * (BUTTON_SIZE.y + BUTTON_GAP),
+            );
+
+            Button::new(Aabb::new_sized(min, BUTTON_SIZE), label)
+        })
+        .collect()
+}
@@ -1,37 +1,191 @@
 #![feature(arbitrary_self_types)]
 #![allow(clippy::type_complexity)]
 
-use bevy_app::App;
-use bevy_ecs::schedule::{LogLevel, ScheduleBuildSettings, ScheduleLabel};
-use macroquad::{
-    color::RED,
-    input::{is_key_pressed, is_quit_requested, KeyCode},
-    text::draw_text,
-    window::next_frame,
+use std::{thread, time::Duration};
+
+use bevy_app::{App, AppExit};
+use bevy_ecs::{
+    event::{Events, ManualEventReader},
+    schedule::{LogLevel, ScheduleBuildSettings, ScheduleLabel},
+    system::Resource,
+};
+
+#[cfg(not(feature = "headless"))]
+use {
+    crate::game::{
+        actor::camera::DynamicResolution,
+        math::draw::DrawStats,
+        replay::ReplayState,
+        settings::{Settings, SETTINGS_PATH},
+        time::GameTime,
+    },
+    macroquad::{
+        color::RED,
+        input::{is_quit_requested, prevent_quit},
+        text::draw_text,
+        time::get_frame_time,
+        window::{next_frame, Conf},
+    },
+    std::time::Instant,
 };
 
+/// Run exactly once per rendered frame before the `Update` schedule, even if the replay viewer
+/// decides to run zero or several updates during that frame.
+#[derive(ScheduleLabel, Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct PreFrame;
+
 #[derive(ScheduleLabel, Debug, Copy, Clone, Hash, Eq, PartialEq)]
 pub struct Render;
 
+/// Run once for every camera viewport during [`Render`] to draw the world as seen by that camera.
+#[derive(ScheduleLabel, Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct RenderViewport;
+
+/// Run exactly once before the process exits, regardless of whether the exit was requested by the
+/// window, the escape key, or an [`AppExit`] event.
+#[derive(ScheduleLabel, Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct Shutdown;
+
+/// The maximum amount of time the [`Shutdown`] schedule is allowed to take before we forcefully
+/// terminate the process.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The number of ticks simulated by the headless binary when `--ticks` isn't given.
+pub const DEFAULT_HEADLESS_TICKS: u32 = 3600;
+
+/// Present when the app runs without a window. Systems which read input from the window are skipped
+/// and the [`Render`] and [`RenderViewport`] schedules are left empty.
+#[derive(Debug, Default, Resource)]
+pub struct Headless;
+
+/// Present when the app mustn't read its configuration from the working directory, as is the case
+/// in tests. The startup loaders are skipped and the resources they fill keep their defaults.
+#[derive(Debug, Default, Resource)]
+pub struct SkipDiskLoads;
+
 pub mod game;
 pub mod schedule;
 pub mod util;
 
-#[macroquad::main("Bevy Demo")]
-async fn main() {
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
-    color_backtrace::install();
-
+/// Creates the app with every system registered. Headless apps leave out input and rendering so
+/// that they can be driven by [`run_n_ticks`] without a window.
+pub fn create_app(headless: bool) -> App {
     let mut app = App::new();
+
+    if headless {
+        app.add_plugins(schedule::headless_plugin);
+    } else {
+        app.add_plugins(schedule::plugin);
+    }
+
+    // This only applies to the schedules which already exist so it must come after the plugins.
     app.configure_schedules(ScheduleBuildSettings {
         ambiguity_detection: LogLevel::Error,
         hierarchy_detection: LogLevel::Warn,
         ..Default::default()
     });
-    app.add_plugins(schedule::plugin);
 
-    while !is_quit_requested() && !is_key_pressed(KeyCode::Escape) {
+    app
+}
+
+/// Simulates `n` ticks of a headless app, running [`PreFrame`] once before every update. The
+/// `Startup` schedule runs as part of the first tick. Returns the number of ticks actually run,
+/// which is less than `n` if an [`AppExit`] event was sent.
+pub fn run_n_ticks(app: &mut App, n: u32) -> u32 {
+    let mut exit_reader = ManualEventReader::<AppExit>::default();
+
+    for tick in 0..n {
+        if exit_requested(app, &mut exit_reader) {
+            return tick;
+        }
+
+        app.world.run_schedule(PreFrame);
         app.update();
+    }
+
+    n
+}
+
+fn exit_requested(app: &App, reader: &mut ManualEventReader<AppExit>) -> bool {
+    reader
+        .read(app.world.resource::<Events<AppExit>>())
+        .next()
+        .is_some()
+}
+
+fn init_logging() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    color_backtrace::install();
+}
+
+#[cfg(feature = "headless")]
+fn main() {
+    init_logging();
+
+    let args = std::env::args().collect::<Vec<_>>();
+
+    let ticks = match args.windows(2).find(|pair| pair[0] == "--ticks") {
+        Some(pair) => match pair[1].parse() {
+            Ok(ticks) => ticks,
+            Err(err) => {
+                log::error!("Invalid tick count {:?}: {err}", pair[1]);
+                std::process::exit(2);
+            }
+        },
+        None => DEFAULT_HEADLESS_TICKS,
+    };
+
+    let mut app = create_app(true);
+    let ran = run_n_ticks(&mut app, ticks);
+    log::info!("Simulated {ran} ticks");
+
+    shutdown(&mut app);
+}
+
+#[cfg(not(feature = "headless"))]
+fn window_conf() -> Conf {
+    // Logging isn't set up yet but `sys_load_settings` reports the same errors once it is.
+    Settings::load(SETTINGS_PATH)
+        .unwrap_or_default()
+        .window_conf("Bevy Demo")
+}
+
+#[cfg(not(feature = "headless"))]
+#[macroquad::main(window_conf)]
+async fn main() {
+    init_logging();
+
+    let mut app = create_app(false);
+
+    // Let the main loop observe window close requests so that we can run our shutdown hooks.
+    prevent_quit();
+
+    let mut exit_reader = ManualEventReader::<AppExit>::default();
+
+    loop {
+        let frame_started = Instant::now();
+
+        if is_quit_requested() {
+            break;
+        }
+
+        if exit_requested(&app, &mut exit_reader) {
+            break;
+        }
+
+        app.world.run_schedule(PreFrame);
+
+        let updates = match app.world.resource_mut::<ReplayState>().take_updates() {
+            Some(updates) => updates,
+            None => app
+                .world
+                .resource_mut::<GameTime>()
+                .take_updates(get_frame_time()),
+        };
+        for _ in 0..updates {
+            app.update();
+        }
+
         app.world.run_schedule(Render);
         draw_text(
             &format!("Entities: {}", app.world.entities().total_count()),
@@ -40,6 +194,146 @@ async fn main() {
             24.,
             RED,
         );
+
+        let draw_stats = DrawStats::take();
+        draw_text(
+            &format!(
+                "Draw calls: {} ({} vertices)",
+                draw_stats.draw_calls, draw_stats.vertices
+            ),
+            15.,
+            35.,
+            24.,
+            RED,
+        );
+
+        // Measured before presenting since, with vsync, that waits for the next refresh.
+        app.world
+            .resource_mut::<DynamicResolution>()
+            .record_work(frame_started.elapsed().as_secs_f32());
+
         next_frame().await;
     }
+
+    shutdown(&mut app);
+}
+
+fn shutdown(app: &mut App) {
+    log::info!("Shutting down...");
+
+    thread::spawn(|| {
+        thread::sleep(SHUTDOWN_TIMEOUT);
+        log::error!("Shutdown took longer than {SHUTDOWN_TIMEOUT:?}; forcefully exiting.");
+        std::process::exit(1);
+    });
+
+    app.world.run_schedule(Shutdown);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use bevy_ecs::schedule::Schedules;
+    use macroquad::math::Vec2;
+
+    use crate::game::{
+        actor::{
+            kinematic::{ColliderMoves, Pos},
+            portal::Portal,
+        },
+        math::aabb::Aabb,
+        save::SaveConfig,
+        tile::collider::{Collider, CollisionLayers, InsideWorld},
+    };
+
+    use super::*;
+
+    /// Builds every schedule of the app up-front rather than on their first run. Since
+    /// [`create_app`] makes ambiguities errors, this catches ambiguously-ordered systems without
+    /// having to run any of them.
+    fn assert_schedules_build(headless: bool) {
+        let mut app = create_app(headless);
+        let labels = app
+            .world
+            .resource::<Schedules>()
+            .iter()
+            .map(|(_, schedule)| schedule.label())
+            .collect::<Vec<_>>();
+
+        for label in labels {
+            app.world.schedule_scope(label, |world, schedule| {
+                if let Err(err) = schedule.initialize(world) {
+                    panic!("failed to build {label:?}: {err}");
+                }
+            });
+        }
+    }
+
+    #[test]
+    fn headless_schedules_have_no_ambiguities() {
+        assert_schedules_build(true);
+    }
+
+    #[test]
+    fn windowed_schedules_have_no_ambiguities() {
+        assert_schedules_build(false);
+    }
+
+    /// Creates a headless app which neither reads the configuration in the working directory nor
+    /// touches the saves in it. The saves go to the returned directory instead, which the caller
+    /// removes once it's done.
+    fn isolated_app(name: &str) -> (App, PathBuf) {
+        let root =
+            std::env::temp_dir().join(format!("bevy-demo-test-{name}-{}", std::process::id()));
+
+        let mut app = create_app(true);
+        app.insert_resource(SkipDiskLoads);
+        app.insert_resource(SaveConfig {
+            root: root.clone(),
+            ..Default::default()
+        });
+
+        (app, root)
+    }
+
+    #[test]
+    fn headless_app_runs_ticks() {
+        let (mut app, root) = isolated_app("ticks");
+        let ran = run_n_ticks(&mut app, 600);
+        let _ = std::fs::remove_dir_all(&root);
+        assert_eq!(ran, 600);
+    }
+
+    #[test]
+    fn portals_move_actors_between_worlds() {
+        let (mut app, root) = isolated_app("portals");
+        run_n_ticks(&mut app, 1);
+
+        let (world, aabb, destination) = app
+            .world
+            .query::<(&InsideWorld, &Collider, &Portal)>()
+            .iter(&app.world)
+            .map(|(world, collider, portal)| (world.0, collider.0, portal.destination))
+            .next()
+            .expect("the default setup should have a portal");
+
+        let traveler = app
+            .world
+            .spawn((
+                Pos(aabb.center()),
+                InsideWorld(world),
+                Collider(Aabb::new_centered(aabb.center(), Vec2::splat(10.))),
+                CollisionLayers::new(CollisionLayers::ITEMS, 0),
+                ColliderMoves,
+            ))
+            .id();
+
+        // The traveler's collider is registered at the end of the first tick and found by the
+        // portal during the second one.
+        run_n_ticks(&mut app, 2);
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(app.world.get::<InsideWorld>(traveler).unwrap().0 == destination);
+    }
 }
@@ -1,47 +1,229 @@
-use bevy_app::{App, Startup, Update};
+use bevy_app::{App, Last, Startup, Update};
+use bevy_ecs::{
+    schedule::{
+        apply_state_transition,
+        common_conditions::{in_state, not},
+        IntoSystemConfigs, IntoSystemSetConfigs, SystemSet,
+    },
+    system::Res,
+};
 
 use crate::{
     game::{
         actor::{
-            camera::{sys_update_camera, ActiveCamera, VirtualCamera},
-            health::Health,
+            bench::sys_run_benchmarks,
+            camera::{
+                sys_present_pixel_target, sys_render_viewports, sys_toggle_pixel_perfect,
+                sys_update_camera, sys_update_dynamic_resolution, ActiveCamera, CameraStack,
+                DynamicResolution, PixelPerfect, VirtualCamera,
+            },
+            checkpoint::{
+                sys_activate_checkpoints, sys_render_checkpoint_toast, sys_render_checkpoints,
+                CheckpointActivated,
+            },
+            controller::sys_update_character_controllers,
+            damage::{sys_apply_damage, DamageEvent},
+            death::{sys_check_death, sys_tick_respawns, ActorDied, ActorRespawned},
+            drops::{sys_pickup_item_drops, sys_render_item_drops, sys_spawn_tile_drops},
+            effects::{
+                sys_tick_status_effects, StatusEffectApplied, StatusEffectExpired, StatusEffects,
+            },
+            enemy::{
+                sys_debug_draw_enemy_paths, sys_render_enemies, sys_steer_enemies,
+                sys_update_enemy_paths,
+            },
+            explosion::{sys_handle_explosions, Explosion},
+            health::{Health, HealthChanged},
+            inspector::{
+                sys_handle_inspector_input, sys_refresh_inspector, sys_render_inspector, Inspector,
+                InspectorRegistry,
+            },
             kinematic::{
-                sys_draw_debug_colliders, sys_update_listening_colliders,
-                sys_update_moving_colliders, ColliderEvent,
+                sys_draw_debug_colliders, sys_handle_collider_debug_input,
+                sys_render_collider_debug_menu, sys_store_previous_pos,
+                sys_update_listening_colliders, sys_update_moving_colliders, ColliderDebug,
+                ColliderEvent,
             },
+            melee::{sys_render_melee_swings, sys_update_melee_swings},
+            platform::{sys_move_platforms, sys_render_platforms},
             player::{
-                sys_create_local_player, sys_focus_camera_on_player, sys_handle_controls,
-                sys_handle_damage, sys_render_health_bar, sys_render_players,
-                sys_render_selection_indicator,
+                sys_animate_health_bar, sys_apply_death_penalty, sys_create_local_player,
+                sys_focus_camera_on_player, sys_handle_controls, sys_render_players,
+                sys_render_selection_indicator, sys_sample_player_input, sys_send_contact_damage,
+                sys_take_player_input, sys_zoom_camera_on_death, PlayerInput,
             },
-            projectile::{sys_apply_bullet_damage, sys_render_bullets, sys_tick_bullet_spawner},
+            portal::{sys_render_portals, sys_use_portals},
+            projectile::{
+                sys_apply_bullet_damage, sys_render_bullets, sys_ricochet_bullets,
+                sys_tick_bullet_spawner, sys_update_pooled_bullets, BulletPool,
+            },
+            shadow::sys_render_actor_shadows,
+            trigger::sys_update_trigger_zones,
+            void::sys_handle_void,
+            wave::{
+                sys_direct_waves, sys_render_wave_banner, WaveDirector, WaveEnded, WaveStarted,
+            },
+        },
+        background::sys_render_background,
+        debug_draw::{sys_expire_debug_draw, sys_render_debug_draw, DebugDraw},
+        draw_order::{sys_flush_draw_queue, DrawQueue},
+        event_bus::{sys_dispatch_event_bus, EventBus},
+        fx::{
+            particles::{
+                sys_render_particles, sys_simulate_particles, sys_spawn_tile_break_particles,
+            },
+            tween::{sys_advance_tweens, TweenFinished},
+        },
+        input::{
+            sys_handle_controls_menu, sys_poll_gamepad, sys_render_controls_menu, ControlsMenu,
+            GamepadBackend, GamepadState, InputMap,
+        },
+        integrity::sys_check_integrity,
+        menu::{
+            sys_handle_main_menu_input, sys_handle_pause_menu_input, sys_render_game_over_menu,
+            sys_render_main_menu, sys_render_pause_menu, LoadMenu,
+        },
+        minimap::{sys_toggle_minimap, Minimap},
+        net::{
+            sys_close_net_session, sys_receive_net_messages, sys_send_net_messages,
+            sys_start_net_session, NetSession,
+        },
+        prefab::{sys_load_prefabs, PrefabRegistry},
+        replay::{
+            sys_handle_replay_controls, sys_load_replay_log, sys_record_replay_input,
+            sys_render_replay_overlay, sys_write_replay_log, ReplayState,
+        },
+        rng::{sys_seed_rng, Rng},
+        rules::{
+            sys_evaluate_game_rules, sys_load_game_rules, sys_render_game_summary, GameOutcome,
+            GameRules, GameStats,
+        },
+        save::{
+            sys_flush_saves, sys_handle_save_input, sys_process_saves, sys_render_save_browser,
+            sys_render_save_indicator, sys_request_exit_autosave, sys_request_initial_restore,
+            AutosaveManager, SaveConfig, SaveState,
+        },
+        scene::{self, GameScene, SceneSet},
+        script::{sys_load_scripts, sys_run_scripts, Scripts},
+        settings::{
+            sys_apply_settings, sys_load_settings, sys_persist_settings, sys_reload_settings,
+            Settings,
+        },
+        spatial::{
+            sys_propagate_spatial, sys_sync_pos_to_spatial, sys_sync_spatial_to_pos, SpatialSyncSet,
         },
         tile::{
+            autotile::AutoTileMaterial,
+            breaking::{sys_render_break_progress, TileBroken},
             collider::{
                 sys_add_collider_to_new_chunk, sys_add_tracked_collider_to_collider,
-                sys_move_tracked_colliders, sys_remove_tracked_collider, TrackedCollider,
-                TrackedColliderChunk, WorldColliders,
+                sys_move_tracked_colliders, TrackedCollider, TrackedColliderChunk, WorldColliders,
+            },
+            data::{
+                sys_unregister_chunk_from_world, TileChanged, TileChunk, TileLayers, TileWorld,
+                WorldCreatedChunk,
+            },
+            edit_log::{sys_handle_edit_history_input, TileEditLog},
+            generator::{sys_generate_new_chunks, sys_load_visible_chunks, TileGenerator},
+            hibernation::{sys_hibernate_distant_chunks, ChunkHibernation},
+            kinematic::{
+                KinematicApi, TangibleMarker, TileColliderDescriptor, TilePhysicsDescriptor,
             },
-            data::{sys_unregister_chunk_from_world, TileChunk, TileWorld, WorldCreatedChunk},
-            kinematic::{KinematicApi, TangibleMarker, TileColliderDescriptor},
+            lighting::{sys_render_lighting, sys_update_lighting},
+            liquid::{sys_flow_liquids, LiquidMaterial},
             material::{BaseMaterialDescriptor, MaterialRegistry},
-            render::{sys_render_chunks, SolidTileMaterial},
+            material_defs::{
+                sys_hot_reload_material_defs, sys_load_material_defs, MaterialHotReload,
+            },
+            render::{
+                sys_render_chunks, FluidTileMaterial, SolidTileMaterial, TexturedTileMaterial,
+            },
+            tile_entity::{TileEntityCreated, TileEntityDescriptor},
+            visibility::{sys_render_fog_of_war, sys_update_visibility},
+        },
+        time::{
+            sys_advance_world_clock, sys_handle_time_controls, sys_render_day_night_tint,
+            sys_render_time_indicator, sys_update_render_alpha, DayPhaseChanged, GameTime,
+            RenderAlpha, WorldClock,
         },
+        ui::hud::{self, sys_render_hud},
     },
-    util::{arena::RandomAppExt, schedule::chain_ambiguous},
-    Render,
+    util::{
+        arena::{RandomAppExt, RandomUnlinkSet},
+        diagnostics::sys_dump_world_stats,
+        profiler::{profiled, sys_render_profiler_overlay, sys_update_profiler, Profiler},
+        timer::sys_tick_timers,
+    },
+    Headless, PreFrame, Render, RenderViewport, Shutdown, SkipDiskLoads,
 };
 
+/// The stages of a tick of the [`Update`] schedule, which run one after the other. Systems within
+/// a stage are only ordered relative to the systems which access the same data so that the rest
+/// are free to run in parallel.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, SystemSet)]
+pub enum UpdateSet {
+    /// Remembers where actors were for interpolated rendering, advances the time of day, and
+    /// generates and simulates terrain.
+    World,
+
+    /// Turns local, recorded, and remote input into actions.
+    Input,
+
+    /// Steers enemies, moves colliders, and propagates the resulting transforms.
+    Physics,
+
+    /// Reacts to the tick's movement with damage, deaths, scripts, and effects.
+    Gameplay,
+
+    /// Moves the cameras to follow their players.
+    Camera,
+
+    /// Registers new colliders with their chunks and moves tracked colliders between them.
+    ColliderMaintenance,
+}
+
+/// Registers the simulation without any of the systems which need a window. See [`Headless`].
+pub fn headless_plugin(app: &mut App) {
+    app.init_resource::<Headless>();
+    app.insert_state(GameScene::InGame);
+    // Nothing would ever draw or expire debug shapes.
+    app.insert_resource(DebugDraw::disabled());
+    plugin(app);
+}
+
+fn has_window(headless: Option<Res<Headless>>) -> bool {
+    headless.is_none()
+}
+
+fn loads_from_disk(skip: Option<Res<SkipDiskLoads>>) -> bool {
+    skip.is_none()
+}
+
 pub fn plugin(app: &mut App) {
+    // Scenes
+    app.add_plugins(scene::plugin);
+
+    // HUD
+    app.add_plugins(hud::plugin);
+
     // Components
+    app.add_random_component::<AutoTileMaterial>();
     app.add_random_component::<BaseMaterialDescriptor>();
+    app.add_random_component::<FluidTileMaterial>();
     app.add_random_component::<Health>();
     app.add_random_component::<KinematicApi>();
+    app.add_random_component::<LiquidMaterial>();
     app.add_random_component::<MaterialRegistry>();
     app.add_random_component::<SolidTileMaterial>();
+    app.add_random_component::<StatusEffects>();
     app.add_random_component::<TangibleMarker>();
+    app.add_random_component::<TexturedTileMaterial>();
     app.add_random_component::<TileChunk>();
     app.add_random_component::<TileColliderDescriptor>();
+    app.add_random_component::<TileEntityDescriptor>();
+    app.add_random_component::<TileGenerator>();
+    app.add_random_component::<TileLayers>();
+    app.add_random_component::<TilePhysicsDescriptor>();
     app.add_random_component::<TileWorld>();
     app.add_random_component::<TrackedCollider>();
     app.add_random_component::<TrackedColliderChunk>();
@@ -50,48 +232,378 @@ pub fn plugin(app: &mut App) {
 
     // Resources
     app.init_resource::<ActiveCamera>();
+    app.init_resource::<AutosaveManager>();
+    app.init_resource::<BulletPool>();
+    app.init_resource::<CameraStack>();
+    app.init_resource::<ChunkHibernation>();
+    app.init_resource::<ColliderDebug>();
+    app.init_resource::<ControlsMenu>();
+    app.init_resource::<DebugDraw>();
+    app.init_resource::<DrawQueue>();
+    app.init_resource::<DynamicResolution>();
+    app.init_resource::<EventBus>();
+    app.init_resource::<GameOutcome>();
+    app.init_resource::<GamepadState>();
+    app.init_resource::<GameRules>();
+    app.init_resource::<GameStats>();
+    app.init_resource::<GameTime>();
+    app.init_resource::<InputMap>();
+    app.init_resource::<Inspector>();
+    app.init_resource::<InspectorRegistry>();
+    app.init_resource::<LoadMenu>();
+    app.init_resource::<MaterialHotReload>();
+    app.init_resource::<Minimap>();
+    app.init_resource::<NetSession>();
+    app.init_resource::<PixelPerfect>();
+    app.init_resource::<PlayerInput>();
+    app.init_resource::<PrefabRegistry>();
+    app.init_resource::<Profiler>();
+    app.init_resource::<RenderAlpha>();
+    app.init_resource::<ReplayState>();
+    app.init_resource::<Rng>();
+    app.init_resource::<SaveConfig>();
+    app.init_resource::<SaveState>();
+    app.init_resource::<Scripts>();
+    app.init_resource::<Settings>();
+    app.init_resource::<TileEditLog>();
+    app.init_resource::<WaveDirector>();
+    app.init_resource::<WorldClock>();
+    app.init_non_send_resource::<GamepadBackend>();
 
     // Events
+    app.add_event::<ActorDied>();
+    app.add_event::<ActorRespawned>();
+    app.add_event::<CheckpointActivated>();
     app.add_event::<ColliderEvent>();
-    app.add_event::<WorldCreatedChunk>();
+    app.add_event::<DamageEvent>();
+    app.add_event::<DayPhaseChanged>();
+    app.add_random_event::<Explosion>();
+    app.add_random_event::<HealthChanged>();
+    app.add_random_event::<StatusEffectApplied>();
+    app.add_random_event::<StatusEffectExpired>();
+    app.add_random_event::<TileBroken>();
+    app.add_random_event::<TileChanged>();
+    app.add_random_event::<TileEntityCreated>();
+    app.add_event::<TweenFinished>();
+    app.add_event::<WaveEnded>();
+    app.add_event::<WaveStarted>();
+    app.add_random_event::<WorldCreatedChunk>();
+
+    // Schedules
+    app.init_schedule(PreFrame);
+    app.init_schedule(RenderViewport);
+    app.init_schedule(Shutdown);
 
     // Systems
-    app.add_systems(Startup, chain_ambiguous(sys_create_local_player));
     app.add_systems(
+        Startup,
+        (
+            sys_seed_rng,
+            sys_load_settings.run_if(loads_from_disk),
+            sys_load_game_rules.run_if(loads_from_disk),
+            sys_load_prefabs.run_if(loads_from_disk),
+            sys_load_scripts.run_if(loads_from_disk),
+            sys_request_initial_restore.run_if(in_state(GameScene::InGame)),
+            // Replays may change the world seed so they're loaded before the worlds are created.
+            sys_load_replay_log,
+            sys_create_local_player,
+            sys_load_material_defs.run_if(loads_from_disk),
+            sys_start_net_session,
+        )
+            .chain(),
+    );
+    app.add_systems(
+        PreFrame,
+        (
+            // Handle frame-rate input
+            (
+                sys_update_profiler,
+                sys_poll_gamepad,
+                // The controls menu goes first so that the systems below can ignore its input.
+                (
+                    sys_reload_settings,
+                    sys_apply_settings,
+                    sys_handle_controls_menu,
+                )
+                    .chain(),
+                sys_toggle_pixel_perfect,
+                sys_toggle_minimap,
+                sys_update_dynamic_resolution,
+                sys_handle_collider_debug_input,
+                sys_handle_inspector_input,
+                sys_handle_time_controls,
+                // Input is sampled once per frame since a frame may run any number of ticks.
+                sys_sample_player_input.in_set(SceneSet(GameScene::InGame)),
+                sys_persist_settings,
+                sys_refresh_inspector,
+                sys_hot_reload_material_defs,
+                // The world in the main menu hasn't been played and mustn't overwrite saves.
+                sys_handle_save_input.run_if(not(in_state(GameScene::MainMenu))),
+                sys_handle_replay_controls,
+                sys_handle_edit_history_input,
+                sys_dump_world_stats,
+                sys_run_benchmarks,
+                sys_handle_pause_menu_input,
+                sys_handle_main_menu_input.in_set(SceneSet(GameScene::MainMenu)),
+            )
+                .chain()
+                .run_if(has_window),
+            // Persist worlds
+            sys_process_saves,
+            // Let menus switch scenes even while the simulation is paused
+            apply_state_transition::<GameScene>,
+        )
+            .chain(),
+    );
+    app.configure_sets(
         Update,
-        chain_ambiguous((
-            // Handle input
-            sys_handle_controls,
-            // Update colliders
-            sys_update_moving_colliders,
-            sys_update_listening_colliders,
-            sys_handle_damage,
-            // Update players
-            sys_tick_bullet_spawner,
-            sys_apply_bullet_damage,
-            sys_focus_camera_on_player,
-            // Update colliders
-            sys_add_collider_to_new_chunk,
-            sys_add_tracked_collider_to_collider,
-            sys_move_tracked_colliders,
-            sys_remove_tracked_collider,
-            sys_unregister_chunk_from_world,
-        )),
+        (
+            UpdateSet::World,
+            UpdateSet::Input,
+            UpdateSet::Physics,
+            UpdateSet::Gameplay,
+            UpdateSet::Camera,
+            UpdateSet::ColliderMaintenance,
+        )
+            .chain()
+            .in_set(SceneSet(GameScene::InGame)),
+    );
+    app.configure_sets(Update, SpatialSyncSet.in_set(UpdateSet::Physics));
+    // Systems within a stage are only ordered after the systems whose data they depend on. The
+    // rest are left unordered so that they can run in parallel, and the ambiguity checks catch any
+    // conflicting pair which was missed.
+    app.add_systems(
+        Update,
+        (
+            (
+                profiled(sys_store_previous_pos),
+                profiled(sys_advance_world_clock),
+                profiled(sys_tick_timers),
+                // Generate terrain
+                profiled((
+                    sys_load_visible_chunks,
+                    sys_generate_new_chunks,
+                    sys_flow_liquids,
+                    sys_update_lighting,
+                    sys_hibernate_distant_chunks,
+                ))
+                .chain(),
+            )
+                .in_set(UpdateSet::World),
+            (
+                profiled(sys_take_player_input),
+                profiled(sys_record_replay_input),
+                profiled(sys_receive_net_messages),
+                profiled(sys_handle_controls),
+                profiled((sys_spawn_tile_drops, sys_spawn_tile_break_particles)),
+            )
+                .chain()
+                .in_set(UpdateSet::Input),
+            (
+                // Tweened positions are applied before anything reads them. Platforms are never
+                // tweened.
+                profiled(sys_advance_tweens)
+                    .before(sys_direct_waves)
+                    .before(sys_update_enemy_paths)
+                    .before(sys_update_moving_colliders)
+                    .ambiguous_with(sys_move_platforms),
+                // Decide where everything wants to go. Enemies, characters and bullets are separate
+                // actors which only share the kinematic caches.
+                (
+                    profiled(sys_direct_waves),
+                    profiled((sys_update_enemy_paths, sys_steer_enemies)).chain(),
+                    profiled(sys_update_character_controllers)
+                        .ambiguous_with(sys_update_enemy_paths)
+                        .ambiguous_with(sys_steer_enemies),
+                    profiled(sys_ricochet_bullets)
+                        .ambiguous_with(sys_update_enemy_paths)
+                        .ambiguous_with(sys_steer_enemies)
+                        .ambiguous_with(sys_update_character_controllers),
+                )
+                    .before(sys_move_platforms),
+                // Move everything, carrying riders along with their platforms, and report what
+                // touched.
+                profiled((
+                    sys_move_platforms,
+                    sys_update_moving_colliders,
+                    sys_update_listening_colliders,
+                ))
+                .chain(),
+                profiled((sys_pickup_item_drops, sys_send_contact_damage))
+                    .after(sys_update_listening_colliders),
+                // Actors only travel once everything touching them in their old world is done.
+                profiled((sys_update_trigger_zones, sys_use_portals))
+                    .chain()
+                    .after(sys_update_listening_colliders),
+            )
+                .before(SpatialSyncSet)
+                .in_set(UpdateSet::Physics),
+            profiled((
+                sys_sync_pos_to_spatial,
+                sys_propagate_spatial,
+                sys_sync_spatial_to_pos,
+            ))
+            .chain()
+            .in_set(SpatialSyncSet),
+            (
+                profiled(sys_activate_checkpoints).before(sys_handle_void),
+                profiled(sys_tick_bullet_spawner).before(sys_apply_bullet_damage),
+                // Damage is applied in the order in which it was sent.
+                profiled((
+                    sys_apply_bullet_damage,
+                    sys_handle_void,
+                    sys_update_melee_swings,
+                    sys_handle_explosions,
+                ))
+                .chain(),
+                // Every one of these reads or changes health.
+                profiled((
+                    sys_apply_damage,
+                    sys_tick_status_effects,
+                    sys_check_death,
+                    sys_apply_death_penalty,
+                    sys_run_scripts,
+                    sys_dispatch_event_bus,
+                    sys_tick_respawns,
+                ))
+                .chain()
+                .after(sys_handle_explosions),
+                // Impacts and explosions start emitting right away. Emitters aren't actors and
+                // draw from their own random stream.
+                profiled(sys_simulate_particles)
+                    .after(sys_handle_explosions)
+                    .ambiguous_with(sys_run_scripts)
+                    .ambiguous_with(sys_dispatch_event_bus)
+                    .ambiguous_with(sys_tick_respawns),
+                profiled((
+                    sys_evaluate_game_rules,
+                    sys_animate_health_bar,
+                    sys_update_visibility,
+                ))
+                .after(sys_tick_respawns),
+            )
+                .in_set(UpdateSet::Gameplay),
+            // Zooming only reads the camera's zoom, which focusing leaves alone.
+            (
+                profiled(sys_zoom_camera_on_death).ambiguous_with(sys_focus_camera_on_player),
+                profiled(sys_focus_camera_on_player),
+            )
+                .in_set(UpdateSet::Camera),
+            (
+                (
+                    // Colliders create the chunks they're registered in on demand as well.
+                    profiled(sys_add_collider_to_new_chunk)
+                        .ambiguous_with(sys_add_tracked_collider_to_collider)
+                        .ambiguous_with(sys_update_pooled_bullets)
+                        .ambiguous_with(sys_move_tracked_colliders),
+                    profiled((
+                        sys_add_tracked_collider_to_collider,
+                        sys_update_pooled_bullets,
+                        sys_move_tracked_colliders,
+                    ))
+                    .chain(),
+                ),
+                // Despawned chunks are only forgotten once nothing else can register colliders in
+                // them.
+                profiled(sys_unregister_chunk_from_world),
+            )
+                .chain()
+                .in_set(UpdateSet::ColliderMaintenance),
+            // Synchronize with other players once the tick is over
+            profiled(sys_send_net_messages)
+                .after(UpdateSet::ColliderMaintenance)
+                .in_set(SceneSet(GameScene::InGame)),
+        ),
+    );
+    app.add_systems(
+        Shutdown,
+        (
+            sys_request_exit_autosave.run_if(not(in_state(GameScene::MainMenu))),
+            sys_flush_saves,
+            sys_write_replay_log,
+            sys_close_net_session,
+        )
+            .chain(),
+    );
+
+    if cfg!(debug_assertions) {
+        app.add_systems(Last, sys_check_integrity.after(RandomUnlinkSet::Plain));
+    }
+
+    // Rendering needs a window.
+    if app.world.contains_resource::<Headless>() {
+        return;
+    }
+
+    app.add_systems(
+        RenderViewport,
+        // Render world
+        (
+            profiled((
+                // Setup
+                sys_update_camera,
+                sys_render_background,
+                // Actors
+                sys_render_portals,
+                sys_render_checkpoints,
+                sys_render_platforms,
+                sys_render_players,
+                sys_render_enemies,
+                sys_render_item_drops,
+                sys_render_bullets,
+                sys_render_melee_swings,
+                sys_flush_draw_queue,
+            ))
+            .chain(),
+            profiled((
+                // Terrain and effects
+                sys_render_chunks,
+                sys_render_break_progress,
+                sys_render_particles,
+                sys_render_lighting,
+                sys_render_actor_shadows,
+                sys_render_day_night_tint,
+                sys_render_fog_of_war,
+                // Debug
+                sys_draw_debug_colliders,
+                sys_render_debug_draw,
+                sys_render_selection_indicator,
+                // Present
+                sys_present_pixel_target,
+            ))
+            .chain(),
+        )
+            .chain(),
     );
     app.add_systems(
         Render,
-        chain_ambiguous((
-            // Setup
-            sys_update_camera,
-            // Actors
-            sys_render_players,
-            sys_render_bullets,
-            sys_render_chunks,
-            // Debug
-            sys_draw_debug_colliders,
-            // UI
-            sys_render_selection_indicator,
-            sys_render_health_bar,
-        )),
+        (
+            // Render world
+            sys_update_render_alpha,
+            sys_debug_draw_enemy_paths,
+            sys_render_viewports,
+            sys_expire_debug_draw,
+            // Render UI
+            profiled((
+                sys_render_hud,
+                sys_render_time_indicator,
+                sys_render_collider_debug_menu,
+                sys_render_inspector,
+                sys_render_controls_menu,
+                sys_render_save_browser,
+                sys_render_save_indicator,
+                sys_render_wave_banner,
+                sys_render_checkpoint_toast,
+                sys_render_game_summary,
+                sys_render_replay_overlay,
+                sys_render_profiler_overlay,
+            ))
+            .chain(),
+            // Render menus
+            sys_render_pause_menu.in_set(SceneSet(GameScene::Paused)),
+            sys_render_game_over_menu.in_set(SceneSet(GameScene::GameOver)),
+            sys_render_main_menu.in_set(SceneSet(GameScene::MainMenu)),
+        )
+            .chain(),
     );
 }
@@ -1,23 +1,31 @@
 #![allow(clippy::missing_safety_doc)]
 
 use std::{
+    any::TypeId,
     cell::Cell,
     collections::hash_map,
     fmt,
     marker::PhantomData,
     ops::{Deref, DerefMut},
+    ptr::null_mut,
+    sync::Mutex,
     thread::LocalKey,
 };
 
 use autoken::{cap, BorrowsMut, BorrowsRef, CapTarget, TokenSet};
-use bevy_app::{App, Last};
+use bevy_app::{App, First, Last};
 use bevy_ecs::{
     bundle::Bundle,
+    change_detection::CHECK_TICK_THRESHOLD,
     component::{Component, ComponentId, Tick},
-    entity::Entity,
+    entity::{Entities, Entity},
     event::{Event, Events},
+    query::With,
     removal_detection::RemovedComponents,
-    system::{Commands, Res, ResMut, Resource, SystemMeta, SystemParam},
+    schedule::{IntoSystemConfigs, IntoSystemSetConfigs, SystemSet},
+    system::{
+        Commands, Local, Query, Res, ResMut, Resource, SystemChangeTick, SystemMeta, SystemParam,
+    },
     world::{unsafe_world_cell::UnsafeWorldCell, World},
 };
 use generational_arena::{Arena, Index};
@@ -27,8 +35,11 @@ use rustc_hash::FxHashMap;
 
 #[derive(Debug, Resource)]
 pub struct RandomArena<T> {
-    pub arena: Arena<(Entity, T)>,
+    /// The slots of the arena along with their owner and the tick at which they were last mutably
+    /// borrowed.
+    pub arena: Arena<(Entity, T, Tick)>,
     pub map: FxHashMap<Entity, Obj<T>>,
+    pub stats: RandomArenaStats,
 }
 
 impl<T> Default for RandomArena<T> {
@@ -36,25 +47,146 @@ impl<T> Default for RandomArena<T> {
         Self {
             arena: Arena::default(),
             map: FxHashMap::default(),
+            stats: RandomArenaStats::default(),
         }
     }
 }
 
+impl<T> RandomArena<T> {
+    /// Iterates over every live component along with its handle and owner. This walks the arena's
+    /// slots directly and is therefore cheaper than querying for [`ObjOwner`]s when the owners'
+    /// other components don't matter.
+    pub fn iter(&self) -> impl Iterator<Item = (Obj<T>, Entity, &T)> + '_ {
+        self.arena
+            .iter()
+            .map(|(index, (owner, value, _))| (Obj::from_index(index), *owner, value))
+    }
+
+    /// Mutably iterates over every live component along with its handle and owner, marking each
+    /// one as changed.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Obj<T>, Entity, &mut T)> + '_ {
+        let this_run = CHANGE_TICKS.get().this_run;
+
+        self.arena
+            .iter_mut()
+            .map(move |(index, (owner, value, changed))| {
+                *changed = this_run;
+                (Obj::from_index(index), *owner, value)
+            })
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct RandomArenaStats {
+    /// The number of slots ever allocated in the arena.
+    pub created: u64,
+
+    /// The number of slots ever freed by the unlinker.
+    pub destroyed: u64,
+
+    /// The number of times an existing slot was overwritten by a re-insertion.
+    pub overwritten: u64,
+}
+
+/// Tracks every registered [`RandomArena`] so that diagnostics can enumerate them without knowing
+/// their concrete types.
+#[derive(Debug, Default, Resource)]
+pub struct RandomArenaRegistry {
+    arenas: Vec<RandomArenaEntry>,
+}
+
+#[derive(Debug)]
+struct RandomArenaEntry {
+    name: &'static str,
+    summarize: fn(&World) -> RandomArenaSummary,
+    describe: fn(&World, Entity) -> Option<String>,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct RandomArenaSummary {
+    pub name: &'static str,
+    pub slots: usize,
+    pub capacity: usize,
+    pub stats: RandomArenaStats,
+}
+
+impl RandomArenaRegistry {
+    pub fn register<T: RandomComponent + fmt::Debug>(&mut self) {
+        self.arenas.push(RandomArenaEntry {
+            name: std::any::type_name::<T>(),
+            summarize: |world| {
+                let arena = world.resource::<RandomArena<T>>();
+
+                RandomArenaSummary {
+                    name: std::any::type_name::<T>(),
+                    slots: arena.arena.len(),
+                    capacity: arena.arena.capacity(),
+                    stats: arena.stats,
+                }
+            },
+            describe: |world, entity| {
+                let arena = world.resource::<RandomArena<T>>();
+                let obj = arena.map.get(&entity)?;
+                Some(format!("{:?}", arena.arena[obj.index].1))
+            },
+        });
+    }
+
+    pub fn summarize(&self, world: &World) -> Vec<RandomArenaSummary> {
+        self.arenas
+            .iter()
+            .map(|entry| (entry.summarize)(world))
+            .collect()
+    }
+
+    /// Formats every random component attached to `entity` alongside its type name.
+    pub fn describe_entity(&self, world: &World, entity: Entity) -> Vec<(&'static str, String)> {
+        self.arenas
+            .iter()
+            .filter_map(|entry| (entry.describe)(world, entity).map(|desc| (entry.name, desc)))
+            .collect()
+    }
+}
+
 // === RandomAccess === //
 
 cap! {
     CommandsCap<'w, 's> = Commands<'w, 's>;
 }
 
+cap! {
+    EntitiesCap<'w> = &'w Entities;
+}
+
 pub struct RandomAccess<'w, 's, L: RandomResourceList> {
     inner: RandomAccessInner<'w, 's, L>,
     commands: Commands<'w, 's>,
+    entities: &'w Entities,
+    ticks: ChangeTicks,
+}
+
+/// The change detection ticks of the system currently providing random access.
+#[derive(Debug, Copy, Clone)]
+struct ChangeTicks {
+    last_run: Tick,
+    this_run: Tick,
+}
+
+thread_local! {
+    static CHANGE_TICKS: Cell<ChangeTicks> = const {
+        Cell::new(ChangeTicks {
+            last_run: Tick::new(0),
+            this_run: Tick::new(0),
+        })
+    };
 }
 
 unsafe impl<'w2, 's2, L: RandomResourceList> SystemParam for RandomAccess<'w2, 's2, L> {
     type State = (
         <RandomAccessInner<'w2, 's2, L> as SystemParam>::State,
         <Commands<'w2, 's2> as SystemParam>::State,
+        <&'w2 Entities as SystemParam>::State,
+        Tick,
     );
 
     type Item<'w, 's> = RandomAccess<'w, 's, L>;
@@ -63,6 +195,9 @@ unsafe impl<'w2, 's2, L: RandomResourceList> SystemParam for RandomAccess<'w2, '
         (
             RandomAccessInner::<L>::init_state(world, system_meta),
             Commands::init_state(world, system_meta),
+            <&Entities>::init_state(world, system_meta),
+            // Like Bevy's own systems, treat everything as changed on the first run.
+            Tick::new(world.change_tick().get().wrapping_sub(Tick::MAX.get())),
         )
     }
 
@@ -79,9 +214,18 @@ unsafe impl<'w2, 's2, L: RandomResourceList> SystemParam for RandomAccess<'w2, '
         world: UnsafeWorldCell<'world>,
         change_tick: Tick,
     ) -> Self::Item<'world, 'state> {
+        // Bevy doesn't know about this tick so we clamp it ourselves before using it.
+        let mut last_run = std::mem::replace(&mut state.3, change_tick);
+        last_run.check_tick(change_tick);
+
         RandomAccess {
             inner: RandomAccessInner::get_param(&mut state.0, system_meta, world, change_tick),
             commands: Commands::get_param(&mut state.1, system_meta, world, change_tick),
+            entities: <&Entities>::get_param(&mut state.2, system_meta, world, change_tick),
+            ticks: ChangeTicks {
+                last_run,
+                this_run: change_tick,
+            },
         }
     }
 }
@@ -96,14 +240,19 @@ unsafe impl<'w2, 's2, L: RandomResourceList> SystemParam for RandomAccessInner<'
     type Item<'w, 's> = RandomAccessInner<'w, 's, L>;
 
     fn init_state(world: &mut World, system_meta: &mut SystemMeta) -> Self::State {
-        // Fetch the IDs of each component used in the borrow and ensure that they don't conflict
-        // with another parameter's borrow access.
-        let state = L::get_param_state(world, system_meta);
+        // Collect the resources used by the borrow, merging accesses to the same resource.
+        let mut access = RandomAccessSet::default();
+        L::collect_access(&mut access);
 
-        // Adjust the borrow set of this system.
-        L::update_access_sets(&state, world, system_meta);
+        // Register them against the system's access sets, ensuring that they don't conflict with
+        // another parameter's borrow access.
+        access.register(world, system_meta);
 
-        state
+        L::get_param_state(&access)
+    }
+
+    fn apply(_state: &mut Self::State, _system_meta: &SystemMeta, world: &mut World) {
+        L::apply(world);
     }
 
     #[inline]
@@ -130,13 +279,22 @@ impl<'w, 's, L: RandomResourceList> RandomAccess<'w, 's, L> {
                 });
                 L::apply_tls_snapshot(&new_snap);
 
+                let _tick_guard = scopeguard::guard(CHANGE_TICKS.get(), |ticks| {
+                    CHANGE_TICKS.set(ticks);
+                });
+                CHANGE_TICKS.set(self.ticks);
+
                 fn dummy<'a, S: TokenSet>() -> &'a () {
                     autoken::tie!('a => set S);
                     &()
                 }
 
                 let _all = dummy::<L::TokensMut>();
-                autoken::absorb::<L::Tokens, R>(|| CommandsCap::provide(&mut self.commands, f))
+                autoken::absorb::<L::Tokens, R>(|| {
+                    EntitiesCap::provide(&mut self.entities, || {
+                        CommandsCap::provide(&mut self.commands, f)
+                    })
+                })
             })
         }
     }
@@ -162,16 +320,12 @@ pub unsafe trait RandomResourceList {
 
     type TlsSnapshot: 'static + Copy;
 
-    /// Fetches the set of [`ComponentId`]s that this component list, ensuring that the existing
-    /// system meta doesn't have any conflicting borrows.
-    fn get_param_state(world: &mut World, system_meta: &mut SystemMeta) -> Self::ParamState;
+    /// Records the set of resources accessed by this list.
+    fn collect_access(access: &mut RandomAccessSet);
 
-    /// Appends this set's resource set to the system metadata.
-    fn update_access_sets(
-        state: &Self::ParamState,
-        world: &mut World,
-        system_meta: &mut SystemMeta,
-    );
+    /// Fetches the [`ComponentId`]s of the resources accessed by this list once the `access` set
+    /// has been registered.
+    fn get_param_state(access: &RandomAccessSet) -> Self::ParamState;
 
     /// Fetch a snapshot of all previous arena TLS states.
     fn fetch_tls_snapshot() -> Self::TlsSnapshot;
@@ -184,50 +338,120 @@ pub unsafe trait RandomResourceList {
 
     /// Applies a snapshot on arena TLS states.
     unsafe fn apply_tls_snapshot(snap: &Self::TlsSnapshot);
+
+    /// Flushes any work buffered by the list back into the world. This is called whenever the
+    /// owning system's deferred buffers are applied.
+    fn apply(world: &mut World) {
+        let _ = world;
+    }
+}
+
+/// The merged set of resources accessed by a [`RandomResourceList`].
+///
+/// A list may mention the same resource several times (e.g. `(&T, &mut T)`) so we merge duplicate
+/// accesses—promoting them to writes where necessary—before registering each resource exactly once
+/// through Bevy's own [`Res`] and [`ResMut`] parameters. This lets the scheduler see our accesses
+/// and makes conflicts with other parameters raise Bevy's usual `B0002` panic.
+#[derive(Default)]
+pub struct RandomAccessSet {
+    entries: Vec<RandomAccessEntry>,
+}
+
+struct RandomAccessEntry {
+    ty: TypeId,
+    write: bool,
+    register_read: fn(&mut World, &mut SystemMeta) -> ComponentId,
+    register_write: fn(&mut World, &mut SystemMeta) -> ComponentId,
+    id: Option<ComponentId>,
+}
+
+impl fmt::Debug for RandomAccessSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.entries
+                    .iter()
+                    .map(|entry| (entry.ty, entry.write, entry.id)),
+            )
+            .finish()
+    }
+}
+
+impl RandomAccessSet {
+    pub fn add_read<R: Resource>(&mut self) {
+        self.add::<R>(false);
+    }
+
+    pub fn add_write<R: Resource>(&mut self) {
+        self.add::<R>(true);
+    }
+
+    fn add<R: Resource>(&mut self, write: bool) {
+        let ty = TypeId::of::<R>();
+
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.ty == ty) {
+            entry.write |= write;
+            return;
+        }
+
+        self.entries.push(RandomAccessEntry {
+            ty,
+            write,
+            register_read: <Res<R> as SystemParam>::init_state,
+            register_write: <ResMut<R> as SystemParam>::init_state,
+            id: None,
+        });
+    }
+
+    /// Registers every access in this set against the system's metadata.
+    pub fn register(&mut self, world: &mut World, system_meta: &mut SystemMeta) {
+        for entry in &mut self.entries {
+            let register = if entry.write {
+                entry.register_write
+            } else {
+                entry.register_read
+            };
+
+            entry.id = Some(register(world, system_meta));
+        }
+    }
+
+    /// Returns `true` if the resource is accessed mutably by any member of the set.
+    pub fn is_write<R: Resource>(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.ty == TypeId::of::<R>() && entry.write)
+    }
+
+    pub fn component_id<R: Resource>(&self) -> ComponentId {
+        self.entries
+            .iter()
+            .find(|entry| entry.ty == TypeId::of::<R>())
+            .and_then(|entry| entry.id)
+            .unwrap_or_else(|| {
+                panic!(
+                    "{} was never registered in this access set",
+                    std::any::type_name::<R>()
+                )
+            })
+    }
 }
 
 unsafe impl<T: RandomComponent> RandomResourceList for &'_ T {
     type Tokens = autoken::Ref<RandomComponentToken<T>>;
     type TokensMut = autoken::Mut<RandomComponentToken<T>>;
-    type ParamState = ComponentId;
-    type TlsSnapshot = *mut RandomArena<T>;
-
-    fn get_param_state(world: &mut World, system_meta: &mut SystemMeta) -> Self::ParamState {
-        // TODO: Use an alias-permitting technique
-        // let component_id = world.init_resource::<RandomArena<T>>();
-        //
-        // let combined_access = system_meta.component_access_set.combined_access();
-        // assert!(
-        //     !combined_access.has_write(component_id),
-        //     "error[B0002]: Res<{}> in system {} conflicts with a previous ResMut<{0}> access. Consider removing the duplicate access.",
-        //     std::any::type_name::<T>(),
-        //     system_meta.name(),
-        // );
-        //
-        // component_id
-
-        <Res<RandomArena<T>> as SystemParam>::init_state(world, system_meta)
-    }
-
-    fn update_access_sets(
-        &component_id: &Self::ParamState,
-        world: &mut World,
-        system_meta: &mut SystemMeta,
-    ) {
-        let _ = (component_id, world, system_meta);
-
-        // TODO: Use an alias-permitting technique
-        // system_meta
-        //     .component_access_set
-        //     .add_unfiltered_read(component_id);
-        //
-        // let archetype_component_id = world
-        //     .get_resource_archetype_component_id(component_id)
-        //     .unwrap();
-        //
-        // system_meta
-        //     .archetype_component_access
-        //     .add_read(archetype_component_id);
+    type ParamState = (ComponentId, bool);
+    type TlsSnapshot = ArenaSnapshot<T>;
+
+    fn collect_access(access: &mut RandomAccessSet) {
+        access.add_read::<RandomArena<T>>();
+    }
+
+    fn get_param_state(access: &RandomAccessSet) -> Self::ParamState {
+        (
+            access.component_id::<RandomArena<T>>(),
+            access.is_write::<RandomArena<T>>(),
+        )
     }
 
     fn fetch_tls_snapshot() -> Self::TlsSnapshot {
@@ -235,19 +459,10 @@ unsafe impl<T: RandomComponent> RandomResourceList for &'_ T {
     }
 
     unsafe fn tls_snapshot_from_world(
-        &state: &Self::ParamState,
+        &(id, exclusive): &Self::ParamState,
         world: UnsafeWorldCell<'_>,
     ) -> Self::TlsSnapshot {
-        world
-            .get_resource_by_id(state)
-            .unwrap_or_else(|| {
-                panic!(
-                    "Random component never registered: {}",
-                    std::any::type_name::<T>()
-                )
-            })
-            .as_ptr()
-            .cast()
+        ArenaSnapshot::from_world(id, exclusive, world)
     }
 
     unsafe fn apply_tls_snapshot(&snap: &Self::TlsSnapshot) {
@@ -258,45 +473,18 @@ unsafe impl<T: RandomComponent> RandomResourceList for &'_ T {
 unsafe impl<T: RandomComponent> RandomResourceList for &'_ mut T {
     type Tokens = autoken::Mut<RandomComponentToken<T>>;
     type TokensMut = autoken::Mut<RandomComponentToken<T>>;
-    type ParamState = ComponentId;
-    type TlsSnapshot = *mut RandomArena<T>;
-
-    fn get_param_state(world: &mut World, system_meta: &mut SystemMeta) -> Self::ParamState {
-        // TODO: Use an alias-permitting technique
-        // let component_id = world.init_resource::<RandomArena<T>>();
-        //
-        // let combined_access = system_meta.component_access_set.combined_access();
-        // assert!(
-        //     !combined_access.has_write(component_id),
-        //     "error[B0002]: Res<{}> in system {} conflicts with a previous ResMut<{0}> access. Consider removing the duplicate access.",
-        //     std::any::type_name::<T>(),
-        //     system_meta.name(),
-        // );
-        //
-        // component_id
-
-        <ResMut<RandomArena<T>> as SystemParam>::init_state(world, system_meta)
-    }
-
-    fn update_access_sets(
-        &component_id: &Self::ParamState,
-        world: &mut World,
-        system_meta: &mut SystemMeta,
-    ) {
-        let _ = (component_id, world, system_meta);
-
-        // TODO: Use an alias-permitting technique
-        // system_meta
-        //     .component_access_set
-        //     .add_unfiltered_read(component_id);
-        //
-        // let archetype_component_id = world
-        //     .get_resource_archetype_component_id(component_id)
-        //     .unwrap();
-        //
-        // system_meta
-        //     .archetype_component_access
-        //     .add_read(archetype_component_id);
+    type ParamState = (ComponentId, bool);
+    type TlsSnapshot = ArenaSnapshot<T>;
+
+    fn collect_access(access: &mut RandomAccessSet) {
+        access.add_write::<RandomArena<T>>();
+    }
+
+    fn get_param_state(access: &RandomAccessSet) -> Self::ParamState {
+        (
+            access.component_id::<RandomArena<T>>(),
+            access.is_write::<RandomArena<T>>(),
+        )
     }
 
     fn fetch_tls_snapshot() -> Self::TlsSnapshot {
@@ -304,19 +492,10 @@ unsafe impl<T: RandomComponent> RandomResourceList for &'_ mut T {
     }
 
     unsafe fn tls_snapshot_from_world(
-        &state: &Self::ParamState,
+        &(id, exclusive): &Self::ParamState,
         world: UnsafeWorldCell<'_>,
     ) -> Self::TlsSnapshot {
-        world
-            .get_resource_by_id(state)
-            .unwrap_or_else(|| {
-                panic!(
-                    "Random component never registered: {}",
-                    std::any::type_name::<T>()
-                )
-            })
-            .as_ptr()
-            .cast()
+        ArenaSnapshot::from_world(id, exclusive, world)
     }
 
     unsafe fn apply_tls_snapshot(&snap: &Self::TlsSnapshot) {
@@ -324,50 +503,25 @@ unsafe impl<T: RandomComponent> RandomResourceList for &'_ mut T {
     }
 }
 
+/// Allows the system to send events of type `T` through [`send_event`].
+///
+/// Events are buffered in the type's [`EventQueue`], which only requires shared access, and are
+/// flushed into [`Events<T>`] when the system's deferred buffers are applied. This means that
+/// systems sending the same event type do not conflict with one another or with readers.
 pub struct SendsEvent<T>(PhantomData<fn() -> T>);
 
 unsafe impl<T: RandomEvent> RandomResourceList for SendsEvent<T> {
-    type Tokens = autoken::Mut<RandomEventToken<T>>;
+    type Tokens = autoken::Ref<RandomEventToken<T>>;
     type TokensMut = autoken::Mut<RandomEventToken<T>>;
     type ParamState = ComponentId;
-    type TlsSnapshot = *mut Events<T>;
-
-    fn get_param_state(world: &mut World, system_meta: &mut SystemMeta) -> Self::ParamState {
-        // TODO: Use an alias-permitting technique
-        // let component_id = world.init_resource::<RandomArena<T>>();
-        //
-        // let combined_access = system_meta.component_access_set.combined_access();
-        // assert!(
-        //     !combined_access.has_write(component_id),
-        //     "error[B0002]: Res<{}> in system {} conflicts with a previous ResMut<{0}> access. Consider removing the duplicate access.",
-        //     std::any::type_name::<T>(),
-        //     system_meta.name(),
-        // );
-        //
-        // component_id
-
-        <ResMut<Events<T>> as SystemParam>::init_state(world, system_meta)
-    }
-
-    fn update_access_sets(
-        &component_id: &Self::ParamState,
-        world: &mut World,
-        system_meta: &mut SystemMeta,
-    ) {
-        let _ = (component_id, world, system_meta);
-
-        // TODO: Use an alias-permitting technique
-        // system_meta
-        //     .component_access_set
-        //     .add_unfiltered_read(component_id);
-        //
-        // let archetype_component_id = world
-        //     .get_resource_archetype_component_id(component_id)
-        //     .unwrap();
-        //
-        // system_meta
-        //     .archetype_component_access
-        //     .add_read(archetype_component_id);
+    type TlsSnapshot = *mut EventQueue<T>;
+
+    fn collect_access(access: &mut RandomAccessSet) {
+        access.add_read::<EventQueue<T>>();
+    }
+
+    fn get_param_state(access: &RandomAccessSet) -> Self::ParamState {
+        access.component_id::<EventQueue<T>>()
     }
 
     fn fetch_tls_snapshot() -> Self::TlsSnapshot {
@@ -388,6 +542,14 @@ unsafe impl<T: RandomEvent> RandomResourceList for SendsEvent<T> {
     unsafe fn apply_tls_snapshot(&snap: &Self::TlsSnapshot) {
         unsafe { T::tls().set(snap) }
     }
+
+    fn apply(world: &mut World) {
+        let pending = world.resource_mut::<EventQueue<T>>().take();
+
+        if !pending.is_empty() {
+            world.resource_mut::<Events<T>>().extend(pending);
+        }
+    }
 }
 
 unsafe impl RandomResourceList for () {
@@ -396,14 +558,9 @@ unsafe impl RandomResourceList for () {
     type ParamState = ();
     type TlsSnapshot = ();
 
-    fn get_param_state(_world: &mut World, _system_meta: &mut SystemMeta) -> Self::ParamState {}
+    fn collect_access(_access: &mut RandomAccessSet) {}
 
-    fn update_access_sets(
-        _state: &Self::ParamState,
-        _world: &mut World,
-        _system_meta: &mut SystemMeta,
-    ) {
-    }
+    fn get_param_state(_access: &RandomAccessSet) -> Self::ParamState {}
 
     fn fetch_tls_snapshot() -> Self::TlsSnapshot {}
 
@@ -425,18 +582,13 @@ macro_rules! impl_random_resource_list {
             type ParamState = ($first::ParamState, $($rest::ParamState,)*);
             type TlsSnapshot = ($first::TlsSnapshot, $($rest::TlsSnapshot,)*);
 
-            fn get_param_state(world: &mut World, system_meta: &mut SystemMeta) -> Self::ParamState {
-                ($first::get_param_state(world, system_meta), $($rest::get_param_state(world, system_meta),)*)
+            fn collect_access(access: &mut RandomAccessSet) {
+                $first::collect_access(access);
+                $($rest::collect_access(access);)*
             }
 
-            #[allow(non_snake_case)]
-            fn update_access_sets(
-                ($first, $($rest,)*): &Self::ParamState,
-                world: &mut World,
-                system_meta: &mut SystemMeta,
-            ) {
-                $first::update_access_sets($first, world, system_meta);
-                $($rest::update_access_sets($rest, world, system_meta);)*
+            fn get_param_state(access: &RandomAccessSet) -> Self::ParamState {
+                ($first::get_param_state(access), $($rest::get_param_state(access),)*)
             }
 
             fn fetch_tls_snapshot() -> Self::TlsSnapshot {
@@ -453,6 +605,11 @@ macro_rules! impl_random_resource_list {
                 $first::apply_tls_snapshot($first);
                 $($rest::apply_tls_snapshot($rest);)*
             }
+
+            fn apply(world: &mut World) {
+                $first::apply(world);
+                $($rest::apply(world);)*
+            }
         }
 
         impl_random_resource_list!($($rest)*);
@@ -468,42 +625,121 @@ pub struct RandomComponentToken<T> {
 }
 
 pub unsafe trait RandomComponent: 'static + Sized + Send + Sync {
-    unsafe fn tls() -> &'static LocalKey<Cell<*mut RandomArena<Self>>>;
+    /// Whether [`on_remove`](Self::on_remove) does anything. Components with a hook are unlinked
+    /// in [`RandomUnlinkSet::Hooked`] so that the components their hook touches are still alive.
+    const HAS_REMOVE_HOOK: bool = false;
+
+    /// The resources accessed by [`on_remove`](Self::on_remove) on top of the component's own
+    /// arena.
+    type RemoveAccess: RandomResourceList;
+
+    unsafe fn tls() -> &'static LocalKey<Cell<ArenaSnapshot<Self>>>;
+
+    /// Called by the [unlinker](make_unlinker_system) once the owner of `obj` has been despawned
+    /// or has had its [`ObjOwner`] removed but before its arena slot is freed. This is the place to
+    /// clean up any cross-references to the component held by other components.
+    fn on_remove(obj: Obj<Self>) {
+        let _ = obj;
+    }
 
     fn arena<'a>() -> &'a RandomArena<Self> {
         autoken::tie!('a => ref RandomComponentToken<Self>);
-        unsafe { &*Self::tls().get() }
+        unsafe { &*Self::tls().get().arena }
     }
 
     fn arena_mut<'a>() -> &'a mut RandomArena<Self> {
         autoken::tie!('a => mut RandomComponentToken<Self>);
-        unsafe { &mut *Self::tls().get() }
+        let snap = unsafe { Self::tls().get() };
+
+        // Systems with shared access to the same arena may be running in parallel on other
+        // threads so we can't let them mutate it.
+        assert!(
+            snap.exclusive,
+            "{} was only provided with shared access",
+            std::any::type_name::<Self>()
+        );
+
+        unsafe { &mut *snap.arena }
+    }
+}
+
+/// The arena provided to the current thread by [`RandomAccess::provide`]. Arenas provided through
+/// a `&T` borrow are shared with any other system reading them in parallel and can therefore only
+/// be mutated when `exclusive` is set.
+pub struct ArenaSnapshot<T> {
+    arena: *mut RandomArena<T>,
+    exclusive: bool,
+}
+
+impl<T> Copy for ArenaSnapshot<T> {}
+
+impl<T> Clone for ArenaSnapshot<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> ArenaSnapshot<T> {
+    pub const NULL: Self = Self {
+        arena: null_mut(),
+        exclusive: false,
+    };
+
+    unsafe fn from_world(id: ComponentId, exclusive: bool, world: UnsafeWorldCell<'_>) -> Self
+    where
+        T: RandomComponent,
+    {
+        let arena = world
+            .get_resource_by_id(id)
+            .unwrap_or_else(|| {
+                panic!(
+                    "Random component never registered: {}",
+                    std::any::type_name::<T>()
+                )
+            })
+            .as_ptr()
+            .cast();
+
+        Self { arena, exclusive }
     }
 }
 
 #[doc(hidden)]
 pub mod random_component_internals {
     pub use {
-        super::{RandomArena, RandomComponent},
-        std::{cell::Cell, ptr::null_mut, thread::LocalKey, thread_local},
+        super::{ArenaSnapshot, RandomComponent},
+        std::{cell::Cell, thread::LocalKey, thread_local},
     };
 }
 
 #[macro_export]
 macro_rules! random_component {
-    ($($ty:ty),*$(,)?) => {$(
+    ($ty:ty { on_remove: $access:ty => $hook:expr $(,)? }) => {
+        $crate::random_component!(@impl $ty;
+            const HAS_REMOVE_HOOK: bool = true;
+
+            type RemoveAccess = $access;
+
+            fn on_remove(obj: $crate::util::arena::Obj<Self>) {
+                $hook(obj)
+            }
+        );
+    };
+    (@impl $ty:ty; $($hook:tt)*) => {
         unsafe impl $crate::util::arena::random_component_internals::RandomComponent for $ty {
+            $($hook)*
+
             unsafe fn tls() -> &'static $crate::util::arena::random_component_internals::LocalKey<
                 $crate::util::arena::random_component_internals::Cell<
-                    *mut $crate::util::arena::random_component_internals::RandomArena<Self>,
+                    $crate::util::arena::random_component_internals::ArenaSnapshot<Self>,
                 >>
             {
                 $crate::util::arena::random_component_internals::thread_local! {
                     static TLS: $crate::util::arena::random_component_internals::Cell<
-                        *mut $crate::util::arena::random_component_internals::RandomArena<$ty>,
+                        $crate::util::arena::random_component_internals::ArenaSnapshot<$ty>,
                     > = const {
                         $crate::util::arena::random_component_internals::Cell::new(
-                            $crate::util::arena::random_component_internals::null_mut(),
+                            $crate::util::arena::random_component_internals::ArenaSnapshot::NULL,
                         )
                     };
                 }
@@ -511,6 +747,9 @@ macro_rules! random_component {
                 &TLS
             }
         }
+    };
+    ($($ty:ty),*$(,)?) => {$(
+        $crate::random_component!(@impl $ty; type RemoveAccess = (););
     )*};
 }
 
@@ -521,24 +760,43 @@ pub struct RandomEventToken<T> {
 }
 
 pub unsafe trait RandomEvent: 'static + Sized + Send + Sync + Event {
-    unsafe fn tls() -> &'static LocalKey<Cell<*mut Events<Self>>>;
+    unsafe fn tls() -> &'static LocalKey<Cell<*mut EventQueue<Self>>>;
 
-    fn events<'a>() -> &'a Events<Self> {
+    fn queue<'a>() -> &'a EventQueue<Self> {
         autoken::tie!('a => ref RandomEventToken<Self>);
         unsafe { &*Self::tls().get() }
     }
+}
+
+/// A buffer of events which have been sent through [`send_event`] but not yet flushed into the
+/// corresponding [`Events`] resource.
+#[derive(Debug, Resource)]
+pub struct EventQueue<T> {
+    pending: Mutex<Vec<T>>,
+}
+
+impl<T> Default for EventQueue<T> {
+    fn default() -> Self {
+        Self {
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+}
 
-    fn events_mut<'a>() -> &'a mut Events<Self> {
-        autoken::tie!('a => mut RandomEventToken<Self>);
-        unsafe { &mut *Self::tls().get() }
+impl<T> EventQueue<T> {
+    pub fn push(&self, event: T) {
+        self.pending.lock().unwrap().push(event);
+    }
+
+    pub fn take(&mut self) -> Vec<T> {
+        std::mem::take(self.pending.get_mut().unwrap())
     }
 }
 
 #[doc(hidden)]
 pub mod random_event_internals {
     pub use {
-        super::RandomEvent,
-        bevy_ecs::event::Events,
+        super::{EventQueue, RandomEvent},
         std::{cell::Cell, ptr::null_mut, thread::LocalKey, thread_local},
     };
 }
@@ -549,12 +807,12 @@ macro_rules! random_event {
         unsafe impl $crate::util::arena::random_event_internals::RandomEvent for $ty {
             unsafe fn tls() -> &'static $crate::util::arena::random_event_internals::LocalKey<
                 $crate::util::arena::random_event_internals::Cell<
-                    *mut $crate::util::arena::random_event_internals::Events<Self>,
+                    *mut $crate::util::arena::random_event_internals::EventQueue<Self>,
                 >>
             {
                 $crate::util::arena::random_event_internals::thread_local! {
                     static TLS: $crate::util::arena::random_event_internals::Cell<
-                        *mut $crate::util::arena::random_event_internals::Events<$ty>,
+                        *mut $crate::util::arena::random_event_internals::EventQueue<$ty>,
                     > = const {
                         $crate::util::arena::random_event_internals::Cell::new(
                             $crate::util::arena::random_event_internals::null_mut(),
@@ -603,20 +861,58 @@ impl<T> PartialEq for Obj<T> {
 
 impl<T: RandomComponent> Obj<T> {
     fn new(owner: Entity, value: T) -> Self {
+        let (obj, created) = Self::insert_slot(T::arena_mut(), owner, value);
+        if created {
+            CommandsCap::get_mut(|v| {
+                v.entity(owner).insert(ObjOwner(obj));
+            });
+        }
+        obj
+    }
+
+    /// Inserts a component onto each entity in `values`, writing the arena slots in bulk and
+    /// linking every newly created slot to its owner with a single command.
+    pub fn insert_batch(values: impl IntoIterator<Item = (Entity, T)>) -> Vec<Self> {
+        let values = values.into_iter();
         let arena = T::arena_mut();
+        arena.arena.reserve(values.size_hint().0);
+        arena.map.reserve(values.size_hint().0);
+
+        let mut objs = Vec::with_capacity(values.size_hint().0);
+        let mut owners = Vec::with_capacity(values.size_hint().0);
+
+        for (owner, value) in values {
+            let (obj, created) = Self::insert_slot(arena, owner, value);
+            if created {
+                owners.push((owner, ObjOwner(obj)));
+            }
+            objs.push(obj);
+        }
+
+        if !owners.is_empty() {
+            CommandsCap::get_mut(|v| v.insert_or_spawn_batch(owners));
+        }
+
+        objs
+    }
+
+    fn insert_slot(arena: &mut RandomArena<T>, owner: Entity, value: T) -> (Self, bool) {
         match arena.map.entry(owner) {
             hash_map::Entry::Occupied(entry) => {
                 let obj = *entry.into_mut();
-                arena.arena[obj.index] = (owner, value);
-                obj
+                arena.arena[obj.index] = (owner, value, CHANGE_TICKS.get().this_run);
+                arena.stats.overwritten += 1;
+                (obj, false)
             }
             hash_map::Entry::Vacant(entry) => {
-                let obj = Self::from_index(arena.arena.insert((owner, value)));
-                CommandsCap::get_mut(|v| {
-                    v.entity(owner).insert(ObjOwner(obj));
-                });
+                let obj = Self::from_index(arena.arena.insert((
+                    owner,
+                    value,
+                    CHANGE_TICKS.get().this_run,
+                )));
+                arena.stats.created += 1;
                 entry.insert(obj);
-                obj
+                (obj, true)
             }
         }
     }
@@ -625,6 +921,16 @@ impl<T: RandomComponent> Obj<T> {
         T::arena().arena[self.index].0
     }
 
+    /// Fetches the `U` component of the entity owning this object, panicking if it has none.
+    pub fn map_entity<U: RandomComponent>(self) -> Obj<U> {
+        self.entity().get::<U>()
+    }
+
+    /// Fetches the `U` component of the entity owning this object, if it has one.
+    pub fn try_map_entity<U: RandomComponent>(self) -> Option<Obj<U>> {
+        self.entity().try_get::<U>()
+    }
+
     pub fn is_alive(self) -> bool {
         T::arena().arena.contains(self.index)
     }
@@ -635,11 +941,81 @@ impl<T: RandomComponent> Obj<T> {
         &T::arena().arena[self.index].1
     }
 
+    /// Mutably borrows the component, marking it as changed.
     #[allow(clippy::should_implement_trait)]
     pub fn deref_mut<'a>(self) -> &'a mut T {
+        autoken::tie!('a => mut RandomComponentToken<T>);
+        let (_, value, changed) = &mut T::arena_mut().arena[self.index];
+        *changed = CHANGE_TICKS.get().this_run;
+        value
+    }
+
+    /// Mutably borrows the component without marking it as changed.
+    pub fn bypass_change_detection<'a>(self) -> &'a mut T {
         autoken::tie!('a => mut RandomComponentToken<T>);
         &mut T::arena_mut().arena[self.index].1
     }
+
+    pub fn try_entity(self) -> Option<Entity> {
+        T::arena().arena.get(self.index).map(|(entity, ..)| *entity)
+    }
+
+    pub fn try_deref<'a>(self) -> Option<&'a T> {
+        autoken::tie!('a => ref RandomComponentToken<T>);
+        T::arena().arena.get(self.index).map(|(_, value, _)| value)
+    }
+
+    pub fn try_deref_mut<'a>(self) -> Option<&'a mut T> {
+        autoken::tie!('a => mut RandomComponentToken<T>);
+        T::arena_mut()
+            .arena
+            .get_mut(self.index)
+            .map(|(_, value, changed)| {
+                *changed = CHANGE_TICKS.get().this_run;
+                value
+            })
+    }
+
+    /// The tick at which the component was last inserted or mutably borrowed.
+    pub fn last_changed(self) -> Tick {
+        T::arena().arena[self.index].2
+    }
+
+    /// Returns `true` if the component was inserted or mutably borrowed since the last time the
+    /// current system ran.
+    pub fn is_changed(self) -> bool {
+        let ticks = CHANGE_TICKS.get();
+        self.last_changed()
+            .is_newer_than(ticks.last_run, ticks.this_run)
+    }
+}
+
+// === ChangedObj === //
+
+/// The random component analogue of Bevy's `Changed<T>` filter. Since mutations through [`Obj`]
+/// never touch the [`ObjOwner`] component, `Changed<ObjOwner<T>>` only fires when the owner is
+/// first linked. Use this instead to find components which were mutated since the current system
+/// last ran.
+pub struct ChangedObj<T>(PhantomData<fn() -> T>);
+
+impl<T: RandomComponent> ChangedObj<T> {
+    /// Iterates over every live component of type `T` which was changed since the current system
+    /// last ran.
+    pub fn iter<'a>() -> impl Iterator<Item = Obj<T>> + 'a {
+        autoken::tie!('a => ref RandomComponentToken<T>);
+        let ticks = CHANGE_TICKS.get();
+
+        T::arena()
+            .arena
+            .iter()
+            .filter(move |(_, (.., changed))| changed.is_newer_than(ticks.last_run, ticks.this_run))
+            .map(|(index, _)| Obj::from_index(index))
+    }
+
+    /// Returns `true` if `obj` is alive and passes the filter.
+    pub fn matches(obj: Obj<T>) -> bool {
+        obj.is_alive() && obj.is_changed()
+    }
 }
 
 impl<T> Obj<T> {
@@ -653,6 +1029,10 @@ impl<T> Obj<T> {
     pub fn index(me: Self) -> Index {
         me.index
     }
+
+    pub fn downgrade(self) -> WeakObj<T> {
+        WeakObj(self)
+    }
 }
 
 impl<T: RandomComponent> Deref for Obj<T> {
@@ -671,6 +1051,95 @@ impl<T: RandomComponent> DerefMut for Obj<T> {
     }
 }
 
+// === WeakObj === //
+
+/// An [`Obj`] which may outlive its target. Every access checks the slot's generation and reports
+/// dangling handles as a [`DanglingObj`] error instead of panicking.
+#[repr(transparent)]
+pub struct WeakObj<T>(Obj<T>);
+
+impl<T> fmt::Debug for WeakObj<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("WeakObj").field(&self.0).finish()
+    }
+}
+
+impl<T> Copy for WeakObj<T> {}
+
+impl<T> Clone for WeakObj<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Eq for WeakObj<T> {}
+
+impl<T> PartialEq for WeakObj<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T> From<Obj<T>> for WeakObj<T> {
+    fn from(obj: Obj<T>) -> Self {
+        obj.downgrade()
+    }
+}
+
+impl<T: RandomComponent> WeakObj<T> {
+    pub fn is_alive(self) -> bool {
+        self.0.is_alive()
+    }
+
+    pub fn upgrade(self) -> Result<Obj<T>, DanglingObj> {
+        if self.is_alive() {
+            Ok(self.0)
+        } else {
+            Err(self.dangling())
+        }
+    }
+
+    pub fn entity(self) -> Result<Entity, DanglingObj> {
+        self.0.try_entity().ok_or_else(|| self.dangling())
+    }
+
+    pub fn get<'a>(self) -> Result<&'a T, DanglingObj> {
+        autoken::tie!('a => ref RandomComponentToken<T>);
+        self.0.try_deref().ok_or_else(|| self.dangling())
+    }
+
+    pub fn get_mut<'a>(self) -> Result<&'a mut T, DanglingObj> {
+        autoken::tie!('a => mut RandomComponentToken<T>);
+        self.0.try_deref_mut().ok_or_else(|| self.dangling())
+    }
+
+    fn dangling(self) -> DanglingObj {
+        DanglingObj {
+            type_name: std::any::type_name::<T>(),
+            index: self.0.index,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DanglingObj {
+    pub type_name: &'static str,
+    pub index: Index,
+}
+
+impl fmt::Display for DanglingObj {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (slot, generation) = self.index.into_raw_parts();
+        write!(
+            f,
+            "dangling Obj<{}> (slot {slot}, generation {generation})",
+            self.type_name
+        )
+    }
+}
+
+impl std::error::Error for DanglingObj {}
+
 pub trait RandomEntityExt {
     fn insert<T: RandomComponent>(self, value: T) -> Obj<T>;
 
@@ -681,6 +1150,10 @@ pub trait RandomEntityExt {
     fn try_get<T: RandomComponent>(self) -> Option<Obj<T>>;
 
     fn get<T: RandomComponent>(self) -> Obj<T>;
+
+    /// Fetches the entity's `T` component, inserting the value produced by `f` if it doesn't have
+    /// one yet.
+    fn get_or_insert_with<T: RandomComponent>(self, f: impl FnOnce() -> T) -> Obj<T>;
 }
 
 impl RandomEntityExt for Entity {
@@ -705,6 +1178,10 @@ impl RandomEntityExt for Entity {
     fn get<T: RandomComponent>(self) -> Obj<T> {
         self.try_get::<T>().unwrap()
     }
+
+    fn get_or_insert_with<T: RandomComponent>(self, f: impl FnOnce() -> T) -> Obj<T> {
+        self.try_get::<T>().unwrap_or_else(|| self.insert(f()))
+    }
 }
 
 // === System Link === //
@@ -727,39 +1204,225 @@ impl<T> Clone for ObjOwner<T> {
 }
 
 pub trait RandomAppExt {
-    fn add_random_component<T: RandomComponent>(&mut self);
+    fn add_random_component<T: RandomComponent + fmt::Debug>(&mut self);
+
+    fn add_random_event<T: RandomEvent>(&mut self);
 }
 
 impl RandomAppExt for App {
-    fn add_random_component<T: RandomComponent>(&mut self) {
+    fn add_random_component<T: RandomComponent + fmt::Debug>(&mut self) {
+        if !self.world.contains_resource::<RandomArenaRegistry>() {
+            self.configure_sets(
+                Last,
+                (RandomUnlinkSet::Hooked, RandomUnlinkSet::Plain).chain(),
+            );
+        }
+
+        let unlink_set = if T::HAS_REMOVE_HOOK {
+            RandomUnlinkSet::Hooked
+        } else {
+            RandomUnlinkSet::Plain
+        };
+
         self.init_resource::<RandomArena<T>>();
-        self.add_systems(Last, make_unlinker_system::<T>());
+        self.world
+            .get_resource_or_insert_with(RandomArenaRegistry::default)
+            .register::<T>();
+
+        if cfg!(debug_assertions) {
+            self.add_systems(
+                Last,
+                (
+                    make_unlinker_system::<T>(),
+                    make_leak_detector_system::<T>(),
+                )
+                    .chain()
+                    .in_set(unlink_set),
+            );
+        } else {
+            self.add_systems(Last, make_unlinker_system::<T>().in_set(unlink_set));
+        }
+
+        self.add_systems(First, make_change_tick_checker_system::<T>());
+    }
+
+    fn add_random_event<T: RandomEvent>(&mut self) {
+        self.add_event::<T>();
+        self.init_resource::<EventQueue<T>>();
     }
 }
 
-pub fn make_unlinker_system<T: RandomComponent>(
-) -> impl 'static + Send + Sync + Fn(RandomAccess<&mut T>, RemovedComponents<ObjOwner<T>>) {
+/// The sets in which the [unlinkers](make_unlinker_system) run during [`Last`]. Components with
+/// a [removal hook](RandomComponent::on_remove) are unlinked first so that every other component
+/// is still alive while the hooks run.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, SystemSet)]
+pub enum RandomUnlinkSet {
+    Hooked,
+    Plain,
+}
+
+pub fn make_unlinker_system<T: RandomComponent>() -> impl 'static
+       + Send
+       + Sync
+       + Fn(RandomAccess<(&mut T, T::RemoveAccess)>, RemovedComponents<ObjOwner<T>>) {
     |mut rand, mut removed| {
         rand.provide(|| {
-            let arena = T::arena_mut();
-
             for removed in removed.read() {
-                if let Some(obj) = arena.map.remove(&removed) {
-                    arena.arena.remove(obj.index);
-                }
+                let Some(&obj) = T::arena().map.get(&removed) else {
+                    continue;
+                };
+
+                T::on_remove(obj);
+
+                let arena = T::arena_mut();
+                arena.map.remove(&removed);
+                arena.arena.remove(obj.index);
+                arena.stats.destroyed += 1;
             }
         });
     }
 }
 
+// === Change Ticks === //
+
+/// Clamps the change ticks of every slot of the arena so that they never grow so old that they wrap
+/// around and look new again. Bevy does the same for its own components in
+/// [`World::check_change_ticks`], and this runs on the same cadence.
+pub fn make_change_tick_checker_system<T: RandomComponent>(
+) -> impl 'static + Send + Sync + Fn(ResMut<RandomArena<T>>, Local<Option<Tick>>, SystemChangeTick)
+{
+    |mut arena, mut last_check, ticks| {
+        let this_run = ticks.this_run();
+        let last_check = last_check.get_or_insert(this_run);
+
+        if this_run.relative_to(*last_check).get() < CHECK_TICK_THRESHOLD {
+            return;
+        }
+        *last_check = this_run;
+
+        for (_, (.., changed)) in arena.bypass_change_detection().arena.iter_mut() {
+            changed.check_tick(this_run);
+        }
+    }
+}
+
+// === Leak Detection === //
+
+#[derive(Debug, Default)]
+pub struct LeakDetectorState {
+    frame: u32,
+    last_slots: usize,
+    last_owners: usize,
+    growth_streak: u32,
+}
+
+impl LeakDetectorState {
+    /// The number of frames between two reports.
+    pub const REPORT_INTERVAL: u32 = 600;
+
+    /// The number of consecutive reports in which the arena must grow without its owner count
+    /// growing before we consider it to be leaking.
+    pub const LEAK_STREAK: u32 = 3;
+}
+
+pub fn make_leak_detector_system<T: RandomComponent>() -> impl 'static
+       + Send
+       + Sync
+       + Fn(Res<RandomArena<T>>, Query<(), With<ObjOwner<T>>>, Local<LeakDetectorState>) {
+    |arena, owners, mut state| {
+        state.frame += 1;
+        if state.frame < LeakDetectorState::REPORT_INTERVAL {
+            return;
+        }
+        state.frame = 0;
+
+        let name = std::any::type_name::<T>();
+        let slots = arena.arena.len();
+        let owners = owners.iter().len();
+        let stats = arena.stats;
+
+        log::debug!(
+            "Arena {name}: {slots} slot(s), {owners} owner(s), {} created, {} destroyed, {} overwritten",
+            stats.created,
+            stats.destroyed,
+            stats.overwritten,
+        );
+
+        if slots != arena.map.len() {
+            log::warn!(
+                "Arena {name} is out of sync with its entity map: {slots} slot(s) but {} mapped entities",
+                arena.map.len(),
+            );
+        }
+
+        if slots > state.last_slots && owners <= state.last_owners {
+            state.growth_streak += 1;
+
+            if state.growth_streak >= LeakDetectorState::LEAK_STREAK {
+                log::warn!(
+                    "Arena {name} may be leaking: its slot count grew for {} consecutive report(s) \
+                     ({} -> {slots}) while its owner count did not ({} -> {owners})",
+                    state.growth_streak,
+                    state.last_slots,
+                    state.last_owners,
+                );
+            }
+        } else {
+            state.growth_streak = 0;
+        }
+
+        state.last_slots = slots;
+        state.last_owners = owners;
+    }
+}
+
+// === Helpers === //
+
 pub fn spawn_entity(bundle: impl Bundle) -> Entity {
     CommandsCap::get_mut(|v| v.spawn(bundle).id()).0
 }
 
+/// Spawns an entity for each bundle in `bundles`. The entity ids are reserved up-front so the whole
+/// batch is spawned by a single command rather than one command per entity.
+pub fn spawn_entities_batch<B: Bundle>(bundles: impl IntoIterator<Item = B>) -> Vec<Entity> {
+    let bundles = bundles.into_iter().collect::<Vec<_>>();
+    let entities =
+        EntitiesCap::get_mut(|v| v.reserve_entities(bundles.len() as u32).collect::<Vec<_>>()).0;
+
+    if !bundles.is_empty() {
+        let batch = entities.clone().into_iter().zip(bundles);
+        CommandsCap::get_mut(|v| v.insert_or_spawn_batch(batch));
+    }
+
+    entities
+}
+
+/// Calls `f` with every live component of type `T` and its owner, marking each one as changed.
+/// The current [`RandomAccess`] must provide `&mut T`.
+pub fn for_each_obj<T: RandomComponent>(mut f: impl FnMut(Entity, &mut T)) {
+    for (_, owner, value) in T::arena_mut().iter_mut() {
+        f(owner, value);
+    }
+}
+
+/// Calls `f` with every live component of type `T` and its owner.
+pub fn for_each_obj_ref<T: RandomComponent>(mut f: impl FnMut(Entity, &T)) {
+    for (_, owner, value) in T::arena().iter() {
+        f(owner, value);
+    }
+}
+
+/// Inserts `bundle` onto an existing entity, overwriting any components it already has.
+pub fn insert_bundle(entity: Entity, bundle: impl Bundle) {
+    CommandsCap::get_mut(|v| {
+        v.entity(entity).insert(bundle);
+    });
+}
+
 pub fn despawn_entity(entity: Entity) {
     CommandsCap::get_mut(|v| v.entity(entity).despawn());
 }
 
 pub fn send_event<E: RandomEvent>(event: E) {
-    E::events_mut().send(event);
+    E::queue().push(event);
 }
@@ -0,0 +1,30 @@
+use std::{fmt, sync::Arc};
+
+// === Delegate === //
+
+/// A cheaply cloneable callback which can be stored in components and resources.
+pub struct Delegate<A> {
+    f: Arc<dyn Fn(A) + Send + Sync>,
+}
+
+impl<A> Delegate<A> {
+    pub fn new(f: impl 'static + Send + Sync + Fn(A)) -> Self {
+        Self { f: Arc::new(f) }
+    }
+
+    pub fn call(&self, arg: A) {
+        (self.f)(arg)
+    }
+}
+
+impl<A> Clone for Delegate<A> {
+    fn clone(&self) -> Self {
+        Self { f: self.f.clone() }
+    }
+}
+
+impl<A> fmt::Debug for Delegate<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Delegate<{}>", std::any::type_name::<A>())
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt::Write;
+
+use bevy_ecs::world::World;
+use macroquad::input::{is_key_pressed, KeyCode};
+
+use super::arena::RandomArenaRegistry;
+
+// === World Statistics === //
+
+/// Shortens a fully qualified type name by stripping the module path from every path segment, e.g.
+/// `bevy_demo::util::arena::ObjOwner<bevy_demo::game::tile::data::TileWorld>` becomes
+/// `ObjOwner<TileWorld>`.
+pub fn short_type_name(name: &str) -> String {
+    let mut short = String::with_capacity(name.len());
+    let mut segment_start = 0;
+
+    for (i, ch) in name.char_indices() {
+        if matches!(
+            ch,
+            '<' | '>' | ',' | '(' | ')' | '[' | ']' | ';' | '&' | ' '
+        ) {
+            short.push_str(last_path_segment(&name[segment_start..i]));
+            short.push(ch);
+            segment_start = i + ch.len_utf8();
+        }
+    }
+
+    short.push_str(last_path_segment(&name[segment_start..]));
+    short
+}
+
+fn last_path_segment(path: &str) -> &str {
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+/// Produces a human-readable report of how entities are distributed across archetypes and tables,
+/// along with the occupancy of every random component arena.
+pub fn describe_world_stats(world: &World) -> String {
+    let mut out = String::new();
+
+    // Archetypes
+    let mut archetypes = world
+        .archetypes()
+        .iter()
+        .filter(|archetype| !archetype.is_empty())
+        .collect::<Vec<_>>();
+
+    archetypes.sort_by_key(|archetype| std::cmp::Reverse(archetype.len()));
+
+    let singletons = archetypes.iter().filter(|arch| arch.len() == 1).count();
+
+    writeln!(
+        out,
+        "{} entities in {} non-empty archetype(s) ({} total, {} holding a single entity)",
+        world.entities().len(),
+        archetypes.len(),
+        world.archetypes().len(),
+        singletons,
+    )
+    .unwrap();
+
+    for archetype in &archetypes {
+        let mut components = archetype
+            .components()
+            .filter_map(|id| world.components().get_info(id))
+            .map(|info| short_type_name(info.name()))
+            .collect::<Vec<_>>();
+
+        components.sort();
+
+        writeln!(
+            out,
+            "  {:?}: {} entities, table {:?} | {}",
+            archetype.id(),
+            archetype.len(),
+            archetype.table_id(),
+            components.join(", "),
+        )
+        .unwrap();
+    }
+
+    // Tables
+    let tables = &world.storages().tables;
+    writeln!(out, "{} table(s)", tables.len()).unwrap();
+
+    for (i, table) in tables.iter().enumerate() {
+        if table.is_empty() {
+            continue;
+        }
+
+        writeln!(
+            out,
+            "  table {i}: {} entities ({} capacity), {} column(s)",
+            table.entity_count(),
+            table.entity_capacity(),
+            table.component_count(),
+        )
+        .unwrap();
+    }
+
+    // Random arenas
+    if let Some(registry) = world.get_resource::<RandomArenaRegistry>() {
+        let mut arenas = registry.summarize(world);
+        arenas.sort_by_key(|arena| std::cmp::Reverse(arena.slots));
+
+        writeln!(out, "{} random arena(s)", arenas.len()).unwrap();
+
+        for arena in arenas {
+            writeln!(
+                out,
+                "  {}: {} slot(s) ({} capacity), {} created, {} destroyed, {} overwritten",
+                short_type_name(arena.name),
+                arena.slots,
+                arena.capacity,
+                arena.stats.created,
+                arena.stats.destroyed,
+                arena.stats.overwritten,
+            )
+            .unwrap();
+        }
+    }
+
+    out
+}
+
+pub fn sys_dump_world_stats(world: &World) {
+    if is_key_pressed(KeyCode::F10) {
+        log::info!("World statistics:\n{}", describe_world_stats(world));
+    }
+}
@@ -1,3 +1,7 @@
 pub mod arena;
+pub mod delegate;
+pub mod diagnostics;
 pub mod lang;
-pub mod schedule;
+pub mod parallel;
+pub mod profiler;
+pub mod timer;
@@ -0,0 +1,56 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bevy_tasks::{ComputeTaskPool, TaskPool};
+
+// === par_map === //
+
+/// Maps `f` over `items` with at most `max_workers` tasks on the shared [`ComputeTaskPool`],
+/// returning the results in the same order as `items`. Lists shorter than `threshold` are mapped on
+/// the calling thread since scheduling tasks would cost more than it saves.
+pub fn par_map<T, R>(
+    items: &[T],
+    max_workers: usize,
+    threshold: usize,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R>
+where
+    T: Sync,
+    R: Send + 'static,
+{
+    let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+    let workers = pool.thread_num().min(max_workers).min(items.len());
+
+    if workers <= 1 || items.len() < threshold {
+        return items.iter().map(f).collect();
+    }
+
+    // Workers claim items one at a time so that slow items don't leave other workers idle.
+    let next = AtomicUsize::new(0);
+
+    let batches = pool.scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(async {
+                let mut done = Vec::new();
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(index) else {
+                        break;
+                    };
+
+                    done.push((index, f(item)));
+                }
+                done
+            });
+        }
+    });
+
+    let mut results = items.iter().map(|_| None).collect::<Vec<_>>();
+    for (index, result) in batches.into_iter().flatten() {
+        results[index] = Some(result);
+    }
+
+    results
+        .into_iter()
+        .map(|result| result.expect("every item is claimed by a worker"))
+        .collect()
+}
@@ -0,0 +1,438 @@
+use std::{
+    any::TypeId,
+    borrow::Cow,
+    collections::VecDeque,
+    fmt::Write,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use bevy_ecs::{
+    archetype::ArchetypeComponentId,
+    component::{ComponentId, Tick},
+    query::Access,
+    schedule::InternedSystemSet,
+    system::{BoxedSystem, IntoSystem, Res, ResMut, Resource, System},
+    world::{unsafe_world_cell::UnsafeWorldCell, World},
+};
+use macroquad::{
+    color::{Color, GREEN, RED, WHITE, YELLOW},
+    input::{is_key_pressed, KeyCode},
+    shapes::{draw_line, draw_rectangle},
+    text::draw_text,
+    time::get_frame_time,
+    window::screen_width,
+};
+
+use super::diagnostics::short_type_name;
+
+// === Sample Collection === //
+
+/// Durations recorded by [`ProfiledSystem`]s since the last time the [`Profiler`] sampled them.
+/// Systems only have access to the world data they declared so they report their timings here
+/// rather than into the resource directly.
+static PENDING_SAMPLES: Mutex<Vec<(usize, Duration)>> = Mutex::new(Vec::new());
+
+/// The names of every system wrapped in a [`ProfiledSystem`], indexed by their profiler id.
+static PROFILED_NAMES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Wraps a system so that the time spent running it is reported to the [`Profiler`].
+pub struct ProfiledSystem<S> {
+    inner: S,
+    id: usize,
+}
+
+impl<S: System<In = (), Out = ()>> ProfiledSystem<S> {
+    pub fn new(inner: S) -> Self {
+        let mut names = PROFILED_NAMES.lock().unwrap();
+        let id = names.len();
+        names.push(short_type_name(&inner.name()));
+
+        Self { inner, id }
+    }
+
+    fn record(&self, start: Instant) {
+        PENDING_SAMPLES
+            .lock()
+            .unwrap()
+            .push((self.id, start.elapsed()));
+    }
+}
+
+impl<S: System<In = (), Out = ()>> System for ProfiledSystem<S> {
+    type In = ();
+    type Out = ();
+
+    fn name(&self) -> Cow<'static, str> {
+        self.inner.name()
+    }
+
+    fn type_id(&self) -> TypeId {
+        self.inner.type_id()
+    }
+
+    fn component_access(&self) -> &Access<ComponentId> {
+        self.inner.component_access()
+    }
+
+    fn archetype_component_access(&self) -> &Access<ArchetypeComponentId> {
+        self.inner.archetype_component_access()
+    }
+
+    fn is_send(&self) -> bool {
+        self.inner.is_send()
+    }
+
+    fn is_exclusive(&self) -> bool {
+        self.inner.is_exclusive()
+    }
+
+    fn has_deferred(&self) -> bool {
+        self.inner.has_deferred()
+    }
+
+    unsafe fn run_unsafe(&mut self, input: (), world: UnsafeWorldCell) {
+        let start = Instant::now();
+        self.inner.run_unsafe(input, world);
+        self.record(start);
+    }
+
+    fn run(&mut self, input: (), world: &mut World) {
+        let start = Instant::now();
+        self.inner.run(input, world);
+        self.record(start);
+    }
+
+    fn apply_deferred(&mut self, world: &mut World) {
+        self.inner.apply_deferred(world);
+    }
+
+    fn initialize(&mut self, world: &mut World) {
+        self.inner.initialize(world);
+    }
+
+    fn update_archetype_component_access(&mut self, world: UnsafeWorldCell) {
+        self.inner.update_archetype_component_access(world);
+    }
+
+    fn check_change_tick(&mut self, change_tick: Tick) {
+        self.inner.check_change_tick(change_tick);
+    }
+
+    fn default_system_sets(&self) -> Vec<InternedSystemSet> {
+        self.inner.default_system_sets()
+    }
+
+    fn get_last_run(&self) -> Tick {
+        self.inner.get_last_run()
+    }
+
+    fn set_last_run(&mut self, last_run: Tick) {
+        self.inner.set_last_run(last_run);
+    }
+}
+
+/// Converts a system or a tuple of systems into their [`ProfiledSystem`] equivalents. The result
+/// can be passed anywhere the original systems could.
+pub trait IntoProfiledSystems<M> {
+    type Output;
+
+    fn into_profiled(self) -> Self::Output;
+}
+
+pub struct SingleProfiledMarker;
+
+pub struct TupleProfiledMarker;
+
+impl<M, S: IntoSystem<(), (), M>> IntoProfiledSystems<(SingleProfiledMarker, M)> for S {
+    type Output = BoxedSystem;
+
+    fn into_profiled(self) -> Self::Output {
+        Box::new(ProfiledSystem::new(IntoSystem::into_system(self)))
+    }
+}
+
+macro_rules! impl_into_profiled_tuple {
+    ($($sys:ident $marker:ident),*) => {
+        impl<$($sys, $marker,)*> IntoProfiledSystems<(TupleProfiledMarker, ($($marker,)*))>
+            for ($($sys,)*)
+        where
+            $($sys: IntoProfiledSystems<$marker>,)*
+        {
+            type Output = ($($sys::Output,)*);
+
+            #[allow(non_snake_case)]
+            fn into_profiled(self) -> Self::Output {
+                let ($($sys,)*) = self;
+                ($($sys.into_profiled(),)*)
+            }
+        }
+    };
+}
+
+impl_into_profiled_tuple!(A0 M0);
+impl_into_profiled_tuple!(A0 M0, A1 M1);
+impl_into_profiled_tuple!(A0 M0, A1 M1, A2 M2);
+impl_into_profiled_tuple!(A0 M0, A1 M1, A2 M2, A3 M3);
+impl_into_profiled_tuple!(A0 M0, A1 M1, A2 M2, A3 M3, A4 M4);
+impl_into_profiled_tuple!(A0 M0, A1 M1, A2 M2, A3 M3, A4 M4, A5 M5);
+impl_into_profiled_tuple!(A0 M0, A1 M1, A2 M2, A3 M3, A4 M4, A5 M5, A6 M6);
+impl_into_profiled_tuple!(A0 M0, A1 M1, A2 M2, A3 M3, A4 M4, A5 M5, A6 M6, A7 M7);
+impl_into_profiled_tuple!(A0 M0, A1 M1, A2 M2, A3 M3, A4 M4, A5 M5, A6 M6, A7 M7, A8 M8);
+impl_into_profiled_tuple!(
+    A0 M0, A1 M1, A2 M2, A3 M3, A4 M4, A5 M5, A6 M6, A7 M7, A8 M8, A9 M9
+);
+impl_into_profiled_tuple!(
+    A0 M0, A1 M1, A2 M2, A3 M3, A4 M4, A5 M5, A6 M6, A7 M7, A8 M8, A9 M9, A10 M10
+);
+impl_into_profiled_tuple!(
+    A0 M0, A1 M1, A2 M2, A3 M3, A4 M4, A5 M5, A6 M6, A7 M7, A8 M8, A9 M9, A10 M10, A11 M11
+);
+impl_into_profiled_tuple!(
+    A0 M0, A1 M1, A2 M2, A3 M3, A4 M4, A5 M5, A6 M6, A7 M7, A8 M8, A9 M9, A10 M10, A11 M11,
+    A12 M12
+);
+impl_into_profiled_tuple!(
+    A0 M0, A1 M1, A2 M2, A3 M3, A4 M4, A5 M5, A6 M6, A7 M7, A8 M8, A9 M9, A10 M10, A11 M11,
+    A12 M12, A13 M13
+);
+impl_into_profiled_tuple!(
+    A0 M0, A1 M1, A2 M2, A3 M3, A4 M4, A5 M5, A6 M6, A7 M7, A8 M8, A9 M9, A10 M10, A11 M11,
+    A12 M12, A13 M13, A14 M14
+);
+impl_into_profiled_tuple!(
+    A0 M0, A1 M1, A2 M2, A3 M3, A4 M4, A5 M5, A6 M6, A7 M7, A8 M8, A9 M9, A10 M10, A11 M11,
+    A12 M12, A13 M13, A14 M14, A15 M15
+);
+impl_into_profiled_tuple!(
+    A0 M0, A1 M1, A2 M2, A3 M3, A4 M4, A5 M5, A6 M6, A7 M7, A8 M8, A9 M9, A10 M10, A11 M11,
+    A12 M12, A13 M13, A14 M14, A15 M15, A16 M16
+);
+impl_into_profiled_tuple!(
+    A0 M0, A1 M1, A2 M2, A3 M3, A4 M4, A5 M5, A6 M6, A7 M7, A8 M8, A9 M9, A10 M10, A11 M11,
+    A12 M12, A13 M13, A14 M14, A15 M15, A16 M16, A17 M17
+);
+impl_into_profiled_tuple!(
+    A0 M0, A1 M1, A2 M2, A3 M3, A4 M4, A5 M5, A6 M6, A7 M7, A8 M8, A9 M9, A10 M10, A11 M11,
+    A12 M12, A13 M13, A14 M14, A15 M15, A16 M16, A17 M17, A18 M18
+);
+impl_into_profiled_tuple!(
+    A0 M0, A1 M1, A2 M2, A3 M3, A4 M4, A5 M5, A6 M6, A7 M7, A8 M8, A9 M9, A10 M10, A11 M11,
+    A12 M12, A13 M13, A14 M14, A15 M15, A16 M16, A17 M17, A18 M18, A19 M19
+);
+
+/// Wraps every system in `systems` in a [`ProfiledSystem`].
+pub fn profiled<M, S: IntoProfiledSystems<M>>(systems: S) -> S::Output {
+    systems.into_profiled()
+}
+
+// === Profiler === //
+
+#[derive(Debug, Clone, Default)]
+pub struct SystemTimings {
+    pub name: String,
+
+    /// The total time spent in the system during each of the most recent frames, oldest first.
+    /// Systems in schedules which run several times a frame have their runs summed together.
+    pub history: VecDeque<Duration>,
+
+    pub max: Duration,
+}
+
+impl SystemTimings {
+    pub fn last(&self) -> Duration {
+        self.history.back().copied().unwrap_or_default()
+    }
+
+    pub fn average(&self) -> Duration {
+        if self.history.is_empty() {
+            return Duration::ZERO;
+        }
+
+        self.history.iter().sum::<Duration>() / self.history.len() as u32
+    }
+}
+
+#[derive(Debug, Resource)]
+pub struct Profiler {
+    pub overlay_visible: bool,
+
+    /// The number of frames of history kept for the frame-time graph and system averages.
+    pub history_len: usize,
+
+    frame_times: VecDeque<f32>,
+    systems: Vec<SystemTimings>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self {
+            overlay_visible: false,
+            history_len: 240,
+            frame_times: VecDeque::new(),
+            systems: Vec::new(),
+        }
+    }
+}
+
+impl Profiler {
+    pub fn frame_times(&self) -> impl ExactSizeIterator<Item = f32> + '_ {
+        self.frame_times.iter().copied()
+    }
+
+    pub fn systems(&self) -> &[SystemTimings] {
+        &self.systems
+    }
+
+    /// Moves the samples recorded by every [`ProfiledSystem`] since the last call into the
+    /// profiler's history as a single frame.
+    pub fn sample_frame(&mut self, frame_time: f32) {
+        push_bounded(&mut self.frame_times, frame_time, self.history_len);
+
+        let names = PROFILED_NAMES.lock().unwrap();
+        if self.systems.len() < names.len() {
+            self.systems.extend(
+                names[self.systems.len()..]
+                    .iter()
+                    .map(|name| SystemTimings {
+                        name: name.clone(),
+                        ..Default::default()
+                    }),
+            );
+        }
+        drop(names);
+
+        let mut frame = vec![Duration::ZERO; self.systems.len()];
+        for (id, duration) in PENDING_SAMPLES.lock().unwrap().drain(..) {
+            frame[id] += duration;
+        }
+
+        for (timings, duration) in self.systems.iter_mut().zip(frame) {
+            push_bounded(&mut timings.history, duration, self.history_len);
+            timings.max = timings.history.iter().copied().max().unwrap_or_default();
+        }
+    }
+
+    /// Produces a table of every profiled system, sorted from most to least expensive on average.
+    pub fn report(&self) -> String {
+        let mut systems = self.systems.iter().collect::<Vec<_>>();
+        systems.sort_by_key(|timings| std::cmp::Reverse(timings.average()));
+
+        let mut out = String::new();
+        let frame_avg = self.frame_times.iter().sum::<f32>() / self.frame_times.len().max(1) as f32;
+        let frame_max = self.frame_times.iter().copied().fold(0., f32::max);
+
+        writeln!(
+            out,
+            "Frame time over {} frames: avg {:.2}ms, max {:.2}ms",
+            self.frame_times.len(),
+            frame_avg * 1000.,
+            frame_max * 1000.,
+        )
+        .unwrap();
+
+        writeln!(out, "{:>10} {:>10} {:>10}  system", "avg", "max", "last").unwrap();
+        for timings in systems {
+            writeln!(
+                out,
+                "{:>8.3}ms {:>8.3}ms {:>8.3}ms  {}",
+                timings.average().as_secs_f64() * 1000.,
+                timings.max.as_secs_f64() * 1000.,
+                timings.last().as_secs_f64() * 1000.,
+                timings.name,
+            )
+            .unwrap();
+        }
+
+        out
+    }
+}
+
+fn push_bounded<T>(queue: &mut VecDeque<T>, value: T, limit: usize) {
+    queue.push_back(value);
+    while queue.len() > limit {
+        queue.pop_front();
+    }
+}
+
+// === Systems === //
+
+pub fn sys_update_profiler(mut profiler: ResMut<Profiler>) {
+    profiler.sample_frame(get_frame_time());
+
+    if is_key_pressed(KeyCode::F8) {
+        profiler.overlay_visible = !profiler.overlay_visible;
+    }
+
+    if is_key_pressed(KeyCode::F12) {
+        log::info!("Profiler report:\n{}", profiler.report());
+    }
+}
+
+pub fn sys_render_profiler_overlay(profiler: Res<Profiler>) {
+    if !profiler.overlay_visible {
+        return;
+    }
+
+    const WIDTH: f32 = 480.;
+    const GRAPH_HEIGHT: f32 = 80.;
+    const ROW_HEIGHT: f32 = 18.;
+    const MAX_ROWS: usize = 12;
+
+    // The graph is scaled such that a frame at the 30 FPS mark touches the top.
+    const GRAPH_CEILING: f32 = 1. / 30.;
+    const BUDGET: f32 = 1. / 60.;
+
+    let left = screen_width() - WIDTH - 15.;
+    let top = 15.;
+
+    let mut systems = profiler.systems().iter().collect::<Vec<_>>();
+    systems.sort_by_key(|timings| std::cmp::Reverse(timings.average()));
+    systems.truncate(MAX_ROWS);
+
+    let height = GRAPH_HEIGHT + 30. + ROW_HEIGHT * systems.len() as f32;
+    draw_rectangle(left, top, WIDTH, height, Color::new(0., 0., 0., 0.7));
+
+    // Draw the frame-time graph
+    let graph_bottom = top + GRAPH_HEIGHT;
+    let budget_y = graph_bottom - BUDGET / GRAPH_CEILING * GRAPH_HEIGHT;
+    draw_line(left, budget_y, left + WIDTH, budget_y, 1., YELLOW);
+
+    let bar_width = WIDTH / profiler.history_len as f32;
+    for (i, frame_time) in profiler.frame_times().enumerate() {
+        let bar_height = (frame_time / GRAPH_CEILING).min(1.) * GRAPH_HEIGHT;
+        let color = if frame_time > BUDGET { RED } else { GREEN };
+
+        draw_rectangle(
+            left + i as f32 * bar_width,
+            graph_bottom - bar_height,
+            bar_width.max(1.),
+            bar_height,
+            color,
+        );
+    }
+
+    let last_frame = profiler.frame_times().last().unwrap_or(0.);
+    draw_text(
+        &format!("Frame: {:.2}ms (F12 to dump a report)", last_frame * 1000.),
+        left + 5.,
+        graph_bottom + 20.,
+        20.,
+        WHITE,
+    );
+
+    // Draw the most expensive systems
+    for (i, timings) in systems.into_iter().enumerate() {
+        draw_text(
+            &format!(
+                "{:>7.3}ms  {}",
+                timings.average().as_secs_f64() * 1000.,
+                timings.name
+            ),
+            left + 5.,
+            graph_bottom + 30. + ROW_HEIGHT * (i + 1) as f32,
+            18.,
+            WHITE,
+        );
+    }
+}
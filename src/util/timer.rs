@@ -0,0 +1,185 @@
+use bevy_ecs::{component::Component, system::Query};
+
+// === Timer === //
+
+/// What a [`Timer`] does once it has run for its full duration.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum TimerMode {
+    /// Stays finished until it is [reset](Timer::reset).
+    Once,
+
+    /// Finishes once every `duration` ticks, starting over right away.
+    Repeating,
+}
+
+/// Counts fixed-update ticks up to a duration. Timers attached to entities as components are
+/// advanced by [`sys_tick_timers`] but they can also be stored elsewhere and ticked manually.
+#[derive(Debug, Copy, Clone, Component)]
+pub struct Timer {
+    duration: u32,
+    elapsed: u32,
+    mode: TimerMode,
+    just_finished: bool,
+}
+
+impl Timer {
+    pub fn new(duration: u32, mode: TimerMode) -> Self {
+        Self {
+            duration,
+            elapsed: 0,
+            mode,
+            just_finished: false,
+        }
+    }
+
+    pub fn once(duration: u32) -> Self {
+        Self::new(duration, TimerMode::Once)
+    }
+
+    pub fn repeating(duration: u32) -> Self {
+        Self::new(duration, TimerMode::Repeating)
+    }
+
+    pub fn duration(&self) -> u32 {
+        self.duration
+    }
+
+    /// Changes the duration without resetting the progress made so far.
+    pub fn set_duration(&mut self, duration: u32) {
+        self.duration = duration;
+    }
+
+    pub fn mode(&self) -> TimerMode {
+        self.mode
+    }
+
+    /// The number of ticks since the timer was started or last repeated.
+    pub fn elapsed(&self) -> u32 {
+        self.elapsed
+    }
+
+    pub fn remaining(&self) -> u32 {
+        self.duration.saturating_sub(self.elapsed)
+    }
+
+    /// How far along the timer is, from `0.0` when it starts to `1.0` when it finishes.
+    pub fn fraction(&self) -> f32 {
+        if self.duration == 0 {
+            1.
+        } else {
+            (self.elapsed as f32 / self.duration as f32).min(1.)
+        }
+    }
+
+    /// Whether the timer has run for its full duration. Repeating timers are only finished on the
+    /// tick they wrapped around.
+    pub fn finished(&self) -> bool {
+        match self.mode {
+            TimerMode::Once => self.elapsed >= self.duration,
+            TimerMode::Repeating => self.just_finished,
+        }
+    }
+
+    /// Whether the timer finished during the last call to [`tick`](Self::tick).
+    pub fn just_finished(&self) -> bool {
+        self.just_finished
+    }
+
+    /// Advances the timer by a single tick, returning whether it finished because of it.
+    pub fn tick(&mut self) -> bool {
+        match self.mode {
+            TimerMode::Once => {
+                let was_finished = self.elapsed >= self.duration;
+                self.elapsed = self.elapsed.saturating_add(1).min(self.duration);
+                self.just_finished = !was_finished && self.elapsed >= self.duration;
+            }
+            TimerMode::Repeating => {
+                self.elapsed += 1;
+                self.just_finished = self.elapsed >= self.duration;
+                if self.just_finished {
+                    self.elapsed = 0;
+                }
+            }
+        }
+
+        self.just_finished
+    }
+
+    /// Starts the timer over from zero.
+    pub fn reset(&mut self) {
+        self.elapsed = 0;
+        self.just_finished = false;
+    }
+}
+
+// === Cooldown === //
+
+/// Limits how often an action can happen: once [triggered](Self::trigger), the cooldown isn't
+/// ready again until `duration` ticks have passed. Cooldowns start out ready.
+#[derive(Debug, Copy, Clone, Component)]
+pub struct Cooldown {
+    pub duration: u32,
+    remaining: u32,
+}
+
+impl Cooldown {
+    pub fn new(duration: u32) -> Self {
+        Self {
+            duration,
+            remaining: 0,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// The number of ticks left until the cooldown is ready again.
+    pub fn remaining(&self) -> u32 {
+        self.remaining
+    }
+
+    /// How much of the cooldown is left, from `1.0` right after it was triggered to `0.0` once
+    /// it's ready.
+    pub fn fraction(&self) -> f32 {
+        self.remaining as f32 / self.duration.max(1) as f32
+    }
+
+    /// Advances the cooldown by a single tick.
+    pub fn tick(&mut self) {
+        self.remaining = self.remaining.saturating_sub(1);
+    }
+
+    /// Starts the cooldown over, whether or not it was ready.
+    pub fn trigger(&mut self) {
+        self.remaining = self.duration;
+    }
+
+    /// Triggers the cooldown if it's ready, returning whether the action may happen.
+    pub fn try_trigger(&mut self) -> bool {
+        let ready = self.is_ready();
+        if ready {
+            self.trigger();
+        }
+        ready
+    }
+
+    /// Makes the cooldown ready right away.
+    pub fn reset(&mut self) {
+        self.remaining = 0;
+    }
+}
+
+// === Systems === //
+
+/// Advances every [`Timer`] and [`Cooldown`] component by a tick. Runs at the start of every
+/// fixed update so that the rest of the tick sees them in their new state.
+pub fn sys_tick_timers(mut timers: Query<&mut Timer>, mut cooldowns: Query<&mut Cooldown>) {
+    for mut timer in timers.iter_mut() {
+        timer.tick();
+    }
+
+    for mut cooldown in cooldowns.iter_mut() {
+        cooldown.tick();
+    }
+}